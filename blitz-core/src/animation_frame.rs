@@ -0,0 +1,115 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tao::event_loop::EventLoopProxy;
+use tokio::sync::oneshot;
+
+use crate::Redraw;
+
+/// How often frames are requested while animations wait for them, without a frame rate limit
+pub(crate) const ANIMATION_FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Runs callbacks once per frame of the window, like `requestAnimationFrame` in a browser, so animations follow the frame clock of the renderer instead of timers.
+///
+/// Pass a clone to [`crate::Config::with_animation_frames`] and keep another one in the application, for example in a Dioxus context. Every callback that is waiting is run once, with the time the frame started, right before the window handles the changes of the document and draws them. Changes made in a callback are laid out and drawn in one of the next frames, because the DOM is updated on its own thread.
+///
+/// The window keeps drawing frames while callbacks are waiting, at most as often as the frame rate limit of [`crate::Config::with_max_fps`] allows.
+#[derive(Clone, Default)]
+pub struct AnimationFrames {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    pending: Vec<oneshot::Sender<Instant>>,
+    callbacks: Vec<Box<dyn FnOnce(Instant) + Send>>,
+    /// When the callbacks last ran
+    last_frame: Option<Instant>,
+    /// Wakes up the window so the next frame is drawn even if nothing changes, set once the window is opened
+    proxy: Option<EventLoopProxy<Redraw>>,
+}
+
+impl AnimationFrames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for the next frame and return the time it started.
+    ///
+    /// Returns an error if the window is closed before the next frame.
+    pub async fn next_frame(&self) -> io::Result<Instant> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner.lock().unwrap().pending.push(sender);
+        self.wake();
+        receiver.await.map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the window was closed before the next frame",
+            )
+        })
+    }
+
+    /// Run a callback with the time the next frame started. Request another frame from the callback to keep animating.
+    pub fn request(&self, callback: impl FnOnce(Instant) + Send + 'static) {
+        self.inner
+            .lock()
+            .unwrap()
+            .callbacks
+            .push(Box::new(callback));
+        self.wake();
+    }
+
+    fn wake(&self) {
+        if let Some(proxy) = &self.inner.lock().unwrap().proxy {
+            let _ = proxy.send_event(Redraw);
+        }
+    }
+
+    pub(crate) fn attach(&self, proxy: EventLoopProxy<Redraw>) {
+        self.inner.lock().unwrap().proxy = Some(proxy);
+    }
+
+    /// When the next frame has to be drawn for the callbacks that are waiting, if there are any. `interval` is the shortest time between frames.
+    pub(crate) fn deadline(&self, interval: Option<Duration>) -> Option<Instant> {
+        let inner = self.inner.lock().unwrap();
+        if inner.pending.is_empty() && inner.callbacks.is_empty() {
+            return None;
+        }
+        let interval = interval.unwrap_or(ANIMATION_FRAME_INTERVAL);
+        Some(
+            inner
+                .last_frame
+                .map_or_else(Instant::now, |last_frame| last_frame + interval),
+        )
+    }
+
+    /// Run the callbacks that are waiting for a frame that starts now, unless the last frame was less than `interval` ago. Callbacks that request another frame run in the next one.
+    pub(crate) fn run(&self, interval: Option<Duration>) {
+        let now = Instant::now();
+        let (pending, callbacks) = {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.pending.is_empty() && inner.callbacks.is_empty() {
+                return;
+            }
+            let interval = interval.unwrap_or(ANIMATION_FRAME_INTERVAL);
+            if inner
+                .last_frame
+                .is_some_and(|last_frame| now < last_frame + interval)
+            {
+                return;
+            }
+            inner.last_frame = Some(now);
+            (
+                std::mem::take(&mut inner.pending),
+                std::mem::take(&mut inner.callbacks),
+            )
+        };
+        for sender in pending {
+            let _ = sender.send(now);
+        }
+        for callback in callbacks {
+            callback(now);
+        }
+    }
+}
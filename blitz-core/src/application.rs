@@ -1,92 +1,382 @@
-use beuk::ash::vk::PresentModeKHR;
-use beuk::ctx::{RenderContext, RenderContextDescriptor};
-
+use peniko::kurbo::{Affine, Vec2};
 use quadtree_rs::area::AreaBuilder;
 use quadtree_rs::Quadtree;
-use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 use shipyard::Component;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockWriteGuard};
-use taffy::geometry::Point;
-use taffy::prelude::Layout;
+use std::time::{Duration, Instant};
+use tao::event::{ElementState, Event, WindowEvent};
+use tao::keyboard::KeyCode;
 use tao::{dpi::PhysicalSize, event_loop::EventLoopProxy, window::Window};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
-use crate::renderer::Renderer;
-use crate::style::Background;
-use crate::Driver;
+use crate::animation_frame::{AnimationFrames, ANIMATION_FRAME_INTERVAL};
+use crate::backend::{draw_scene, Backend, DebugGeometry, DynamicRange, Msaa, PresentMode};
+use crate::content_scale::DesignSize;
+use crate::coordinates::WindowGeometry;
+use crate::document::{create_real_dom, to_color32};
+use crate::frame::Frames;
+use crate::frame_rate::FrameRate;
+use crate::hud::DebugHud;
+use crate::plugin::Plugins;
+use crate::raster::rasterize;
+use crate::report::Reporter;
+use crate::scene::{damage, Scene, TessellationCache};
+use crate::screenshot::Screenshots;
+use crate::scroll::{scroll_range, ScrollOffset};
+use crate::session::Session;
+use crate::shader_watcher::ShaderWatcher;
+use crate::snapshot::{node_at, Snapshot};
+use crate::stats::{self, Counter, FrameStats, Stats};
+use crate::style::StyleDiagnostics;
+use crate::system_settings::set_system_settings;
+use crate::toast::Toasts;
+use crate::window_chrome::Chrome;
 use crate::{
     events::{BlitzEventHandler, DomEvent},
     focus::{Focus, FocusState},
-    layout::TaffyLayout,
-    mouse::MouseEffected,
-    prevent_default::PreventDefault,
+    fonts,
+    layout::{compute_root_layout, reflow_fonts, TaffyLayout},
+    mouse::{viewport_size, Placement},
+    position::is_fixed,
     render::render,
-    style::{Border, ForgroundColor},
     Redraw, TaoEvent,
 };
+use crate::{Config, Driver, Executor};
+use dioxus_html::input_data::keyboard_types::Modifiers;
 use dioxus_native_core::{prelude::*, FxDashSet};
-use taffy::{
-    prelude::{AvailableSpace, Size},
-    style::Dimension,
-    Taffy,
-};
+use taffy::{prelude::Size, Taffy};
 
 pub struct ApplicationState {
     dom: DomManager,
-    render_context: RenderContext,
-    lyon_renderer: Renderer,
+    backend: Box<dyn Backend>,
+    /// The meshes of the last frame that was drawn
+    tessellation_cache: TessellationCache,
+    scene: Scene,
     event_handler: BlitzEventHandler,
     quadtree: Quadtree<u64, NodeId>,
+    reporter: Option<Arc<Reporter>>,
+    /// The area painted by each element in the last frame that was presented
+    previous_bounds: Option<FxHashMap<NodeId, epaint::Rect>>,
+    /// The file the document is saved to when the window is closed
+    snapshot: Option<PathBuf>,
+    /// The path to the element that was focused in the snapshot, which is focused once the VirtualDom has been rebuilt
+    pending_focus: Option<Vec<usize>>,
+    /// The paths to the scroll containers that were scrolled in the snapshot, which are scrolled once the VirtualDom has been rebuilt
+    pending_scroll: Vec<(Vec<usize>, Vec2)>,
+    shader_watcher: Option<ShaderWatcher>,
+    /// Set while the user is resizing the window
+    live_resize: Option<LiveResize>,
+    window_size: PhysicalSize<u32>,
+    /// The fixed size the document is laid out at and scaled from, if it doesn't follow the size of the window
+    design_size: Option<DesignSize>,
+    /// The corners and shadow of a window without decorations
+    chrome: Option<Chrome>,
+    screenshots: Option<Screenshots>,
+    animation_frames: Option<AnimationFrames>,
+    toasts: Option<Toasts>,
+    stats: Option<Stats>,
+    /// The debug HUD, if it can be toggled
+    hud: Option<DebugHud>,
+    debug_geometry: Option<DebugGeometry>,
+    frames: Option<Frames>,
+    session: Option<Session>,
+    /// The generation of the fonts in the last frame
+    font_generation: u64,
+    /// The frame rate limit, from [`Config::with_max_fps`] or [`Config::with_frame_rate`]
+    frame_rate: FrameRate,
+    /// Whether the window has focus, which can lower the frame rate limit
+    focused: bool,
+    /// When the last frame was drawn
+    last_frame: Option<Instant>,
+    /// Set when a frame was held back because it came too soon after the last one
+    frame_deferred: bool,
+}
+
+/// How long the window has to keep its size before a resize is over
+const RESIZE_SETTLE_TIME: Duration = Duration::from_millis(200);
+/// How often the document is laid out again while the window is being resized
+const RESIZE_LAYOUT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// While the window is resized, frames are drawn as cheaply as possible so dragging the edge of the window stays smooth: the document is laid out at a fixed cadence instead of for every size, unchanged layers reuse their meshes and hit testing isn't updated. A full frame is drawn once the resize is over.
+struct LiveResize {
+    last_event: Instant,
+    last_layout: Instant,
+    /// The size the document hasn't been laid out for yet
+    pending_size: Option<PhysicalSize<u32>>,
 }
 
 impl ApplicationState {
     /// Create a new window state and spawn a vdom thread.
-    pub async fn new<R: Driver>(
+    pub fn new<R: Driver>(
         spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>) -> R + Send + 'static,
         window: &Window,
         proxy: EventLoopProxy<Redraw>,
+        cfg: Config,
     ) -> Self {
         let inner_size = window.inner_size();
+        let mut backend = match cfg.backend {
+            Some(create) => create(window),
+            None => default_backend(window, cfg.msaa, cfg.present_mode, cfg.dynamic_range),
+        };
+        // The backend decides if the corners and the shadow of the window can be see-through
+        let chrome = cfg
+            .window_chrome
+            .map(|style| Chrome::new(style, cfg.clear_color, &mut *backend, window.scale_factor()));
 
-        let mut rdom = RealDom::new([
-            MouseEffected::to_type_erased(),
-            TaffyLayout::to_type_erased(),
-            ForgroundColor::to_type_erased(),
-            Background::to_type_erased(),
-            Border::to_type_erased(),
-            Focus::to_type_erased(),
-            PreventDefault::to_type_erased(),
-        ]);
+        let mut rdom = create_real_dom(cfg.root_style, cfg.states);
+        let restored = cfg
+            .snapshot
+            .as_deref()
+            .and_then(|path| match Snapshot::load(path) {
+                Ok(snapshot) => Some(snapshot.restore(&mut rdom)),
+                // There is no snapshot on the first launch
+                Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                Err(err) => {
+                    log::warn!("failed to load the snapshot {}: {err}", path.display());
+                    None
+                }
+            });
+        let (restored_nodes, pending_focus, pending_scroll) = match restored {
+            Some(restored) => (restored.nodes, restored.focused, restored.scrolled),
+            None => (Vec::new(), None, Vec::new()),
+        };
 
         let focus_state = FocusState::create(&mut rdom);
+        let root_id = rdom.root_id();
+        let mut geometry = WindowGeometry::from_window(window);
+        geometry.content_transform =
+            content_transform(cfg.design_size, chrome.as_ref(), inner_size);
+        rdom.get_mut(root_id).unwrap().insert(geometry);
+        if let Some(screenshots) = &cfg.screenshots {
+            screenshots.attach(proxy.clone());
+        }
+        if let Some(animation_frames) = &cfg.animation_frames {
+            animation_frames.attach(proxy.clone());
+        }
+        if let Some(frames) = &cfg.frames {
+            frames.attach(proxy.clone());
+        }
+        if let Some(toasts) = &cfg.toasts {
+            toasts.attach(proxy.clone());
+        }
+        if let Some(session) = &cfg.session {
+            session.attach(proxy.clone());
+        }
+        cfg.frame_rate.attach(proxy.clone());
+        fonts::attach(proxy.clone());
+        let plugins = Arc::new(cfg.plugins);
+        let shader_watcher = cfg
+            .shader_dir
+            .map(|dir| ShaderWatcher::spawn(dir, proxy.clone()));
 
-        let dom = DomManager::spawn(rdom, inner_size, spawn_renderer, proxy);
+        let dom = DomManager::spawn(
+            rdom,
+            cfg.design_size
+                .map_or(document_size(chrome.as_ref(), inner_size), |design_size| {
+                    design_size.size
+                }),
+            spawn_renderer,
+            proxy,
+            cfg.style_diagnostics.clone(),
+            cfg.executor,
+            plugins.clone(),
+            restored_nodes,
+        );
 
-        let event_handler = BlitzEventHandler::new(focus_state);
+        if let Some(settings) = cfg.system_settings {
+            set_system_settings(settings);
+        }
+        fonts::register_config_fonts(cfg.fonts, cfg.font_faces);
+        if let Some(fallback) = cfg.font_fallback {
+            fonts::set_font_fallback(fallback);
+        }
+        let mut event_handler = BlitzEventHandler::new(focus_state, cfg.gestures, plugins);
+        event_handler.set_input_filter(cfg.input_filter);
+        event_handler.set_docking(cfg.docking);
+        event_handler.set_toasts(cfg.toasts.clone());
 
-        let mut render_context = RenderContext::new(RenderContextDescriptor {
-            display_handle: window.raw_display_handle(),
-            window_handle: window.raw_window_handle(),
-            present_mode: PresentModeKHR::default(),
+        if cfg.stats.is_some() || cfg.debug_hud {
+            backend.enable_gpu_timing();
+        }
+        let subpixel_text = cfg.subpixel_text && backend.draws_subpixel_text();
+        if cfg.subpixel_text && !subpixel_text {
+            log::warn!("the backend doesn't draw subpixel text, smoothing text in grayscale");
+        }
+        let new_scene = || Scene {
+            clear_color: chrome
+                .as_ref()
+                .map_or(to_color32(cfg.clear_color), Chrome::clear_color),
+            feathering: cfg.feathering,
+            subpixel_text,
+            ..Default::default()
+        };
+
+        let reporter = cfg.diagnostics_dir.map(|dir| {
+            let reporter = Arc::new(Reporter {
+                dir,
+                adapter: backend.adapter_name(),
+                rdom: dom.rdom.clone(),
+                taffy: dom.taffy.clone(),
+                size: dom.size.clone(),
+                style_diagnostics: cfg.style_diagnostics,
+                last_frame: Mutex::new(new_scene()),
+            });
+            reporter.install_panic_hook();
+            reporter
         });
-        let lyon_renderer = Renderer::new(&mut render_context);
 
         ApplicationState {
             dom,
-            render_context,
-            lyon_renderer,
+            backend,
+            tessellation_cache: TessellationCache::default(),
+            scene: new_scene(),
             event_handler,
             quadtree: Quadtree::new(20),
+            reporter,
+            previous_bounds: None,
+            snapshot: cfg.snapshot,
+            pending_focus,
+            pending_scroll,
+            shader_watcher,
+            live_resize: None,
+            window_size: inner_size,
+            design_size: cfg.design_size,
+            chrome,
+            screenshots: cfg.screenshots,
+            animation_frames: cfg.animation_frames,
+            toasts: cfg.toasts,
+            stats: cfg.stats,
+            hud: cfg.debug_hud.then(DebugHud::default),
+            debug_geometry: cfg.debug_geometry,
+            frames: cfg.frames,
+            session: cfg.session,
+            font_generation: fonts::generation(),
+            frame_rate: cfg.frame_rate,
+            focused: window.is_focused(),
+            last_frame: None,
+            frame_deferred: false,
+        }
+    }
+
+    pub fn render(&mut self, dirty: DirtyNodes) {
+        let dirty_nodes = match &dirty {
+            DirtyNodes::All => None,
+            DirtyNodes::Some(nodes) => Some(nodes.len() as u64),
+        };
+        let started = Instant::now();
+        self.scene.clear();
+        let clipped = self
+            .chrome
+            .as_ref()
+            .is_some_and(|chrome| chrome.paint(&mut self.scene, self.window_size));
+        self.dom
+            .render(&mut self.scene, self.window_size, self.content_transform());
+        if clipped {
+            self.scene.pop_clip_path();
+        }
+        let paint_time = started.elapsed();
+        let window = epaint::Rect::from_min_size(
+            epaint::Pos2::ZERO,
+            epaint::vec2(
+                self.window_size.width as f32,
+                self.window_size.height as f32,
+            ),
+        );
+        let mut damage = match &self.previous_bounds {
+            Some(previous) => damage(previous, &self.scene.node_bounds, &dirty, window),
+            // Nothing has been presented yet
+            None => window,
+        };
+        if let Some(toasts) = &self.toasts {
+            damage = damage.union(toasts.paint(&mut self.scene, window, Instant::now()));
+        }
+        // Screenshots are copied from the frame the backend draws. The HUD is left out of them, so while it is shown they are drawn on the CPU before it is painted
+        let capture = self
+            .screenshots
+            .as_ref()
+            .is_some_and(Screenshots::is_pending)
+            && self.hud.is_none()
+            && window.is_positive()
+            && self.backend.captures_frames();
+        if let Some(screenshots) = self.screenshots.as_ref().filter(|_| !capture) {
+            let size = self.window_size;
+            screenshots.fulfill(|| rasterize(&self.scene, size.width, size.height, 1.0));
+        }
+        // The HUD changes with every frame, so it is always drawn again
+        if let Some(hud) = &self.hud {
+            damage = damage.union(hud.paint(&mut self.scene, window));
         }
+        if capture {
+            self.backend.capture_next_frame();
+            // The whole window is copied
+            damage = window;
+        }
+        // The bounds of this frame are kept for the next one. The scene is cleared before it is used again, so they don't need to be copied
+        self.previous_bounds = Some(std::mem::take(&mut self.scene.node_bounds));
+        // The window keeps showing the last frame if nothing changed
+        if damage.is_positive() {
+            let started = Instant::now();
+            let tessellation_time = draw_scene(
+                &mut *self.backend,
+                &self.scene,
+                damage,
+                &mut self.tessellation_cache,
+                self.live_resize.is_some(),
+                self.debug_geometry,
+            );
+            if let Some(screenshots) = self.screenshots.as_ref().filter(|_| capture) {
+                let size = self.window_size;
+                let frame = self.backend.captured_frame();
+                screenshots.fulfill(|| {
+                    frame.unwrap_or_else(|| rasterize(&self.scene, size.width, size.height, 1.0))
+                });
+            }
+            let mut frame_stats = FrameStats {
+                paint_time,
+                tessellation_time,
+                submit_time: started.elapsed().saturating_sub(tessellation_time),
+                gpu_time: self.backend.gpu_time(),
+                dirty_nodes,
+                ..Default::default()
+            };
+            frame_stats.take_counters();
+            if let Some(stats) = &self.stats {
+                stats.record(frame_stats);
+            }
+            if let Some(hud) = &mut self.hud {
+                hud.record(frame_stats, Instant::now());
+            }
+            if let Some(reporter) = &self.reporter {
+                reporter.swap_frame(&mut self.scene);
+            }
+            self.last_frame = Some(Instant::now());
+        }
+        // After we render, we need to update the quadtree to reflect the new positions of the nodes. The cursor is on the edge of the window while it is resized, so this waits until the resize is over
+        if self.live_resize.is_none() {
+            self.update_quadtree();
+        }
+    }
+
+    /// The transform that maps the document to the window
+    fn content_transform(&self) -> Affine {
+        content_transform(self.design_size, self.chrome.as_ref(), self.window_size)
     }
 
-    pub fn render(&mut self) {
-        self.lyon_renderer.shapes.clear();
-        self.dom.render(&mut self.lyon_renderer);
-        self.lyon_renderer.render(&mut self.render_context);
-        // After we render, we need to update the quadtree to reflect the new positions of the nodes
-        self.update_quadtree();
+    /// Rebuild the graphics pipeline if the watched shaders changed. Returns true if the window needs to be redrawn.
+    pub fn reload_shaders(&mut self) -> bool {
+        let Some((vertex, fragment)) = self
+            .shader_watcher
+            .as_ref()
+            .and_then(ShaderWatcher::take_changed)
+        else {
+            return false;
+        };
+        self.backend.reload_shaders(&vertex, &fragment);
+        true
     }
 
     // TODO: Once we implement a custom tree for Taffy we can call this when the layout actually changes for each node instead of the diffing approach this currently uses
@@ -96,31 +386,34 @@ impl ApplicationState {
 
         fn add_to_quadtree(
             node_id: NodeId,
-            parent_location: Point<f32>,
+            parent: Placement,
+            viewport_size: &Size<u32>,
             taffy: &Taffy,
             rdom: &mut RealDom,
             quadtree: &mut Quadtree<u64, NodeId>,
         ) {
             if let Some(node) = rdom.get(node_id) {
-                if let Some((size, location)) = {
-                    let layout = node.get::<TaffyLayout>();
-                    layout.and_then(|l| {
-                        if let Ok(Layout { size, location, .. }) = taffy.layout(l.node.unwrap()) {
-                            Some((size, location))
-                        } else {
-                            None
-                        }
-                    })
-                } {
-                    let location = Point {
-                        x: location.x + parent_location.x,
-                        y: location.y + parent_location.y,
-                    };
+                if let Some((placement, children_placement)) =
+                    parent.place(taffy, node, viewport_size)
+                {
+                    let size = taffy
+                        .layout(node.get::<TaffyLayout>().unwrap().node.unwrap())
+                        .unwrap()
+                        .size;
+                    // Transformed elements are found by the bounds of where they are drawn
+                    let bounds = placement.transform_rect(epaint::Rect::from_min_size(
+                        epaint::pos2(placement.location.x as f32, placement.location.y as f32),
+                        epaint::vec2(size.width, size.height),
+                    ));
+                    let min = bounds.min.max(epaint::Pos2::ZERO);
 
                     let mut qtree_id = None;
                     let area = AreaBuilder::default()
-                        .anchor((location.x as u64, location.y as u64).into())
-                        .dimensions((size.width as u64, size.height as u64))
+                        .anchor((min.x as u64, min.y as u64).into())
+                        .dimensions((
+                            (bounds.max.x - min.x).max(1.0) as u64,
+                            (bounds.max.y - min.y).max(1.0) as u64,
+                        ))
                         .build()
                         .unwrap();
                     match node.get::<QuadtreeId>() {
@@ -143,9 +436,19 @@ impl ApplicationState {
                             qtree_id = quadtree.insert(area, node_id);
                         }
                     }
-                    // Repeat for all children
-                    for child in node.child_ids() {
-                        add_to_quadtree(child, location, taffy, rdom, quadtree);
+                    // Repeat for all children. Fixed elements are laid out in the root, which is at the origin
+                    let children: Vec<(NodeId, bool)> = node
+                        .children()
+                        .into_iter()
+                        .map(|child| (child.id(), is_fixed(child)))
+                        .collect();
+                    for (child, fixed) in children {
+                        let parent = if fixed {
+                            Placement::DOCUMENT
+                        } else {
+                            children_placement
+                        };
+                        add_to_quadtree(child, parent, viewport_size, taffy, rdom, quadtree);
                     }
                     // If the node was added or updated, we need to update the node's quadtree id
                     if let Some(id) = qtree_id {
@@ -157,9 +460,13 @@ impl ApplicationState {
         }
         let mut rdom = self.dom.rdom();
         let taffy = self.dom.taffy();
+        let Some(viewport_size) = viewport_size(&rdom, &taffy) else {
+            return;
+        };
         add_to_quadtree(
             rdom.root_id(),
-            Point::ZERO,
+            Placement::DOCUMENT,
+            &viewport_size,
             &taffy,
             &mut rdom,
             &mut self.quadtree,
@@ -169,17 +476,285 @@ impl ApplicationState {
     pub fn set_size(&mut self, size: PhysicalSize<u32>) {
         // the window size is zero when minimized which causes the renderer to panic
         if size.width > 0 && size.height > 0 {
-            self.dom.set_size(size);
-            // self.render_context
-            //     .resize_surface(&mut self.surface, size.width, size.height);
+            self.backend.resize(size);
+            self.window_size = size;
+            if self.design_size.is_some() {
+                // The document keeps its layout and is only scaled, so the resize is cheap
+                let geometry = WindowGeometry::of(&self.dom.rdom());
+                self.set_window_geometry(geometry);
+                self.dom.force_redraw();
+                return;
+            }
+            let document_size = document_size(self.chrome.as_ref(), size);
+            let now = Instant::now();
+            match &mut self.live_resize {
+                Some(resize) => {
+                    resize.last_event = now;
+                    if now - resize.last_layout >= RESIZE_LAYOUT_INTERVAL {
+                        resize.last_layout = now;
+                        resize.pending_size = None;
+                        self.dom.set_size(document_size);
+                    } else {
+                        resize.pending_size = Some(document_size);
+                        // Draw the last layout into the resized window
+                        self.dom.force_redraw();
+                    }
+                }
+                None => {
+                    self.live_resize = Some(LiveResize {
+                        last_event: now,
+                        last_layout: now,
+                        pending_size: None,
+                    });
+                    self.dom.set_size(document_size);
+                }
+            }
+        }
+    }
+
+    /// When the window is being resized, the time the resize is over if the size doesn't change again.
+    pub fn resize_deadline(&self) -> Option<Instant> {
+        self.live_resize
+            .as_ref()
+            .map(|resize| resize.last_event + RESIZE_SETTLE_TIME)
+    }
+
+    /// When the next frame may be drawn with the frame rate limit, if one was held back.
+    fn frame_deadline(&self) -> Option<Instant> {
+        if !self.frame_deferred {
+            return None;
+        }
+        Some(self.last_frame? + self.frame_interval()?)
+    }
+
+    /// The shortest time between two frames, if the frame rate is limited.
+    fn frame_interval(&self) -> Option<Duration> {
+        self.frame_rate.interval(self.focused)
+    }
+
+    /// True if a frame can be drawn now without going over the frame rate limit. Otherwise the frame is held back until the deadline of the limit.
+    pub fn frame_due(&mut self) -> bool {
+        let due = match (self.last_frame, self.frame_interval()) {
+            (Some(last_frame), Some(interval)) => last_frame.elapsed() >= interval,
+            _ => true,
+        };
+        self.frame_deferred = !due;
+        due
+    }
+
+    /// When the next frame of an animation can be drawn
+    fn next_frame(&self) -> Instant {
+        self.last_frame.map_or_else(Instant::now, |last_frame| {
+            last_frame + self.frame_interval().unwrap_or(ANIMATION_FRAME_INTERVAL)
+        })
+    }
+
+    /// True if the toasts changed or move, so a frame is drawn even if the document didn't change.
+    pub fn toasts_due(&self) -> bool {
+        let now = Instant::now();
+        self.toasts
+            .as_ref()
+            .and_then(|toasts| toasts.next_change(now))
+            .is_some_and(|change| change <= now)
+    }
+
+    /// Run the callbacks that wait for the frame that is about to be drawn.
+    pub fn run_animation_frames(&self) {
+        if let Some(animation_frames) = &self.animation_frames {
+            animation_frames.run(self.frame_interval());
+        }
+    }
+
+    /// When the event loop has to wake up without an event: when a live resize is over, a frame that was held back by the frame rate limit is due, an animation waits for the next frame, a gesture that waits for time to pass may be recognized, the caret blinks or text that waits for its font is shown in the fallback font.
+    pub fn wake_deadline(&self) -> Option<Instant> {
+        self.resize_deadline()
+            .into_iter()
+            .chain(self.frame_deadline())
+            .chain(
+                self.animation_frames
+                    .as_ref()
+                    .and_then(|animation_frames| animation_frames.deadline(self.frame_interval())),
+            )
+            .chain(
+                self.event_handler
+                    .plugins_animating()
+                    .then(|| self.next_frame()),
+            )
+            .chain(self.toasts.as_ref().and_then(|toasts| {
+                let now = Instant::now();
+                // Toasts that move are drawn every frame
+                toasts.next_change(now).map(|change| {
+                    if change <= now {
+                        self.next_frame()
+                    } else {
+                        change
+                    }
+                })
+            }))
+            .chain(self.event_handler.gesture_deadline())
+            .chain(self.event_handler.caret_deadline())
+            .chain(fonts::block_deadline())
+            .min()
+    }
+
+    /// Send the gestures that are recognized because time passed, like long presses.
+    pub fn poll_gestures(&mut self) {
+        let events = {
+            let rdom = self.dom.rdom();
+            self.event_handler.poll_gestures(&rdom);
+            self.event_handler.drain_events()
+        };
+        self.dom.send_events(events);
+    }
+
+    /// End the live resize once the size stopped changing, and lay out and draw the document at full quality.
+    pub fn finish_resize(&mut self) {
+        if !self
+            .resize_deadline()
+            .is_some_and(|deadline| deadline <= Instant::now())
+        {
+            return;
+        }
+        match self.live_resize.take().unwrap().pending_size {
+            Some(size) => self.dom.set_size(size),
+            None => self.dom.force_redraw(),
+        }
+    }
+
+    pub fn set_window_geometry(&mut self, mut geometry: WindowGeometry) {
+        if let Some(chrome) = &mut self.chrome {
+            chrome.set_scale_factor(geometry.scale_factor);
         }
+        geometry.content_transform = self.content_transform();
+        let mut rdom = self.dom.rdom();
+        let root_id = rdom.root_id();
+        rdom.get_mut(root_id).unwrap().insert(geometry);
     }
 
     pub fn clean(&mut self) -> DirtyNodes {
-        self.event_handler.clean().or(self.dom.clean())
+        if (self.pending_focus.is_some() || !self.pending_scroll.is_empty())
+            && self.dom.hydrated.load(Ordering::Acquire)
+        {
+            self.restore_focus();
+            self.restore_scroll();
+        }
+        // The DOM thread measures the text with the fonts that changed again, the text is drawn with them in the meantime
+        let font_generation = fonts::generation();
+        if font_generation != self.font_generation {
+            self.font_generation = font_generation;
+            self.dom.force_redraw();
+        }
+        let dirty = self.event_handler.clean().or(self.dom.clean());
+        // A screenshot needs a frame, even if nothing changed
+        if self
+            .screenshots
+            .as_ref()
+            .is_some_and(Screenshots::is_pending)
+        {
+            return DirtyNodes::All;
+        }
+        // The documents of frames don't track which of their nodes changed
+        if self.frames.as_ref().is_some_and(Frames::take_changed) {
+            return DirtyNodes::All;
+        }
+        dirty
+    }
+
+    /// Focus the element at the place of the element that was focused in the snapshot, if it can be focused.
+    fn restore_focus(&mut self) {
+        let Some(path) = self.pending_focus.take() else {
+            return;
+        };
+        let mut rdom = self.dom.rdom();
+        let focusable = node_at(&rdom, &path).filter(|id| {
+            rdom.get(*id)
+                .and_then(|node| node.get::<Focus>().map(|focus| focus.level.focusable()))
+                .unwrap_or(false)
+        });
+        if let Some(id) = focusable {
+            self.event_handler.set_focus(&mut rdom, id);
+        }
+    }
+
+    /// Scroll the elements at the places of the containers that were scrolled in the snapshot, as far as their content allows.
+    fn restore_scroll(&mut self) {
+        let scrolled = std::mem::take(&mut self.pending_scroll);
+        let mut rdom = self.dom.rdom();
+        let taffy = self.dom.taffy();
+        for (path, offset) in scrolled {
+            let Some(id) = node_at(&rdom, &path) else {
+                continue;
+            };
+            let range = scroll_range(&taffy, rdom.get(id).unwrap());
+            let offset = Vec2::new(
+                offset.x.clamp(0.0, range.x.max(0.0)),
+                offset.y.clamp(0.0, range.y.max(0.0)),
+            );
+            rdom.get_mut(id).unwrap().insert(ScrollOffset(offset));
+        }
+        drop((rdom, taffy));
+        self.dom.force_redraw();
+    }
+
+    /// Save the document to the snapshot file, if there is one.
+    pub fn save_snapshot(&self) {
+        if let Some(path) = &self.snapshot {
+            if let Err(err) = Snapshot::capture(&self.dom.rdom()).save(path) {
+                log::error!("failed to save the snapshot {}: {err}", path.display());
+            }
+        }
+    }
+
+    /// Handle the input a [`Session`] is waiting on and send the events to the DOM.
+    pub fn run_session(&mut self) {
+        let Some(pending) = self.session.as_ref().map(Session::take_pending) else {
+            return;
+        };
+        let size = self.dom.size();
+        let size = Size {
+            width: size.width,
+            height: size.height,
+        };
+        for (input, sender) in pending {
+            let evts;
+            let result;
+            {
+                let rdom = &mut self.dom.rdom();
+                let taffy = &mut self.dom.taffy();
+                result =
+                    self.event_handler
+                        .synthesize(input, rdom, taffy, &size, Some(&self.quadtree));
+                evts = self.event_handler.drain_events();
+            }
+            self.dom.send_events(evts);
+            let _ = sender.send(result);
+        }
     }
 
     pub fn send_event(&mut self, event: &TaoEvent) {
+        if let Event::WindowEvent {
+            event: WindowEvent::Focused(focused),
+            ..
+        } = event
+        {
+            self.focused = *focused;
+        }
+        if let Some(hud) = &mut self.hud {
+            if is_hud_shortcut(event) {
+                hud.toggle();
+                // Hiding the HUD uncovers the document under it
+                self.dom.force_redraw();
+                return;
+            }
+        }
+        if let Some(reporter) = &self.reporter {
+            if is_report_shortcut(event, self.event_handler.modifiers()) {
+                match reporter.write("requested with Ctrl+Shift+D") {
+                    Ok(path) => log::info!("wrote a diagnostics report to {}", path.display()),
+                    Err(err) => log::error!("failed to write a diagnostics report: {err}"),
+                }
+            }
+        }
         let size = self.dom.size();
         let size = Size {
             width: size.width,
@@ -188,7 +763,7 @@ impl ApplicationState {
         let evts;
         {
             let rdom = &mut self.dom.rdom();
-            let taffy = &self.dom.taffy();
+            let taffy = &mut self.dom.taffy();
             self.event_handler
                 .register_event(event, rdom, taffy, &size, &self.quadtree);
             evts = self.event_handler.drain_events();
@@ -197,6 +772,128 @@ impl ApplicationState {
     }
 }
 
+/// Ctrl+Shift+D writes a diagnostics report
+fn is_report_shortcut(event: &TaoEvent, modifiers: Modifiers) -> bool {
+    matches!(
+        event,
+        Event::WindowEvent {
+            event: WindowEvent::KeyboardInput { event, .. },
+            ..
+        } if event.state == ElementState::Pressed
+            && !event.repeat
+            && event.physical_key == KeyCode::KeyD
+            && modifiers.contains(Modifiers::CONTROL | Modifiers::SHIFT)
+    )
+}
+
+/// F12 shows or hides the debug HUD
+fn is_hud_shortcut(event: &TaoEvent) -> bool {
+    matches!(
+        event,
+        Event::WindowEvent {
+            event: WindowEvent::KeyboardInput { event, .. },
+            ..
+        } if event.state == ElementState::Pressed
+            && !event.repeat
+            && event.physical_key == KeyCode::F12
+    )
+}
+
+/// The size the document is laid out at in a window of a size, inside of the margin of its chrome.
+fn document_size(chrome: Option<&Chrome>, window_size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+    chrome.map_or(window_size, |chrome| chrome.document_size(window_size))
+}
+
+/// The transform that maps the document to a window of a size: moved inside of the margin of its chrome and scaled to it from its design size.
+fn content_transform(
+    design_size: Option<DesignSize>,
+    chrome: Option<&Chrome>,
+    window_size: PhysicalSize<u32>,
+) -> Affine {
+    let offset = chrome.map_or(Affine::IDENTITY, Chrome::transform);
+    let scale = design_size.map_or(Affine::IDENTITY, |design_size| {
+        design_size.transform(document_size(chrome, window_size))
+    });
+    offset * scale
+}
+
+/// The backend of the features Blitz was built with. If the GPU backend can't be created, the window is drawn on the CPU instead.
+fn default_backend(
+    window: &Window,
+    msaa: Msaa,
+    present_mode: PresentMode,
+    dynamic_range: DynamicRange,
+) -> Box<dyn Backend> {
+    // The GPU backends panic when there is no adapter that can draw to the window
+    match crate::report::catch_panic(std::panic::AssertUnwindSafe(|| {
+        gpu_backend(window, msaa, present_mode, dynamic_range)
+    })) {
+        Ok(Some(backend)) => return backend,
+        Ok(None) => {}
+        Err(_) => log::warn!("failed to create the GPU backend, drawing on the CPU instead"),
+    }
+    software_backend(window)
+}
+
+#[cfg(feature = "vulkan")]
+fn gpu_backend(
+    window: &Window,
+    msaa: Msaa,
+    present_mode: PresentMode,
+    dynamic_range: DynamicRange,
+) -> Option<Box<dyn Backend>> {
+    // The Vulkan backend can't choose the format of the swapchain, so HDR windows are drawn with wgpu, see `Config::with_dynamic_range`
+    #[cfg(feature = "wgpu")]
+    if dynamic_range != DynamicRange::Standard {
+        return Some(Box::new(crate::WgpuBackend::with_dynamic_range(
+            window,
+            msaa,
+            present_mode,
+            dynamic_range,
+        )));
+    }
+    // Without the wgpu feature the range can't be configured
+    debug_assert_eq!(dynamic_range, DynamicRange::Standard);
+    Some(Box::new(crate::renderer::VulkanBackend::new(
+        window,
+        msaa,
+        present_mode,
+    )))
+}
+
+#[cfg(all(feature = "wgpu", not(feature = "vulkan")))]
+fn gpu_backend(
+    window: &Window,
+    msaa: Msaa,
+    present_mode: PresentMode,
+    dynamic_range: DynamicRange,
+) -> Option<Box<dyn Backend>> {
+    Some(Box::new(crate::WgpuBackend::with_dynamic_range(
+        window,
+        msaa,
+        present_mode,
+        dynamic_range,
+    )))
+}
+
+#[cfg(not(any(feature = "vulkan", feature = "wgpu")))]
+fn gpu_backend(_: &Window, _: Msaa, _: PresentMode, _: DynamicRange) -> Option<Box<dyn Backend>> {
+    None
+}
+
+#[cfg(feature = "software")]
+fn software_backend(window: &Window) -> Box<dyn Backend> {
+    match crate::SoftwareBackend::new(window) {
+        Ok(backend) => Box::new(backend),
+        Err(err) => panic!("failed to create the software backend: {err}"),
+    }
+}
+
+#[cfg(not(feature = "software"))]
+fn software_backend(_: &Window) -> Box<dyn Backend> {
+    panic!("Blitz has no backend that can draw the window. Enable the software feature or set a backend with Config::with_backend")
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn spawn_dom<R: Driver>(
     rdom: Arc<RwLock<RealDom>>,
@@ -207,46 +904,47 @@ async fn spawn_dom<R: Driver>(
     mut event_receiver: UnboundedReceiver<DomEvent>,
     mut redraw_receiver: UnboundedReceiver<()>,
     vdom_dirty: Arc<FxDashSet<NodeId>>,
+    style_diagnostics: Arc<StyleDiagnostics>,
+    plugins: Arc<Plugins>,
+    restored: Vec<NodeId>,
+    hydrated: Arc<AtomicBool>,
 ) -> Option<()> {
     let mut renderer = spawn_renderer(&rdom, &taffy);
     let mut last_size;
+    let mut font_generation = fonts::generation();
 
     // initial render
     {
         let mut rdom = rdom.write().ok()?;
+        // The snapshot is shown until the driver has built the document
+        for id in restored {
+            if let Some(mut node) = rdom.get_mut(id) {
+                node.remove();
+            }
+        }
         let root_id = rdom.root_id();
         renderer.update(rdom.get_mut(root_id)?);
         let mut ctx = SendAnyMap::new();
         ctx.insert(taffy.clone());
+        ctx.insert(style_diagnostics.clone());
+        ctx.insert(plugins.clone());
         // update the state of the real dom
         let (to_rerender, _) = rdom.update_state(ctx);
+        stats::count(Counter::NodesStyled, to_rerender.len());
         let size = size.lock().unwrap();
 
-        let width = size.width as f32;
-        let height = size.height as f32;
         let size = Size {
-            width: AvailableSpace::Definite(width),
-            height: AvailableSpace::Definite(height),
+            width: size.width as f32,
+            height: size.height as f32,
         };
 
         last_size = size;
 
-        let mut locked_taffy = taffy.lock().unwrap();
-
-        // the root node fills the entire area
-        let root_node = rdom.get(rdom.root_id()).unwrap();
-        let root_taffy_node = root_node.get::<TaffyLayout>().unwrap().node.unwrap();
-
-        let mut style = locked_taffy.style(root_taffy_node).unwrap().clone();
-        style.size = Size {
-            width: Dimension::Points(width),
-            height: Dimension::Points(height),
-        };
-        locked_taffy.set_style(root_taffy_node, style).unwrap();
-        locked_taffy.compute_layout(root_taffy_node, size).unwrap();
+        compute_root_layout(&rdom, &mut taffy.lock().unwrap(), size);
         for k in to_rerender.into_iter() {
             vdom_dirty.insert(k);
         }
+        hydrated.store(true, Ordering::Release);
         proxy.send_event(Redraw).unwrap();
     }
 
@@ -258,7 +956,14 @@ async fn spawn_dom<R: Driver>(
             Some(event) = event_receiver.recv() => {
                 let DomEvent { name, data, element, bubbles } = event;
                 let mut rdom = rdom.write().ok()?;
-                renderer.handle_event(rdom.get_mut(element)?, name, data, bubbles);
+                // Custom elements see their events before the driver
+                let handled = match plugins.for_node(rdom.get(element)?) {
+                    Some(plugin) => plugin.handle_event(rdom.get_mut(element)?, name, &data),
+                    None => false,
+                };
+                if !handled {
+                    renderer.handle_event(rdom.get_mut(element)?, name, data, bubbles);
+                }
             }
         }
 
@@ -269,35 +974,24 @@ async fn spawn_dom<R: Driver>(
 
         let mut ctx = SendAnyMap::new();
         ctx.insert(taffy.clone());
+        ctx.insert(style_diagnostics.clone());
+        ctx.insert(plugins.clone());
 
         // update the real dom
         let (to_rerender, _) = rdom.update_state(ctx);
+        stats::count(Counter::NodesStyled, to_rerender.len());
+        let reflowed = reflow_fonts(&mut rdom, &mut taffy.lock().unwrap(), &mut font_generation);
 
         let size = size.lock().ok()?;
 
-        let width = size.width as f32;
-        let height = size.height as f32;
         let size = Size {
-            width: AvailableSpace::Definite(width),
-            height: AvailableSpace::Definite(height),
+            width: size.width as f32,
+            height: size.height as f32,
         };
-        if !to_rerender.is_empty() || last_size != size {
+        if !to_rerender.is_empty() || !reflowed.is_empty() || last_size != size {
             last_size = size;
-            let mut taffy = taffy.lock().unwrap();
-            let root_node = rdom.get(rdom.root_id()).unwrap();
-            let root_node_layout = root_node.get::<TaffyLayout>().unwrap();
-            let root_taffy_node = root_node_layout.node.unwrap();
-            let mut style = taffy.style(root_taffy_node).unwrap().clone();
-            let new_size = Size {
-                width: Dimension::Points(width),
-                height: Dimension::Points(height),
-            };
-            if style.size != new_size {
-                style.size = new_size;
-                taffy.set_style(root_taffy_node, style).unwrap();
-            }
-            taffy.compute_layout(root_taffy_node, size).unwrap();
-            for k in to_rerender.into_iter() {
+            compute_root_layout(&rdom, &mut taffy.lock().unwrap(), size);
+            for k in to_rerender.into_iter().chain(reflowed) {
                 vdom_dirty.insert(k);
             }
 
@@ -316,32 +1010,60 @@ struct DomManager {
     force_redraw: bool,
     event_sender: UnboundedSender<DomEvent>,
     redraw_sender: UnboundedSender<()>,
+    /// Set once the driver has built the document and the nodes restored from a snapshot are removed
+    hydrated: Arc<AtomicBool>,
+    plugins: Arc<Plugins>,
 }
 
 impl DomManager {
+    #[allow(clippy::too_many_arguments)]
     fn spawn<R: Driver>(
-        rdom: RealDom,
+        mut rdom: RealDom,
         size: PhysicalSize<u32>,
         spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>) -> R + Send + 'static,
         proxy: EventLoopProxy<Redraw>,
+        style_diagnostics: Arc<StyleDiagnostics>,
+        executor: Option<Box<dyn Executor>>,
+        plugins: Arc<Plugins>,
+        restored: Vec<NodeId>,
     ) -> Self {
-        let rdom: Arc<RwLock<RealDom>> = Arc::new(RwLock::new(rdom));
         let taffy = Arc::new(Mutex::new(Taffy::new()));
+        if !restored.is_empty() {
+            // Lay out the snapshot before the VirtualDom starts, so the first frame shows it
+            let mut ctx = SendAnyMap::new();
+            ctx.insert(taffy.clone());
+            ctx.insert(style_diagnostics.clone());
+            ctx.insert(plugins.clone());
+            rdom.update_state(ctx);
+            compute_root_layout(
+                &rdom,
+                &mut taffy.lock().unwrap(),
+                Size {
+                    width: size.width as f32,
+                    height: size.height as f32,
+                },
+            );
+        }
+        let rdom: Arc<RwLock<RealDom>> = Arc::new(RwLock::new(rdom));
         let size = Arc::new(Mutex::new(size));
         let dirty = Arc::new(FxDashSet::default());
+        let hydrated = Arc::new(AtomicBool::new(false));
 
         let (event_sender, event_receiver) = unbounded_channel::<DomEvent>();
         let (redraw_sender, redraw_receiver) = unbounded_channel::<()>();
 
-        let (rdom_clone, size_clone, dirty_clone, taffy_clone) =
-            (rdom.clone(), size.clone(), dirty.clone(), taffy.clone());
+        let (rdom_clone, size_clone, dirty_clone, taffy_clone, plugins_clone, hydrated_clone) = (
+            rdom.clone(),
+            size.clone(),
+            dirty.clone(),
+            taffy.clone(),
+            plugins.clone(),
+            hydrated.clone(),
+        );
         // Spawn a thread to run the virtual dom and update the real dom.
         std::thread::spawn(move || {
-            tokio::runtime::Builder::new_current_thread()
-                .enable_all()
-                .build()
-                .unwrap()
-                .block_on(spawn_dom(
+            let dom = Box::pin(async move {
+                spawn_dom(
                     rdom_clone,
                     taffy_clone,
                     size_clone,
@@ -350,7 +1072,30 @@ impl DomManager {
                     event_receiver,
                     redraw_receiver,
                     dirty_clone,
-                ));
+                    style_diagnostics,
+                    plugins_clone,
+                    restored,
+                    hydrated_clone,
+                )
+                .await;
+            });
+            // The driver isn't Send, so it is always polled on this thread
+            match executor {
+                Some(executor) => {
+                    let executor: Arc<dyn Executor> = Arc::from(executor);
+                    crate::executor::set_current(executor.clone());
+                    executor.block_on(dom)
+                }
+                None => {
+                    let runtime = tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .unwrap();
+                    // Spawned tasks run while the DOM blocks on the runtime
+                    crate::executor::set_current(Arc::new(runtime.handle().clone()));
+                    runtime.block_on(dom)
+                }
+            }
         });
 
         Self {
@@ -361,11 +1106,15 @@ impl DomManager {
             event_sender,
             redraw_sender,
             force_redraw: false,
+            hydrated,
+            plugins,
         }
     }
 
-    fn clean(&self) -> DirtyNodes {
+    fn clean(&mut self) -> DirtyNodes {
         if self.force_redraw {
+            self.force_redraw = false;
+            self.dirty.clear();
             DirtyNodes::All
         } else {
             let dirty = self.dirty.iter().map(|k| *k.key()).collect();
@@ -396,12 +1145,15 @@ impl DomManager {
         self.redraw_sender.send(()).unwrap();
     }
 
-    fn render(&self, renderer: &mut Renderer) {
+    fn render(&self, scene: &mut Scene, window_size: PhysicalSize<u32>, transform: Affine) {
         render(
             &self.rdom(),
             &self.taffy(),
-            renderer,
+            scene,
             *self.size.lock().unwrap(),
+            window_size,
+            transform,
+            &self.plugins,
         );
     }
 
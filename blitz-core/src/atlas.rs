@@ -0,0 +1,384 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use epaint::textures::TextureOptions;
+use epaint::{Color32, ColorImage, ImageDelta, Rect, Vec2};
+use once_cell::sync::Lazy;
+use rustc_hash::FxHashMap;
+
+use crate::stats::{self, Counter};
+
+/// The width and height of the atlas in texels
+const ATLAS_SIZE: usize = 2048;
+/// The empty texels around each image, so linear filtering doesn't pick up the neighbours of an image
+const PADDING: usize = 1;
+/// The texels at the origin are white, so shapes without a texture can sample uv 0, 0
+const WHITE_SIZE: usize = 2;
+/// How many changes to the atlas are remembered for partial uploads
+const MAX_CHANGES: usize = 64;
+/// Images are only placed on a shelf that is at most this many times as tall as they are, so short glyphs don't waste the room of tall images
+const MAX_SHELF_WASTE: usize = 2;
+
+/// The one texture that glyphs, images and icons are drawn from. Every mesh samples it, so backends bind a single texture for the whole frame, see [`crate::Backend::update_texture`].
+///
+/// Images are packed on shelves: rows with the height of the first image that was placed on them, which are filled from left to right. Images stay where they are, so meshes that were built before stay valid.
+///
+/// TODO: Evict images that weren't drawn for a while once the atlas is full
+pub(crate) struct Atlas {
+    image: ColorImage,
+    regions: FxHashMap<AtlasKey, Option<AtlasRegion>>,
+    shelves: Vec<Shelf>,
+    /// Incremented with every change to the image
+    generation: u64,
+    /// The areas that changed as min x, min y, max x and max y, with the generation they changed in. The oldest change is first
+    changes: VecDeque<(u64, [usize; 4])>,
+    /// Set once a full atlas was reported, so it is only logged once
+    full: bool,
+}
+
+/// What an area of the atlas holds
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum AtlasKey {
+    /// A glyph of a face at a size in quarter pixels. Subpixel glyphs have the coverage of each color channel in their texels
+    Glyph {
+        face: u16,
+        id: u16,
+        size: u32,
+        subpixel: bool,
+    },
+    /// The color bitmap or color layers of a glyph of a color font at a size in quarter pixels, with premultiplied colors. `foreground` is the color of the text for glyphs with layers that are filled with it
+    ColorGlyph {
+        face: u16,
+        id: u16,
+        size: u32,
+        foreground: Option<[u8; 3]>,
+    },
+    /// An image, identified by the id of its data
+    Image(u64),
+    /// One of the slots that the tiles of maps are swapped in and out of, with the size of the tiles in texels, see [`crate::TileMap`]
+    Tile { slot: u16, width: u16, height: u16 },
+}
+
+/// An image in the atlas
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct AtlasRegion {
+    /// The area of the atlas the image is in, from 0 to 1
+    pub uv: Rect,
+    /// The offset of the top left corner of the image from the point it is drawn at, like the bearing of a glyph
+    pub offset: Vec2,
+    /// The size of the image in texels
+    pub size: Vec2,
+}
+
+struct Shelf {
+    y: usize,
+    height: usize,
+    /// Where the next image on the shelf is placed
+    x: usize,
+}
+
+pub(crate) static ATLAS: Lazy<Mutex<Atlas>> = Lazy::new(|| Mutex::new(Atlas::new()));
+
+impl Atlas {
+    fn new() -> Self {
+        let mut image = ColorImage::new([ATLAS_SIZE, ATLAS_SIZE], Color32::TRANSPARENT);
+        for y in 0..WHITE_SIZE {
+            for x in 0..WHITE_SIZE {
+                image[(x, y)] = Color32::WHITE;
+            }
+        }
+        Self {
+            image,
+            regions: FxHashMap::default(),
+            shelves: vec![Shelf {
+                y: 0,
+                height: WHITE_SIZE,
+                x: WHITE_SIZE + PADDING,
+            }],
+            generation: 1,
+            changes: VecDeque::new(),
+            full: false,
+        }
+    }
+
+    /// Find the region of an image, adding it with `create` the first time. `create` returns the texels with premultiplied colors and their offset, or None if there is nothing to draw, like for a space.
+    ///
+    /// Returns None if there is nothing to draw or the atlas is full.
+    pub fn get_or_insert(
+        &mut self,
+        key: AtlasKey,
+        create: impl FnOnce() -> Option<(ColorImage, Vec2)>,
+    ) -> Option<AtlasRegion> {
+        let glyph = matches!(key, AtlasKey::Glyph { .. } | AtlasKey::ColorGlyph { .. });
+        if let Some(region) = self.regions.get(&key) {
+            if glyph {
+                stats::count(Counter::GlyphHits, 1);
+            }
+            return *region;
+        }
+        if glyph {
+            stats::count(Counter::GlyphMisses, 1);
+        }
+        let region = create().and_then(|(image, offset)| self.insert(&image, offset));
+        self.regions.insert(key, region);
+        region
+    }
+
+    /// The region of an image that is in the atlas, without adding it.
+    pub fn region(&self, key: AtlasKey) -> Option<AtlasRegion> {
+        self.regions.get(&key).copied().flatten()
+    }
+
+    /// Put an image in the region of a key, replacing the image that was there. The region is added the first time, and keeps its place after that, so keys with the same size are swapped in and out of the same texels.
+    ///
+    /// Returns None if the atlas is full.
+    pub fn replace(&mut self, key: AtlasKey, image: &ColorImage) -> Option<AtlasRegion> {
+        let region = self.regions.get(&key).copied().flatten().filter(|region| {
+            region.size == epaint::vec2(image.size[0] as f32, image.size[1] as f32)
+        });
+        match region {
+            Some(region) => {
+                let min = region.uv.min.to_vec2() * ATLAS_SIZE as f32;
+                self.write([min.x.round() as usize, min.y.round() as usize], image);
+                Some(region)
+            }
+            None => {
+                let region = self.insert(image, Vec2::ZERO);
+                self.regions.insert(key, region);
+                region
+            }
+        }
+    }
+
+    fn insert(&mut self, image: &ColorImage, offset: Vec2) -> Option<AtlasRegion> {
+        let [width, height] = image.size;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let Some([x, y]) = self.allocate(width, height) else {
+            if !std::mem::replace(&mut self.full, true) {
+                log::warn!("the texture atlas is full, images that don't fit are skipped");
+            }
+            return None;
+        };
+        self.write([x, y], image);
+        let texel = 1.0 / ATLAS_SIZE as f32;
+        Some(AtlasRegion {
+            uv: Rect::from_min_size(
+                epaint::pos2(x as f32 * texel, y as f32 * texel),
+                epaint::vec2(width as f32 * texel, height as f32 * texel),
+            ),
+            offset,
+            size: epaint::vec2(width as f32, height as f32),
+        })
+    }
+
+    /// Copy the texels of an image into the atlas at a position and remember the change.
+    fn write(&mut self, [x, y]: [usize; 2], image: &ColorImage) {
+        let [width, height] = image.size;
+        for row in 0..height {
+            let source = &image.pixels[row * width..(row + 1) * width];
+            let start = (y + row) * ATLAS_SIZE + x;
+            self.image.pixels[start..start + width].copy_from_slice(source);
+        }
+        self.generation += 1;
+        self.changes
+            .push_back((self.generation, [x, y, x + width, y + height]));
+        if self.changes.len() > MAX_CHANGES {
+            self.changes.pop_front();
+        }
+    }
+
+    /// Find room for an image on the shelf that wastes the least height, or on a new shelf. Returns None if the atlas is full.
+    fn allocate(&mut self, width: usize, height: usize) -> Option<[usize; 2]> {
+        if width + PADDING > ATLAS_SIZE {
+            return None;
+        }
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| {
+                shelf.height >= height
+                    && shelf.height <= height * MAX_SHELF_WASTE
+                    && shelf.x + width <= ATLAS_SIZE
+            })
+            .min_by_key(|shelf| shelf.height);
+        let shelf = match shelf {
+            Some(shelf) => shelf,
+            None => {
+                let y = self
+                    .shelves
+                    .last()
+                    .map_or(0, |shelf| shelf.y + shelf.height + PADDING);
+                if y + height > ATLAS_SIZE {
+                    return None;
+                }
+                self.shelves.push(Shelf { y, height, x: 0 });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+        let position = [shelf.x, shelf.y];
+        shelf.x += width + PADDING;
+        Some(position)
+    }
+}
+
+/// The region of an image in the atlas, adding it the first time it is drawn.
+pub(crate) fn image_region(image: &peniko::Image) -> Option<AtlasRegion> {
+    ATLAS
+        .lock()
+        .unwrap()
+        .get_or_insert(AtlasKey::Image(image.data.id()), || {
+            Some((premultiplied(image), Vec2::ZERO))
+        })
+}
+
+/// The texels of an image with premultiplied colors, which the atlas holds.
+pub(crate) fn premultiplied(image: &peniko::Image) -> ColorImage {
+    let pixels = image
+        .data
+        .data()
+        .chunks_exact(4)
+        .map(|texel| Color32::from_rgba_unmultiplied(texel[0], texel[1], texel[2], texel[3]))
+        .collect();
+    let size = [image.width as usize, image.height as usize];
+    ColorImage { size, pixels }
+}
+
+/// A copy of the atlas, for drawing on the CPU.
+pub(crate) fn atlas_image() -> ColorImage {
+    ATLAS.lock().unwrap().image.clone()
+}
+
+/// The changes to the atlas since a generation, for [`crate::Backend::update_texture`]. `generation` is set to the current generation, so the next call only returns later changes. A generation of 0 returns the whole atlas.
+pub(crate) fn atlas_delta(generation: &mut u64) -> Option<ImageDelta> {
+    let atlas = ATLAS.lock().unwrap();
+    if *generation == atlas.generation {
+        return None;
+    }
+    let remembered = atlas
+        .changes
+        .front()
+        .map_or(atlas.generation, |(oldest, _)| oldest - 1);
+    let delta = if *generation == 0 || *generation < remembered {
+        ImageDelta::full(atlas.image.clone(), TextureOptions::LINEAR)
+    } else {
+        let [min_x, min_y, max_x, max_y] = atlas
+            .changes
+            .iter()
+            .filter(|(changed, _)| *changed > *generation)
+            .fold(
+                [usize::MAX, usize::MAX, 0, 0],
+                |[min_x, min_y, max_x, max_y], (_, area)| {
+                    [
+                        min_x.min(area[0]),
+                        min_y.min(area[1]),
+                        max_x.max(area[2]),
+                        max_y.max(area[3]),
+                    ]
+                },
+            );
+        let pixels = (min_y..max_y)
+            .flat_map(|y| (min_x..max_x).map(move |x| (x, y)))
+            .map(|position| atlas.image[position])
+            .collect();
+        let region = ColorImage {
+            size: [max_x - min_x, max_y - min_y],
+            pixels,
+        };
+        ImageDelta::partial([min_x, min_y], region, TextureOptions::LINEAR)
+    };
+    *generation = atlas.generation;
+    Some(delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: usize, height: usize, color: Color32) -> ColorImage {
+        ColorImage::new([width, height], color)
+    }
+
+    #[test]
+    fn shelves() {
+        let mut atlas = Atlas::new();
+        // The shelf of the white texels is too short, so the first image starts a shelf under it
+        assert_eq!(atlas.allocate(10, 10), Some([0, 3]));
+        assert_eq!(atlas.allocate(10, 8), Some([11, 3]));
+        // The shelf would waste more than half of its height
+        assert_eq!(atlas.allocate(4, 4), Some([0, 14]));
+        assert_eq!(atlas.allocate(2, 2), Some([3, 0]));
+        assert_eq!(atlas.allocate(4, 3), Some([5, 14]));
+    }
+
+    #[test]
+    fn full() {
+        let mut atlas = Atlas::new();
+        assert_eq!(atlas.allocate(ATLAS_SIZE, 1), None);
+        assert_eq!(atlas.allocate(1000, 1000), Some([0, 3]));
+        assert_eq!(atlas.allocate(1000, 1000), Some([1001, 3]));
+        assert_eq!(atlas.allocate(1000, 1000), Some([0, 1004]));
+        assert_eq!(atlas.allocate(1000, 1000), Some([1001, 1004]));
+        assert_eq!(atlas.allocate(1000, 1000), None);
+        // Smaller images still fit
+        assert_eq!(atlas.allocate(10, 10), Some([0, 2005]));
+        assert!(!atlas.full);
+        assert_eq!(
+            atlas.insert(&image(1000, 1000, Color32::RED), Vec2::ZERO),
+            None
+        );
+        assert!(atlas.full);
+    }
+
+    #[test]
+    fn regions() {
+        let mut atlas = Atlas::new();
+        let key = AtlasKey::Image(1);
+        let offset = epaint::vec2(-1.0, 2.0);
+        let region = atlas
+            .get_or_insert(key, || Some((image(4, 2, Color32::RED), offset)))
+            .unwrap();
+        let texel = 1.0 / ATLAS_SIZE as f32;
+        // Short images go next to the white texels
+        assert_eq!(
+            region.uv,
+            Rect::from_min_size(
+                epaint::pos2(3.0 * texel, 0.0),
+                epaint::vec2(4.0 * texel, 2.0 * texel)
+            )
+        );
+        assert_eq!(region.offset, offset);
+        assert_eq!(region.size, epaint::vec2(4.0, 2.0));
+        assert_eq!(atlas.image[(6, 1)], Color32::RED);
+        assert_eq!(atlas.image[(7, 1)], Color32::TRANSPARENT);
+        assert_eq!(atlas.image[(0, 0)], Color32::WHITE);
+
+        // Images are only created once, even when there is nothing to draw
+        let again = atlas.get_or_insert(key, || unreachable!());
+        assert_eq!(again, Some(region));
+        let space = AtlasKey::Image(2);
+        assert_eq!(atlas.get_or_insert(space, || None), None);
+        assert_eq!(atlas.get_or_insert(space, || unreachable!()), None);
+        assert_eq!(atlas.region(key), Some(region));
+        assert_eq!(atlas.region(space), None);
+    }
+
+    #[test]
+    fn replace() {
+        let mut atlas = Atlas::new();
+        let key = AtlasKey::Tile {
+            slot: 0,
+            width: 2,
+            height: 2,
+        };
+        let first = atlas.replace(key, &image(2, 2, Color32::RED)).unwrap();
+        let generation = atlas.generation;
+        // The same size keeps its texels
+        let second = atlas.replace(key, &image(2, 2, Color32::BLUE)).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(atlas.image[(3, 0)], Color32::BLUE);
+        assert_eq!(atlas.generation, generation + 1);
+        assert_eq!(atlas.changes.back(), Some(&(generation + 1, [3, 0, 5, 2])));
+    }
+}
@@ -0,0 +1,178 @@
+use beuk::ash::vk;
+use beuk::ctx::RenderContext;
+
+/// An image on the GPU that can be rendered to or, with the usage for it, sampled and copied to.
+pub(crate) struct Attachment {
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+    pub extent: vk::Extent2D,
+    memory: vk::DeviceMemory,
+}
+
+impl Attachment {
+    pub fn new(
+        ctx: &RenderContext,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        samples: vk::SampleCountFlags,
+        usage: vk::ImageUsageFlags,
+    ) -> Self {
+        unsafe {
+            let image = ctx
+                .device
+                .create_image(
+                    &vk::ImageCreateInfo::default()
+                        .image_type(vk::ImageType::TYPE_2D)
+                        .format(format)
+                        .extent(vk::Extent3D {
+                            width: extent.width,
+                            height: extent.height,
+                            depth: 1,
+                        })
+                        .mip_levels(1)
+                        .array_layers(1)
+                        .samples(samples)
+                        .tiling(vk::ImageTiling::OPTIMAL)
+                        .usage(usage)
+                        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                        .initial_layout(vk::ImageLayout::UNDEFINED),
+                    None,
+                )
+                .unwrap();
+
+            let requirements = ctx.device.get_image_memory_requirements(image);
+            let memory_properties = ctx
+                .instance
+                .get_physical_device_memory_properties(ctx.physical_device);
+            let memory_type_index = memory_properties
+                .memory_types_as_slice()
+                .iter()
+                .enumerate()
+                .position(|(index, memory_type)| {
+                    requirements.memory_type_bits & (1 << index) != 0
+                        && memory_type
+                            .property_flags
+                            .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                })
+                .expect("no device local memory for the attachment")
+                as u32;
+            let memory = ctx
+                .device
+                .allocate_memory(
+                    &vk::MemoryAllocateInfo::default()
+                        .allocation_size(requirements.size)
+                        .memory_type_index(memory_type_index),
+                    None,
+                )
+                .unwrap();
+            ctx.device.bind_image_memory(image, memory, 0).unwrap();
+
+            let view = ctx
+                .device
+                .create_image_view(
+                    &vk::ImageViewCreateInfo::default()
+                        .image(image)
+                        .view_type(vk::ImageViewType::TYPE_2D)
+                        .format(format)
+                        .subresource_range(COLOR_SUBRESOURCE_RANGE),
+                    None,
+                )
+                .unwrap();
+
+            Self {
+                image,
+                view,
+                extent,
+                memory,
+            }
+        }
+    }
+
+    /// Record a barrier that discards the contents of the image and prepares it to be rendered to, after it was rendered to or sampled before.
+    pub fn begin_rendering(&self, device: &beuk::ash::Device, command_buffer: vk::CommandBuffer) {
+        let barrier = vk::ImageMemoryBarrier::default()
+            .image(self.image)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(
+                vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            )
+            .subresource_range(COLOR_SUBRESOURCE_RANGE);
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                std::slice::from_ref(&barrier),
+            );
+        }
+    }
+
+    /// Record a barrier that prepares an image that was sampled to be rendered to again, keeping its contents.
+    pub fn resume_rendering(&self, device: &beuk::ash::Device, command_buffer: vk::CommandBuffer) {
+        let barrier = vk::ImageMemoryBarrier::default()
+            .image(self.image)
+            .old_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .new_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .src_access_mask(vk::AccessFlags::SHADER_READ)
+            .dst_access_mask(
+                vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            )
+            .subresource_range(COLOR_SUBRESOURCE_RANGE);
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                std::slice::from_ref(&barrier),
+            );
+        }
+    }
+
+    /// Record a barrier that makes what was rendered to the image visible to the fragment shaders that sample it next.
+    pub fn end_rendering(&self, device: &beuk::ash::Device, command_buffer: vk::CommandBuffer) {
+        let barrier = vk::ImageMemoryBarrier::default()
+            .image(self.image)
+            .old_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ)
+            .subresource_range(COLOR_SUBRESOURCE_RANGE);
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                std::slice::from_ref(&barrier),
+            );
+        }
+    }
+
+    /// Free the image. The GPU must be done using it.
+    pub fn destroy(&self, ctx: &RenderContext) {
+        unsafe {
+            ctx.device.destroy_image_view(self.view, None);
+            ctx.device.destroy_image(self.image, None);
+            ctx.device.free_memory(self.memory, None);
+        }
+    }
+}
+
+pub(crate) const COLOR_SUBRESOURCE_RANGE: vk::ImageSubresourceRange = vk::ImageSubresourceRange {
+    aspect_mask: vk::ImageAspectFlags::COLOR,
+    base_mip_level: 0,
+    level_count: 1,
+    base_array_layer: 0,
+    layer_count: 1,
+};
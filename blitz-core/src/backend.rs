@@ -0,0 +1,626 @@
+use std::time::{Duration, Instant};
+
+use epaint::{Color32, ImageDelta, Mesh, Rect, RectShape, Rgba};
+use peniko::kurbo::Affine;
+use tao::dpi::PhysicalSize;
+
+use crate::atlas::atlas_delta;
+use crate::scene::{BackdropBlur, Batch, BatchedLayer, Scene, TessellationCache};
+use crate::stats::{self, Counter};
+
+/// The largest texture a cached layer is drawn into along each axis. Larger content is drawn with the rest of the window
+const MAX_CACHED_LAYER_SIZE: f32 = 4096.0;
+
+/// Draws the frames of a window. Blitz draws with Vulkan by default. Set another backend with [`crate::Config::with_backend`] to draw with a different graphics API or to run where Vulkan isn't available.
+///
+/// Blitz tessellates the document into triangle meshes, so a backend only has to draw triangles. Each frame is drawn with one call to [`Backend::begin_frame`], the meshes in paint order and one call to [`Backend::end_frame`].
+pub trait Backend {
+    /// The name of the graphics adapter, which is included in diagnostics reports
+    fn adapter_name(&self) -> String {
+        "unknown".to_string()
+    }
+
+    /// The window was resized to the given size in physical pixels. It is called before the next frame is drawn.
+    fn resize(&mut self, size: PhysicalSize<u32>);
+
+    /// Update the texture meshes are sampled from with their uv coordinates. It is an atlas of the glyphs of text and of images, so one texture is bound for the whole frame. `delta` either replaces the whole texture or, if [`ImageDelta::pos`] is set, an area of it. Texels are premultiplied sRGB with linear alpha like [`Color32`], so they are sampled from an sRGB texture and multiplied with the color of the vertex in linear space.
+    ///
+    /// Shapes without a texture sample the white texels at uv 0, 0. It is called before [`Backend::begin_frame`] when the texture changed. Backends that ignore it draw text and images as solid boxes.
+    fn update_texture(&mut self, delta: &ImageDelta) {
+        let _ = delta;
+    }
+
+    /// Start drawing a frame that is cleared to a color.
+    ///
+    /// Only the `damage` area of the window looks different than in the last frame. Backends that keep the last frame can draw only that area, others draw everything.
+    fn begin_frame(&mut self, clear_color: Color32, damage: Rect);
+
+    /// Draw a mesh. The vertices are in window coordinates in physical pixels before the transform is applied and their colors are premultiplied sRGB. Fragments outside of `clip` are discarded and the colors are multiplied by `opacity`.
+    fn draw_mesh(&mut self, mesh: &Mesh, transform: Affine, clip: Rect, opacity: f32);
+
+    /// Whether the backend draws text with subpixel anti-aliasing with [`Backend::draw_subpixel_text`]. Blitz then rasterizes the glyphs of text that is drawn straight into the window with the coverage of each color channel, if [`crate::Config::with_subpixel_text`] is set.
+    fn draws_subpixel_text(&self) -> bool {
+        false
+    }
+
+    /// Draw a text mesh whose texels have the coverage of the red, green and blue stripes of the pixels in their color channels, with the same coordinates, clip and opacity as [`Backend::draw_mesh`]. Each color channel of the pixels below is blended with the coverage of that channel times the alpha of the vertex, instead of the alpha of the texel. Only called if [`Backend::draws_subpixel_text`] returns true.
+    fn draw_subpixel_text(&mut self, mesh: &Mesh, transform: Affine, clip: Rect, opacity: f32) {
+        self.draw_mesh(mesh, transform, clip, opacity);
+    }
+
+    /// Composite the window with the windows behind it by the alpha of its pixels, for windows with parts that are see-through like the rounded corners and the shadow of [`crate::Config::with_undecorated`]. Called once before the first frame. Returns false if the surface can't be composited, which is the default, and the window stays opaque.
+    fn set_transparent(&mut self) -> bool {
+        false
+    }
+
+    /// Whether the backend draws rects with [`Backend::draw_rects`]. Blitz then leaves rects out of the meshes, so a backend can draw each of them as a single quad.
+    fn draws_rects(&self) -> bool {
+        false
+    }
+
+    /// Draw rects, with the same coordinates, clip and opacity as [`Backend::draw_mesh`]. Only called if [`Backend::draws_rects`] returns true.
+    fn draw_rects(&mut self, rects: &[RectInstance], transform: Affine, clip: Rect, opacity: f32) {
+        let _ = (rects, transform, clip, opacity);
+    }
+
+    /// Blur what was drawn so far in an area of the window, for `backdrop-filter`. The area is in window coordinates in physical pixels and `radius` is the standard deviation of the blur in pixels. Pixels outside of the area aren't sampled.
+    ///
+    /// Meshes drawn after it are drawn over the blurred pixels. Backends that ignore it draw the backdrop without the blur.
+    fn blur_backdrop(&mut self, area: Rect, radius: f32) {
+        let _ = (area, radius);
+    }
+
+    /// Whether the backend draws filtered and translucent elements offscreen with [`Backend::begin_filter`] and [`Backend::end_filter`]. Otherwise Blitz applies the filter and the opacity to the colors of each mesh and rect, which differs where translucent shapes overlap and leaves images unfiltered.
+    fn draws_filters(&self) -> bool {
+        false
+    }
+
+    /// Start drawing into a new layer that is cleared to transparent. Meshes are drawn into it until the matching [`Backend::end_filter`]. Filters can be nested. Only called if [`Backend::draws_filters`] returns true.
+    fn begin_filter(&mut self) {}
+
+    /// Draw the layer started by the matching [`Backend::begin_filter`] over the layer below it, with a color matrix applied to each of its pixels and then multiplied by `opacity`.
+    fn end_filter(&mut self, matrix: &ColorMatrix, opacity: f32) {
+        let _ = (matrix, opacity);
+    }
+
+    /// Whether the backend keeps textures between frames with [`Backend::begin_cached_layer`]. Blitz then draws the content of elements that will move or scroll without changing into a texture when it changes, and draws the texture in every frame instead of every mesh of the content. Only used if the backend also draws filters.
+    fn caches_layers(&self) -> bool {
+        false
+    }
+
+    /// Start drawing into the texture of a cached layer with a size in pixels, which is cleared to transparent. The texture is created if it doesn't exist yet or has another size. Meshes are drawn into it in the coordinates of the texture until the matching [`Backend::end_cached_layer`], and aren't clipped to the damaged area of the window.
+    ///
+    /// Only called if [`Backend::caches_layers`] returns true, after [`Backend::begin_frame`] and before the meshes of the frame.
+    fn begin_cached_layer(&mut self, id: u64, size: [u32; 2]) {
+        let _ = (id, size);
+    }
+
+    /// Go back to drawing the frame.
+    fn end_cached_layer(&mut self) {}
+
+    /// Draw a mesh like [`Backend::draw_mesh`] that samples the texture of a cached layer instead of the atlas. Its uv coordinates span the texture from 0 to 1.
+    fn draw_cached_layer(
+        &mut self,
+        id: u64,
+        mesh: &Mesh,
+        transform: Affine,
+        clip: Rect,
+        opacity: f32,
+    ) {
+        let _ = (id, mesh, transform, clip, opacity);
+    }
+
+    /// The content of a cached layer is gone, so its texture can be freed.
+    fn release_cached_layer(&mut self, id: u64) {
+        let _ = id;
+    }
+
+    /// Finish the frame and present it.
+    fn end_frame(&mut self);
+
+    /// Measure how long the GPU takes to draw each frame, see [`Backend::gpu_time`]. Backends that can't measure it ignore this.
+    fn enable_gpu_timing(&mut self) {}
+
+    /// How long the GPU took to draw the last frame whose time is known, or `None` if it isn't measured.
+    fn gpu_time(&mut self) -> Option<Duration> {
+        None
+    }
+
+    /// Whether the backend can copy a frame to the CPU with [`Backend::capture_next_frame`]. Otherwise screenshots are drawn on the CPU from the same shapes, see [`crate::Screenshots`].
+    fn captures_frames(&self) -> bool {
+        false
+    }
+
+    /// Copy the next frame to the CPU once it is drawn, so [`Backend::captured_frame`] returns it. Only called if [`Backend::captures_frames`] returns true.
+    fn capture_next_frame(&mut self) {}
+
+    /// The frame copied after [`Backend::capture_next_frame`], in physical pixels with sRGB colors that aren't premultiplied. Returns `None` if no frame was copied, for example because the surface was lost.
+    fn captured_frame(&mut self) -> Option<image::RgbaImage> {
+        None
+    }
+
+    /// Rebuild the pipeline from new GLSL shaders, see [`crate::Config::with_shader_reload`]. Backends without GLSL shaders ignore it.
+    fn reload_shaders(&mut self, vertex_source: &str, fragment_source: &str) {
+        let _ = (vertex_source, fragment_source);
+    }
+}
+
+/// Draw a frame with a backend. Only the layers that intersect the damaged area of the window are tessellated again.
+///
+/// While the window is live resized the whole window is damaged, so every layer that didn't change reuses its mesh instead. Returns how long tessellating took.
+///
+/// With `debug`, rects are tessellated too and the triangles of every mesh are drawn instead of the mesh.
+pub(crate) fn draw_scene(
+    backend: &mut dyn Backend,
+    scene: &Scene,
+    damage: Rect,
+    cache: &mut TessellationCache,
+    live_resize: bool,
+    debug: Option<DebugGeometry>,
+) -> Duration {
+    if let Some(delta) = atlas_delta(&mut cache.texture_generation) {
+        backend.update_texture(&delta);
+    }
+    let damage = scene.backdrop_damage(damage);
+    backend.begin_frame(scene.clear_color, damage);
+    let tessellated = if live_resize { Rect::NOTHING } else { damage };
+    let started = Instant::now();
+    let rects = backend.draws_rects() && debug.is_none();
+    let mut layers = scene.tessellate_damaged(1.0, tessellated, cache, rects);
+    let tessellation_time = started.elapsed();
+    for id in cache.released.drain(..) {
+        backend.release_cached_layer(id);
+    }
+    // The colors of a texture can't be filtered through its vertices, so filters have to be drawn offscreen
+    let caches_layers = backend.caches_layers() && backend.draws_filters();
+    for (node, content) in &scene.contents {
+        let Some(cached) = cache.contents.get_mut(node) else {
+            continue;
+        };
+        // The texture covers the area of the content in whole pixels
+        let area = epaint::Rect::from_min_max(content.area.min.floor(), content.area.max.ceil());
+        let fits = caches_layers
+            && area.is_positive()
+            && area.width() <= MAX_CACHED_LAYER_SIZE
+            && area.height() <= MAX_CACHED_LAYER_SIZE;
+        if !fits {
+            if let Some((id, _)) = cached.texture.take() {
+                backend.release_cached_layer(id);
+            }
+            // The meshes of the content are drawn with the other layers, moved to where the content is
+            for (offset, (layer, batches)) in cached.layers.iter().enumerate() {
+                let window = content.to_window(layer);
+                layers.push(BatchedLayer {
+                    index: cached.first + offset,
+                    transform: window.transform,
+                    clip: window.clip,
+                    group: window.group,
+                    batches: batches.clone(),
+                    backdrop: window.backdrop,
+                });
+            }
+            continue;
+        }
+        let redraw = cached.changed || cached.texture.map_or(true, |(_, texture)| texture != area);
+        let id = match cached.texture {
+            Some((id, _)) => id,
+            None => {
+                cache.last_texture += 1;
+                cache.last_texture
+            }
+        };
+        cached.texture = Some((id, area));
+        if redraw {
+            backend.begin_cached_layer(id, [area.width() as u32, area.height() as u32]);
+            let offset = -area.min.to_vec2();
+            let content_layers: Vec<BatchedLayer> = cached
+                .layers
+                .iter()
+                .enumerate()
+                .map(|(index, (layer, batches))| BatchedLayer {
+                    index,
+                    transform: Affine::translate((offset.x as f64, offset.y as f64))
+                        * layer.transform,
+                    clip: layer.clip.translate(offset),
+                    group: layer.group,
+                    batches: batches.clone(),
+                    backdrop: layer.backdrop.map(|backdrop| BackdropBlur {
+                        area: backdrop.area.translate(offset),
+                        ..backdrop
+                    }),
+                })
+                .collect();
+            draw_layers(backend, scene, &content_layers, content.group, debug);
+            backend.end_cached_layer();
+        }
+        layers.push(BatchedLayer {
+            index: cached.first,
+            transform: content.transform
+                * Affine::translate((area.min.x as f64, area.min.y as f64)),
+            clip: content.clip,
+            group: content.group,
+            batches: vec![Batch::CachedLayer(id, texture_quad(area.size()))],
+            backdrop: None,
+        });
+    }
+    // The layers of content are put back in paint order
+    layers.sort_by_key(|layer| layer.index);
+    draw_layers(backend, scene, &layers, None, debug);
+    backend.end_frame();
+    tessellation_time
+}
+
+/// Draw layers in paint order. Their opacity and filters are those of their groups inside of the `base` group, which is the group the layers are composited in.
+fn draw_layers(
+    backend: &mut dyn Backend,
+    scene: &Scene,
+    layers: &[BatchedLayer],
+    base: Option<usize>,
+    debug: Option<DebugGeometry>,
+) {
+    let draws_filters = backend.draws_filters();
+    // The groups that are drawn offscreen, from the outermost to the innermost
+    let mut open_groups: Vec<usize> = Vec::new();
+    for layer in layers {
+        let mut chain = scene.group_chain(layer.group);
+        if let Some(base) = base.and_then(|base| chain.iter().position(|group| *group == base)) {
+            chain.drain(..=base);
+        }
+        let (opacity, filter) = if draws_filters {
+            // Translucent and filtered groups are drawn into a layer of their own and composited once, so their children don't blend with each other
+            let offscreen: Vec<usize> = chain
+                .into_iter()
+                .filter(|group| {
+                    let group = &scene.groups[*group];
+                    group.opacity < 1.0 || group.filter != ColorMatrix::IDENTITY
+                })
+                .collect();
+            let shared = open_groups
+                .iter()
+                .zip(&offscreen)
+                .take_while(|(open, group)| open == group)
+                .count();
+            while open_groups.len() > shared {
+                let group = &scene.groups[open_groups.pop().unwrap()];
+                backend.end_filter(&group.filter, group.opacity);
+            }
+            for group in &offscreen[shared..] {
+                backend.begin_filter();
+                open_groups.push(*group);
+            }
+            (1.0, ColorMatrix::IDENTITY)
+        } else {
+            let opacity: f32 = chain
+                .iter()
+                .map(|group| scene.groups[*group].opacity)
+                .product();
+            // The filter of the innermost group is applied first
+            let filter = chain
+                .iter()
+                .rev()
+                .fold(ColorMatrix::IDENTITY, |filter, group| {
+                    filter.then(scene.groups[*group].filter)
+                });
+            (opacity, filter)
+        };
+        if let Some(backdrop) = layer.backdrop {
+            backend.blur_backdrop(backdrop.area, backdrop.radius);
+        }
+        for batch in &layer.batches {
+            match batch {
+                Batch::Mesh(mesh) | Batch::CachedLayer(_, mesh) | Batch::SubpixelText(mesh) => {
+                    count_mesh(mesh)
+                }
+                Batch::Rects(_) => stats::count(Counter::DrawCalls, 1),
+            }
+            if let (Some(debug), Batch::Mesh(mesh) | Batch::SubpixelText(mesh)) = (debug, batch) {
+                backend.draw_mesh(&debug.mesh(mesh), layer.transform, layer.clip, opacity);
+                continue;
+            }
+            match batch {
+                Batch::Mesh(mesh) if filter != ColorMatrix::IDENTITY => {
+                    let mut mesh = mesh.clone();
+                    for vertex in &mut mesh.vertices {
+                        vertex.color = filter.apply_to_color32(vertex.color);
+                    }
+                    backend.draw_mesh(&mesh, layer.transform, layer.clip, opacity)
+                }
+                Batch::Mesh(mesh) => backend.draw_mesh(mesh, layer.transform, layer.clip, opacity),
+                Batch::Rects(rects) if filter != ColorMatrix::IDENTITY => {
+                    let rects: Vec<RectInstance> = rects
+                        .iter()
+                        .map(|rect| RectInstance {
+                            fill: filter.apply_to_color32(rect.fill),
+                            border_color: filter.apply_to_color32(rect.border_color),
+                            ..*rect
+                        })
+                        .collect();
+                    backend.draw_rects(&rects, layer.transform, layer.clip, opacity)
+                }
+                Batch::Rects(rects) => {
+                    backend.draw_rects(rects, layer.transform, layer.clip, opacity)
+                }
+                Batch::CachedLayer(id, mesh) => {
+                    backend.draw_cached_layer(*id, mesh, layer.transform, layer.clip, opacity)
+                }
+                Batch::SubpixelText(mesh) => {
+                    backend.draw_subpixel_text(mesh, layer.transform, layer.clip, opacity)
+                }
+            }
+        }
+    }
+    while let Some(group) = open_groups.pop() {
+        let group = &scene.groups[group];
+        backend.end_filter(&group.filter, group.opacity);
+    }
+}
+
+/// Count a mesh that is drawn into the [`crate::FrameStats`] of the frame.
+fn count_mesh(mesh: &Mesh) {
+    stats::count(Counter::DrawCalls, 1);
+    stats::count(Counter::VerticesDrawn, mesh.vertices.len());
+    stats::count(Counter::IndicesDrawn, mesh.indices.len());
+}
+
+/// A quad that covers the texture of a cached layer with a size in pixels.
+fn texture_quad(size: epaint::Vec2) -> Mesh {
+    let mut mesh = Mesh::default();
+    mesh.add_rect_with_uv(
+        Rect::from_min_size(epaint::Pos2::ZERO, size),
+        Rect::from_min_max(epaint::Pos2::ZERO, epaint::pos2(1.0, 1.0)),
+        Color32::WHITE,
+    );
+    mesh
+}
+
+/// A rect with rounded corners and a border, drawn by backends that return true from [`Backend::draws_rects`].
+///
+/// It is laid out to be used as instance data: the vertex shader covers the rect with a quad and the fragment shader fills it with the signed distance from its edge, which keeps the corners smooth at any scale.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct RectInstance {
+    /// The rect as min x, min y, max x and max y in the coordinates of the layer
+    pub rect: [f32; 4],
+    /// The radii of the top left, top right, bottom right and bottom left corners. They are at most half the width and height of the rect
+    pub radii: [f32; 4],
+    /// Premultiplied sRGB, like the colors of meshes
+    pub fill: Color32,
+    pub border_color: Color32,
+    /// The width of the border, which is centered on the edge of the rect like the stroke of an epaint rect
+    pub border_width: f32,
+}
+
+impl RectInstance {
+    pub(crate) fn new(shape: &RectShape) -> Self {
+        let rect = shape.rect;
+        let max_radius = rect.width().min(rect.height()) / 2.0;
+        let radius = |radius: f32| radius.clamp(0.0, max_radius.max(0.0));
+        let rounding = shape.rounding;
+        Self {
+            rect: [rect.min.x, rect.min.y, rect.max.x, rect.max.y],
+            radii: [
+                radius(rounding.nw),
+                radius(rounding.ne),
+                radius(rounding.se),
+                radius(rounding.sw),
+            ],
+            fill: shape.fill,
+            border_color: shape.stroke.color,
+            border_width: shape.stroke.width,
+        }
+    }
+
+    /// The area the rect draws to, including the outer half of the border
+    pub(crate) fn bounds(&self) -> Rect {
+        let [min_x, min_y, max_x, max_y] = self.rect;
+        Rect::from_min_max(epaint::pos2(min_x, min_y), epaint::pos2(max_x, max_y))
+            .expand(self.border_width / 2.0)
+    }
+
+    /// The rect with its colors multiplied by an opacity
+    pub(crate) fn with_opacity(self, opacity: f32) -> Self {
+        if opacity >= 1.0 {
+            return self;
+        }
+        Self {
+            fill: self.fill.linear_multiply(opacity),
+            border_color: self.border_color.linear_multiply(opacity),
+            ..self
+        }
+    }
+}
+
+/// Maps colors to other colors, for the CSS `filter` functions. Each row computes the red, green, blue or alpha of the new color from the red, green, blue and alpha of the old color.
+///
+/// Colors are premultiplied and linear, so a constant is added by multiplying the alpha of the old color with it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorMatrix(pub [[f32; 4]; 4]);
+
+impl ColorMatrix {
+    pub const IDENTITY: Self = Self([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    /// The matrix that applies this matrix and then another one.
+    pub fn then(self, next: Self) -> Self {
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| next.0[i][k] * self.0[k][j]).sum();
+            }
+        }
+        Self(rows)
+    }
+
+    /// Apply the matrix to a premultiplied linear color. The result is clamped to a valid premultiplied color.
+    pub fn apply(&self, color: Rgba) -> Rgba {
+        let old = color.to_array();
+        let [r, g, b, a] = self.0.map(|row| {
+            row.iter()
+                .zip(old)
+                .map(|(weight, old)| weight * old)
+                .sum::<f32>()
+        });
+        let a = a.clamp(0.0, 1.0);
+        Rgba::from_rgba_premultiplied(r.clamp(0.0, a), g.clamp(0.0, a), b.clamp(0.0, a), a)
+    }
+
+    pub(crate) fn apply_to_color32(&self, color: Color32) -> Color32 {
+        Color32::from(self.apply(Rgba::from(color)))
+    }
+}
+
+/// The number of samples per pixel used to smooth the edges of shapes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Msaa {
+    #[default]
+    Off,
+    X2,
+    X4,
+    X8,
+}
+
+impl Msaa {
+    pub(crate) fn samples(self) -> u32 {
+        match self {
+            Msaa::Off => 1,
+            Msaa::X2 => 2,
+            Msaa::X4 => 4,
+            Msaa::X8 => 8,
+        }
+    }
+}
+
+/// How frames are handed to the display, which trades latency for tearing and power.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PresentMode {
+    /// Wait for the vertical blank and queue frames, so the frame rate follows the refresh rate of the display without tearing. Every adapter supports it
+    #[default]
+    Fifo,
+    /// Wait for the vertical blank, but replace the queued frame with a newer one, so the latency is low without tearing. The GPU keeps drawing frames the display doesn't show
+    Mailbox,
+    /// Show frames as soon as they are drawn, which has the lowest latency but may tear
+    Immediate,
+}
+
+/// The range of brightness a window is drawn in, see `Config::with_dynamic_range`. Only the wgpu backend draws in the high range.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum DynamicRange {
+    /// Draw into an 8 bit sRGB surface, where CSS white is the brightest color. Every adapter supports it
+    #[default]
+    Standard,
+    /// Draw into a 16 bit float surface in extended linear sRGB (scRGB), which displays colors brighter than white in HDR mode. CSS colors are mapped into the range so white is `white_nits` bright, the brightness the system shows SDR white at, often around 200 nits. Without the mapping white would be 80 nits, which looks dim next to HDR content
+    High { white_nits: f32 },
+}
+
+/// The brightness of 1.0 in scRGB
+const SCRGB_WHITE_NITS: f32 = 80.0;
+
+impl DynamicRange {
+    /// The value CSS white is drawn with, which the linear colors of the shaders are multiplied with.
+    pub(crate) fn white_level(self) -> f32 {
+        match self {
+            DynamicRange::Standard => 1.0,
+            DynamicRange::High { white_nits } => white_nits / SCRGB_WHITE_NITS,
+        }
+    }
+}
+
+/// Draws the triangles of the meshes in a way that shows how much geometry each shape is tessellated into, see [`crate::Config::with_debug_geometry`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DebugGeometry {
+    /// Draw the edges of the triangles instead of filling them
+    Wireframe,
+    /// Fill each triangle with a color of its own
+    Triangles,
+}
+
+/// The color of the edges of [`DebugGeometry::Wireframe`]
+const WIREFRAME_COLOR: Color32 = Color32::from_rgb(0, 200, 80);
+/// The width of the edges of [`DebugGeometry::Wireframe`] in pixels
+const WIREFRAME_WIDTH: f32 = 1.0;
+
+impl DebugGeometry {
+    /// The mesh that shows the triangles of a mesh.
+    fn mesh(self, mesh: &Mesh) -> Mesh {
+        let mut debug = Mesh::default();
+        for (index, triangle) in mesh.indices.chunks_exact(3).enumerate() {
+            let [a, b, c] = [0, 1, 2].map(|corner| mesh.vertices[triangle[corner] as usize].pos);
+            match self {
+                DebugGeometry::Wireframe => {
+                    for (from, to) in [(a, b), (b, c), (c, a)] {
+                        let edge = to - from;
+                        if edge.length_sq() == 0.0 {
+                            continue;
+                        }
+                        let normal = edge.normalized().rot90() * (WIREFRAME_WIDTH / 2.0);
+                        let first = debug.vertices.len() as u32;
+                        for pos in [from - normal, from + normal, to + normal, to - normal] {
+                            debug.colored_vertex(pos, WIREFRAME_COLOR);
+                        }
+                        debug.add_triangle(first, first + 1, first + 2);
+                        debug.add_triangle(first, first + 2, first + 3);
+                    }
+                }
+                DebugGeometry::Triangles => {
+                    // Neighboring triangles get hues far apart
+                    let hue = (index as f32 * 0.618_034).fract();
+                    let color = Color32::from(epaint::Hsva::new(hue, 0.7, 0.9, 1.0));
+                    let first = debug.vertices.len() as u32;
+                    for pos in [a, b, c] {
+                        debug.colored_vertex(pos, color);
+                    }
+                    debug.add_triangle(first, first + 1, first + 2);
+                }
+            }
+        }
+        debug
+    }
+}
+
+/// The constants a mesh is drawn with. Vulkan passes them as push constants and wgpu as a uniform, so both shaders share the layout.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct DrawConstants {
+    /// The rect fragments are clipped to as min x, min y, max x and max y in window coordinates
+    pub clip_rect: [f32; 4],
+    /// The size of the viewport in physical pixels. Meshes stay in window coordinates and the vertex shader converts them to clip space, so they don't need to be tessellated again when the window is resized
+    pub screen_size: [f32; 2],
+    pub translation: [f32; 2],
+    /// The columns of the linear part of the transform
+    pub matrix: [f32; 4],
+    /// 1 if the target stores colors as they are written, so the fragment shader encodes its linear colors as sRGB itself. Targets with an sRGB format encode them when they are written. The wgpu backend always draws into sRGB views and leaves it out of its shaders
+    pub encode_srgb: u32,
+    /// The value linear colors are multiplied with, so CSS white is as bright as SDR white on an HDR surface, see [`DynamicRange`]. Only the wgpu backend draws to HDR surfaces, so the Vulkan shaders leave it out
+    pub white_level: f32,
+    _padding: [u32; 2],
+}
+
+impl DrawConstants {
+    pub fn new(
+        screen_size: [f32; 2],
+        transform: Affine,
+        clip: epaint::Rect,
+        encode_srgb: bool,
+        white_level: f32,
+    ) -> Self {
+        let [a, b, c, d, e, f] = transform.as_coeffs().map(|coeff| coeff as f32);
+        // Clamp the clip rect so an unbounded clip stays finite on the GPU
+        let clip = clip.intersect(epaint::Rect::from_min_size(
+            epaint::Pos2::ZERO,
+            screen_size.into(),
+        ));
+        Self {
+            clip_rect: [clip.min.x, clip.min.y, clip.max.x, clip.max.y],
+            screen_size,
+            translation: [e, f],
+            matrix: [a, b, c, d],
+            encode_srgb: encode_srgb as u32,
+            white_level,
+            _padding: [0; 2],
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use std::sync::{Arc, Mutex};
+
+use epaint::{vec2, Color32, Rect, Rounding, Shape};
+use taffy::prelude::{AvailableSpace, Size};
+
+use crate::plugin::{CustomElement, ElementPlugin, Painter};
+use crate::qr::{ErrorCorrection, QrCode};
+
+/// The light modules around a QR code, which readers need to find its edges
+const QR_QUIET_ZONE: usize = 4;
+/// The light modules before and after a Code 128 barcode
+const CODE_128_QUIET_ZONE: usize = 10;
+/// The height of a barcode without a height in its styles
+const BARCODE_HEIGHT: f32 = 64.0;
+
+/// The widths of the bars and spaces of the symbols of Code 128, by value, starting with a bar. The last symbol is the stop pattern, which has a second bar.
+#[rustfmt::skip]
+const CODE_128_PATTERNS: [&[u8]; 107] = [
+    b"212222", b"222122", b"222221", b"121223", b"121322", b"131222", b"122213", b"122312", b"132212", b"221213",
+    b"221312", b"231212", b"112232", b"122132", b"122231", b"113222", b"123122", b"123221", b"223211", b"221132",
+    b"221231", b"213212", b"223112", b"312131", b"311222", b"321122", b"321221", b"312212", b"322112", b"322211",
+    b"212123", b"212321", b"232121", b"111323", b"131123", b"131321", b"112313", b"132113", b"132311", b"211313",
+    b"231113", b"231311", b"112133", b"112331", b"132131", b"113123", b"113321", b"133121", b"313121", b"211331",
+    b"231131", b"213113", b"213311", b"213131", b"311123", b"311321", b"331121", b"312113", b"312311", b"332111",
+    b"314111", b"221411", b"431111", b"111224", b"111422", b"121124", b"121421", b"141122", b"141221", b"112214",
+    b"112412", b"122114", b"122411", b"142112", b"142211", b"241211", b"221114", b"413111", b"241112", b"134111",
+    b"111242", b"121142", b"121241", b"114212", b"124112", b"124211", b"411212", b"421112", b"421211", b"212141",
+    b"214121", b"412121", b"111143", b"111341", b"131141", b"114113", b"114311", b"411113", b"411311", b"113141",
+    b"114131", b"311141", b"411131", b"211412", b"211214", b"211232", b"2331112",
+];
+const CODE_B: u8 = 100;
+const CODE_C: u8 = 99;
+const START_B: u8 = 104;
+const START_C: u8 = 105;
+const STOP: u8 = 106;
+
+/// The kind of code a [`Barcode`] draws.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BarcodeFormat {
+    /// A QR code of the bytes of the text, at a level of error correction
+    Qr(ErrorCorrection),
+    /// A linear Code 128 barcode, for printable ASCII text. Runs of digits are packed two to a symbol.
+    Code128,
+}
+
+/// An element that draws the text of its `data` attribute as a QR code or barcode, like the code a phone scans to pair with a desktop app. Register a clone with [`crate::Config::with_element_plugin`]:
+///
+/// ```no_run
+/// # use blitz_core::{Barcode, BarcodeFormat, Config, ErrorCorrection};
+/// Config::new().with_element_plugin(Barcode::new(
+///     "pairing-code",
+///     BarcodeFormat::Qr(ErrorCorrection::Medium),
+/// ));
+/// ```
+///
+/// and put `<pairing-code data="https://example.com/pair?token=..."/>` in the document. The code is drawn as rects instead of an image, so it stays sharp at any scale, and the modules are sized to whole pixels on screen when they are at least a pixel wide. It is centered in the content box with the quiet zone readers need around it. A QR code is square, and a barcode spans the height of the content box.
+///
+/// Changes to `data` are drawn with the next change to the element. Text that doesn't fit the format draws nothing.
+#[derive(Clone)]
+pub struct Barcode {
+    tag: Arc<str>,
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    format: BarcodeFormat,
+    dark: Color32,
+    light: Color32,
+    /// The size of a module when the styles don't size the element
+    module_size: f32,
+    /// The last text that was encoded and its modules, so the code is only made again when `data` changes
+    encoded: Option<(String, Option<Modules>)>,
+}
+
+/// The modules of a code with its quiet zone, row by row. A barcode is one row that is stretched to the height of the element.
+struct Modules {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+}
+
+impl Inner {
+    fn modules(&mut self, data: &str) -> Option<&Modules> {
+        if self.encoded.as_ref().map(|(text, _)| text.as_str()) != Some(data) {
+            let modules = match self.format {
+                BarcodeFormat::Qr(level) => QrCode::encode(data.as_bytes(), level).map(|code| {
+                    let width = code.size + 2 * QR_QUIET_ZONE;
+                    let mut dark = vec![false; width * width];
+                    for y in 0..code.size {
+                        for x in 0..code.size {
+                            dark[(y + QR_QUIET_ZONE) * width + x + QR_QUIET_ZONE] = code.get(x, y);
+                        }
+                    }
+                    Modules {
+                        width,
+                        height: width,
+                        dark,
+                    }
+                }),
+                BarcodeFormat::Code128 => code_128(data).map(|bars| Modules {
+                    width: bars.len(),
+                    height: 1,
+                    dark: bars,
+                }),
+            };
+            if modules.is_none() && !data.is_empty() {
+                log::warn!("{data:?} can't be encoded as {:?}", self.format);
+            }
+            self.encoded = Some((data.to_string(), modules));
+        }
+        self.encoded.as_ref()?.1.as_ref()
+    }
+}
+
+impl Barcode {
+    /// A plugin that draws the elements with a tag as codes of a format.
+    pub fn new(tag: &str, format: BarcodeFormat) -> Self {
+        Self {
+            tag: tag.into(),
+            inner: Arc::new(Mutex::new(Inner {
+                format,
+                dark: Color32::BLACK,
+                light: Color32::WHITE,
+                module_size: match format {
+                    BarcodeFormat::Qr(_) => 4.0,
+                    BarcodeFormat::Code128 => 2.0,
+                },
+                encoded: None,
+            })),
+        }
+    }
+
+    /// Set the colors of the dark and the light modules. Codes are drawn black on white by default, and most readers need the dark modules to be darker.
+    pub fn with_colors(self, dark: Color32, light: Color32) -> Self {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.dark = dark;
+            inner.light = light;
+        }
+        self
+    }
+
+    /// Set the size of a module for elements without a size in their styles. Modules are 4 pixels for QR codes and 2 pixels for barcodes by default.
+    pub fn with_module_size(self, module_size: f32) -> Self {
+        self.inner.lock().unwrap().module_size = module_size.max(1.0);
+        self
+    }
+}
+
+impl ElementPlugin for Barcode {
+    fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    fn measure(
+        &self,
+        element: &CustomElement,
+        known_dimensions: Size<Option<f32>>,
+        _available_space: Size<AvailableSpace>,
+    ) -> Size<f32> {
+        let mut inner = self.inner.lock().unwrap();
+        let module_size = inner.module_size;
+        let square = matches!(inner.format, BarcodeFormat::Qr(_));
+        let width = inner
+            .modules(element.attribute("data").unwrap_or_default())
+            .map_or(0.0, |modules| modules.width as f32 * module_size);
+        match (known_dimensions.width, known_dimensions.height) {
+            (Some(width), Some(height)) => Size { width, height },
+            // A QR code keeps its side square to the size the styles give it
+            (Some(width), None) if square => Size {
+                width,
+                height: width,
+            },
+            (None, Some(height)) if square => Size {
+                width: height,
+                height,
+            },
+            (Some(width), None) => Size {
+                width,
+                height: BARCODE_HEIGHT,
+            },
+            (None, Some(height)) => Size { width, height },
+            (None, None) => Size {
+                width,
+                height: if square { width } else { BARCODE_HEIGHT },
+            },
+        }
+    }
+
+    fn paint(&self, element: &CustomElement, content_box: Rect, painter: &mut Painter) {
+        let Some(scale) = painter.scale() else {
+            return;
+        };
+        let mut inner = self.inner.lock().unwrap();
+        let (dark, light) = (inner.dark, inner.light);
+        let Some(modules) = inner.modules(element.attribute("data").unwrap_or_default()) else {
+            return;
+        };
+        let square = modules.height > 1;
+        let fit = if square {
+            content_box.width().min(content_box.height())
+        } else {
+            content_box.width()
+        };
+        // Whole pixels on screen keep every module the same width, unless the modules would be smaller than a pixel
+        let mut module = fit / modules.width as f32;
+        if module * scale >= 1.0 {
+            module = (module * scale).floor() / scale;
+        }
+        let size = vec2(
+            module * modules.width as f32,
+            if square {
+                module * modules.height as f32
+            } else {
+                content_box.height()
+            },
+        );
+        let offset = ((content_box.size() - size) / 2.0 * scale).round() / scale;
+        let bounds = Rect::from_min_size(content_box.min + offset, size);
+        let row_height = size.y / modules.height as f32;
+
+        painter.add(Shape::rect_filled(bounds, Rounding::none(), light));
+        // Neighbouring dark modules of a row are drawn as one rect, so there are no seams between them
+        let mut rects = Vec::new();
+        for (row, cells) in modules.dark.chunks(modules.width).enumerate() {
+            let mut column = 0;
+            while column < cells.len() {
+                let count = cells[column..]
+                    .iter()
+                    .take_while(|dark| **dark == cells[column])
+                    .count();
+                if cells[column] {
+                    rects.push(Rect::from_min_size(
+                        bounds.min + vec2(column as f32 * module, row as f32 * row_height),
+                        vec2(count as f32 * module, row_height),
+                    ));
+                }
+                column += count;
+            }
+        }
+        painter.clipped(content_box, |painter| painter.bars(&rects, dark));
+    }
+}
+
+/// The modules of a Code 128 barcode of printable ASCII text with its quiet zone, or `None` if the text is empty or has other characters.
+fn code_128(text: &str) -> Option<Vec<bool>> {
+    let mut modules = vec![false; CODE_128_QUIET_ZONE];
+    for value in code_128_values(text)? {
+        for (index, width) in CODE_128_PATTERNS[value as usize].iter().enumerate() {
+            let dark = index % 2 == 0;
+            modules.extend(std::iter::repeat(dark).take((width - b'0') as usize));
+        }
+    }
+    modules.extend(std::iter::repeat(false).take(CODE_128_QUIET_ZONE));
+    Some(modules)
+}
+
+/// The symbols of a Code 128 barcode from the start symbol to the stop symbol
+fn code_128_values(text: &str) -> Option<Vec<u8>> {
+    let bytes = text.as_bytes();
+    if bytes.is_empty() || !bytes.iter().all(|byte| (32..=127).contains(byte)) {
+        return None;
+    }
+    let digits_at = |index: usize| {
+        bytes[index..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+    // Runs of digits are packed in code set C when that is shorter than switching to it and back
+    let use_c = |index: usize| {
+        let digits = digits_at(index);
+        let at_edge = index == 0 || index + digits == bytes.len();
+        digits >= if at_edge { 4 } else { 6 } || (digits == 2 && bytes.len() == 2)
+    };
+
+    let mut values = Vec::new();
+    let mut in_c = use_c(0);
+    values.push(if in_c { START_C } else { START_B });
+    let mut index = 0;
+    while index < bytes.len() {
+        if !in_c && use_c(index) {
+            // An odd run keeps its first digit in code set B so the rest pair up
+            if digits_at(index) % 2 == 1 {
+                values.push(bytes[index] - 32);
+                index += 1;
+            }
+            values.push(CODE_C);
+            in_c = true;
+        }
+        if in_c {
+            if digits_at(index) >= 2 {
+                values.push((bytes[index] - b'0') * 10 + bytes[index + 1] - b'0');
+                index += 2;
+                continue;
+            }
+            values.push(CODE_B);
+            in_c = false;
+        }
+        values.push(bytes[index] - 32);
+        index += 1;
+    }
+    let checksum = values
+        .iter()
+        .enumerate()
+        .map(|(position, value)| position.max(1) * *value as usize)
+        .sum::<usize>()
+        % 103;
+    values.push(checksum as u8);
+    values.push(STOP);
+    Some(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_128_code_sets() {
+        assert_eq!(
+            code_128_values("Wikipedia").unwrap(),
+            [104, 55, 73, 75, 73, 80, 69, 68, 73, 65, 88, 106]
+        );
+        assert_eq!(code_128_values("1234").unwrap(), [105, 12, 34, 82, 106]);
+        assert_eq!(
+            code_128_values("AB123456").unwrap(),
+            [104, 33, 34, 99, 12, 34, 56, 26, 106]
+        );
+        // The odd digit stays in code set B
+        assert_eq!(
+            code_128_values("A12345").unwrap(),
+            [104, 33, 17, 99, 23, 45, 64, 106]
+        );
+    }
+
+    #[test]
+    fn code_128_modules() {
+        let modules = code_128("1234").unwrap();
+        // Every symbol is 11 modules wide and the stop symbol 13
+        assert_eq!(modules.len(), CODE_128_QUIET_ZONE * 2 + 4 * 11 + 13);
+        assert!(modules[..CODE_128_QUIET_ZONE].iter().all(|dark| !dark));
+        // Start C is 2 1 1 2 3 2
+        let start: Vec<bool> = "11010011100".bytes().map(|module| module == b'1').collect();
+        assert_eq!(
+            modules[CODE_128_QUIET_ZONE..CODE_128_QUIET_ZONE + 11],
+            start[..]
+        );
+        assert!(modules[modules.len() - CODE_128_QUIET_ZONE..]
+            .iter()
+            .all(|dark| !dark));
+    }
+
+    #[test]
+    fn code_128_rejects_text() {
+        assert_eq!(code_128(""), None);
+        assert_eq!(code_128("blitz ✓"), None);
+        assert_eq!(code_128("tab\t"), None);
+    }
+}
@@ -0,0 +1,64 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+// Blurs an area of an image along one direction with a gaussian, for backdrop-filter. Blurring the rows and then the columns blurs in both directions. The GLSL version of blur.wgsl
+
+layout(location = 0) out vec4 outColor;
+
+// The image that is blurred. It has the coordinates of the target, so the area is at the same texels in both
+layout(set = 0, binding = 0) uniform texture2D source;
+layout(set = 0, binding = 1) uniform sampler sourceSampler;
+
+layout(push_constant) uniform PushConstants {
+  // The step between two taps in texels
+  vec2 direction;
+  // The first texel of the area and the texel past its last one. Taps past its edges repeat the texels at the edges
+  vec2 area_min;
+  vec2 area_max;
+  // The standard deviation of the gaussian in texels
+  float sigma;
+  // See DrawConstants::encode_srgb
+  uint encode_srgb;
+} pushConstants;
+
+// 0-1 linear  from  0-1 sRGB
+vec3 linear_from_srgb(vec3 srgb) {
+    bvec3 cutoff = lessThan(srgb, vec3(0.04045));
+    vec3 lower = srgb / vec3(12.92);
+    vec3 higher = pow((srgb + vec3(0.055)) / vec3(1.055), vec3(2.4));
+    return mix(higher, lower, cutoff);
+}
+
+// 0-1 sRGB  from  0-1 linear
+vec3 srgb_from_linear(vec3 linear) {
+    bvec3 cutoff = lessThan(linear, vec3(0.0031308));
+    vec3 lower = linear * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(linear, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, cutoff);
+}
+
+void main() {
+  ivec2 min_texel = ivec2(pushConstants.area_min);
+  ivec2 max_texel = ivec2(pushConstants.area_max) - ivec2(1);
+  // Taps past three standard deviations are left out, like in raster.rs
+  int taps = clamp(int(ceil(pushConstants.sigma * 3.0)), 1, 128);
+  vec4 color = vec4(0.0);
+  float total = 0.0;
+  for (int i = -taps; i <= taps; i += 1) {
+    ivec2 texel = clamp(ivec2(floor(gl_FragCoord.xy + pushConstants.direction * float(i))), min_texel, max_texel);
+    vec4 tap = texelFetch(sampler2D(source, sourceSampler), texel, 0);
+    // Images of targets that don't encode colors hold them encoded by shader.frag, so they are blurred in linear space like on the other targets
+    if (pushConstants.encode_srgb != 0u && tap.a > 0.0) {
+      tap.rgb = linear_from_srgb(tap.rgb / tap.a) * tap.a;
+    }
+    float weight = exp(-float(i * i) / (2.0 * pushConstants.sigma * pushConstants.sigma));
+    color += tap * weight;
+    total += weight;
+  }
+  color /= total;
+  if (pushConstants.encode_srgb != 0u && color.a > 0.0) {
+    color.rgb = srgb_from_linear(color.rgb / color.a) * color.a;
+  }
+  outColor = color;
+}
@@ -0,0 +1,42 @@
+// Blurs an area of a texture along one direction with a gaussian, for backdrop-filter. Blurring the rows and then the columns blurs in both directions
+
+struct BlurConstants {
+    // The step between two taps in texels
+    direction: vec2<f32>,
+    // Where the area is drawn in the target
+    offset: vec2<f32>,
+    // The size of the area in texels. Taps past its edges repeat the texels at the edges
+    size: vec2<f32>,
+    // The standard deviation of the gaussian in texels
+    sigma: f32,
+    _padding: f32,
+};
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> constants: BlurConstants;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // A triangle that covers the whole target, the scissor rect limits it to the area
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let center = position.xy - constants.offset;
+    let max_texel = vec2<i32>(constants.size) - vec2<i32>(1);
+    // Taps past three standard deviations are left out, like in raster.rs
+    let taps = clamp(i32(ceil(constants.sigma * 3.0)), 1, 128);
+    var color = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var i = -taps; i <= taps; i = i + 1) {
+        let texel = clamp(vec2<i32>(floor(center + constants.direction * f32(i))), vec2<i32>(0), max_texel);
+        let weight = exp(-f32(i * i) / (2.0 * constants.sigma * constants.sigma));
+        color = color + textureLoad(source, texel, 0) * weight;
+        total = total + weight;
+    }
+    return color / total;
+}
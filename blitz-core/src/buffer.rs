@@ -0,0 +1,51 @@
+use beuk::ash::vk;
+use beuk::ctx::RenderContext;
+use beuk::memory::{BufferHandle, MemoryLocation};
+
+/// The smallest buffer that is allocated in bytes
+const MIN_CAPACITY: usize = 64 * 1024;
+
+/// A buffer on the GPU that is reused between frames. When the data outgrows it, it is reallocated with room to spare.
+pub(crate) struct GrowableBuffer {
+    name: &'static str,
+    usage: vk::BufferUsageFlags,
+    buffer: Option<BufferHandle>,
+    /// The size of the buffer in bytes
+    capacity: usize,
+}
+
+impl GrowableBuffer {
+    pub fn new(name: &'static str, usage: vk::BufferUsageFlags) -> Self {
+        Self {
+            name,
+            usage,
+            buffer: None,
+            capacity: 0,
+        }
+    }
+
+    /// Copy the data to the start of the buffer, growing the buffer first if the data doesn't fit.
+    pub fn write<T: bytemuck::Pod>(&mut self, ctx: &mut RenderContext, data: &[T]) -> BufferHandle {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        if self.buffer.is_none() || bytes.len() > self.capacity {
+            if let Some(buffer) = self.buffer.take() {
+                // The last frame may still be reading from the buffer
+                unsafe { ctx.device.device_wait_idle().unwrap() };
+                ctx.buffer_manager.remove_buffer(buffer);
+            }
+            // Double the size so a growing document doesn't reallocate every frame
+            self.capacity = bytes.len().next_power_of_two().max(MIN_CAPACITY);
+            self.buffer = Some(ctx.buffer_manager.create_buffer(
+                self.name,
+                self.capacity as u64,
+                self.usage,
+                MemoryLocation::CpuToGpu,
+            ));
+        }
+        let buffer = self.buffer.unwrap();
+        ctx.buffer_manager
+            .get_buffer_mut(buffer)
+            .copy_from_slice(bytes, 0);
+        buffer
+    }
+}
@@ -0,0 +1,157 @@
+use epaint::{Color32, Mesh, Pos2, Rect, Vec2};
+
+/// Miters longer than this many half widths are cut short, like the default miter limit of SVG
+const MITER_LIMIT: f32 = 4.0;
+
+/// Values for the ticks of an axis from `min` to `max`, about `count` of them, at round steps of 1, 2 or 5 times a power of ten. The ticks are multiples of the step inside of the range, so labels like 0, 25, 50 and 75 line up with the grid of the chart.
+///
+/// Returns no ticks if the range is empty or not finite.
+pub fn axis_ticks(min: f64, max: f64, count: usize) -> Vec<f64> {
+    let (min, max) = (min.min(max), min.max(max));
+    if !min.is_finite() || !max.is_finite() || min == max || count == 0 {
+        return Vec::new();
+    }
+    let rough = (max - min) / count as f64;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap_or(10.0 * magnitude);
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last)
+        // Multiplying instead of adding keeps the error of each tick from growing, so zero is exactly zero
+        .map(|index| index as f64 * step)
+        .collect()
+}
+
+/// The runs of points of a line between gaps, which are points that aren't finite. Points as close to the last point of their run as `min_distance` are skipped, since they don't change how the line looks but cost triangles.
+fn runs(points: &[Pos2], min_distance: f32) -> Vec<Vec<Pos2>> {
+    let mut runs: Vec<Vec<Pos2>> = vec![Vec::new()];
+    for (index, point) in points.iter().enumerate() {
+        if !point.is_finite() {
+            if !runs.last().unwrap().is_empty() {
+                runs.push(Vec::new());
+            }
+            continue;
+        }
+        let run = runs.last_mut().unwrap();
+        let is_last = points.get(index + 1).map_or(true, |next| !next.is_finite());
+        match run.last() {
+            Some(last) if last.distance(*point) <= min_distance => {
+                // The last point of a run is kept so the line ends where it should, in place of the point before it
+                if is_last && run.len() > 1 {
+                    *run.last_mut().unwrap() = *point;
+                }
+            }
+            _ => run.push(*point),
+        }
+    }
+    runs.retain(|run| !run.is_empty());
+    runs
+}
+
+/// Tessellate a line through points into a triangle strip with mitered joins and butt ends. Points that aren't finite leave a gap in the line, like missing values in a series.
+///
+/// Each point adds two vertices and two triangles, so a line through thousands of points stays one small mesh. `min_distance` is the shortest segment that is kept.
+pub(crate) fn polyline_mesh(
+    points: &[Pos2],
+    width: f32,
+    color: Color32,
+    min_distance: f32,
+) -> Mesh {
+    let mut mesh = Mesh::default();
+    if width <= 0.0 || color == Color32::TRANSPARENT {
+        return mesh;
+    }
+    let half_width = width / 2.0;
+    for run in runs(points, min_distance) {
+        if run.len() < 2 {
+            continue;
+        }
+        let normal = |from: Pos2, to: Pos2| (to - from).normalized().rot90();
+        let start = mesh.vertices.len() as u32;
+        for (index, point) in run.iter().enumerate() {
+            let before = index
+                .checked_sub(1)
+                .map(|before| normal(run[before], *point));
+            let after = run.get(index + 1).map(|after| normal(*point, *after));
+            let offset = match (before, after) {
+                // A line that turns back on itself has no miter
+                (Some(before), Some(after)) if (before + after).length_sq() < 1e-6 => {
+                    after * half_width
+                }
+                (Some(before), Some(after)) => {
+                    // The miter is along the average of the normals and as long as it has to be to keep the width of both segments
+                    let miter = (before + after).normalized();
+                    let cos = miter.dot(after);
+                    if cos * MITER_LIMIT > 1.0 {
+                        miter * (half_width / cos)
+                    } else {
+                        miter * half_width
+                    }
+                }
+                (Some(normal), None) | (None, Some(normal)) => normal * half_width,
+                (None, None) => Vec2::ZERO,
+            };
+            mesh.colored_vertex(*point + offset, color);
+            mesh.colored_vertex(*point - offset, color);
+        }
+        for index in 0..run.len() as u32 - 1 {
+            let i = start + index * 2;
+            mesh.add_triangle(i, i + 1, i + 2);
+            mesh.add_triangle(i + 1, i + 3, i + 2);
+        }
+    }
+    mesh
+}
+
+/// Tessellate the area between a line through points and a horizontal baseline, like the fill under a series. Where the line crosses the baseline, the area is split at the crossing so both sides are filled. Points that aren't finite leave a gap.
+pub(crate) fn area_mesh(points: &[Pos2], baseline: f32, color: Color32, min_distance: f32) -> Mesh {
+    let mut mesh = Mesh::default();
+    if color == Color32::TRANSPARENT {
+        return mesh;
+    }
+    for run in runs(points, min_distance) {
+        let mut edge = Vec::with_capacity(run.len());
+        for (index, point) in run.iter().enumerate() {
+            if let Some(before) = index.checked_sub(1).map(|before| run[before]) {
+                let (a, b) = (before.y - baseline, point.y - baseline);
+                if a * b < 0.0 {
+                    let t = a / (a - b);
+                    edge.push(Pos2::new(before.x + (point.x - before.x) * t, baseline));
+                }
+            }
+            edge.push(*point);
+        }
+        if edge.len() < 2 {
+            continue;
+        }
+        let start = mesh.vertices.len() as u32;
+        for point in &edge {
+            mesh.colored_vertex(*point, color);
+            mesh.colored_vertex(Pos2::new(point.x, baseline), color);
+        }
+        for index in 0..edge.len() as u32 - 1 {
+            let i = start + index * 2;
+            mesh.add_triangle(i, i + 1, i + 2);
+            mesh.add_triangle(i + 1, i + 3, i + 2);
+        }
+    }
+    mesh
+}
+
+/// Tessellate rects into one mesh, like the bars of a bar chart.
+pub(crate) fn bars_mesh(bars: &[Rect], color: Color32) -> Mesh {
+    let mut mesh = Mesh::default();
+    if color == Color32::TRANSPARENT {
+        return mesh;
+    }
+    for bar in bars {
+        if bar.is_finite() && bar.is_positive() {
+            mesh.add_colored_rect(*bar, color);
+        }
+    }
+    mesh
+}
@@ -0,0 +1,211 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::io;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core::FxHashMap;
+
+/// The system clipboard.
+///
+/// Copied content is offered in several formats so it keeps its formatting when it is pasted into an application that understands HTML, and falls back to plain text elsewhere. On Linux the content is only available while the clipboard is alive, so keep it around instead of creating one for every copy.
+pub struct Clipboard(arboard::Clipboard);
+
+impl Clipboard {
+    pub fn new() -> io::Result<Self> {
+        arboard::Clipboard::new()
+            .map(Self)
+            .map_err(io::Error::other)
+    }
+
+    pub fn copy_text(&mut self, text: &str) -> io::Result<()> {
+        self.0.set_text(text).map_err(io::Error::other)
+    }
+
+    /// Copy HTML together with a plain text version of it for applications that don't accept HTML.
+    pub fn copy_html(&mut self, html: &str, text: &str) -> io::Result<()> {
+        self.0.set_html(html, Some(text)).map_err(io::Error::other)
+    }
+
+    /// Copy an element and its children as HTML and as plain text.
+    pub fn copy_element(&mut self, rdom: &RealDom, element: NodeId) -> io::Result<()> {
+        let node = rdom
+            .get(element)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the element doesn't exist"))?;
+        let mut html = String::new();
+        let mut text = String::new();
+        write_html(node, &mut html);
+        write_text(node, &mut text);
+        self.copy_html(&html, text.trim_end())
+    }
+
+    /// Copy an image, for example one rendered with [`crate::Document::render_element_to_image`].
+    pub fn copy_image(&mut self, image: &image::RgbaImage) -> io::Result<()> {
+        self.0
+            .set_image(arboard::ImageData {
+                width: image.width() as usize,
+                height: image.height() as usize,
+                bytes: Cow::Borrowed(image.as_raw()),
+            })
+            .map_err(io::Error::other)
+    }
+
+    pub fn paste_text(&mut self) -> io::Result<String> {
+        self.0.get_text().map_err(io::Error::other)
+    }
+
+    /// Put text in the primary selection, which X11 and Wayland paste with a middle click. Set it when the user selects text. Other platforms don't have a primary selection, so this does nothing there.
+    pub fn copy_primary_text(&mut self, text: &str) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            use arboard::{LinuxClipboardKind, SetExtLinux};
+            self.0
+                .set()
+                .clipboard(LinuxClipboardKind::Primary)
+                .text(text)
+                .map_err(io::Error::other)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = text;
+            Ok(())
+        }
+    }
+
+    /// The text in the primary selection. Returns `None` on platforms without a primary selection.
+    pub fn paste_primary_text(&mut self) -> io::Result<Option<String>> {
+        #[cfg(target_os = "linux")]
+        {
+            use arboard::{GetExtLinux, LinuxClipboardKind};
+            self.0
+                .get()
+                .clipboard(LinuxClipboardKind::Primary)
+                .text()
+                .map(Some)
+                .map_err(io::Error::other)
+        }
+        #[cfg(not(target_os = "linux"))]
+        Ok(None)
+    }
+}
+
+/// Elements that are void in HTML and can't have children or a closing tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements that start on a new line when they are copied as plain text
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+
+fn write_html(node: NodeRef, html: &mut String) {
+    match &*node.node_type() {
+        NodeType::Element(element) => {
+            html.push('<');
+            html.push_str(&element.tag);
+            for (attribute, value) in &element.attributes {
+                // Namespaced attributes (like styles set by Dioxus) aren't attributes in HTML
+                if attribute.namespace.is_some() {
+                    continue;
+                }
+                let value = match value {
+                    OwnedAttributeValue::Text(text) => Cow::Borrowed(text.as_str()),
+                    OwnedAttributeValue::Float(float) => Cow::Owned(float.to_string()),
+                    OwnedAttributeValue::Int(int) => Cow::Owned(int.to_string()),
+                    OwnedAttributeValue::Bool(bool) => Cow::Owned(bool.to_string()),
+                    _ => continue,
+                };
+                let _ = write!(html, " {}=\"{}\"", attribute.name, escape(&value, true));
+            }
+            let style = inline_style(&element.attributes);
+            if !style.is_empty() {
+                let _ = write!(html, " style=\"{}\"", escape(&style, true));
+            }
+            html.push('>');
+            if VOID_ELEMENTS.contains(&element.tag.as_str()) {
+                return;
+            }
+            for child in node.children() {
+                write_html(child, html);
+            }
+            let _ = write!(html, "</{}>", element.tag);
+        }
+        NodeType::Text(text) => html.push_str(&escape(&text.text, false)),
+        NodeType::Placeholder => {}
+    }
+}
+
+/// Dioxus sets styles as attributes in the style namespace, which are written back as a style attribute so the formatting survives pasting
+fn inline_style(attributes: &FxHashMap<OwnedAttributeDiscription, OwnedAttributeValue>) -> String {
+    attributes
+        .iter()
+        .filter(|(attribute, _)| attribute.namespace.as_deref() == Some("style"))
+        .filter_map(|(attribute, value)| Some(format!("{}:{};", attribute.name, value.as_text()?)))
+        .collect()
+}
+
+fn write_text(node: NodeRef, text: &mut String) {
+    match &*node.node_type() {
+        NodeType::Element(element) => {
+            let block = BLOCK_ELEMENTS.contains(&element.tag.as_str());
+            if block && !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            for child in node.children() {
+                write_text(child, text);
+            }
+            if block && !text.ends_with('\n') {
+                text.push('\n');
+            }
+        }
+        NodeType::Text(node) => text.push_str(&node.text),
+        NodeType::Placeholder => {}
+    }
+}
+
+fn escape(text: &str, attribute: bool) -> Cow<str> {
+    if !text.contains(['&', '<', '>', '"']) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
@@ -0,0 +1,48 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) out vec4 outColor;
+
+// The layer a filtered or translucent element was drawn into. It has the size of the target
+layout(set = 0, binding = 0) uniform texture2D layer;
+layout(set = 0, binding = 1) uniform sampler layerSampler;
+
+layout(push_constant) uniform PushConstants {
+  // Applied to premultiplied linear colors, with the alpha column adding a constant
+  mat4 color_matrix;
+  float opacity;
+  uint encode_srgb;
+} pushConstants;
+
+// 0-1 linear  from  0-1 sRGB
+vec3 linear_from_srgb(vec3 srgb) {
+    bvec3 cutoff = lessThan(srgb, vec3(0.04045));
+    vec3 lower = srgb / vec3(12.92);
+    vec3 higher = pow((srgb + vec3(0.055)) / vec3(1.055), vec3(2.4));
+    return mix(higher, lower, cutoff);
+}
+
+// 0-1 sRGB  from  0-1 linear
+vec3 srgb_from_linear(vec3 linear) {
+    bvec3 cutoff = lessThan(linear, vec3(0.0031308));
+    vec3 lower = linear * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(linear, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, cutoff);
+}
+
+void main() {
+  vec4 texel = texelFetch(sampler2D(layer, layerSampler), ivec2(gl_FragCoord.xy), 0);
+  // Layers of targets that don't encode colors hold them encoded by shader.frag
+  if (pushConstants.encode_srgb != 0u && texel.a > 0.0) {
+    texel.rgb = linear_from_srgb(texel.rgb / texel.a) * texel.a;
+  }
+  vec4 color = clamp(pushConstants.color_matrix * texel, vec4(0.0), vec4(1.0));
+  // Premultiplied colors can't be brighter than their alpha
+  color.rgb = min(color.rgb, vec3(color.a));
+  // Encoded again like the output of shader.frag
+  if (pushConstants.encode_srgb != 0u && color.a > 0.0) {
+    color.rgb = srgb_from_linear(color.rgb / color.a) * color.a;
+  }
+  outColor = color * pushConstants.opacity;
+}
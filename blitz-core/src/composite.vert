@@ -0,0 +1,9 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+// Draws a triangle that covers the whole target, so composite.frag and blur.frag run once for each of its pixels
+void main() {
+  vec2 corner = vec2(float((gl_VertexIndex << 1) & 2), float(gl_VertexIndex & 2));
+  gl_Position = vec4(corner * 2.0 - 1.0, 0.0, 1.0);
+}
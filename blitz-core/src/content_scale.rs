@@ -0,0 +1,46 @@
+use peniko::kurbo::Affine;
+use tao::dpi::PhysicalSize;
+
+/// How a document with a fixed design size is fitted into a window with another size, see [`crate::Config::with_design_size`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ContentFit {
+    /// Scale the document until it touches two edges of the window and center it. The bars left over on the other sides are filled with the clear color.
+    #[default]
+    Letterbox,
+    /// Like [`ContentFit::Letterbox`], but only scale by whole numbers so pixel art stays sharp. The document is drawn at its design size if the window is smaller.
+    IntegerLetterbox,
+    /// Scale the document until it covers the whole window and center it. The parts that don't fit are cut off.
+    Crop,
+    /// Stretch the document to the size of the window, which distorts it if the aspect ratios differ.
+    Stretch,
+}
+
+/// The fixed size a document is laid out at, which is scaled to fit the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct DesignSize {
+    pub size: PhysicalSize<u32>,
+    pub fit: ContentFit,
+}
+
+impl DesignSize {
+    /// The transform that maps document coordinates to the window.
+    pub fn transform(&self, window_size: PhysicalSize<u32>) -> Affine {
+        let design = (self.size.width as f64, self.size.height as f64);
+        let window = (window_size.width as f64, window_size.height as f64);
+        let scale_x = window.0 / design.0;
+        let scale_y = window.1 / design.1;
+        let (scale_x, scale_y) = match self.fit {
+            ContentFit::Letterbox => (scale_x.min(scale_y), scale_x.min(scale_y)),
+            ContentFit::IntegerLetterbox => {
+                let scale = scale_x.min(scale_y).floor().max(1.0);
+                (scale, scale)
+            }
+            ContentFit::Crop => (scale_x.max(scale_y), scale_x.max(scale_y)),
+            ContentFit::Stretch => (scale_x, scale_y),
+        };
+        // Whole pixel offsets keep the edges of the document sharp
+        let offset_x = ((window.0 - design.0 * scale_x) / 2.0).round();
+        let offset_y = ((window.1 - design.1 * scale_y) / 2.0).round();
+        Affine::translate((offset_x, offset_y)) * Affine::scale_non_uniform(scale_x, scale_y)
+    }
+}
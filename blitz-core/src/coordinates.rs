@@ -0,0 +1,152 @@
+use dioxus_html::geometry::{ClientPoint, ElementPoint, ScreenPoint};
+use dioxus_native_core::prelude::*;
+use peniko::kurbo::{Affine, Point, Vec2};
+use shipyard::Component;
+use taffy::{prelude::Size, Taffy};
+use tao::dpi::{LogicalPosition, PhysicalPosition};
+use tao::window::Window;
+
+use crate::layout::TaffyLayout;
+use crate::position::is_fixed;
+use crate::scroll::{scroll_effect, scroll_offset};
+use crate::style::Transform;
+
+/// Where the window is on the screen and how its pixels are scaled.
+///
+/// The document is laid out in the physical pixels of the window, so client coordinates (like the ones in mouse events) are physical. If the document has a fixed design size (see [`crate::Config::with_design_size`]), client coordinates are in the pixels of the document before it is scaled to the window. The geometry is stored on the root of the RealDom and kept up to date when the window moves or its scale factor changes. Read it with [`WindowGeometry::of`].
+#[derive(Clone, Copy, Debug, PartialEq, Component)]
+pub struct WindowGeometry {
+    /// The top left corner of the client area of the window on the screen in physical pixels
+    pub position: PhysicalPosition<f64>,
+    pub scale_factor: f64,
+    /// Maps client coordinates to physical pixels in the window. It is the identity unless the document is scaled to fit the window or moved inside of the shadow of its chrome, see [`crate::Config::with_undecorated`]
+    pub content_transform: Affine,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            position: PhysicalPosition::new(0.0, 0.0),
+            scale_factor: 1.0,
+            content_transform: Affine::IDENTITY,
+        }
+    }
+}
+
+impl WindowGeometry {
+    pub(crate) fn from_window(window: &Window) -> Self {
+        Self {
+            // Some platforms (like Wayland) don't let windows know where they are
+            position: window
+                .inner_position()
+                .map(|position| position.cast())
+                .unwrap_or(PhysicalPosition::new(0.0, 0.0)),
+            scale_factor: window.scale_factor(),
+            content_transform: Affine::IDENTITY,
+        }
+    }
+
+    /// The geometry of the window the document is shown in. A document that isn't shown in a window has the default geometry.
+    pub fn of(rdom: &RealDom) -> Self {
+        rdom.get(rdom.root_id())
+            .and_then(|root| root.get::<WindowGeometry>().map(|geometry| *geometry))
+            .unwrap_or_default()
+    }
+
+    pub fn screen_to_client(&self, point: ScreenPoint) -> ClientPoint {
+        self.window_to_client(PhysicalPosition::new(
+            point.x - self.position.x,
+            point.y - self.position.y,
+        ))
+    }
+
+    pub fn client_to_screen(&self, point: ClientPoint) -> ScreenPoint {
+        let point = self.client_to_window(point);
+        ScreenPoint::new(point.x + self.position.x, point.y + self.position.y)
+    }
+
+    /// Convert a position in the window in physical pixels, like the position of the cursor, into client coordinates.
+    pub fn window_to_client(&self, point: PhysicalPosition<f64>) -> ClientPoint {
+        let point = self.content_transform.inverse() * Point::new(point.x, point.y);
+        ClientPoint::new(point.x, point.y)
+    }
+
+    pub fn client_to_window(&self, point: ClientPoint) -> PhysicalPosition<f64> {
+        let point = self.content_transform * Point::new(point.x, point.y);
+        PhysicalPosition::new(point.x, point.y)
+    }
+
+    /// Convert client coordinates into logical pixels of the window, which are independent of the scale factor of the monitor.
+    pub fn client_to_logical(&self, point: ClientPoint) -> LogicalPosition<f64> {
+        self.client_to_window(point).to_logical(self.scale_factor)
+    }
+
+    pub fn logical_to_client(&self, point: LogicalPosition<f64>) -> ClientPoint {
+        self.window_to_client(point.to_physical(self.scale_factor))
+    }
+}
+
+/// The transform that maps coordinates relative to the top left corner of the border box of an element to client coordinates. It includes the transforms of the element and all of its ancestors.
+///
+/// Returns `None` if the element doesn't exist or hasn't been laid out yet.
+pub fn element_transform(rdom: &RealDom, taffy: &Taffy, element: NodeId) -> Option<Affine> {
+    let mut ancestors = vec![rdom.get(element)?];
+    // Fixed elements are laid out in the root, so the ancestors above them don't move them
+    while let Some(parent) = ancestors.last().unwrap().parent() {
+        if is_fixed(*ancestors.last().unwrap()) {
+            break;
+        }
+        ancestors.push(parent);
+    }
+    // The root fills the viewport
+    let root_layout = taffy
+        .layout(rdom.get(rdom.root_id())?.get::<TaffyLayout>()?.node?)
+        .ok()?;
+    let viewport_size = Size {
+        width: root_layout.size.width as u32,
+        height: root_layout.size.height as u32,
+    };
+
+    // Locations and transforms add up from the root down, the same way the element is drawn
+    let mut location = Point::ZERO;
+    let mut transform = Affine::IDENTITY;
+    // Children are moved by the scroll offset of their parent
+    let mut scrolled = Vec2::ZERO;
+    for node in ancestors.into_iter().rev() {
+        let layout = taffy.layout(node.get::<TaffyLayout>()?.node?).ok()?;
+        location += Vec2::new(layout.location.x as f64, layout.location.y as f64) - scrolled;
+        transform = transform
+            * node
+                .get::<Transform>()?
+                .resolve(location, &layout.size, &viewport_size)
+            * scroll_effect(taffy, node, location).0;
+        scrolled = scroll_offset(node);
+    }
+    Some(transform * Affine::translate(location.to_vec2()))
+}
+
+/// Convert a point relative to the border box of an element into client coordinates.
+pub fn element_to_client(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    element: NodeId,
+    point: ElementPoint,
+) -> Option<ClientPoint> {
+    let point = element_transform(rdom, taffy, element)? * Point::new(point.x, point.y);
+    Some(ClientPoint::new(point.x, point.y))
+}
+
+/// Convert client coordinates into a point relative to the border box of an element. Returns `None` if the element is scaled down to nothing.
+pub fn client_to_element(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    element: NodeId,
+    point: ClientPoint,
+) -> Option<ElementPoint> {
+    let transform = element_transform(rdom, taffy, element)?;
+    if transform.determinant() == 0.0 {
+        return None;
+    }
+    let point = transform.inverse() * Point::new(point.x, point.y);
+    Some(ElementPoint::new(point.x, point.y))
+}
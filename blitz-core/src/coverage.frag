@@ -0,0 +1,33 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec4 inColor;
+layout(location = 1) in vec2 inUV;
+
+layout(location = 0) out vec4 outColor;
+
+// Holds the subpixel glyphs of text, with the coverage of each color channel in its texels
+layout(set = 0, binding = 0) uniform texture2D atlas;
+layout(set = 0, binding = 1) uniform sampler atlasSampler;
+
+layout(push_constant) uniform PushConstants {
+  vec4 clip_rect;
+  vec2 screen_size;
+  vec2 translation;
+  // The columns of the linear part of the transform, see DrawConstants
+  vec4 matrix;
+  uint encode_srgb;
+} pushConstants;
+
+// The first pass of subpixel text, which darkens each color channel of what was drawn before by its coverage. The blend state multiplies the target by the inverse of the output, and subpixel.frag adds the color of the text after it
+void main() {
+  // Textures can only be sampled before the first discard, where control flow is still uniform
+  vec4 texel = texture(sampler2D(atlas, atlasSampler), inUV);
+  if (any(lessThan(gl_FragCoord.xy, pushConstants.clip_rect.xy)) ||
+      any(greaterThanEqual(gl_FragCoord.xy, pushConstants.clip_rect.zw))) {
+    discard;
+  }
+  // The coverage is a blend factor, so it isn't encoded on targets that don't encode colors
+  outColor = vec4(texel.rgb * inColor.a, texel.a * inColor.a);
+}
\ No newline at end of file
@@ -0,0 +1,406 @@
+use std::sync::{Arc, Mutex};
+
+use epaint::{pos2, vec2, Color32, Pos2, Rect, Rounding, Shape, Stroke, Vec2};
+use taffy::prelude::{AvailableSpace, Size};
+
+use crate::plugin::{CustomElement, ElementPlugin, Painter, PointerPhase};
+
+const FONT_SIZE: f32 = 13.0;
+/// The space between the text of a cell and its edges
+const CELL_PADDING: f32 = 8.0;
+/// How far from the right edge of a header the mouse grabs the edge to resize the column
+const RESIZE_HANDLE: f32 = 4.0;
+const HEADER_BACKGROUND: Color32 = Color32::from_rgb(0xf3, 0xf4, 0xf6);
+const STRIPE_BACKGROUND: Color32 = Color32::from_rgb(0xf9, 0xfa, 0xfb);
+const GRID_LINE: Color32 = Color32::from_rgb(0xe5, 0xe7, 0xeb);
+const TEXT_COLOR: Color32 = Color32::from_rgb(0x11, 0x18, 0x27);
+const HEADER_TEXT_COLOR: Color32 = Color32::from_rgb(0x37, 0x41, 0x51);
+
+/// The rows of a [`DataGrid`]. Only the cells of the rows that are visible are read when the grid is drawn, so the data can be large or computed on demand.
+pub trait GridData: Send + Sync + 'static {
+    fn row_count(&self) -> usize;
+
+    /// The text of a cell.
+    fn cell(&self, row: usize, column: usize) -> String;
+}
+
+impl GridData for Vec<Vec<String>> {
+    fn row_count(&self) -> usize {
+        self.len()
+    }
+
+    fn cell(&self, row: usize, column: usize) -> String {
+        self[row].get(column).cloned().unwrap_or_default()
+    }
+}
+
+/// A column of a [`DataGrid`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridColumn {
+    pub title: String,
+    /// The width in pixels, which the user changes by dragging the right edge of the header
+    pub width: f32,
+    /// The narrowest the column can be dragged
+    pub min_width: f32,
+    /// True if clicking the header sorts the rows by the column
+    pub sortable: bool,
+}
+
+impl GridColumn {
+    pub fn new(title: &str, width: f32) -> Self {
+        Self {
+            title: title.to_string(),
+            width,
+            min_width: 40.0,
+            sortable: true,
+        }
+    }
+
+    pub fn with_min_width(mut self, min_width: f32) -> Self {
+        self.min_width = min_width;
+        self
+    }
+
+    pub fn with_sortable(mut self, sortable: bool) -> Self {
+        self.sortable = sortable;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+type SortHook = Arc<dyn Fn(usize, SortDirection) + Send + Sync>;
+
+/// A table for large datasets, drawn by a plugin instead of elements: a header that stays at the top while the rows scroll, columns that are resized by dragging the edges of their headers, and headers that sort the rows when they are clicked.
+///
+/// Only the rows in view are drawn, as rects and lines of text that the backend draws in batches, so scrolling through 100,000 rows costs the same as scrolling through 100. Register a clone with [`crate::Config::with_element_plugin`] and put an element with its tag in the document, sized by its styles:
+///
+/// ```no_run
+/// # use blitz_core::{Config, DataGrid, GridColumn};
+/// let rows: Vec<Vec<String>> = (0..100_000)
+///     .map(|row| vec![row.to_string(), format!("Item {row}")])
+///     .collect();
+/// let columns = vec![GridColumn::new("Id", 80.0), GridColumn::new("Name", 240.0)];
+/// let grid = DataGrid::new("orders-grid", columns).with_data(rows);
+/// let sorted = grid.clone();
+/// grid.on_sort(move |column, direction| {
+///     // Sort the rows and pass them to sorted.set_data
+/// });
+/// Config::new().with_element_plugin(grid);
+/// ```
+///
+/// The grid is drawn again when it scrolls or the user resizes or sorts it. Data that changes otherwise is drawn with the next change to the element, so change one of its attributes, like a version number, after [`DataGrid::set_data`].
+#[derive(Clone)]
+pub struct DataGrid {
+    tag: Arc<str>,
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    columns: Vec<GridColumn>,
+    data: Arc<dyn GridData>,
+    row_height: f32,
+    header_height: f32,
+    sort: Option<(usize, SortDirection)>,
+    on_sort: Option<SortHook>,
+    /// How far the rows are scrolled, which the header follows horizontally
+    scroll: Vec2,
+    /// The size of the content box when the grid was drawn last, which limits scrolling
+    viewport: Vec2,
+    /// The column whose edge is dragged, with its width and the mouse when the drag started
+    resizing: Option<(usize, f32, f32)>,
+    /// The header the mouse was pressed on
+    pressed: Option<usize>,
+}
+
+impl Inner {
+    fn content_size(&self) -> Vec2 {
+        vec2(
+            self.columns.iter().map(|column| column.width).sum(),
+            self.header_height + self.data.row_count() as f32 * self.row_height,
+        )
+    }
+
+    fn clamp_scroll(&mut self) {
+        let max = (self.content_size() - self.viewport).max(Vec2::ZERO);
+        self.scroll = self.scroll.clamp(Vec2::ZERO, max);
+    }
+
+    /// The column under an x position in the content box, and true if the position is on its right edge.
+    fn column_at(&self, x: f32) -> Option<(usize, bool)> {
+        let x = x + self.scroll.x;
+        let mut end = 0.0;
+        for (index, column) in self.columns.iter().enumerate() {
+            end += column.width;
+            if (x - end).abs() <= RESIZE_HANDLE {
+                return Some((index, true));
+            }
+            if x < end {
+                return Some((index, false));
+            }
+        }
+        None
+    }
+}
+
+impl DataGrid {
+    /// A grid for the elements with a tag, without rows.
+    pub fn new(tag: &str, columns: Vec<GridColumn>) -> Self {
+        Self {
+            tag: tag.into(),
+            inner: Arc::new(Mutex::new(Inner {
+                columns,
+                data: Arc::new(Vec::<Vec<String>>::new()),
+                row_height: 28.0,
+                header_height: 32.0,
+                sort: None,
+                on_sort: None,
+                scroll: Vec2::ZERO,
+                viewport: Vec2::ZERO,
+                resizing: None,
+                pressed: None,
+            })),
+        }
+    }
+
+    pub fn with_data(self, data: impl GridData) -> Self {
+        self.set_data(data);
+        self
+    }
+
+    /// Set the height of the rows and of the header in pixels.
+    pub fn with_row_height(self, row_height: f32, header_height: f32) -> Self {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.row_height = row_height.max(1.0);
+            inner.header_height = header_height.max(0.0);
+        }
+        self
+    }
+
+    /// Replace the rows, for example after sorting them. The grid keeps its scroll position as far as the new rows reach.
+    pub fn set_data(&self, data: impl GridData) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.data = Arc::new(data);
+        inner.clamp_scroll();
+    }
+
+    /// Call a hook when the user clicks the header of a sortable column, with the column and the direction to sort the rows in. The grid shows the direction in the header; the hook sorts the rows and passes them to [`DataGrid::set_data`].
+    pub fn on_sort(&self, hook: impl Fn(usize, SortDirection) + Send + Sync + 'static) {
+        self.inner.lock().unwrap().on_sort = Some(Arc::new(hook));
+    }
+
+    /// The column the rows are sorted by and its direction, as the user last clicked it.
+    pub fn sort(&self) -> Option<(usize, SortDirection)> {
+        self.inner.lock().unwrap().sort
+    }
+
+    /// Show a column as sorted without calling the hook, for example when the rows start out sorted.
+    pub fn set_sort(&self, sort: Option<(usize, SortDirection)>) {
+        self.inner.lock().unwrap().sort = sort;
+    }
+
+    /// The columns with the widths the user dragged them to, to keep them for the next launch.
+    pub fn columns(&self) -> Vec<GridColumn> {
+        self.inner.lock().unwrap().columns.clone()
+    }
+}
+
+impl ElementPlugin for DataGrid {
+    fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    fn measure(
+        &self,
+        _element: &CustomElement,
+        known_dimensions: Size<Option<f32>>,
+        _available_space: Size<AvailableSpace>,
+    ) -> Size<f32> {
+        // The grid scrolls instead of growing with its rows, so only the width of the columns and the header are measured
+        let inner = self.inner.lock().unwrap();
+        let content = inner.content_size();
+        Size {
+            width: known_dimensions.width.unwrap_or(content.x),
+            height: known_dimensions.height.unwrap_or(inner.header_height),
+        }
+    }
+
+    fn paint(&self, _element: &CustomElement, content_box: Rect, painter: &mut Painter) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.viewport = content_box.size();
+        inner.clamp_scroll();
+        let scroll = inner.scroll;
+        let body = Rect::from_min_max(
+            content_box.min + vec2(0.0, inner.header_height),
+            content_box.max,
+        );
+
+        // Only the rows that are at least partly in view are drawn
+        let row_height = inner.row_height;
+        let row_count = inner.data.row_count();
+        let first = (scroll.y / row_height).floor() as usize;
+        let last = (((scroll.y + body.height()) / row_height).ceil() as usize).min(row_count);
+        let data = inner.data.clone();
+        let columns = inner.columns.clone();
+        let width: f32 = columns.iter().map(|column| column.width).sum();
+        let left = content_box.min.x - scroll.x;
+        painter.clipped(body, |painter| {
+            for row in first..last {
+                let top = body.min.y + row as f32 * row_height - scroll.y;
+                let rect = Rect::from_min_size(pos2(left, top), vec2(width, row_height));
+                if row % 2 == 1 {
+                    painter.add(Shape::rect_filled(
+                        rect,
+                        Rounding::none(),
+                        STRIPE_BACKGROUND,
+                    ));
+                }
+                painter.add(Shape::line_segment(
+                    [rect.left_bottom(), rect.right_bottom()],
+                    Stroke::new(1.0, GRID_LINE),
+                ));
+                let mut x = left;
+                for (index, column) in columns.iter().enumerate() {
+                    let cell = Rect::from_min_size(pos2(x, top), vec2(column.width, row_height));
+                    x += column.width;
+                    if cell.max.x < body.min.x || cell.min.x > body.max.x {
+                        continue;
+                    }
+                    let text = data.cell(row, index);
+                    painter.clipped(cell.shrink2(vec2(CELL_PADDING, 0.0)), |painter| {
+                        let position =
+                            pos2(cell.min.x + CELL_PADDING, cell.center().y - FONT_SIZE * 0.6);
+                        painter.text(&text, position, FONT_SIZE, TEXT_COLOR);
+                    });
+                }
+            }
+        });
+
+        // The header stays at the top and scrolls sideways with the rows
+        let header = Rect::from_min_size(
+            content_box.min,
+            vec2(content_box.width(), inner.header_height),
+        );
+        painter.clipped(content_box, |painter| {
+            painter.add(Shape::rect_filled(
+                header,
+                Rounding::none(),
+                HEADER_BACKGROUND,
+            ));
+            painter.add(Shape::line_segment(
+                [header.left_bottom(), header.right_bottom()],
+                Stroke::new(1.0, GRID_LINE),
+            ));
+            let mut x = left;
+            for (index, column) in columns.iter().enumerate() {
+                let cell =
+                    Rect::from_min_size(pos2(x, header.min.y), vec2(column.width, header.height()));
+                x += column.width;
+                let sorted = inner
+                    .sort
+                    .filter(|(sorted, _)| *sorted == index)
+                    .map(|(_, direction)| direction);
+                painter.clipped(cell.shrink2(vec2(CELL_PADDING, 0.0)), |painter| {
+                    let position =
+                        pos2(cell.min.x + CELL_PADDING, cell.center().y - FONT_SIZE * 0.6);
+                    let width = painter.text(&column.title, position, FONT_SIZE, HEADER_TEXT_COLOR);
+                    if let Some(direction) = sorted {
+                        let center = pos2(position.x + width + 8.0, cell.center().y);
+                        painter.add(sort_indicator(center, direction));
+                    }
+                });
+                // The edge that is dragged to resize the column
+                painter.add(Shape::line_segment(
+                    [cell.right_top(), cell.right_bottom()],
+                    Stroke::new(1.0, GRID_LINE),
+                ));
+            }
+        });
+    }
+
+    fn scroll(&self, _element: &CustomElement, _point: Pos2, delta: Vec2) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let before = inner.scroll;
+        inner.scroll -= delta;
+        inner.clamp_scroll();
+        inner.scroll != before
+    }
+
+    fn pointer(&self, _element: &CustomElement, phase: PointerPhase, point: Pos2) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let in_header = point.y >= 0.0 && point.y < inner.header_height;
+        match phase {
+            PointerPhase::Down => {
+                inner.pressed = None;
+                match inner.column_at(point.x).filter(|_| in_header) {
+                    Some((column, true)) => {
+                        let width = inner.columns[column].width;
+                        inner.resizing = Some((column, width, point.x));
+                    }
+                    Some((column, false)) => inner.pressed = Some(column),
+                    None => {}
+                }
+                false
+            }
+            PointerPhase::Move => {
+                let Some((column, start_width, start_x)) = inner.resizing else {
+                    return false;
+                };
+                let column = &mut inner.columns[column];
+                let width = (start_width + point.x - start_x).max(column.min_width);
+                let changed = width != column.width;
+                column.width = width;
+                changed
+            }
+            PointerPhase::Up => {
+                if inner.resizing.take().is_some() {
+                    inner.clamp_scroll();
+                    return true;
+                }
+                let clicked = inner.pressed.take().filter(|pressed| {
+                    in_header && inner.column_at(point.x) == Some((*pressed, false))
+                });
+                let Some(column) = clicked.filter(|column| inner.columns[*column].sortable) else {
+                    return false;
+                };
+                // Clicking the sorted column again reverses the order
+                let direction = match inner.sort {
+                    Some((sorted, SortDirection::Ascending)) if sorted == column => {
+                        SortDirection::Descending
+                    }
+                    _ => SortDirection::Ascending,
+                };
+                inner.sort = Some((column, direction));
+                let hook = inner.on_sort.clone();
+                // The hook can set the data of the grid
+                drop(inner);
+                if let Some(hook) = hook {
+                    hook(column, direction);
+                }
+                true
+            }
+        }
+    }
+}
+
+/// A small triangle that points up for ascending and down for descending order.
+fn sort_indicator(center: Pos2, direction: SortDirection) -> Shape {
+    let (tip, base) = match direction {
+        SortDirection::Ascending => (-3.0, 3.0),
+        SortDirection::Descending => (3.0, -3.0),
+    };
+    Shape::convex_polygon(
+        vec![
+            center + vec2(0.0, tip),
+            center + vec2(4.0, base),
+            center + vec2(-4.0, base),
+        ],
+        HEADER_TEXT_COLOR,
+        Stroke::NONE,
+    )
+}
@@ -0,0 +1,663 @@
+use std::sync::Arc;
+
+use dioxus_html::geometry::ClientPoint;
+use dioxus_native_core::prelude::*;
+use epaint::{Color32, Pos2, Rect, Rounding, Shape, Stroke};
+use peniko::kurbo::{Affine, Point};
+use serde::{Deserialize, Serialize};
+use shipyard::Component;
+use taffy::Taffy;
+use tokio::sync::watch;
+
+use crate::coordinates::element_transform;
+use crate::layout::TaffyLayout;
+use crate::mouse::hit_test;
+use crate::scene::{transform_rect, Scene};
+use crate::split::pane_sizes;
+use crate::system_settings::system_settings;
+
+/// The attribute of a tab that is dragged to dock its panel somewhere else, with the name of the panel
+const DOCK_TAB_ATTRIBUTE: &str = "dock-tab";
+/// The attribute of the element that shows the active panel of a tab stack, with the name of the panel. Tabs are dropped on it
+const DOCK_PANEL_ATTRIBUTE: &str = "dock-panel";
+/// How much of a panel along each edge docks next to it instead of into its tabs
+const EDGE_ZONE: f32 = 0.25;
+/// The size of a panel that is dragged out of the dock for the first time
+const FLOATING_SIZE: (f32, f32) = (320.0, 240.0);
+const PREVIEW_FILL: Color32 = Color32::from_rgba_premultiplied(0x13, 0x31, 0x75, 0x50);
+const PREVIEW_STROKE: Color32 = Color32::from_rgb(0x25, 0x63, 0xeb);
+
+/// The direction the children of a split are laid out in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DockAxis {
+    /// Side by side, like `flex-direction: row`
+    Horizontal,
+    /// Stacked, like `flex-direction: column`
+    Vertical,
+}
+
+/// Panels that share space as tabs, of which one is shown.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TabStack {
+    pub panels: Vec<String>,
+    /// The index of the panel that is shown
+    pub active: usize,
+}
+
+impl TabStack {
+    pub fn new(panels: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            panels: panels.into_iter().map(Into::into).collect(),
+            active: 0,
+        }
+    }
+
+    /// The panel that is shown, or `None` if the stack is empty.
+    pub fn active_panel(&self) -> Option<&str> {
+        self.panels.get(self.active).map(String::as_str)
+    }
+
+    fn contains(&self, panel: &str) -> bool {
+        self.panels.iter().any(|other| other == panel)
+    }
+
+    fn push(&mut self, panel: &str) {
+        self.panels.push(panel.to_string());
+        self.active = self.panels.len() - 1;
+    }
+
+    /// Remove a panel, keeping the panel that is shown if it isn't the removed one. Returns true if the stack had the panel.
+    fn remove(&mut self, panel: &str) -> bool {
+        let Some(index) = self.panels.iter().position(|other| other == panel) else {
+            return false;
+        };
+        self.panels.remove(index);
+        if self.active > index || self.active >= self.panels.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+        true
+    }
+}
+
+/// A part of the docked area of a [`DockLayout`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DockNode {
+    /// Parts side by side or stacked with dividers between them
+    Split {
+        axis: DockAxis,
+        children: Vec<DockNode>,
+        /// The share of the space of each child, like its `flex-grow`
+        weights: Vec<f32>,
+    },
+    Tabs(TabStack),
+}
+
+impl DockNode {
+    /// The tab stack with a panel in this part, if there is one.
+    fn stack_of(&self, panel: &str) -> Option<&TabStack> {
+        match self {
+            DockNode::Tabs(tabs) => tabs.contains(panel).then_some(tabs),
+            DockNode::Split { children, .. } => {
+                children.iter().find_map(|child| child.stack_of(panel))
+            }
+        }
+    }
+
+    fn stack_of_mut(&mut self, panel: &str) -> Option<&mut TabStack> {
+        match self {
+            DockNode::Tabs(tabs) => tabs.contains(panel).then_some(tabs),
+            DockNode::Split { children, .. } => children
+                .iter_mut()
+                .find_map(|child| child.stack_of_mut(panel)),
+        }
+    }
+
+    /// Give the child of the innermost split that has `before` the share `fraction` of the weights of it and the next child, which has `after`. Returns false if no split has the panels in neighbouring children.
+    fn share(&mut self, before: &str, after: &str, fraction: f32) -> bool {
+        let DockNode::Split {
+            children, weights, ..
+        } = self
+        else {
+            return false;
+        };
+        if let Some(child) = children
+            .iter_mut()
+            .find(|child| child.stack_of(before).is_some() && child.stack_of(after).is_some())
+        {
+            return child.share(before, after, fraction);
+        }
+        let Some(index) = children
+            .iter()
+            .position(|child| child.stack_of(before).is_some())
+        else {
+            return false;
+        };
+        if !children
+            .get(index + 1)
+            .is_some_and(|child| child.stack_of(after).is_some())
+        {
+            return false;
+        }
+        let total = weights[index] + weights[index + 1];
+        weights[index] = total * fraction;
+        weights[index + 1] = total - weights[index];
+        true
+    }
+
+    /// This part without a panel. Empty tab stacks are removed and splits with one child are replaced by the child.
+    fn without(self, panel: &str, removed: &mut bool) -> Option<DockNode> {
+        match self {
+            DockNode::Tabs(mut tabs) => {
+                *removed |= tabs.remove(panel);
+                (!tabs.panels.is_empty()).then_some(DockNode::Tabs(tabs))
+            }
+            DockNode::Split {
+                axis,
+                children,
+                weights,
+            } => {
+                let (mut children, weights): (Vec<_>, Vec<_>) = children
+                    .into_iter()
+                    .zip(weights)
+                    .filter_map(|(child, weight)| Some((child.without(panel, removed)?, weight)))
+                    .unzip();
+                match children.len() {
+                    0 => None,
+                    1 => children.pop(),
+                    _ => Some(DockNode::Split {
+                        axis,
+                        children,
+                        weights,
+                    }),
+                }
+            }
+        }
+    }
+
+    /// Add a panel to the tab stack of `target` or next to it. Returns false if this part doesn't have the target.
+    fn insert(&mut self, panel: &str, target: &str, zone: DockZone) -> bool {
+        match self {
+            DockNode::Tabs(tabs) if tabs.contains(target) => {
+                match zone.edge() {
+                    None => tabs.push(panel),
+                    Some((axis, before)) => {
+                        let docked = DockNode::Tabs(TabStack::new([panel]));
+                        let stack = std::mem::replace(self, DockNode::Tabs(TabStack::default()));
+                        let children = if before {
+                            vec![docked, stack]
+                        } else {
+                            vec![stack, docked]
+                        };
+                        *self = DockNode::Split {
+                            axis,
+                            children,
+                            weights: vec![1.0, 1.0],
+                        };
+                    }
+                }
+                true
+            }
+            DockNode::Tabs(_) => false,
+            DockNode::Split {
+                axis,
+                children,
+                weights,
+            } => {
+                for index in 0..children.len() {
+                    // A panel docked at an edge along the axis of the split becomes a sibling of the stack, which shares its space with it
+                    let is_target =
+                        matches!(&children[index], DockNode::Tabs(tabs) if tabs.contains(target));
+                    if let Some((edge_axis, before)) = zone.edge().filter(|_| is_target) {
+                        if edge_axis == *axis {
+                            let weight = weights[index] / 2.0;
+                            weights[index] = weight;
+                            let at = if before { index } else { index + 1 };
+                            children.insert(at, DockNode::Tabs(TabStack::new([panel])));
+                            weights.insert(at, weight);
+                            return true;
+                        }
+                    }
+                    if children[index].insert(panel, target, zone) {
+                        return true;
+                    }
+                }
+                false
+            }
+        }
+    }
+}
+
+/// A tab stack that floats over the docked area, at a position in client coordinates.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FloatingPanel {
+    pub tabs: TabStack,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Where a panel is docked relative to a tab stack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DockZone {
+    /// Into the tabs of the stack
+    Center,
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl DockZone {
+    /// The zone of a panel under a point. The outer quarter of the panel along each edge docks next to it, the rest into its tabs.
+    fn at(panel: Rect, point: Pos2) -> Self {
+        let x = (point.x - panel.min.x) / panel.width();
+        let y = (point.y - panel.min.y) / panel.height();
+        // The closest edge wins in the corners
+        let edges = [
+            (x, DockZone::Left),
+            (1.0 - x, DockZone::Right),
+            (y, DockZone::Top),
+            (1.0 - y, DockZone::Bottom),
+        ];
+        edges
+            .into_iter()
+            .filter(|(distance, _)| *distance < EDGE_ZONE)
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map_or(DockZone::Center, |(_, zone)| zone)
+    }
+
+    /// The part of a panel the docked panel takes, shown while it is dragged.
+    fn preview(self, panel: Rect) -> Rect {
+        let center = panel.center();
+        match self {
+            DockZone::Center => panel,
+            DockZone::Left => Rect::from_x_y_ranges(panel.min.x..=center.x, panel.y_range()),
+            DockZone::Right => Rect::from_x_y_ranges(center.x..=panel.max.x, panel.y_range()),
+            DockZone::Top => Rect::from_x_y_ranges(panel.x_range(), panel.min.y..=center.y),
+            DockZone::Bottom => Rect::from_x_y_ranges(panel.x_range(), center.y..=panel.max.y),
+        }
+    }
+
+    /// The axis of the split a panel docked at an edge is added to, and whether it comes before the stack.
+    fn edge(self) -> Option<(DockAxis, bool)> {
+        match self {
+            DockZone::Center => None,
+            DockZone::Left => Some((DockAxis::Horizontal, true)),
+            DockZone::Right => Some((DockAxis::Horizontal, false)),
+            DockZone::Top => Some((DockAxis::Vertical, true)),
+            DockZone::Bottom => Some((DockAxis::Vertical, false)),
+        }
+    }
+}
+
+/// How the panels of a tool or IDE style application are arranged: a tree of splits and tab stacks that fills the window, and panels that float over it.
+///
+/// Panels are named by the application. The layout is plain data that can be serialized with serde, to restore the arrangement the user left on the next launch.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DockLayout {
+    /// The docked area, or `None` if every panel floats or was closed
+    pub root: Option<DockNode>,
+    pub floating: Vec<FloatingPanel>,
+}
+
+impl DockLayout {
+    /// A layout with every panel in one tab stack.
+    pub fn new(panels: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let tabs = TabStack::new(panels);
+        Self {
+            root: (!tabs.panels.is_empty()).then_some(DockNode::Tabs(tabs)),
+            floating: Vec::new(),
+        }
+    }
+
+    /// The tab stack a panel is in, docked or floating.
+    pub fn stack_of(&self, panel: &str) -> Option<&TabStack> {
+        self.root
+            .as_ref()
+            .and_then(|root| root.stack_of(panel))
+            .or_else(|| {
+                self.floating
+                    .iter()
+                    .map(|floating| &floating.tabs)
+                    .find(|tabs| tabs.contains(panel))
+            })
+    }
+
+    fn stack_of_mut(&mut self, panel: &str) -> Option<&mut TabStack> {
+        if let Some(tabs) = self.root.as_mut().and_then(|root| root.stack_of_mut(panel)) {
+            return Some(tabs);
+        }
+        self.floating
+            .iter_mut()
+            .map(|floating| &mut floating.tabs)
+            .find(|tabs| tabs.contains(panel))
+    }
+
+    pub fn contains(&self, panel: &str) -> bool {
+        self.stack_of(panel).is_some()
+    }
+
+    /// Show a panel in its tab stack. Returns false if the layout doesn't have the panel.
+    pub fn activate(&mut self, panel: &str) -> bool {
+        let Some(tabs) = self.stack_of_mut(panel) else {
+            return false;
+        };
+        tabs.active = tabs.panels.iter().position(|other| other == panel).unwrap();
+        true
+    }
+
+    /// Close a panel. Returns false if the layout doesn't have the panel.
+    pub fn remove(&mut self, panel: &str) -> bool {
+        let mut removed = false;
+        self.root = self
+            .root
+            .take()
+            .and_then(|root| root.without(panel, &mut removed));
+        for floating in &mut self.floating {
+            removed |= floating.tabs.remove(panel);
+        }
+        self.floating
+            .retain(|floating| !floating.tabs.panels.is_empty());
+        removed
+    }
+
+    /// Move a panel into the tab stack of `target` or next to it. Docking a panel next to its own stack splits it off. A panel docked at the edge of a floating stack is added to its tabs, because floating stacks don't split.
+    ///
+    /// Returns false if the layout doesn't have the target, or if the panel would end up where it is.
+    pub fn dock(&mut self, panel: &str, target: &str, zone: DockZone) -> bool {
+        let Some(stack) = self.stack_of(target) else {
+            return false;
+        };
+        // The stack is found by another of its panels once the panel left it
+        let target = if panel == target {
+            if zone == DockZone::Center {
+                return false;
+            }
+            match stack.panels.iter().find(|other| *other != panel) {
+                Some(other) => other.clone(),
+                None => return false,
+            }
+        } else {
+            target.to_string()
+        };
+        self.remove(panel);
+        if self
+            .root
+            .as_mut()
+            .is_some_and(|root| root.insert(panel, &target, zone))
+        {
+            return true;
+        }
+        let tabs = self.stack_of_mut(&target).unwrap();
+        tabs.push(panel);
+        true
+    }
+
+    /// Dock a panel as the whole docked area, if every other panel floats. Returns false if the docked area isn't empty.
+    pub fn dock_root(&mut self, panel: &str) -> bool {
+        if self.root.is_some() {
+            return false;
+        }
+        self.remove(panel);
+        self.root = Some(DockNode::Tabs(TabStack::new([panel])));
+        true
+    }
+
+    /// Take a panel out of its tab stack and float it at a rect in client coordinates. A panel that floats on its own is moved.
+    pub fn float(&mut self, panel: &str, x: f32, y: f32, width: f32, height: f32) {
+        self.remove(panel);
+        self.floating.push(FloatingPanel {
+            tabs: TabStack::new([panel]),
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    /// The floating stack a panel is in.
+    fn floating_of(&self, panel: &str) -> Option<&FloatingPanel> {
+        self.floating
+            .iter()
+            .find(|floating| floating.tabs.contains(panel))
+    }
+}
+
+/// The dock layout of a window, which is changed by the application and by the user dragging tabs.
+///
+/// Pass a clone to [`crate::Config::with_docking`] and render the layout of another one, for example in a Dioxus context. Render each [`DockNode::Split`] as a flex container in the direction of its axis with its children growing by their weights, and put an element with `split-divider: resize` between them. Render each tab stack as its tabs and the active panel:
+///
+/// - each tab has a `dock-tab` attribute with the name of its panel. Clicking it activates the panel and dragging it past the drag threshold docks it where it is dropped
+/// - the element that shows the active panel has a `dock-panel` attribute with its name. Tabs dropped on the edges of it are docked next to the stack, the rest into its tabs
+/// - floating panels are positioned absolutely at their rect, in a container above the docked area. Tabs dropped outside of every panel float there
+///
+/// A preview of where the tab goes is drawn over the document while it is dragged. When a divider between two children of a split is dragged or moved with the keyboard, the sizes it leaves them are kept in the weights of the split. Changes the user makes are sent to [`Docking::changed`].
+#[derive(Clone)]
+pub struct Docking {
+    sender: Arc<watch::Sender<DockLayout>>,
+}
+
+impl Default for Docking {
+    fn default() -> Self {
+        Self::new(DockLayout::default())
+    }
+}
+
+impl Docking {
+    pub fn new(layout: DockLayout) -> Self {
+        Self {
+            sender: Arc::new(watch::channel(layout).0),
+        }
+    }
+
+    pub fn layout(&self) -> DockLayout {
+        self.sender.borrow().clone()
+    }
+
+    pub fn set_layout(&self, layout: DockLayout) {
+        self.sender.send_replace(layout);
+    }
+
+    /// Change the layout. Returns what the change returned.
+    pub fn update<R>(&self, change: impl FnOnce(&mut DockLayout) -> R) -> R {
+        let mut result = None;
+        self.sender
+            .send_modify(|layout| result = Some(change(layout)));
+        result.unwrap()
+    }
+
+    /// Wait for the next change to the layout and return it.
+    pub async fn changed(&self) -> DockLayout {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.changed().await;
+        let layout = receiver.borrow().clone();
+        layout
+    }
+}
+
+/// Keep the sizes of the panes on both sides of a divider the user moved in the weights of the split they are children of. Each pane is found in the layout by the first panel in it. Returns true if the layout changed.
+pub(crate) fn keep_split_sizes(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    divider: NodeId,
+    docking: &Docking,
+) -> bool {
+    let Some(([before, after], [before_size, after_size])) = pane_sizes(rdom, taffy, divider)
+    else {
+        return false;
+    };
+    let total = before_size + after_size;
+    let (Some(before), Some(after)) = (first_panel(rdom, before), first_panel(rdom, after)) else {
+        return false;
+    };
+    if total <= 0.0 {
+        return false;
+    }
+    docking.sender.send_if_modified(|layout| {
+        layout
+            .root
+            .as_mut()
+            .is_some_and(|root| root.share(&before, &after, before_size / total))
+    })
+}
+
+/// The name of the first panel in an element or its descendants, in tree order.
+fn first_panel(rdom: &RealDom, element: NodeId) -> Option<String> {
+    let mut stack = vec![rdom.get(element)?];
+    while let Some(node) = stack.pop() {
+        if let Some(panel) = attribute(node, DOCK_PANEL_ATTRIBUTE) {
+            return Some(panel);
+        }
+        stack.extend(node.children().into_iter().rev());
+    }
+    None
+}
+
+/// The preview of where a dragged tab is docked, in client coordinates, stored on the root
+#[derive(Component)]
+struct DockPreview(Option<Rect>);
+
+fn set_preview(rdom: &mut RealDom, preview: Option<Rect>) {
+    let root_id = rdom.root_id();
+    rdom.get_mut(root_id).unwrap().insert(DockPreview(preview));
+}
+
+/// A text attribute of an element without a namespace.
+fn attribute(node: NodeRef, name: &str) -> Option<String> {
+    match &*node.node_type() {
+        NodeType::Element(element) => element
+            .attributes
+            .iter()
+            .find(|(attribute, _)| attribute.name == name && attribute.namespace.is_none())
+            .and_then(|(_, value)| value.as_text())
+            .map(str::to_string),
+        _ => None,
+    }
+}
+
+/// The closest element with an attribute, starting at an element, with the value of the attribute.
+fn closest_with(rdom: &RealDom, element: NodeId, name: &str) -> Option<(NodeId, String)> {
+    let mut current = rdom.get(element);
+    while let Some(node) = current {
+        if let Some(value) = attribute(node, name) {
+            return Some((node.id(), value));
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// The mouse dragging the tab of a panel, see [`Docking`].
+pub(crate) struct DockDrag {
+    panel: String,
+    /// Where the drag started in client coordinates
+    start: Point,
+    /// Set once the mouse moved further than the drag threshold
+    dragging: bool,
+}
+
+impl DockDrag {
+    /// Start dragging the tab the mouse was pressed on, if the element is part of one.
+    pub fn start(rdom: &RealDom, element: NodeId, point: Point) -> Option<Self> {
+        let (_, panel) = closest_with(rdom, element, DOCK_TAB_ATTRIBUTE)?;
+        Some(Self {
+            panel,
+            start: point,
+            dragging: false,
+        })
+    }
+
+    /// Follow the mouse at a point in client coordinates. Returns true if the preview changed.
+    pub fn update(&mut self, rdom: &mut RealDom, taffy: &Taffy, point: Point) -> bool {
+        if !self.dragging && (point - self.start).hypot() <= system_settings().drag_threshold {
+            return false;
+        }
+        self.dragging = true;
+        let preview = drop_target(rdom, taffy, point).map(|(_, bounds, zone)| zone.preview(bounds));
+        set_preview(rdom, preview);
+        true
+    }
+
+    /// Drop the tab at a point in client coordinates, or activate its panel if it wasn't dragged. Returns true if the tab was dragged, in which case it isn't clicked.
+    pub fn finish(
+        self,
+        rdom: &mut RealDom,
+        taffy: &Taffy,
+        point: Point,
+        docking: &Docking,
+    ) -> bool {
+        if !self.dragging {
+            docking.update(|layout| layout.activate(&self.panel));
+            return false;
+        }
+        set_preview(rdom, None);
+        let target = drop_target(rdom, taffy, point);
+        docking.update(|layout| match target {
+            Some((target, _, zone)) => {
+                layout.dock(&self.panel, &target, zone);
+            }
+            None if layout.root.is_none() => {
+                layout.dock_root(&self.panel);
+            }
+            None => {
+                let (width, height) = layout
+                    .floating_of(&self.panel)
+                    .map_or(FLOATING_SIZE, |floating| (floating.width, floating.height));
+                layout.float(
+                    &self.panel,
+                    point.x as f32 - width / 2.0,
+                    point.y as f32,
+                    width,
+                    height,
+                );
+            }
+        });
+        true
+    }
+
+    /// Stop dragging without dropping the tab, for example when the window loses focus.
+    pub fn cancel(self, rdom: &mut RealDom) {
+        if self.dragging {
+            set_preview(rdom, None);
+        }
+    }
+}
+
+/// The panel under a point in client coordinates with its border box in client coordinates and the zone of it under the point.
+fn drop_target(rdom: &RealDom, taffy: &Taffy, point: Point) -> Option<(String, Rect, DockZone)> {
+    let hovered = *hit_test(rdom, taffy, ClientPoint::new(point.x, point.y), false).first()?;
+    let (element, panel) = closest_with(rdom, hovered, DOCK_PANEL_ATTRIBUTE)?;
+    let layout = rdom.get(element)?.get::<TaffyLayout>()?.node?;
+    let size = taffy.layout(layout).ok()?.size;
+    let bounds = transform_rect(
+        element_transform(rdom, taffy, element)?,
+        Rect::from_min_size(Pos2::ZERO, epaint::vec2(size.width, size.height)),
+    );
+    let zone = DockZone::at(bounds, Pos2::new(point.x as f32, point.y as f32));
+    Some((panel, bounds, zone))
+}
+
+/// Draw the preview of where a dragged tab is docked over the document. `transform` maps the document to the window.
+pub(crate) fn paint_dock_preview(rdom: &RealDom, scene: &mut Scene, clip: Rect, transform: Affine) {
+    let Some(preview) = rdom
+        .get(rdom.root_id())
+        .and_then(|root| root.get::<DockPreview>().and_then(|preview| preview.0))
+    else {
+        return;
+    };
+    scene.transform = transform;
+    scene.push(
+        clip,
+        Shape::rect_filled(preview.shrink(2.0), Rounding::same(4.0), PREVIEW_FILL),
+    );
+    scene.push(
+        clip,
+        Shape::rect_stroke(
+            preview.shrink(2.0),
+            Rounding::same(4.0),
+            Stroke::new(2.0, PREVIEW_STROKE),
+        ),
+    );
+    scene.transform = Affine::IDENTITY;
+}
@@ -0,0 +1,378 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use dioxus_html::geometry::ClientPoint;
+use dioxus_native_core::{prelude::*, real_dom::NodeTypeMut};
+use epaint::Color32;
+use peniko::kurbo::{Affine, Rect};
+use shipyard::Component;
+use taffy::{prelude::Size, Taffy};
+use tao::dpi::PhysicalSize;
+
+use crate::{
+    coordinates::element_transform,
+    events::{BlitzEventHandler, DomEvent},
+    focus::Focus,
+    fonts,
+    layout::{compute_root_layout, reflow_fonts, TaffyLayout},
+    mouse::{hit_test, MouseEffected},
+    plugin::{Painter, Plugins},
+    prevent_default::PreventDefault,
+    raster::rasterize,
+    render::{render, render_subtree},
+    report::Report,
+    scene::{transform_rect, Scene},
+    scroll::scroll_at,
+    session::{query_selector, SyntheticInput},
+    stable_id::stable_id,
+    style::{
+        BackdropFilter, Background, Border, BorderImage, Filter, ForgroundColor, Opacity, Outline,
+        Overflow, PointerEvents, Resize, SplitDivider, StyleDiagnostics, TextStyle, TouchAction,
+        Transform, UserSelect, WillChange, ZIndex,
+    },
+    system_settings::set_system_settings,
+    Config,
+};
+
+/// Create a RealDom that tracks all of the state Blitz needs to lay out and render the tree, followed by the states of the application.
+pub(crate) fn create_real_dom(
+    root_style: Vec<(String, String)>,
+    states: Vec<TypeErasedState<()>>,
+) -> RealDom {
+    let mut tracked_states = vec![
+        MouseEffected::to_type_erased(),
+        PointerEvents::to_type_erased(),
+        TouchAction::to_type_erased(),
+        Resize::to_type_erased(),
+        SplitDivider::to_type_erased(),
+        UserSelect::to_type_erased(),
+        TaffyLayout::to_type_erased(),
+        ForgroundColor::to_type_erased(),
+        TextStyle::to_type_erased(),
+        Background::to_type_erased(),
+        Border::to_type_erased(),
+        BorderImage::to_type_erased(),
+        Opacity::to_type_erased(),
+        Outline::to_type_erased(),
+        BackdropFilter::to_type_erased(),
+        Filter::to_type_erased(),
+        Overflow::to_type_erased(),
+        Transform::to_type_erased(),
+        ZIndex::to_type_erased(),
+        WillChange::to_type_erased(),
+        Focus::to_type_erased(),
+        PreventDefault::to_type_erased(),
+    ];
+    tracked_states.extend(states);
+    let mut rdom = RealDom::new(tracked_states);
+
+    // Styles for the root of the document are applied before anything else so they are resolved with the rest of the tree
+    let root_id = rdom.root_id();
+    if let NodeTypeMut::Element(mut root) = rdom.get_mut(root_id).unwrap().node_type_mut() {
+        for (name, value) in root_style {
+            root.set_attribute(
+                OwnedAttributeDiscription {
+                    name,
+                    namespace: Some("style".to_string()),
+                },
+                OwnedAttributeValue::Text(value),
+            );
+        }
+    }
+
+    rdom
+}
+
+pub(crate) fn to_color32(color: peniko::Color) -> Color32 {
+    Color32::from_rgba_unmultiplied(color.r, color.g, color.b, color.a)
+}
+
+/// A document that is styled, laid out and rendered without a window.
+///
+/// This is useful for rendering screenshots and for tests that run without a GPU.
+pub struct Document {
+    rdom: RealDom,
+    taffy: Arc<Mutex<Taffy>>,
+    style_diagnostics: Arc<StyleDiagnostics>,
+    clear_color: Color32,
+    feathering: f32,
+    plugins: Arc<Plugins>,
+    /// The viewport of the last resolve in logical pixels
+    viewport_size: Size<f32>,
+    /// The generation of the fonts the text was measured with
+    font_generation: u64,
+}
+
+impl Document {
+    pub fn new(cfg: Config) -> Self {
+        if let Some(settings) = cfg.system_settings {
+            set_system_settings(settings);
+        }
+        fonts::register_config_fonts(cfg.fonts, cfg.font_faces);
+        if let Some(fallback) = cfg.font_fallback {
+            fonts::set_font_fallback(fallback);
+        }
+        Self {
+            rdom: create_real_dom(cfg.root_style, cfg.states),
+            taffy: Arc::new(Mutex::new(Taffy::new())),
+            style_diagnostics: cfg.style_diagnostics,
+            clear_color: to_color32(cfg.clear_color),
+            feathering: cfg.feathering,
+            plugins: Arc::new(cfg.plugins),
+            viewport_size: Size::ZERO,
+            font_generation: fonts::generation(),
+        }
+    }
+
+    pub fn real_dom(&self) -> &RealDom {
+        &self.rdom
+    }
+
+    pub fn real_dom_mut(&mut self) -> &mut RealDom {
+        &mut self.rdom
+    }
+
+    /// Resolve the styles and layout of the document for a viewport size in logical pixels.
+    pub fn resolve(&mut self, viewport_size: Size<f32>) {
+        let mut ctx = SendAnyMap::new();
+        ctx.insert(self.taffy.clone());
+        ctx.insert(self.style_diagnostics.clone());
+        ctx.insert(self.plugins.clone());
+        self.rdom.update_state(ctx);
+        self.viewport_size = viewport_size;
+        let mut taffy = self.taffy.lock().unwrap();
+        reflow_fonts(&mut self.rdom, &mut taffy, &mut self.font_generation);
+        compute_root_layout(&self.rdom, &mut taffy, viewport_size);
+    }
+
+    /// Render the document into an image with the given size in physical pixels.
+    pub fn render_to_image(
+        &mut self,
+        width: u32,
+        height: u32,
+        scale_factor: f32,
+    ) -> image::RgbaImage {
+        let scene = self.scene(width, height, scale_factor);
+        rasterize(&scene, width, height, scale_factor)
+    }
+
+    /// Render an element and its children into an image with a transparent background, for drag previews, thumbnails or exporting part of a document.
+    ///
+    /// The element is laid out in the viewport of the last call to [`Document::resolve`] or [`Document::render_to_image`]. `size` is in physical pixels and defaults to the size of the element; the element is stretched to fill a different size.
+    ///
+    /// Returns `None` if the element doesn't exist or has no size.
+    pub fn render_element_to_image(
+        &mut self,
+        element: NodeId,
+        size: Option<(u32, u32)>,
+        scale_factor: f32,
+    ) -> Option<image::RgbaImage> {
+        self.resolve(self.viewport_size);
+        let taffy = self.taffy.lock().unwrap();
+        let node = self.rdom.get(element)?;
+        let layout = taffy.layout(node.get::<TaffyLayout>()?.node?).ok()?;
+        let (width, height) = size.unwrap_or((
+            (layout.size.width * scale_factor).round() as u32,
+            (layout.size.height * scale_factor).round() as u32,
+        ));
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let mut scene = Scene {
+            clear_color: Color32::TRANSPARENT,
+            feathering: self.feathering,
+            ..Default::default()
+        };
+        let rendered = render_subtree(
+            &taffy,
+            node,
+            &mut scene,
+            Size {
+                width: width as f32 / scale_factor,
+                height: height as f32 / scale_factor,
+            },
+            PhysicalSize::new(
+                self.viewport_size.width as u32,
+                self.viewport_size.height as u32,
+            ),
+            &self.plugins,
+        );
+        rendered.then(|| rasterize(&scene, width, height, scale_factor))
+    }
+
+    /// Capture the part of the rendered document that an element covers, with everything that is drawn over and under it, for example to show a blurred copy behind an onboarding overlay. `blur` is the standard deviation of a gaussian blur in physical pixels, 0 keeps the capture sharp.
+    ///
+    /// The document is rendered in the viewport of the last call to [`Document::resolve`] or [`Document::render_to_image`]. A transformed element is captured by its bounding box.
+    ///
+    /// Returns `None` if the element doesn't exist or is outside of the viewport.
+    pub fn capture_element(
+        &mut self,
+        element: NodeId,
+        scale_factor: f32,
+        blur: f32,
+    ) -> Option<image::RgbaImage> {
+        let width = (self.viewport_size.width * scale_factor).round() as u32;
+        let height = (self.viewport_size.height * scale_factor).round() as u32;
+        let image = self.render_to_image(width, height, scale_factor);
+
+        let taffy = self.taffy.lock().unwrap();
+        let node = self.rdom.get(element)?;
+        let layout = taffy.layout(node.get::<TaffyLayout>()?.node?).ok()?;
+        let border_box = epaint::Rect::from_min_size(
+            epaint::Pos2::ZERO,
+            epaint::vec2(layout.size.width, layout.size.height),
+        );
+        let transform = element_transform(&self.rdom, &taffy, element)?;
+        let bounds = transform_rect(Affine::scale(scale_factor as f64) * transform, border_box);
+        // Whole pixels that are at least partly covered by the element
+        let bounds = epaint::Rect::from_min_max(bounds.min.floor(), bounds.max.ceil()).intersect(
+            epaint::Rect::from_min_size(
+                epaint::Pos2::ZERO,
+                epaint::vec2(width as f32, height as f32),
+            ),
+        );
+        if !bounds.is_positive() {
+            return None;
+        }
+        let capture = image::imageops::crop_imm(
+            &image,
+            bounds.min.x as u32,
+            bounds.min.y as u32,
+            bounds.width() as u32,
+            bounds.height() as u32,
+        )
+        .to_image();
+        Some(if blur > 0.0 {
+            image::imageops::blur(&capture, blur)
+        } else {
+            capture
+        })
+    }
+
+    /// The elements under a point in client coordinates, from the topmost to the bottommost, in the layout of the last call to [`Document::resolve`] or [`Document::render_to_image`]. See [`hit_test`].
+    pub fn hit_test(&self, point: ClientPoint, all: bool) -> Vec<NodeId> {
+        let taffy = self.taffy.lock().unwrap();
+        hit_test(&self.rdom, &taffy, point, all)
+    }
+
+    /// The first element in document order that matches a selector of a tag name, ids and classes like `li.item.selected`, or the element with a stable id like `#menu/li[2]`.
+    pub fn query_selector(&self, selector: &str) -> Option<NodeId> {
+        query_selector(&self.rdom, selector)
+    }
+
+    /// The stable id of an element, which finds the same element in the document after it is rebuilt. See [`stable_id`].
+    pub fn stable_id(&self, element: NodeId) -> Option<String> {
+        stable_id(&self.rdom, element)
+    }
+
+    /// The bounding box of the border box of an element in client coordinates, in the layout of the last call to [`Document::resolve`]. Returns `None` if the element doesn't exist or hasn't been laid out.
+    pub fn bounding_box(&self, element: NodeId) -> Option<Rect> {
+        let taffy = self.taffy.lock().unwrap();
+        let layout = taffy
+            .layout(self.rdom.get(element)?.get::<TaffyLayout>()?.node?)
+            .ok()?;
+        let border_box = Rect::new(
+            0.0,
+            0.0,
+            layout.size.width as f64,
+            layout.size.height as f64,
+        );
+        let transform = element_transform(&self.rdom, &taffy, element)?;
+        Some(transform.transform_rect_bbox(border_box))
+    }
+
+    /// Write a diagnostics report for the document rendered at the given size in physical pixels into a new directory inside of `dir`.
+    ///
+    /// Returns the path of the directory the report was written to.
+    pub fn write_diagnostics_report(
+        &mut self,
+        dir: impl AsRef<Path>,
+        width: u32,
+        height: u32,
+        scale_factor: f32,
+    ) -> io::Result<PathBuf> {
+        let scene = self.scene(width, height, scale_factor);
+        let taffy = self.taffy.lock().unwrap();
+        Report {
+            reason: "requested",
+            adapter: None,
+            size: PhysicalSize::new(width, height),
+            scale_factor,
+            scene: Some(&scene),
+            dom: Some((&self.rdom, &taffy)),
+            style_diagnostics: &self.style_diagnostics,
+        }
+        .write(dir.as_ref())
+    }
+
+    pub(crate) fn plugins(&self) -> Arc<Plugins> {
+        self.plugins.clone()
+    }
+
+    /// Handle synthetic input in the layout of the last resolve and return the events the elements get, see [`crate::HeadlessSession`].
+    pub(crate) fn synthesize(
+        &mut self,
+        events: &mut BlitzEventHandler,
+        input: SyntheticInput,
+    ) -> io::Result<Vec<DomEvent>> {
+        let mut taffy = self.taffy.lock().unwrap();
+        let viewport_size = Size {
+            width: self.viewport_size.width as u32,
+            height: self.viewport_size.height as u32,
+        };
+        let result = events.synthesize(input, &mut self.rdom, &mut taffy, &viewport_size, None);
+        let dom_events = events.drain_events();
+        result.map(|()| dom_events)
+    }
+
+    /// Lay the document out in a rect and draw it there, for documents shown in frames.
+    pub(crate) fn paint(&mut self, rect: epaint::Rect, painter: &mut Painter) {
+        self.resolve(Size {
+            width: rect.width(),
+            height: rect.height(),
+        });
+        let taffy = self.taffy.lock().unwrap();
+        painter.add_document(&self.rdom, &taffy, rect, &self.plugins);
+    }
+
+    /// Scroll the document with the mouse wheel over a point in client coordinates. Returns true if anything scrolled.
+    pub(crate) fn scroll(&mut self, point: epaint::Pos2, delta: epaint::Vec2) -> bool {
+        let taffy = self.taffy.lock().unwrap();
+        scroll_at(
+            &mut self.rdom,
+            &taffy,
+            &self.plugins,
+            peniko::kurbo::Point::new(point.x as f64, point.y as f64),
+            peniko::kurbo::Vec2::new(delta.x as f64, delta.y as f64),
+        )
+    }
+
+    /// Resolve the document and collect the shapes that make up a frame with the given size in physical pixels.
+    pub(crate) fn scene(&mut self, width: u32, height: u32, scale_factor: f32) -> Scene {
+        let viewport_size = Size {
+            width: width as f32 / scale_factor,
+            height: height as f32 / scale_factor,
+        };
+        self.resolve(viewport_size);
+
+        let mut scene = Scene {
+            clear_color: self.clear_color,
+            feathering: self.feathering,
+            ..Default::default()
+        };
+        let viewport_size =
+            PhysicalSize::new(viewport_size.width as u32, viewport_size.height as u32);
+        render(
+            &self.rdom,
+            &self.taffy.lock().unwrap(),
+            &mut scene,
+            viewport_size,
+            viewport_size,
+            Affine::IDENTITY,
+            &self.plugins,
+        );
+        scene
+    }
+}
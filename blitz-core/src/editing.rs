@@ -0,0 +1,106 @@
+use std::ops::Range;
+use std::time::Instant;
+
+use dioxus_native_core::prelude::*;
+use shipyard::Component;
+
+use crate::system_settings::system_settings;
+
+/// The caret and selection of the element whose text is being edited, or `None` for every other element. The renderer draws the selection behind the text of the element and the blinking caret over it.
+///
+/// Offsets are in bytes into the text of the text children of the element, joined in order, see [`editable_text`].
+#[derive(Clone, Copy, PartialEq, Debug, Default, Component)]
+pub(crate) struct Editing(pub Option<TextSelection>);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct TextSelection {
+    /// Where the selection started
+    pub anchor: usize,
+    /// Where the selection ends, which is where the caret is drawn
+    pub caret: usize,
+    /// When the caret was last moved. It is shown right after it moves and then blinks
+    pub moved: Instant,
+}
+
+impl TextSelection {
+    /// A selection without any text selected, with the caret at an offset
+    pub fn collapsed(caret: usize) -> Self {
+        Self {
+            anchor: caret,
+            caret,
+            moved: Instant::now(),
+        }
+    }
+
+    /// The selected text, which is empty if the selection is collapsed
+    pub fn range(&self) -> Range<usize> {
+        self.anchor.min(self.caret)..self.anchor.max(self.caret)
+    }
+
+    /// True if the blinking caret is shown at a point in time. It blinks with the interval of the system settings
+    pub fn caret_visible(&self, now: Instant) -> bool {
+        let Some(interval) = system_settings().caret_blink_interval else {
+            return true;
+        };
+        let elapsed = now.saturating_duration_since(self.moved);
+        (elapsed.as_millis() / interval.as_millis().max(1)) % 2 == 0
+    }
+
+    /// When the caret is shown or hidden next, after a point in time, or `None` if it doesn't blink
+    pub fn next_blink(&self, now: Instant) -> Option<Instant> {
+        let interval = system_settings().caret_blink_interval?;
+        let elapsed = now.saturating_duration_since(self.moved);
+        let blinks = elapsed.as_millis() / interval.as_millis().max(1) + 1;
+        Some(self.moved + interval * blinks as u32)
+    }
+}
+
+/// The text of the text children of an element, which is the text that is edited when the element is.
+pub(crate) fn editable_text(node: NodeRef) -> String {
+    node.children()
+        .into_iter()
+        .filter_map(|child| match &*child.node_type() {
+            NodeType::Text(TextNode { text, .. }) => Some(text.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The word around a byte offset in a text, which a double click selects. A word is a run of letters and digits, or of any other characters that aren't whitespace, or of whitespace.
+pub(crate) fn word_at(text: &str, offset: usize) -> Range<usize> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Whitespace,
+        Other,
+    }
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Whitespace
+        } else {
+            Class::Other
+        }
+    };
+    // The character after the offset, or before it at the end of the text
+    let Some(clicked) = text[offset..]
+        .chars()
+        .next()
+        .or_else(|| text[..offset].chars().next_back())
+        .map(class)
+    else {
+        return offset..offset;
+    };
+    let start = text[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| class(*c) == clicked)
+        .last()
+        .map_or(offset, |(index, _)| index);
+    let end = text[offset..]
+        .char_indices()
+        .find(|(_, c)| class(*c) != clicked)
+        .map_or(text.len(), |(index, _)| offset + index);
+    start..end
+}
@@ -1,39 +1,50 @@
 use keyboard_types::Code;
-use peniko::kurbo::Point;
+use peniko::kurbo::{Point, Vec2};
 use quadtree_rs::Quadtree;
-use std::{
-    any::Any,
-    rc::Rc,
-    str::FromStr,
-    sync::Arc,
-    time::{Duration, Instant},
-};
+use std::{any::Any, io, rc::Rc, str::FromStr, sync::Arc, time::Instant};
 use taffy::{prelude::Size, Taffy};
 use tao::event::MouseButton;
 
 use dioxus_html::{
-    events::{FocusData, KeyboardData, MouseData, WheelData},
-    geometry::{euclid::Point2D, ClientPoint, Coordinates, ElementPoint, PagePoint, ScreenPoint},
+    events::{FocusData, FormData, KeyboardData, MouseData, WheelData},
+    geometry::{euclid::Point2D, ClientPoint, Coordinates, ElementPoint, PagePoint},
     input_data::{self, keyboard_types::Modifiers, MouseButtonSet},
 };
 use dioxus_native_core::prelude::*;
 
-use tao::keyboard::Key;
-
 use crate::{
     application::DirtyNodes,
+    clipboard::Clipboard,
+    coordinates::WindowGeometry,
+    dock::{keep_split_sizes, DockDrag, Docking},
+    editing::{editable_text, word_at, Editing, TextSelection},
     focus::{Focus, FocusState},
+    gestures::{GestureConfig, GestureData, GestureRecognizer},
+    input_filter::{Input, InputFilter},
+    layout::{compute_root_layout, relayout},
     mouse::get_hovered,
+    plugin::{content_point, CustomElement, Plugins, PointerPhase},
     prevent_default::PreventDefault,
+    render::text_offset_at,
+    resize::ResizeDrag,
+    scroll::{scroll_at, wheel_delta, ScrollbarDrag},
+    selection::{clear_selection, selected_text, SelectionDrag},
+    session::{element_center, query_selector, SyntheticInput},
+    split::{divider_key, SplitDrag},
+    style::{TouchAction, UserSelect},
+    system_settings::system_settings,
+    toast::Toasts,
     RealDom, TaoEvent,
 };
 
-const DBL_CLICK_TIME: Duration = Duration::from_millis(500);
+/// The pointer id of the mouse in the gesture recognizer. Touches use the ids of the platform
+const MOUSE_POINTER: u64 = u64::MAX;
 
 struct CursorState {
     position: Coordinates,
     buttons: MouseButtonSet,
-    last_click: Option<Instant>,
+    /// When and where the last click was, which a double click has to be close to
+    last_click: Option<(Instant, ClientPoint)>,
     last_pressed_element: Option<NodeId>,
     last_clicked_element: Option<NodeId>,
     hovered: Option<NodeId>,
@@ -99,6 +110,8 @@ pub enum EventData {
     Keyboard(KeyboardData),
     Focus(FocusData),
     Wheel(WheelData),
+    Form(FormData),
+    Gesture(GestureData),
 }
 
 impl EventData {
@@ -108,18 +121,68 @@ impl EventData {
             EventData::Keyboard(data) => Rc::new(data),
             EventData::Focus(data) => Rc::new(data),
             EventData::Wheel(data) => Rc::new(data),
+            EventData::Form(data) => Rc::new(data),
+            EventData::Gesture(data) => Rc::new(data),
         }
     }
 }
 
+/// A touch that scrolls the container under it when it moves, in the directions the element it started on allows
+struct TouchPan {
+    id: u64,
+    start: Point,
+    last: Point,
+    action: TouchAction,
+    /// Set once the touch moved further than the drag threshold
+    panning: bool,
+}
+
 /// Stores the perisistent state of the event handler, and handles the event queue
 pub struct BlitzEventHandler {
     state: EventState,
     queued_events: Vec<DomEvent>,
+    /// Opened the first time it is needed, because there may be no clipboard (for example without a display server)
+    clipboard: Option<Clipboard>,
+    gestures: GestureRecognizer,
+    /// Set when a container was scrolled since the last frame
+    scrolled: bool,
+    /// The touch that scrolls, if only one touch is down
+    touch_pan: Option<TouchPan>,
+    /// The element whose grip the mouse is dragging
+    resizing: Option<ResizeDrag>,
+    /// The divider between two panes the mouse is dragging
+    splitting: Option<SplitDrag>,
+    /// The scrollbar whose thumb the mouse is dragging
+    scrollbar_drag: Option<ScrollbarDrag>,
+    /// Set when an element was resized since the last frame
+    resized: bool,
+    /// The layout whose panels are rearranged by dragging their tabs, see [`crate::Config::with_docking`]
+    docking: Option<Docking>,
+    /// The tab the mouse is dragging
+    docking_drag: Option<DockDrag>,
+    /// The toasts shown over the document, see [`crate::Config::with_toasts`]
+    toasts: Option<Toasts>,
+    /// Set while the primary button that was pressed on a toast is held, so the document doesn't get its release
+    toast_pressed: bool,
+    /// The custom element the primary button was pressed on, which gets the mouse until it is released
+    plugin_capture: Option<NodeId>,
+    /// Set when a custom element changed its content or the selected text changed since the last frame
+    repaint: bool,
+    /// The text the mouse is selecting by dragging over it
+    selecting: Option<SelectionDrag>,
+    plugins: Arc<Plugins>,
+    /// The element whose text is edited with its selection, and when its caret is shown or hidden next if it blinks
+    editing: Option<(NodeId, TextSelection, Option<Instant>)>,
+    /// Remaps or drops the input of the window before it is handled
+    input_filter: Option<Box<dyn InputFilter>>,
 }
 
 impl BlitzEventHandler {
-    pub(crate) fn new(focus_state: FocusState) -> Self {
+    pub(crate) fn new(
+        focus_state: FocusState,
+        gesture_config: GestureConfig,
+        plugins: Arc<Plugins>,
+    ) -> Self {
         Self {
             state: EventState {
                 focus_state,
@@ -127,18 +190,727 @@ impl BlitzEventHandler {
                 cursor_state: Default::default(),
             },
             queued_events: Default::default(),
+            clipboard: None,
+            gestures: GestureRecognizer::new(gesture_config),
+            scrolled: false,
+            touch_pan: None,
+            resizing: None,
+            splitting: None,
+            scrollbar_drag: None,
+            resized: false,
+            docking: None,
+            docking_drag: None,
+            toasts: None,
+            toast_pressed: false,
+            plugin_capture: None,
+            repaint: false,
+            selecting: None,
+            plugins,
+            editing: None,
+            input_filter: None,
         }
     }
 
     pub(crate) fn clean(&mut self) -> DirtyNodes {
-        self.state.clean()
+        let mut dirty = self.state.clean();
+        // Everything inside of a container moves when it scrolls, and everything after an element moves when it is resized
+        if std::mem::take(&mut self.scrolled)
+            | std::mem::take(&mut self.resized)
+            | std::mem::take(&mut self.repaint)
+            || self.plugins.animating()
+        {
+            return DirtyNodes::All;
+        }
+        // The edited element is drawn again when its caret blinks
+        if let Some((id, selection, next_blink)) = &mut self.editing {
+            let now = Instant::now();
+            if next_blink.is_some_and(|next_blink| next_blink <= now) {
+                *next_blink = selection.next_blink(now);
+                if let DirtyNodes::Some(nodes) = &mut dirty {
+                    nodes.insert(*id);
+                }
+            }
+        }
+        dirty
+    }
+
+    /// When the caret of the edited element is shown or hidden next
+    pub(crate) fn caret_deadline(&self) -> Option<Instant> {
+        self.editing.and_then(|(_, _, next_blink)| next_blink)
+    }
+
+    /// The modifier keys that are currently held
+    pub(crate) fn modifiers(&self) -> Modifiers {
+        self.state.modifier_state
+    }
+
+    /// The element that has focus
+    pub(crate) fn focused(&self) -> Option<NodeId> {
+        self.state.focus_state.last_focused_id
+    }
+
+    pub(crate) fn set_focus(&mut self, rdom: &mut RealDom, id: NodeId) {
+        self.state.focus_state.set_focus(rdom, id);
+        self.edit(rdom, id);
+    }
+
+    /// Stop editing the element that was edited before and start editing the text of an element if it is editable, with the caret at the end of its text. The whole text is selected if the element has `user-select: all`.
+    fn edit(&mut self, rdom: &mut RealDom, id: NodeId) {
+        if let Some((old, _, _)) = self.editing.take() {
+            if let Some(mut node) = rdom.get_mut(old) {
+                node.insert(Editing(None));
+            }
+            self.state.focus_state.dirty.insert(old);
+        }
+        let Some(node) = rdom.get(id) else {
+            return;
+        };
+        if editable_value(node).is_none() {
+            return;
+        }
+        let len = editable_text(node).len();
+        let selection = match *node.get::<UserSelect>().unwrap() {
+            UserSelect::All => TextSelection {
+                anchor: 0,
+                ..TextSelection::collapsed(len)
+            },
+            _ => TextSelection::collapsed(len),
+        };
+        rdom.get_mut(id).unwrap().insert(Editing(Some(selection)));
+        self.editing = Some((id, selection, selection.next_blink(selection.moved)));
+    }
+
+    /// Select the word of the edited element under a point in client coordinates that was double clicked, or all of its text with `user-select: all`. Nothing is selected with `user-select: none`.
+    fn select_word(&mut self, rdom: &mut RealDom, taffy: &Taffy, id: NodeId, point: Point) {
+        if self.editing.map(|(editing, _, _)| editing) != Some(id) {
+            return;
+        }
+        let Some(node) = rdom.get(id) else {
+            return;
+        };
+        let text = editable_text(node);
+        let range = match *node.get::<UserSelect>().unwrap() {
+            UserSelect::None => return,
+            UserSelect::All => 0..text.len(),
+            UserSelect::Text => word_at(&text, text_offset_at(taffy, node, point)),
+        };
+        let selection = TextSelection {
+            anchor: range.start,
+            ..TextSelection::collapsed(range.end)
+        };
+        rdom.get_mut(id).unwrap().insert(Editing(Some(selection)));
+        self.state.focus_state.dirty.insert(id);
+        self.editing = Some((id, selection, selection.next_blink(selection.moved)));
+    }
+
+    /// The clipboard of the system, opened the first time it is needed
+    fn clipboard(&mut self) -> Option<&mut Clipboard> {
+        if self.clipboard.is_none() {
+            match Clipboard::new() {
+                Ok(clipboard) => self.clipboard = Some(clipboard),
+                Err(err) => {
+                    log::warn!("failed to open the clipboard: {err}");
+                    return None;
+                }
+            }
+        }
+        self.clipboard.as_mut()
+    }
+
+    /// Copy the text selected in the document to the clipboard, or to the primary selection on Linux when it was selected with the mouse. Returns true if text was selected.
+    fn copy_selection(&mut self, rdom: &RealDom, taffy: &Taffy, primary: bool) -> bool {
+        let Some(text) = selected_text(rdom, taffy) else {
+            return false;
+        };
+        let Some(clipboard) = self.clipboard() else {
+            return true;
+        };
+        let copied = match primary {
+            true => clipboard.copy_primary_text(&text),
+            false => clipboard.copy_text(&text),
+        };
+        if let Err(err) = copied {
+            log::warn!("failed to copy the selected text: {err}");
+        }
+        true
+    }
+
+    /// Paste the primary selection into an editable element that was middle clicked, like other applications on Linux do. Like a keyboard paste, the text replaces the selection of the element if it is edited, or is inserted at its caret. The element gets an input event with its new value.
+    fn paste_primary_selection(&mut self, rdom: &mut RealDom, id: NodeId) {
+        let Some(mut value) = rdom.get(id).and_then(editable_value) else {
+            return;
+        };
+        let Some(clipboard) = self.clipboard() else {
+            return;
+        };
+        let pasted = match clipboard.paste_primary_text() {
+            Ok(Some(pasted)) => pasted,
+            Ok(None) => return,
+            Err(err) => {
+                log::warn!("failed to read the primary selection: {err}");
+                return;
+            }
+        };
+        // Editing an element starts with the caret at the end of its value
+        let edited = self
+            .editing
+            .filter(|(editing, _, _)| *editing == id)
+            .map(|(_, selection, _)| selection.range());
+        let range = edited.clone().unwrap_or(value.len()..value.len());
+        let floor = |offset: usize| {
+            (0..=offset.min(value.len()))
+                .rev()
+                .find(|offset| value.is_char_boundary(*offset))
+                .unwrap_or(0)
+        };
+        let range = floor(range.start)..floor(range.end);
+        value.replace_range(range.clone(), &pasted);
+        // The caret moves to the end of the pasted text
+        if edited.is_some() {
+            let selection = TextSelection::collapsed(range.start + pasted.len());
+            rdom.get_mut(id).unwrap().insert(Editing(Some(selection)));
+            self.state.focus_state.dirty.insert(id);
+            self.editing = Some((id, selection, selection.next_blink(selection.moved)));
+        }
+        self.queued_events.push(DomEvent {
+            element: id,
+            name: "input",
+            data: Arc::new(EventData::Form(FormData {
+                value,
+                values: Default::default(),
+                files: None,
+            })),
+            bubbles: true,
+        });
+    }
+
+    /// Feed a pointer that was pressed or released to the gesture recognizer
+    fn pointer_input(
+        &mut self,
+        id: u64,
+        state: tao::event::ElementState,
+        position: Point,
+        hovered: Option<NodeId>,
+        rdom: &RealDom,
+    ) {
+        let now = Instant::now();
+        match state {
+            tao::event::ElementState::Pressed => {
+                self.gestures.pointer_down(id, position, hovered, now)
+            }
+            _ => {
+                let waits_for_double_tap = self
+                    .gestures
+                    .target()
+                    .and_then(|target| gesture_target(rdom, &self.plugins, target, "doubletap"))
+                    .is_some();
+                self.gestures.pointer_up(id, now, waits_for_double_tap);
+            }
+        }
+        self.dispatch_gestures(rdom);
+    }
+
+    /// Send the recognized gestures to the innermost element under the gesture that handles them, so a nested element handles a gesture before its ancestors. Gesture events don't bubble.
+    fn dispatch_gestures(&mut self, rdom: &RealDom) {
+        for (data, target) in self.gestures.drain() {
+            let name = data.gesture.event_name();
+            if let Some(element) = gesture_target(rdom, &self.plugins, target, name) {
+                self.queued_events.push(DomEvent {
+                    element,
+                    name,
+                    data: Arc::new(EventData::Gesture(data)),
+                    bubbles: false,
+                });
+            }
+        }
+    }
+
+    /// Send the mouse to the custom element it is over, or to the one the primary button was pressed on while it is held, see [`crate::ElementPlugin::pointer`].
+    fn plugin_pointer(&mut self, rdom: &RealDom, taffy: &Taffy, phase: PointerPhase, point: Point) {
+        let element = self.plugin_capture.or(self.state.cursor_state.hovered);
+        let plugins = self.plugins.clone();
+        let Some((element, plugin)) =
+            element.and_then(|element| Some((element, plugins.for_node(rdom.get(element)?)?)))
+        else {
+            return;
+        };
+        self.plugin_capture = match phase {
+            PointerPhase::Down => Some(element),
+            PointerPhase::Move => self.plugin_capture,
+            PointerPhase::Up => None,
+        };
+        let Some(local) = content_point(rdom, taffy, element, point) else {
+            return;
+        };
+        let Some(custom) = rdom.get(element).and_then(CustomElement::from_node) else {
+            return;
+        };
+        if plugin.pointer(&custom, phase, local) {
+            self.repaint = true;
+        }
+    }
+
+    /// Scroll the container under a touch that moved, unless `touch-action` of the element it started on leaves the move to the gestures of the element. Once a touch scrolls, it no longer takes part in gestures, like a browser cancels the pointer.
+    fn pan_touch(&mut self, id: u64, position: Point, rdom: &mut RealDom, taffy: &Taffy) {
+        let Some(pan) = self.touch_pan.as_mut().filter(|pan| pan.id == id) else {
+            return;
+        };
+        let mut delta = position - pan.last;
+        pan.last = position;
+        if !pan.panning {
+            if (position - pan.start).hypot() <= system_settings().drag_threshold {
+                return;
+            }
+            pan.panning = true;
+            delta = position - pan.start;
+        }
+        let delta = Vec2::new(
+            if pan.action.pan_x { delta.x } else { 0.0 },
+            if pan.action.pan_y { delta.y } else { 0.0 },
+        );
+        if delta != Vec2::ZERO && scroll_at(rdom, taffy, &self.plugins, position, delta) {
+            self.scrolled = true;
+            self.gestures.cancel();
+        }
+    }
+
+    /// Recognize the gestures that are waiting for time to pass, like long presses.
+    pub(crate) fn poll_gestures(&mut self, rdom: &RealDom) {
+        self.gestures.poll(Instant::now());
+        self.dispatch_gestures(rdom);
+    }
+
+    /// True if custom elements animate, so the next frame has to be drawn
+    pub(crate) fn plugins_animating(&self) -> bool {
+        self.plugins.animating()
+    }
+
+    /// When [`BlitzEventHandler::poll_gestures`] has to be called next
+    pub(crate) fn gesture_deadline(&self) -> Option<Instant> {
+        self.gestures.deadline()
+    }
+
+    /// Handle input from the window after it went through the input filter.
+    fn input(&mut self, input: Input, rdom: &mut RealDom, taffy: &mut Taffy) {
+        let input = match &mut self.input_filter {
+            Some(filter) => match filter.filter(input, self.state.modifier_state) {
+                Some(input) => input,
+                None => return,
+            },
+            None => input,
+        };
+        let state = |pressed| match pressed {
+            true => tao::event::ElementState::Pressed,
+            false => tao::event::ElementState::Released,
+        };
+        match input {
+            Input::Key {
+                key,
+                code,
+                location,
+                pressed,
+                repeat,
+                has_text,
+            } => self.key_input(
+                key,
+                code,
+                location,
+                repeat,
+                state(pressed),
+                has_text,
+                rdom,
+                taffy,
+            ),
+            Input::Modifiers(modifiers) => self.state.modifier_state = modifiers,
+            Input::MouseButton { button, pressed } => {
+                self.mouse_input(state(pressed), button, rdom, taffy)
+            }
+            Input::Wheel { delta_x, delta_y } => {
+                let position = self.state.cursor_state.position.client();
+                let position = Point::new(position.x, position.y);
+                let delta = Vec2::new(delta_x, delta_y);
+                if scroll_at(rdom, taffy, &self.plugins, position, delta) {
+                    self.scrolled = true;
+                }
+            }
+        }
+    }
+
+    /// Replace the input filter, see [`crate::Config::with_input_filter`].
+    pub(crate) fn set_input_filter(&mut self, filter: Option<Box<dyn InputFilter>>) {
+        self.input_filter = filter;
+    }
+
+    /// Replace the dock layout, see [`crate::Config::with_docking`].
+    pub(crate) fn set_docking(&mut self, docking: Option<Docking>) {
+        self.docking = docking;
+    }
+
+    pub(crate) fn set_toasts(&mut self, toasts: Option<Toasts>) {
+        self.toasts = toasts;
+    }
+
+    /// Send the key events of a key that was pressed or released to the focused element. `has_text` is true if the key types text, which also sends a keypress event.
+    #[allow(clippy::too_many_arguments)]
+    fn key_input(
+        &mut self,
+        key: keyboard_types::Key,
+        code: Code,
+        location: input_data::keyboard_types::Location,
+        repeat: bool,
+        state: tao::event::ElementState,
+        has_text: bool,
+        rdom: &mut RealDom,
+        taffy: &mut Taffy,
+    ) {
+        let is_tab = key == keyboard_types::Key::Tab;
+        // A divider that has focus moves with the arrow keys, and still sends the key events
+        if let (tao::event::ElementState::Pressed, Some(focused)) =
+            (state, self.state.focus_state.last_focused_id)
+        {
+            let shift = self.state.modifier_state.contains(Modifiers::SHIFT);
+            if divider_key(rdom, taffy, focused, &key, shift) {
+                relayout(rdom, taffy);
+                if let Some(docking) = &self.docking {
+                    keep_split_sizes(rdom, taffy, focused, docking);
+                }
+                self.resized = true;
+            }
+        }
+        let data = Arc::new(EventData::Keyboard(KeyboardData::new(
+            key,
+            code,
+            location,
+            repeat,
+            self.state.modifier_state,
+        )));
+
+        // The text selected in the document is copied, unless an element is edited and has a selection of its own
+        if state == tao::event::ElementState::Pressed
+            && self.editing.is_none()
+            && self
+                .state
+                .modifier_state
+                .intersects(Modifiers::CONTROL | Modifiers::META)
+            && matches!(&key, keyboard_types::Key::Character(c) if c.eq_ignore_ascii_case("c"))
+        {
+            self.copy_selection(rdom, taffy, false);
+        }
+
+        // keypress events are only triggered when a key that has text is pressed
+        if let tao::event::ElementState::Pressed = state {
+            if has_text {
+                self.queued_events.push(DomEvent {
+                    name: "keypress",
+                    element: *rdom
+                        .get(rdom.root_id())
+                        .unwrap()
+                        .child_ids()
+                        .first()
+                        .unwrap(),
+                    data: data.clone(),
+                    bubbles: true,
+                });
+            }
+            if is_tab {
+                // self.state.focus_state.progress(
+                //     rdom,
+                //     !self.state.modifier_state.contains(Modifiers::SHIFT),
+                // );
+                return;
+            }
+        }
+
+        if let Some(element) = self.state.focus_state.last_focused_id {
+            self.queued_events.push(DomEvent {
+                element,
+                name: match state {
+                    tao::event::ElementState::Pressed => "keydown",
+                    tao::event::ElementState::Released => "keyup",
+                    _ => todo!(),
+                },
+                data,
+                bubbles: true,
+            });
+        }
+    }
+
+    /// Move the mouse to a point in client coordinates and send the enter and leave events of the elements under it.
+    fn cursor_moved(
+        &mut self,
+        pos: Point,
+        rdom: &RealDom,
+        taffy: &Taffy,
+        viewport_size: &Size<u32>,
+        quadtree: Option<&Quadtree<u64, NodeId>>,
+    ) {
+        let geometry = WindowGeometry::of(rdom);
+        let hovered = get_hovered(taffy, rdom, viewport_size, pos, quadtree);
+        self.gestures
+            .pointer_move(MOUSE_POINTER, pos, Instant::now());
+        self.dispatch_gestures(rdom);
+        let (mouse_x, mouse_y) = (pos.x as i32, pos.y as i32);
+        let client_point = ClientPoint::new(mouse_x as f64, mouse_y as f64);
+        let screen_point = geometry.client_to_screen(client_point);
+        let page_point = PagePoint::new(mouse_x as f64, mouse_y as f64);
+        // the position of the element is subtracted later
+        let element_point = ElementPoint::new(mouse_x as f64, mouse_y as f64);
+        let position = Coordinates::new(screen_point, client_point, element_point, page_point);
+
+        let data = MouseData::new(
+            Coordinates::new(screen_point, client_point, element_point, page_point),
+            None,
+            self.state.cursor_state.buttons,
+            self.state.modifier_state,
+        );
+        match (hovered, self.state.cursor_state.hovered) {
+            (Some(hovered), Some(old_hovered)) => {
+                if hovered != old_hovered {
+                    self.queued_events.push(DomEvent {
+                        element: hovered,
+                        name: "mouseenter",
+                        data: Arc::new(EventData::Mouse(data.clone())),
+                        bubbles: true,
+                    });
+                    self.queued_events.push(DomEvent {
+                        element: old_hovered,
+                        name: "mouseleave",
+                        data: Arc::new(EventData::Mouse(data)),
+                        bubbles: true,
+                    });
+                    self.state.cursor_state.hovered = Some(hovered);
+                }
+            }
+            (Some(hovered), None) => {
+                self.queued_events.push(DomEvent {
+                    element: hovered,
+                    name: "mouseenter",
+                    data: Arc::new(EventData::Mouse(data)),
+                    bubbles: true,
+                });
+                self.state.cursor_state.hovered = Some(hovered);
+            }
+            (None, Some(old_hovered)) => {
+                self.queued_events.push(DomEvent {
+                    element: old_hovered,
+                    name: "mouseleave",
+                    data: Arc::new(EventData::Mouse(data)),
+                    bubbles: true,
+                });
+                self.state.cursor_state.hovered = None;
+            }
+            (None, None) => (),
+        }
+        self.state.cursor_state.position = position;
+    }
+
+    /// Press or release a mouse button over the hovered element. Releasing the button over the element it was pressed on clicks it.
+    fn mouse_input(
+        &mut self,
+        state: tao::event::ElementState,
+        button: input_data::MouseButton,
+        rdom: &mut RealDom,
+        taffy: &Taffy,
+    ) {
+        if button == input_data::MouseButton::Primary {
+            let position = self.state.cursor_state.position.client();
+            // Dragging the grip of a resizable element resizes it instead of pressing it
+            match state {
+                tao::event::ElementState::Pressed => {
+                    // Toasts are over the document, so they get the button first
+                    let window_point = WindowGeometry::of(rdom).client_to_window(position);
+                    let window_point = epaint::pos2(window_point.x as f32, window_point.y as f32);
+                    if self
+                        .toasts
+                        .as_ref()
+                        .is_some_and(|toasts| toasts.press(window_point))
+                    {
+                        self.toast_pressed = true;
+                        return;
+                    }
+                    let point = Point::new(position.x, position.y);
+                    if let Some(drag) = ResizeDrag::start(rdom, taffy, point) {
+                        self.resizing = Some(drag);
+                        return;
+                    }
+                    // Scrollbars are drawn over the content, so they get the button before it
+                    if let Some(drag) = ScrollbarDrag::start(rdom, taffy, point) {
+                        self.scrollbar_drag = Some(drag);
+                        self.scrolled = true;
+                        return;
+                    }
+                    // Dividers are pressed like other elements, so they can take focus for the arrow keys
+                    self.splitting = self
+                        .state
+                        .cursor_state
+                        .hovered
+                        .and_then(|hovered| SplitDrag::start(rdom, taffy, hovered, point));
+                    // Tabs are pressed like other elements until they are dragged
+                    self.docking_drag = self
+                        .state
+                        .cursor_state
+                        .hovered
+                        .filter(|_| self.docking.is_some())
+                        .and_then(|hovered| DockDrag::start(rdom, hovered, point));
+                    // Pressing anywhere deselects the text, and dragging over text that isn't edited selects it
+                    if clear_selection(rdom) {
+                        self.repaint = true;
+                    }
+                    let selects_text = self.splitting.is_none()
+                        && self.docking_drag.is_none()
+                        && self
+                            .state
+                            .cursor_state
+                            .hovered
+                            .and_then(|hovered| rdom.get(hovered))
+                            .is_some_and(|hovered| {
+                                editable_value(hovered).is_none()
+                                    && self.plugins.for_node(hovered).is_none()
+                            });
+                    self.selecting = selects_text
+                        .then(|| SelectionDrag::start(rdom, taffy, point))
+                        .flatten();
+                }
+                _ => {
+                    if std::mem::take(&mut self.toast_pressed) {
+                        return;
+                    }
+                    if let (Some(drag), Some(docking)) = (self.splitting.take(), &self.docking) {
+                        keep_split_sizes(rdom, taffy, drag.divider(), docking);
+                    }
+                    if self.selecting.take().is_some_and(|drag| drag.dragged()) {
+                        self.copy_selection(rdom, taffy, true);
+                    }
+                    if self.resizing.take().is_some() || self.scrollbar_drag.take().is_some() {
+                        return;
+                    }
+                    if let (Some(drag), Some(docking)) = (self.docking_drag.take(), &self.docking) {
+                        if drag.finish(rdom, taffy, Point::new(position.x, position.y), docking) {
+                            // The preview is removed and the tab isn't clicked
+                            self.resized = true;
+                            return;
+                        }
+                    }
+                }
+            }
+            let phase = match state {
+                tao::event::ElementState::Pressed => PointerPhase::Down,
+                _ => PointerPhase::Up,
+            };
+            self.plugin_pointer(rdom, taffy, phase, Point::new(position.x, position.y));
+            self.pointer_input(
+                MOUSE_POINTER,
+                state,
+                Point::new(position.x, position.y),
+                self.state.cursor_state.hovered,
+                rdom,
+            );
+        }
+        if let Some(hovered) = self.state.cursor_state.hovered {
+            match state {
+                tao::event::ElementState::Pressed => {
+                    self.state.cursor_state.buttons |= button;
+                }
+                tao::event::ElementState::Released => {
+                    self.state.cursor_state.buttons.remove(button);
+                }
+                _ => todo!(),
+            }
+
+            let pos = &self.state.cursor_state.position;
+
+            let data = Arc::new(EventData::Mouse(MouseData::new(
+                Coordinates::new(pos.screen(), pos.client(), pos.element(), pos.page()),
+                None,
+                self.state.cursor_state.buttons,
+                self.state.modifier_state,
+            )));
+
+            let prevent_default = {
+                let hovered_node = rdom.get(hovered).unwrap();
+                let default = *hovered_node.get::<PreventDefault>().unwrap();
+                default
+            };
+            // Where the element was double clicked, which selects a word once the click focused it
+            let mut double_clicked = None;
+            match state {
+                tao::event::ElementState::Pressed => {
+                    self.queued_events.push(DomEvent {
+                        element: hovered,
+                        name: "mousedown",
+                        data,
+                        bubbles: true,
+                    });
+                    self.state.cursor_state.last_pressed_element = Some(hovered);
+                }
+                tao::event::ElementState::Released => {
+                    self.queued_events.push(DomEvent {
+                        element: hovered,
+                        name: "mouseup",
+                        data: data.clone(),
+                        bubbles: true,
+                    });
+
+                    if button == input_data::MouseButton::Auxiliary {
+                        self.paste_primary_selection(rdom, hovered);
+                    }
+
+                    // click events only trigger if the mouse button is pressed and released on the same element
+                    if self.state.cursor_state.last_pressed_element.take() == Some(hovered) {
+                        self.queued_events.push(DomEvent {
+                            element: hovered,
+                            name: "click",
+                            data: data.clone(),
+                            bubbles: true,
+                        });
+
+                        let position = self.state.cursor_state.position.client();
+                        if let Some((last_clicked, last_position)) =
+                            self.state.cursor_state.last_click.take()
+                        {
+                            let settings = system_settings();
+                            if self.state.cursor_state.last_clicked_element == Some(hovered)
+                                && last_clicked.elapsed() < settings.double_click_time
+                                && (position - last_position).length() <= settings.double_click_slop
+                            {
+                                self.queued_events.push(DomEvent {
+                                    element: hovered,
+                                    name: "dblclick",
+                                    data,
+                                    bubbles: true,
+                                });
+                                double_clicked = Some(Point::new(position.x, position.y));
+                            }
+                        }
+
+                        self.state.cursor_state.last_clicked_element = Some(hovered);
+                        self.state.cursor_state.last_click = Some((Instant::now(), position));
+                    }
+                }
+                _ => todo!(),
+            }
+            if prevent_default != PreventDefault::MouseUp
+                && rdom
+                    .get(hovered)
+                    .unwrap()
+                    .get::<Focus>()
+                    .unwrap()
+                    .level
+                    .focusable()
+            {
+                self.set_focus(rdom, hovered);
+            }
+            if let Some(point) = double_clicked {
+                self.select_word(rdom, taffy, hovered, point);
+            }
+        }
     }
 
     pub(crate) fn register_event(
         &mut self,
         event: &TaoEvent,
         rdom: &mut RealDom,
-        taffy: &Taffy,
+        taffy: &mut Taffy,
         viewport_size: &Size<u32>,
         quadtree: &Quadtree<u64, NodeId>,
     ) {
@@ -148,316 +920,252 @@ impl BlitzEventHandler {
                 window_id: _,
                 event,
                 ..
-            } => {
-                match event {
-                    tao::event::WindowEvent::Resized(_) => (),
-                    tao::event::WindowEvent::Moved(_) => (),
-                    tao::event::WindowEvent::CloseRequested => (),
-                    tao::event::WindowEvent::Destroyed => (),
-                    tao::event::WindowEvent::DroppedFile(_) => (),
-                    tao::event::WindowEvent::HoveredFile(_) => (),
-                    tao::event::WindowEvent::HoveredFileCancelled => (),
-                    tao::event::WindowEvent::ReceivedImeText(_) => (),
-                    tao::event::WindowEvent::Focused(_) => (),
-                    tao::event::WindowEvent::KeyboardInput {
-                        device_id: _,
-                        event,
-                        is_synthetic: _,
-                        ..
-                    } => {
-                        let key = map_key(&event.logical_key);
-                        let code = map_code(&event.physical_key);
-
-                        let data = Arc::new(EventData::Keyboard(KeyboardData::new(
-                            key,
-                            code,
-                            match event.location {
-                                tao::keyboard::KeyLocation::Standard => {
-                                    input_data::keyboard_types::Location::Standard
-                                }
-                                tao::keyboard::KeyLocation::Left => {
-                                    input_data::keyboard_types::Location::Left
-                                }
-                                tao::keyboard::KeyLocation::Right => {
-                                    input_data::keyboard_types::Location::Right
-                                }
-                                tao::keyboard::KeyLocation::Numpad => {
-                                    input_data::keyboard_types::Location::Numpad
-                                }
-                                _ => todo!(),
-                            },
-                            event.repeat,
-                            self.state.modifier_state,
-                        )));
-
-                        // keypress events are only triggered when a key that has text is pressed
-                        if let tao::event::ElementState::Pressed = event.state {
-                            if event.text.is_some() {
-                                self.queued_events.push(DomEvent {
-                                    name: "keypress",
-                                    element: *rdom
-                                        .get(rdom.root_id())
-                                        .unwrap()
-                                        .child_ids()
-                                        .first()
-                                        .unwrap(),
-                                    data: data.clone(),
-                                    bubbles: true,
-                                });
-                            }
-                            if let Key::Tab = event.logical_key {
-                                // self.state.focus_state.progress(
-                                //     rdom,
-                                //     !self.state.modifier_state.contains(Modifiers::SHIFT),
-                                // );
-                                return;
-                            }
-                        }
-
-                        if let Some(element) = self.state.focus_state.last_focused_id {
-                            self.queued_events.push(DomEvent {
-                                element,
-                                name: match event.state {
-                                    tao::event::ElementState::Pressed => "keydown",
-                                    tao::event::ElementState::Released => "keyup",
-                                    _ => todo!(),
-                                },
-                                data,
-                                bubbles: true,
-                            });
-                        }
+            } => match event {
+                tao::event::WindowEvent::Resized(_) => (),
+                tao::event::WindowEvent::Moved(_) => (),
+                tao::event::WindowEvent::CloseRequested => (),
+                tao::event::WindowEvent::Destroyed => (),
+                tao::event::WindowEvent::DroppedFile(_) => (),
+                tao::event::WindowEvent::HoveredFile(_) => (),
+                tao::event::WindowEvent::HoveredFileCancelled => (),
+                tao::event::WindowEvent::ReceivedImeText(_) => (),
+                tao::event::WindowEvent::Focused(false) => {
+                    // The mouse may be released outside of the window
+                    self.selecting = None;
+                    self.scrollbar_drag = None;
+                    if let Some(drag) = self.docking_drag.take() {
+                        drag.cancel(rdom);
+                        self.resized = true;
                     }
-                    tao::event::WindowEvent::ModifiersChanged(mods) => {
-                        let mut modifiers = Modifiers::empty();
-                        if mods.alt_key() {
-                            modifiers |= Modifiers::ALT;
+                }
+                tao::event::WindowEvent::Focused(_) => (),
+                tao::event::WindowEvent::KeyboardInput {
+                    device_id: _,
+                    event,
+                    is_synthetic: _,
+                    ..
+                } => {
+                    let location = match event.location {
+                        tao::keyboard::KeyLocation::Standard => {
+                            input_data::keyboard_types::Location::Standard
                         }
-                        if mods.control_key() {
-                            modifiers |= Modifiers::CONTROL;
+                        tao::keyboard::KeyLocation::Left => {
+                            input_data::keyboard_types::Location::Left
                         }
-                        if mods.super_key() {
-                            modifiers |= Modifiers::META;
+                        tao::keyboard::KeyLocation::Right => {
+                            input_data::keyboard_types::Location::Right
                         }
-                        if mods.shift_key() {
-                            modifiers |= Modifiers::SHIFT;
+                        tao::keyboard::KeyLocation::Numpad => {
+                            input_data::keyboard_types::Location::Numpad
                         }
-                        self.state.modifier_state = modifiers;
+                        _ => todo!(),
+                    };
+                    self.input(
+                        Input::Key {
+                            key: map_key(&event.logical_key),
+                            code: map_code(&event.physical_key),
+                            location,
+                            pressed: event.state == tao::event::ElementState::Pressed,
+                            repeat: event.repeat,
+                            has_text: event.text.is_some(),
+                        },
+                        rdom,
+                        taffy,
+                    );
+                }
+                tao::event::WindowEvent::ModifiersChanged(mods) => {
+                    let mut modifiers = Modifiers::empty();
+                    if mods.alt_key() {
+                        modifiers |= Modifiers::ALT;
                     }
-                    tao::event::WindowEvent::CursorMoved {
-                        device_id: _,
-                        position,
-                        ..
-                    } => {
-                        let pos = Point::new(position.x, position.y);
-                        let hovered = get_hovered(taffy, rdom, viewport_size, pos, quadtree);
-                        let (mouse_x, mouse_y) = (pos.x as i32, pos.y as i32);
-                        let screen_point = ScreenPoint::new(mouse_x as f64, mouse_y as f64);
-                        let client_point = ClientPoint::new(mouse_x as f64, mouse_y as f64);
-                        let page_point = PagePoint::new(mouse_x as f64, mouse_y as f64);
-                        // the position of the element is subtracted later
-                        let element_point = ElementPoint::new(mouse_x as f64, mouse_y as f64);
-                        let position =
-                            Coordinates::new(screen_point, client_point, element_point, page_point);
-
-                        let data = MouseData::new(
-                            Coordinates::new(screen_point, client_point, element_point, page_point),
-                            None,
-                            self.state.cursor_state.buttons,
-                            self.state.modifier_state,
-                        );
-                        match (hovered, self.state.cursor_state.hovered) {
-                            (Some(hovered), Some(old_hovered)) => {
-                                if hovered != old_hovered {
-                                    self.queued_events.push(DomEvent {
-                                        element: hovered,
-                                        name: "mouseenter",
-                                        data: Arc::new(EventData::Mouse(data.clone())),
-                                        bubbles: true,
-                                    });
-                                    self.queued_events.push(DomEvent {
-                                        element: old_hovered,
-                                        name: "mouseleave",
-                                        data: Arc::new(EventData::Mouse(data)),
-                                        bubbles: true,
-                                    });
-                                    self.state.cursor_state.hovered = Some(hovered);
-                                }
-                            }
-                            (Some(hovered), None) => {
-                                self.queued_events.push(DomEvent {
-                                    element: hovered,
-                                    name: "mouseenter",
-                                    data: Arc::new(EventData::Mouse(data)),
-                                    bubbles: true,
-                                });
-                                self.state.cursor_state.hovered = Some(hovered);
-                            }
-                            (None, Some(old_hovered)) => {
-                                self.queued_events.push(DomEvent {
-                                    element: old_hovered,
-                                    name: "mouseleave",
-                                    data: Arc::new(EventData::Mouse(data)),
-                                    bubbles: true,
-                                });
-                                self.state.cursor_state.hovered = None;
-                            }
-                            (None, None) => (),
+                    if mods.control_key() {
+                        modifiers |= Modifiers::CONTROL;
+                    }
+                    if mods.super_key() {
+                        modifiers |= Modifiers::META;
+                    }
+                    if mods.shift_key() {
+                        modifiers |= Modifiers::SHIFT;
+                    }
+                    self.input(Input::Modifiers(modifiers), rdom, taffy);
+                }
+                tao::event::WindowEvent::CursorMoved {
+                    device_id: _,
+                    position,
+                    ..
+                } => {
+                    let client_point = WindowGeometry::of(rdom).window_to_client(*position);
+                    let point = Point::new(client_point.x, client_point.y);
+                    if let Some(drag) = &self.splitting {
+                        drag.update(rdom, taffy, point);
+                        relayout(rdom, taffy);
+                        self.resized = true;
+                    }
+                    if let Some(drag) = &mut self.docking_drag {
+                        if drag.update(rdom, taffy, point) {
+                            self.resized = true;
                         }
-                        self.state.cursor_state.position = position;
                     }
-                    tao::event::WindowEvent::CursorEntered { device_id: _ } => {}
-                    tao::event::WindowEvent::CursorLeft { device_id: _ } => {
-                        if let Some(old_hovered) = self.state.cursor_state.hovered {
-                            self.queued_events.push(DomEvent {
-                                element: old_hovered,
-                                name: "mouseleave",
-                                data: Arc::new(EventData::Mouse(
-                                    self.state.cursor_state.get_event_mouse_data(),
-                                )),
-                                bubbles: true,
-                            });
-                            self.state.cursor_state.hovered = None;
+                    if let Some(drag) = &mut self.selecting {
+                        if drag.update(rdom, taffy, point) {
+                            self.repaint = true;
                         }
                     }
-                    tao::event::WindowEvent::MouseWheel {
-                        device_id: _,
-                        delta: _,
-                        phase: _,
-                        ..
-                    } => (),
-                    tao::event::WindowEvent::MouseInput {
-                        device_id: _,
-                        state,
-                        button,
-                        ..
-                    } => {
-                        if let Some(hovered) = self.state.cursor_state.hovered {
-                            let button = match button {
-                                MouseButton::Left => input_data::MouseButton::Primary,
-                                MouseButton::Middle => input_data::MouseButton::Auxiliary,
-                                MouseButton::Right => input_data::MouseButton::Secondary,
-                                MouseButton::Other(num) => match num {
-                                    4 => input_data::MouseButton::Fourth,
-                                    5 => input_data::MouseButton::Fifth,
-                                    _ => input_data::MouseButton::Unknown,
-                                },
-                                _ => input_data::MouseButton::Unknown,
-                            };
-
-                            match state {
-                                tao::event::ElementState::Pressed => {
-                                    self.state.cursor_state.buttons |= button;
-                                }
-                                tao::event::ElementState::Released => {
-                                    self.state.cursor_state.buttons.remove(button);
-                                }
-                                _ => todo!(),
-                            }
-
-                            let pos = &self.state.cursor_state.position;
-
-                            let data = Arc::new(EventData::Mouse(MouseData::new(
-                                Coordinates::new(
-                                    pos.screen(),
-                                    pos.client(),
-                                    pos.element(),
-                                    pos.page(),
-                                ),
-                                None,
-                                self.state.cursor_state.buttons,
-                                self.state.modifier_state,
-                            )));
-
-                            let prevent_default = {
-                                let hovered_node = rdom.get(hovered).unwrap();
-                                let default = *hovered_node.get::<PreventDefault>().unwrap();
-                                default
-                            };
-                            match state {
-                                tao::event::ElementState::Pressed => {
-                                    self.queued_events.push(DomEvent {
-                                        element: hovered,
-                                        name: "mousedown",
-                                        data,
-                                        bubbles: true,
-                                    });
-                                    self.state.cursor_state.last_pressed_element = Some(hovered);
+                    if let Some(drag) = &self.scrollbar_drag {
+                        if drag.update(rdom, taffy, point) {
+                            self.scrolled = true;
+                        }
+                    }
+                    if let Some(drag) = &self.resizing {
+                        drag.update(rdom, taffy, point);
+                        compute_root_layout(
+                            rdom,
+                            taffy,
+                            Size {
+                                width: viewport_size.width as f32,
+                                height: viewport_size.height as f32,
+                            },
+                        );
+                        self.resized = true;
+                    }
+                    self.cursor_moved(
+                        Point::new(client_point.x, client_point.y),
+                        rdom,
+                        taffy,
+                        viewport_size,
+                        Some(quadtree),
+                    );
+                    self.plugin_pointer(rdom, taffy, PointerPhase::Move, point);
+                }
+                tao::event::WindowEvent::CursorEntered { device_id: _ } => {}
+                tao::event::WindowEvent::CursorLeft { device_id: _ } => {
+                    if let Some(old_hovered) = self.state.cursor_state.hovered {
+                        self.queued_events.push(DomEvent {
+                            element: old_hovered,
+                            name: "mouseleave",
+                            data: Arc::new(EventData::Mouse(
+                                self.state.cursor_state.get_event_mouse_data(),
+                            )),
+                            bubbles: true,
+                        });
+                        self.state.cursor_state.hovered = None;
+                    }
+                }
+                tao::event::WindowEvent::MouseWheel {
+                    device_id: _,
+                    delta,
+                    phase: _,
+                    ..
+                } => {
+                    let delta = wheel_delta(delta);
+                    self.input(
+                        Input::Wheel {
+                            delta_x: delta.x,
+                            delta_y: delta.y,
+                        },
+                        rdom,
+                        taffy,
+                    );
+                }
+                tao::event::WindowEvent::MouseInput {
+                    device_id: _,
+                    state,
+                    button,
+                    ..
+                } => self.input(
+                    Input::MouseButton {
+                        button: map_button(*button),
+                        pressed: *state == tao::event::ElementState::Pressed,
+                    },
+                    rdom,
+                    taffy,
+                ),
+                tao::event::WindowEvent::TouchpadPressure {
+                    device_id: _,
+                    pressure: _,
+                    stage: _,
+                } => (),
+                tao::event::WindowEvent::AxisMotion {
+                    device_id: _,
+                    axis: _,
+                    value: _,
+                } => (),
+                tao::event::WindowEvent::Touch(touch) => {
+                    let client_point = WindowGeometry::of(rdom).window_to_client(touch.location);
+                    let position = Point::new(client_point.x, client_point.y);
+                    match touch.phase {
+                        tao::event::TouchPhase::Started => {
+                            let hovered =
+                                get_hovered(taffy, rdom, viewport_size, position, Some(quadtree));
+                            match &mut self.touch_pan {
+                                // A second touch pinches or rotates instead of scrolling
+                                Some(pan) => {
+                                    pan.action = TouchAction {
+                                        pan_x: false,
+                                        pan_y: false,
+                                    }
                                 }
-                                tao::event::ElementState::Released => {
-                                    self.queued_events.push(DomEvent {
-                                        element: hovered,
-                                        name: "mouseup",
-                                        data: data.clone(),
-                                        bubbles: true,
+                                None => {
+                                    let action = hovered
+                                        .and_then(|id| rdom.get(id))
+                                        .and_then(|node| {
+                                            node.get::<TouchAction>().map(|action| *action)
+                                        })
+                                        .unwrap_or_default();
+                                    self.touch_pan = Some(TouchPan {
+                                        id: touch.id,
+                                        start: position,
+                                        last: position,
+                                        action,
+                                        panning: false,
                                     });
-
-                                    // click events only trigger if the mouse button is pressed and released on the same element
-                                    if self.state.cursor_state.last_pressed_element.take()
-                                        == Some(hovered)
-                                    {
-                                        self.queued_events.push(DomEvent {
-                                            element: hovered,
-                                            name: "click",
-                                            data: data.clone(),
-                                            bubbles: true,
-                                        });
-
-                                        if let Some(last_clicked) =
-                                            self.state.cursor_state.last_click.take()
-                                        {
-                                            if self.state.cursor_state.last_clicked_element
-                                                == Some(hovered)
-                                                && last_clicked.elapsed() < DBL_CLICK_TIME
-                                            {
-                                                self.queued_events.push(DomEvent {
-                                                    element: hovered,
-                                                    name: "dblclick",
-                                                    data,
-                                                    bubbles: true,
-                                                });
-                                            }
-                                        }
-
-                                        self.state.cursor_state.last_clicked_element =
-                                            Some(hovered);
-                                        self.state.cursor_state.last_click = Some(Instant::now());
-                                    }
                                 }
-                                _ => todo!(),
                             }
-                            if prevent_default != PreventDefault::MouseUp
-                                && rdom
-                                    .get(hovered)
-                                    .unwrap()
-                                    .get::<Focus>()
-                                    .unwrap()
-                                    .level
-                                    .focusable()
+                            self.pointer_input(
+                                touch.id,
+                                tao::event::ElementState::Pressed,
+                                position,
+                                hovered,
+                                rdom,
+                            );
+                        }
+                        tao::event::TouchPhase::Moved => {
+                            self.pan_touch(touch.id, position, rdom, taffy);
+                            self.gestures
+                                .pointer_move(touch.id, position, Instant::now());
+                            self.dispatch_gestures(rdom);
+                        }
+                        tao::event::TouchPhase::Ended => {
+                            if self
+                                .touch_pan
+                                .as_ref()
+                                .is_some_and(|pan| pan.id == touch.id)
                             {
-                                self.state.focus_state.set_focus(rdom, hovered);
+                                self.touch_pan = None;
                             }
+                            self.pointer_input(
+                                touch.id,
+                                tao::event::ElementState::Released,
+                                position,
+                                None,
+                                rdom,
+                            )
+                        }
+                        _ => {
+                            self.touch_pan = None;
+                            self.gestures.cancel();
+                            self.dispatch_gestures(rdom);
                         }
                     }
-                    tao::event::WindowEvent::TouchpadPressure {
-                        device_id: _,
-                        pressure: _,
-                        stage: _,
-                    } => (),
-                    tao::event::WindowEvent::AxisMotion {
-                        device_id: _,
-                        axis: _,
-                        value: _,
-                    } => (),
-                    tao::event::WindowEvent::Touch(_) => (),
-                    tao::event::WindowEvent::ScaleFactorChanged {
-                        scale_factor: _,
-                        new_inner_size: _,
-                    } => (),
-                    tao::event::WindowEvent::ThemeChanged(_) => (),
-                    tao::event::WindowEvent::DecorationsClick => (),
-                    _ => (),
                 }
-            }
+                tao::event::WindowEvent::ScaleFactorChanged {
+                    scale_factor: _,
+                    new_inner_size: _,
+                } => (),
+                tao::event::WindowEvent::ThemeChanged(_) => (),
+                tao::event::WindowEvent::DecorationsClick => (),
+                _ => (),
+            },
             tao::event::Event::DeviceEvent {
                 device_id: _,
                 event: _,
@@ -486,6 +1194,106 @@ impl BlitzEventHandler {
         }
     }
 
+    /// Handle input from a [`crate::Session`] like input from the window. Returns an error if there is no element to click.
+    ///
+    /// Headless documents have no quadtree, so every element is checked for the mouse instead.
+    pub(crate) fn synthesize(
+        &mut self,
+        input: SyntheticInput,
+        rdom: &mut RealDom,
+        taffy: &mut Taffy,
+        viewport_size: &Size<u32>,
+        quadtree: Option<&Quadtree<u64, NodeId>>,
+    ) -> io::Result<()> {
+        let point = match input {
+            SyntheticInput::Click(selector) => query_selector(rdom, &selector)
+                .and_then(|element| element_center(rdom, taffy, element))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no element that was laid out matches {selector}"),
+                    )
+                })?,
+            SyntheticInput::ClickAt(point) => point,
+            SyntheticInput::Key(key) => {
+                let has_text = matches!(key, keyboard_types::Key::Character(_));
+                for state in [
+                    tao::event::ElementState::Pressed,
+                    tao::event::ElementState::Released,
+                ] {
+                    self.key_input(
+                        key.clone(),
+                        Code::Unidentified,
+                        input_data::keyboard_types::Location::Standard,
+                        false,
+                        state,
+                        has_text,
+                        rdom,
+                        taffy,
+                    );
+                }
+                return Ok(());
+            }
+            SyntheticInput::Text(text) => {
+                // The driver updates the value after the events are handled, so the typed characters are added up here
+                let mut typed = String::new();
+                for character in text.chars() {
+                    let key = keyboard_types::Key::Character(character.to_string());
+                    self.key_input(
+                        key.clone(),
+                        Code::Unidentified,
+                        input_data::keyboard_types::Location::Standard,
+                        false,
+                        tao::event::ElementState::Pressed,
+                        true,
+                        rdom,
+                        taffy,
+                    );
+                    typed.push(character);
+                    if let Some(element) = self.state.focus_state.last_focused_id {
+                        if let Some(value) = rdom.get(element).and_then(editable_value) {
+                            self.queued_events.push(DomEvent {
+                                element,
+                                name: "input",
+                                data: Arc::new(EventData::Form(FormData {
+                                    value: value + &typed,
+                                    values: Default::default(),
+                                    files: None,
+                                })),
+                                bubbles: true,
+                            });
+                        }
+                    }
+                    self.key_input(
+                        key,
+                        Code::Unidentified,
+                        input_data::keyboard_types::Location::Standard,
+                        false,
+                        tao::event::ElementState::Released,
+                        true,
+                        rdom,
+                        taffy,
+                    );
+                }
+                return Ok(());
+            }
+        };
+        self.cursor_moved(
+            Point::new(point.x, point.y),
+            rdom,
+            taffy,
+            viewport_size,
+            quadtree,
+        );
+        for state in [
+            tao::event::ElementState::Pressed,
+            tao::event::ElementState::Released,
+        ] {
+            self.mouse_input(state, input_data::MouseButton::Primary, rdom, taffy);
+        }
+        Ok(())
+    }
+
     pub fn drain_events(&mut self) -> Vec<DomEvent> {
         let mut events = Vec::new();
         std::mem::swap(&mut self.queued_events, &mut events);
@@ -493,6 +1301,79 @@ impl BlitzEventHandler {
     }
 }
 
+/// The innermost element from an element up that handles a gesture: one that listens for it, or a custom element whose plugin handles gestures, see [`crate::ElementPlugin::handles_gestures`].
+fn gesture_target(
+    rdom: &RealDom,
+    plugins: &Plugins,
+    element: NodeId,
+    name: &str,
+) -> Option<NodeId> {
+    let mut node = rdom.get(element);
+    while let Some(current) = node {
+        if let NodeType::Element(element) = &*current.node_type() {
+            let handled_by_plugin = plugins
+                .get(&element.tag)
+                .is_some_and(|plugin| plugin.handles_gestures());
+            if element.listeners.contains(name) || handled_by_plugin {
+                return Some(current.id());
+            }
+        }
+        node = current.parent();
+    }
+    None
+}
+
+/// The value of an element the user can type into, or `None` if the element isn't editable.
+pub(crate) fn editable_value(node: NodeRef) -> Option<String> {
+    let NodeType::Element(element) = &*node.node_type() else {
+        return None;
+    };
+    let attribute = |name: &str| {
+        element
+            .attributes
+            .iter()
+            .find(|(attribute, _)| attribute.name == name && attribute.namespace.is_none())
+            .map(|(_, value)| value)
+    };
+    let editable = match element.tag.as_str() {
+        "textarea" => true,
+        "input" => matches!(
+            attribute("type").and_then(|value| value.as_text()),
+            None | Some("text" | "search" | "url" | "tel" | "email" | "password" | "number")
+        ),
+        _ => false,
+    };
+    let disabled = ["disabled", "readonly"].into_iter().any(|name| {
+        // Dioxus sets boolean attributes to "false" instead of removing them
+        attribute(name).is_some_and(|value| {
+            !matches!(value, OwnedAttributeValue::Bool(false)) && value.as_text() != Some("false")
+        })
+    });
+    if !editable || disabled {
+        return None;
+    }
+    Some(
+        attribute("value")
+            .and_then(|value| value.as_text())
+            .unwrap_or_default()
+            .to_string(),
+    )
+}
+
+fn map_button(button: MouseButton) -> input_data::MouseButton {
+    match button {
+        MouseButton::Left => input_data::MouseButton::Primary,
+        MouseButton::Middle => input_data::MouseButton::Auxiliary,
+        MouseButton::Right => input_data::MouseButton::Secondary,
+        MouseButton::Other(num) => match num {
+            4 => input_data::MouseButton::Fourth,
+            5 => input_data::MouseButton::Fifth,
+            _ => input_data::MouseButton::Unknown,
+        },
+        _ => input_data::MouseButton::Unknown,
+    }
+}
+
 fn map_key(key: &tao::keyboard::Key) -> keyboard_types::Key {
     use tao::keyboard::Key::*;
     match key {
@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::Thread;
+
+/// Drives the DOM and the async tasks of the driver.
+///
+/// Blitz runs the DOM on its own thread and calls [`Executor::block_on`] on that thread with a future that completes when the window is closed. The future isn't `Send`, so it must be polled on the calling thread. Async work of the driver that is `Send`, like an event handler that fetches data, is passed to [`Executor::spawn`] through [`spawn`].
+pub trait Executor: Send + 'static {
+    fn block_on(&self, future: Pin<Box<dyn Future<Output = ()> + '_>>);
+
+    /// Run a future in the background until it completes. It may be polled on any thread.
+    fn spawn(&self, future: Pin<Box<dyn Future<Output = ()> + Send + 'static>>);
+}
+
+/// Runs the DOM on a tokio runtime. The runtime must be multi-threaded because the main thread is blocked by the event loop.
+impl Executor for tokio::runtime::Handle {
+    fn block_on(&self, future: Pin<Box<dyn Future<Output = ()> + '_>>) {
+        tokio::runtime::Handle::block_on(self, future)
+    }
+
+    fn spawn(&self, future: Pin<Box<dyn Future<Output = ()> + Send + 'static>>) {
+        // The task keeps running when its handle is dropped
+        tokio::runtime::Handle::spawn(self, future);
+    }
+}
+
+/// A minimal executor that polls the future on the current thread and sleeps until it is woken. Spawned futures are polled on a thread of their own.
+///
+/// This doesn't provide timers or IO, so futures that depend on a runtime like tokio won't work with it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BlockingExecutor;
+
+impl Executor for BlockingExecutor {
+    fn block_on(&self, future: Pin<Box<dyn Future<Output = ()> + '_>>) {
+        poll_until_ready(future);
+    }
+
+    fn spawn(&self, future: Pin<Box<dyn Future<Output = ()> + Send + 'static>>) {
+        std::thread::spawn(move || poll_until_ready(future));
+    }
+}
+
+fn poll_until_ready(mut future: Pin<Box<dyn Future<Output = ()> + '_>>) {
+    let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    while let Poll::Pending = future.as_mut().poll(&mut cx) {
+        std::thread::park();
+    }
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+thread_local! {
+    /// The executor of the DOM that runs on this thread
+    static CURRENT: RefCell<Option<Arc<dyn Executor>>> = RefCell::new(None);
+}
+
+/// Make the executor available to [`spawn`] on the thread that runs the DOM.
+pub(crate) fn set_current(executor: Arc<dyn Executor>) {
+    CURRENT.with(|current| *current.borrow_mut() = Some(executor));
+}
+
+/// Run a future in the background with the executor of the DOM, see [`crate::Config::with_executor`]. Without a custom executor, it runs on the tokio runtime Blitz creates for the DOM.
+///
+/// Call it from the driver, for example from an event handler of a Dioxus component, to start async work that doesn't need the DOM.
+///
+/// # Panics
+///
+/// Panics if it is called outside of the thread that runs the DOM.
+pub fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+    CURRENT.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .expect("blitz_core::spawn must be called from the thread that runs the DOM")
+            .spawn(Box::pin(future))
+    })
+}
@@ -0,0 +1,33 @@
+// Draws a layer that a filtered or translucent element was drawn into over the layer below it, with the colors multiplied by the matrix of its filters and then by its opacity
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+struct FilterConstants {
+    // Applied to premultiplied linear colors, with the alpha column adding a constant
+    color_matrix: mat4x4<f32>,
+    // The value CSS white is drawn with. The matrix is applied to colors where white is 1, so HDR frames are clamped at white like SDR frames
+    white_level: f32,
+    opacity: f32,
+};
+
+@group(0) @binding(1)
+var<uniform> constants: FilterConstants;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // A triangle that covers the whole target
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // The layer has the size of the target, so each fragment reads the texel under it
+    let texel = textureLoad(source, vec2<i32>(position.xy), 0);
+    let unscaled = vec4<f32>(texel.rgb / constants.white_level, texel.a);
+    let color = clamp(constants.color_matrix * unscaled, vec4<f32>(0.0), vec4<f32>(1.0));
+    // Premultiplied colors can't be brighter than their alpha
+    let clamped = vec4<f32>(min(color.rgb, vec3<f32>(color.a)) * constants.white_level, color.a);
+    return clamped * constants.opacity;
+}
@@ -260,7 +260,6 @@ impl FocusState {
         }
     }
 
-    #[allow(unused)]
     pub(crate) fn set_focus(&mut self, rdom: &mut RealDom, id: NodeId) {
         if let Some(old) = self.last_focused_id.replace(id) {
             rdom.get_mut(old).unwrap().insert(Focused(false));
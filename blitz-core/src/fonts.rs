@@ -0,0 +1,1158 @@
+use std::borrow::Cow;
+use std::io;
+use std::ops::RangeInclusive;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use epaint::Color32;
+use lightningcss::properties::font::{AbsoluteFontWeight, FontWeight};
+use lightningcss::rules::font_face::{self, FontFaceProperty, Source};
+use lightningcss::rules::CssRule;
+use lightningcss::stylesheet::{ParserOptions, PrinterOptions, StyleSheet};
+use lightningcss::traits::ToCss;
+use once_cell::sync::Lazy;
+use rustc_hash::{FxHashMap, FxHashSet};
+use tao::event_loop::EventLoopProxy;
+use unicode_script::{Script, UnicodeScript};
+
+use crate::{woff, Redraw};
+
+/// A font face that text is shaped and rasterized with
+pub(crate) struct Face {
+    pub shaper: rustybuzz::Face<'static>,
+    pub outlines: ab_glyph::FontRef<'static>,
+    /// True if the face has color bitmaps or color layers of its glyphs, like emoji fonts
+    pub color: bool,
+    /// The color layers of the glyphs of a face with a COLR table
+    pub layers: Option<ColorLayers>,
+}
+
+impl Face {
+    fn new(data: &'static [u8], index: u32) -> Option<Self> {
+        let shaper = rustybuzz::Face::from_slice(data, index)?;
+        let tables = shaper.tables();
+        let layers = ColorLayers::parse(&shaper);
+        let color = tables.cbdt.is_some() || tables.sbix.is_some() || layers.is_some();
+        Some(Self {
+            shaper,
+            outlines: ab_glyph::FontRef::try_from_slice_and_index(data, index).ok()?,
+            color,
+            layers,
+        })
+    }
+
+    /// The number of pixels per font unit at a font size
+    pub fn scale(&self, font_size: f32) -> f32 {
+        font_size / self.shaper.units_per_em() as f32
+    }
+
+    pub fn has_glyph(&self, character: char) -> bool {
+        self.shaper.glyph_index(character).is_some()
+    }
+}
+
+/// The layers of the glyphs of a COLR table, version 0, with the colors of the first palette of the CPAL table. Each layer is the outline of another glyph filled with one color. The gradients of version 1 aren't read, so glyphs that only have those are drawn from their outlines.
+pub(crate) struct ColorLayers {
+    colr: &'static [u8],
+    /// The colors of the first palette
+    palette: Vec<Color32>,
+}
+
+/// The palette index of a layer that is filled with the color of the text
+const FOREGROUND_PALETTE_INDEX: u16 = 0xffff;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+impl ColorLayers {
+    fn parse(face: &rustybuzz::Face<'static>) -> Option<Self> {
+        let raw = face.raw_face();
+        let colr = raw.table(rustybuzz::ttf_parser::Tag::from_bytes(b"COLR"))?;
+        let cpal = raw.table(rustybuzz::ttf_parser::Tag::from_bytes(b"CPAL"))?;
+        // The colors of the first palette start at the index of its first color record. Records are stored as BGRA
+        let entries = read_u16(cpal, 2)? as usize;
+        let records = read_u32(cpal, 8)? as usize;
+        let first = read_u16(cpal, 12)? as usize;
+        let palette = (first..first + entries)
+            .map(|index| {
+                let offset = records + index * 4;
+                let [b, g, r, a]: [u8; 4] = cpal.get(offset..offset + 4)?.try_into().ok()?;
+                Some(Color32::from_rgba_unmultiplied(r, g, b, a))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        // A table without base glyphs only has the paints of version 1
+        (read_u16(colr, 2)? > 0).then_some(Self { colr, palette })
+    }
+
+    /// The glyphs a glyph is painted with from the bottom to the top, with the colors they are filled with. `None` is the color of the text. Returns `None` if the glyph has no layers.
+    pub fn of(&self, glyph: u16) -> Option<Vec<(u16, Option<Color32>)>> {
+        let colr = self.colr;
+        let base_glyphs = read_u16(colr, 2)? as usize;
+        let base_records = read_u32(colr, 4)? as usize;
+        let layer_records = read_u32(colr, 8)? as usize;
+        let layer_count = read_u16(colr, 12)? as usize;
+        // Base glyph records are sorted by glyph id and are 6 bytes long
+        let (mut low, mut high) = (0, base_glyphs);
+        let record = loop {
+            if low >= high {
+                return None;
+            }
+            let middle = (low + high) / 2;
+            let record = base_records + middle * 6;
+            match read_u16(colr, record)?.cmp(&glyph) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => break record,
+            }
+        };
+        let first = read_u16(colr, record + 2)? as usize;
+        let count = read_u16(colr, record + 4)? as usize;
+        if first + count > layer_count || count == 0 {
+            return None;
+        }
+        // Layer records are 4 bytes long, the glyph and the index of its color
+        (first..first + count)
+            .map(|index| {
+                let layer = layer_records + index * 4;
+                let glyph = read_u16(colr, layer)?;
+                let color = match read_u16(colr, layer + 2)? {
+                    FOREGROUND_PALETTE_INDEX => None,
+                    index => Some(*self.palette.get(index as usize)?),
+                };
+                Some((glyph, color))
+            })
+            .collect()
+    }
+}
+
+/// A font epaint ships with.
+fn epaint_face(name: &str) -> Face {
+    let definitions = epaint::text::FontDefinitions::default();
+    let font = &definitions.font_data[name];
+    let data: &'static [u8] = match &font.font {
+        Cow::Borrowed(data) => data,
+        Cow::Owned(data) => Box::leak(data.clone().into_boxed_slice()),
+    };
+    Face::new(data, font.index).expect("the fonts of epaint are valid")
+}
+
+/// The face text is drawn with if none of its families are loaded, which is the proportional font epaint ships with.
+static DEFAULT_FACE: Lazy<Face> = Lazy::new(|| epaint_face("Ubuntu-Light"));
+
+/// The emoji and icon fonts epaint ships with, which characters fall back to after the families of the [`FontFallback`] and the default face
+static BUILTIN_FALLBACK_FACES: Lazy<[Face; 2]> = Lazy::new(|| {
+    [
+        epaint_face("NotoEmoji-Regular"),
+        epaint_face("emoji-icon-font"),
+    ]
+});
+
+/// The ids of the default face and the built in fallback faces, which are the first faces
+const BUILTIN_FACES: std::ops::Range<u16> = 0..3;
+
+/// The fonts of every document. Faces are never unloaded, so text that was shaped with a face can always be drawn with it.
+static FONTS: Lazy<Mutex<Fonts>> = Lazy::new(|| {
+    Mutex::new(Fonts {
+        faces: std::iter::once(&*DEFAULT_FACE)
+            .chain(BUILTIN_FALLBACK_FACES.iter())
+            .collect(),
+        families: FxHashMap::default(),
+        sources: FxHashMap::default(),
+        fallback: FontFallback::default(),
+        generation: 0,
+        changes: Vec::new(),
+        proxies: Vec::new(),
+    })
+});
+
+struct Fonts {
+    /// The faces by id. The default face is 0
+    faces: Vec<&'static Face>,
+    /// The faces of each family by its name in lowercase, in the order they were declared
+    families: FxHashMap<String, Vec<FamilyFace>>,
+    /// The face loaded from each source, so a file or url is only loaded once
+    sources: FxHashMap<String, u16>,
+    fallback: FontFallback,
+    /// Incremented with every change to how text with a family looks
+    generation: u64,
+    /// The family that changed in each generation, so text that depends on it can be measured again, or `None` if the fallback families changed. The oldest change is first
+    changes: Vec<(u64, Option<String>)>,
+    /// Wakes up the windows to lay out and draw the text again when a font loads
+    proxies: Vec<EventLoopProxy<Redraw>>,
+}
+
+/// A face of a family and the descriptors text is matched with
+struct FamilyFace {
+    descriptors: Descriptors,
+    display: FontDisplay,
+    state: FaceState,
+}
+
+/// What a face is used for. A face that is declared again with the same descriptors replaces the old one
+#[derive(Clone, PartialEq, Debug)]
+struct Descriptors {
+    weight: RangeInclusive<u16>,
+    style: FontStyle,
+    /// The characters the face has glyphs for, or every character if it is empty
+    unicode_range: Vec<RangeInclusive<u32>>,
+}
+
+impl Default for Descriptors {
+    fn default() -> Self {
+        Self {
+            weight: 400..=400,
+            style: FontStyle::Normal,
+            unicode_range: Vec::new(),
+        }
+    }
+}
+
+impl Descriptors {
+    fn covers(&self, character: char) -> bool {
+        self.unicode_range.is_empty()
+            || self
+                .unicode_range
+                .iter()
+                .any(|range| range.contains(&(character as u32)))
+    }
+}
+
+enum FaceState {
+    /// The face is loaded from the first of its sources that loads once text uses it
+    Declared(Vec<String>),
+    Loading {
+        started: Instant,
+        /// Text with the face is invisible until the block period is over
+        blocking: bool,
+        /// The face if it was loaded before, which is used until the new font loads
+        previous: Option<u16>,
+    },
+    Loaded(u16),
+    /// The font failed to load or loaded after its swap period, so text stays in the fallback font
+    Unavailable,
+}
+
+/// What text with a family looks like while its font loads, like the `font-display` descriptor of CSS.
+///
+/// Text is invisible during the block period of the font, so it doesn't flash in the fallback font when the font loads quickly. It is drawn in the fallback font after that, and the font is swapped in if it loads before the swap period is over. Text takes the space of the fallback font while it is invisible, so the layout doesn't change when it appears.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FontDisplay {
+    /// A short block period and an infinite swap period
+    #[default]
+    Block,
+    /// No block period and an infinite swap period
+    Swap,
+    /// A very short block period and a short swap period
+    Fallback,
+    /// A very short block period and no swap period. The font is only used if it loads almost immediately, otherwise the next documents can use it once it is cached
+    Optional,
+}
+
+impl FontDisplay {
+    /// How long text is invisible after the font started loading
+    fn block_period(self) -> Duration {
+        match self {
+            FontDisplay::Block => Duration::from_secs(3),
+            FontDisplay::Swap => Duration::ZERO,
+            FontDisplay::Fallback | FontDisplay::Optional => Duration::from_millis(100),
+        }
+    }
+
+    /// How long after the block period the font is still swapped in when it loads, or `None` if it always is
+    fn swap_period(self) -> Option<Duration> {
+        match self {
+            FontDisplay::Block | FontDisplay::Swap => None,
+            FontDisplay::Fallback => Some(Duration::from_secs(3)),
+            FontDisplay::Optional => Some(Duration::ZERO),
+        }
+    }
+}
+
+/// The slant of a face or of text, like `font-style`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FontStyle {
+    #[default]
+    Normal,
+    Italic,
+    Oblique,
+}
+
+impl FontStyle {
+    /// The styles of faces in the order they are used for text with this style, like the font matching of CSS
+    fn preference(self) -> [FontStyle; 3] {
+        match self {
+            FontStyle::Normal => [FontStyle::Normal, FontStyle::Oblique, FontStyle::Italic],
+            FontStyle::Italic => [FontStyle::Italic, FontStyle::Oblique, FontStyle::Normal],
+            FontStyle::Oblique => [FontStyle::Oblique, FontStyle::Italic, FontStyle::Normal],
+        }
+    }
+}
+
+/// A face of a font family, like an `@font-face` rule. Text with the family is shaped with the face whose descriptors match its `font-weight`, `font-style` and characters best.
+///
+/// ```no_run
+/// # use blitz_core::{FontDisplay, FontFace, FontStyle};
+/// FontFace::new("Inter")
+///     .with_src("https://example.com/inter-italic.ttf")
+///     .with_weight(100, 900)
+///     .with_style(FontStyle::Italic)
+///     .with_display(FontDisplay::Swap)
+///     .declare();
+/// ```
+#[derive(Clone, Debug)]
+pub struct FontFace {
+    family: String,
+    sources: Vec<String>,
+    descriptors: Descriptors,
+    display: FontDisplay,
+}
+
+impl FontFace {
+    /// A face of a family for the normal weight and style and every character. Family names are matched with the `font-family` style without regard to ASCII case.
+    pub fn new(family: &str) -> Self {
+        Self {
+            family: family.trim().to_ascii_lowercase(),
+            sources: Vec::new(),
+            descriptors: Descriptors::default(),
+            display: FontDisplay::default(),
+        }
+    }
+
+    /// Load the font from a TrueType, OpenType or WOFF font file. Sources are paths relative to the working directory of the application, `file://` urls, or `http://` and `https://` urls if Blitz is built with the `http` feature. When a source fails to load, the next one is tried. WOFF2 fonts aren't supported and are skipped.
+    pub fn with_src(mut self, src: impl Into<String>) -> Self {
+        self.sources.push(src.into());
+        self
+    }
+
+    /// Load the font from a font installed on the system with a full name or PostScript name, like `local()`. It is tried in order with the other sources.
+    pub fn with_local(mut self, name: &str) -> Self {
+        self.sources.push(format!("local({})", name.trim()));
+        self
+    }
+
+    /// The weights the face is used for. A variable font covers a range of weights, a static font has the same minimum and maximum.
+    pub fn with_weight(mut self, min: u16, max: u16) -> Self {
+        self.descriptors.weight = min.min(max)..=max.max(min);
+        self
+    }
+
+    pub fn with_style(mut self, style: FontStyle) -> Self {
+        self.descriptors.style = style;
+        self
+    }
+
+    /// Use the face only for a range of code points, like `unicode-range`. A face with ranges is only loaded once text uses one of their characters, so a large font can be split into faces that are loaded as they are needed.
+    pub fn with_unicode_range(mut self, start: u32, end: u32) -> Self {
+        self.descriptors.unicode_range.push(start..=end);
+        self
+    }
+
+    pub fn with_display(mut self, display: FontDisplay) -> Self {
+        self.display = display;
+        self
+    }
+
+    /// Add the face to its family. Its font is loaded in the background from its sources the first time text is shaped with it, and text is shown as the [`FontDisplay`] policy says until then.
+    ///
+    /// A face that was loaded from one of the sources before is used right away.
+    pub fn declare(self) {
+        let mut fonts = fonts();
+        let loaded = self
+            .sources
+            .iter()
+            .find_map(|src| fonts.sources.get(src).copied());
+        let state = match loaded {
+            Some(face) => FaceState::Loaded(face),
+            None => FaceState::Declared(self.sources),
+        };
+        fonts.insert(&self.family, self.descriptors, self.display, state);
+        fonts.changed(&self.family);
+    }
+
+    /// Add the face with the data of a TrueType or OpenType font file, which is used right away, see [`register_font`]. Its sources aren't used.
+    pub fn register(self, data: impl Into<Vec<u8>>) -> io::Result<()> {
+        self.with_display(FontDisplay::Swap)
+            .load()
+            .finish(data.into())
+    }
+
+    /// Start loading the font of the face with data from elsewhere, for example a font that is embedded in the application or fetched by it, see [`load_font`]. Its sources aren't used.
+    pub fn load(self) -> FontLoad {
+        let mut fonts = fonts();
+        let previous = fonts
+            .families
+            .get(&self.family)
+            .and_then(|faces| {
+                faces
+                    .iter()
+                    .find(|face| face.descriptors == self.descriptors)
+            })
+            .and_then(|face| match face.state {
+                FaceState::Loaded(face) => Some(face),
+                FaceState::Loading { previous, .. } => previous,
+                _ => None,
+            });
+        // Text with a face that was loaded before doesn't disappear
+        let blocking = previous.is_none() && !self.display.block_period().is_zero();
+        let state = FaceState::Loading {
+            started: Instant::now(),
+            blocking,
+            previous,
+        };
+        fonts.insert(&self.family, self.descriptors.clone(), self.display, state);
+        fonts.changed(&self.family);
+        FontLoad {
+            face: Some((self.family, self.descriptors)),
+            source: None,
+            index: 0,
+        }
+    }
+}
+
+/// Add a font to a family with the data of a TrueType or OpenType font file, for example a font that is embedded in the application with `include_bytes!`. Text with the family is drawn with it from the next frame on, without a block period.
+///
+/// Returns an error if the data isn't a valid font. Use [`FontFace::register`] to register the faces of a family with different weights or styles.
+pub fn register_font(family: &str, data: impl Into<Vec<u8>>) -> io::Result<()> {
+    FontFace::new(family).register(data)
+}
+
+/// Start loading the font of a family, for example while it is downloaded or read from disk. Text with the family is shown with the fallback font as the [`FontDisplay`] policy says until the font is passed to [`FontLoad::finish`], then every window measures, lays out and draws the text again with the font.
+///
+/// Family names are matched with the `font-family` style without regard to ASCII case. Loading a family that was loaded before keeps the old font until the new one loads. The font is used for every weight, style and character of the family, unless other faces of it are declared with [`FontFace`].
+pub fn load_font(family: &str, display: FontDisplay) -> FontLoad {
+    FontFace::new(family).with_display(display).load()
+}
+
+/// Declare the faces of the `@font-face` rules of a style sheet, see [`FontFace::declare`]. The `src`, `font-family`, `font-weight`, `font-style`, `unicode-range` and `font-display` descriptors are supported. Other rules are ignored.
+///
+/// `local()` sources are loaded from the fonts installed on the system, see [`FontFace::with_local`]. Sources in a format that can't be loaded, like WOFF2, are skipped with a warning. Returns an error if the style sheet can't be parsed. Rules without a family or a source that can be loaded are skipped with a warning.
+pub fn load_font_faces(css: &str) -> io::Result<()> {
+    let stylesheet = StyleSheet::parse(css, ParserOptions::default())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+    for rule in &stylesheet.rules.0 {
+        let CssRule::FontFace(rule) = rule else {
+            continue;
+        };
+        let mut family = None;
+        let mut sources = Vec::new();
+        let mut descriptors = Descriptors::default();
+        let mut display = FontDisplay::default();
+        for property in &rule.properties {
+            match property {
+                FontFaceProperty::Source(sources_list) => {
+                    sources = sources_list
+                        .iter()
+                        .filter_map(|source| match source {
+                            Source::Url(url) if loadable_format(url.format.as_ref()) => {
+                                Some(url.url.url.to_string())
+                            }
+                            Source::Url(url) => {
+                                log::warn!(
+                                    "skipping {} in @font-face, its format isn't supported",
+                                    url.url.url
+                                );
+                                None
+                            }
+                            Source::Local(name) => {
+                                let name = name.to_css_string(PrinterOptions::default()).ok()?;
+                                Some(format!("local({})", name.trim_matches(['"', '\''])))
+                            }
+                        })
+                        .collect()
+                }
+                FontFaceProperty::FontWeight(weight) => {
+                    let [min, max] = [&weight.0, &weight.1].map(|weight| match weight {
+                        FontWeight::Absolute(AbsoluteFontWeight::Weight(weight)) => {
+                            weight.clamp(1.0, 1000.0) as u16
+                        }
+                        FontWeight::Absolute(AbsoluteFontWeight::Bold) => 700,
+                        _ => 400,
+                    });
+                    descriptors.weight = min.min(max)..=max.max(min);
+                }
+                FontFaceProperty::FontStyle(style) => {
+                    descriptors.style = match style {
+                        font_face::FontStyle::Normal => FontStyle::Normal,
+                        font_face::FontStyle::Italic => FontStyle::Italic,
+                        font_face::FontStyle::Oblique(_) => FontStyle::Oblique,
+                    }
+                }
+                FontFaceProperty::UnicodeRange(ranges) => {
+                    descriptors.unicode_range =
+                        ranges.iter().map(|range| range.start..=range.end).collect()
+                }
+                // The family and font-display are matched by their text, which is simpler than their typed values
+                property => {
+                    let Ok(declaration) = property.to_css_string(PrinterOptions::default()) else {
+                        continue;
+                    };
+                    let Some((name, value)) = declaration.split_once(':') else {
+                        continue;
+                    };
+                    match name.trim() {
+                        "font-family" => {
+                            family = Some(value.trim().trim_matches(['"', '\'']).to_string())
+                        }
+                        "font-display" => match value.trim() {
+                            "block" | "auto" => display = FontDisplay::Block,
+                            "swap" => display = FontDisplay::Swap,
+                            "fallback" => display = FontDisplay::Fallback,
+                            "optional" => display = FontDisplay::Optional,
+                            value => log::warn!("unsupported font-display in @font-face: {value}"),
+                        },
+                        _ => {}
+                    }
+                }
+            }
+        }
+        let Some(family) = family else {
+            log::warn!("skipping an @font-face rule without a font-family");
+            continue;
+        };
+        if sources.is_empty() {
+            log::warn!(
+                "skipping the @font-face rule of {family}, it has no source that can be loaded"
+            );
+            continue;
+        }
+        FontFace {
+            family: family.to_ascii_lowercase(),
+            sources,
+            descriptors,
+            display,
+        }
+        .declare();
+    }
+    Ok(())
+}
+
+/// Register the fonts and declare the `@font-face` rules of a [`crate::Config`] when a window or document is created. Fonts that fail are skipped with an error.
+pub(crate) fn register_config_fonts(fonts: Vec<(FontFace, Vec<u8>)>, font_faces: Vec<String>) {
+    for (face, data) in fonts {
+        let family = face.family.clone();
+        if let Err(err) = face.register(data) {
+            log::error!("failed to register a font of {family}: {err}");
+        }
+    }
+    for css in font_faces {
+        if let Err(err) = load_font_faces(&css) {
+            log::error!("failed to parse the @font-face rules of the config: {err}");
+        }
+    }
+}
+
+/// Whether a font in a format can be loaded. Sources without a format are tried
+fn loadable_format(format: Option<&font_face::FontFormat>) -> bool {
+    matches!(
+        format,
+        None | Some(
+            font_face::FontFormat::TrueType
+                | font_face::FontFormat::OpenType
+                | font_face::FontFormat::Collection
+                | font_face::FontFormat::WOFF
+        )
+    )
+}
+
+/// A font that is loading, from [`load_font`] or [`FontFace::load`]. The font fails to load if this is dropped before it finishes.
+#[must_use = "the font fails to load when the load is dropped"]
+pub struct FontLoad {
+    face: Option<(String, Descriptors)>,
+    /// The source the font is loaded from, so other faces with the source reuse it
+    source: Option<String>,
+    /// The index of the face in a font collection
+    index: u32,
+}
+
+impl FontLoad {
+    /// Finish loading the font with the data of a TrueType, OpenType or WOFF font file, and swap it in if its swap period isn't over.
+    ///
+    /// Returns an error if the data isn't a valid font or is a WOFF2 font, the text stays in the fallback font then.
+    pub fn finish(mut self, data: Vec<u8>) -> io::Result<()> {
+        let (family, descriptors) = self.face.take().unwrap();
+        let data = match woff::to_sfnt(data) {
+            Ok(data) => data,
+            Err(err) => {
+                fonts().fail(&family, &descriptors);
+                return Err(err);
+            }
+        };
+        let data: &'static [u8] = Box::leak(data.into_boxed_slice());
+        let Some(face) = Face::new(data, self.index) else {
+            fonts().fail(&family, &descriptors);
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the font of {family} isn't a valid font"),
+            ));
+        };
+        let mut fonts = fonts();
+        let id = fonts.faces.len() as u16;
+        fonts.faces.push(Box::leak(Box::new(face)));
+        if let Some(source) = self.source.take() {
+            fonts.sources.insert(source, id);
+        }
+        let Some(family_face) = fonts.face_mut(&family, &descriptors) else {
+            return Ok(());
+        };
+        let FaceState::Loading { started, .. } = family_face.state else {
+            return Ok(());
+        };
+        let display = family_face.display;
+        let in_time = display.swap_period().map_or(true, |swap_period| {
+            started.elapsed() <= display.block_period() + swap_period
+        });
+        if !in_time {
+            log::info!("the font of {family} loaded after its swap period, it isn't used");
+            fonts.fail(&family, &descriptors);
+            return Ok(());
+        }
+        family_face.state = FaceState::Loaded(id);
+        fonts.changed(&family);
+        Ok(())
+    }
+
+    /// Give up loading the font, for example when the download failed. Text stays in the fallback font.
+    pub fn fail(mut self) {
+        if let Some((family, descriptors)) = self.face.take() {
+            fonts().fail(&family, &descriptors);
+        }
+    }
+}
+
+impl Drop for FontLoad {
+    fn drop(&mut self) {
+        if let Some((family, descriptors)) = self.face.take() {
+            fonts().fail(&family, &descriptors);
+        }
+    }
+}
+
+/// Load the font of a declared face from the first of its sources that is a valid font, on a thread of its own.
+fn load_sources(mut load: FontLoad, sources: Vec<String>) {
+    std::thread::spawn(move || {
+        for src in sources {
+            match read_source(&src) {
+                Ok((data, index)) if rustybuzz::Face::from_slice(&data, index).is_some() => {
+                    load.source = Some(src);
+                    load.index = index;
+                    // The data was checked, so this only fails if the face was declared again
+                    let _ = load.finish(data);
+                    return;
+                }
+                Ok(_) => log::warn!("{src} isn't a valid font"),
+                Err(err) => log::warn!("failed to load the font {src}: {err}"),
+            }
+        }
+        load.fail();
+    });
+}
+
+/// Read a font file from a path, a `file://` url, an `http://` or `https://` url, or a `local()` font installed on the system. Returns the data as a TrueType or OpenType font with the index of the face in it.
+fn read_source(src: &str) -> io::Result<(Vec<u8>, u32)> {
+    if let Some(name) = src
+        .strip_prefix("local(")
+        .and_then(|name| name.strip_suffix(')'))
+    {
+        return read_local(name);
+    }
+    let data = if src.starts_with("http://") || src.starts_with("https://") {
+        fetch(src)?
+    } else {
+        std::fs::read(src.strip_prefix("file://").unwrap_or(src))?
+    };
+    Ok((woff::to_sfnt(data)?, 0))
+}
+
+/// The fonts installed on the system, which `local()` sources are loaded from. They are only listed once the first one is loaded
+static SYSTEM_FONTS: Lazy<fontdb::Database> = Lazy::new(|| {
+    let mut database = fontdb::Database::new();
+    database.load_system_fonts();
+    database
+});
+
+/// Read a font installed on the system with a full name or PostScript name, like CSS matches `local()`.
+fn read_local(name: &str) -> io::Result<(Vec<u8>, u32)> {
+    let matches = |data: &[u8], index: u32| {
+        let Ok(face) = rustybuzz::ttf_parser::Face::parse(data, index) else {
+            return false;
+        };
+        face.names().into_iter().any(|entry| {
+            entry.name_id == rustybuzz::ttf_parser::name_id::FULL_NAME
+                && entry
+                    .to_string()
+                    .is_some_and(|full_name| full_name.eq_ignore_ascii_case(name))
+        })
+    };
+    SYSTEM_FONTS
+        .faces()
+        .find_map(|info| {
+            let by_postscript_name = info.post_script_name.eq_ignore_ascii_case(name);
+            SYSTEM_FONTS.with_face_data(info.id, |data, index| {
+                (by_postscript_name || matches(data, index)).then(|| (data.to_vec(), index))
+            })?
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no font named {name} is installed"),
+            )
+        })
+}
+
+#[cfg(feature = "http")]
+fn fetch(url: &str) -> io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let response = ureq::get(url)
+        .call()
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+    let mut data = Vec::new();
+    response.into_reader().read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch(url: &str) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{url} can't be loaded without the http feature"),
+    ))
+}
+
+impl Fonts {
+    /// Add a face to a family, replacing the face with the same descriptors
+    fn insert(
+        &mut self,
+        family: &str,
+        descriptors: Descriptors,
+        display: FontDisplay,
+        state: FaceState,
+    ) {
+        let face = FamilyFace {
+            descriptors,
+            display,
+            state,
+        };
+        let faces = self.families.entry(family.to_string()).or_default();
+        match faces
+            .iter_mut()
+            .find(|old| old.descriptors == face.descriptors)
+        {
+            Some(old) => *old = face,
+            None => faces.push(face),
+        }
+    }
+
+    fn face_mut(&mut self, family: &str, descriptors: &Descriptors) -> Option<&mut FamilyFace> {
+        self.families
+            .get_mut(family)?
+            .iter_mut()
+            .find(|face| face.descriptors == *descriptors)
+    }
+
+    /// End the block and swap periods that are over
+    fn expire(&mut self, now: Instant) {
+        let mut expired = Vec::new();
+        for (name, faces) in &mut self.families {
+            for face in faces {
+                let FaceState::Loading {
+                    started,
+                    blocking,
+                    previous,
+                } = &mut face.state
+                else {
+                    continue;
+                };
+                let block_end = *started + face.display.block_period();
+                if *blocking && now >= block_end {
+                    *blocking = false;
+                    expired.push(name.clone());
+                }
+                if previous.is_none()
+                    && face
+                        .display
+                        .swap_period()
+                        .is_some_and(|swap_period| now > block_end + swap_period)
+                {
+                    face.state = FaceState::Unavailable;
+                }
+            }
+        }
+        for name in expired {
+            self.changed(&name);
+        }
+    }
+
+    fn fail(&mut self, family: &str, descriptors: &Descriptors) {
+        let Some(face) = self.face_mut(family, descriptors) else {
+            return;
+        };
+        let FaceState::Loading { previous, .. } = face.state else {
+            return;
+        };
+        face.state = match previous {
+            Some(face) => FaceState::Loaded(face),
+            None => FaceState::Unavailable,
+        };
+        self.changed(family);
+    }
+
+    /// Record that text with a family looks different and wake up the windows to draw it again
+    fn changed(&mut self, family: &str) {
+        self.record_change(Some(family.to_string()));
+    }
+
+    fn record_change(&mut self, family: Option<String>) {
+        self.generation += 1;
+        self.changes.push((self.generation, family));
+        self.proxies
+            .retain(|proxy| proxy.send_event(Redraw).is_ok());
+    }
+}
+
+/// The font families characters fall back to when the fonts of their text don't have glyphs for them, so a paragraph that mixes scripts, like Latin and CJK, or has symbols and emoji in it shows them instead of boxes for missing glyphs.
+///
+/// A character tries the families of its script, then the families for every script, then the default font and the emoji fonts epaint ships with. Characters that scripts share, like punctuation, digits, symbols and emoji, only try the families for every script. The faces of fallback families are matched by the weight and style of the text like the faces of its own families, and start loading the first time a character falls back to them.
+///
+/// ```no_run
+/// # use blitz_core::{Config, FontFallback};
+/// Config::new().with_font_fallback(
+///     FontFallback::new()
+///         .with_script("Hani", ["Noto Sans SC"])
+///         .with_script("Arab", ["Noto Naskh Arabic"])
+///         .with_family("Noto Sans Symbols 2")
+///         .with_family("Noto Color Emoji"),
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FontFallback {
+    /// The families of every script, by their names in lowercase
+    families: Vec<String>,
+    /// The families of each script by its ISO 15924 code
+    scripts: Vec<(String, Vec<String>)>,
+}
+
+impl FontFallback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a family that characters of every script fall back to, after the families that were added before.
+    pub fn with_family(mut self, family: &str) -> Self {
+        self.families.push(family.to_lowercase());
+        self
+    }
+
+    /// Add families that the characters of a script fall back to before the families of every script. Scripts are named by their four letter ISO 15924 codes, like `Hani` for Chinese characters, `Hira` and `Kana` for Japanese, `Hang` for Korean, `Arab` for Arabic and `Deva` for Devanagari.
+    pub fn with_script(
+        mut self,
+        script: &str,
+        families: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Self {
+        let families = families
+            .into_iter()
+            .map(|family| family.as_ref().to_lowercase());
+        match self
+            .scripts
+            .iter_mut()
+            .find(|(other, _)| other.eq_ignore_ascii_case(script))
+        {
+            Some((_, old)) => old.extend(families),
+            None => self.scripts.push((script.to_string(), families.collect())),
+        }
+        self
+    }
+
+    /// The families a character of a script tries, in order.
+    fn families_for(&self, script: Script) -> impl Iterator<Item = &String> {
+        self.scripts
+            .iter()
+            .filter(move |(code, _)| code.eq_ignore_ascii_case(script.short_name()))
+            .flat_map(|(_, families)| families)
+            .chain(&self.families)
+    }
+
+    fn contains(&self, family: &str) -> bool {
+        self.families
+            .iter()
+            .chain(self.scripts.iter().flat_map(|(_, families)| families))
+            .any(|other| other == family)
+    }
+}
+
+/// Replace the families characters fall back to in every window and document, see [`FontFallback`]. Text is measured and drawn again with them.
+pub fn set_font_fallback(fallback: FontFallback) {
+    let mut fonts = fonts();
+    fonts.fallback = fallback;
+    fonts.record_change(None);
+}
+
+fn fonts() -> MutexGuard<'static, Fonts> {
+    let mut fonts = FONTS.lock().unwrap();
+    fonts.expire(Instant::now());
+    fonts
+}
+
+/// How a family matched text
+enum Matched {
+    Loaded(u16),
+    /// The face that matches is in its block period
+    Blocking,
+    /// The family has no face that matches, or the face isn't loaded yet
+    Unavailable,
+}
+
+/// Match text with the faces of a family, see [`select`]. A declared face that matches starts loading once the fonts are unlocked, with the load pushed to `loads`.
+fn match_family(
+    fonts: &mut Fonts,
+    family: &str,
+    weight: u16,
+    style: FontStyle,
+    text: &str,
+    loads: &mut Vec<(FontLoad, Vec<String>)>,
+) -> Matched {
+    let Some(faces) = fonts.families.get_mut(family) else {
+        return Matched::Unavailable;
+    };
+    let Some(face) = best_match(faces, weight, style, text) else {
+        return Matched::Unavailable;
+    };
+    match &face.state {
+        FaceState::Loaded(face)
+        | FaceState::Loading {
+            previous: Some(face),
+            ..
+        } => Matched::Loaded(*face),
+        FaceState::Loading { blocking: true, .. } => Matched::Blocking,
+        FaceState::Declared(sources) => {
+            let blocking = !face.display.block_period().is_zero();
+            loads.push((
+                FontLoad {
+                    face: Some((family.to_string(), face.descriptors.clone())),
+                    source: None,
+                    index: 0,
+                },
+                sources.clone(),
+            ));
+            face.state = FaceState::Loading {
+                started: Instant::now(),
+                blocking,
+                previous: None,
+            };
+            if blocking {
+                Matched::Blocking
+            } else {
+                Matched::Unavailable
+            }
+        }
+        _ => Matched::Unavailable,
+    }
+}
+
+/// Pick the face to shape text with from a list of families. Each family is matched by the descriptors of its faces: the face that covers the most characters of the text, then the face with the closest style and then the closest weight. The first family whose match is loaded is used, or the default face.
+///
+/// Declared faces start loading when they are matched. Also returns false if the text is invisible because a face before the one that is used is in its block period.
+///
+/// The characters the face doesn't have glyphs for are drawn with the face from [`fallback`].
+pub(crate) fn select(
+    families: &[String],
+    weight: u16,
+    style: FontStyle,
+    text: &str,
+) -> (u16, bool) {
+    let mut fonts = fonts();
+    let mut visible = true;
+    let mut selected = 0;
+    let mut loads = Vec::new();
+    for family in families {
+        match match_family(&mut fonts, family, weight, style, text, &mut loads) {
+            Matched::Loaded(face) => {
+                selected = face;
+                break;
+            }
+            Matched::Blocking => visible = false,
+            Matched::Unavailable => {}
+        }
+    }
+    // The loads lock the fonts when they finish
+    drop(fonts);
+    for (load, sources) in loads {
+        load_sources(load, sources);
+    }
+    (selected, visible)
+}
+
+/// Pick the face to draw a character with that the face of its text doesn't have a glyph for, see [`FontFallback`]. Fallback faces don't make text invisible while they load; the character is drawn with the next face that has it until then.
+///
+/// Returns `None` if no face has a glyph for the character.
+pub(crate) fn fallback(character: char, weight: u16, style: FontStyle) -> Option<u16> {
+    let mut fonts = fonts();
+    let mut loads = Vec::new();
+    let text = character.to_string();
+    let families: Vec<String> = fonts
+        .fallback
+        .families_for(character.script())
+        .cloned()
+        .collect();
+    let mut selected = families.iter().find_map(|family| {
+        match match_family(&mut fonts, family, weight, style, &text, &mut loads) {
+            Matched::Loaded(face) => {
+                Some(face).filter(|face| fonts.faces[*face as usize].has_glyph(character))
+            }
+            _ => None,
+        }
+    });
+    if selected.is_none() {
+        selected = BUILTIN_FACES
+            .into_iter()
+            .find(|face| fonts.faces[*face as usize].has_glyph(character));
+    }
+    // The loads lock the fonts when they finish
+    drop(fonts);
+    for (load, sources) in loads {
+        load_sources(load, sources);
+    }
+    selected
+}
+
+/// The face of a family that matches text best, or `None` if no face covers any of its characters. Faces that failed to load are skipped, so the next best face is used instead.
+fn best_match<'a>(
+    faces: &'a mut [FamilyFace],
+    weight: u16,
+    style: FontStyle,
+    text: &str,
+) -> Option<&'a mut FamilyFace> {
+    let preference = style.preference();
+    faces
+        .iter_mut()
+        .filter(|face| !matches!(face.state, FaceState::Unavailable))
+        .filter_map(|face| {
+            let uncovered = text
+                .chars()
+                .filter(|character| !face.descriptors.covers(*character))
+                .count();
+            // A face that covers none of the characters isn't used, unless there are none
+            if uncovered > 0 && uncovered == text.chars().count() {
+                return None;
+            }
+            let style = preference
+                .iter()
+                .position(|style| *style == face.descriptors.style);
+            let key = (
+                uncovered,
+                style,
+                weight_distance(&face.descriptors.weight, weight),
+            );
+            Some((key, face))
+        })
+        .min_by_key(|(key, _)| *key)
+        .map(|(_, face)| face)
+}
+
+/// How far the weights of a face are from the weight of text, like the font matching of CSS: normal weights prefer the weights up to 500, then lighter ones, and other weights prefer weights further from normal. Lower is closer.
+fn weight_distance(weights: &RangeInclusive<u16>, weight: u16) -> (u8, u16) {
+    let (min, max) = (*weights.start(), *weights.end());
+    if weights.contains(&weight) {
+        (0, 0)
+    } else if (400..=500).contains(&weight) {
+        if min > weight && min <= 500 {
+            (1, min - weight)
+        } else if max < weight {
+            (2, weight - max)
+        } else {
+            (3, min - weight)
+        }
+    } else if weight < 400 {
+        if max < weight {
+            (1, weight - max)
+        } else {
+            (2, min - weight)
+        }
+    } else if min > weight {
+        (1, min - weight)
+    } else {
+        (2, weight - max)
+    }
+}
+
+/// The face with an id from [`select`]
+pub(crate) fn face(id: u16) -> &'static Face {
+    FONTS.lock().unwrap().faces[id as usize]
+}
+
+/// Incremented every time a font loads or fails, or its block period ends
+pub(crate) fn generation() -> u64 {
+    fonts().generation
+}
+
+/// The families whose text looks different since a generation, see [`changed_since`]
+#[derive(Default)]
+pub(crate) struct FontChanges {
+    families: FxHashSet<String>,
+    /// True if the fallbacks changed, which any text can be drawn with
+    fallback: bool,
+}
+
+impl FontChanges {
+    pub fn is_empty(&self) -> bool {
+        self.families.is_empty() && !self.fallback
+    }
+
+    /// True if text with a list of families may look different.
+    pub fn affects(&self, families: &[String]) -> bool {
+        self.fallback || families.iter().any(|family| self.families.contains(family))
+    }
+}
+
+/// The families whose text looks different since a generation, and move the generation to the current one.
+pub(crate) fn changed_since(generation: &mut u64) -> FontChanges {
+    let fonts = fonts();
+    let mut changes = FontChanges::default();
+    for (_, family) in fonts
+        .changes
+        .iter()
+        .rev()
+        .take_while(|(changed, _)| changed > generation)
+    {
+        match family {
+            Some(family) => {
+                changes.fallback |= fonts.fallback.contains(family);
+                changes.families.insert(family.clone());
+            }
+            None => changes.fallback = true,
+        }
+    }
+    *generation = fonts.generation;
+    changes
+}
+
+/// When the next block period ends and the text of its family has to be drawn in the fallback font
+pub(crate) fn block_deadline() -> Option<Instant> {
+    fonts()
+        .families
+        .values()
+        .flatten()
+        .filter_map(|face| match face.state {
+            FaceState::Loading {
+                started,
+                blocking: true,
+                ..
+            } => Some(started + face.display.block_period()),
+            _ => None,
+        })
+        .min()
+}
+
+/// Wake up a window when fonts change, so it lays out and draws its text again.
+pub(crate) fn attach(proxy: EventLoopProxy<Redraw>) {
+    FONTS.lock().unwrap().proxies.push(proxy);
+}
@@ -0,0 +1,96 @@
+use std::sync::{Arc, Mutex};
+
+use dioxus_native_core::FxHashMap;
+use tao::event_loop::EventLoopProxy;
+
+use crate::plugin::{CustomElement, ElementPlugin, Painter};
+use crate::{Document, Redraw};
+
+/// Independent documents shown inside of `iframe` elements, for sandboxed content like a markdown preview inside of an editor. Register them with [`crate::Config::with_frames`].
+///
+/// An `iframe` shows the document with the same name as its `name` attribute. Each document has its own RealDom, root style and layout, so its styles and elements can't affect the document around it. It is laid out in the content box of the `iframe` and clipped to it. Set `overflow: auto` in the root style of the document (see [`crate::Config::with_root_style`]) to scroll its content with the mouse wheel.
+///
+/// Keep a clone to change the documents while the application runs. The window is drawn again after every change. The documents can't show frames of the same `Frames` themselves.
+#[derive(Clone, Default)]
+pub struct Frames {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    documents: FxHashMap<String, Document>,
+    /// Set when a document changed since the last frame
+    changed: bool,
+    /// Wakes up the window when a document changes, set once the window is opened
+    proxy: Option<EventLoopProxy<Redraw>>,
+}
+
+impl Frames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show a document in the `iframe` elements with the name, replacing the document that was shown before.
+    pub fn insert(&self, name: impl Into<String>, document: Document) {
+        self.changed(|inner| {
+            inner.documents.insert(name.into(), document);
+        });
+    }
+
+    pub fn remove(&self, name: &str) -> Option<Document> {
+        self.changed(|inner| inner.documents.remove(name))
+    }
+
+    /// Change the document with the name, if there is one.
+    pub fn update<T>(&self, name: &str, update: impl FnOnce(&mut Document) -> T) -> Option<T> {
+        self.changed(|inner| inner.documents.get_mut(name).map(update))
+    }
+
+    fn changed<T>(&self, change: impl FnOnce(&mut Inner) -> T) -> T {
+        let mut inner = self.inner.lock().unwrap();
+        let result = change(&mut inner);
+        inner.changed = true;
+        if let Some(proxy) = &inner.proxy {
+            let _ = proxy.send_event(Redraw);
+        }
+        result
+    }
+
+    pub(crate) fn attach(&self, proxy: EventLoopProxy<Redraw>) {
+        self.inner.lock().unwrap().proxy = Some(proxy);
+    }
+
+    /// Returns true if a document changed since the last call.
+    pub(crate) fn take_changed(&self) -> bool {
+        std::mem::take(&mut self.inner.lock().unwrap().changed)
+    }
+
+    fn with_document<T>(
+        &self,
+        element: &CustomElement,
+        f: impl FnOnce(&mut Document) -> T,
+    ) -> Option<T> {
+        let name = element.attribute("name")?;
+        self.inner.lock().unwrap().documents.get_mut(name).map(f)
+    }
+}
+
+/// Draws the documents of [`Frames`] in `iframe` elements
+pub(crate) struct FramePlugin(pub Frames);
+
+impl ElementPlugin for FramePlugin {
+    fn tag(&self) -> &str {
+        "iframe"
+    }
+
+    fn paint(&self, element: &CustomElement, content_box: epaint::Rect, painter: &mut Painter) {
+        self.0
+            .with_document(element, |document| document.paint(content_box, painter));
+    }
+
+    fn scroll(&self, element: &CustomElement, point: epaint::Pos2, delta: epaint::Vec2) -> bool {
+        self.0
+            .with_document(element, |document| document.scroll(point, delta))
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tao::event_loop::EventLoopProxy;
+
+use crate::Redraw;
+
+/// The frame rate limit of a window, which can be changed while the window is open.
+///
+/// Every window has its own limit, so a secondary window that only shows a dashboard can be drawn at 10 frames per second while the main window follows the display. Pass a clone to [`crate::Config::with_frame_rate`] and keep another one to change the limit later, for example when the window is minimized to a monitor. Changes that come in faster than the limit are drawn together in the next frame.
+#[derive(Clone, Default)]
+pub struct FrameRate {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// The shortest time between two frames
+    interval: Option<Duration>,
+    /// The shortest time between two frames while the window doesn't have focus
+    unfocused_interval: Option<Duration>,
+    /// Wakes up the window so it picks up a new limit, set once the window is opened
+    proxy: Option<EventLoopProxy<Redraw>>,
+}
+
+/// The time between frames at a frame rate, or `None` for 0, which is no limit.
+fn interval(fps: u32) -> Option<Duration> {
+    (fps > 0).then(|| Duration::from_secs(1) / fps)
+}
+
+impl FrameRate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw at most a number of frames per second. 0 removes the limit.
+    pub fn set_max_fps(&self, fps: u32) {
+        self.inner.lock().unwrap().interval = interval(fps);
+        self.wake();
+    }
+
+    /// Draw at most a number of frames per second while the window doesn't have focus, which saves power in windows the user isn't looking at. The lower of the two limits applies. 0 removes the limit.
+    pub fn set_unfocused_max_fps(&self, fps: u32) {
+        self.inner.lock().unwrap().unfocused_interval = interval(fps);
+        self.wake();
+    }
+
+    fn wake(&self) {
+        if let Some(proxy) = &self.inner.lock().unwrap().proxy {
+            let _ = proxy.send_event(Redraw);
+        }
+    }
+
+    pub(crate) fn attach(&self, proxy: EventLoopProxy<Redraw>) {
+        self.inner.lock().unwrap().proxy = Some(proxy);
+    }
+
+    /// The shortest time between two frames of the window, if it has a limit.
+    pub(crate) fn interval(&self, focused: bool) -> Option<Duration> {
+        let inner = self.inner.lock().unwrap();
+        if focused {
+            return inner.interval;
+        }
+        inner.interval.max(inner.unfocused_interval)
+    }
+}
@@ -0,0 +1,736 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use dioxus_native_core::prelude::*;
+use peniko::kurbo::{Point, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::system_settings::system_settings;
+
+/// How far back the movement of a pointer is looked at to find the velocity of a swipe
+const VELOCITY_WINDOW: Duration = Duration::from_millis(100);
+
+/// The thresholds the gestures are recognized with, see [`crate::Config::with_gestures`]. Distances are in client pixels. The slop defaults to the drag threshold of the platform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GestureConfig {
+    /// How far a pointer can move before a tap or long press turns into a swipe
+    pub slop: f64,
+    /// How long a pointer has to be held still for a long press
+    pub long_press_time: Duration,
+    /// The longest time between the two taps of a double tap
+    pub double_tap_time: Duration,
+    /// How far the pointer has to move for a swipe
+    pub swipe_min_distance: f64,
+    /// How fast in pixels per second the pointer has to move when it is released for a swipe
+    pub swipe_min_velocity: f64,
+    /// How much the distance between two pointers has to change, relative to the distance they started at, for a pinch
+    pub pinch_threshold: f64,
+    /// How far in radians two pointers have to turn around each other for a rotation
+    pub rotate_threshold: f64,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            slop: system_settings().drag_threshold,
+            long_press_time: Duration::from_millis(500),
+            double_tap_time: Duration::from_millis(300),
+            swipe_min_distance: 50.0,
+            swipe_min_velocity: 300.0,
+            pinch_threshold: 0.05,
+            rotate_threshold: 0.15,
+        }
+    }
+}
+
+/// A gesture that was recognized from pointer and touch input. It is sent to elements as a `tap`, `doubletap`, `longpress`, `swipe`, `pinch` or `rotate` event.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GestureData {
+    pub gesture: Gesture,
+    pub phase: GesturePhase,
+    /// Where the gesture is in client coordinates. For gestures with two pointers this is the point between them
+    pub client_x: f64,
+    pub client_y: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Gesture {
+    Tap,
+    DoubleTap,
+    LongPress,
+    Swipe {
+        direction: SwipeDirection,
+        /// The velocity of the pointer when it was released in pixels per second
+        velocity_x: f64,
+        velocity_y: f64,
+    },
+    /// The distance between the pointers relative to the distance they started at
+    Pinch {
+        scale: f64,
+    },
+    /// How far the pointers turned around each other since they started in radians, clockwise
+    Rotate {
+        angle: f64,
+    },
+}
+
+impl Gesture {
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            Gesture::Tap => "tap",
+            Gesture::DoubleTap => "doubletap",
+            Gesture::LongPress => "longpress",
+            Gesture::Swipe { .. } => "swipe",
+            Gesture::Pinch { .. } => "pinch",
+            Gesture::Rotate { .. } => "rotate",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Pinches and rotations are continuous and are sent once when they start, while they change and once when they end. The other gestures are only sent when they end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GesturePhase {
+    Start,
+    Change,
+    End,
+}
+
+/// The events of every gesture
+pub(crate) const GESTURE_EVENTS: [&str; 6] =
+    ["tap", "doubletap", "longpress", "swipe", "pinch", "rotate"];
+
+struct Pointer {
+    id: u64,
+    start: Point,
+    position: Point,
+    /// The recent positions, used to find the velocity
+    samples: VecDeque<(Instant, Point)>,
+}
+
+impl Pointer {
+    fn velocity(&self) -> Vec2 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some((start_time, start)), Some((end_time, end))) if end_time > start_time => {
+                (*end - *start) / (*end_time - *start_time).as_secs_f64()
+            }
+            _ => Vec2::ZERO,
+        }
+    }
+}
+
+enum Phase {
+    Idle,
+    /// One pointer is down and hasn't moved far yet, so it may become a tap or long press
+    Pressed {
+        long_press_at: Instant,
+    },
+    LongPressed,
+    /// One pointer moved past the slop, so it may become a swipe
+    Dragging,
+    /// Two pointers are down
+    MultiTouch {
+        start_distance: f64,
+        start_angle: f64,
+        pinching: bool,
+        rotating: bool,
+    },
+    /// The gesture ended or was cancelled, and nothing is recognized until every pointer is released
+    Done,
+}
+
+/// Turns pointer input into gestures. Each gesture is sent to the element the first pointer went down on, see [`crate::events::BlitzEventHandler`] for how nested elements are resolved.
+pub(crate) struct GestureRecognizer {
+    config: GestureConfig,
+    pointers: Vec<Pointer>,
+    phase: Phase,
+    /// The element under the first pointer
+    target: Option<NodeId>,
+    /// The last tap, which may be the first half of a double tap
+    last_tap: Option<(Instant, Point, NodeId)>,
+    /// A tap that is held back until it can't become a double tap anymore, because the target listens for double taps
+    pending_tap: Option<(Instant, GestureData, NodeId)>,
+    recognized: Vec<(GestureData, NodeId)>,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        Self {
+            config,
+            pointers: Vec::new(),
+            phase: Phase::Idle,
+            target: None,
+            last_tap: None,
+            pending_tap: None,
+            recognized: Vec::new(),
+        }
+    }
+
+    /// A pointer went down, on an element or outside of every element.
+    pub fn pointer_down(&mut self, id: u64, position: Point, target: Option<NodeId>, now: Instant) {
+        self.pointers.retain(|pointer| pointer.id != id);
+        self.pointers.push(Pointer {
+            id,
+            start: position,
+            position,
+            samples: VecDeque::from([(now, position)]),
+        });
+        match self.pointers.len() {
+            1 => {
+                self.target = target;
+                self.phase = match target {
+                    Some(_) => Phase::Pressed {
+                        long_press_at: now + self.config.long_press_time,
+                    },
+                    None => Phase::Done,
+                };
+            }
+            2 if !matches!(self.phase, Phase::Done | Phase::LongPressed) => {
+                let (distance, angle) = self.span();
+                self.phase = Phase::MultiTouch {
+                    start_distance: distance,
+                    start_angle: angle,
+                    pinching: false,
+                    rotating: false,
+                };
+            }
+            // Gestures with more than two pointers aren't recognized
+            _ => self.end_multi_touch(),
+        }
+    }
+
+    pub fn pointer_move(&mut self, id: u64, position: Point, now: Instant) {
+        let Some(pointer) = self.pointers.iter_mut().find(|pointer| pointer.id == id) else {
+            return;
+        };
+        pointer.position = position;
+        pointer.samples.push_back((now, position));
+        while pointer
+            .samples
+            .front()
+            .is_some_and(|(time, _)| now - *time > VELOCITY_WINDOW)
+        {
+            pointer.samples.pop_front();
+        }
+        let moved = (pointer.position - pointer.start).hypot();
+
+        match self.phase {
+            Phase::Pressed { .. } if moved > self.config.slop => self.phase = Phase::Dragging,
+            Phase::MultiTouch {
+                start_distance,
+                start_angle,
+                pinching,
+                rotating,
+            } => {
+                let (distance, angle) = self.span();
+                let scale = if start_distance > 0.0 {
+                    distance / start_distance
+                } else {
+                    1.0
+                };
+                let angle = normalize_angle(angle - start_angle);
+                // Both can be recognized at once, like when a map is zoomed and turned with two fingers
+                let pinch_phase = if pinching {
+                    Some(GesturePhase::Change)
+                } else if (scale - 1.0).abs() > self.config.pinch_threshold {
+                    Some(GesturePhase::Start)
+                } else {
+                    None
+                };
+                let rotate_phase = if rotating {
+                    Some(GesturePhase::Change)
+                } else if angle.abs() > self.config.rotate_threshold {
+                    Some(GesturePhase::Start)
+                } else {
+                    None
+                };
+                self.phase = Phase::MultiTouch {
+                    start_distance,
+                    start_angle,
+                    pinching: pinch_phase.is_some(),
+                    rotating: rotate_phase.is_some(),
+                };
+                let center = self.center();
+                if let Some(phase) = pinch_phase {
+                    self.emit(Gesture::Pinch { scale }, phase, center);
+                }
+                if let Some(phase) = rotate_phase {
+                    self.emit(Gesture::Rotate { angle }, phase, center);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// A pointer was released. `waits_for_double_tap` tells if a tap on the target has to be held back to see if it becomes a double tap, because the target listens for double taps.
+    pub fn pointer_up(&mut self, id: u64, now: Instant, waits_for_double_tap: bool) {
+        let Some(index) = self.pointers.iter().position(|pointer| pointer.id == id) else {
+            return;
+        };
+        if matches!(self.phase, Phase::MultiTouch { .. }) {
+            self.end_multi_touch();
+        }
+        let pointer = self.pointers.remove(index);
+        if !self.pointers.is_empty() {
+            return;
+        }
+        let phase = std::mem::replace(&mut self.phase, Phase::Idle);
+        match (phase, self.target) {
+            (Phase::Pressed { .. }, Some(target)) => {
+                self.tap(pointer.position, target, now, waits_for_double_tap)
+            }
+            (Phase::Dragging, _) => {
+                let velocity = pointer.velocity();
+                let offset = pointer.position - pointer.start;
+                if offset.hypot() >= self.config.swipe_min_distance
+                    && velocity.hypot() >= self.config.swipe_min_velocity
+                {
+                    let direction = if offset.x.abs() > offset.y.abs() {
+                        if offset.x > 0.0 {
+                            SwipeDirection::Right
+                        } else {
+                            SwipeDirection::Left
+                        }
+                    } else if offset.y > 0.0 {
+                        SwipeDirection::Down
+                    } else {
+                        SwipeDirection::Up
+                    };
+                    self.emit(
+                        Gesture::Swipe {
+                            direction,
+                            velocity_x: velocity.x,
+                            velocity_y: velocity.y,
+                        },
+                        GesturePhase::End,
+                        pointer.position,
+                    );
+                }
+            }
+            _ => {}
+        }
+        self.target = None;
+    }
+
+    /// Forget every pointer, for example when the cursor leaves the window or a touch is cancelled. Gestures that started are ended.
+    pub fn cancel(&mut self) {
+        if matches!(self.phase, Phase::MultiTouch { .. }) {
+            self.end_multi_touch();
+        }
+        self.pointers.clear();
+        self.phase = Phase::Idle;
+        self.target = None;
+    }
+
+    /// Recognize the gestures that are waiting for time to pass: long presses, and taps that didn't become double taps.
+    pub fn poll(&mut self, now: Instant) {
+        if let Phase::Pressed { long_press_at } = self.phase {
+            if now >= long_press_at {
+                self.phase = Phase::LongPressed;
+                let position = self.pointers[0].position;
+                self.emit(Gesture::LongPress, GesturePhase::End, position);
+            }
+        }
+        if self
+            .pending_tap
+            .as_ref()
+            .is_some_and(|(deadline, ..)| now >= *deadline)
+        {
+            let (_, data, target) = self.pending_tap.take().unwrap();
+            self.recognized.push((data, target));
+        }
+    }
+
+    /// When [`GestureRecognizer::poll`] has to be called next
+    pub fn deadline(&self) -> Option<Instant> {
+        let long_press = match self.phase {
+            Phase::Pressed { long_press_at } => Some(long_press_at),
+            _ => None,
+        };
+        let pending_tap = self.pending_tap.as_ref().map(|(deadline, ..)| *deadline);
+        long_press.into_iter().chain(pending_tap).min()
+    }
+
+    /// The element the first pointer of the current gesture went down on
+    pub fn target(&self) -> Option<NodeId> {
+        self.target
+    }
+
+    /// The gestures recognized since the last call and the elements the first pointer went down on
+    pub fn drain(&mut self) -> Vec<(GestureData, NodeId)> {
+        std::mem::take(&mut self.recognized)
+    }
+
+    fn tap(&mut self, position: Point, target: NodeId, now: Instant, waits_for_double_tap: bool) {
+        let data = |gesture| GestureData {
+            gesture,
+            phase: GesturePhase::End,
+            client_x: position.x,
+            client_y: position.y,
+        };
+        // Elements that don't listen for double taps get every tap
+        let double_tap = waits_for_double_tap
+            && self
+                .last_tap
+                .take()
+                .is_some_and(|(time, last_position, last_target)| {
+                    last_target == target
+                        && now - time <= self.config.double_tap_time
+                        && (position - last_position).hypot() <= self.config.slop * 2.0
+                });
+        if double_tap {
+            // The first tap never happened for an element that waits for double taps
+            self.pending_tap = None;
+            self.recognized.push((data(Gesture::DoubleTap), target));
+            return;
+        }
+        // A tap that is still held back can't become a double tap anymore
+        if let Some((_, data, target)) = self.pending_tap.take() {
+            self.recognized.push((data, target));
+        }
+        self.last_tap = Some((now, position, target));
+        if waits_for_double_tap {
+            self.pending_tap = Some((
+                now + self.config.double_tap_time,
+                data(Gesture::Tap),
+                target,
+            ));
+        } else {
+            self.recognized.push((data(Gesture::Tap), target));
+        }
+    }
+
+    fn end_multi_touch(&mut self) {
+        if let Phase::MultiTouch {
+            start_distance,
+            start_angle,
+            pinching,
+            rotating,
+        } = self.phase
+        {
+            let (distance, angle) = self.span();
+            let center = self.center();
+            if pinching {
+                let scale = if start_distance > 0.0 {
+                    distance / start_distance
+                } else {
+                    1.0
+                };
+                self.emit(Gesture::Pinch { scale }, GesturePhase::End, center);
+            }
+            if rotating {
+                let angle = normalize_angle(angle - start_angle);
+                self.emit(Gesture::Rotate { angle }, GesturePhase::End, center);
+            }
+        }
+        self.phase = Phase::Done;
+    }
+
+    /// The distance between the first two pointers and the angle of the line between them
+    fn span(&self) -> (f64, f64) {
+        let [first, second, ..] = &self.pointers[..] else {
+            return (0.0, 0.0);
+        };
+        let offset = second.position - first.position;
+        (offset.hypot(), offset.atan2())
+    }
+
+    /// The point between the first two pointers
+    fn center(&self) -> Point {
+        match &self.pointers[..] {
+            [first, second, ..] => first.position.midpoint(second.position),
+            [first] => first.position,
+            [] => Point::ZERO,
+        }
+    }
+
+    fn emit(&mut self, gesture: Gesture, phase: GesturePhase, position: Point) {
+        if let Some(target) = self.target {
+            self.recognized.push((
+                GestureData {
+                    gesture,
+                    phase,
+                    client_x: position.x,
+                    client_y: position.y,
+                },
+                target,
+            ));
+        }
+    }
+}
+
+/// Wrap an angle into -π..π, so turning across the negative x axis doesn't jump
+fn normalize_angle(angle: f64) -> f64 {
+    use std::f64::consts::{PI, TAU};
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recognizer() -> GestureRecognizer {
+        // The default slop depends on the platform
+        GestureRecognizer::new(GestureConfig {
+            slop: 4.0,
+            long_press_time: Duration::from_millis(500),
+            double_tap_time: Duration::from_millis(300),
+            swipe_min_distance: 50.0,
+            swipe_min_velocity: 300.0,
+            pinch_threshold: 0.05,
+            rotate_threshold: 0.15,
+        })
+    }
+
+    /// Two elements the gestures can target
+    fn targets() -> (NodeId, NodeId) {
+        let mut rdom: RealDom = RealDom::new(Vec::new());
+        let first = rdom.root_id();
+        let second = rdom
+            .create_node(NodeType::Text(TextNode::new(String::new())))
+            .id();
+        (first, second)
+    }
+
+    fn data(gesture: Gesture, phase: GesturePhase, x: f64, y: f64) -> GestureData {
+        GestureData {
+            gesture,
+            phase,
+            client_x: x,
+            client_y: y,
+        }
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn tap() {
+        let (target, _) = targets();
+        let start = Instant::now();
+        let mut gestures = recognizer();
+        gestures.pointer_down(0, Point::new(10.0, 10.0), Some(target), start);
+        gestures.pointer_move(0, Point::new(12.0, 11.0), start + ms(20));
+        gestures.pointer_up(0, start + ms(50), false);
+        assert_eq!(
+            gestures.drain(),
+            [(data(Gesture::Tap, GesturePhase::End, 12.0, 11.0), target)]
+        );
+    }
+
+    #[test]
+    fn no_tap_outside_of_elements() {
+        let start = Instant::now();
+        let mut gestures = recognizer();
+        gestures.pointer_down(0, Point::new(10.0, 10.0), None, start);
+        gestures.pointer_up(0, start + ms(50), false);
+        assert!(gestures.drain().is_empty());
+    }
+
+    #[test]
+    fn double_tap() {
+        let (target, _) = targets();
+        let start = Instant::now();
+        let mut gestures = recognizer();
+        gestures.pointer_down(0, Point::new(10.0, 10.0), Some(target), start);
+        gestures.pointer_up(0, start + ms(50), true);
+        // The first tap is held back while it can still become a double tap
+        assert!(gestures.drain().is_empty());
+        assert_eq!(gestures.deadline(), Some(start + ms(350)));
+        gestures.pointer_down(0, Point::new(11.0, 10.0), Some(target), start + ms(150));
+        gestures.pointer_up(0, start + ms(200), true);
+        assert_eq!(
+            gestures.drain(),
+            [(
+                data(Gesture::DoubleTap, GesturePhase::End, 11.0, 10.0),
+                target
+            )]
+        );
+        gestures.poll(start + ms(1000));
+        assert!(gestures.drain().is_empty());
+    }
+
+    #[test]
+    fn held_back_tap() {
+        let (target, _) = targets();
+        let start = Instant::now();
+        let mut gestures = recognizer();
+        gestures.pointer_down(0, Point::new(10.0, 10.0), Some(target), start);
+        gestures.pointer_up(0, start + ms(50), true);
+        gestures.poll(start + ms(349));
+        assert!(gestures.drain().is_empty());
+        gestures.poll(start + ms(350));
+        assert_eq!(
+            gestures.drain(),
+            [(data(Gesture::Tap, GesturePhase::End, 10.0, 10.0), target)]
+        );
+        assert_eq!(gestures.deadline(), None);
+    }
+
+    #[test]
+    fn taps_on_different_elements() {
+        let (first, second) = targets();
+        let start = Instant::now();
+        let mut gestures = recognizer();
+        gestures.pointer_down(0, Point::new(10.0, 10.0), Some(first), start);
+        gestures.pointer_up(0, start + ms(50), true);
+        gestures.pointer_down(0, Point::new(10.0, 10.0), Some(second), start + ms(100));
+        gestures.pointer_up(0, start + ms(150), true);
+        assert_eq!(
+            gestures.drain(),
+            [(data(Gesture::Tap, GesturePhase::End, 10.0, 10.0), first)]
+        );
+        gestures.poll(start + ms(450));
+        assert_eq!(
+            gestures.drain(),
+            [(data(Gesture::Tap, GesturePhase::End, 10.0, 10.0), second)]
+        );
+    }
+
+    #[test]
+    fn long_press() {
+        let (target, _) = targets();
+        let start = Instant::now();
+        let mut gestures = recognizer();
+        gestures.pointer_down(0, Point::new(10.0, 10.0), Some(target), start);
+        assert_eq!(gestures.deadline(), Some(start + ms(500)));
+        gestures.poll(start + ms(499));
+        assert!(gestures.drain().is_empty());
+        gestures.poll(start + ms(500));
+        assert_eq!(
+            gestures.drain(),
+            [(
+                data(Gesture::LongPress, GesturePhase::End, 10.0, 10.0),
+                target
+            )]
+        );
+        // Releasing after a long press isn't a tap
+        gestures.pointer_up(0, start + ms(600), false);
+        assert!(gestures.drain().is_empty());
+    }
+
+    #[test]
+    fn moving_cancels_long_press() {
+        let (target, _) = targets();
+        let start = Instant::now();
+        let mut gestures = recognizer();
+        gestures.pointer_down(0, Point::new(10.0, 10.0), Some(target), start);
+        gestures.pointer_move(0, Point::new(20.0, 10.0), start + ms(100));
+        assert_eq!(gestures.deadline(), None);
+        gestures.poll(start + ms(600));
+        gestures.pointer_up(0, start + ms(700), false);
+        assert!(gestures.drain().is_empty());
+    }
+
+    #[test]
+    fn swipe() {
+        let (target, _) = targets();
+        let start = Instant::now();
+        let mut gestures = recognizer();
+        gestures.pointer_down(0, Point::ZERO, Some(target), start);
+        gestures.pointer_move(0, Point::new(20.0, 0.0), start + ms(20));
+        gestures.pointer_move(0, Point::new(60.0, 0.0), start + ms(40));
+        gestures.pointer_move(0, Point::new(100.0, 0.0), start + ms(60));
+        gestures.pointer_up(0, start + ms(60), false);
+        let recognized = gestures.drain();
+        let [(
+            GestureData {
+                gesture:
+                    Gesture::Swipe {
+                        direction,
+                        velocity_x,
+                        velocity_y,
+                    },
+                phase: GesturePhase::End,
+                ..
+            },
+            swiped,
+        )] = recognized[..]
+        else {
+            panic!("expected a swipe, got {recognized:?}");
+        };
+        assert_eq!(direction, SwipeDirection::Right);
+        assert!((velocity_x - 100.0 / 0.06).abs() < 1.0);
+        assert_eq!(velocity_y, 0.0);
+        assert_eq!(swiped, target);
+    }
+
+    #[test]
+    fn slow_drag_is_no_swipe() {
+        let (target, _) = targets();
+        let start = Instant::now();
+        let mut gestures = recognizer();
+        gestures.pointer_down(0, Point::ZERO, Some(target), start);
+        for step in 1..=10 {
+            gestures.pointer_move(
+                0,
+                Point::new(0.0, step as f64 * -10.0),
+                start + ms(step * 100),
+            );
+        }
+        gestures.pointer_up(0, start + ms(1000), false);
+        assert!(gestures.drain().is_empty());
+    }
+
+    #[test]
+    fn pinch() {
+        let (target, _) = targets();
+        let start = Instant::now();
+        let mut gestures = recognizer();
+        gestures.pointer_down(0, Point::ZERO, Some(target), start);
+        gestures.pointer_down(1, Point::new(100.0, 0.0), None, start + ms(10));
+        gestures.pointer_move(1, Point::new(102.0, 0.0), start + ms(20));
+        // Within the threshold
+        assert!(gestures.drain().is_empty());
+        gestures.pointer_move(1, Point::new(200.0, 0.0), start + ms(30));
+        gestures.pointer_up(0, start + ms(40), false);
+        gestures.pointer_up(1, start + ms(50), false);
+        let pinch = Gesture::Pinch { scale: 2.0 };
+        assert_eq!(
+            gestures.drain(),
+            [
+                (data(pinch, GesturePhase::Start, 100.0, 0.0), target),
+                (data(pinch, GesturePhase::End, 100.0, 0.0), target),
+            ]
+        );
+    }
+
+    #[test]
+    fn rotate() {
+        let (target, _) = targets();
+        let start = Instant::now();
+        let mut gestures = recognizer();
+        gestures.pointer_down(0, Point::ZERO, Some(target), start);
+        gestures.pointer_down(1, Point::new(100.0, 0.0), None, start);
+        gestures.pointer_move(1, Point::new(0.0, 100.0), start + ms(10));
+        let recognized = gestures.drain();
+        let [(
+            GestureData {
+                gesture: Gesture::Rotate { angle },
+                phase: GesturePhase::Start,
+                ..
+            },
+            _,
+        )] = recognized[..]
+        else {
+            panic!("expected a rotation, got {recognized:?}");
+        };
+        assert!((angle - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn angles_wrap() {
+        use std::f64::consts::PI;
+        assert!((normalize_angle(1.5 * PI) + 0.5 * PI).abs() < 1e-9);
+        assert!((normalize_angle(-1.5 * PI) - 0.5 * PI).abs() < 1e-9);
+        assert!((normalize_angle(0.25) - 0.25).abs() < 1e-9);
+    }
+}
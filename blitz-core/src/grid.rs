@@ -0,0 +1,262 @@
+use lightningcss::traits::Parse;
+use lightningcss::values::length::LengthValue;
+use lightningcss::values::percentage::DimensionPercentage;
+use taffy::geometry::{Line, MinMax};
+use taffy::style::{
+    Display, GridAutoFlow, GridPlacement, GridTrackRepetition, LengthPercentage,
+    MaxTrackSizingFunction, MinTrackSizingFunction, NonRepeatedTrackSizingFunction, Style,
+    TrackSizingFunction,
+};
+
+use crate::style::DEFAULT_FONT_SIZE;
+
+/// Apply a CSS grid property to the style of an element, after [`dioxus_native_core::layout_attributes::apply_layout_attributes`] applied the flexbox properties. `font_size` is the font size of the element, which `em` is relative to.
+///
+/// Returns `None` if the value of a grid property can't be parsed. Other properties are left alone. Lines and tracks can't be named, so `grid-template-areas` and named lines aren't supported.
+pub(crate) fn apply_grid_attribute(
+    name: &str,
+    value: &str,
+    font_size: f32,
+    style: &mut Style,
+) -> Option<()> {
+    let value = value.trim();
+    match name {
+        "display" => {
+            if matches!(value, "grid" | "inline-grid") {
+                style.display = Display::Grid;
+            }
+        }
+        "grid-template-columns" => style.grid_template_columns = parse_template(value, font_size)?,
+        "grid-template-rows" => style.grid_template_rows = parse_template(value, font_size)?,
+        "grid-auto-columns" => style.grid_auto_columns = parse_tracks(value, font_size)?,
+        "grid-auto-rows" => style.grid_auto_rows = parse_tracks(value, font_size)?,
+        "grid-auto-flow" => style.grid_auto_flow = parse_auto_flow(value)?,
+        "grid-row" => style.grid_row = parse_line(value)?,
+        "grid-column" => style.grid_column = parse_line(value)?,
+        "grid-row-start" => style.grid_row.start = parse_placement(value)?,
+        "grid-row-end" => style.grid_row.end = parse_placement(value)?,
+        "grid-column-start" => style.grid_column.start = parse_placement(value)?,
+        "grid-column-end" => style.grid_column.end = parse_placement(value)?,
+        "grid-area" => {
+            // row-start / column-start / row-end / column-end, where the lines that are left out are auto
+            let lines: Vec<GridPlacement> = value
+                .split('/')
+                .map(|line| parse_placement(line.trim()))
+                .collect::<Option<_>>()?;
+            if lines.len() > 4 {
+                return None;
+            }
+            let line = |index: usize| lines.get(index).copied().unwrap_or(GridPlacement::Auto);
+            style.grid_row = Line {
+                start: line(0),
+                end: line(2),
+            };
+            style.grid_column = Line {
+                start: line(1),
+                end: line(3),
+            };
+        }
+        // The gaps of a grid are the same as in a flexbox, but they are set here too so they resolve `em` like the tracks
+        "gap" => {
+            let mut gaps = value.split_whitespace();
+            let row = parse_length(gaps.next()?, font_size)?;
+            let column = match gaps.next() {
+                Some(column) => parse_length(column, font_size)?,
+                None => row,
+            };
+            if gaps.next().is_some() {
+                return None;
+            }
+            style.gap.height = row;
+            style.gap.width = column;
+        }
+        "row-gap" => style.gap.height = parse_length(value, font_size)?,
+        "column-gap" => style.gap.width = parse_length(value, font_size)?,
+        _ => {}
+    }
+    Some(())
+}
+
+/// Parse a length or percentage. Lengths in `em` are relative to a font size.
+pub(crate) fn parse_length(value: &str, font_size: f32) -> Option<LengthPercentage> {
+    if value == "0" {
+        return Some(LengthPercentage::Points(0.0));
+    }
+    match DimensionPercentage::<LengthValue>::parse_string(value).ok()? {
+        DimensionPercentage::Dimension(LengthValue::Em(em)) => {
+            Some(LengthPercentage::Points(em * font_size))
+        }
+        DimensionPercentage::Dimension(LengthValue::Rem(rem)) => {
+            Some(LengthPercentage::Points(rem * DEFAULT_FONT_SIZE))
+        }
+        DimensionPercentage::Dimension(length) => Some(LengthPercentage::Points(length.to_px()?)),
+        DimensionPercentage::Percentage(percentage) => {
+            Some(LengthPercentage::Percent(percentage.0))
+        }
+        DimensionPercentage::Calc(_) => None,
+    }
+}
+
+/// Split a value at the white space or commas that aren't inside of parentheses.
+fn split_top_level(value: &str, separator: impl Fn(char) -> bool) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (offset, character) in value.char_indices() {
+        match character {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            character if depth == 0 && separator(character) => {
+                parts.push(&value[start..offset]);
+                start = offset + character.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Split a function like `minmax(10px, 1fr)` into its name and arguments.
+fn parse_function(value: &str) -> Option<(&str, Vec<&str>)> {
+    let (name, arguments) = value.strip_suffix(')')?.split_once('(')?;
+    Some((name.trim(), split_top_level(arguments, |c| c == ',')))
+}
+
+/// Parse `none` or a track list of grid-template-columns or grid-template-rows, like `200px repeat(auto-fill, minmax(100px, 1fr))`.
+fn parse_template(value: &str, font_size: f32) -> Option<Vec<TrackSizingFunction>> {
+    if value == "none" {
+        return Some(Vec::new());
+    }
+    split_top_level(value, char::is_whitespace)
+        .into_iter()
+        .map(|track| match parse_function(track) {
+            Some(("repeat", arguments)) => {
+                let [count, tracks] = arguments[..] else {
+                    return None;
+                };
+                let repetition = match count {
+                    "auto-fill" => GridTrackRepetition::AutoFill,
+                    "auto-fit" => GridTrackRepetition::AutoFit,
+                    count => GridTrackRepetition::Count(count.parse().ok().filter(|n| *n > 0)?),
+                };
+                Some(TrackSizingFunction::Repeat(
+                    repetition,
+                    parse_tracks(tracks, font_size)?,
+                ))
+            }
+            _ => parse_track(track, font_size).map(TrackSizingFunction::Single),
+        })
+        .collect()
+}
+
+/// Parse a list of tracks without repetitions, like the value of grid-auto-rows.
+fn parse_tracks(value: &str, font_size: f32) -> Option<Vec<NonRepeatedTrackSizingFunction>> {
+    let tracks: Vec<_> = split_top_level(value, char::is_whitespace)
+        .into_iter()
+        .map(|track| parse_track(track, font_size))
+        .collect::<Option<_>>()?;
+    (!tracks.is_empty()).then_some(tracks)
+}
+
+/// Parse the size of a track, like `1fr`, `auto`, `minmax(100px, 1fr)` or `fit-content(200px)`.
+fn parse_track(value: &str, font_size: f32) -> Option<NonRepeatedTrackSizingFunction> {
+    match parse_function(value) {
+        Some(("minmax", arguments)) => {
+            let [min, max] = arguments[..] else {
+                return None;
+            };
+            Some(MinMax {
+                min: parse_min_track(min, font_size)?,
+                max: parse_max_track(max, font_size)?,
+            })
+        }
+        Some(("fit-content", arguments)) => {
+            let [limit] = arguments[..] else {
+                return None;
+            };
+            Some(MinMax {
+                min: MinTrackSizingFunction::Auto,
+                max: MaxTrackSizingFunction::FitContent(parse_length(limit, font_size)?),
+            })
+        }
+        Some(_) => None,
+        // A flexible track on its own has a minimum of auto, like `minmax(auto, 1fr)`
+        None if value.ends_with("fr") => Some(MinMax {
+            min: MinTrackSizingFunction::Auto,
+            max: parse_max_track(value, font_size)?,
+        }),
+        None => Some(MinMax {
+            min: parse_min_track(value, font_size)?,
+            max: parse_max_track(value, font_size)?,
+        }),
+    }
+}
+
+fn parse_min_track(value: &str, font_size: f32) -> Option<MinTrackSizingFunction> {
+    match value {
+        "auto" => Some(MinTrackSizingFunction::Auto),
+        "min-content" => Some(MinTrackSizingFunction::MinContent),
+        "max-content" => Some(MinTrackSizingFunction::MaxContent),
+        length => parse_length(length, font_size).map(MinTrackSizingFunction::Fixed),
+    }
+}
+
+fn parse_max_track(value: &str, font_size: f32) -> Option<MaxTrackSizingFunction> {
+    match value {
+        "auto" => Some(MaxTrackSizingFunction::Auto),
+        "min-content" => Some(MaxTrackSizingFunction::MinContent),
+        "max-content" => Some(MaxTrackSizingFunction::MaxContent),
+        value => match value.strip_suffix("fr") {
+            Some(flex) => flex
+                .parse()
+                .ok()
+                .filter(|flex: &f32| *flex >= 0.0)
+                .map(MaxTrackSizingFunction::Flex),
+            None => parse_length(value, font_size).map(MaxTrackSizingFunction::Fixed),
+        },
+    }
+}
+
+fn parse_auto_flow(value: &str) -> Option<GridAutoFlow> {
+    let keywords: Vec<&str> = value.split_whitespace().collect();
+    match keywords[..] {
+        ["row"] => Some(GridAutoFlow::Row),
+        ["column"] => Some(GridAutoFlow::Column),
+        ["dense"] | ["row", "dense"] | ["dense", "row"] => Some(GridAutoFlow::RowDense),
+        ["column", "dense"] | ["dense", "column"] => Some(GridAutoFlow::ColumnDense),
+        _ => None,
+    }
+}
+
+/// Parse the start and end line of grid-row or grid-column, like `1 / 3` or `span 2`. Without an end, the end is auto.
+fn parse_line(value: &str) -> Option<Line<GridPlacement>> {
+    let (start, end) = match value.split_once('/') {
+        Some((start, end)) => (parse_placement(start.trim())?, parse_placement(end.trim())?),
+        None => (parse_placement(value)?, GridPlacement::Auto),
+    };
+    Some(Line { start, end })
+}
+
+/// Parse `auto`, a line number or `span` and a number of tracks. Line numbers count from the end of the grid when they are negative.
+fn parse_placement(value: &str) -> Option<GridPlacement> {
+    let keywords: Vec<&str> = value.split_whitespace().collect();
+    match keywords[..] {
+        ["auto"] => Some(GridPlacement::Auto),
+        ["span", count] | [count, "span"] => count
+            .parse()
+            .ok()
+            .filter(|count| *count > 0)
+            .map(GridPlacement::from_span),
+        [line] => line
+            .parse()
+            .ok()
+            .filter(|line| *line != 0)
+            .map(GridPlacement::from_line_index),
+        _ => None,
+    }
+}
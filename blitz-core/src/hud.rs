@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use epaint::{Color32, Rect, Rounding, Shape};
+
+use crate::scene::Scene;
+use crate::stats::FrameStats;
+use crate::style::TextStyle;
+use crate::text::{shape, text_mesh};
+
+/// The frame rate is the number of frames drawn in this time
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+const FONT_SIZE: f32 = 12.0;
+/// The space between the text and the edge of the background
+const PADDING: f32 = 6.0;
+/// The space between the HUD and the corner of the window
+const MARGIN: f32 = 8.0;
+
+/// A debug overlay in the top left corner of the window that shows how fast frames are drawn and how much work they take, see [`crate::Config::with_debug_hud`].
+///
+/// The HUD shows the stats of the last frame, so it changes when the window is drawn and doesn't request frames itself.
+#[derive(Default)]
+pub(crate) struct DebugHud {
+    visible: bool,
+    /// When the frames of the last second were drawn
+    frames: VecDeque<Instant>,
+    last: Option<FrameStats>,
+}
+
+impl DebugHud {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Remember the stats of a frame that was drawn.
+    pub fn record(&mut self, stats: FrameStats, now: Instant) {
+        self.frames.push_back(now);
+        while self
+            .frames
+            .front()
+            .is_some_and(|frame| now.duration_since(*frame) > FPS_WINDOW)
+        {
+            self.frames.pop_front();
+        }
+        self.last = Some(stats);
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let Some(stats) = self.last else {
+            return vec!["waiting for a frame".to_string()];
+        };
+        let millis = |time: Duration| time.as_secs_f64() * 1000.0;
+        let mut frame_time = format!(
+            "{} fps  cpu {:.2} ms",
+            self.frames.len(),
+            millis(stats.cpu_time())
+        );
+        if let Some(gpu_time) = stats.gpu_time {
+            frame_time.push_str(&format!("  gpu {:.2} ms", millis(gpu_time)));
+        }
+        let dirty_nodes = stats
+            .dirty_nodes
+            .map_or_else(|| "all".to_string(), |nodes| nodes.to_string());
+        vec![
+            frame_time,
+            format!(
+                "vertices {}  indices {}",
+                stats.vertices_drawn, stats.indices_drawn
+            ),
+            format!("draw calls {}", stats.draw_calls),
+            format!("dirty nodes {dirty_nodes}"),
+        ]
+    }
+
+    /// Paint the HUD over everything else in the scene, if it is visible. Returns the area of the window it covers.
+    pub fn paint(&self, scene: &mut Scene, window: Rect) -> Rect {
+        if !self.visible {
+            return Rect::NOTHING;
+        }
+        let style = TextStyle {
+            font_size: FONT_SIZE,
+            ..TextStyle::default()
+        };
+        let lines: Vec<_> = self
+            .lines()
+            .iter()
+            .map(|line| shape(line, &style))
+            .collect();
+        let width = lines.iter().map(|line| line.width).fold(0.0, f32::max);
+        let height: f32 = lines.iter().map(|line| line.height()).sum();
+        let background = Rect::from_min_size(
+            epaint::pos2(MARGIN, MARGIN),
+            epaint::vec2(width, height) + epaint::Vec2::splat(PADDING * 2.0),
+        );
+        // The HUD gets a layer of its own, so the layers of the document keep their meshes
+        scene.mark();
+        scene.push(
+            window,
+            Shape::rect_filled(
+                background,
+                Rounding::same(4.0),
+                Color32::from_black_alpha(200),
+            ),
+        );
+        let mut y = background.min.y + PADDING;
+        for line in &lines {
+            let origin = epaint::pos2(background.min.x + PADDING, y + line.ascent);
+            let mesh = text_mesh(line, origin, Color32::WHITE, 1.0, false);
+            if !mesh.is_empty() {
+                scene.push(window, Shape::mesh(mesh));
+            }
+            y += line.height();
+        }
+        background.intersect(window)
+    }
+}
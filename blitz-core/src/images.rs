@@ -0,0 +1,44 @@
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+use peniko::{Blob, Format, Image};
+use rustc_hash::FxHashMap;
+
+/// Images that were loaded by their url. Images that failed to load are cached as None, so they are only reported once.
+static IMAGES: Lazy<Mutex<FxHashMap<String, Option<Arc<Image>>>>> = Lazy::new(Default::default);
+
+/// Load the image a style refers to with `url()`. Urls are paths to files, relative to the working directory of the application.
+///
+/// TODO: Load images over http and from the assets of the application, without blocking while the style is resolved
+pub(crate) fn load_image(url: &str) -> Option<Arc<Image>> {
+    let mut images = IMAGES.lock().unwrap();
+    if let Some(image) = images.get(url) {
+        return image.clone();
+    }
+    let path = url.strip_prefix("file://").unwrap_or(url);
+    let image = match image::open(path) {
+        Ok(image) => Some(Arc::new(to_image(image))),
+        Err(err) => {
+            log::warn!("failed to load the image `{url}`: {err}");
+            None
+        }
+    };
+    images.insert(url.to_string(), image.clone());
+    image
+}
+
+/// Decode an image file in memory, like a PNG or JPEG, without caching it.
+pub(crate) fn decode_image(data: &[u8]) -> image::ImageResult<Image> {
+    image::load_from_memory(data).map(to_image)
+}
+
+fn to_image(image: image::DynamicImage) -> Image {
+    let image = image.into_rgba8();
+    let (width, height) = image.dimensions();
+    Image::new(
+        Blob::new(Arc::new(image.into_raw())),
+        Format::Rgba8,
+        width,
+        height,
+    )
+}
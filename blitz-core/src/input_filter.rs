@@ -0,0 +1,162 @@
+use dioxus_html::input_data::keyboard_types::{Code, Key, Location, Modifiers};
+use dioxus_html::input_data::MouseButton;
+
+/// Input from the window, which an [`InputFilter`] can change or drop before it is dispatched to the elements.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Input {
+    /// A key was pressed or released
+    Key {
+        key: Key,
+        code: Code,
+        location: Location,
+        pressed: bool,
+        repeat: bool,
+        /// True if the key types text, which also sends a keypress event
+        has_text: bool,
+    },
+    /// The modifier keys that are held changed
+    Modifiers(Modifiers),
+    /// A mouse button was pressed or released over the element under the mouse
+    MouseButton { button: MouseButton, pressed: bool },
+    /// The mouse wheel scrolled by a distance in pixels
+    Wheel { delta_x: f64, delta_y: f64 },
+}
+
+/// Remaps or filters the input of the window before it is dispatched, see [`crate::Config::with_input_filter`].
+///
+/// Closures that take the input and the modifier keys that are held are filters too.
+pub trait InputFilter: 'static {
+    /// Return the input the elements get instead, or `None` to drop it. `modifiers` are the modifier keys that are held, as they were filtered.
+    fn filter(&mut self, input: Input, modifiers: Modifiers) -> Option<Input>;
+}
+
+impl<F: FnMut(Input, Modifiers) -> Option<Input> + 'static> InputFilter for F {
+    fn filter(&mut self, input: Input, modifiers: Modifiers) -> Option<Input> {
+        self(input, modifiers)
+    }
+}
+
+/// A filter for the common remappings of kiosks and managed deployments.
+///
+/// ```no_run
+/// # use blitz_core::{Config, InputRemap};
+/// # use dioxus_html::input_data::keyboard_types::{Key, Modifiers};
+/// let cfg = Config::new().with_input_filter(
+///     InputRemap::new()
+///         .swap_control_and_meta()
+///         .block_shortcut(Modifiers::CONTROL, Key::Character("p".into()))
+///         .middle_click_with(Modifiers::ALT),
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct InputRemap {
+    swap_control_and_meta: bool,
+    blocked_shortcuts: Vec<(Modifiers, Key)>,
+    blocked_buttons: Vec<MouseButton>,
+    middle_click: Option<Modifiers>,
+    /// Set while the primary button is held as the middle button, so it is released as the middle button too
+    middle_clicking: bool,
+}
+
+impl InputRemap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Swap the Control and Meta keys, so shortcuts written for Command on macOS work with Control on other platforms or the other way around.
+    pub fn swap_control_and_meta(mut self) -> Self {
+        self.swap_control_and_meta = true;
+        self
+    }
+
+    /// Drop the key events of a shortcut, so neither the elements nor the built in handling of Blitz get it. The shortcut matches when exactly these modifiers are held. Characters are matched without regard to case.
+    pub fn block_shortcut(mut self, modifiers: Modifiers, key: Key) -> Self {
+        self.blocked_shortcuts.push((modifiers, key));
+        self
+    }
+
+    /// Drop every press and release of a mouse button.
+    pub fn block_button(mut self, button: MouseButton) -> Self {
+        self.blocked_buttons.push(button);
+        self
+    }
+
+    /// Turn primary clicks into middle clicks while the modifiers are held, for mice and touchpads without a middle button.
+    pub fn middle_click_with(mut self, modifiers: Modifiers) -> Self {
+        self.middle_click = Some(modifiers);
+        self
+    }
+
+    fn is_blocked(&self, key: &Key, modifiers: Modifiers) -> bool {
+        self.blocked_shortcuts
+            .iter()
+            .any(|(blocked_modifiers, blocked_key)| {
+                *blocked_modifiers == modifiers
+                    && match (blocked_key, key) {
+                        (Key::Character(blocked), Key::Character(key)) => {
+                            blocked.to_lowercase() == key.to_lowercase()
+                        }
+                        (blocked, key) => blocked == key,
+                    }
+            })
+    }
+}
+
+impl InputFilter for InputRemap {
+    fn filter(&mut self, input: Input, modifiers: Modifiers) -> Option<Input> {
+        match input {
+            Input::Key {
+                key,
+                code,
+                location,
+                pressed,
+                repeat,
+                has_text,
+            } => {
+                let key = match key {
+                    Key::Control if self.swap_control_and_meta => Key::Meta,
+                    Key::Meta if self.swap_control_and_meta => Key::Control,
+                    key => key,
+                };
+                if self.is_blocked(&key, modifiers) {
+                    return None;
+                }
+                Some(Input::Key {
+                    key,
+                    code,
+                    location,
+                    pressed,
+                    repeat,
+                    has_text,
+                })
+            }
+            Input::Modifiers(mut modifiers) if self.swap_control_and_meta => {
+                let control = modifiers.contains(Modifiers::CONTROL);
+                modifiers.set(Modifiers::CONTROL, modifiers.contains(Modifiers::META));
+                modifiers.set(Modifiers::META, control);
+                Some(Input::Modifiers(modifiers))
+            }
+            Input::MouseButton { button, .. } if self.blocked_buttons.contains(&button) => None,
+            Input::MouseButton {
+                button: MouseButton::Primary,
+                pressed,
+            } => {
+                let middle_click = if pressed {
+                    self.middle_clicking = self
+                        .middle_click
+                        .is_some_and(|middle_click| modifiers.contains(middle_click));
+                    self.middle_clicking
+                } else {
+                    std::mem::take(&mut self.middle_clicking)
+                };
+                let button = if middle_click {
+                    MouseButton::Auxiliary
+                } else {
+                    MouseButton::Primary
+                };
+                Some(Input::MouseButton { button, pressed })
+            }
+            input => Some(input),
+        }
+    }
+}
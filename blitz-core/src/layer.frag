@@ -0,0 +1,56 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec4 inColor;
+layout(location = 1) in vec2 inUV;
+
+layout(location = 0) out vec4 outColor;
+
+// The image of a cached layer, which holds its colors like the frame
+layout(set = 0, binding = 0) uniform texture2D layer;
+layout(set = 0, binding = 1) uniform sampler layerSampler;
+
+layout(push_constant) uniform PushConstants {
+  vec4 clip_rect;
+  vec2 screen_size;
+  vec2 translation;
+  // The columns of the linear part of the transform, see DrawConstants
+  vec4 matrix;
+  uint encode_srgb;
+} pushConstants;
+
+// 0-1 linear  from  0-1 sRGB
+vec3 linear_from_srgb(vec3 srgb) {
+    bvec3 cutoff = lessThan(srgb, vec3(0.04045));
+    vec3 lower = srgb / vec3(12.92);
+    vec3 higher = pow((srgb + vec3(0.055)) / vec3(1.055), vec3(2.4));
+    return mix(higher, lower, cutoff);
+}
+
+// 0-1 sRGB  from  0-1 linear
+vec3 srgb_from_linear(vec3 linear) {
+    bvec3 cutoff = lessThan(linear, vec3(0.0031308));
+    vec3 lower = linear * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(linear, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, cutoff);
+}
+
+void main() {
+  // Textures can only be sampled before the first discard, where control flow is still uniform
+  vec4 texel = texture(sampler2D(layer, layerSampler), inUV);
+  if (any(lessThan(gl_FragCoord.xy, pushConstants.clip_rect.xy)) ||
+      any(greaterThanEqual(gl_FragCoord.xy, pushConstants.clip_rect.zw))) {
+    discard;
+  }
+  // Layers of targets that don't encode colors hold them encoded by shader.frag
+  if (pushConstants.encode_srgb != 0u && texel.a > 0.0) {
+    texel.rgb = linear_from_srgb(texel.rgb / texel.a) * texel.a;
+  }
+  vec4 color = inColor * texel;
+  // Encoded again like the output of shader.frag
+  if (pushConstants.encode_srgb != 0u && color.a > 0.0) {
+    color.rgb = srgb_from_linear(color.rgb / color.a) * color.a;
+  }
+  outColor = color;
+}
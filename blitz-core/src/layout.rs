@@ -6,73 +6,195 @@ use dioxus_native_core_macro::partial_derive_state;
 use shipyard::Component;
 use taffy::prelude::*;
 
+use crate::fonts;
+use crate::grid::apply_grid_attribute;
+use crate::plugin::{CustomElement, Plugins};
+use crate::position::{apply_position_attribute, finish_position, Position};
+use crate::stats::{self, Counter};
+use crate::style::{StyleDiagnostics, TextStyle, WhiteSpace};
+use crate::text::shape_text;
+
 // TODO: More layout types. This should default to box layout
 #[derive(Clone, Default, Debug, Component)]
 pub(crate) struct TaffyLayout {
     pub style: Style,
     pub node: Option<Node>,
+    /// The element the measure function of a custom element was created for
+    pub custom: Option<CustomElement>,
+    /// The text and style the measure function of text that wraps, or has `fit-text` or `text-overflow: ellipsis`, was created for
+    pub fitted: Option<(String, TextStyle)>,
+    /// The size the user resized the element to with its grip, which overrides the size of its style along each axis that was resized
+    pub resized: Size<Option<f32>>,
+    /// How the element is positioned, which Taffy can't tell apart for fixed and absolute elements
+    pub position: Position,
+    /// The fixed elements in the subtree of the node, including itself, which are laid out as children of the root instead of their parents
+    pub fixed: Vec<NodeId>,
 }
 
 impl PartialEq<Self> for TaffyLayout {
     fn eq(&self, other: &Self) -> bool {
-        self.style == other.style && self.node == other.node
+        self.style == other.style
+            && self.node == other.node
+            && self.custom == other.custom
+            && self.fitted == other.fitted
+            && self.resized == other.resized
+            && self.position == other.position
+            && self.fixed == other.fixed
     }
 }
 
+impl TaffyLayout {
+    /// Measure text with a style and update its leaf
+    fn update_text(&mut self, text: &str, text_style: &TextStyle, taffy: &mut Taffy) -> bool {
+        let mut changed = false;
+        let measured = text_style.fit.is_some()
+            || text_style.ellipsis
+            || text_style.white_space == WhiteSpace::Normal;
+        let (style, fitted) = match measured {
+            // Wrapped, fitted and cut off text is measured while the tree is laid out, once the width it gets is known
+            true => (
+                Style::default(),
+                Some((text.to_string(), text_style.clone())),
+            ),
+            false => {
+                let text = shape_text(text, text_style, None);
+                let style = Style {
+                    size: Size {
+                        width: Dimension::Points(text.width),
+                        height: Dimension::Points(text.height()),
+                    },
+                    ..Default::default()
+                };
+                (style, None)
+            }
+        };
+
+        let style_has_changed = self.style != style;
+
+        if let Some(n) = self.node {
+            if style_has_changed {
+                taffy.set_style(n, style.clone()).unwrap();
+                changed = true;
+            }
+        } else {
+            self.node = Some(taffy.new_leaf(style.clone()).unwrap());
+            changed = true;
+        }
+
+        if style_has_changed {
+            self.style = style;
+            changed = true;
+        }
+
+        if self.fitted != fitted {
+            let measure = fitted.clone().map(|(text, style)| {
+                let measure =
+                    move |known_dimensions: Size<Option<f32>>,
+                          available_space: Size<AvailableSpace>| {
+                        let width = known_dimensions.width.or(match available_space.width {
+                            AvailableSpace::Definite(width) => Some(width),
+                            AvailableSpace::MinContent => Some(0.0),
+                            AvailableSpace::MaxContent => None,
+                        });
+                        let text = shape_text(&text, &style, width);
+                        Size {
+                            width: text.width,
+                            height: text.height(),
+                        }
+                    };
+                MeasureFunc::Boxed(Box::new(measure))
+            });
+            taffy.set_measure(self.node.unwrap(), measure).unwrap();
+            self.fitted = fitted;
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// The style properties the layout of elements is resolved from, by `apply_layout_attributes` and the grid, position and aspect ratio attributes
+pub(crate) const LAYOUT_PROPERTIES: &[&str] = &[
+    "display",
+    "position",
+    "top",
+    "right",
+    "bottom",
+    "left",
+    "inset",
+    "width",
+    "height",
+    "min-width",
+    "min-height",
+    "max-width",
+    "max-height",
+    "aspect-ratio",
+    "margin",
+    "margin-top",
+    "margin-right",
+    "margin-bottom",
+    "margin-left",
+    "padding",
+    "padding-top",
+    "padding-right",
+    "padding-bottom",
+    "padding-left",
+    "flex",
+    "flex-direction",
+    "flex-wrap",
+    "flex-grow",
+    "flex-shrink",
+    "flex-basis",
+    "align-items",
+    "align-self",
+    "align-content",
+    "justify-content",
+    "gap",
+    "row-gap",
+    "column-gap",
+    "grid-template-columns",
+    "grid-template-rows",
+    "grid-auto-columns",
+    "grid-auto-rows",
+    "grid-auto-flow",
+    "grid-area",
+    "grid-row",
+    "grid-column",
+    "grid-row-start",
+    "grid-row-end",
+    "grid-column-start",
+    "grid-column-end",
+];
+
 #[partial_derive_state]
 impl State for TaffyLayout {
     type ChildDependencies = (Self,);
     type ParentDependencies = ();
-    type NodeDependencies = ();
+    type NodeDependencies = (TextStyle,);
 
     const NODE_MASK: NodeMaskBuilder<'static> = NodeMaskBuilder::new()
         .with_attrs(AttributeMaskBuilder::All)
+        .with_element()
         .with_text();
 
     fn update<'a>(
         &mut self,
         node_view: NodeView<()>,
-        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        (text_style,): <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
         _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
         children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
         context: &SendAnyMap,
     ) -> bool {
         let taffy: &Arc<Mutex<Taffy>> = context.get().unwrap();
-        // let text_context: &Arc<Mutex<TextContext>> = context.get().unwrap();
+        let style_diagnostics: &Arc<StyleDiagnostics> = context.get().unwrap();
+        let plugins: &Arc<Plugins> = context.get().unwrap();
         let mut taffy = taffy.lock().unwrap();
         let mut changed = false;
-        if let Some(_text) = node_view.text() {
-            // let mut text_context = text_context.lock().unwrap();
-            // let font_size = fz.0;
-            // let (width, height) = text_context.get_text_size(None, font_size, text);
-
-            // let style = Style {
-            //     size: Size {
-            //         height: Dimension::Points(height as f32),
-            //         width: Dimension::Points(width as f32),
-            //     },
-            //     ..Default::default()
-            // };
-
-            // let style_has_changed = self.style != style;
-
-            // if let Some(n) = self.node {
-            //     if style_has_changed {
-            //         taffy.set_style(n, style.clone()).unwrap();
-            //         changed = true;
-            //     }
-            // } else {
-            //     self.node = Some(taffy.new_leaf(style.clone()).unwrap());
-            //     changed = true;
-            // }
-
-            // if style_has_changed {
-            //     self.style = style;
-            //     changed = true;
-            // }
+        if let Some(text) = node_view.text() {
+            changed = self.update_text(text, text_style, &mut taffy);
         } else {
             // gather up all the styles from the attribute list
             let mut style = Style::default();
+            let mut position = Position::default();
 
             // Images default to a fixed size
             // TODO: The aspect ratio should be preserved when the image is scaled when box layout is implemented
@@ -88,16 +210,79 @@ impl State for TaffyLayout {
 
             for attr in node_view.attributes().into_iter().flatten() {
                 let name = &attr.attribute.name;
+                // This is the only state that sees every attribute, so it checks for styles no other state will resolve
+                if attr.attribute.namespace.as_deref() == Some("style") {
+                    style_diagnostics.check_property(name);
+                }
                 let value = attr.value;
                 if let Some(value) = value.as_text() {
                     apply_layout_attributes(name, value, &mut style);
+                    if apply_grid_attribute(name, value, text_style.font_size, &mut style).is_none()
+                    {
+                        style_diagnostics.invalid_value(name, value);
+                    }
+                    if apply_position_attribute(
+                        name,
+                        value,
+                        text_style.font_size,
+                        &mut style,
+                        &mut position,
+                    )
+                    .is_none()
+                    {
+                        style_diagnostics.invalid_value(name, value);
+                    }
+                    if apply_aspect_ratio_attribute(name, value, &mut style).is_none() {
+                        style_diagnostics.invalid_value(name, value);
+                    }
                 }
             }
+            finish_position(position, &mut style);
+            apply_resized(self.resized, &mut style);
+
+            let custom = node_view
+                .tag()
+                .and_then(|tag| plugins.get(tag))
+                .map(|plugin| {
+                    let element = CustomElement {
+                        tag: plugin.tag().to_string(),
+                        attributes: node_view
+                            .attributes()
+                            .into_iter()
+                            .flatten()
+                            .filter(|attr| attr.attribute.namespace.is_none())
+                            .filter_map(|attr| {
+                                Some((
+                                    attr.attribute.name.clone(),
+                                    attr.value.as_text()?.to_string(),
+                                ))
+                            })
+                            .collect(),
+                    };
+                    (plugin.clone(), element)
+                });
 
-            // Set all direct nodes as our children
+            // Set all direct nodes as our children. Custom elements are leaves that are measured by their plugin
             let mut child_layout = vec![];
-            for (l,) in children {
-                child_layout.push(l.node.unwrap());
+            let mut fixed = Vec::new();
+            if position == Position::Fixed {
+                fixed.push(node_view.node_id());
+            }
+            if custom.is_none() {
+                for (l,) in children {
+                    // Fixed children are children of the root, unless this element isn't displayed, which hides them too
+                    if l.position != Position::Fixed {
+                        child_layout.push(l.node.unwrap());
+                    }
+                    if style.display != Display::None {
+                        fixed.extend(l.fixed.iter().copied());
+                    }
+                }
+            }
+            if self.position != position || self.fixed != fixed {
+                self.position = position;
+                self.fixed = fixed;
+                changed = true;
             }
 
             let style_has_changed = self.style != style;
@@ -123,6 +308,32 @@ impl State for TaffyLayout {
                 self.style = style;
                 changed = true;
             }
+
+            let node = self.node.unwrap();
+            match custom {
+                Some((plugin, element)) => {
+                    if self.custom.as_ref() != Some(&element) {
+                        let measured = element.clone();
+                        let measure = move |known_dimensions, available_space| {
+                            plugin.measure(&measured, known_dimensions, available_space)
+                        };
+                        taffy
+                            .set_measure(node, Some(MeasureFunc::Boxed(Box::new(measure))))
+                            .unwrap();
+                        self.custom = Some(element);
+                        changed = true;
+                    }
+                }
+                None => {
+                    if self.custom.take().is_some() {
+                        taffy.set_measure(node, None).unwrap();
+                        changed = true;
+                    }
+                }
+            }
+        }
+        if changed {
+            stats::count(Counter::NodesLaidOut, 1);
         }
         changed
     }
@@ -139,3 +350,171 @@ impl State for TaffyLayout {
         myself
     }
 }
+
+/// Apply `aspect-ratio`, which is `auto`, a ratio like `16 / 9` or a number like `1.5`. Taffy uses the ratio to size an element from its width or height when only one of them is known, including a width that is stretched by its parent.
+///
+/// Elements have no natural ratio, so `auto 16 / 9` is the same as `16 / 9`. A ratio with a zero is the same as `auto`. Returns `None` if the value can't be parsed.
+fn apply_aspect_ratio_attribute(name: &str, value: &str, style: &mut Style) -> Option<()> {
+    if name != "aspect-ratio" {
+        return Some(());
+    }
+    let value = value.trim();
+    if value == "auto" {
+        style.aspect_ratio = None;
+        return Some(());
+    }
+    let ratio = value
+        .strip_prefix("auto")
+        .or_else(|| value.strip_suffix("auto"))
+        .unwrap_or(value);
+    let (width, height) = ratio.split_once('/').unwrap_or((ratio, "1"));
+    let width: f32 = width.trim().parse().ok()?;
+    let height: f32 = height.trim().parse().ok()?;
+    if !(width.is_finite() && height.is_finite()) || width < 0.0 || height < 0.0 {
+        return None;
+    }
+    style.aspect_ratio = (width > 0.0 && height > 0.0).then_some(width / height);
+    Some(())
+}
+
+/// Override the size of a style with the size the user resized the element to.
+fn apply_resized(resized: Size<Option<f32>>, style: &mut Style) {
+    if let Some(width) = resized.width {
+        style.size.width = Dimension::Points(width);
+    }
+    if let Some(height) = resized.height {
+        style.size.height = Dimension::Points(height);
+    }
+}
+
+/// Give an element the size the user resized it to, which it keeps when its style changes.
+pub(crate) fn set_resized(
+    rdom: &mut RealDom,
+    taffy: &mut Taffy,
+    id: NodeId,
+    size: Size<Option<f32>>,
+) {
+    let Some(mut node) = rdom.get_mut(id) else {
+        return;
+    };
+    let Some(mut layout) = node.get::<TaffyLayout>().map(|layout| layout.clone()) else {
+        return;
+    };
+    if layout.resized == size {
+        return;
+    }
+    layout.resized = size;
+    apply_resized(size, &mut layout.style);
+    if let Some(taffy_node) = layout.node {
+        taffy.set_style(taffy_node, layout.style.clone()).unwrap();
+    }
+    node.insert(layout);
+}
+
+/// Measure the text that depends on the fonts that changed since a generation again, and move the generation to the current one. Returns the text nodes that were measured, which have to be drawn again.
+pub(crate) fn reflow_fonts(
+    rdom: &mut RealDom,
+    taffy: &mut Taffy,
+    generation: &mut u64,
+) -> Vec<NodeId> {
+    let changed = fonts::changed_since(generation);
+    if changed.is_empty() {
+        return Vec::new();
+    }
+    let mut text_nodes = Vec::new();
+    rdom.traverse_depth_first(|node| {
+        let affected = matches!(&*node.node_type(), NodeType::Text(_))
+            && node
+                .get::<TextStyle>()
+                .is_some_and(|style| changed.affects(&style.font_family));
+        if affected {
+            text_nodes.push(node.id());
+        }
+    });
+    for &id in &text_nodes {
+        let mut node = rdom.get_mut(id).unwrap();
+        let text = match &*node.node_type() {
+            NodeType::Text(TextNode { text, .. }) => text.clone(),
+            _ => continue,
+        };
+        let text_style = node.get::<TextStyle>().unwrap().clone();
+        let mut layout = node.get::<TaffyLayout>().unwrap().clone();
+        layout.update_text(&text, &text_style, taffy);
+        // Fitted text is measured while the tree is laid out, so its cached size is thrown away
+        if let Some(leaf) = layout.node {
+            taffy.mark_dirty(leaf).unwrap();
+        }
+        node.insert(layout);
+    }
+    text_nodes
+}
+
+/// Lay out the whole tree again at the size the root was last given, after the layout of a node changed outside of the DOM thread.
+pub(crate) fn relayout(rdom: &RealDom, taffy: &mut Taffy) {
+    let root_node = rdom.get(rdom.root_id()).unwrap();
+    let root_taffy_node = root_node.get::<TaffyLayout>().unwrap().node.unwrap();
+    attach_fixed(rdom, taffy);
+    let size = taffy.style(root_taffy_node).unwrap().size;
+    let available = |dimension| match dimension {
+        Dimension::Points(points) => AvailableSpace::Definite(points),
+        _ => AvailableSpace::MaxContent,
+    };
+    taffy
+        .compute_layout(
+            root_taffy_node,
+            Size {
+                width: available(size.width),
+                height: available(size.height),
+            },
+        )
+        .unwrap();
+}
+
+/// Resize the root node to fill the viewport and lay out the whole tree.
+pub(crate) fn compute_root_layout(rdom: &RealDom, taffy: &mut Taffy, viewport_size: Size<f32>) {
+    let root_node = rdom.get(rdom.root_id()).unwrap();
+    let root_taffy_node = root_node.get::<TaffyLayout>().unwrap().node.unwrap();
+
+    // the root node fills the entire area
+    let mut style = taffy.style(root_taffy_node).unwrap().clone();
+    let new_size = Size {
+        width: Dimension::Points(viewport_size.width),
+        height: Dimension::Points(viewport_size.height),
+    };
+    if style.size != new_size {
+        style.size = new_size;
+        taffy.set_style(root_taffy_node, style).unwrap();
+    }
+    attach_fixed(rdom, taffy);
+    taffy
+        .compute_layout(
+            root_taffy_node,
+            Size {
+                width: AvailableSpace::Definite(viewport_size.width),
+                height: AvailableSpace::Definite(viewport_size.height),
+            },
+        )
+        .unwrap();
+}
+
+/// Make the fixed elements of the document children of the root after its other children, so they are laid out in the viewport.
+fn attach_fixed(rdom: &RealDom, taffy: &mut Taffy) {
+    let root_node = rdom.get(rdom.root_id()).unwrap();
+    let root_layout = root_node.get::<TaffyLayout>().unwrap();
+    let root_taffy_node = root_layout.node.unwrap();
+    let fixed: Vec<Node> = root_layout
+        .fixed
+        .iter()
+        .filter_map(|id| rdom.get(*id)?.get::<TaffyLayout>()?.node)
+        .collect();
+    let mut children: Vec<Node> = taffy
+        .children(root_taffy_node)
+        .unwrap()
+        .into_iter()
+        .filter(|child| !fixed.contains(child))
+        .collect();
+    children.extend(fixed);
+    if taffy.children(root_taffy_node).unwrap() != children {
+        taffy.set_children(root_taffy_node, &children).unwrap();
+    }
+}
@@ -1,55 +1,576 @@
 use std::{
+    path::PathBuf,
     pin::Pin,
     sync::{Arc, Mutex, RwLock},
 };
 
-use application::ApplicationState;
+use application::{ApplicationState, DirtyNodes};
+use content_scale::DesignSize;
 use dioxus_native_core::prelude::*;
+use plugin::Plugins;
 
 use futures_util::Future;
 use taffy::Taffy;
 use tao::{
+    dpi::PhysicalSize,
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::{Window, WindowBuilder},
 };
 
-pub use crate::events::EventData;
+pub use crate::animation_frame::AnimationFrames;
+pub use crate::backend::{
+    Backend, ColorMatrix, DebugGeometry, DynamicRange, Msaa, PresentMode, RectInstance,
+};
+pub use crate::barcode::{Barcode, BarcodeFormat};
+pub use crate::chart::axis_ticks;
+pub use crate::clipboard::Clipboard;
+pub use crate::content_scale::ContentFit;
+pub use crate::coordinates::{
+    client_to_element, element_to_client, element_transform, WindowGeometry,
+};
+pub use crate::data_grid::{DataGrid, GridColumn, GridData, SortDirection};
+pub use crate::dock::{DockAxis, DockLayout, DockNode, DockZone, Docking, FloatingPanel, TabStack};
+pub use crate::document::Document;
+pub use crate::events::{DomEvent, EventData};
+pub use crate::executor::{spawn, BlockingExecutor, Executor};
+pub use crate::fonts::{
+    load_font, load_font_faces, register_font, set_font_fallback, FontDisplay, FontFace,
+    FontFallback, FontLoad, FontStyle,
+};
+pub use crate::frame::Frames;
+pub use crate::frame_rate::FrameRate;
+pub use crate::gestures::{Gesture, GestureConfig, GestureData, GesturePhase, SwipeDirection};
+pub use crate::input_filter::{Input, InputFilter, InputRemap};
+pub use crate::mouse::hit_test;
+pub use crate::plugin::{CustomElement, ElementPlugin, Painter, PointerPhase};
+pub use crate::qr::ErrorCorrection;
+pub use crate::screenshot::Screenshots;
+pub use crate::scroll::ScrollLink;
+pub use crate::session::{HeadlessSession, Session};
+#[cfg(feature = "software")]
+pub use crate::software_backend::SoftwareBackend;
+pub use crate::spotlight::Spotlight;
+pub use crate::stable_id::{find_stable_id, stable_id};
+pub use crate::stats::{CacheStats, FrameStats, Stats};
+pub use crate::style::{StyleDiagnostic, StyleDiagnostics};
+pub use crate::system_settings::SystemSettings;
+pub use crate::terminal::{CellAttributes, TerminalCell, TerminalGrid};
+pub use crate::text::{measure_text, TextMetrics};
+pub use crate::tile_map::{TileId, TileMap, TileSource};
+pub use crate::toast::{Toast, ToastId, Toasts};
+pub use crate::tree_view::{TreeItem, TreePath, TreeView};
+#[cfg(feature = "wgpu")]
+pub use crate::wgpu_backend::WgpuBackend;
+pub use crate::window_chrome::{WindowChrome, WindowShadow};
+pub use peniko::Color;
 
+mod animation_frame;
 mod application;
+mod atlas;
+#[cfg(feature = "vulkan")]
+mod attachment;
+mod backend;
+mod barcode;
+#[cfg(feature = "vulkan")]
+mod buffer;
+mod chart;
+mod clipboard;
+mod content_scale;
+mod coordinates;
+mod data_grid;
+mod dock;
+mod document;
+mod editing;
 mod events;
+mod executor;
 mod focus;
+mod fonts;
+mod frame;
+mod frame_rate;
+mod gestures;
+mod grid;
+mod hud;
+mod images;
+mod input_filter;
 mod layout;
 mod mouse;
+mod path;
+#[cfg(feature = "vulkan")]
+mod pipeline;
+mod plugin;
+mod position;
 mod prevent_default;
+mod qr;
+mod raster;
+#[cfg(feature = "vulkan")]
+mod readback;
 mod render;
+#[cfg(feature = "vulkan")]
 mod renderer;
+mod report;
+mod resize;
+mod scene;
+mod screenshot;
+mod scroll;
+mod selection;
+mod session;
+mod shader_watcher;
+mod skeleton;
+mod snapshot;
+#[cfg(feature = "software")]
+mod software_backend;
+mod split;
+mod spotlight;
+mod stable_id;
+mod stats;
 mod style;
+mod system_settings;
+mod terminal;
+mod text;
+#[cfg(feature = "vulkan")]
+mod texture;
+mod tile_map;
+mod toast;
+mod tree_view;
 mod util;
+#[cfg(feature = "wgpu")]
+mod wgpu_backend;
+mod window_chrome;
+mod woff;
 
 type TaoEvent<'a> = Event<'a, Redraw>;
 
 #[derive(Debug)]
 pub struct Redraw;
 
-#[derive(Default)]
-pub struct Config;
+pub struct Config {
+    style_diagnostics: Arc<StyleDiagnostics>,
+    clear_color: Color,
+    root_style: Vec<(String, String)>,
+    diagnostics_dir: Option<PathBuf>,
+    executor: Option<Box<dyn Executor>>,
+    /// Set by [`Config::with_runtime`] with a runtime that can't drive the DOM
+    current_thread_runtime: bool,
+    msaa: Msaa,
+    present_mode: PresentMode,
+    dynamic_range: DynamicRange,
+    /// The frame rate limit of the window
+    frame_rate: FrameRate,
+    feathering: f32,
+    subpixel_text: bool,
+    snapshot: Option<PathBuf>,
+    plugins: Plugins,
+    states: Vec<TypeErasedState<()>>,
+    shader_dir: Option<PathBuf>,
+    backend: Option<Box<dyn FnOnce(&Window) -> Box<dyn Backend>>>,
+    design_size: Option<DesignSize>,
+    /// The chrome of the window, if it has no decorations
+    window_chrome: Option<WindowChrome>,
+    gestures: GestureConfig,
+    system_settings: Option<SystemSettings>,
+    /// Fonts that are registered with their data when the window opens
+    fonts: Vec<(FontFace, Vec<u8>)>,
+    /// Style sheets with `@font-face` rules that are declared when the window opens
+    font_faces: Vec<String>,
+    font_fallback: Option<FontFallback>,
+    input_filter: Option<Box<dyn InputFilter>>,
+    screenshots: Option<Screenshots>,
+    animation_frames: Option<AnimationFrames>,
+    docking: Option<Docking>,
+    toasts: Option<Toasts>,
+    stats: Option<Stats>,
+    debug_hud: bool,
+    debug_geometry: Option<DebugGeometry>,
+    frames: Option<Frames>,
+    session: Option<Session>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // Built in elements are plugins too, so applications can replace them
+        let mut plugins = Plugins::default();
+        plugins.register(Arc::new(path::PathPlugin::default()));
+        plugins.register(Arc::new(skeleton::SkeletonPlugin::default()));
+        Self {
+            style_diagnostics: Default::default(),
+            clear_color: Color::WHITE,
+            root_style: Vec::new(),
+            diagnostics_dir: None,
+            executor: None,
+            current_thread_runtime: false,
+            msaa: Msaa::Off,
+            present_mode: PresentMode::Fifo,
+            dynamic_range: DynamicRange::Standard,
+            frame_rate: FrameRate::default(),
+            feathering: 1.0,
+            subpixel_text: false,
+            snapshot: None,
+            plugins,
+            states: Vec::new(),
+            shader_dir: None,
+            backend: None,
+            design_size: None,
+            window_chrome: None,
+            gestures: GestureConfig::default(),
+            system_settings: None,
+            fonts: Vec::new(),
+            font_faces: Vec::new(),
+            font_fallback: None,
+            input_filter: None,
+            screenshots: None,
+            animation_frames: None,
+            docking: None,
+            toasts: None,
+            stats: None,
+            debug_hud: false,
+            debug_geometry: None,
+            frames: None,
+            session: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the color the window is cleared to before the document is drawn.
+    pub fn with_clear_color(mut self, color: Color) -> Self {
+        self.clear_color = color;
+        self
+    }
+
+    /// Set a style property on the root of the document. The root fills the window, so this can be used to give the whole document a background color, image or gradient.
+    pub fn with_root_style(
+        mut self,
+        property: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.root_style.push((property.into(), value.into()));
+        self
+    }
+
+    /// Collect style diagnostics (unknown properties, invalid values, etc.) into the given sink so they can be inspected.
+    pub fn with_style_diagnostics(mut self, style_diagnostics: Arc<StyleDiagnostics>) -> Self {
+        self.style_diagnostics = style_diagnostics;
+        self
+    }
+
+    /// Write a diagnostics report into the directory when the application panics or when Ctrl+Shift+D is pressed.
+    ///
+    /// Each report is written to a new directory and contains the graphics adapter and its last validation messages, the last frame's display list and a screenshot of it, the styles and layout of the focused subtree and the style diagnostics. Attach it to bug reports.
+    pub fn with_diagnostics_report(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.diagnostics_dir = Some(dir.into());
+        self
+    }
+
+    /// Smooth the edges of shapes with multisampling. Falls back to the highest sample count the adapter supports. Only the default Vulkan backend multisamples.
+    pub fn with_msaa(mut self, msaa: Msaa) -> Self {
+        self.msaa = msaa;
+        self
+    }
+
+    /// Choose how frames are presented. The default [`PresentMode::Fifo`] is synchronized with the display, the other modes lower the latency of input.
+    ///
+    /// The wgpu backend falls back to FIFO if the surface doesn't support the mode. The Vulkan backend can't check the modes of the surface yet and creates its swapchain with the mode as it is, so only ask for a mode other than FIFO on Vulkan if the display supports it.
+    ///
+    /// This applies to the default backends. A backend set with [`Config::with_backend`] is configured when it is created, like with `WgpuBackend::with_present_mode`.
+    pub fn with_present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    /// Draw into an HDR surface, so a UI that is layered over HDR content, like the frames of a game, keeps its brightness instead of being clamped to the standard range. Falls back to the standard range if the surface has no HDR format.
+    ///
+    /// Only the wgpu backend draws to HDR surfaces, so the option needs the `wgpu` feature. Windows that draw in the high range use the wgpu backend even if the `vulkan` feature is enabled, because the Vulkan backend can't choose the format of its swapchain yet. A backend set with [`Config::with_backend`] is configured when it is created, like with `WgpuBackend::with_dynamic_range`.
+    #[cfg(feature = "wgpu")]
+    pub fn with_dynamic_range(mut self, dynamic_range: DynamicRange) -> Self {
+        self.dynamic_range = dynamic_range;
+        self
+    }
+
+    /// Draw at most a number of frames per second. Changes in between are drawn together in the next frame, which saves power with the present modes that aren't synchronized with the display or while something animates continuously. 0 removes the limit.
+    ///
+    /// Each window has its own limit. It sets the limit of the [`FrameRate`] of [`Config::with_frame_rate`] if one was passed before.
+    pub fn with_max_fps(self, fps: u32) -> Self {
+        self.frame_rate.set_max_fps(fps);
+        self
+    }
+
+    /// Draw at most a number of frames per second while the window doesn't have focus. 0 removes the limit.
+    pub fn with_unfocused_max_fps(self, fps: u32) -> Self {
+        self.frame_rate.set_unfocused_max_fps(fps);
+        self
+    }
+
+    /// Limit the frame rate of the window with a [`FrameRate`], which can change the limit while the window is open. It replaces the limits set before.
+    pub fn with_frame_rate(mut self, frame_rate: FrameRate) -> Self {
+        self.frame_rate = frame_rate;
+        self
+    }
+
+    /// Set the width in physical pixels of the strip that fades out along the edges of shapes to smooth them. The default is 1 pixel, which smooths edges without multisampling. 0 disables feathering.
+    ///
+    /// Feathering can be combined with [`Config::with_msaa`], but the edges may look blurry with high sample counts.
+    pub fn with_feathering(mut self, width: f32) -> Self {
+        self.feathering = width.max(0.0);
+        self
+    }
+
+    /// Smooth text with subpixel anti-aliasing, which uses the red, green and blue stripes of LCD pixels to make small text sharper on displays with a low pixel density. It assumes stripes in RGB order. Text that is rotated, translucent, filtered or drawn into a cached layer is smoothed in grayscale.
+    ///
+    /// Falls back to grayscale anti-aliasing if the backend doesn't draw subpixel text.
+    pub fn with_subpixel_text(mut self) -> Self {
+        self.subpixel_text = true;
+        self
+    }
+
+    /// Save the document to a file when the window is closed and show it on the next launch until the VirtualDom has been rebuilt, so the last UI of the application appears instantly.
+    ///
+    /// The focused element is restored if the rebuilt document has an element at the same place.
+    pub fn with_snapshot(mut self, path: impl Into<PathBuf>) -> Self {
+        self.snapshot = Some(path.into());
+        self
+    }
+
+    /// Let a plugin lay out, paint and handle the events of the elements with its tag. A plugin registered later for the same tag replaces the earlier one.
+    pub fn with_element_plugin(mut self, plugin: impl ElementPlugin) -> Self {
+        self.plugins.register(Arc::new(plugin));
+        self
+    }
+
+    /// Track an additional [`State`] on every node of the document. It is resolved in the same pass as the styles and layout of Blitz, so it is always up to date when the document is drawn.
+    ///
+    /// The state can depend on other states of the application. Read it from a node with `node.get::<S>()`.
+    pub fn with_state<S: State>(mut self) -> Self {
+        self.states.push(S::to_type_erased());
+        self
+    }
+
+    /// Load the shaders from `shader.vert` and `shader.frag` in a directory and rebuild the graphics pipeline whenever they change, so rendering can be iterated on without restarting the application. Point it at `blitz-core/src` to edit the built-in shaders.
+    ///
+    /// This is meant for development. If the shaders fail to compile, the last working pipeline is kept.
+    pub fn with_shader_reload(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.shader_dir = Some(dir.into());
+        self
+    }
+
+    /// Draw the window with a custom backend instead of Vulkan. The backend is created from the window once it is opened.
+    ///
+    /// Without the `vulkan`, `wgpu` or `software` feature a backend must be set.
+    pub fn with_backend<B: Backend + 'static>(
+        mut self,
+        create: impl FnOnce(&Window) -> B + 'static,
+    ) -> Self {
+        self.backend = Some(Box::new(move |window: &Window| {
+            Box::new(create(window)) as Box<dyn Backend>
+        }));
+        self
+    }
+
+    /// Lay the document out at a fixed size in pixels and scale the whole document to fit the window, like the UI of a kiosk, a sign or a game that is designed for one resolution. `fit` decides what happens when the aspect ratio of the window is different.
+    ///
+    /// Mouse events are in the pixels of the document, so the application doesn't need to know how much it is scaled. The window's clear color fills the letterbox bars.
+    pub fn with_design_size(mut self, width: u32, height: u32, fit: ContentFit) -> Self {
+        self.design_size = Some(DesignSize {
+            size: PhysicalSize::new(width.max(1), height.max(1)),
+            fit,
+        });
+        self
+    }
+
+    /// Open the window without the title bar and borders of the platform, for applications that draw their own, and give it rounded corners and a drop shadow so it doesn't look flat and square.
+    ///
+    /// Windows draws the shadow itself and rounds the corners of windows with a shadow on Windows 11. macOS draws the shadow around the rounded corners Blitz draws. On other platforms Blitz draws the shadow too, in a see-through margin around the document that the document is laid out inside of, so client coordinates start at the corner of the window inside of the shadow. The corners and the shadow need a backend that draws transparent windows, like `WgpuBackend`. With other backends the window is square and only gets the shadow of the platform.
+    pub fn with_undecorated(mut self, chrome: WindowChrome) -> Self {
+        self.window_chrome = Some(chrome);
+        self
+    }
+
+    /// Set the thresholds gestures are recognized with.
+    ///
+    /// Elements get `tap`, `doubletap`, `longpress`, `swipe`, `pinch` and `rotate` events with [`GestureData`] from the mouse and touch input on them. When nested elements listen for the same gesture, the innermost one gets it. A tap on an element that listens for double taps is held back until it can't become a double tap.
+    pub fn with_gestures(mut self, gestures: GestureConfig) -> Self {
+        self.gestures = gestures;
+        self
+    }
+
+    /// Handle input with other settings than the ones of the platform, like the double click time and the number of lines the wheel scrolls. The settings are shared by every window and document of the process.
+    ///
+    /// This also sets the slop of gestures to the drag threshold of the settings, like the default [`GestureConfig`] does with the settings of the platform.
+    pub fn with_system_settings(mut self, settings: SystemSettings) -> Self {
+        self.gestures.slop = settings.drag_threshold;
+        self.system_settings = Some(settings);
+        self
+    }
+
+    /// Register a font with the data of a TrueType or OpenType font file when the window opens, like [`register_font`], so text with its family is never drawn in the fallback font. Fonts are shared by every window and document of the process.
+    ///
+    /// ```no_run
+    /// # use blitz_core::{Config, FontFace};
+    /// let regular = std::fs::read("fonts/Inter-Regular.ttf").unwrap();
+    /// let bold = std::fs::read("fonts/Inter-Bold.ttf").unwrap();
+    /// Config::new()
+    ///     .with_font(FontFace::new("Inter"), regular)
+    ///     .with_font(FontFace::new("Inter").with_weight(700, 700), bold);
+    /// ```
+    pub fn with_font(mut self, face: FontFace, data: impl Into<Vec<u8>>) -> Self {
+        self.fonts.push((face, data.into()));
+        self
+    }
+
+    /// Declare the faces of the `@font-face` rules of a style sheet when the window opens, see [`load_font_faces`]. Their fonts are loaded in the background the first time text uses them.
+    pub fn with_font_faces(mut self, css: impl Into<String>) -> Self {
+        self.font_faces.push(css.into());
+        self
+    }
+
+    /// Set the families characters fall back to when the fonts of their text don't have glyphs for them, see [`FontFallback`]. Like fonts, the fallbacks are shared by every window and document of the process.
+    pub fn with_font_fallback(mut self, fallback: FontFallback) -> Self {
+        self.font_fallback = Some(fallback);
+        self
+    }
+
+    /// Remap or drop the input of the window before it is dispatched, for example to swap Control and Command, to disable shortcuts in a kiosk or to emulate a middle click. See [`InputRemap`] for the common remappings.
+    ///
+    /// The filter sees the input before Blitz handles it, so dropped input doesn't focus, scroll or edit elements either. Input from a [`Session`] isn't filtered.
+    pub fn with_input_filter(mut self, filter: impl InputFilter) -> Self {
+        self.input_filter = Some(Box::new(filter));
+        self
+    }
 
-pub async fn render<R: Driver>(
+    /// Let the application take screenshots of the window with [`Screenshots::capture`].
+    pub fn with_screenshots(mut self, screenshots: Screenshots) -> Self {
+        self.screenshots = Some(screenshots);
+        self
+    }
+
+    /// Run the callbacks of [`AnimationFrames`] once per frame of the window.
+    pub fn with_animation_frames(mut self, animation_frames: AnimationFrames) -> Self {
+        self.animation_frames = Some(animation_frames);
+        self
+    }
+
+    /// Let the user rearrange the panels of a [`Docking`] layout by dragging their tabs.
+    pub fn with_docking(mut self, docking: Docking) -> Self {
+        self.docking = Some(docking);
+        self
+    }
+
+    /// Show the toasts of [`Toasts`] over the document.
+    pub fn with_toasts(mut self, toasts: Toasts) -> Self {
+        self.toasts = Some(toasts);
+        self
+    }
+
+    /// Collect how long each frame takes on the CPU and the GPU and how much work its stages do into [`Stats`].
+    pub fn with_stats(mut self, stats: Stats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Let F12 show and hide a debug HUD in the top left corner of the window, with the frame rate, how long the last frame took, the vertices, indices and draw calls it drew and how many nodes changed in it. The F12 key presses don't reach the document.
+    pub fn with_debug_hud(mut self) -> Self {
+        self.debug_hud = true;
+        self
+    }
+
+    /// Draw the triangles shapes are tessellated into instead of the shapes, to see how much geometry each element generates. Rects are tessellated too.
+    pub fn with_debug_geometry(mut self, debug: DebugGeometry) -> Self {
+        self.debug_geometry = Some(debug);
+        self
+    }
+
+    /// Show the documents of [`Frames`] in `iframe` elements.
+    pub fn with_frames(mut self, frames: Frames) -> Self {
+        self.plugins
+            .register(Arc::new(crate::frame::FramePlugin(frames.clone())));
+        self.frames = Some(frames);
+        self
+    }
+
+    /// Let a test drive the window with synthetic input through the [`Session`].
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Run the DOM and the async tasks of the driver on an existing tokio runtime.
+    ///
+    /// The runtime must be multi-threaded because the event loop blocks the main thread, which would stop a current thread runtime from making progress. [`render`] returns [`RenderError::CurrentThreadRuntime`] otherwise. By default Blitz creates a runtime for the DOM, so the application doesn't need to set up tokio.
+    pub fn with_runtime(mut self, runtime: tokio::runtime::Handle) -> Self {
+        self.current_thread_runtime =
+            runtime.runtime_flavor() == tokio::runtime::RuntimeFlavor::CurrentThread;
+        self.with_executor(runtime)
+    }
+
+    /// Run the DOM and the async tasks of the driver with a custom executor instead of tokio.
+    pub fn with_executor(mut self, executor: impl Executor) -> Self {
+        self.executor = Some(Box::new(executor));
+        self
+    }
+}
+
+/// Why [`render`] couldn't open a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderError {
+    /// The runtime set with [`Config::with_runtime`] runs its tasks on the thread that awaits them, so the DOM would never make progress while the event loop blocks the main thread
+    CurrentThreadRuntime,
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::CurrentThreadRuntime => write!(
+                f,
+                "Blitz needs a multi-threaded tokio runtime, the runtime runs on the current thread"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// Open a window and render the DOM created by the driver until the window is closed.
+///
+/// The window's event loop must be owned by the main thread on most platforms, so this blocks the main thread and only returns if the config can't be rendered. The DOM and the driver run on a separate thread with the executor from [`Config::with_executor`], or a tokio runtime Blitz creates.
+///
+/// # Errors
+///
+/// Returns [`RenderError::CurrentThreadRuntime`] if [`Config::with_runtime`] set a current thread runtime.
+///
+/// # Panics
+///
+/// Panics if the window can't be opened or no graphics adapter can draw to it.
+pub fn render<R: Driver>(
     spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>) -> R + Send + 'static,
-    _cfg: Config,
-) {
+    cfg: Config,
+) -> Result<(), RenderError> {
+    if cfg.current_thread_runtime {
+        return Err(RenderError::CurrentThreadRuntime);
+    }
+
     let event_loop = EventLoop::with_user_event();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let builder = match &cfg.window_chrome {
+        Some(chrome) => chrome.window_builder(WindowBuilder::new()),
+        None => WindowBuilder::new(),
+    };
+    let window = builder.build(&event_loop).unwrap();
     let mut application =
-        ApplicationState::new(spawn_renderer, &window, event_loop.create_proxy()).await;
-    application.render();
+        ApplicationState::new(spawn_renderer, &window, event_loop.create_proxy(), cfg);
+    application.render(DirtyNodes::All);
 
     event_loop.run(move |event, _, control_flow| {
         // ControlFlow::Wait pauses the event loop if no events are available to process.
         // This is ideal for non-game applications that only update in response to user
         // input, and uses significantly less power/CPU time than ControlFlow::Poll.
-        *control_flow = ControlFlow::Wait;
+        *control_flow = match application.wake_deadline() {
+            // Wake up when the resize is over to draw a full frame, or to recognize a long press
+            Some(deadline) => ControlFlow::WaitUntil(deadline),
+            None => ControlFlow::Wait,
+        };
 
         application.send_event(&event);
 
@@ -57,9 +578,14 @@ pub async fn render<R: Driver>(
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
-            } => *control_flow = ControlFlow::Exit,
+            } => {
+                application.save_snapshot();
+                *control_flow = ControlFlow::Exit
+            }
             Event::MainEventsCleared => {
                 // Application update code.
+                application.finish_resize();
+                application.poll_gestures();
 
                 // Queue a RedrawRequested event.
                 //
@@ -75,13 +601,29 @@ pub async fn render<R: Driver>(
                 // this event rather than in MainEventsCleared, since rendering in here allows
                 // the program to gracefully handle redraws requested by the OS.
 
-                if !application.clean().is_empty() {
-                    application.render();
+                // The changes are kept until the next frame is due
+                if !application.frame_due() {
+                    return;
+                }
+                application.run_animation_frames();
+                let mut dirty = application.clean();
+                if application.reload_shaders() {
+                    dirty = DirtyNodes::All;
+                }
+                if !dirty.is_empty() || application.toasts_due() {
+                    application.render(dirty);
                 }
             }
             Event::UserEvent(_redraw) => {
+                application.run_session();
                 window.request_redraw();
             }
+            Event::WindowEvent {
+                event: WindowEvent::Moved(_) | WindowEvent::ScaleFactorChanged { .. },
+                ..
+            } => {
+                application.set_window_geometry(WindowGeometry::from_window(&window));
+            }
             Event::WindowEvent {
                 event: WindowEvent::Resized(physical_size),
                 window_id: _,
@@ -94,6 +636,31 @@ pub async fn render<R: Driver>(
     });
 }
 
+/// Like [`render`], but the DOM runs on the multi-threaded tokio runtime this is awaited in, unless [`Config::with_executor`] sets another executor.
+///
+/// This still blocks the main thread and only completes if the config can't be rendered, so it should be awaited directly in `main`. Tasks spawned on the runtime keep running on its worker threads. A current thread runtime can't drive the DOM, so Blitz creates a runtime instead.
+///
+/// # Errors
+///
+/// See [`render`].
+///
+/// # Panics
+///
+/// See [`render`].
+pub async fn render_async<R: Driver>(
+    spawn_renderer: impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>) -> R + Send + 'static,
+    mut cfg: Config,
+) -> Result<(), RenderError> {
+    if cfg.executor.is_none() {
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            if runtime.runtime_flavor() != tokio::runtime::RuntimeFlavor::CurrentThread {
+                cfg = cfg.with_runtime(runtime);
+            }
+        }
+    }
+    render(spawn_renderer, cfg)
+}
+
 pub trait Driver {
     fn update(&mut self, root: NodeMut);
     fn handle_event(&mut self, node: NodeMut, event: &str, value: Arc<EventData>, bubbles: bool);
@@ -1,15 +1,23 @@
+use std::cmp::Reverse;
+
+use dioxus_html::geometry::ClientPoint;
 use dioxus_native_core::prelude::*;
 use dioxus_native_core_macro::partial_derive_state;
 use once_cell::sync::Lazy;
-use peniko::kurbo::{Point, Shape};
+use peniko::kurbo::{Affine, Point, Shape, Vec2};
 use quadtree_rs::{area::AreaBuilder, Quadtree};
 use rustc_hash::FxHashSet;
 use shipyard::Component;
 use taffy::{prelude::Size, Taffy};
 
 use crate::{
+    gestures::GESTURE_EVENTS,
     layout::TaffyLayout,
-    render::{get_abs_pos, get_shape},
+    position::{is_fixed, is_positioned},
+    render::{get_shape, is_stacking_context, paint_layer},
+    scene::transform_rect,
+    scroll::{padding_box, scroll_effect, scroll_offset},
+    style::{Overflow, PointerEvents, Transform},
 };
 
 pub(crate) fn get_hovered(
@@ -17,28 +25,132 @@ pub(crate) fn get_hovered(
     dom: &RealDom,
     viewport_size: &Size<u32>,
     mouse_pos: Point,
-    quadtree: &Quadtree<u64, NodeId>,
+    quadtree: Option<&Quadtree<u64, NodeId>>,
 ) -> Option<NodeId> {
-    quadtree
-        .query(
-            AreaBuilder::default()
-                .anchor((mouse_pos.x as u64, mouse_pos.y as u64).into())
-                .dimensions((1, 1))
-                .build()
-                .unwrap(),
-        )
-        .find(|entry| {
-            // filter out nodes that are not actually hovered
-            if let Some(node) = dom.get(*entry.value_ref()) {
-                node.get::<MouseEffected>()
-                    .filter(|effected| effected.0)
-                    .is_some()
-                    && check_hovered(taffy, node, viewport_size, mouse_pos)
-            } else {
-                false
-            }
+    let candidates: Vec<NodeId> = match quadtree {
+        Some(quadtree) => quadtree
+            .query(
+                AreaBuilder::default()
+                    .anchor((mouse_pos.x as u64, mouse_pos.y as u64).into())
+                    .dimensions((1, 1))
+                    .build()
+                    .unwrap(),
+            )
+            .map(|entry| *entry.value_ref())
+            .collect(),
+        // Headless documents have no quadtree, so every node is checked
+        None => {
+            let mut ids = Vec::new();
+            dom.traverse_depth_first(|node| ids.push(node.id()));
+            ids
+        }
+    };
+    candidates
+        .into_iter()
+        .filter_map(|id| dom.get(id))
+        // filter out nodes that are not actually hovered
+        .filter(|node| {
+            node.get::<MouseEffected>()
+                .filter(|effected| effected.0)
+                .is_some()
+                && takes_pointer_events(*node)
+                && check_hovered(taffy, *node, viewport_size, mouse_pos)
         })
-        .map(|entry| *entry.value_ref())
+        // The quadtree returns overlapping nodes in any order, the node painted last is on top
+        .max_by_key(|node| paint_order(*node))
+        .map(|node| node.id())
+}
+
+/// The elements under a point in client coordinates, from the topmost to the bottommost. Elements are hit by the bounds of their border box like mouse events, whether or not they listen to events, unless the pointer passes through them with `pointer-events: none`. Without `all`, only the topmost element is returned.
+/// Points are mapped through the transforms of the elements, and elements are only hit inside of the padding boxes of the ancestors that clip their overflow.
+///
+/// Returns nothing if the document hasn't been laid out yet.
+pub fn hit_test(rdom: &RealDom, taffy: &Taffy, point: ClientPoint, all: bool) -> Vec<NodeId> {
+    let Some(viewport_size) = viewport_size(rdom, taffy) else {
+        return Vec::new();
+    };
+    let root = rdom.get(rdom.root_id()).unwrap();
+    let laid_out = |node: &NodeRef| node.get::<TaffyLayout>().and_then(|layout| layout.node);
+    let point = Point::new(point.x, point.y);
+    let mut hits = Vec::new();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if matches!(*node.node_type(), NodeType::Element(_))
+            && laid_out(&node).is_some()
+            && takes_pointer_events(node)
+            && check_hovered(taffy, node, &viewport_size, point)
+        {
+            hits.push(node);
+        }
+        stack.extend(node.children().into_iter().rev());
+    }
+    hits.sort_by_cached_key(|node| Reverse(paint_order(*node)));
+    if !all {
+        hits.truncate(1);
+    }
+    hits.into_iter().map(|node| node.id()).collect()
+}
+
+/// The position of a node in paint order. Nodes painted later have a greater key.
+///
+/// The key has the layer and the position in the document of each stacking context and positioned element the node is in and of the node itself, from the outermost to the innermost. Elements with a higher z-index are on top of the rest of their stacking context, wherever they are in the document, and positioned elements are on top of the elements in the flow.
+fn paint_order(node: NodeRef) -> Vec<(i64, Vec<usize>)> {
+    let mut key = Vec::new();
+    let mut current = Some(node);
+    while let Some(ancestor) = current {
+        // The root is the stacking context of every node
+        if ancestor.parent().is_some()
+            && (ancestor.id() == node.id()
+                || is_stacking_context(ancestor)
+                || is_positioned(ancestor))
+        {
+            // Negative z-indices are below the flow, which is below positioned elements and a z-index of 0, which are below positive z-indices
+            let layer = match paint_layer(ancestor) {
+                Some(z_index) if z_index < 0 => z_index as i64 * 2,
+                None => 0,
+                Some(z_index) => z_index as i64 * 2 + 1,
+            };
+            key.push((layer, document_order(ancestor)));
+        }
+        current = ancestor.parent();
+    }
+    key.reverse();
+    key
+}
+
+/// The position of a node in the document: the index of each of its ancestors and of itself among their siblings. Nodes later in the document have a greater key.
+fn document_order(node: NodeRef) -> Vec<usize> {
+    let mut indices = Vec::new();
+    let mut current = node;
+    while let Some(parent) = current.parent() {
+        let id = current.id();
+        indices.push(
+            parent
+                .child_ids()
+                .iter()
+                .position(|child| *child == id)
+                .unwrap_or_default(),
+        );
+        current = parent;
+    }
+    indices.reverse();
+    indices
+}
+
+/// False if the pointer passes through the node, see [`PointerEvents`].
+fn takes_pointer_events(node: NodeRef) -> bool {
+    node.get::<PointerEvents>()
+        .map_or(true, |pointer_events| pointer_events.0)
+}
+
+/// The size of the viewport the document was last laid out in, which is the size of the root. `None` if the document hasn't been laid out yet.
+pub(crate) fn viewport_size(rdom: &RealDom, taffy: &Taffy) -> Option<Size<u32>> {
+    let root = rdom.get(rdom.root_id()).unwrap();
+    let root_layout = taffy.layout(root.get::<TaffyLayout>()?.node?).ok()?;
+    Some(Size {
+        width: root_layout.size.width as u32,
+        height: root_layout.size.height as u32,
+    })
 }
 
 pub(crate) fn check_hovered(
@@ -47,19 +159,132 @@ pub(crate) fn check_hovered(
     viewport_size: &Size<u32>,
     mouse_pos: Point,
 ) -> bool {
+    let Some(placement) = Placement::of(taffy, node, viewport_size) else {
+        return false;
+    };
     let taffy_node = node.get::<TaffyLayout>().unwrap().node.unwrap();
     let node_layout = taffy.layout(taffy_node).unwrap();
-    get_shape(
-        node_layout,
-        node,
-        viewport_size,
-        get_abs_pos(*node_layout, taffy, node),
-    )
-    .visual_bounding_rect()
-    .contains(epaint::Pos2 {
-        x: mouse_pos.x as f32,
-        y: mouse_pos.y as f32,
-    })
+    let bounds =
+        get_shape(node_layout, node, viewport_size, placement.location).visual_bounding_rect();
+    placement.contains(bounds, mouse_pos)
+}
+
+/// Where a node is painted, the way `render_node` places it. Hit testing maps points through the same transforms, so elements are hit where they are drawn.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Placement {
+    /// The top left corner of the border box in the document, before it is transformed
+    pub location: Point,
+    /// The transforms of the node and its ancestors, from the document to the window
+    pub transform: Affine,
+    /// The padding boxes of the ancestors that clip their overflow, in window coordinates. Like the clip rects of `render_node`, it is axis aligned, so a rotated box clips to its bounds
+    pub clip: epaint::Rect,
+}
+
+impl Placement {
+    /// The placement the root and fixed elements are laid out in.
+    pub const DOCUMENT: Self = Self {
+        location: Point::ZERO,
+        transform: Affine::IDENTITY,
+        clip: epaint::Rect::EVERYTHING,
+    };
+
+    /// The placement of a node, from the placements of its ancestors. `None` if it or one of its ancestors has no layout.
+    pub fn of(taffy: &Taffy, node: NodeRef, viewport_size: &Size<u32>) -> Option<Self> {
+        // Fixed elements are painted from the root, without the transforms of their parents
+        let mut ancestors = vec![node];
+        let mut current = node;
+        while !is_fixed(current) {
+            let Some(parent) = current.parent() else {
+                break;
+            };
+            ancestors.push(parent);
+            current = parent;
+        }
+        let mut parent = Self::DOCUMENT;
+        let mut placement = None;
+        for ancestor in ancestors.into_iter().rev() {
+            let (own, children) = parent.place(taffy, ancestor, viewport_size)?;
+            placement = Some(own);
+            parent = children;
+        }
+        placement
+    }
+
+    /// Place a node laid out in `self`, the placement of the children of its parent. Returns the placement of the node and the placement its children are laid out in, or `None` if the node has no layout.
+    pub fn place(
+        self,
+        taffy: &Taffy,
+        node: NodeRef,
+        viewport_size: &Size<u32>,
+    ) -> Option<(Self, Self)> {
+        let taffy_layout = node.get::<TaffyLayout>()?;
+        let layout = taffy.layout(taffy_layout.node?).ok()?;
+        let location =
+            self.location + Vec2::new(layout.location.x as f64, layout.location.y as f64);
+        let (scroll_transform, _) = scroll_effect(taffy, node, location);
+        // Transforms are relative to the transform of the parent
+        let transform = self.transform
+            * node
+                .get::<Transform>()
+                .map_or(Affine::IDENTITY, |transform| {
+                    transform.resolve(location, &layout.size, viewport_size)
+                })
+            * scroll_transform;
+        // Children that overflow are clipped to the padding box, see render_node
+        let mut clip = self.clip;
+        if let Some(overflow) = node.get::<Overflow>() {
+            let padding_box = transform_rect(
+                transform,
+                padding_box(layout, &taffy_layout.style)
+                    .translate(epaint::vec2(location.x as f32, location.y as f32)),
+            );
+            if overflow.clips_x() {
+                clip.min.x = clip.min.x.max(padding_box.min.x);
+                clip.max.x = clip.max.x.min(padding_box.max.x);
+            }
+            if overflow.clips_y() {
+                clip.min.y = clip.min.y.max(padding_box.min.y);
+                clip.max.y = clip.max.y.min(padding_box.max.y);
+            }
+        }
+        let placement = Self {
+            location,
+            transform,
+            clip: self.clip,
+        };
+        let children = Self {
+            location: location - scroll_offset(node),
+            transform,
+            clip,
+        };
+        Some((placement, children))
+    }
+
+    /// The bounds of a rect in the coordinates of the node, once it is transformed.
+    pub fn transform_rect(&self, rect: epaint::Rect) -> epaint::Rect {
+        transform_rect(self.transform, rect)
+    }
+
+    /// True if a point in window coordinates is inside of a rect in the coordinates of the node and isn't clipped away by its ancestors.
+    pub fn contains(&self, rect: epaint::Rect, point: Point) -> bool {
+        self.to_local(point)
+            .is_some_and(|local| rect.contains(epaint::pos2(local.x as f32, local.y as f32)))
+    }
+
+    /// Map a point in window coordinates to the coordinates of the node. `None` if the point is clipped away by the ancestors of the node.
+    pub fn to_local(&self, point: Point) -> Option<Point> {
+        if !self
+            .clip
+            .contains(epaint::pos2(point.x as f32, point.y as f32))
+        {
+            return None;
+        }
+        // A transform that flattens the node to a line or a point doesn't leave anything to hit
+        if self.transform.determinant() == 0.0 {
+            return None;
+        }
+        Some(self.transform.inverse() * point)
+    }
 }
 
 #[derive(Debug, Default, PartialEq, Clone, Component)]
@@ -119,5 +344,7 @@ static MOUSE_EVENTS: Lazy<FxHashSet<&'static str>> = Lazy::new(|| {
         "mouseover",
     ]
     .into_iter()
+    // Gestures are recognized on the elements under the pointer
+    .chain(GESTURE_EVENTS)
     .collect()
 });
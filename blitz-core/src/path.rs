@@ -0,0 +1,400 @@
+use std::sync::{Arc, Mutex};
+
+use dioxus_native_core::FxHashMap;
+use epaint::{Color32, Mesh, Pos2, Rect};
+use lightningcss::traits::Parse;
+use lightningcss::values::color::CssColor;
+use lyon_tessellation::path::Path;
+use lyon_tessellation::{
+    BuffersBuilder, FillOptions, FillRule, FillTessellator, FillVertex, LineCap, LineJoin,
+    StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers,
+};
+use peniko::kurbo::{BezPath, Cap, Join, PathEl, Stroke};
+use taffy::prelude::{AvailableSpace, Size};
+
+use crate::document::to_color32;
+use crate::plugin::{CustomElement, ElementPlugin, Painter};
+use crate::stats::{self, Counter};
+use crate::util::translate_color;
+
+/// The largest distance in pixels between a curve and the triangles it is tessellated into
+pub(crate) const TOLERANCE: f32 = 0.1;
+/// How many tessellated paths are kept before the cache is cleared
+const MAX_CACHED: usize = 512;
+
+/// Draws `path` elements, so vector icons can be shown without an SVG renderer. It is registered by default.
+///
+/// The element takes the SVG path data in `d`, a `viewBox` of `min-x min-y width height` that is scaled to fit the content box while keeping its aspect ratio, `fill` and `stroke` colors, `stroke-width` in units of the view box and a `fill-rule` of `nonzero` or `evenodd`. Strokes also take `stroke-linejoin`, `stroke-linecap`, `stroke-miterlimit`, `stroke-dasharray` and `stroke-dashoffset` like in SVG. Without a view box the path is drawn in pixels from the top left corner of the content box. The fill defaults to black and the stroke to none.
+///
+/// Paths are tessellated once for each size they are drawn at and cached.
+#[derive(Default)]
+pub(crate) struct PathPlugin {
+    cache: Mutex<FxHashMap<PathKey, Arc<Mesh>>>,
+}
+
+/// The attributes a path is tessellated from and the tolerance, in units of the path
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct PathKey {
+    attributes: [Option<String>; 10],
+    /// The scale of the path rounded to a quarter power of two, which the tolerance is picked for
+    scale: i32,
+}
+
+impl ElementPlugin for PathPlugin {
+    fn tag(&self) -> &str {
+        "path"
+    }
+
+    /// Elements without a size are as large as their view box
+    fn measure(
+        &self,
+        element: &CustomElement,
+        known_dimensions: Size<Option<f32>>,
+        _: Size<AvailableSpace>,
+    ) -> Size<f32> {
+        let Some(view_box) = view_box(element) else {
+            return Size {
+                width: known_dimensions.width.unwrap_or_default(),
+                height: known_dimensions.height.unwrap_or_default(),
+            };
+        };
+        let aspect_ratio = view_box.width() / view_box.height();
+        match (known_dimensions.width, known_dimensions.height) {
+            (Some(width), Some(height)) => Size { width, height },
+            (Some(width), None) => Size {
+                width,
+                height: width / aspect_ratio,
+            },
+            (None, Some(height)) => Size {
+                width: height * aspect_ratio,
+                height,
+            },
+            (None, None) => Size {
+                width: view_box.width(),
+                height: view_box.height(),
+            },
+        }
+    }
+
+    fn paint(&self, element: &CustomElement, content_box: Rect, painter: &mut Painter) {
+        // The view box is centered in the content box and scaled to fit
+        let (scale, offset) = match view_box(element) {
+            Some(view_box) => {
+                let scale = (content_box.width() / view_box.width())
+                    .min(content_box.height() / view_box.height());
+                let offset = content_box.center() - view_box.center().to_vec2() * scale;
+                (scale, offset)
+            }
+            None => (1.0, content_box.min),
+        };
+        if scale <= 0.0 {
+            return;
+        }
+        let key = PathKey {
+            attributes: [
+                "d",
+                "fill",
+                "stroke",
+                "stroke-width",
+                "fill-rule",
+                "stroke-linejoin",
+                "stroke-linecap",
+                "stroke-miterlimit",
+                "stroke-dasharray",
+                "stroke-dashoffset",
+            ]
+            .map(|name| element.attribute(name).map(str::to_string)),
+            scale: (scale.log2() * 4.0).round() as i32,
+        };
+        let mesh = {
+            let mut cache = self.cache.lock().unwrap();
+            let cached = cache.contains_key(&key);
+            stats::count(
+                if cached {
+                    Counter::PathHits
+                } else {
+                    Counter::PathMisses
+                },
+                1,
+            );
+            if cache.len() >= MAX_CACHED && !cached {
+                cache.clear();
+            }
+            cache
+                .entry(key)
+                .or_insert_with(|| Arc::new(tessellate(element, TOLERANCE / scale)))
+                .clone()
+        };
+        if mesh.is_empty() {
+            return;
+        }
+        let mut mesh = (*mesh).clone();
+        for vertex in &mut mesh.vertices {
+            vertex.pos = offset + vertex.pos.to_vec2() * scale;
+        }
+        painter.add(epaint::Shape::mesh(mesh));
+    }
+}
+
+/// The view box of an element, if it has a valid one.
+fn view_box(element: &CustomElement) -> Option<Rect> {
+    let numbers: Vec<f32> = element
+        .attribute("viewBox")?
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    let [x, y, width, height] = numbers[..] else {
+        return None;
+    };
+    (width > 0.0 && height > 0.0)
+        .then(|| Rect::from_min_size(epaint::pos2(x, y), epaint::vec2(width, height)))
+}
+
+/// A color attribute of an element, like the `fill` of a path. `none` is transparent.
+pub(crate) fn parse_color(value: Option<&str>, default: Color32) -> Color32 {
+    let Some(value) = value else {
+        return default;
+    };
+    if value.trim() == "none" {
+        return Color32::TRANSPARENT;
+    }
+    match CssColor::parse_string(value) {
+        Ok(CssColor::CurrentColor) | Err(_) => {
+            log::warn!("unsupported color `{value}`");
+            default
+        }
+        Ok(color) => to_color32(translate_color(&color)),
+    }
+}
+
+/// Tessellate the fill and stroke of a path element in the units of its path.
+fn tessellate(element: &CustomElement, tolerance: f32) -> Mesh {
+    let mut mesh = Mesh::default();
+    let Some(d) = element.attribute("d") else {
+        return mesh;
+    };
+    let bez_path = match BezPath::from_svg(d) {
+        Ok(bez_path) => bez_path,
+        Err(err) => {
+            log::warn!("invalid path data `{d}`: {err}");
+            return mesh;
+        }
+    };
+    let path = to_lyon(&bez_path);
+
+    let fill = parse_color(element.attribute("fill"), Color32::BLACK);
+    if fill != Color32::TRANSPARENT {
+        let fill_rule = match element.attribute("fill-rule") {
+            Some("evenodd") => FillRule::EvenOdd,
+            _ => FillRule::NonZero,
+        };
+        let mut buffers: VertexBuffers<Pos2, u32> = VertexBuffers::new();
+        let result = FillTessellator::new().tessellate_path(
+            &path,
+            &FillOptions::tolerance(tolerance).with_fill_rule(fill_rule),
+            &mut BuffersBuilder::new(&mut buffers, |vertex: FillVertex| {
+                epaint::pos2(vertex.position().x, vertex.position().y)
+            }),
+        );
+        match result {
+            Ok(()) => append(&mut mesh, &buffers, fill),
+            Err(err) => log::warn!("failed to fill path `{d}`: {err:?}"),
+        }
+    }
+
+    let stroke_color = parse_color(element.attribute("stroke"), Color32::TRANSPARENT);
+    let stroke = parse_stroke(element);
+    let stroked = stroke_mesh(&bez_path, &stroke, stroke_color, tolerance);
+    mesh.append(stroked);
+    mesh
+}
+
+/// The stroke options of a path element, with the defaults of SVG.
+fn parse_stroke(element: &CustomElement) -> Stroke {
+    let number = |name: &str| {
+        element
+            .attribute(name)
+            .and_then(|value| value.trim().trim_end_matches("px").parse::<f64>().ok())
+    };
+    let join = match element.attribute("stroke-linejoin").map(str::trim) {
+        Some("round") => Join::Round,
+        Some("bevel") => Join::Bevel,
+        _ => Join::Miter,
+    };
+    let cap = match element.attribute("stroke-linecap").map(str::trim) {
+        Some("round") => Cap::Round,
+        Some("square") => Cap::Square,
+        _ => Cap::Butt,
+    };
+    let mut dashes: Vec<f64> = element
+        .attribute("stroke-dasharray")
+        .and_then(|dashes| {
+            dashes
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|dash| !dash.is_empty())
+                .map(|dash| dash.trim_end_matches("px").parse::<f64>().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    // An odd number of dashes is repeated to get an even number
+    if dashes.len() % 2 == 1 {
+        dashes.extend_from_within(..);
+    }
+    Stroke::new(number("stroke-width").unwrap_or(1.0))
+        .with_join(join)
+        .with_caps(cap)
+        .with_miter_limit(number("stroke-miterlimit").unwrap_or(4.0))
+        .with_dashes(number("stroke-dashoffset").unwrap_or(0.0), dashes)
+}
+
+/// Tessellate the outline of a path with the width, joins, caps, miter limit and dashes of a stroke. `tolerance` is in units of the path.
+pub(crate) fn stroke_mesh(path: &BezPath, stroke: &Stroke, color: Color32, tolerance: f32) -> Mesh {
+    let mut mesh = Mesh::default();
+    if color == Color32::TRANSPARENT || stroke.width <= 0.0 {
+        return mesh;
+    }
+    // The dashes are cut out of the path before it is tessellated. Invalid patterns are drawn solid, like in SVG
+    let dashes = &stroke.dash_pattern;
+    let dashed = dashes.iter().all(|dash| *dash >= 0.0) && dashes.iter().sum::<f64>() > 0.0;
+    let path = if dashed {
+        to_lyon(&BezPath::from_iter(peniko::kurbo::dash(
+            path.iter(),
+            stroke.dash_offset,
+            dashes,
+        )))
+    } else {
+        to_lyon(path)
+    };
+    let cap = |cap: Cap| match cap {
+        Cap::Butt => LineCap::Butt,
+        Cap::Square => LineCap::Square,
+        Cap::Round => LineCap::Round,
+    };
+    let options = StrokeOptions::tolerance(tolerance)
+        .with_line_width(stroke.width as f32)
+        .with_line_join(match stroke.join {
+            Join::Bevel => LineJoin::Bevel,
+            Join::Miter => LineJoin::Miter,
+            Join::Round => LineJoin::Round,
+        })
+        // lyon doesn't accept limits below 1
+        .with_miter_limit((stroke.miter_limit as f32).max(1.0))
+        .with_start_cap(cap(stroke.start_cap))
+        .with_end_cap(cap(stroke.end_cap));
+    let mut buffers: VertexBuffers<Pos2, u32> = VertexBuffers::new();
+    let result = StrokeTessellator::new().tessellate_path(
+        &path,
+        &options,
+        &mut BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex| {
+            epaint::pos2(vertex.position().x, vertex.position().y)
+        }),
+    );
+    match result {
+        Ok(()) => append(&mut mesh, &buffers, color),
+        Err(err) => log::warn!("failed to stroke a path: {err:?}"),
+    }
+    mesh
+}
+
+fn append(mesh: &mut Mesh, buffers: &VertexBuffers<Pos2, u32>, color: Color32) {
+    let offset = mesh.vertices.len() as u32;
+    mesh.vertices
+        .extend(buffers.vertices.iter().map(|pos| epaint::Vertex {
+            pos: *pos,
+            uv: epaint::WHITE_UV,
+            color,
+        }));
+    mesh.indices
+        .extend(buffers.indices.iter().map(|index| index + offset));
+}
+
+fn to_lyon(bez_path: &BezPath) -> Path {
+    let point = |point: peniko::kurbo::Point| {
+        lyon_tessellation::math::point(point.x as f32, point.y as f32)
+    };
+    let mut builder = Path::builder();
+    let mut open = false;
+    // The start of the last subpath. Like in SVG, segments after a close without a move start a new subpath there
+    let mut start = None;
+    for element in bez_path.elements() {
+        if !open && !matches!(element, PathEl::MoveTo(_) | PathEl::ClosePath) {
+            // Segments without a start point are invalid, kurbo always starts with a move
+            let Some(start) = start else {
+                continue;
+            };
+            builder.begin(start);
+            open = true;
+        }
+        match *element {
+            PathEl::MoveTo(to) => {
+                if open {
+                    builder.end(false);
+                }
+                builder.begin(point(to));
+                start = Some(point(to));
+                open = true;
+            }
+            PathEl::LineTo(to) => {
+                builder.line_to(point(to));
+            }
+            PathEl::QuadTo(ctrl, to) => {
+                builder.quadratic_bezier_to(point(ctrl), point(to));
+            }
+            PathEl::CurveTo(ctrl1, ctrl2, to) => {
+                builder.cubic_bezier_to(point(ctrl1), point(ctrl2), point(to));
+            }
+            PathEl::ClosePath => {
+                if open {
+                    builder.end(true);
+                    open = false;
+                }
+            }
+        }
+    }
+    if open {
+        builder.end(false);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lyon_tessellation::path::Event;
+    use peniko::kurbo::Point;
+
+    /// The start and end points and whether they are closed of the subpaths of a path
+    fn subpaths(path: &Path) -> Vec<([f32; 2], [f32; 2], bool)> {
+        path.iter()
+            .filter_map(|event| match event {
+                Event::End { last, first, close } => {
+                    Some(([first.x, first.y], [last.x, last.y], close))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn segments_after_a_close_start_at_the_closed_subpath() {
+        let bez_path = BezPath::from_vec(vec![
+            PathEl::MoveTo(Point::new(0.0, 0.0)),
+            PathEl::LineTo(Point::new(10.0, 0.0)),
+            PathEl::LineTo(Point::new(10.0, 10.0)),
+            PathEl::ClosePath,
+            PathEl::LineTo(Point::new(0.0, 10.0)),
+        ]);
+        assert_eq!(
+            subpaths(&to_lyon(&bez_path)),
+            [
+                ([0.0, 0.0], [10.0, 10.0], true),
+                ([0.0, 0.0], [0.0, 10.0], false)
+            ]
+        );
+        // Parsed paths give the same subpaths
+        let parsed = BezPath::from_svg("M0 0 L10 0 L10 10 Z L0 10").unwrap();
+        assert_eq!(subpaths(&to_lyon(&parsed)), subpaths(&to_lyon(&bez_path)));
+    }
+}
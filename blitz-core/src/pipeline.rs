@@ -0,0 +1,245 @@
+use std::ffi::CStr;
+
+use beuk::ash::vk;
+use beuk::ctx::RenderContext;
+use naga::back::spv;
+use naga::front::glsl;
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+const ENTRY_POINT: &CStr = c"main";
+
+/// A graphics pipeline that draws to the swapchain or to images with its format.
+pub(crate) struct Pipeline {
+    pub pipeline: vk::Pipeline,
+    pub layout: vk::PipelineLayout,
+}
+
+/// How a [`Pipeline`] is built
+pub(crate) struct PipelineDescriptor<'a> {
+    pub vertex_source: &'a str,
+    pub fragment_source: &'a str,
+    pub vertex_input: vk::PipelineVertexInputStateCreateInfo<'a>,
+    /// The descriptor sets the shaders read from, in the order of their set numbers
+    pub set_layouts: &'a [vk::DescriptorSetLayout],
+    /// The size of the push constants in bytes, the size of [`crate::backend::DrawConstants`] for the pipelines that draw shapes
+    pub push_constants_size: u32,
+    pub samples: vk::SampleCountFlags,
+    pub blend: Blend,
+}
+
+/// How the colors a [`Pipeline`] draws are combined with what was drawn before
+#[derive(Clone, Copy)]
+pub(crate) enum Blend {
+    /// Premultiplied colors are blended over with the inverse of their alpha
+    Over,
+    /// Colors replace what was drawn before
+    Replace,
+    /// Each color channel is multiplied with the inverse of the channel that is drawn, and alpha with the inverse of alpha. It darkens the pixels below subpixel text by the coverage of each channel
+    Coverage,
+    /// Colors are added to what was drawn before, after [`Blend::Coverage`] made room for them
+    Add,
+}
+
+impl Pipeline {
+    /// Compile the GLSL shaders and build the pipeline. Returns the error of the compiler if a shader doesn't compile.
+    pub fn new(ctx: &RenderContext, descriptor: &PipelineDescriptor) -> Result<Self, String> {
+        let device = &ctx.device;
+        let vertex_shader =
+            compile_shader(device, descriptor.vertex_source, naga::ShaderStage::Vertex)?;
+        let fragment_shader = match compile_shader(
+            device,
+            descriptor.fragment_source,
+            naga::ShaderStage::Fragment,
+        ) {
+            Ok(shader) => shader,
+            Err(err) => {
+                unsafe { device.destroy_shader_module(vertex_shader, None) };
+                return Err(err);
+            }
+        };
+
+        let push_constant_range = vk::PushConstantRange::default()
+            .stage_flags(vk::ShaderStageFlags::ALL_GRAPHICS)
+            .offset(0)
+            .size(descriptor.push_constants_size);
+        let layout = unsafe {
+            device.create_pipeline_layout(
+                &vk::PipelineLayoutCreateInfo::default()
+                    .set_layouts(descriptor.set_layouts)
+                    .push_constant_ranges(std::slice::from_ref(&push_constant_range)),
+                None,
+            )
+        }
+        .unwrap();
+
+        let stages = [
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(vertex_shader)
+                .name(ENTRY_POINT),
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(fragment_shader)
+                .name(ENTRY_POINT),
+        ];
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        // The viewport and the scissor are set after a pass begins, because passes only draw to the damaged area of the frame and cached layers have sizes of their own
+        let viewport = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+        let rasterization = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .cull_mode(vk::CullModeFlags::NONE)
+            .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+            .line_width(1.0);
+        let multisample = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(descriptor.samples);
+        // Colors are premultiplied, so they are blended with the inverse of their alpha
+        let (src_color, dst_color, src_alpha, dst_alpha) = match descriptor.blend {
+            Blend::Over | Blend::Replace => (
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            ),
+            Blend::Coverage => (
+                vk::BlendFactor::ZERO,
+                vk::BlendFactor::ONE_MINUS_SRC_COLOR,
+                vk::BlendFactor::ZERO,
+                vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            ),
+            Blend::Add => (
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ONE,
+                vk::BlendFactor::ONE,
+            ),
+        };
+        let blend_attachments = [vk::PipelineColorBlendAttachmentState::default()
+            .blend_enable(!matches!(descriptor.blend, Blend::Replace))
+            .src_color_blend_factor(src_color)
+            .dst_color_blend_factor(dst_color)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(src_alpha)
+            .dst_alpha_blend_factor(dst_alpha)
+            .alpha_blend_op(vk::BlendOp::ADD)
+            .color_write_mask(vk::ColorComponentFlags::RGBA)];
+        let blend =
+            vk::PipelineColorBlendStateCreateInfo::default().attachments(&blend_attachments);
+        // Pipelines draw with dynamic rendering, so they only need the format of the target instead of a render pass
+        let formats = [ctx.render_swapchain.surface_format.format];
+        let mut rendering =
+            vk::PipelineRenderingCreateInfo::default().color_attachment_formats(&formats);
+        let info = vk::GraphicsPipelineCreateInfo::default()
+            .stages(&stages)
+            .vertex_input_state(&descriptor.vertex_input)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport)
+            .rasterization_state(&rasterization)
+            .multisample_state(&multisample)
+            .color_blend_state(&blend)
+            .dynamic_state(&dynamic_state)
+            .layout(layout)
+            .push_next(&mut rendering);
+        let pipeline = unsafe {
+            device.create_graphics_pipelines(
+                vk::PipelineCache::null(),
+                std::slice::from_ref(&info),
+                None,
+            )
+        }
+        .map(|pipelines| pipelines[0])
+        .map_err(|(_, err)| err.to_string());
+        // The modules are only needed while the pipeline is built
+        unsafe {
+            device.destroy_shader_module(vertex_shader, None);
+            device.destroy_shader_module(fragment_shader, None);
+        }
+        match pipeline {
+            Ok(pipeline) => Ok(Self { pipeline, layout }),
+            Err(err) => {
+                unsafe { device.destroy_pipeline_layout(layout, None) };
+                Err(err)
+            }
+        }
+    }
+
+    pub fn bind(&self, device: &beuk::ash::Device, command_buffer: vk::CommandBuffer) {
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline,
+            )
+        };
+    }
+
+    /// Free the pipeline. The GPU must be done using it.
+    pub fn destroy(&self, device: &beuk::ash::Device) {
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.layout, None);
+        }
+    }
+}
+
+/// Set the viewport of the pass that was begun to a target with a size, and the scissor to the area of it that is drawn to.
+pub(crate) fn set_viewport(
+    device: &beuk::ash::Device,
+    command_buffer: vk::CommandBuffer,
+    extent: vk::Extent2D,
+    area: vk::Rect2D,
+) {
+    let viewport = vk::Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: extent.width as f32,
+        height: extent.height as f32,
+        min_depth: 0.0,
+        max_depth: 1.0,
+    };
+    unsafe {
+        device.cmd_set_viewport(command_buffer, 0, std::slice::from_ref(&viewport));
+        device.cmd_set_scissor(command_buffer, 0, std::slice::from_ref(&area));
+    }
+}
+
+/// Compile a GLSL shader to SPIR-V with naga. The errors of the compiler are returned with the lines they point to.
+fn compile_shader(
+    device: &beuk::ash::Device,
+    source: &str,
+    stage: naga::ShaderStage,
+) -> Result<vk::ShaderModule, String> {
+    let module = glsl::Frontend::default()
+        .parse(&glsl::Options::from(stage), source)
+        .map_err(|errors| {
+            errors
+                .iter()
+                .map(|error| {
+                    let line = error.meta.location(source).line_number;
+                    format!("line {line}: {}", error.kind)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })?;
+    let info = Validator::new(ValidationFlags::all(), Capabilities::PUSH_CONSTANT)
+        .validate(&module)
+        .map_err(|err| format!("{:?}", err.into_inner()))?;
+    let mut options = spv::Options::default();
+    // The shaders are written for the clip space of Vulkan, which naga would otherwise flip like the clip space of wgpu
+    options
+        .flags
+        .remove(spv::WriterFlags::ADJUST_COORDINATE_SPACE);
+    let pipeline_options = spv::PipelineOptions {
+        shader_stage: stage,
+        entry_point: "main".to_string(),
+    };
+    let code = spv::write_vec(&module, &info, &options, Some(&pipeline_options))
+        .map_err(|err| err.to_string())?;
+    unsafe { device.create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&code), None) }
+        .map_err(|err| err.to_string())
+}
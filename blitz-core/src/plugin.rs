@@ -0,0 +1,323 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core::FxHashMap;
+use peniko::kurbo::{Affine, BezPath, Point, Stroke};
+use taffy::prelude::{AvailableSpace, Size};
+use taffy::Taffy;
+
+use crate::chart::{area_mesh, bars_mesh, polyline_mesh};
+use crate::coordinates::element_transform;
+use crate::layout::TaffyLayout;
+use crate::path::{stroke_mesh, TOLERANCE};
+use crate::render::{content_box, render_document};
+use crate::scene::{transform_rect, Scene};
+use crate::style::TextStyle;
+use crate::text::{shape, text_mesh};
+use crate::EventData;
+
+/// A custom element type that is laid out, painted and sent events by a plugin instead of Blitz. Register it with [`crate::Config::with_element_plugin`].
+///
+/// The element is still styled like any other element, so it gets a background and border, but it is a leaf: its children are not laid out or drawn.
+pub trait ElementPlugin: Send + Sync + 'static {
+    /// The tag of the elements the plugin handles
+    fn tag(&self) -> &str;
+
+    /// Measure the content of an element. `known_dimensions` are the sizes the styles already decide and `available_space` is the space the parent offers.
+    ///
+    /// The default has no content, so the element is only as large as its styles make it.
+    fn measure(
+        &self,
+        element: &CustomElement,
+        known_dimensions: Size<Option<f32>>,
+        available_space: Size<AvailableSpace>,
+    ) -> Size<f32> {
+        let _ = (element, available_space);
+        Size {
+            width: known_dimensions.width.unwrap_or_default(),
+            height: known_dimensions.height.unwrap_or_default(),
+        }
+    }
+
+    /// Paint the content of an element into its content box, which is given in window coordinates before the element's transform is applied.
+    fn paint(&self, element: &CustomElement, content_box: epaint::Rect, painter: &mut Painter);
+
+    /// Scroll the content of an element by `delta` when the mouse wheel is turned over `point`, which is relative to the content box. A positive delta moves the content right and down. Return true if the content moved, so the window is drawn again.
+    ///
+    /// The default doesn't scroll, so the wheel scrolls the elements around it instead.
+    fn scroll(&self, element: &CustomElement, point: epaint::Pos2, delta: epaint::Vec2) -> bool {
+        let _ = (element, point, delta);
+        false
+    }
+
+    /// Follow the primary mouse button and the mouse over an element, for content that is dragged like the columns of a table. `point` is relative to the content box. After the button is pressed on the element, it gets the moves and the release even outside of it. Return true if the content changed, so the window is drawn again.
+    ///
+    /// The events of the DOM are sent as well. The default ignores the mouse.
+    fn pointer(&self, element: &CustomElement, phase: PointerPhase, point: epaint::Pos2) -> bool {
+        let _ = (element, phase, point);
+        false
+    }
+
+    /// True if the plugin handles gestures like pinches in [`ElementPlugin::handle_event`], so they are sent to its elements as if they listened for them, for content that zooms like a map.
+    ///
+    /// The default leaves gestures to the elements that listen for them.
+    fn handles_gestures(&self) -> bool {
+        false
+    }
+
+    /// True while the content of the elements of the plugin animates, so the window draws them every frame until it returns false.
+    ///
+    /// The default doesn't animate.
+    fn animating(&self) -> bool {
+        false
+    }
+
+    /// Handle an event on an element. Return true to stop the event from reaching the driver.
+    ///
+    /// The attributes of the element can be changed to update how it is drawn.
+    fn handle_event(&self, node: NodeMut, event: &str, data: &EventData) -> bool {
+        let _ = (node, event, data);
+        false
+    }
+}
+
+/// What the mouse did over a custom element, see [`ElementPlugin::pointer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerPhase {
+    /// The primary button was pressed
+    Down,
+    Move,
+    /// The primary button was released
+    Up,
+}
+
+/// A point in client coordinates relative to the content box of an element, for [`ElementPlugin::pointer`].
+pub(crate) fn content_point(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    element: NodeId,
+    point: Point,
+) -> Option<epaint::Pos2> {
+    let local = element_transform(rdom, taffy, element)?.inverse() * point;
+    let node = rdom.get(element)?;
+    let taffy_layout = node.get::<TaffyLayout>()?;
+    let layout = taffy.layout(taffy_layout.node?).ok()?;
+    let content_box = content_box(layout, &taffy_layout.style, Point::ZERO);
+    Some(epaint::pos2(local.x as f32, local.y as f32) - content_box.min.to_vec2())
+}
+
+/// The tag and attributes of a custom element. Styles are left out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CustomElement {
+    pub tag: String,
+    pub attributes: FxHashMap<String, String>,
+}
+
+impl CustomElement {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    pub(crate) fn from_node(node: NodeRef) -> Option<Self> {
+        match &*node.node_type() {
+            NodeType::Element(element) => Some(Self {
+                tag: element.tag.clone(),
+                attributes: element
+                    .attributes
+                    .iter()
+                    .filter(|(attribute, _)| attribute.namespace.is_none())
+                    .filter_map(|(attribute, value)| {
+                        Some((attribute.name.clone(), value.as_text()?.to_string()))
+                    })
+                    .collect(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Adds the shapes of a custom element to the frame. Shapes are clipped like the rest of the element and use its transform.
+pub struct Painter<'a> {
+    scene: &'a mut Scene,
+    clip: epaint::Rect,
+}
+
+impl<'a> Painter<'a> {
+    pub(crate) fn new(scene: &'a mut Scene, clip: epaint::Rect) -> Self {
+        Self { scene, clip }
+    }
+
+    pub fn add(&mut self, shape: impl Into<epaint::Shape>) {
+        self.scene.push(self.clip, shape.into());
+    }
+
+    /// Draw the outline of a path with the width, joins, caps, miter limit and dashes of a stroke. The path is in the same coordinates as the content box passed to [`ElementPlugin::paint`].
+    pub fn stroke_path(&mut self, path: &BezPath, stroke: &Stroke, color: epaint::Color32) {
+        // The tolerance is in pixels on screen, so scaled paths stay smooth
+        let scale = self.scene.transform.determinant().abs().sqrt() as f32;
+        if scale <= 0.0 {
+            return;
+        }
+        let mesh = stroke_mesh(path, stroke, color, TOLERANCE / scale);
+        if !mesh.is_empty() {
+            self.add(epaint::Shape::mesh(mesh));
+        }
+    }
+
+    /// Draw a line through points with a width, like a series of a line chart. Points that aren't finite leave a gap in the line.
+    ///
+    /// The line is tessellated into one triangle strip with mitered joins. Points less than half a pixel on screen from the last point that was kept are skipped, so dense series with thousands of points cost about as many triangles as the line has pixels.
+    pub fn polyline(&mut self, points: &[epaint::Pos2], width: f32, color: epaint::Color32) {
+        let Some(min_distance) = self.half_pixel() else {
+            return;
+        };
+        let mesh = polyline_mesh(points, width, color, min_distance);
+        if !mesh.is_empty() {
+            self.add(epaint::Shape::mesh(mesh));
+        }
+    }
+
+    /// Fill the area between a line through points and a horizontal line at `baseline`, like the area under a series. Points are skipped and leave gaps like in [`Painter::polyline`].
+    pub fn area(&mut self, points: &[epaint::Pos2], baseline: f32, color: epaint::Color32) {
+        let Some(min_distance) = self.half_pixel() else {
+            return;
+        };
+        let mesh = area_mesh(points, baseline, color, min_distance);
+        if !mesh.is_empty() {
+            self.add(epaint::Shape::mesh(mesh));
+        }
+    }
+
+    /// Fill rects with one color in one mesh, like the bars of a series of a bar chart.
+    pub fn bars(&mut self, bars: &[epaint::Rect], color: epaint::Color32) {
+        let mesh = bars_mesh(bars, color);
+        if !mesh.is_empty() {
+            self.add(epaint::Shape::mesh(mesh));
+        }
+    }
+
+    /// Draw a line of text in the default font aligned to a point, like the label of a tick of an axis, and return the rect it covers. `RIGHT_CENTER` puts the end of the text at the point, for the labels left of a vertical axis, and `CENTER_TOP` centers the text below the point, for the labels under a horizontal axis.
+    pub fn label(
+        &mut self,
+        text: &str,
+        anchor: epaint::Pos2,
+        align: epaint::emath::Align2,
+        font_size: f32,
+        color: epaint::Color32,
+    ) -> epaint::Rect {
+        let style = TextStyle {
+            font_size,
+            ..TextStyle::default()
+        };
+        let line = shape(text, &style);
+        let rect = align.anchor_rect(epaint::Rect::from_min_size(
+            anchor,
+            epaint::vec2(line.width, line.height()),
+        ));
+        self.text(text, rect.min, font_size, color);
+        rect
+    }
+
+    /// Half of a pixel on screen in the units of the shapes, or `None` if nothing is visible at the scale of the transform.
+    fn half_pixel(&self) -> Option<f32> {
+        self.scale().map(|scale| 0.5 / scale)
+    }
+
+    /// The number of pixels on screen per unit of the shapes, or `None` if nothing is visible at the scale of the transform. Meshes of text have to be built at this scale to be sharp.
+    pub(crate) fn scale(&self) -> Option<f32> {
+        let scale = self.scene.transform.determinant().abs().sqrt() as f32;
+        (scale > 0.0).then_some(scale)
+    }
+
+    /// Draw a line of text in the default font with the top left corner of the line at `position`, and return its width. Glyphs are rasterized into the texture atlas once and batched with the rest of the text of the frame.
+    pub fn text(
+        &mut self,
+        text: &str,
+        position: epaint::Pos2,
+        font_size: f32,
+        color: epaint::Color32,
+    ) -> f32 {
+        let style = TextStyle {
+            font_size,
+            ..TextStyle::default()
+        };
+        let line = shape(text, &style);
+        let scale = self.scene.transform.determinant().abs().sqrt() as f32;
+        let mesh = text_mesh(
+            &line,
+            position + epaint::vec2(0.0, line.ascent),
+            color,
+            scale,
+            false,
+        );
+        if !mesh.is_empty() {
+            self.add(epaint::Shape::mesh(mesh));
+        }
+        line.width
+    }
+
+    /// Paint with the shapes clipped to a rect in the same coordinates as the content box, for example to cut the text of a cell off at its edge.
+    pub fn clipped(&mut self, rect: epaint::Rect, paint: impl FnOnce(&mut Painter)) {
+        let clip = transform_rect(self.scene.transform, rect).intersect(self.clip);
+        paint(&mut Painter::new(self.scene, clip));
+    }
+
+    /// Draw another document into a rect, clipped to it.
+    pub(crate) fn add_document(
+        &mut self,
+        rdom: &RealDom,
+        taffy: &Taffy,
+        rect: epaint::Rect,
+        plugins: &Plugins,
+    ) {
+        let outer_transform = self.scene.transform;
+        let transform = outer_transform * Affine::translate((rect.min.x as f64, rect.min.y as f64));
+        let clip = transform_rect(outer_transform, rect).intersect(self.clip);
+        // The nodes of the document have the same ids as nodes of the outer document, so their bounds are left out. The area they paint still counts for the element that hosts the document
+        let node_bounds = std::mem::take(&mut self.scene.node_bounds);
+        render_document(
+            rdom,
+            taffy,
+            self.scene,
+            &Size {
+                width: rect.width() as u32,
+                height: rect.height() as u32,
+            },
+            clip,
+            transform,
+            plugins,
+        );
+        self.scene.node_bounds = node_bounds;
+        self.scene.transform = outer_transform;
+    }
+}
+
+/// The plugins registered for a document, by tag.
+#[derive(Clone, Default)]
+pub(crate) struct Plugins(FxHashMap<String, Arc<dyn ElementPlugin>>);
+
+impl Plugins {
+    pub fn register(&mut self, plugin: Arc<dyn ElementPlugin>) {
+        self.0.insert(plugin.tag().to_string(), plugin);
+    }
+
+    pub fn get(&self, tag: &str) -> Option<&Arc<dyn ElementPlugin>> {
+        self.0.get(tag)
+    }
+
+    /// True if one of the plugins animates the content of its elements.
+    pub fn animating(&self) -> bool {
+        self.0.values().any(|plugin| plugin.animating())
+    }
+
+    /// The plugin that handles an element, if any.
+    pub fn for_node(&self, node: NodeRef) -> Option<&Arc<dyn ElementPlugin>> {
+        if self.0.is_empty() {
+            return None;
+        }
+        match &*node.node_type() {
+            NodeType::Element(element) => self.get(&element.tag),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use dioxus_native_core::prelude::*;
+use taffy::geometry::Rect;
+use taffy::style::{LengthPercentage, LengthPercentageAuto, Style};
+
+use crate::grid::parse_length;
+use crate::layout::TaffyLayout;
+
+/// How an element is positioned, from `position`.
+///
+/// Taffy places absolutely positioned elements in their parent, so the parent is the containing block of an absolute element whether or not it is positioned itself. Fixed elements are laid out in the root instead, so they are placed in the viewport and don't scroll with their ancestors.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum Position {
+    /// In the flow of its parent, where `top`, `right`, `bottom` and `left` are ignored
+    #[default]
+    Static,
+    /// In the flow of its parent and then moved by its offsets, without moving its siblings
+    Relative,
+    /// Out of the flow, placed in its parent by its offsets
+    Absolute,
+    /// Out of the flow, placed in the viewport by its offsets
+    Fixed,
+}
+
+impl Position {
+    /// True unless the element is static. Positioned elements are painted over the elements in the flow of their stacking context, like an element with a z-index of 0.
+    pub fn is_positioned(self) -> bool {
+        self != Self::Static
+    }
+}
+
+/// Apply `position` and its offsets to the style of an element, after [`dioxus_native_core::layout_attributes::apply_layout_attributes`]. Offsets in `em` are relative to `font_size`.
+///
+/// Returns `None` if the value can't be parsed. Other properties are left alone.
+pub(crate) fn apply_position_attribute(
+    name: &str,
+    value: &str,
+    font_size: f32,
+    style: &mut Style,
+    position: &mut Position,
+) -> Option<()> {
+    let value = value.trim();
+    match name {
+        "position" => {
+            *position = match value {
+                "static" => Position::Static,
+                "relative" => Position::Relative,
+                "absolute" => Position::Absolute,
+                "fixed" => Position::Fixed,
+                _ => return None,
+            }
+        }
+        "top" => style.inset.top = parse_offset(value, font_size)?,
+        "right" => style.inset.right = parse_offset(value, font_size)?,
+        "bottom" => style.inset.bottom = parse_offset(value, font_size)?,
+        "left" => style.inset.left = parse_offset(value, font_size)?,
+        "inset" => {
+            // One to four offsets in the order of margin: top, right, bottom and left
+            let offsets: Vec<LengthPercentageAuto> = value
+                .split_whitespace()
+                .map(|offset| parse_offset(offset, font_size))
+                .collect::<Option<_>>()?;
+            let (top, right, bottom, left) = match offsets[..] {
+                [all] => (all, all, all, all),
+                [vertical, horizontal] => (vertical, horizontal, vertical, horizontal),
+                [top, horizontal, bottom] => (top, horizontal, bottom, horizontal),
+                [top, right, bottom, left] => (top, right, bottom, left),
+                _ => return None,
+            };
+            style.inset = Rect {
+                left,
+                right,
+                top,
+                bottom,
+            };
+        }
+        _ => {}
+    }
+    Some(())
+}
+
+/// Set the position of a style once all of its attributes are applied. Static elements ignore their offsets.
+pub(crate) fn finish_position(position: Position, style: &mut Style) {
+    style.position = match position {
+        Position::Static | Position::Relative => taffy::style::Position::Relative,
+        Position::Absolute | Position::Fixed => taffy::style::Position::Absolute,
+    };
+    if position == Position::Static {
+        style.inset = Style::default().inset;
+    }
+}
+
+fn parse_offset(value: &str, font_size: f32) -> Option<LengthPercentageAuto> {
+    match value {
+        "auto" => Some(LengthPercentageAuto::Auto),
+        value => match parse_length(value, font_size)? {
+            LengthPercentage::Points(points) => Some(LengthPercentageAuto::Points(points)),
+            LengthPercentage::Percent(percent) => Some(LengthPercentageAuto::Percent(percent)),
+        },
+    }
+}
+
+/// True if an element is positioned, see [`Position::is_positioned`].
+pub(crate) fn is_positioned(node: NodeRef) -> bool {
+    node.get::<TaffyLayout>()
+        .is_some_and(|layout| layout.position.is_positioned())
+}
+
+/// True if an element has `position: fixed`, so it is laid out in the root and its location is relative to the viewport instead of its parent.
+pub(crate) fn is_fixed(node: NodeRef) -> bool {
+    node.get::<TaffyLayout>()
+        .is_some_and(|layout| layout.position == Position::Fixed)
+}
@@ -0,0 +1,560 @@
+/// How much of a QR code can be damaged or covered while it still reads. Higher levels make the code larger for the same data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ErrorCorrection {
+    /// About 7% of the code can be restored
+    Low,
+    /// About 15% of the code can be restored
+    #[default]
+    Medium,
+    /// About 25% of the code can be restored
+    Quartile,
+    /// About 30% of the code can be restored
+    High,
+}
+
+impl ErrorCorrection {
+    fn index(self) -> usize {
+        match self {
+            Self::Low => 0,
+            Self::Medium => 1,
+            Self::Quartile => 2,
+            Self::High => 3,
+        }
+    }
+
+    /// The bits of the level in the format information
+    fn format_bits(self) -> u32 {
+        match self {
+            Self::Low => 1,
+            Self::Medium => 0,
+            Self::Quartile => 3,
+            Self::High => 2,
+        }
+    }
+}
+
+/// The error correction codewords of each block, by level and version
+#[rustfmt::skip]
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28, 30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28],
+    [0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30, 30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+];
+
+/// The blocks the codewords are split into, by level and version
+#[rustfmt::skip]
+const ERROR_CORRECTION_BLOCKS: [[u8; 41]; 4] = [
+    [0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13, 14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25],
+    [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49],
+    [0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29, 34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68],
+    [0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32, 35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81],
+];
+
+/// The modules of a QR code in byte mode, made like the reference algorithm of ISO/IEC 18004.
+pub(crate) struct QrCode {
+    /// The number of modules on each side, without the quiet zone
+    pub size: usize,
+    /// Row by row, true where the module is dark
+    pub modules: Vec<bool>,
+    /// The modules of the finder, timing and alignment patterns and of the format and version information, which the mask leaves alone
+    function: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encode bytes in the smallest version that fits them at a level of error correction, or `None` if they don't fit in version 40.
+    pub fn encode(data: &[u8], level: ErrorCorrection) -> Option<Self> {
+        let version = (1..=40).find(|version| {
+            let count_bits = if *version < 10 { 8 } else { 16 };
+            data.len() < 1 << count_bits
+                && 4 + count_bits + data.len() * 8 <= data_codewords(*version, level) * 8
+        })?;
+
+        let mut bits = BitBuffer::default();
+        // The mode indicator of byte mode, the number of bytes and the bytes
+        bits.push(0b0100, 4);
+        bits.push(data.len() as u32, if version < 10 { 8 } else { 16 });
+        for byte in data {
+            bits.push(*byte as u32, 8);
+        }
+        // The terminator, then zeros to the end of the byte, then pad bytes to the capacity
+        let capacity = data_codewords(version, level) * 8;
+        bits.push(0, (capacity - bits.0.len()).min(4));
+        bits.push(0, (8 - bits.0.len() % 8) % 8);
+        for pad in [0xec, 0x11].into_iter().cycle() {
+            if bits.0.len() >= capacity {
+                break;
+            }
+            bits.push(pad, 8);
+        }
+        let codewords: Vec<u8> = bits
+            .0
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, bit| acc << 1 | *bit as u8))
+            .collect();
+
+        let size = version * 4 + 17;
+        let mut code = Self {
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        };
+        code.draw_function_patterns(version);
+        code.draw_codewords(&add_error_correction(&codewords, version, level));
+        // The mask that leaves the fewest patterns that confuse readers is used
+        let mask = (0..8)
+            .min_by_key(|mask| {
+                code.apply_mask(*mask);
+                code.draw_format(level, *mask);
+                let penalty = code.penalty();
+                code.apply_mask(*mask);
+                penalty
+            })
+            .unwrap();
+        code.apply_mask(mask);
+        code.draw_format(level, mask);
+        Some(code)
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        for (x, y) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            self.draw_finder(x, y);
+        }
+        let positions = alignment_positions(version);
+        let last = positions.len().saturating_sub(1);
+        for (i, x) in positions.iter().enumerate() {
+            for (j, y) in positions.iter().enumerate() {
+                // Alignment patterns aren't drawn over the finders
+                if (i, j) != (0, 0) && (i, j) != (0, last) && (i, j) != (last, 0) {
+                    self.draw_alignment(*x, *y);
+                }
+            }
+        }
+        // The format is drawn after the mask is chosen, but its modules are reserved now
+        self.draw_format(ErrorCorrection::Medium, 0);
+        self.draw_version(version);
+    }
+
+    /// Draw a finder pattern and the light separator around it, which is cut off at the edges of the code.
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4..=4i32 {
+            for dx in -4..=4i32 {
+                let (xx, yy) = (x as i32 + dx, y as i32 + dy);
+                if (0..self.size as i32).contains(&xx) && (0..self.size as i32).contains(&yy) {
+                    let distance = dx.abs().max(dy.abs());
+                    self.set_function(xx as usize, yy as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, x: usize, y: usize) {
+        for dy in -2..=2i32 {
+            for dx in -2..=2i32 {
+                self.set_function(
+                    (x as i32 + dx) as usize,
+                    (y as i32 + dy) as usize,
+                    dx.abs().max(dy.abs()) != 1,
+                );
+            }
+        }
+    }
+
+    /// Draw both copies of the level and mask, protected by a BCH code.
+    fn draw_format(&mut self, level: ErrorCorrection, mask: u8) {
+        let data = level.format_bits() << 3 | mask as u32;
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = (data << 10 | remainder) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        let size = self.size;
+
+        // Around the top left finder
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        // Split between the other two finders, next to the module that is always dark
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    /// Draw both copies of the version, protected by a BCH code, for versions 7 and up.
+    fn draw_version(&mut self, version: usize) {
+        if version < 7 {
+            return;
+        }
+        let mut remainder = version as u32;
+        for _ in 0..12 {
+            remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1f25);
+        }
+        let bits = (version as u32) << 12 | remainder;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let (a, b) = (self.size - 11 + i % 3, i / 3);
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Draw the codewords in the zigzag of pairs of columns from the bottom right corner, skipping the function patterns.
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let mut index = 0;
+        let mut right = size - 1;
+        loop {
+            // The vertical timing pattern is skipped as a whole column
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vertical in 0..size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let y = if upward {
+                        size - 1 - vertical
+                    } else {
+                        vertical
+                    };
+                    if !self.function[y * size + x] && index < codewords.len() * 8 {
+                        self.modules[y * size + x] =
+                            (codewords[index / 8] >> (7 - index % 8)) & 1 != 0;
+                        index += 1;
+                    }
+                }
+            }
+            if right < 3 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    /// Flip the modules that aren't function patterns where a mask pattern is true. Applying a mask twice undoes it.
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let flip = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let index = y * self.size + x;
+                if flip && !self.function[index] {
+                    self.modules[index] = !self.modules[index];
+                }
+            }
+        }
+    }
+
+    /// The penalty of the rules of the standard for long runs, blocks of one color, patterns that look like finders and unbalanced colors.
+    fn penalty(&self) -> u32 {
+        let size = self.size;
+        let mut penalty = 0;
+        let lines = (0..size).flat_map(|i| {
+            [
+                (0..size).map(|x| self.get(x, i)).collect::<Vec<_>>(),
+                (0..size).map(|y| self.get(i, y)).collect::<Vec<_>>(),
+            ]
+        });
+        for line in lines {
+            let mut run = 1;
+            for i in 1..=size {
+                if i < size && line[i] == line[i - 1] {
+                    run += 1;
+                    continue;
+                }
+                if run >= 5 {
+                    penalty += run - 2;
+                }
+                run = 1;
+            }
+            // Runs of dark, light, dark, light and dark modules in the ratio 1:1:3:1:1 with four times as many light modules on a side. The quiet zone around the code is light, so it extends the light runs at the ends
+            let mut runs = vec![size];
+            // Light runs are at the even indices
+            for dark in line.iter().chain([&false]) {
+                if *dark != (runs.len() % 2 == 0) {
+                    runs.push(0);
+                }
+                *runs.last_mut().unwrap() += 1;
+            }
+            *runs.last_mut().unwrap() += size - 1;
+            for window in runs.windows(7).step_by(2) {
+                let n = window[1];
+                if window[2..6] != [n, n * 3, n, n] {
+                    continue;
+                }
+                let (before, after) = (window[0], window[6]);
+                penalty += 40
+                    * (u32::from(before >= n * 4 && after >= n)
+                        + u32::from(after >= n * 4 && before >= n));
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.get(x, y);
+                if dark == self.get(x + 1, y)
+                    && dark == self.get(x, y + 1)
+                    && dark == self.get(x + 1, y + 1)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+        let dark = self.modules.iter().filter(|dark| **dark).count();
+        let total = self.modules.len();
+        // 10 points for each 5% the dark modules are away from half
+        let k = (((dark * 20).abs_diff(total * 10) + total - 1) / total).saturating_sub(1);
+        penalty + k as u32 * 10
+    }
+}
+
+#[derive(Default)]
+struct BitBuffer(Vec<bool>);
+
+impl BitBuffer {
+    fn push(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            self.0.push((value >> i) & 1 != 0);
+        }
+    }
+}
+
+/// The modules of a version left for codewords once the function patterns are drawn.
+fn raw_data_modules(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        modules -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+/// The codewords of a version that hold data at a level, without the error correction codewords.
+fn data_codewords(version: usize, level: ErrorCorrection) -> usize {
+    raw_data_modules(version) / 8
+        - ECC_CODEWORDS_PER_BLOCK[level.index()][version] as usize
+            * ERROR_CORRECTION_BLOCKS[level.index()][version] as usize
+}
+
+/// The centers of the alignment patterns along each axis.
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let size = version * 4 + 17;
+    let step = if version == 32 {
+        26
+    } else {
+        (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2
+    };
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Split the data codewords into blocks, add the Reed-Solomon codewords of each block and interleave the blocks.
+fn add_error_correction(data: &[u8], version: usize, level: ErrorCorrection) -> Vec<u8> {
+    let blocks = ERROR_CORRECTION_BLOCKS[level.index()][version] as usize;
+    let ecc_length = ECC_CODEWORDS_PER_BLOCK[level.index()][version] as usize;
+    let raw_codewords = raw_data_modules(version) / 8;
+    // The first blocks have one data codeword less than the rest
+    let short_blocks = blocks - raw_codewords % blocks;
+    let short_length = raw_codewords / blocks;
+    let divisor = reed_solomon_divisor(ecc_length);
+
+    let mut start = 0;
+    let blocks: Vec<Vec<u8>> = (0..blocks)
+        .map(|i| {
+            let length = short_length - ecc_length + usize::from(i >= short_blocks);
+            let mut block = data[start..start + length].to_vec();
+            start += length;
+            let ecc = reed_solomon_remainder(&block, &divisor);
+            if i < short_blocks {
+                // A placeholder, so all blocks line up when they are interleaved
+                block.push(0);
+            }
+            block.extend(ecc);
+            block
+        })
+        .collect();
+
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..blocks[0].len() {
+        for (j, block) in blocks.iter().enumerate() {
+            if i != short_length - ecc_length || j >= short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+/// The coefficients of the generator polynomial of a degree, from the highest power down without the leading 1.
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0; degree];
+    result[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; divisor.len()];
+    for byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (coefficient, value) in divisor.iter().zip(&mut result) {
+            *value ^= gf_multiply(*coefficient, factor);
+        }
+    }
+    result
+}
+
+/// Multiply in GF(2^8) modulo the polynomial 0x11d of QR codes.
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11d);
+        z ^= ((y as u32 >> i) & 1) * x as u32;
+    }
+    z as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(code: &QrCode) -> Vec<String> {
+        (0..code.size)
+            .map(|y| {
+                (0..code.size)
+                    .map(|x| if code.get(x, y) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hello_world() {
+        let code = QrCode::encode(b"HELLO WORLD", ErrorCorrection::Quartile).unwrap();
+        assert_eq!(
+            rows(&code),
+            [
+                "#######.#..#..#######",
+                "#.....#..#....#.....#",
+                "#.###.#.#..#..#.###.#",
+                "#.###.#.#.##..#.###.#",
+                "#.###.#..##.#.#.###.#",
+                "#.....#.##.#..#.....#",
+                "#######.#.#.#.#######",
+                "........#.###........",
+                ".#.#.####..#####.##.#",
+                "..####...#....##...#.",
+                ".#..#.##.#.##..#.##.#",
+                "#.###..#.####.#.##.##",
+                ".#.##.#.#.##.####.#..",
+                "........##..#...#.#..",
+                "#######.##.#..######.",
+                "#.....#.#####..#....#",
+                "#.###.#..#..###...##.",
+                "#.###.#.#.#....######",
+                "#.###.#...#.#.#.#.#.#",
+                "#.....#.#.##.#.......",
+                "#######...#.#..#.###.",
+            ]
+        );
+    }
+
+    #[test]
+    fn low_error_correction() {
+        let code = QrCode::encode(b"blitz", ErrorCorrection::Low).unwrap();
+        assert_eq!(
+            rows(&code),
+            [
+                "#######..#.##.#######",
+                "#.....#.##.#..#.....#",
+                "#.###.#.##..#.#.###.#",
+                "#.###.#..#.#..#.###.#",
+                "#.###.#.#...#.#.###.#",
+                "#.....#.#..##.#.....#",
+                "#######.#.#.#.#######",
+                "........#####........",
+                "##.#..##.##...###.##.",
+                "..#.##.##.#...#...###",
+                "...#.####.#.##....#.#",
+                ".#..#....#.#.....#.#.",
+                ".###..##....#.#.#...#",
+                "........#..#...####.#",
+                "#######.###..#.#..##.",
+                "#.....#....###.##....",
+                "#.###.#..#.#..####..#",
+                "#.###.#.##.#...#.#.##",
+                "#.###.#.....#...#.#.#",
+                "#.....#.##...###.....",
+                "#######.#..##......#.",
+            ]
+        );
+    }
+
+    #[test]
+    fn versions() {
+        let url = b"https://example.com/pair?token=abc123";
+        assert_eq!(
+            QrCode::encode(url, ErrorCorrection::Medium).unwrap().size,
+            29
+        );
+        // Version 40 holds 2953 bytes at the lowest level
+        assert_eq!(
+            QrCode::encode(&[b'a'; 2953], ErrorCorrection::Low)
+                .unwrap()
+                .size,
+            177
+        );
+        assert!(QrCode::encode(&[b'a'; 2954], ErrorCorrection::Low).is_none());
+    }
+}
@@ -0,0 +1,415 @@
+use epaint::{Color32, ColorImage, ImageData, ImageDelta, Mesh, Pos2, Rect, Rgba, Vertex};
+use peniko::kurbo::Affine;
+
+use crate::atlas::atlas_image;
+use crate::backend::{ColorMatrix, RectInstance};
+use crate::scene::{clip_mesh, transform_mesh, transform_rect, Scene};
+
+/// The most taps on each side of a pixel that are blurred, the same as in blur.wgsl. It covers three standard deviations of a 42 pixel blur
+const MAX_BLUR_TAPS: i32 = 128;
+
+/// Draw a scene into an image on the CPU.
+///
+/// Colors are blended in linear space, which matches the GPU renderer on an sRGB surface.
+pub(crate) fn rasterize(
+    scene: &Scene,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> image::RgbaImage {
+    let mut target = Target::new(width, height, scene.clear_color);
+    let texture = atlas_image();
+    // The groups that are open and the targets they will be composited onto
+    let mut open_groups: Vec<(usize, Target)> = Vec::new();
+
+    for layer in scene.tessellate(scale_factor) {
+        let chain = scene.group_chain(layer.group);
+        let shared = open_groups
+            .iter()
+            .zip(&chain)
+            .take_while(|((open, _), group)| open == *group)
+            .count();
+        while open_groups.len() > shared {
+            let (group, parent) = open_groups.pop().unwrap();
+            let group_target = std::mem::replace(&mut target, parent);
+            let group = &scene.groups[group];
+            target.composite(&group_target, group.opacity, &group.filter);
+        }
+        for group in &chain[shared..] {
+            let parent = std::mem::replace(&mut target, Target::transparent(width, height));
+            open_groups.push((*group, parent));
+        }
+
+        if let Some(backdrop) = layer.backdrop {
+            let area = Rect::from_min_max(
+                (backdrop.area.min.to_vec2() * scale_factor).to_pos2(),
+                (backdrop.area.max.to_vec2() * scale_factor).to_pos2(),
+            );
+            target.blur(area, backdrop.radius * scale_factor);
+        }
+        target.draw_mesh(
+            layer.mesh,
+            layer.transform,
+            layer.clip,
+            scale_factor,
+            1.0,
+            &texture,
+        );
+    }
+    while let Some((group, parent)) = open_groups.pop() {
+        let group_target = std::mem::replace(&mut target, parent);
+        let group = &scene.groups[group];
+        target.composite(&group_target, group.opacity, &group.filter);
+    }
+
+    image::RgbaImage::from_fn(width, height, |x, y| {
+        image::Rgba(target.pixel(x, y).to_srgba_unmultiplied())
+    })
+}
+
+/// The pixels a scene is drawn into, in premultiplied linear space
+pub(crate) struct Target {
+    width: u32,
+    height: u32,
+    pixels: Vec<Rgba>,
+}
+
+impl Target {
+    pub fn new(width: u32, height: u32, clear_color: Color32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Rgba::from(clear_color); (width * height) as usize],
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color32 {
+        Color32::from(self.pixels[(y * self.width + x) as usize])
+    }
+
+    /// The width and height in pixels
+    pub fn size(&self) -> [u32; 2] {
+        [self.width, self.height]
+    }
+
+    /// The pixels as a texture meshes can sample, like the texture of a cached layer on the GPU
+    pub fn to_image(&self) -> ColorImage {
+        ColorImage {
+            size: [self.width as usize, self.height as usize],
+            pixels: self
+                .pixels
+                .iter()
+                .map(|pixel| Color32::from(*pixel))
+                .collect(),
+        }
+    }
+
+    /// Fill the pixels in a rect with a color, without blending
+    pub fn clear_rect(&mut self, rect: Rect, color: Color32) {
+        let color = Rgba::from(color);
+        let min_x = rect.min.x.floor().max(0.0) as u32;
+        let min_y = rect.min.y.floor().max(0.0) as u32;
+        let max_x = (rect.max.x.ceil().max(0.0) as u32).min(self.width);
+        let max_y = (rect.max.y.ceil().max(0.0) as u32).min(self.height);
+        for y in min_y..max_y {
+            let row = (y * self.width) as usize;
+            self.pixels[row + min_x as usize..row + max_x as usize].fill(color);
+        }
+    }
+
+    /// Draw a mesh the same way the GPU backends do. The mesh is transformed and clipped in logical pixels and then scaled to the pixels of the target. Its colors are multiplied by the texture at their uv coordinates
+    pub fn draw_mesh(
+        &mut self,
+        mut mesh: Mesh,
+        transform: Affine,
+        clip: Rect,
+        scale_factor: f32,
+        opacity: f32,
+        texture: &ColorImage,
+    ) {
+        self.draw_triangles(mesh, transform, clip, scale_factor, opacity, texture, false);
+    }
+
+    /// Draw a text mesh with subpixel glyphs like [`Target::draw_mesh`], with each color channel blended by its own coverage, see [`crate::Backend::draw_subpixel_text`]
+    pub fn draw_subpixel_text(
+        &mut self,
+        mesh: Mesh,
+        transform: Affine,
+        clip: Rect,
+        opacity: f32,
+        texture: &ColorImage,
+    ) {
+        self.draw_triangles(mesh, transform, clip, 1.0, opacity, texture, true);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_triangles(
+        &mut self,
+        mut mesh: Mesh,
+        transform: Affine,
+        clip: Rect,
+        scale_factor: f32,
+        opacity: f32,
+        texture: &ColorImage,
+        subpixel: bool,
+    ) {
+        transform_mesh(&mut mesh, transform);
+        let mesh = clip_mesh(&mesh, clip);
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| {
+                let vertex = mesh.vertices[triangle[i] as usize];
+                Vertex {
+                    pos: (vertex.pos.to_vec2() * scale_factor).to_pos2(),
+                    color: if opacity < 1.0 {
+                        vertex.color.linear_multiply(opacity)
+                    } else {
+                        vertex.color
+                    },
+                    ..vertex
+                }
+            });
+            self.fill_triangle(&a, &b, &c, texture, subpixel);
+        }
+    }
+
+    /// Fill rects by their signed distance, the same way the rect shader of the GPU backends does. The rects are in physical pixels before the transform is applied
+    pub fn draw_rects(
+        &mut self,
+        rects: &[RectInstance],
+        transform: Affine,
+        clip: Rect,
+        opacity: f32,
+    ) {
+        let inverse = transform.inverse();
+        // The size of a pixel in the coordinates of the rects, so the edge is smoothed over one pixel at any scale
+        let pixel = (inverse.determinant().abs().sqrt() as f32).max(0.0001);
+        for rect in rects {
+            let rect = rect.with_opacity(opacity);
+            let bounds = transform_rect(transform, rect.bounds().expand(pixel)).intersect(clip);
+            if !bounds.is_positive() {
+                continue;
+            }
+            let [left, top, right, bottom] = rect.rect;
+            let center = Pos2::new((left + right) / 2.0, (top + bottom) / 2.0);
+            let half_size = epaint::vec2((right - left) / 2.0, (bottom - top) / 2.0);
+            let fill = Rgba::from(rect.fill);
+            let border_color = Rgba::from(rect.border_color);
+
+            let min_x = bounds.min.x.floor().max(0.0) as u32;
+            let min_y = bounds.min.y.floor().max(0.0) as u32;
+            let max_x = (bounds.max.x.ceil().max(0.0) as u32).min(self.width);
+            let max_y = (bounds.max.y.ceil().max(0.0) as u32).min(self.height);
+            for y in min_y..max_y {
+                for x in min_x..max_x {
+                    // Sample the center of the pixel, which has to be inside of the clip like a fragment
+                    let point = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                    if !clip.contains(point) {
+                        continue;
+                    }
+                    let local = inverse * peniko::kurbo::Point::new(point.x as f64, point.y as f64);
+                    let distance = rounded_rect_distance(
+                        Pos2::new(local.x as f32, local.y as f32) - center,
+                        half_size,
+                        rect.radii,
+                    );
+                    let fill_coverage = (0.5 - distance / pixel).clamp(0.0, 1.0);
+                    let border_coverage = if rect.border_width > 0.0 {
+                        (0.5 - (distance.abs() - rect.border_width / 2.0) / pixel).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                    // The border is drawn over the fill, both premultiplied
+                    let color = border_color * border_coverage
+                        + fill * (fill_coverage * (1.0 - border_coverage));
+                    let pixel = &mut self.pixels[(y * self.width + x) as usize];
+                    *pixel = color + *pixel * (1.0 - color.a());
+                }
+            }
+        }
+    }
+
+    pub fn transparent(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Rgba::TRANSPARENT; (width * height) as usize],
+        }
+    }
+
+    /// Draw another target over this one with a filter and an opacity
+    pub fn composite(&mut self, source: &Target, opacity: f32, filter: &ColorMatrix) {
+        let filtered = *filter != ColorMatrix::IDENTITY;
+        for (pixel, source) in self.pixels.iter_mut().zip(&source.pixels) {
+            let source = if filtered {
+                filter.apply(*source)
+            } else {
+                *source
+            };
+            let color = source * opacity;
+            *pixel = color + *pixel * (1.0 - color.a());
+        }
+    }
+
+    /// Blur the pixels in an area with a gaussian of a standard deviation in pixels, like the blur shader of the GPU backends. Pixels outside of the area aren't sampled
+    pub fn blur(&mut self, area: Rect, radius: f32) {
+        let min_x = area.min.x.floor().max(0.0) as u32;
+        let min_y = area.min.y.floor().max(0.0) as u32;
+        let max_x = (area.max.x.ceil().max(0.0) as u32).min(self.width);
+        let max_y = (area.max.y.ceil().max(0.0) as u32).min(self.height);
+        if min_x >= max_x || min_y >= max_y || radius <= 0.0 {
+            return;
+        }
+        let width = (max_x - min_x) as usize;
+        let height = (max_y - min_y) as usize;
+        let pixels: Vec<Rgba> = (min_y..max_y)
+            .flat_map(|y| (min_x..max_x).map(move |x| (y * self.width + x) as usize))
+            .map(|index| self.pixels[index])
+            .collect();
+        // A gaussian is separable, so the rows are blurred and then the columns
+        let kernel = gaussian_kernel(radius);
+        let pixels = blur_pass(&pixels, [width, height], &kernel, [1, 0]);
+        let pixels = blur_pass(&pixels, [width, height], &kernel, [0, 1]);
+        for (row, y) in (min_y..max_y).enumerate() {
+            let start = (y * self.width + min_x) as usize;
+            self.pixels[start..start + width]
+                .copy_from_slice(&pixels[row * width..(row + 1) * width]);
+        }
+    }
+
+    fn fill_triangle(
+        &mut self,
+        a: &Vertex,
+        b: &Vertex,
+        c: &Vertex,
+        texture: &ColorImage,
+        subpixel: bool,
+    ) {
+        let area = edge(a.pos, b.pos, c.pos);
+        if area.abs() <= f32::EPSILON {
+            return;
+        }
+
+        let min_x = a.pos.x.min(b.pos.x).min(c.pos.x).floor().max(0.0) as u32;
+        let min_y = a.pos.y.min(b.pos.y).min(c.pos.y).floor().max(0.0) as u32;
+        let max_x = (a.pos.x.max(b.pos.x).max(c.pos.x).ceil() as u32).min(self.width);
+        let max_y = (a.pos.y.max(b.pos.y).max(c.pos.y).ceil() as u32).min(self.height);
+
+        let colors = [a.color, b.color, c.color].map(Rgba::from);
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                // Sample the center of the pixel
+                let point = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let weights = [
+                    edge(b.pos, c.pos, point) / area,
+                    edge(c.pos, a.pos, point) / area,
+                    edge(a.pos, b.pos, point) / area,
+                ];
+                if weights.iter().any(|weight| *weight < 0.0) {
+                    continue;
+                }
+                let uv = a.uv.to_vec2() * weights[0]
+                    + b.uv.to_vec2() * weights[1]
+                    + c.uv.to_vec2() * weights[2];
+                let vertex_color =
+                    colors[0] * weights[0] + colors[1] * weights[1] + colors[2] * weights[2];
+                let texel = sample(texture, uv.to_pos2());
+                let color = vertex_color * texel;
+                let pixel = &mut self.pixels[(y * self.width + x) as usize];
+                if subpixel {
+                    // Each channel of the texel is the coverage of that channel
+                    let covered = texel * vertex_color.a();
+                    for channel in 0..3 {
+                        pixel[channel] = color[channel] + pixel[channel] * (1.0 - covered[channel]);
+                    }
+                    pixel[3] = color.a() + pixel[3] * (1.0 - color.a());
+                } else {
+                    // Premultiplied source over
+                    *pixel = color + *pixel * (1.0 - color.a());
+                }
+            }
+        }
+    }
+}
+
+/// The weights of a gaussian with a standard deviation, from the leftmost tap to the rightmost. Taps past three standard deviations are left out, like in the blur shader
+fn gaussian_kernel(radius: f32) -> Vec<f32> {
+    let taps = ((radius * 3.0).ceil() as i32).clamp(1, MAX_BLUR_TAPS);
+    let weights: Vec<f32> = (-taps..=taps)
+        .map(|i| (-(i * i) as f32 / (2.0 * radius * radius)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+/// Blur pixels along one direction. Taps past the edges repeat the pixels at the edges
+fn blur_pass(
+    pixels: &[Rgba],
+    size: [usize; 2],
+    kernel: &[f32],
+    direction: [usize; 2],
+) -> Vec<Rgba> {
+    let [width, height] = size;
+    let taps = (kernel.len() / 2) as isize;
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| [x, y]))
+        .map(|position| {
+            kernel
+                .iter()
+                .enumerate()
+                .fold(Rgba::TRANSPARENT, |sum, (i, weight)| {
+                    let [x, y] = [0, 1].map(|axis| {
+                        let offset = (i as isize - taps) * direction[axis] as isize;
+                        (position[axis] as isize + offset).clamp(0, size[axis] as isize - 1)
+                            as usize
+                    });
+                    sum + pixels[y * width + x] * *weight
+                })
+        })
+        .collect()
+}
+
+/// Sample the texel at a uv coordinate as premultiplied linear color
+fn sample(texture: &ColorImage, uv: Pos2) -> Rgba {
+    let [width, height] = texture.size;
+    let x = ((uv.x * width as f32) as usize).min(width - 1);
+    let y = ((uv.y * height as f32) as usize).min(height - 1);
+    Rgba::from(texture[(x, y)])
+}
+
+/// Apply a change to a texture, see [`crate::Backend::update_texture`]
+pub(crate) fn update_texture(texture: &mut ColorImage, delta: &ImageDelta) {
+    let image = match &delta.image {
+        ImageData::Color(image) => image.clone(),
+        ImageData::Font(image) => ColorImage {
+            size: image.size,
+            pixels: image.srgba_pixels(None).collect(),
+        },
+    };
+    let Some([x, y]) = delta.pos else {
+        *texture = image;
+        return;
+    };
+    let [width, height] = image.size;
+    for row in 0..height {
+        for column in 0..width {
+            texture[(x + column, y + row)] = image[(column, row)];
+        }
+    }
+}
+
+/// The signed distance from the edge of a rect centered at the origin, negative inside. The radii are top left, top right, bottom right and bottom left
+fn rounded_rect_distance(point: epaint::Vec2, half_size: epaint::Vec2, radii: [f32; 4]) -> f32 {
+    let radius = match (point.x < 0.0, point.y < 0.0) {
+        (true, true) => radii[0],
+        (false, true) => radii[1],
+        (false, false) => radii[2],
+        (true, false) => radii[3],
+    };
+    let q = point.abs() - half_size + epaint::Vec2::splat(radius);
+    q.x.max(q.y).min(0.0) + q.max(epaint::Vec2::ZERO).length() - radius
+}
+
+/// Twice the signed area of the triangle formed by the points
+fn edge(a: Pos2, b: Pos2, c: Pos2) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
@@ -0,0 +1,157 @@
+use beuk::ash::vk;
+use beuk::ctx::RenderContext;
+
+use crate::attachment::{Attachment, COLOR_SUBRESOURCE_RANGE};
+
+/// A buffer in host visible memory that an image is copied into, so it can be read on the CPU, for screenshots.
+pub(crate) struct ReadbackBuffer {
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    /// The size of the buffer in bytes
+    size: u64,
+}
+
+impl ReadbackBuffer {
+    /// Create a buffer for the texels of an image with 4 bytes per texel.
+    pub fn new(ctx: &RenderContext, extent: vk::Extent2D) -> Self {
+        let size = u64::from(extent.width) * u64::from(extent.height) * 4;
+        unsafe {
+            let buffer = ctx
+                .device
+                .create_buffer(
+                    &vk::BufferCreateInfo::default()
+                        .size(size)
+                        .usage(vk::BufferUsageFlags::TRANSFER_DST)
+                        .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                    None,
+                )
+                .unwrap();
+            let requirements = ctx.device.get_buffer_memory_requirements(buffer);
+            let memory_properties = ctx
+                .instance
+                .get_physical_device_memory_properties(ctx.physical_device);
+            // Coherent memory can be read without invalidating it first
+            let flags =
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+            let memory_type_index = memory_properties
+                .memory_types_as_slice()
+                .iter()
+                .enumerate()
+                .position(|(index, memory_type)| {
+                    requirements.memory_type_bits & (1 << index) != 0
+                        && memory_type.property_flags.contains(flags)
+                })
+                .expect("no host visible memory for the readback buffer")
+                as u32;
+            let memory = ctx
+                .device
+                .allocate_memory(
+                    &vk::MemoryAllocateInfo::default()
+                        .allocation_size(requirements.size)
+                        .memory_type_index(memory_type_index),
+                    None,
+                )
+                .unwrap();
+            ctx.device.bind_buffer_memory(buffer, memory, 0).unwrap();
+            Self {
+                buffer,
+                memory,
+                size,
+            }
+        }
+    }
+
+    /// Record a copy of an image that was rendered to and is sampled next. The image is left in the layout it had.
+    pub fn record_copy(
+        &self,
+        device: &beuk::ash::Device,
+        command_buffer: vk::CommandBuffer,
+        image: &Attachment,
+    ) {
+        let barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| {
+            vk::ImageMemoryBarrier::default()
+                .image(image.image)
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .subresource_range(COLOR_SUBRESOURCE_RANGE)
+        };
+        let to_transfer = barrier(
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::TRANSFER_READ,
+        );
+        let to_shader = barrier(
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::SHADER_READ,
+        );
+        let region = vk::BufferImageCopy::default()
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .image_extent(vk::Extent3D {
+                width: image.extent.width,
+                height: image.extent.height,
+                depth: 1,
+            });
+        // The buffer is read on the CPU once the frame is finished
+        let to_host = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ);
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                std::slice::from_ref(&to_transfer),
+            );
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                image.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.buffer,
+                std::slice::from_ref(&region),
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                std::slice::from_ref(&to_host),
+                &[],
+                std::slice::from_ref(&to_shader),
+            );
+        }
+    }
+
+    /// Read the texels that were copied into the buffer. The GPU must be done copying them.
+    pub fn read(&self, ctx: &RenderContext) -> Vec<u8> {
+        unsafe {
+            let data = ctx
+                .device
+                .map_memory(self.memory, 0, self.size, vk::MemoryMapFlags::empty())
+                .unwrap();
+            let texels = std::slice::from_raw_parts(data.cast::<u8>(), self.size as usize).to_vec();
+            ctx.device.unmap_memory(self.memory);
+            texels
+        }
+    }
+
+    /// Free the buffer. The GPU must be done using it.
+    pub fn destroy(&self, ctx: &RenderContext) {
+        unsafe {
+            ctx.device.destroy_buffer(self.buffer, None);
+            ctx.device.free_memory(self.memory, None);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec2 inLocal;
+layout(location = 1) flat in vec2 inHalfSize;
+layout(location = 2) flat in vec4 inRadii;
+layout(location = 3) flat in vec4 inFill;
+layout(location = 4) flat in vec4 inBorderColor;
+layout(location = 5) flat in float inBorderWidth;
+
+layout(location = 0) out vec4 outColor;
+
+layout(push_constant) uniform PushConstants {
+  vec4 clip_rect;
+  vec2 screen_size;
+  vec2 translation;
+  // The columns of the linear part of the transform, see DrawConstants
+  vec4 matrix;
+  uint encode_srgb;
+} pushConstants;
+
+// 0-1 sRGB  from  0-1 linear
+vec3 srgb_from_linear(vec3 linear) {
+    bvec3 cutoff = lessThan(linear, vec3(0.0031308));
+    vec3 lower = linear * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(linear, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, cutoff);
+}
+
+// Encode a premultiplied linear color for a target that doesn't encode it. Colors are blended after they are encoded, like in a browser without linear blending
+vec4 encode_output(vec4 color) {
+  if (pushConstants.encode_srgb == 0u || color.a <= 0.0) {
+    return color;
+  }
+  return vec4(srgb_from_linear(color.rgb / color.a) * color.a, color.a);
+}
+
+// The signed distance from the edge of a rect centered at the origin, negative inside. The radii are top left, top right, bottom right and bottom left
+float rounded_rect_distance(vec2 p, vec2 half_size, vec4 radii) {
+  float radius = p.x < 0.0 ? (p.y < 0.0 ? radii.x : radii.w)
+                           : (p.y < 0.0 ? radii.y : radii.z);
+  vec2 q = abs(p) - half_size + radius;
+  return min(max(q.x, q.y), 0.0) + length(max(q, 0.0)) - radius;
+}
+
+void main() {
+  float distance = rounded_rect_distance(inLocal, inHalfSize, inRadii);
+  // The size of a pixel in the coordinates of the rect, so the edge is smoothed over one pixel at any scale. Derivatives have to be taken before any fragment is discarded
+  float pixel = max(fwidth(distance), 0.0001);
+  // The clip rect is in window coordinates, so it still clips correctly after the transform is applied
+  if (any(lessThan(gl_FragCoord.xy, pushConstants.clip_rect.xy)) ||
+      any(greaterThanEqual(gl_FragCoord.xy, pushConstants.clip_rect.zw))) {
+    discard;
+  }
+  float fill = clamp(0.5 - distance / pixel, 0.0, 1.0);
+  float border = inBorderWidth > 0.0
+      ? clamp(0.5 - (abs(distance) - inBorderWidth / 2.0) / pixel, 0.0, 1.0)
+      : 0.0;
+  // The border is drawn over the fill, both premultiplied
+  outColor = encode_output(inBorderColor * border + inFill * fill * (1.0 - border));
+}
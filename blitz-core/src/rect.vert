@@ -0,0 +1,62 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+// One instance per rect, see RectInstance
+layout(location = 0) in vec4 rect;
+layout(location = 1) in vec4 radii;
+layout(location = 2) in vec4 fill;
+layout(location = 3) in vec4 borderColor;
+layout(location = 4) in float borderWidth;
+
+layout(location = 0) out vec2 outLocal;
+layout(location = 1) flat out vec2 outHalfSize;
+layout(location = 2) flat out vec4 outRadii;
+layout(location = 3) flat out vec4 outFill;
+layout(location = 4) flat out vec4 outBorderColor;
+layout(location = 5) flat out float outBorderWidth;
+
+layout(push_constant) uniform PushConstants {
+  vec4 clip_rect;
+  vec2 screen_size;
+  vec2 translation;
+  // The columns of the linear part of the transform, see DrawConstants
+  vec4 matrix;
+  uint encode_srgb;
+} pushConstants;
+
+// The two triangles of the quad
+const vec2 CORNERS[6] = vec2[](vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 1.0),
+                               vec2(0.0, 1.0), vec2(1.0, 0.0), vec2(1.0, 1.0));
+
+// 0-1 linear  from  0-255 sRGB
+vec3 linear_from_srgb(vec3 srgb) {
+    bvec3 cutoff = lessThan(srgb, vec3(10.31475));
+    vec3 lower = srgb / vec3(3294.6);
+    vec3 higher = pow((srgb + vec3(14.025)) / vec3(269.025), vec3(2.4));
+    return mix(higher, lower, cutoff);
+}
+
+vec4 linear_from_srgba(vec4 srgba) {
+    return vec4(linear_from_srgb(srgba.rgb * 255.0), srgba.a);
+}
+
+void main() {
+  // The quad covers the outer half of the border and a pixel for the smoothed edge
+  float scale = sqrt(abs(determinant(mat2(pushConstants.matrix))));
+  float extent = borderWidth / 2.0 + 1.0 / max(scale, 0.0001);
+  vec2 local = mix(rect.xy - extent, rect.zw + extent, CORNERS[gl_VertexIndex]);
+
+  vec2 transformed = mat2(pushConstants.matrix) * local + pushConstants.translation;
+  gl_Position =
+      vec4(2.0 * transformed.x / pushConstants.screen_size.x - 1.0,
+           2.0 * transformed.y / pushConstants.screen_size.y - 1.0, 0.0, 1.0);
+
+  // The fragment shader measures from the center of the rect
+  outLocal = local - (rect.xy + rect.zw) / 2.0;
+  outHalfSize = (rect.zw - rect.xy) / 2.0;
+  outRadii = radii;
+  outFill = linear_from_srgba(fill);
+  outBorderColor = linear_from_srgba(borderColor);
+  outBorderWidth = borderWidth;
+}
@@ -0,0 +1,100 @@
+// The wgpu version of rect.vert and rect.frag
+
+struct Constants {
+    clip_rect: vec4<f32>,
+    screen_size: vec2<f32>,
+    translation: vec2<f32>,
+    matrix: mat2x2<f32>,
+    // Always 0, the frame is drawn through an sRGB view
+    encode_srgb: u32,
+    white_level: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> constants: Constants;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) @interpolate(flat) half_size: vec2<f32>,
+    @location(2) @interpolate(flat) radii: vec4<f32>,
+    @location(3) @interpolate(flat) fill: vec4<f32>,
+    @location(4) @interpolate(flat) border_color: vec4<f32>,
+    @location(5) @interpolate(flat) border_width: f32,
+};
+
+// 0-1 linear  from  0-255 sRGB
+fn linear_from_srgb(srgb: vec3<f32>) -> vec3<f32> {
+    let cutoff = srgb < vec3<f32>(10.31475);
+    let lower = srgb / vec3<f32>(3294.6);
+    let higher = pow((srgb + vec3<f32>(14.025)) / vec3<f32>(269.025), vec3<f32>(2.4));
+    return select(higher, lower, cutoff);
+}
+
+fn linear_from_srgba(srgba: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(linear_from_srgb(srgba.rgb * 255.0), srgba.a);
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @location(0) rect: vec4<f32>,
+    @location(1) radii: vec4<f32>,
+    @location(2) fill: vec4<f32>,
+    @location(3) border_color: vec4<f32>,
+    @location(4) border_width: f32,
+) -> VertexOutput {
+    // The two triangles of the quad
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0), vec2<f32>(1.0, 1.0),
+    );
+    // The quad covers the outer half of the border and a pixel for the smoothed edge
+    let scale = sqrt(abs(determinant(constants.matrix)));
+    let extent = border_width / 2.0 + 1.0 / max(scale, 0.0001);
+    let local = mix(rect.xy - extent, rect.zw + extent, corners[vertex_index]);
+
+    let transformed = constants.matrix * local + constants.translation;
+    var out: VertexOutput;
+    // Clip space points up in wgpu, unlike in Vulkan
+    out.position = vec4<f32>(
+        2.0 * transformed.x / constants.screen_size.x - 1.0,
+        1.0 - 2.0 * transformed.y / constants.screen_size.y,
+        0.0,
+        1.0,
+    );
+    // The fragment shader measures from the center of the rect
+    out.local = local - (rect.xy + rect.zw) / 2.0;
+    out.half_size = (rect.zw - rect.xy) / 2.0;
+    out.radii = radii;
+    out.fill = linear_from_srgba(fill);
+    out.border_color = linear_from_srgba(border_color);
+    out.border_width = border_width;
+    return out;
+}
+
+// The signed distance from the edge of a rect centered at the origin, negative inside. The radii are top left, top right, bottom right and bottom left
+fn rounded_rect_distance(p: vec2<f32>, half_size: vec2<f32>, radii: vec4<f32>) -> f32 {
+    let radius = select(select(radii.y, radii.z, p.y >= 0.0), select(radii.x, radii.w, p.y >= 0.0), p.x < 0.0);
+    let q = abs(p) - half_size + radius;
+    return min(max(q.x, q.y), 0.0) + length(max(q, vec2<f32>(0.0))) - radius;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = rounded_rect_distance(in.local, in.half_size, in.radii);
+    // The size of a pixel in the coordinates of the rect, so the edge is smoothed over one pixel at any scale. Derivatives have to be taken before any fragment is discarded
+    let pixel = max(fwidth(distance), 0.0001);
+    // The clip rect is in window coordinates, so it still clips correctly after the transform is applied
+    if any(in.position.xy < constants.clip_rect.xy) || any(in.position.xy >= constants.clip_rect.zw) {
+        discard;
+    }
+    let fill = clamp(0.5 - distance / pixel, 0.0, 1.0);
+    var border = 0.0;
+    if in.border_width > 0.0 {
+        border = clamp(0.5 - (abs(distance) - in.border_width / 2.0) / pixel, 0.0, 1.0);
+    }
+    // The border is drawn over the fill, both premultiplied
+    let color = in.border_color * border + in.fill * fill * (1.0 - border);
+    return vec4<f32>(color.rgb * constants.white_level, color.a);
+}
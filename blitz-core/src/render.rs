@@ -1,81 +1,637 @@
+use std::ops::Range;
+use std::time::Instant;
+
 use dioxus_native_core::prelude::*;
-use epaint::{ClippedShape, Color32};
-use peniko::kurbo::{Point, Vec2};
+use epaint::Color32;
+use lightningcss::properties::border::{BorderSideWidth, LineStyle};
+use lightningcss::properties::outline::OutlineStyle;
+use lightningcss::values::color::CssColor;
+use peniko::kurbo::{Affine, Point, Vec2};
 
-use taffy::prelude::Layout;
 use taffy::prelude::Size;
+use taffy::prelude::{Layout, LengthPercentage};
 use taffy::Taffy;
 use tao::dpi::PhysicalSize;
 
+use crate::backend::ColorMatrix;
+use crate::dock::paint_dock_preview;
+use crate::document::to_color32;
+use crate::editing::{Editing, TextSelection};
 use crate::focus::Focused;
 use crate::layout::TaffyLayout;
-use crate::renderer::Renderer;
-use crate::style::{Background, Border};
+use crate::plugin::{CustomElement, Painter, Plugins};
+use crate::position::{is_fixed, is_positioned};
+use crate::resize::{grip_shapes, resizable};
+use crate::scene::{transform_rect, Scene};
+use crate::scroll::{scroll_effect, scroll_offset, scroll_range, scrollbars};
+use crate::selection::Selected;
+use crate::spotlight::paint_spotlight;
+use crate::style::{
+    BackdropFilter, Background, Border, BorderImage, Filter, ForgroundColor, Opacity, Outline,
+    Overflow, TextStyle, Transform, WillChange, ZIndex,
+};
+use crate::system_settings::system_settings;
+use crate::text::{shape, shape_text, text_mesh};
 
 use crate::util::Resolve;
 use crate::util::{translate_color, Axis};
 use crate::RealDom;
 
-const FOCUS_BORDER_WIDTH: f64 = 6.0;
+/// The color of the focus ring of focused elements without an outline color
+const FOCUS_RING_COLOR: Color32 = Color32::from_rgb(0x25, 0x63, 0xeb);
+/// The width of the focus ring of focused elements without an outline width
+const FOCUS_RING_WIDTH: f32 = 2.0;
+/// The gap between a focused element and its focus ring without an outline offset
+const FOCUS_RING_OFFSET: f32 = 2.0;
+/// The highlight behind selected text, which is the focus ring color at 35% opacity
+const SELECTION_COLOR: Color32 = Color32::from_rgba_premultiplied(0x0d, 0x22, 0x52, 0x59);
 
+/// Render the document. `transform` maps the document to the window, which is the identity unless the document is scaled to fit the window.
 pub(crate) fn render(
     dom: &RealDom,
     taffy: &Taffy,
-    renderer: &mut Renderer,
+    scene: &mut Scene,
+    viewport_size: PhysicalSize<u32>,
     window_size: PhysicalSize<u32>,
+    transform: Affine,
+    plugins: &Plugins,
+) {
+    let viewport_rect = epaint::Rect::from_min_size(
+        epaint::Pos2::ZERO,
+        epaint::vec2(viewport_size.width as f32, viewport_size.height as f32),
+    );
+    let window_rect = epaint::Rect::from_min_size(
+        epaint::Pos2::ZERO,
+        epaint::vec2(window_size.width as f32, window_size.height as f32),
+    );
+    let viewport_size = Size {
+        width: viewport_size.width,
+        height: viewport_size.height,
+    };
+    let clip = transform_rect(transform, viewport_rect).intersect(window_rect);
+    render_document(dom, taffy, scene, &viewport_size, clip, transform, plugins);
+}
+
+/// Render a document clipped to a rect in window coordinates.
+pub(crate) fn render_document(
+    dom: &RealDom,
+    taffy: &Taffy,
+    scene: &mut Scene,
+    viewport_size: &Size<u32>,
+    clip: epaint::Rect,
+    transform: Affine,
+    plugins: &Plugins,
 ) {
     let root = &dom.get(dom.root_id()).unwrap();
+    // The root starts the stacking context of the document
+    let start = scene.mark();
+    let mut layered = Vec::new();
     render_node(
         taffy,
         *root,
-        renderer,
+        scene,
         Point::ZERO,
+        viewport_size,
+        clip,
+        transform,
+        plugins,
+        &mut layered,
+    );
+    // Fixed elements are laid out in the root, so they are painted from it over the rest of the document, as if they were its last children
+    let fixed = root.get::<TaffyLayout>().unwrap().fixed.clone();
+    for id in fixed {
+        if let Some(node) = dom.get(id) {
+            render_node(
+                taffy,
+                node,
+                scene,
+                Point::ZERO,
+                viewport_size,
+                clip,
+                transform,
+                plugins,
+                &mut layered,
+            );
+        }
+    }
+    scene.stack(start, layered);
+    paint_spotlight(dom, taffy, scene, clip, transform, viewport_size);
+    paint_dock_preview(dom, scene, clip, transform);
+}
+
+/// Render an element and its children on their own, moved so the border box of the element starts at the origin and scaled to fill `size`. Returns false if the element has no layout.
+///
+/// The transforms and opacity of the ancestors of the element are left out.
+pub(crate) fn render_subtree(
+    taffy: &Taffy,
+    node: NodeRef,
+    scene: &mut Scene,
+    size: Size<f32>,
+    viewport_size: PhysicalSize<u32>,
+    plugins: &Plugins,
+) -> bool {
+    let Some(taffy_node) = node.get::<TaffyLayout>().and_then(|layout| layout.node) else {
+        return false;
+    };
+    let layout = *taffy.layout(taffy_node).unwrap();
+    if layout.size.width <= 0.0 || layout.size.height <= 0.0 {
+        return false;
+    }
+    let position = get_abs_pos(layout, taffy, node);
+    let transform = Affine::scale_non_uniform(
+        (size.width / layout.size.width) as f64,
+        (size.height / layout.size.height) as f64,
+    ) * Affine::translate(-position.to_vec2());
+    let clip =
+        epaint::Rect::from_min_size(epaint::Pos2::ZERO, epaint::vec2(size.width, size.height));
+    // render_node adds the location of the element itself
+    let parent_location = position - Vec2::new(layout.location.x as f64, layout.location.y as f64);
+    let start = scene.mark();
+    let mut layered = Vec::new();
+    render_node(
+        taffy,
+        node,
+        scene,
+        parent_location,
         &Size {
-            width: window_size.width,
-            height: window_size.height,
+            width: viewport_size.width,
+            height: viewport_size.height,
         },
+        clip,
+        transform,
+        plugins,
+        &mut layered,
     );
+    scene.stack(start, layered);
+    true
+}
+
+/// True if an element starts a stacking context. The elements with a z-index inside of it are painted in order of their z-index relative to each other and to the rest of its children, and never mix with the elements outside of it.
+pub(crate) fn is_stacking_context(node: NodeRef) -> bool {
+    node.get::<ZIndex>()
+        .is_some_and(|z_index| z_index.0.is_some())
+        || is_fixed(node)
+        || node.get::<Opacity>().is_some_and(|opacity| opacity.0 < 1.0)
+        || node
+            .get::<Filter>()
+            .is_some_and(|filter| filter.0 != ColorMatrix::IDENTITY)
+        || node
+            .get::<Transform>()
+            .is_some_and(|transform| !transform.transform.0.is_empty())
+        || node
+            .get::<BackdropFilter>()
+            .is_some_and(|backdrop| backdrop.blur.is_some())
+        || node
+            .get::<WillChange>()
+            .is_some_and(|will_change| will_change.0)
 }
 
+/// True if the children of an element are pushed as a content of their own, because the element will move or fade without them changing or it scrolls them, see [`crate::scene::Content`]. The element is painted as a stacking context then, so the layers of its content stay together.
+fn keeps_content_apart(taffy: &Taffy, node: NodeRef) -> bool {
+    node.get::<WillChange>()
+        .is_some_and(|will_change| will_change.0)
+        || scroll_range(taffy, node) != Vec2::ZERO
+}
+
+/// The z-index an element is layered with in its stacking context: its own, or 0 for positioned elements without one, which are painted over the elements in the flow. `None` if the element is painted in the flow.
+pub(crate) fn paint_layer(node: NodeRef) -> Option<i32> {
+    node.get::<ZIndex>()
+        .and_then(|z_index| z_index.0)
+        .or_else(|| is_positioned(node).then_some(0))
+}
+
+/// Render a node and its children. `layered` collects the layers painted by the elements with a z-index or a position in the stacking context of the node, which the element that started the stacking context moves into place once its children are painted.
+#[allow(clippy::too_many_arguments)]
 fn render_node(
     taffy: &Taffy,
     node: NodeRef,
-    renderer: &mut Renderer,
+    scene: &mut Scene,
     location: Point,
     viewport_size: &Size<u32>,
+    clip: epaint::Rect,
+    transform: Affine,
+    plugins: &Plugins,
+    layered: &mut Vec<(i32, Range<usize>)>,
 ) {
     let taffy_node = node.get::<TaffyLayout>().unwrap().node.unwrap();
     let layout = taffy.layout(taffy_node).unwrap();
     let location = location + Vec2::new(layout.location.x as f64, layout.location.y as f64);
     match &*node.node_type() {
-        NodeType::Text(TextNode { text: _, .. }) => {
-            // let text_color = translate_color(&node.get::<ForgroundColor>().unwrap().0);
-            // let font_size = if let Some(font_size) = node.get::<FontSize>() {
-            //     font_size.0
-            // } else {
-            //     DEFAULT_FONT_SIZE
-            // };
-            // text_context.add(
-            //     scene_builder,
-            //     None,
-            //     font_size,
-            //     Some(text_color),
-            //     Affine::translate(pos.to_vec2() + Vec2::new(0.0, font_size as f64)),
-            //     text,
-            // )
+        NodeType::Text(TextNode { text, .. }) => {
+            scene.transform = transform;
+            let color = translate_color(&node.get::<ForgroundColor>().unwrap().0);
+            let color = to_color32(color);
+            let shaped = shape_text(
+                text,
+                &node.get::<TextStyle>().unwrap(),
+                Some(layout.size.width),
+            );
+            // Glyphs are rasterized at the size they are drawn at, so scaled text stays sharp
+            let scale = transform.determinant().abs().sqrt() as f32;
+            let origin = epaint::pos2(location.x as f32, location.y as f32);
+            if let Some(selected) = node.get::<Selected>() {
+                for highlight in shaped.highlights(selected.0.clone()) {
+                    scene.push(
+                        clip,
+                        epaint::Shape::rect_filled(
+                            highlight.translate(origin.to_vec2()),
+                            0.0,
+                            SELECTION_COLOR,
+                        ),
+                    );
+                }
+            }
+            // The stripes of LCD pixels run horizontally, so rotated and mirrored text is smoothed in grayscale
+            let [a, b, c, d, _, _] = transform.as_coeffs();
+            // Color glyphs like emoji keep their colors, so they are blended as a whole
+            let subpixel = scene.draws_subpixel_text()
+                && !shaped.has_color_glyphs()
+                && b == 0.0
+                && c == 0.0
+                && a > 0.0
+                && d > 0.0;
+            for line in &shaped.lines {
+                let baseline = origin + epaint::vec2(line.left, line.top + line.line.ascent);
+                let mesh = text_mesh(&line.line, baseline, color, scale, subpixel);
+                if !mesh.is_empty() {
+                    if subpixel {
+                        scene.push_subpixel_text(clip, mesh);
+                    } else {
+                        scene.push(clip, epaint::Shape::mesh(mesh));
+                    }
+                }
+            }
         }
         NodeType::Element(_) => {
+            let (scroll_transform, scroll_opacity) = scroll_effect(taffy, node, location);
+            let opacity = node.get::<Opacity>().unwrap().0 * scroll_opacity;
+            if opacity <= 0.0 {
+                return;
+            }
+            let z_index = paint_layer(node);
+            let start = z_index.map(|_| scene.mark());
+            let painted_before = scene.start_node();
+            // A translucent or filtered element is drawn together with its children and then faded and filtered as a whole
+            let filter = node.get::<Filter>().unwrap().0;
+            let grouped = opacity < 1.0 || filter != ColorMatrix::IDENTITY;
+            let content_apart = keeps_content_apart(taffy, node);
+            let stacking_context = grouped || content_apart || is_stacking_context(node);
+            if grouped {
+                scene.push_group(opacity, filter);
+            }
+
+            // Transforms are relative to the transform of the parent
+            let transform = transform
+                * node
+                    .get::<Transform>()
+                    .unwrap()
+                    .resolve(location, &layout.size, viewport_size)
+                * scroll_transform;
+            scene.transform = transform;
+
+            // The backdrop is blurred before the element is drawn over it
+            if let Some(blur) = &node.get::<BackdropFilter>().unwrap().blur {
+                let border_box = epaint::Rect::from_min_size(
+                    epaint::pos2(location.x as f32, location.y as f32),
+                    epaint::vec2(layout.size.width, layout.size.height),
+                );
+                let scale = transform.determinant().abs().sqrt();
+                let radius = blur.resolve(Axis::Min, &layout.size, viewport_size) * scale;
+                scene.push_backdrop_blur(
+                    transform_rect(transform, border_box).intersect(clip),
+                    radius as f32,
+                );
+            }
+
             let shape = get_shape(layout, node, viewport_size, location);
-            let clip = shape.visual_bounding_rect();
-            renderer.shapes.push(ClippedShape(clip, shape));
-            for child in node.children() {
-                render_node(taffy, child, renderer, location, viewport_size);
+            let padding_box = match &shape {
+                epaint::Shape::Rect(rect) => Some(rect.rect.shrink(rect.stroke.width / 2.0)),
+                _ => None,
+            };
+            let border = node.get::<Border>().unwrap();
+            let border_style = border.style.top;
+            let radii = corner_radii(&border, &layout.size, viewport_size);
+            let border_image = node.get::<BorderImage>().unwrap();
+            match shape {
+                // The border image replaces the border, but still takes up its width
+                epaint::Shape::Rect(mut rect) if border_image.image.is_some() => {
+                    rect.stroke = epaint::Stroke::NONE;
+                    for shape in rounded_rect(&rect, &radii) {
+                        scene.push(clip, shape);
+                    }
+                    let border_box = epaint::Rect::from_min_size(
+                        epaint::pos2(location.x as f32, location.y as f32),
+                        epaint::vec2(layout.size.width, layout.size.height),
+                    );
+                    let border_widths = border_widths(&border, layout, viewport_size);
+                    border_image.draw(scene, border_box, border_widths, clip, viewport_size);
+                }
+                epaint::Shape::Rect(mut rect) if border_style != LineStyle::Solid => {
+                    let border = styled_border(&rect, &radii, border_style);
+                    rect.stroke = epaint::Stroke::NONE;
+                    for shape in rounded_rect(&rect, &radii).into_iter().chain(border) {
+                        scene.push(clip, shape);
+                    }
+                }
+                epaint::Shape::Rect(rect) => {
+                    for shape in rounded_rect(&rect, &radii) {
+                        scene.push(clip, shape);
+                    }
+                }
+                shape => scene.push(clip, shape),
+            }
+            if let Some(padding_box) = padding_box {
+                let background = node.get::<Background>().unwrap();
+                background.draw_image(scene, padding_box, clip, viewport_size);
+            }
+
+            // Custom elements are painted by their plugin and don't draw their children
+            let plugin = plugins.for_node(node);
+            if let Some(plugin) = plugin {
+                let taffy_layout = node.get::<TaffyLayout>().unwrap();
+                let content_box = content_box(layout, &taffy_layout.style, location);
+                if let Some(element) = CustomElement::from_node(node) {
+                    plugin.paint(&element, content_box, &mut Painter::new(scene, clip));
+                }
+            }
+
+            // Children that overflow are clipped to the padding box. Clip rects are axis aligned in window coordinates, so a rotated box clips to its bounds
+            let overflow = node.get::<Overflow>().unwrap();
+            let mut child_clip = clip;
+            if let Some(padding_box) = padding_box.map(|rect| transform_rect(transform, rect)) {
+                if overflow.clips_x() {
+                    child_clip.min.x = child_clip.min.x.max(padding_box.min.x);
+                    child_clip.max.x = child_clip.max.x.min(padding_box.max.x);
+                }
+                if overflow.clips_y() {
+                    child_clip.min.y = child_clip.min.y.max(padding_box.min.y);
+                    child_clip.max.y = child_clip.max.y.min(padding_box.max.y);
+                }
+            }
+            // Rounded corners also clip children that overflow both ways, to the padding box with the radii reduced by the border widths
+            let rounded_clip = padding_box
+                .filter(|_| overflow.clips_x() && overflow.clips_y())
+                .map(|padding_box| {
+                    let [top, right, bottom, left] = border_widths(&border, layout, viewport_size);
+                    let widths = [
+                        epaint::vec2(left, top),
+                        epaint::vec2(right, top),
+                        epaint::vec2(right, bottom),
+                        epaint::vec2(left, bottom),
+                    ];
+                    let radii: CornerRadii = std::array::from_fn(|corner| {
+                        (radii[corner] - widths[corner]).max(epaint::Vec2::ZERO)
+                    });
+                    (padding_box, radii)
+                })
+                .filter(|(_, radii)| radii.iter().any(|radius| radius.min_elem() > 0.0));
+            if let Some((padding_box, radii)) = &rounded_clip {
+                scene.transform = transform;
+                scene.push_clip_path(rounded_outline(*padding_box, radii));
+            }
+            // Fixed children are painted from the root, see render_document
+            let children = match plugin {
+                Some(_) => Vec::new(),
+                None => node
+                    .children()
+                    .into_iter()
+                    .filter(|child| !is_fixed(*child))
+                    .collect(),
+            };
+            let children_location = location - scroll_offset(node);
+            // The selection is drawn behind the text of an edited element and the caret over it
+            let (highlights, caret) = match node.get::<Editing>().and_then(|editing| editing.0) {
+                Some(selection) => {
+                    let taffy_layout = node.get::<TaffyLayout>().unwrap();
+                    let content_box = content_box(layout, &taffy_layout.style, children_location);
+                    editing_shapes(taffy, node, &selection, content_box, children_location)
+                }
+                None => (Vec::new(), None),
+            };
+            if !highlights.is_empty() {
+                scene.transform = transform;
+                for highlight in highlights {
+                    scene.push(child_clip, highlight);
+                }
+            }
+            // The content is pushed in coordinates that are scaled like the window, so it is as sharp when it is drawn into a texture
+            let scale = transform.determinant().abs().sqrt();
+            let content = content_apart
+                && scale > 0.0
+                && scene.begin_content(
+                    node.id(),
+                    transform
+                        * Affine::translate(children_location.to_vec2())
+                        * Affine::scale(1.0 / scale),
+                    child_clip,
+                );
+            let (content_location, content_clip, content_transform) = if content {
+                (Point::ZERO, epaint::Rect::EVERYTHING, Affine::scale(scale))
+            } else {
+                (children_location, child_clip, transform)
+            };
+            // The children of a stacking context are painted over its background, even with a negative z-index
+            let content_start = stacking_context.then(|| scene.mark());
+            let mut own_layered = Vec::new();
+            for child in children {
+                render_node(
+                    taffy,
+                    child,
+                    scene,
+                    content_location,
+                    viewport_size,
+                    content_clip,
+                    content_transform,
+                    plugins,
+                    if stacking_context {
+                        &mut own_layered
+                    } else {
+                        &mut *layered
+                    },
+                );
+            }
+            if let Some(content_start) = content_start {
+                scene.stack(content_start, own_layered);
+            }
+            if content {
+                scene.end_content();
+            }
+
+            if let Some(caret) = caret {
+                scene.transform = transform;
+                scene.push(child_clip, caret);
+            }
+            // Scrollbars are drawn over the content, inside of the rounded corners
+            if let Some(padding_box) = padding_box.filter(|_| plugin.is_none()) {
+                scene.transform = transform;
+                for (bar, always) in scrollbars(taffy, node, padding_box) {
+                    for shape in bar.shapes(always) {
+                        scene.push(clip, shape);
+                    }
+                }
+            }
+            if rounded_clip.is_some() {
+                scene.pop_clip_path();
+            }
+            // The outline is drawn over the children and isn't clipped by the element
+            let border_box = epaint::Rect::from_min_size(
+                epaint::pos2(location.x as f32, location.y as f32),
+                epaint::vec2(layout.size.width, layout.size.height),
+            );
+            if resizable(node).is_some() {
+                scene.transform = transform;
+                for shape in grip_shapes(border_box) {
+                    scene.push(clip, shape);
+                }
+            }
+            let outline = outline_shapes(node, border_box, &radii, &layout.size, viewport_size);
+            if !outline.is_empty() {
+                scene.transform = transform;
+                for shape in outline {
+                    scene.push(clip, shape);
+                }
+            }
+            if grouped {
+                scene.pop_group();
+            }
+            scene.end_node(node.id(), painted_before);
+            if let (Some(z_index), Some(start)) = (z_index, start) {
+                layered.push((z_index, start..scene.mark()));
             }
         }
         _ => {}
     }
 }
 
+/// The highlights of the selected text of an element that is edited, and its caret if the caret is shown. `location` is where the text children of the element are laid out from. Without text, the caret is at the start of the content box.
+fn editing_shapes(
+    taffy: &Taffy,
+    node: NodeRef,
+    selection: &TextSelection,
+    content_box: epaint::Rect,
+    location: Point,
+) -> (Vec<epaint::Shape>, Option<epaint::Shape>) {
+    let range = selection.range();
+    let mut highlights = Vec::new();
+    // The top left corner and height of the caret, and of the end of the text for a caret past it before the text is updated
+    let mut caret = None;
+    let mut end_of_text = None;
+    // The offset of the text of each text child in the edited text
+    let mut start = 0;
+    for child in node.children() {
+        let NodeType::Text(TextNode { text, .. }) = &*child.node_type() else {
+            continue;
+        };
+        let layout = taffy
+            .layout(child.get::<TaffyLayout>().unwrap().node.unwrap())
+            .unwrap();
+        let shaped = shape_text(
+            text,
+            &child.get::<TextStyle>().unwrap(),
+            Some(layout.size.width),
+        );
+        let origin = epaint::vec2(
+            location.x as f32 + layout.location.x,
+            location.y as f32 + layout.location.y,
+        );
+        let end = start + text.len();
+        let selected = range.start.max(start) - start..range.end.min(end).max(start) - start;
+        highlights.extend(shaped.highlights(selected).into_iter().map(|highlight| {
+            epaint::Shape::rect_filled(highlight.translate(origin), 0.0, SELECTION_COLOR)
+        }));
+        let caret_at = |offset: usize| {
+            let (position, height) = shaped.caret(offset - start);
+            (position + origin, height)
+        };
+        if caret.is_none() && (start..=end).contains(&selection.caret) {
+            caret = Some(caret_at(selection.caret));
+        }
+        end_of_text = Some(caret_at(end));
+        start = end;
+    }
+    if !selection.caret_visible(Instant::now()) {
+        return (highlights, None);
+    }
+    let (position, height) = caret.or(end_of_text).unwrap_or_else(|| {
+        let line = shape("", &node.get::<TextStyle>().unwrap());
+        (content_box.min, line.height())
+    });
+    // The caret has the color of the text, like the default caret-color of browsers
+    let color = to_color32(translate_color(&node.get::<ForgroundColor>().unwrap().0));
+    let caret = epaint::Shape::rect_filled(
+        epaint::Rect::from_min_size(
+            position,
+            epaint::vec2(system_settings().caret_width, height),
+        ),
+        0.0,
+        color,
+    );
+    (highlights, Some(caret))
+}
+
+/// The offset in the edited text of an element that is closest to a point in client coordinates, like the word a double click selects. See [`crate::editing::editable_text`].
+pub(crate) fn text_offset_at(taffy: &Taffy, node: NodeRef, point: Point) -> usize {
+    let layout = taffy
+        .layout(node.get::<TaffyLayout>().unwrap().node.unwrap())
+        .unwrap();
+    let location = get_abs_pos(*layout, taffy, node) - scroll_offset(node);
+    // The distance of the closest text child from the point along the y axis and the offset in it, so a point between two children is in the one it is closer to
+    let mut closest: Option<(f32, usize)> = None;
+    let mut start = 0;
+    for child in node.children() {
+        let NodeType::Text(TextNode { text, .. }) = &*child.node_type() else {
+            continue;
+        };
+        let child_layout = taffy
+            .layout(child.get::<TaffyLayout>().unwrap().node.unwrap())
+            .unwrap();
+        let shaped = shape_text(
+            text,
+            &child.get::<TextStyle>().unwrap(),
+            Some(child_layout.size.width),
+        );
+        let top = location.y as f32 + child_layout.location.y;
+        let local = epaint::pos2(
+            (point.x - location.x) as f32 - child_layout.location.x,
+            point.y as f32 - top,
+        );
+        let distance = if local.y < 0.0 {
+            -local.y
+        } else {
+            (local.y - shaped.height()).max(0.0)
+        };
+        if closest.map_or(true, |(closest, _)| distance < closest) {
+            closest = Some((distance, start + shaped.offset_at(local)));
+        }
+        start += text.len();
+    }
+    closest.map_or(start, |(_, offset)| offset)
+}
+
+/// The box inside of the padding and border of an element, where its content is drawn.
+pub(crate) fn content_box(
+    layout: &Layout,
+    style: &taffy::style::Style,
+    location: Point,
+) -> epaint::Rect {
+    // TODO: Percentages should resolve against the width of the containing block
+    let resolve = |value: LengthPercentage| match value {
+        LengthPercentage::Points(points) => points,
+        LengthPercentage::Percent(percent) => percent * layout.size.width,
+    };
+    let inset =
+        |padding: LengthPercentage, border: LengthPercentage| resolve(padding) + resolve(border);
+    let min = epaint::pos2(
+        location.x as f32 + inset(style.padding.left, style.border.left),
+        location.y as f32 + inset(style.padding.top, style.border.top),
+    );
+    let max = epaint::pos2(
+        location.x as f32 + layout.size.width - inset(style.padding.right, style.border.right),
+        location.y as f32 + layout.size.height - inset(style.padding.bottom, style.border.bottom),
+    );
+    epaint::Rect::from_min_max(min, max.max(min))
+}
+
 pub(crate) fn get_shape(
     layout: &Layout,
     node: NodeRef,
@@ -89,27 +645,10 @@ pub(crate) fn get_shape(
     let width: f64 = layout.size.width.into();
     let height: f64 = layout.size.height.into();
     let border: &Border = &node.get().unwrap();
-    let focused = node.get::<Focused>().filter(|focused| focused.0).is_some();
-    let left_border_width = if focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        border.width.left.resolve(axis, &rect, viewport_size)
-    };
-    let right_border_width = if focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        border.width.right.resolve(axis, &rect, viewport_size)
-    };
-    let top_border_width = if focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        border.width.top.resolve(axis, &rect, viewport_size)
-    };
-    let bottom_border_width = if focused {
-        FOCUS_BORDER_WIDTH
-    } else {
-        border.width.bottom.resolve(axis, &rect, viewport_size)
-    };
+    let left_border_width = border.width.left.resolve(axis, &rect, viewport_size);
+    let right_border_width = border.width.right.resolve(axis, &rect, viewport_size);
+    let top_border_width = border.width.top.resolve(axis, &rect, viewport_size);
+    let bottom_border_width = border.width.bottom.resolve(axis, &rect, viewport_size);
 
     // The stroke is drawn on the outside of the border, so we need to offset the rect by the border width for each side.
     let x_start = x + left_border_width / 2.0;
@@ -119,6 +658,7 @@ pub(crate) fn get_shape(
 
     let background = node.get::<Background>().unwrap();
     let border_color = translate_color(&border.colors.bottom);
+    let radii = corner_radii(border, &rect, viewport_size);
 
     epaint::Shape::Rect(epaint::RectShape {
         rect: epaint::Rect {
@@ -131,23 +671,12 @@ pub(crate) fn get_shape(
                 y: y_end as f32,
             },
         },
+        // Backends draw circular corners, elliptical ones are drawn as paths by `rounded_rect`
         rounding: epaint::Rounding {
-            nw: border.radius.top_left.0.resolve(axis, &rect, viewport_size) as f32,
-            ne: border
-                .radius
-                .top_right
-                .0
-                .resolve(axis, &rect, viewport_size) as f32,
-            se: border
-                .radius
-                .bottom_right
-                .0
-                .resolve(axis, &rect, viewport_size) as f32,
-            sw: border
-                .radius
-                .bottom_left
-                .0
-                .resolve(axis, &rect, viewport_size) as f32,
+            nw: radii[0].min_elem(),
+            ne: radii[1].min_elem(),
+            se: radii[2].min_elem(),
+            sw: radii[3].min_elem(),
         },
         fill: Color32::from_rgba_unmultiplied(
             background.color.r,
@@ -167,20 +696,227 @@ pub(crate) fn get_shape(
     })
 }
 
+/// The shapes of the outline around the border box of an element, or of the focus ring of a focused element. The outline follows the rounded corners of the border box.
+fn outline_shapes(
+    node: NodeRef,
+    border_box: epaint::Rect,
+    radii: &CornerRadii,
+    size: &Size<f32>,
+    viewport_size: &Size<u32>,
+) -> Vec<epaint::Shape> {
+    let outline = node.get::<Outline>().unwrap();
+    let focused = node.get::<Focused>().filter(|focused| focused.0).is_some();
+    // Elements that don't set an outline style get the focus ring
+    let ring = focused && matches!(outline.style, None | Some(OutlineStyle::Auto));
+    let style = match outline.style {
+        Some(OutlineStyle::LineStyle(LineStyle::None | LineStyle::Hidden)) => return Vec::new(),
+        Some(OutlineStyle::LineStyle(style)) => style,
+        _ if ring => LineStyle::Solid,
+        _ => return Vec::new(),
+    };
+    let width = match &outline.width {
+        Some(width) => width.resolve(Axis::Min, size, viewport_size) as f32,
+        None if ring => FOCUS_RING_WIDTH,
+        None => BorderSideWidth::Medium.resolve(Axis::Min, size, viewport_size) as f32,
+    };
+    let foreground = || to_color32(translate_color(&node.get::<ForgroundColor>().unwrap().0));
+    let color = match &outline.color {
+        Some(CssColor::CurrentColor) => foreground(),
+        Some(color) => to_color32(translate_color(color)),
+        None if ring => FOCUS_RING_COLOR,
+        None => foreground(),
+    };
+    let offset = match &outline.offset {
+        Some(offset) => offset.resolve(Axis::Min, size, viewport_size) as f32,
+        None if ring => FOCUS_RING_OFFSET,
+        None => 0.0,
+    };
+    if width <= 0.0 || color == Color32::TRANSPARENT {
+        return Vec::new();
+    }
+
+    // The stroke is centered on the middle of the outline. Rounded corners grow with the distance from the border box, square ones stay square
+    let distance = offset + width / 2.0;
+    let radii = radii.map(|radius| {
+        if radius.min_elem() > 0.0 {
+            (radius + epaint::Vec2::splat(distance)).max(epaint::Vec2::ZERO)
+        } else {
+            radius
+        }
+    });
+    let rect = epaint::RectShape {
+        rect: border_box.expand(distance),
+        rounding: epaint::Rounding {
+            nw: radii[0].min_elem(),
+            ne: radii[1].min_elem(),
+            se: radii[2].min_elem(),
+            sw: radii[3].min_elem(),
+        },
+        fill: Color32::TRANSPARENT,
+        stroke: epaint::Stroke::new(width, color),
+    };
+    match style {
+        LineStyle::Solid => rounded_rect(&rect, &radii),
+        style => styled_border(&rect, &radii, style),
+    }
+}
+
+/// The widths of the top, right, bottom and left border.
+fn border_widths(border: &Border, layout: &Layout, viewport_size: &Size<u32>) -> [f32; 4] {
+    let width = &border.width;
+    [&width.top, &width.right, &width.bottom, &width.left]
+        .map(|width| width.resolve(Axis::Min, &layout.size, viewport_size) as f32)
+}
+
+/// Create the border for a rect with a style that can't be drawn as part of the rect's stroke.
+fn styled_border(
+    rect: &epaint::RectShape,
+    radii: &CornerRadii,
+    style: LineStyle,
+) -> Vec<epaint::Shape> {
+    let stroke = rect.stroke;
+    let width = stroke.width;
+    if width <= 0.0 {
+        return Vec::new();
+    }
+    // The outline of the rect moved outward by the offset
+    let outline = |offset: f32| {
+        let radii =
+            radii.map(|radius| (radius + epaint::Vec2::splat(offset)).max(epaint::Vec2::ZERO));
+        rounded_outline(rect.rect.expand(offset), &radii)
+    };
+    let closed_outline = || {
+        let mut path = outline(0.0);
+        if let Some(first) = path.first().copied() {
+            path.push(first);
+        }
+        path
+    };
+    match style {
+        LineStyle::None | LineStyle::Hidden => Vec::new(),
+        LineStyle::Dashed => {
+            epaint::Shape::dashed_line(&closed_outline(), stroke, width * 3.0, width * 3.0)
+        }
+        LineStyle::Dotted => {
+            epaint::Shape::dotted_line(&closed_outline(), stroke.color, width * 2.0, width / 2.0)
+        }
+        LineStyle::Double => {
+            // Two lines that are each a third of the border width with a gap of the same size in between
+            let line = epaint::Stroke::new(width / 3.0, stroke.color);
+            vec![
+                epaint::Shape::closed_line(outline(width / 3.0), line),
+                epaint::Shape::closed_line(outline(-width / 3.0), line),
+            ]
+        }
+        // TODO: Support the 3d border styles. For now they are drawn like solid borders
+        _ => rounded_rect(
+            &epaint::RectShape {
+                fill: epaint::Color32::TRANSPARENT,
+                ..*rect
+            },
+            radii,
+        ),
+    }
+}
+
+/// The horizontal and vertical radius of the top left, top right, bottom right and bottom left corner of a box
+type CornerRadii = [epaint::Vec2; 4];
+
+/// Resolve the corner radii of a border box. Percentages of the horizontal radius are relative to the width of the box and of the vertical radius to its height.
+///
+/// Radii that would overlap are scaled down together until they fit, like [CSS](https://www.w3.org/TR/css-backgrounds-3/#corner-overlap) does.
+fn corner_radii(border: &Border, size: &Size<f32>, viewport_size: &Size<u32>) -> CornerRadii {
+    let radius = &border.radius;
+    let mut radii = [
+        &radius.top_left,
+        &radius.top_right,
+        &radius.bottom_right,
+        &radius.bottom_left,
+    ]
+    .map(|radius| {
+        epaint::vec2(
+            radius.0.resolve(Axis::X, size, viewport_size) as f32,
+            radius.1.resolve(Axis::Y, size, viewport_size) as f32,
+        )
+        .max(epaint::Vec2::ZERO)
+    });
+    let [nw, ne, se, sw] = radii;
+    let scale = [
+        size.width / (nw.x + ne.x),
+        size.width / (sw.x + se.x),
+        size.height / (nw.y + sw.y),
+        size.height / (ne.y + se.y),
+    ]
+    .into_iter()
+    .filter(|scale| scale.is_finite())
+    .fold(1.0, f32::min);
+    if scale < 1.0 {
+        for radius in &mut radii {
+            *radius *= scale;
+        }
+    }
+    radii
+}
+
+/// The shapes that draw a rect with corner radii. Rects with circular corners stay rects, which backends draw directly.
+fn rounded_rect(rect: &epaint::RectShape, radii: &CornerRadii) -> Vec<epaint::Shape> {
+    if radii.iter().all(|radius| radius.x == radius.y) {
+        return vec![epaint::Shape::Rect(*rect)];
+    }
+    vec![epaint::Shape::Path(epaint::PathShape {
+        points: rounded_outline(rect.rect, radii),
+        closed: true,
+        fill: rect.fill,
+        stroke: rect.stroke,
+    })]
+}
+
+/// The outline of a rect with elliptical corners in clockwise order, starting at the bottom right corner like the paths of epaint.
+pub(crate) fn rounded_outline(rect: epaint::Rect, radii: &CornerRadii) -> Vec<epaint::Pos2> {
+    use std::f32::consts::FRAC_PI_2;
+
+    let [nw, ne, se, sw] =
+        radii.map(|radius| radius.min(rect.size() / 2.0).max(epaint::Vec2::ZERO));
+    // Each corner with its radius, the direction towards the inside of the rect and the quadrant of the ellipse it is drawn with
+    let corners = [
+        (rect.right_bottom(), se, epaint::vec2(-1.0, -1.0), 0.0),
+        (rect.left_bottom(), sw, epaint::vec2(1.0, -1.0), 1.0),
+        (rect.left_top(), nw, epaint::vec2(1.0, 1.0), 2.0),
+        (rect.right_top(), ne, epaint::vec2(-1.0, 1.0), 3.0),
+    ];
+    let mut path = Vec::new();
+    for (corner, radius, inward, quadrant) in corners {
+        if radius.x <= 0.0 || radius.y <= 0.0 {
+            path.push(corner);
+            continue;
+        }
+        let center = corner + inward * radius;
+        // Larger corners are split into more segments to stay smooth
+        let segments = (radius.max_elem().sqrt() * 2.0).ceil().clamp(2.0, 32.0) as usize;
+        for i in 0..=segments {
+            let angle = (quadrant + i as f32 / segments as f32) * FRAC_PI_2;
+            path.push(center + epaint::vec2(radius.x * angle.cos(), radius.y * angle.sin()));
+        }
+    }
+    path
+}
+
 pub(crate) fn get_abs_pos(layout: Layout, taffy: &Taffy, node: NodeRef) -> Point {
     let mut node_layout = layout.location;
     let mut current = node.id();
     while let Some(parent) = node.real_dom().get(current).unwrap().parent() {
         let parent_id = parent.id();
-        // the root element is positioned at (0, 0)
-        if parent_id == node.real_dom().root_id() {
+        // the root element is positioned at (0, 0), and fixed elements are laid out in it
+        if parent_id == node.real_dom().root_id() || is_fixed(node.real_dom().get(current).unwrap())
+        {
             break;
         }
         current = parent_id;
         let taffy_node = parent.get::<TaffyLayout>().unwrap().node.unwrap();
         let parent_layout = taffy.layout(taffy_node).unwrap();
-        node_layout.x += parent_layout.location.x;
-        node_layout.y += parent_layout.location.y;
+        let scrolled = scroll_offset(parent);
+        node_layout.x += parent_layout.location.x - scrolled.x as f32;
+        node_layout.y += parent_layout.location.y - scrolled.y as f32;
     }
     Point::new(node_layout.x as f64, node_layout.y as f64)
 }
@@ -1,197 +1,1796 @@
+use std::ffi::c_void;
 use std::mem::size_of;
+use std::time::Duration;
 
-use beuk::ash::vk::{
-    self, PipelineVertexInputStateCreateInfo, PushConstantRange, ShaderStageFlags,
-};
-use beuk::memory::MemoryLocation;
-use beuk::pipeline::BlendState;
-use beuk::{ctx::RenderContext, memory::PipelineHandle};
-use beuk::{
-    pipeline::{GraphicsPipelineDescriptor, PrimitiveState},
-    shaders::Shader,
-};
+use beuk::ash::ext::debug_utils;
+use beuk::ash::vk::{self, PipelineVertexInputStateCreateInfo, PresentModeKHR};
+use beuk::ctx::{RenderContext, RenderContextDescriptor};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 
-use epaint::{Primitive, TessellationOptions};
+use std::borrow::Cow;
 
+use epaint::{Color32, ImageDelta, Mesh, Rgba};
+use peniko::kurbo::Affine;
+use rustc_hash::FxHashMap;
+use tao::{dpi::PhysicalSize, window::Window};
+
+use crate::attachment::Attachment;
+use crate::backend::{Backend, ColorMatrix, DrawConstants, Msaa, PresentMode, RectInstance};
+use crate::buffer::GrowableBuffer;
+use crate::pipeline::{set_viewport, Blend, Pipeline, PipelineDescriptor};
+use crate::readback::ReadbackBuffer;
+use crate::screenshot::image_from_texels;
+use crate::texture::{create_sampler, create_texture_layout, SampledImage, Texture};
+
+impl Msaa {
+    fn sample_count(self) -> vk::SampleCountFlags {
+        match self {
+            Msaa::Off => vk::SampleCountFlags::TYPE_1,
+            Msaa::X2 => vk::SampleCountFlags::TYPE_2,
+            Msaa::X4 => vk::SampleCountFlags::TYPE_4,
+            Msaa::X8 => vk::SampleCountFlags::TYPE_8,
+        }
+    }
+
+    /// The highest sample count up to this one that the adapter supports
+    fn supported(self, ctx: &RenderContext) -> Self {
+        let properties = unsafe {
+            ctx.instance
+                .get_physical_device_properties(ctx.physical_device)
+        };
+        let supported = properties.limits.framebuffer_color_sample_counts;
+        [Msaa::X8, Msaa::X4, Msaa::X2]
+            .into_iter()
+            .filter(|msaa| *msaa as u8 <= self as u8)
+            .find(|msaa| supported.contains(msaa.sample_count()))
+            .unwrap_or(Msaa::Off)
+    }
+}
+
+/// The default backend, which draws with Vulkan through beuk.
+pub(crate) struct VulkanBackend {
+    render_context: RenderContext,
+    pipeline: Pipeline,
+    /// Draws rects as quads filled with their signed distance field
+    rect_pipeline: Pipeline,
+    /// Draws a filtered or translucent layer over the layer below it
+    composite_pipeline: Pipeline,
+    /// Draws the frame to the swapchain image, which isn't multisampled
+    present_pipeline: Pipeline,
+    /// Draws meshes that sample the image of a cached layer, whose colors are stored like the frame
+    layer_pipeline: Pipeline,
+    /// Darkens the pixels below subpixel text by the coverage of each color channel, see coverage.frag
+    coverage_pipeline: Pipeline,
+    /// Adds the color of subpixel text to the pixels the coverage pipeline darkened, see subpixel.frag
+    subpixel_pipeline: Pipeline,
+    /// Blurs the rows of an area of a target into the blur image
+    blur_pipeline: Pipeline,
+    /// Blurs the columns of the blur image back into the target
+    backdrop_pipeline: Pipeline,
+    /// Pipelines replaced by [`Backend::reload_shaders`], with the index of the first frame that doesn't draw with them. They are destroyed once that frame is recorded
+    retired_pipelines: Vec<(u64, Pipeline)>,
+    /// The index of the frame that is recorded next
+    frame_index: u64,
+    /// The layout of the descriptor set textures are bound with and the sampler they are sampled with
+    texture_layout: vk::DescriptorSetLayout,
+    sampler: vk::Sampler,
+    /// The texture meshes sample with their uv coordinates
+    texture: Texture,
+    msaa: Msaa,
+    /// The image the frame is drawn into. It is kept between frames, so only the damaged area is drawn again, and drawn to the swapchain image at the end of each frame
+    frame: Option<OffscreenTarget>,
+    /// The area of the frame that is drawn again, in whole pixels
+    damage: epaint::Rect,
+    /// The layers filtered and translucent elements are drawn into, one for each level of nesting. Created with the first frame that has such elements, with the size of the swapchain or of the largest cached layer they were drawn in
+    filter_targets: Vec<OffscreenTarget>,
+    /// The image the rows of blurred backdrops are blurred into, as large as the filter targets. Created with the first blurred backdrop
+    blur_target: Option<SampledImage>,
+    /// The images of cached layers by id, see [`Backend::begin_cached_layer`]
+    cached_layers: FxHashMap<u64, SampledImage>,
+    /// The multisampled image cached layers are drawn into and resolved from. Layers are drawn one at a time, so they share it. It grows to the largest layer
+    layer_msaa: Option<Attachment>,
+    /// Images of cached layers that were released or replaced, with the index of the first frame that doesn't sample them, like `retired_pipelines`
+    retired_layers: Vec<(u64, SampledImage)>,
+    /// The damaged area of the frame while a cached layer is drawn
+    frame_damage: Option<epaint::Rect>,
+    vertex_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+    rect_buffer: GrowableBuffer,
+    /// The largest index the adapter can draw
+    max_index: u32,
+    /// The frame that is being drawn. All meshes share one vertex and one index buffer and are drawn with an offset into them
+    clear_color: Color32,
+    vertices: Vec<epaint::Vertex>,
+    indices: Vec<u32>,
+    rects: Vec<RectInstance>,
+    draw_list: Vec<Draw>,
+    /// The timestamps written at the start and the end of each frame, once GPU timing is enabled
+    timestamps: Option<Timestamps>,
+    /// Set by [`Backend::capture_next_frame`] until the next frame is drawn
+    capture_next: bool,
+    /// The frame copied to the CPU, until it is taken by [`Backend::captured_frame`]
+    captured: Option<image::RgbaImage>,
+    /// Records validation messages for diagnostics reports
+    debug_messenger: Option<(debug_utils::Instance, vk::DebugUtilsMessengerEXT)>,
+}
+
+struct Timestamps {
+    pool: vk::QueryPool,
+    /// The nanoseconds per tick of a timestamp
+    period: f32,
+    /// Set once a frame wrote the timestamps, so they can be read
+    written: bool,
+    /// The GPU time of the last frame whose timestamps were read
+    last: Option<Duration>,
+}
+
+/// Primitives that are drawn with one set of push constants
+struct Draw {
+    primitives: Primitives,
+    transform: Affine,
+    clip: epaint::Rect,
+}
+
+enum Primitives {
+    /// A range of the index buffer
+    Mesh {
+        first_index: u32,
+        index_count: u32,
+        vertex_offset: i32,
+        /// The cached layer the mesh samples instead of the texture
+        layer: Option<u64>,
+        /// Text with subpixel glyphs, which is drawn twice, see [`Backend::draw_subpixel_text`]
+        subpixel: bool,
+    },
+    /// A range of the rect buffer, drawn with one instance per rect
+    Rects { first_rect: u32, rect_count: u32 },
+    /// Blur what was drawn before in an area of whole pixels, see [`Backend::blur_backdrop`]
+    BackdropBlur { area: epaint::Rect, radius: f32 },
+    /// Draw into a new layer, see [`Backend::begin_filter`]
+    BeginFilter,
+    /// Draw the layer over the one below it with an opacity, see [`Backend::end_filter`]
+    EndFilter(ColorMatrix, f32),
+    /// Draw into the image of a cached layer, see [`Backend::begin_cached_layer`]
+    BeginCachedLayer(u64),
+    /// Go back to drawing the frame
+    EndCachedLayer,
+}
+
+/// An image that the frame or a filtered layer is drawn into
+struct OffscreenTarget {
+    /// The image composite.frag samples
+    resolved: SampledImage,
+    /// The multisampled image that is resolved into it
+    msaa: Option<Attachment>,
+}
+
+/// What a pass draws to: the frame, a filtered layer or a cached layer
+#[derive(Clone, Copy)]
+struct RenderTarget<'a> {
+    /// The image that is sampled once it is drawn
+    image: &'a SampledImage,
+    /// The multisampled image that is resolved into it
+    msaa: Option<&'a Attachment>,
+}
+
+/// What a pass that draws to a [`RenderTarget`] starts with
+#[derive(Clone, Copy)]
+enum Load {
+    /// Clear the area of the pass to a color and throw away the rest of the target
+    Clear([f32; 4]),
+    /// Clear the area of the pass to a color and keep the rest of the target, which the last frame drew
+    ClearArea([f32; 4]),
+    /// Keep what the passes before drew into the target
+    Keep,
+    /// Keep what the passes before drew into the target, which was sampled since
+    Resume,
+}
+
+impl OffscreenTarget {
+    fn new(
+        ctx: &RenderContext,
+        extent: vk::Extent2D,
+        msaa: Msaa,
+        layout: vk::DescriptorSetLayout,
+        sampler: vk::Sampler,
+    ) -> Self {
+        let format = ctx.render_swapchain.surface_format.format;
+        let image = Attachment::new(
+            ctx,
+            extent,
+            format,
+            vk::SampleCountFlags::TYPE_1,
+            // The frame is copied from for screenshots
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_SRC,
+        );
+        // The samples are kept while nested layers are drawn, so the image can't be transient
+        let msaa = (msaa != Msaa::Off).then(|| {
+            Attachment::new(
+                ctx,
+                extent,
+                format,
+                msaa.sample_count(),
+                vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            )
+        });
+        Self {
+            resolved: SampledImage::new(ctx, image, layout, sampler),
+            msaa,
+        }
+    }
+
+    fn target(&self) -> RenderTarget<'_> {
+        RenderTarget {
+            image: &self.resolved,
+            msaa: self.msaa.as_ref(),
+        }
+    }
+
+    /// Free the images. The GPU must be done using them.
+    fn destroy(&self, ctx: &RenderContext) {
+        self.resolved.destroy(ctx);
+        if let Some(msaa) = &self.msaa {
+            msaa.destroy(ctx);
+        }
+    }
+}
+
+impl RenderTarget<'_> {
+    /// Begin a pass that draws to an area of the target. The multisampled image is only resolved in the area, so it can be larger than the image.
+    fn begin_rendering(
+        &self,
+        device: &beuk::ash::Device,
+        command_buffer: vk::CommandBuffer,
+        area: vk::Rect2D,
+        load: Load,
+    ) {
+        let clear = match load {
+            Load::Clear(color) => {
+                self.image.image.begin_rendering(device, command_buffer);
+                if let Some(msaa) = self.msaa {
+                    msaa.begin_rendering(device, command_buffer);
+                }
+                Some(color)
+            }
+            Load::ClearArea(color) => {
+                self.image.image.resume_rendering(device, command_buffer);
+                // The samples outside of the area are never resolved, so the ones of the last frame aren't needed
+                if let Some(msaa) = self.msaa {
+                    msaa.begin_rendering(device, command_buffer);
+                }
+                Some(color)
+            }
+            Load::Keep | Load::Resume => {
+                if matches!(load, Load::Resume) {
+                    self.image.image.resume_rendering(device, command_buffer);
+                }
+                // The pass loads what the last pass stored
+                let barrier = vk::MemoryBarrier::default()
+                    .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                    .dst_access_mask(
+                        vk::AccessFlags::COLOR_ATTACHMENT_READ
+                            | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                    );
+                unsafe {
+                    device.cmd_pipeline_barrier(
+                        command_buffer,
+                        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                        vk::DependencyFlags::empty(),
+                        std::slice::from_ref(&barrier),
+                        &[],
+                        &[],
+                    )
+                };
+                None
+            }
+        };
+        // Clearing only clears the render area
+        let attachment = vk::RenderingAttachmentInfo::default()
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(match clear {
+                Some(_) => vk::AttachmentLoadOp::CLEAR,
+                None => vk::AttachmentLoadOp::LOAD,
+            })
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .clear_value(vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: clear.unwrap_or_default(),
+                },
+            });
+        let attachment = match self.msaa {
+            Some(msaa) => attachment
+                .image_view(msaa.view)
+                .resolve_mode(vk::ResolveModeFlags::AVERAGE)
+                .resolve_image_view(self.image.image.view)
+                .resolve_image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            None => attachment.image_view(self.image.image.view),
+        };
+        let info = vk::RenderingInfo::default()
+            .render_area(area)
+            .layer_count(1)
+            .color_attachments(std::slice::from_ref(&attachment));
+        unsafe { device.cmd_begin_rendering(command_buffer, &info) };
+    }
+}
+
+/// The push constants of composite.frag
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CompositeConstants {
+    /// The columns of the color matrix, like GLSL stores matrices
+    color_matrix: [[f32; 4]; 4],
+    opacity: f32,
+    /// See [`DrawConstants::encode_srgb`]
+    encode_srgb: u32,
+    _padding: [u32; 2],
+}
+
+/// The push constants of blur.frag
 #[repr(C)]
-#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct PushConstants {
-    pub screen_size: [f32; 2],
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct BlurConstants {
+    direction: [f32; 2],
+    area_min: [f32; 2],
+    area_max: [f32; 2],
+    sigma: f32,
+    /// See [`DrawConstants::encode_srgb`]
+    encode_srgb: u32,
 }
 
-pub struct Renderer {
-    pub pipeline_handle: PipelineHandle,
-    // pub vertex_buffer: Option<BufferHandle>,
-    // pub index_buffer: Option<BufferHandle>,
-    pub shapes: Vec<epaint::ClippedShape>,
+/// A target that passes draw to while the frame is recorded
+struct Level<'a> {
+    target: RenderTarget<'a>,
+    /// The size of the viewport, which is the size of the frame or of the cached layer the target is drawn in
+    extent: vk::Extent2D,
+    /// The area the passes draw to, the damaged area of the frame or the whole cached layer
+    area: vk::Rect2D,
+    /// What the next pass starts with
+    load: Load,
 }
 
-impl Renderer {
-    pub fn new(ctx: &mut RenderContext) -> Self {
-        let vertex_shader = Shader::from_source_text(
-            &ctx.device,
-            include_str!("./shader.vert"),
-            "shader.vert",
-            beuk::shaders::ShaderKind::Vertex,
-            "main",
+/// Records the draws of a frame into its command buffer
+struct FrameRecorder<'a> {
+    ctx: &'a RenderContext,
+    command_buffer: vk::CommandBuffer,
+    mesh_pipeline: &'a Pipeline,
+    rect_pipeline: &'a Pipeline,
+    composite_pipeline: &'a Pipeline,
+    present_pipeline: &'a Pipeline,
+    layer_pipeline: &'a Pipeline,
+    /// The coverage and the subpixel pipeline, which draw subpixel text in this order
+    subpixel_pipelines: [&'a Pipeline; 2],
+    blur_pipeline: &'a Pipeline,
+    backdrop_pipeline: &'a Pipeline,
+    texture_set: vk::DescriptorSet,
+    cached_layers: &'a FxHashMap<u64, SampledImage>,
+    encode_srgb: bool,
+}
+
+impl FrameRecorder<'_> {
+    /// Begin a pass that draws to a level, unless its area is empty. Returns true if the pass was begun.
+    fn begin(&self, level: &mut Level) -> bool {
+        if level.area.extent.width == 0 || level.area.extent.height == 0 {
+            return false;
+        }
+        let device = &self.ctx.device;
+        level
+            .target
+            .begin_rendering(device, self.command_buffer, level.area, level.load);
+        set_viewport(device, self.command_buffer, level.extent, level.area);
+        level.load = Load::Keep;
+        true
+    }
+
+    /// Draw meshes and rects into the current pass, whose viewport has a size. The pipelines are bound again in each range, because composites bind their own.
+    fn draw_range(&self, draws: &[Draw], extent: vk::Extent2D) {
+        let device = &self.ctx.device;
+        let command_buffer = self.command_buffer;
+        let mut bound_pipeline = None;
+        let mut bound_set = None;
+        for draw in draws {
+            let (pipelines, set) = match draw.primitives {
+                Primitives::Mesh {
+                    layer: None,
+                    subpixel: false,
+                    ..
+                } => (
+                    std::slice::from_ref(&self.mesh_pipeline),
+                    Some(self.texture_set),
+                ),
+                // Subpixel text darkens each channel by its coverage before its color is added, which needs a draw for each blend. Where glyphs overlap, all of them darken the pixel before any color is added, unlike on the CPU
+                Primitives::Mesh {
+                    layer: None,
+                    subpixel: true,
+                    ..
+                } => (&self.subpixel_pipelines[..], Some(self.texture_set)),
+                Primitives::Mesh {
+                    layer: Some(id), ..
+                } => match self.cached_layers.get(&id) {
+                    Some(layer) => (std::slice::from_ref(&self.layer_pipeline), Some(layer.set)),
+                    None => continue,
+                },
+                Primitives::Rects { .. } => (std::slice::from_ref(&self.rect_pipeline), None),
+                // Blurs, filters and cached layers end the pass, see `end_frame`
+                Primitives::BackdropBlur { .. }
+                | Primitives::BeginFilter
+                | Primitives::EndFilter(..)
+                | Primitives::BeginCachedLayer(_)
+                | Primitives::EndCachedLayer => continue,
+            };
+            for pipeline in pipelines {
+                unsafe {
+                    if bound_pipeline != Some(pipeline.pipeline) {
+                        pipeline.bind(device, command_buffer);
+                        bound_pipeline = Some(pipeline.pipeline);
+                        bound_set = None;
+                    }
+                    if let Some(set) = set.filter(|set| bound_set != Some(*set)) {
+                        device.cmd_bind_descriptor_sets(
+                            command_buffer,
+                            vk::PipelineBindPoint::GRAPHICS,
+                            pipeline.layout,
+                            0,
+                            &[set],
+                            &[],
+                        );
+                        bound_set = Some(set);
+                    }
+                    // Each layer is drawn with its own transform and clip, so moving a layer only changes these constants
+                    device.cmd_push_constants(
+                        command_buffer,
+                        pipeline.layout,
+                        vk::ShaderStageFlags::ALL_GRAPHICS,
+                        0,
+                        bytemuck::bytes_of(&DrawConstants::new(
+                            [extent.width as f32, extent.height as f32],
+                            draw.transform,
+                            draw.clip,
+                            self.encode_srgb,
+                            1.0,
+                        )),
+                    );
+                    match draw.primitives {
+                        Primitives::Mesh {
+                            first_index,
+                            index_count,
+                            vertex_offset,
+                            ..
+                        } => device.cmd_draw_indexed(
+                            command_buffer,
+                            index_count,
+                            1,
+                            first_index,
+                            vertex_offset,
+                            0,
+                        ),
+                        // The vertex shader builds the 6 vertices of the quad from the vertex index
+                        Primitives::Rects {
+                            first_rect,
+                            rect_count,
+                        } => device.cmd_draw(command_buffer, 6, rect_count, 0, first_rect),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draw a finished layer over the current pass with a color matrix and an opacity.
+    fn composite(&self, layer: &SampledImage, matrix: &ColorMatrix, opacity: f32) {
+        self.draw_layer(self.composite_pipeline, layer, matrix, opacity);
+    }
+
+    /// Draw the finished frame to the swapchain image.
+    fn present(&self, frame: &SampledImage) {
+        self.draw_layer(self.present_pipeline, frame, &ColorMatrix::IDENTITY, 1.0);
+    }
+
+    fn draw_layer(
+        &self,
+        pipeline: &Pipeline,
+        layer: &SampledImage,
+        matrix: &ColorMatrix,
+        opacity: f32,
+    ) {
+        // GLSL matrices are stored as columns
+        let constants = CompositeConstants {
+            color_matrix: std::array::from_fn(|column| matrix.0.map(|row| row[column])),
+            opacity,
+            encode_srgb: self.encode_srgb as u32,
+            _padding: [0; 2],
+        };
+        self.draw_effect(pipeline, layer, bytemuck::bytes_of(&constants));
+    }
+
+    /// Blur an area of what was drawn to a level, after its pass ended. The rows are blurred into the blur image and the columns back into the level.
+    fn blur(&self, level: &Level, blur_target: &SampledImage, area: vk::Rect2D, radius: f32) {
+        let device = &self.ctx.device;
+        let command_buffer = self.command_buffer;
+        let constants = |direction: [f32; 2]| BlurConstants {
+            direction,
+            area_min: [area.offset.x as f32, area.offset.y as f32],
+            area_max: [
+                (area.offset.x + area.extent.width as i32) as f32,
+                (area.offset.y + area.extent.height as i32) as f32,
+            ],
+            sigma: radius,
+            encode_srgb: self.encode_srgb as u32,
+        };
+        let source = level.target.image;
+        source.image.end_rendering(device, command_buffer);
+        let rows = RenderTarget {
+            image: blur_target,
+            msaa: None,
+        };
+        rows.begin_rendering(device, command_buffer, area, Load::Clear([0.0; 4]));
+        set_viewport(device, command_buffer, level.extent, area);
+        self.draw_effect(
+            self.blur_pipeline,
+            source,
+            bytemuck::bytes_of(&constants([1.0, 0.0])),
+        );
+        unsafe { device.cmd_end_rendering(command_buffer) };
+        blur_target.image.end_rendering(device, command_buffer);
+        // The columns are drawn through the samples of the level, so the passes after it resolve the blurred pixels
+        level
+            .target
+            .begin_rendering(device, command_buffer, area, Load::Resume);
+        set_viewport(device, command_buffer, level.extent, area);
+        self.draw_effect(
+            self.backdrop_pipeline,
+            blur_target,
+            bytemuck::bytes_of(&constants([0.0, 1.0])),
         );
+        unsafe { device.cmd_end_rendering(command_buffer) };
+    }
 
-        let fragment_shader = Shader::from_source_text(
-            &ctx.device,
+    /// Draw a triangle that covers the current pass with a pipeline that samples an image.
+    fn draw_effect(&self, pipeline: &Pipeline, source: &SampledImage, constants: &[u8]) {
+        let device = &self.ctx.device;
+        pipeline.bind(device, self.command_buffer);
+        unsafe {
+            device.cmd_bind_descriptor_sets(
+                self.command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.layout,
+                0,
+                &[source.set],
+                &[],
+            );
+            device.cmd_push_constants(
+                self.command_buffer,
+                pipeline.layout,
+                vk::ShaderStageFlags::ALL_GRAPHICS,
+                0,
+                constants,
+            );
+            // The vertex shader builds a triangle that covers the target from the vertex index
+            device.cmd_draw(self.command_buffer, 3, 1, 0, 0);
+        }
+    }
+}
+
+impl VulkanBackend {
+    /// The present mode is used as it is, see [`crate::Config::with_present_mode`].
+    ///
+    /// TODO: Fall back to FIFO when the surface doesn't support the present mode, like the wgpu backend, once beuk exposes the surface to query its modes. Draw to HDR surfaces once beuk lets the backend choose the format of the swapchain
+    pub fn new(window: &Window, msaa: Msaa, present_mode: PresentMode) -> Self {
+        let ctx = RenderContext::new(RenderContextDescriptor {
+            display_handle: window.raw_display_handle(),
+            window_handle: window.raw_window_handle(),
+            present_mode: match present_mode {
+                PresentMode::Fifo => PresentModeKHR::FIFO,
+                PresentMode::Mailbox => PresentModeKHR::MAILBOX,
+                PresentMode::Immediate => PresentModeKHR::IMMEDIATE,
+            },
+        });
+        let requested = msaa;
+        let msaa = requested.supported(&ctx);
+        if msaa != requested {
+            log::warn!(
+                "{requested:?} multisampling is not supported by the adapter, using {msaa:?}"
+            );
+        }
+        let texture_layout = create_texture_layout(&ctx.device);
+        let sampler = create_sampler(&ctx.device);
+        let texture = Texture::new(&ctx, texture_layout, sampler);
+        let pipeline = create_pipeline(
+            &ctx,
+            msaa,
+            texture_layout,
+            include_str!("./shader.vert"),
             include_str!("./shader.frag"),
-            "shader.frag",
-            beuk::shaders::ShaderKind::Fragment,
-            "main",
-        );
+            Blend::Over,
+        )
+        .unwrap_or_else(|err| panic!("failed to compile the built-in shaders: {err}"));
+        let rect_pipeline = create_rect_pipeline(&ctx, msaa)
+            .unwrap_or_else(|err| panic!("failed to compile the built-in rect shaders: {err}"));
+        let composite_pipeline = create_composite_pipeline(&ctx, msaa, texture_layout)
+            .unwrap_or_else(|err| {
+                panic!("failed to compile the built-in composite shaders: {err}")
+            });
+        let present_pipeline = create_composite_pipeline(&ctx, Msaa::Off, texture_layout)
+            .unwrap_or_else(|err| {
+                panic!("failed to compile the built-in composite shaders: {err}")
+            });
+        let layer_pipeline = create_pipeline(
+            &ctx,
+            msaa,
+            texture_layout,
+            include_str!("./shader.vert"),
+            include_str!("./layer.frag"),
+            Blend::Over,
+        )
+        .unwrap_or_else(|err| panic!("failed to compile the built-in layer shaders: {err}"));
+        let coverage_pipeline = create_pipeline(
+            &ctx,
+            msaa,
+            texture_layout,
+            include_str!("./shader.vert"),
+            include_str!("./coverage.frag"),
+            Blend::Coverage,
+        )
+        .unwrap_or_else(|err| panic!("failed to compile the built-in subpixel shaders: {err}"));
+        let subpixel_pipeline = create_pipeline(
+            &ctx,
+            msaa,
+            texture_layout,
+            include_str!("./shader.vert"),
+            include_str!("./subpixel.frag"),
+            Blend::Add,
+        )
+        .unwrap_or_else(|err| panic!("failed to compile the built-in subpixel shaders: {err}"));
+        let blur_pipeline = create_blur_pipeline(&ctx, Msaa::Off, texture_layout)
+            .unwrap_or_else(|err| panic!("failed to compile the built-in blur shaders: {err}"));
+        let backdrop_pipeline = create_blur_pipeline(&ctx, msaa, texture_layout)
+            .unwrap_or_else(|err| panic!("failed to compile the built-in blur shaders: {err}"));
+        let max_index = unsafe {
+            ctx.instance
+                .get_physical_device_properties(ctx.physical_device)
+                .limits
+                .max_draw_indexed_index_value
+        };
 
-        let pipeline_handle =
-            ctx.pipeline_manager
-                .create_graphics_pipeline(GraphicsPipelineDescriptor {
-                    vertex_shader,
-                    fragment_shader,
-                    vertex_input: PipelineVertexInputStateCreateInfo::default()
-                        .vertex_attribute_descriptions(&[
-                            vk::VertexInputAttributeDescription {
-                                location: 0,
-                                binding: 0,
-                                format: vk::Format::R32G32_SFLOAT,
-                                offset: bytemuck::offset_of!(epaint::Vertex, pos) as u32,
-                            },
-                            vk::VertexInputAttributeDescription {
-                                location: 1,
-                                binding: 0,
-                                format: vk::Format::R32G32_SFLOAT,
-                                offset: bytemuck::offset_of!(epaint::Vertex, uv) as u32,
-                            },
-                            vk::VertexInputAttributeDescription {
-                                location: 2,
-                                binding: 0,
-                                format: vk::Format::R8G8B8A8_UNORM,
-                                offset: bytemuck::offset_of!(epaint::Vertex, color) as u32,
-                            },
-                        ])
-                        .vertex_binding_descriptions(&[vk::VertexInputBindingDescription {
-                            binding: 0,
-                            stride: std::mem::size_of::<epaint::Vertex>() as u32,
-                            input_rate: vk::VertexInputRate::VERTEX,
-                        }]),
-                    color_attachment_formats: &[ctx.render_swapchain.surface_format.format],
-                    depth_attachment_format: vk::Format::UNDEFINED,
-                    viewport: ctx.render_swapchain.surface_resolution,
-                    primitive: PrimitiveState {
-                        cull_mode: vk::CullModeFlags::NONE,
-                        topology: vk::PrimitiveTopology::TRIANGLE_LIST,
-                        front_face: vk::FrontFace::COUNTER_CLOCKWISE,
-                        ..Default::default()
-                    },
-                    depth_stencil: Default::default(),
-                    push_constant_range: Some(
-                        PushConstantRange::default()
-                            .stage_flags(ShaderStageFlags::ALL_GRAPHICS)
-                            .offset(0)
-                            .size(size_of::<PushConstants>() as u32),
-                    ),
-                    blend: vec![BlendState::ALPHA_BLENDING],
-                });
+        let debug_messenger = create_debug_messenger(&ctx);
 
         Self {
-            pipeline_handle,
+            render_context: ctx,
+            pipeline,
+            rect_pipeline,
+            composite_pipeline,
+            present_pipeline,
+            layer_pipeline,
+            coverage_pipeline,
+            subpixel_pipeline,
+            blur_pipeline,
+            backdrop_pipeline,
+            retired_pipelines: Vec::new(),
+            frame_index: 0,
+            texture_layout,
+            sampler,
+            texture,
+            msaa,
+            frame: None,
+            damage: epaint::Rect::NOTHING,
+            filter_targets: Vec::new(),
+            blur_target: None,
+            cached_layers: FxHashMap::default(),
+            layer_msaa: None,
+            retired_layers: Vec::new(),
+            frame_damage: None,
+            vertex_buffer: GrowableBuffer::new("vertices", vk::BufferUsageFlags::VERTEX_BUFFER),
+            index_buffer: GrowableBuffer::new("indices", vk::BufferUsageFlags::INDEX_BUFFER),
+            rect_buffer: GrowableBuffer::new("rects", vk::BufferUsageFlags::VERTEX_BUFFER),
+            max_index,
+            clear_color: Color32::TRANSPARENT,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            rects: Vec::new(),
+            draw_list: Vec::new(),
+            timestamps: None,
+            capture_next: false,
+            captured: None,
+            debug_messenger,
+        }
+    }
+
+    /// Read the timestamps of the last frame. Frames aren't overlapped, so they are written by the time the next frame is drawn
+    fn read_timestamps(&mut self) {
+        let Some(timestamps) = &mut self.timestamps else {
+            return;
+        };
+        if !timestamps.written {
+            return;
+        }
+        let mut ticks = [0u64; 2];
+        let read = unsafe {
+            self.render_context.device.get_query_pool_results(
+                timestamps.pool,
+                0,
+                &mut ticks,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        };
+        if read.is_ok() {
+            let nanos = ticks[1].saturating_sub(ticks[0]) as f64 * timestamps.period as f64;
+            timestamps.last = Some(Duration::from_nanos(nanos as u64));
+        }
+    }
+
+    /// Destroy the pipelines and the images of cached layers that no frame draws with anymore. Frames aren't overlapped, so the frames before the one that is recorded now are finished
+    fn destroy_retired(&mut self) {
+        let ctx = &self.render_context;
+        let frame_index = self.frame_index;
+        self.retired_pipelines.retain(|(first_unused, pipeline)| {
+            if *first_unused > frame_index {
+                return true;
+            }
+            pipeline.destroy(&ctx.device);
+            false
+        });
+        self.retired_layers.retain(|(first_unused, layer)| {
+            if *first_unused > frame_index {
+                return true;
+            }
+            layer.destroy(ctx);
+            false
+        });
+    }
 
-            shapes: vec![],
+    /// Add a mesh to the frame, split into meshes the adapter can draw. `layer` is the cached layer it samples instead of the texture and `subpixel` is set for text with subpixel glyphs.
+    fn push_mesh(
+        &mut self,
+        mesh: &Mesh,
+        transform: Affine,
+        clip: epaint::Rect,
+        opacity: f32,
+        layer: Option<u64>,
+        subpixel: bool,
+    ) {
+        // The rest of the frame is kept from the last one
+        let clip = clip.intersect(self.damage);
+        if !clip.is_positive() {
+            return;
+        }
+        for mesh in split_mesh(mesh, self.max_index) {
+            let first_index = self.indices.len() as u32;
+            let vertex_offset = self.vertices.len() as i32;
+            if opacity < 1.0 {
+                self.vertices
+                    .extend(mesh.vertices.iter().map(|vertex| epaint::Vertex {
+                        color: vertex.color.linear_multiply(opacity),
+                        ..*vertex
+                    }));
+            } else {
+                self.vertices.extend_from_slice(&mesh.vertices);
+            }
+            self.indices.extend_from_slice(&mesh.indices);
+            self.draw_list.push(Draw {
+                primitives: Primitives::Mesh {
+                    first_index,
+                    index_count: mesh.indices.len() as u32,
+                    vertex_offset,
+                    layer,
+                    subpixel,
+                },
+                transform,
+                clip,
+            });
         }
     }
 
-    pub fn render(&mut self, render_context: &mut RenderContext) {
-        let primitives = epaint::tessellator::tessellate_shapes(
-            1.0,
-            TessellationOptions::default(),
-            [1, 1],
-            vec![],
-            self.shapes.clone(),
+    /// The frame in window coordinates
+    fn window_rect(&self) -> epaint::Rect {
+        let extent = self.render_context.render_swapchain.surface_resolution;
+        epaint::Rect::from_min_size(
+            epaint::Pos2::ZERO,
+            epaint::vec2(extent.width as f32, extent.height as f32),
+        )
+    }
+
+    /// Make sure the frame has the size of the swapchain. Returns true if it was created, so it holds nothing yet.
+    fn create_frame(&mut self) -> bool {
+        let ctx = &self.render_context;
+        let extent = ctx.render_swapchain.surface_resolution;
+        if let Some(frame) = &self.frame {
+            if frame.resolved.image.extent == extent {
+                return false;
+            }
+        }
+        if let Some(frame) = self.frame.take() {
+            unsafe { ctx.device.device_wait_idle().unwrap() };
+            frame.destroy(ctx);
+        }
+        self.frame = Some(OffscreenTarget::new(
+            ctx,
+            extent,
+            self.msaa,
+            self.texture_layout,
+            self.sampler,
+        ));
+        true
+    }
+
+    /// Make sure the image blurred rows are drawn into is at least as large as `extent`.
+    fn create_blur_target(&mut self, extent: vk::Extent2D) {
+        let ctx = &self.render_context;
+        if self.blur_target.as_ref().is_some_and(|target| {
+            let size = target.image.extent;
+            size.width >= extent.width && size.height >= extent.height
+        }) {
+            return;
+        }
+        if let Some(target) = self.blur_target.take() {
+            unsafe { ctx.device.device_wait_idle().unwrap() };
+            target.destroy(ctx);
+        }
+        let image = Attachment::new(
+            ctx,
+            extent,
+            ctx.render_swapchain.surface_format.format,
+            vk::SampleCountFlags::TYPE_1,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
         );
-        let mut draw_list = Vec::with_capacity(primitives.len());
-        for (index, primitive) in primitives.iter().enumerate() {
-            match &primitive.primitive {
-                Primitive::Mesh(mesh) => {
-                    let vertex_buffer = render_context.buffer_manager.create_buffer_with_data(
-                        &format!("vertices_{}", index),
-                        bytemuck::cast_slice(&mesh.vertices),
-                        vk::BufferUsageFlags::VERTEX_BUFFER,
-                        MemoryLocation::CpuToGpu,
-                    );
+        self.blur_target = Some(SampledImage::new(
+            ctx,
+            image,
+            self.texture_layout,
+            self.sampler,
+        ));
+    }
 
-                    let index_buffer = render_context.buffer_manager.create_buffer_with_data(
-                        &format!("indices-{}", index),
-                        bytemuck::cast_slice(&mesh.indices),
-                        vk::BufferUsageFlags::INDEX_BUFFER,
-                        MemoryLocation::CpuToGpu,
-                    );
-                    draw_list.push((vertex_buffer, index_buffer, mesh.indices.len() as u32));
+    /// Make sure there are `count` offscreen targets that are at least as large as `extent`, a layer for each level of nesting.
+    fn create_filter_targets(&mut self, count: usize, extent: vk::Extent2D) {
+        let ctx = &self.render_context;
+        if self.filter_targets.first().is_some_and(|target| {
+            let size = target.resolved.image.extent;
+            size.width < extent.width || size.height < extent.height
+        }) {
+            unsafe { ctx.device.device_wait_idle().unwrap() };
+            for target in self.filter_targets.drain(..) {
+                target.destroy(ctx);
+            }
+        }
+        while self.filter_targets.len() < count {
+            self.filter_targets.push(OffscreenTarget::new(
+                ctx,
+                extent,
+                self.msaa,
+                self.texture_layout,
+                self.sampler,
+            ));
+        }
+    }
+}
+
+impl Backend for VulkanBackend {
+    fn adapter_name(&self) -> String {
+        let properties = unsafe {
+            self.render_context
+                .instance
+                .get_physical_device_properties(self.render_context.physical_device)
+        };
+        let name = properties
+            .device_name_as_c_str()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        format!("{name} ({:?})", properties.device_type)
+    }
+
+    fn resize(&mut self, _size: PhysicalSize<u32>) {
+        // TODO: Recreate the swapchain once beuk can resize it
+    }
+
+    fn update_texture(&mut self, delta: &ImageDelta) {
+        self.texture.update(
+            &self.render_context,
+            self.texture_layout,
+            self.sampler,
+            delta,
+        );
+    }
+
+    fn begin_frame(&mut self, clear_color: Color32, damage: epaint::Rect) {
+        self.clear_color = clear_color;
+        self.vertices.clear();
+        self.indices.clear();
+        self.rects.clear();
+        self.draw_list.clear();
+        let window = self.window_rect();
+        // A new frame holds nothing of the last one, so all of it is drawn
+        let damage = if self.create_frame() { window } else { damage };
+        // Passes draw to whole pixels
+        self.damage =
+            epaint::Rect::from_min_max(damage.min.floor(), damage.max.ceil()).intersect(window);
+    }
+
+    fn draw_mesh(&mut self, mesh: &Mesh, transform: Affine, clip: epaint::Rect, opacity: f32) {
+        self.push_mesh(mesh, transform, clip, opacity, None, false);
+    }
+
+    fn draws_subpixel_text(&self) -> bool {
+        true
+    }
+
+    fn draw_subpixel_text(
+        &mut self,
+        mesh: &Mesh,
+        transform: Affine,
+        clip: epaint::Rect,
+        opacity: f32,
+    ) {
+        self.push_mesh(mesh, transform, clip, opacity, None, true);
+    }
+
+    fn draws_rects(&self) -> bool {
+        true
+    }
+
+    fn draw_rects(
+        &mut self,
+        rects: &[RectInstance],
+        transform: Affine,
+        clip: epaint::Rect,
+        opacity: f32,
+    ) {
+        let clip = clip.intersect(self.damage);
+        if !clip.is_positive() {
+            return;
+        }
+        let first_rect = self.rects.len() as u32;
+        self.rects
+            .extend(rects.iter().map(|rect| rect.with_opacity(opacity)));
+        self.draw_list.push(Draw {
+            primitives: Primitives::Rects {
+                first_rect,
+                rect_count: rects.len() as u32,
+            },
+            transform,
+            clip,
+        });
+    }
+
+    fn blur_backdrop(&mut self, area: epaint::Rect, radius: f32) {
+        // Passes draw to whole pixels
+        let area =
+            epaint::Rect::from_min_max(area.min.floor(), area.max.ceil()).intersect(self.damage);
+        if !area.is_positive() {
+            return;
+        }
+        self.draw_list.push(Draw {
+            primitives: Primitives::BackdropBlur { area, radius },
+            transform: Affine::IDENTITY,
+            clip: area,
+        });
+    }
+
+    fn draws_filters(&self) -> bool {
+        true
+    }
+
+    fn begin_filter(&mut self) {
+        self.draw_list.push(Draw {
+            primitives: Primitives::BeginFilter,
+            transform: Affine::IDENTITY,
+            clip: epaint::Rect::EVERYTHING,
+        });
+    }
+
+    fn end_filter(&mut self, matrix: &ColorMatrix, opacity: f32) {
+        self.draw_list.push(Draw {
+            primitives: Primitives::EndFilter(*matrix, opacity),
+            transform: Affine::IDENTITY,
+            clip: epaint::Rect::EVERYTHING,
+        });
+    }
+
+    fn caches_layers(&self) -> bool {
+        true
+    }
+
+    fn begin_cached_layer(&mut self, id: u64, [width, height]: [u32; 2]) {
+        let ctx = &self.render_context;
+        let extent = vk::Extent2D { width, height };
+        // The last frame may still sample the image of the layer, so it is destroyed once the next frame is recorded
+        if self
+            .cached_layers
+            .get(&id)
+            .is_some_and(|layer| layer.image.extent != extent)
+        {
+            let layer = self.cached_layers.remove(&id).unwrap();
+            self.retired_layers.push((self.frame_index, layer));
+        }
+        let (texture_layout, sampler) = (self.texture_layout, self.sampler);
+        self.cached_layers.entry(id).or_insert_with(|| {
+            let image = Attachment::new(
+                ctx,
+                extent,
+                ctx.render_swapchain.surface_format.format,
+                vk::SampleCountFlags::TYPE_1,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            );
+            SampledImage::new(ctx, image, texture_layout, sampler)
+        });
+        if self.msaa != Msaa::Off
+            && !self
+                .layer_msaa
+                .as_ref()
+                .is_some_and(|msaa| msaa.extent.width >= width && msaa.extent.height >= height)
+        {
+            let mut msaa_extent = extent;
+            if let Some(msaa) = self.layer_msaa.take() {
+                msaa_extent.width = msaa_extent.width.max(msaa.extent.width);
+                msaa_extent.height = msaa_extent.height.max(msaa.extent.height);
+                unsafe { ctx.device.device_wait_idle().unwrap() };
+                msaa.destroy(ctx);
+            }
+            self.layer_msaa = Some(Attachment::new(
+                ctx,
+                msaa_extent,
+                ctx.render_swapchain.surface_format.format,
+                self.msaa.sample_count(),
+                vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            ));
+        }
+        self.draw_list.push(Draw {
+            primitives: Primitives::BeginCachedLayer(id),
+            transform: Affine::IDENTITY,
+            clip: epaint::Rect::EVERYTHING,
+        });
+        // Everything in the layer is drawn
+        let damage = std::mem::replace(
+            &mut self.damage,
+            epaint::Rect::from_min_size(
+                epaint::Pos2::ZERO,
+                epaint::vec2(width as f32, height as f32),
+            ),
+        );
+        self.frame_damage = Some(damage);
+    }
+
+    fn end_cached_layer(&mut self) {
+        let Some(damage) = self.frame_damage.take() else {
+            return;
+        };
+        self.damage = damage;
+        self.draw_list.push(Draw {
+            primitives: Primitives::EndCachedLayer,
+            transform: Affine::IDENTITY,
+            clip: epaint::Rect::EVERYTHING,
+        });
+    }
+
+    fn draw_cached_layer(
+        &mut self,
+        id: u64,
+        mesh: &Mesh,
+        transform: Affine,
+        clip: epaint::Rect,
+        opacity: f32,
+    ) {
+        if self.cached_layers.contains_key(&id) {
+            self.push_mesh(mesh, transform, clip, opacity, Some(id), false);
+        }
+    }
+
+    fn release_cached_layer(&mut self, id: u64) {
+        if let Some(layer) = self.cached_layers.remove(&id) {
+            self.retired_layers.push((self.frame_index, layer));
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.read_timestamps();
+        self.destroy_retired();
+        // Frames aren't overlapped, so the buffers can be written again once the last frame was submitted
+        let vertex_buffer = self
+            .vertex_buffer
+            .write(&mut self.render_context, &self.vertices);
+        let index_buffer = self
+            .index_buffer
+            .write(&mut self.render_context, &self.indices);
+        let rect_buffer = self
+            .rect_buffer
+            .write(&mut self.render_context, &self.rects);
+        let texture_upload = self.texture.prepare_upload(&mut self.render_context);
+        let texture_set = self.texture.set();
+
+        let encode_srgb =
+            needs_srgb_encoding(self.render_context.render_swapchain.surface_format.format);
+        // The shader converts vertex colors to linear space, so the clear color needs to be linear too, unless the shader encodes them again
+        let clear_color = if encode_srgb {
+            self.clear_color
+                .to_array()
+                .map(|channel| channel as f32 / 255.0)
+        } else {
+            Rgba::from(self.clear_color).to_array()
+        };
+        let mut depth = 0;
+        let mut max_depth = 0;
+        for draw in &self.draw_list {
+            match draw.primitives {
+                Primitives::BeginFilter => {
+                    depth += 1;
+                    max_depth = max_depth.max(depth);
                 }
-                Primitive::Callback(_) => unreachable!(),
+                Primitives::EndFilter(..) => depth -= 1,
+                _ => {}
             }
         }
+        // Filtered layers are drawn inside of cached layers too, which can be larger than the swapchain
+        let extent = self
+            .draw_list
+            .iter()
+            .filter_map(|draw| match draw.primitives {
+                Primitives::BeginCachedLayer(id) => self.cached_layers.get(&id),
+                _ => None,
+            })
+            .fold(
+                self.render_context.render_swapchain.surface_resolution,
+                |extent, layer| vk::Extent2D {
+                    width: extent.width.max(layer.image.extent.width),
+                    height: extent.height.max(layer.image.extent.height),
+                },
+            );
+        self.create_filter_targets(max_depth, extent);
+        if self
+            .draw_list
+            .iter()
+            .any(|draw| matches!(draw.primitives, Primitives::BackdropBlur { .. }))
+        {
+            self.create_blur_target(extent);
+        }
+        // Captured frames are copied from the offscreen frame, because the swapchain images can't be copied from
+        let capture = std::mem::take(&mut self.capture_next).then(|| {
+            ReadbackBuffer::new(
+                &self.render_context,
+                self.render_context.render_swapchain.surface_resolution,
+            )
+        });
+        let damage = self.damage;
+        // Nothing of the last frame is kept if all of it is drawn again
+        let frame_load = if damage == self.window_rect() {
+            Load::Clear(clear_color)
+        } else {
+            Load::ClearArea(clear_color)
+        };
+        let area = pixel_rect(damage);
+        let Some(frame) = &self.frame else {
+            return;
+        };
+        let filter_targets = &self.filter_targets;
+        let blur_target = self.blur_target.as_ref();
+        let cached_layers = &self.cached_layers;
+        let layer_msaa = self.layer_msaa.as_ref();
+        let draw_list = &self.draw_list;
+        let mesh_pipeline = &self.pipeline;
+        let rect_pipeline = &self.rect_pipeline;
+        let composite_pipeline = &self.composite_pipeline;
+        let present_pipeline = &self.present_pipeline;
+        let layer_pipeline = &self.layer_pipeline;
+        let subpixel_pipelines = [&self.coverage_pipeline, &self.subpixel_pipeline];
+        let blur_pipeline = &self.blur_pipeline;
+        let backdrop_pipeline = &self.backdrop_pipeline;
+        let query_pool = self.timestamps.as_ref().map(|timestamps| timestamps.pool);
+        let render_context = &mut self.render_context;
 
         let present_index = render_context.acquire_present_index();
         render_context.present_record(
             present_index,
             |ctx, command_buffer, present_index: u32| unsafe {
-                let color_attachments = &[vk::RenderingAttachmentInfo::default()
-                    .image_view(ctx.render_swapchain.present_image_views[present_index as usize])
-                    .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-                    .load_op(vk::AttachmentLoadOp::CLEAR)
-                    .store_op(vk::AttachmentStoreOp::STORE)
-                    .clear_value(vk::ClearValue {
-                        color: vk::ClearColorValue {
-                            float32: [1.0, 1.0, 1.0, 1.0],
-                        },
-                    })];
-
-                ctx.begin_rendering(command_buffer, color_attachments, None);
-
-                let pipeline = ctx
-                    .pipeline_manager
-                    .get_graphics_pipeline(&self.pipeline_handle);
-                pipeline.bind(&ctx.device, command_buffer);
-                ctx.device.cmd_push_constants(
-                    command_buffer,
-                    pipeline.layout,
-                    vk::ShaderStageFlags::ALL_GRAPHICS,
-                    0,
-                    bytemuck::bytes_of(&PushConstants {
-                        screen_size: [
-                            ctx.render_swapchain.surface_resolution.width as f32,
-                            ctx.render_swapchain.surface_resolution.height as f32,
-                        ],
-                    }),
-                );
-
-                for (vertex_handle, index_handle, indices_len) in draw_list.iter() {
+                if let Some(pool) = query_pool {
+                    ctx.device.cmd_reset_query_pool(command_buffer, pool, 0, 2);
+                    ctx.device.cmd_write_timestamp(
+                        command_buffer,
+                        vk::PipelineStageFlags::TOP_OF_PIPE,
+                        pool,
+                        0,
+                    );
+                }
+                // Glyphs and images are copied to the texture before it is sampled
+                if let Some(upload) = &texture_upload {
+                    upload.record(ctx, command_buffer);
+                }
+                if !draw_list.is_empty() {
+                    // The vertices of meshes are bound to the first binding and rects to the second
                     ctx.device.cmd_bind_vertex_buffers(
                         command_buffer,
                         0,
-                        std::slice::from_ref(&ctx.buffer_manager.get_buffer(*vertex_handle).buffer),
-                        &[0],
+                        &[
+                            ctx.buffer_manager.get_buffer(vertex_buffer).buffer,
+                            ctx.buffer_manager.get_buffer(rect_buffer).buffer,
+                        ],
+                        &[0, 0],
                     );
                     ctx.device.cmd_bind_index_buffer(
                         command_buffer,
-                        ctx.buffer_manager.get_buffer(*index_handle).buffer,
+                        ctx.buffer_manager.get_buffer(index_buffer).buffer,
                         0,
                         vk::IndexType::UINT32,
                     );
-                    ctx.device
-                        .cmd_draw_indexed(command_buffer, *indices_len, 1, 0, 0, 1);
+                }
+                let extent = ctx.render_swapchain.surface_resolution;
+                let recorder = FrameRecorder {
+                    ctx,
+                    command_buffer,
+                    mesh_pipeline,
+                    rect_pipeline,
+                    composite_pipeline,
+                    present_pipeline,
+                    layer_pipeline,
+                    subpixel_pipelines,
+                    blur_pipeline,
+                    backdrop_pipeline,
+                    texture_set,
+                    cached_layers,
+                    encode_srgb,
+                };
+
+                // The frame is drawn in passes that end at each filter and cached layer, because a filtered layer is drawn over the layer below it once it is finished and a cached layer is drawn into an image of its own
+                let mut levels = vec![Level {
+                    target: frame.target(),
+                    extent,
+                    area,
+                    load: frame_load,
+                }];
+                // Whether a pass that draws to the last level is open
+                let mut open = false;
+                let mut filters = 0;
+                let mut start = 0;
+                loop {
+                    let end = draw_list[start..]
+                        .iter()
+                        .position(|draw| {
+                            !matches!(
+                                draw.primitives,
+                                Primitives::Mesh { .. } | Primitives::Rects { .. }
+                            )
+                        })
+                        .map_or(draw_list.len(), |control| start + control);
+                    let level = levels.last_mut().unwrap();
+                    if start < end && !open {
+                        open = recorder.begin(level);
+                    }
+                    if open {
+                        recorder.draw_range(&draw_list[start..end], level.extent);
+                    }
+                    if end == draw_list.len() {
+                        break;
+                    }
+                    if open {
+                        ctx.device.cmd_end_rendering(command_buffer);
+                        open = false;
+                    }
+                    match draw_list[end].primitives {
+                        Primitives::BackdropBlur { area, radius } => {
+                            // The blur reads what was drawn so far, so a level that was only cleared is cleared first
+                            if !matches!(level.load, Load::Keep) && recorder.begin(level) {
+                                ctx.device.cmd_end_rendering(command_buffer);
+                            }
+                            if let (Load::Keep, Some(blur_target)) = (level.load, blur_target) {
+                                recorder.blur(level, blur_target, pixel_rect(area), radius);
+                            }
+                        }
+                        Primitives::BeginFilter => {
+                            let mut layer = Level {
+                                target: filter_targets[filters].target(),
+                                extent: level.extent,
+                                area: level.area,
+                                load: Load::Clear([0.0; 4]),
+                            };
+                            filters += 1;
+                            open = recorder.begin(&mut layer);
+                            levels.push(layer);
+                        }
+                        Primitives::EndFilter(matrix, opacity) if filters > 0 => {
+                            filters -= 1;
+                            let layer = levels.pop().unwrap();
+                            // A layer outside of the damaged area was never drawn to
+                            if matches!(layer.load, Load::Keep) {
+                                layer
+                                    .target
+                                    .image
+                                    .image
+                                    .end_rendering(&ctx.device, command_buffer);
+                                open = recorder.begin(levels.last_mut().unwrap());
+                                if open {
+                                    recorder.composite(layer.target.image, &matrix, opacity);
+                                }
+                            }
+                        }
+                        Primitives::BeginCachedLayer(id) => {
+                            if let Some(image) = cached_layers.get(&id) {
+                                let extent = image.image.extent;
+                                let mut layer = Level {
+                                    target: RenderTarget {
+                                        image,
+                                        msaa: layer_msaa,
+                                    },
+                                    extent,
+                                    area: vk::Rect2D {
+                                        offset: vk::Offset2D { x: 0, y: 0 },
+                                        extent,
+                                    },
+                                    load: Load::Clear([0.0; 4]),
+                                };
+                                open = recorder.begin(&mut layer);
+                                levels.push(layer);
+                            }
+                        }
+                        Primitives::EndCachedLayer if levels.len() > 1 => {
+                            let layer = levels.pop().unwrap();
+                            layer
+                                .target
+                                .image
+                                .image
+                                .end_rendering(&ctx.device, command_buffer);
+                        }
+                        _ => {}
+                    }
+                    start = end + 1;
+                }
+                if open {
+                    ctx.device.cmd_end_rendering(command_buffer);
+                }
+                // The damaged area is cleared even if nothing is drawn to it
+                let frame_level = &mut levels[0];
+                if !matches!(frame_level.load, Load::Keep) && recorder.begin(frame_level) {
+                    ctx.device.cmd_end_rendering(command_buffer);
+                }
+                if matches!(frame_level.load, Load::Keep) {
+                    frame
+                        .resolved
+                        .image
+                        .end_rendering(&ctx.device, command_buffer);
+                }
+                if let Some(readback) = &capture {
+                    readback.record_copy(&ctx.device, command_buffer, &frame.resolved.image);
                 }
 
+                // The swapchain images don't keep what was presented, so the whole frame is drawn to them
+                let color_attachment = vk::RenderingAttachmentInfo::default()
+                    .image_view(ctx.render_swapchain.present_image_views[present_index as usize])
+                    .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                    .load_op(vk::AttachmentLoadOp::CLEAR)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .clear_value(vk::ClearValue {
+                        color: vk::ClearColorValue { float32: [0.0; 4] },
+                    });
+                ctx.begin_rendering(command_buffer, &[color_attachment], None);
+                set_viewport(
+                    &ctx.device,
+                    command_buffer,
+                    extent,
+                    vk::Rect2D {
+                        offset: vk::Offset2D { x: 0, y: 0 },
+                        extent,
+                    },
+                );
+                recorder.present(&frame.resolved);
                 ctx.end_rendering(command_buffer);
+                if let Some(pool) = query_pool {
+                    ctx.device.cmd_write_timestamp(
+                        command_buffer,
+                        vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                        pool,
+                        1,
+                    );
+                }
             },
         );
 
         render_context.present_submit(present_index);
+        self.frame_index += 1;
+        if let Some(readback) = capture {
+            let ctx = &self.render_context;
+            // Screenshots are rare, so the frame is waited for instead of read with the next one
+            unsafe { ctx.device.device_wait_idle().unwrap() };
+            let extent = ctx.render_swapchain.surface_resolution;
+            let bgra = matches!(
+                ctx.render_swapchain.surface_format.format,
+                vk::Format::B8G8R8A8_SRGB | vk::Format::B8G8R8A8_UNORM
+            );
+            self.captured = Some(image_from_texels(
+                [extent.width, extent.height],
+                extent.width as usize * 4,
+                &readback.read(ctx),
+                bgra,
+            ));
+            readback.destroy(ctx);
+        }
+        if let Some(timestamps) = &mut self.timestamps {
+            timestamps.written = true;
+        }
+    }
+
+    fn enable_gpu_timing(&mut self) {
+        if self.timestamps.is_some() {
+            return;
+        }
+        let ctx = &self.render_context;
+        let limits = unsafe {
+            ctx.instance
+                .get_physical_device_properties(ctx.physical_device)
+                .limits
+        };
+        // TODO: Check the timestamp bits of the queue family, some adapters only write timestamps on the compute queues
+        if limits.timestamp_compute_and_graphics == vk::FALSE {
+            log::warn!("the adapter can't write timestamps, the GPU time of frames isn't measured");
+            return;
+        }
+        let info = vk::QueryPoolCreateInfo::default()
+            .query_type(vk::QueryType::TIMESTAMP)
+            .query_count(2);
+        match unsafe { ctx.device.create_query_pool(&info, None) } {
+            Ok(pool) => {
+                self.timestamps = Some(Timestamps {
+                    pool,
+                    period: limits.timestamp_period,
+                    written: false,
+                    last: None,
+                })
+            }
+            Err(err) => log::warn!("failed to create the timestamp queries: {err}"),
+        }
+    }
+
+    fn gpu_time(&mut self) -> Option<Duration> {
+        self.read_timestamps();
+        self.timestamps.as_ref()?.last
+    }
 
-        for (vertex_handle, index_handle, _) in draw_list {
-            render_context.buffer_manager.remove_buffer(vertex_handle);
-            render_context.buffer_manager.remove_buffer(index_handle);
+    /// Frames are copied if the swapchain stores 8 bits per channel
+    fn captures_frames(&self) -> bool {
+        matches!(
+            self.render_context.render_swapchain.surface_format.format,
+            vk::Format::B8G8R8A8_SRGB
+                | vk::Format::B8G8R8A8_UNORM
+                | vk::Format::R8G8B8A8_SRGB
+                | vk::Format::R8G8B8A8_UNORM
+                | vk::Format::A8B8G8R8_SRGB_PACK32
+                | vk::Format::A8B8G8R8_UNORM_PACK32
+        )
+    }
+
+    fn capture_next_frame(&mut self) {
+        self.capture_next = true;
+    }
+
+    fn captured_frame(&mut self) -> Option<image::RgbaImage> {
+        self.captured.take()
+    }
+
+    /// The current pipeline is kept if the shaders fail to compile. Rects are drawn with their own shaders, which are not reloaded.
+    fn reload_shaders(&mut self, vertex_source: &str, fragment_source: &str) {
+        let ctx = &self.render_context;
+        match create_pipeline(
+            ctx,
+            self.msaa,
+            self.texture_layout,
+            vertex_source,
+            fragment_source,
+            Blend::Over,
+        ) {
+            Ok(pipeline) => {
+                // The last frame may still be drawn with the old pipeline, so it is destroyed once the next frame is recorded
+                let old = std::mem::replace(&mut self.pipeline, pipeline);
+                self.retired_pipelines.push((self.frame_index, old));
+                log::info!("reloaded the shaders");
+            }
+            Err(err) => {
+                log::error!("failed to compile the shaders, keeping the last pipeline:\n{err}")
+            }
+        }
+    }
+}
+
+/// The pixels of a rect of whole pixels in a target.
+fn pixel_rect(rect: epaint::Rect) -> vk::Rect2D {
+    vk::Rect2D {
+        offset: vk::Offset2D {
+            x: rect.min.x as i32,
+            y: rect.min.y as i32,
+        },
+        extent: vk::Extent2D {
+            width: rect.width() as u32,
+            height: rect.height() as u32,
+        },
+    }
+}
+
+/// True if the swapchain stores colors as they are written. The shaders output linear colors, which look too dark on these formats unless the shaders encode them as sRGB. Formats with sRGB in their name encode them when they are written, and float formats are linear.
+fn needs_srgb_encoding(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::B8G8R8A8_UNORM
+            | vk::Format::R8G8B8A8_UNORM
+            | vk::Format::A8B8G8R8_UNORM_PACK32
+            | vk::Format::A2B10G10R10_UNORM_PACK32
+            | vk::Format::A2R10G10B10_UNORM_PACK32
+    )
+}
+
+impl Drop for VulkanBackend {
+    fn drop(&mut self) {
+        let ctx = &self.render_context;
+        let device = &ctx.device;
+        unsafe {
+            // The last frame may still be drawn with the objects
+            let _ = device.device_wait_idle();
+            if let Some(timestamps) = self.timestamps.take() {
+                device.destroy_query_pool(timestamps.pool, None);
+            }
+            self.pipeline.destroy(device);
+            self.rect_pipeline.destroy(device);
+            self.composite_pipeline.destroy(device);
+            self.present_pipeline.destroy(device);
+            self.layer_pipeline.destroy(device);
+            self.coverage_pipeline.destroy(device);
+            self.subpixel_pipeline.destroy(device);
+            self.blur_pipeline.destroy(device);
+            self.backdrop_pipeline.destroy(device);
+            for (_, pipeline) in &self.retired_pipelines {
+                pipeline.destroy(device);
+            }
+            for layer in self.cached_layers.values() {
+                layer.destroy(ctx);
+            }
+            for (_, layer) in &self.retired_layers {
+                layer.destroy(ctx);
+            }
+            if let Some(msaa) = &self.layer_msaa {
+                msaa.destroy(ctx);
+            }
+            if let Some(frame) = &self.frame {
+                frame.destroy(ctx);
+            }
+            for target in &self.filter_targets {
+                target.destroy(ctx);
+            }
+            if let Some(blur_target) = &self.blur_target {
+                blur_target.destroy(ctx);
+            }
+            self.texture.destroy(ctx);
+            device.destroy_sampler(self.sampler, None);
+            device.destroy_descriptor_set_layout(self.texture_layout, None);
+        }
+        if let Some((loader, messenger)) = self.debug_messenger.take() {
+            unsafe { loader.destroy_debug_utils_messenger(messenger, None) };
+        }
+    }
+}
+
+/// Record the warnings and errors of the validation layers for diagnostics reports. Returns None if the instance wasn't created with VK_EXT_debug_utils.
+fn create_debug_messenger(
+    ctx: &RenderContext,
+) -> Option<(debug_utils::Instance, vk::DebugUtilsMessengerEXT)> {
+    let entry = unsafe { beuk::ash::Entry::load() }.ok()?;
+    // The functions of extensions that weren't enabled can't be loaded
+    unsafe {
+        entry.get_instance_proc_addr(
+            ctx.instance.handle(),
+            c"vkCreateDebugUtilsMessengerEXT".as_ptr(),
+        )
+    }?;
+    let loader = debug_utils::Instance::new(&entry, &ctx.instance);
+    let info = vk::DebugUtilsMessengerCreateInfoEXT::default()
+        .message_severity(
+            vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
+                | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
+        )
+        .message_type(
+            vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
+                | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
+                | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
+        )
+        .pfn_user_callback(Some(record_debug_message));
+    match unsafe { loader.create_debug_utils_messenger(&info, None) } {
+        Ok(messenger) => Some((loader, messenger)),
+        Err(err) => {
+            log::warn!("failed to create the debug messenger: {err}");
+            None
+        }
+    }
+}
+
+unsafe extern "system" fn record_debug_message(
+    severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    types: vk::DebugUtilsMessageTypeFlagsEXT,
+    data: *const vk::DebugUtilsMessengerCallbackDataEXT<'_>,
+    _user_data: *mut c_void,
+) -> vk::Bool32 {
+    let message = data
+        .as_ref()
+        .and_then(|data| data.message_as_c_str())
+        .map(|message| message.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    crate::report::record_validation_message(format!("{severity:?} {types:?}: {message}"));
+    // Returning true would abort the call that caused the message
+    vk::FALSE
+}
+
+/// Compile the shaders and build the graphics pipeline that draws the meshes of a scene. The texture is bound to the first descriptor set.
+fn create_pipeline(
+    ctx: &RenderContext,
+    msaa: Msaa,
+    texture_layout: vk::DescriptorSetLayout,
+    vertex_source: &str,
+    fragment_source: &str,
+    blend: Blend,
+) -> Result<Pipeline, String> {
+    Pipeline::new(
+        ctx,
+        &PipelineDescriptor {
+            vertex_source,
+            fragment_source,
+            vertex_input: PipelineVertexInputStateCreateInfo::default()
+                .vertex_attribute_descriptions(&[
+                    vk::VertexInputAttributeDescription {
+                        location: 0,
+                        binding: 0,
+                        format: vk::Format::R32G32_SFLOAT,
+                        offset: bytemuck::offset_of!(epaint::Vertex, pos) as u32,
+                    },
+                    vk::VertexInputAttributeDescription {
+                        location: 1,
+                        binding: 0,
+                        format: vk::Format::R32G32_SFLOAT,
+                        offset: bytemuck::offset_of!(epaint::Vertex, uv) as u32,
+                    },
+                    vk::VertexInputAttributeDescription {
+                        location: 2,
+                        binding: 0,
+                        format: vk::Format::R8G8B8A8_UNORM,
+                        offset: bytemuck::offset_of!(epaint::Vertex, color) as u32,
+                    },
+                ])
+                .vertex_binding_descriptions(&[vk::VertexInputBindingDescription {
+                    binding: 0,
+                    stride: size_of::<epaint::Vertex>() as u32,
+                    input_rate: vk::VertexInputRate::VERTEX,
+                }]),
+            set_layouts: &[texture_layout],
+            push_constants_size: size_of::<DrawConstants>() as u32,
+            samples: msaa.sample_count(),
+            blend,
+        },
+    )
+}
+
+/// Build the pipeline that draws rects as instanced quads. Its vertex input is the second vertex buffer binding, so the buffers of a frame are bound once for both pipelines.
+fn create_rect_pipeline(ctx: &RenderContext, msaa: Msaa) -> Result<Pipeline, String> {
+    let attribute =
+        |location: u32, format: vk::Format, offset: usize| vk::VertexInputAttributeDescription {
+            location,
+            binding: 1,
+            format,
+            offset: offset as u32,
+        };
+    Pipeline::new(
+        ctx,
+        &PipelineDescriptor {
+            vertex_source: include_str!("./rect.vert"),
+            fragment_source: include_str!("./rect.frag"),
+            vertex_input: PipelineVertexInputStateCreateInfo::default()
+                .vertex_attribute_descriptions(&[
+                    attribute(
+                        0,
+                        vk::Format::R32G32B32A32_SFLOAT,
+                        bytemuck::offset_of!(RectInstance, rect),
+                    ),
+                    attribute(
+                        1,
+                        vk::Format::R32G32B32A32_SFLOAT,
+                        bytemuck::offset_of!(RectInstance, radii),
+                    ),
+                    attribute(
+                        2,
+                        vk::Format::R8G8B8A8_UNORM,
+                        bytemuck::offset_of!(RectInstance, fill),
+                    ),
+                    attribute(
+                        3,
+                        vk::Format::R8G8B8A8_UNORM,
+                        bytemuck::offset_of!(RectInstance, border_color),
+                    ),
+                    attribute(
+                        4,
+                        vk::Format::R32_SFLOAT,
+                        bytemuck::offset_of!(RectInstance, border_width),
+                    ),
+                ])
+                .vertex_binding_descriptions(&[vk::VertexInputBindingDescription {
+                    binding: 1,
+                    stride: size_of::<RectInstance>() as u32,
+                    input_rate: vk::VertexInputRate::INSTANCE,
+                }]),
+            set_layouts: &[],
+            push_constants_size: size_of::<DrawConstants>() as u32,
+            samples: msaa.sample_count(),
+            blend: Blend::Over,
+        },
+    )
+}
+
+/// Build the pipeline that draws a filtered or translucent layer over its whole target. The layer is bound to the first descriptor set like the texture of meshes.
+fn create_composite_pipeline(
+    ctx: &RenderContext,
+    msaa: Msaa,
+    texture_layout: vk::DescriptorSetLayout,
+) -> Result<Pipeline, String> {
+    Pipeline::new(
+        ctx,
+        &PipelineDescriptor {
+            vertex_source: include_str!("./composite.vert"),
+            fragment_source: include_str!("./composite.frag"),
+            vertex_input: PipelineVertexInputStateCreateInfo::default(),
+            set_layouts: &[texture_layout],
+            push_constants_size: size_of::<CompositeConstants>() as u32,
+            samples: msaa.sample_count(),
+            blend: Blend::Over,
+        },
+    )
+}
+
+/// Build a pipeline that blurs an area of a sampled image along one direction, see blur.frag. The blurred pixels replace what was drawn before.
+fn create_blur_pipeline(
+    ctx: &RenderContext,
+    msaa: Msaa,
+    texture_layout: vk::DescriptorSetLayout,
+) -> Result<Pipeline, String> {
+    Pipeline::new(
+        ctx,
+        &PipelineDescriptor {
+            vertex_source: include_str!("./composite.vert"),
+            fragment_source: include_str!("./blur.frag"),
+            vertex_input: PipelineVertexInputStateCreateInfo::default(),
+            set_layouts: &[texture_layout],
+            push_constants_size: size_of::<BlurConstants>() as u32,
+            samples: msaa.sample_count(),
+            blend: Blend::Replace,
+        },
+    )
+}
+
+/// Split a mesh into meshes that don't use indices above the largest index the adapter can draw. Adapters only have to support indices up to 2^24 - 1.
+fn split_mesh(mesh: &Mesh, max_index: u32) -> Vec<Cow<'_, Mesh>> {
+    if mesh.vertices.len() <= max_index as usize + 1 {
+        return vec![Cow::Borrowed(mesh)];
+    }
+    // Tessellated shapes only reference the vertices near them, so the triangles are grouped into ranges of vertices
+    let mut meshes = Vec::new();
+    let mut start = 0;
+    while start < mesh.indices.len() {
+        let mut min = u32::MAX;
+        let mut max = 0;
+        let mut end = start;
+        for triangle in mesh.indices[start..].chunks_exact(3) {
+            let triangle_min = min.min(triangle[0].min(triangle[1]).min(triangle[2]));
+            let triangle_max = max.max(triangle[0].max(triangle[1]).max(triangle[2]));
+            if triangle_max - triangle_min > max_index {
+                break;
+            }
+            (min, max) = (triangle_min, triangle_max);
+            end += 3;
+        }
+        // A triangle that spans too many vertices can't be drawn
+        if end == start {
+            log::warn!("skipping a triangle that uses indices too far apart to draw");
+            start += 3;
+            continue;
         }
+        meshes.push(Cow::Owned(Mesh {
+            indices: mesh.indices[start..end]
+                .iter()
+                .map(|index| index - min)
+                .collect(),
+            vertices: mesh.vertices[min as usize..=max as usize].to_vec(),
+            texture_id: mesh.texture_id,
+        }));
+        start = end;
     }
+    meshes
 }
@@ -0,0 +1,307 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io;
+use std::panic::UnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, TryLockError, TryLockResult};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use dioxus_native_core::prelude::*;
+use taffy::Taffy;
+use tao::dpi::PhysicalSize;
+
+use crate::focus::Focused;
+use crate::layout::TaffyLayout;
+use crate::raster::rasterize;
+use crate::scene::Scene;
+use crate::stable_id::stable_id;
+use crate::style::StyleDiagnostics;
+
+/// The number of validation messages that are kept for reports. Older messages are dropped
+const MAX_VALIDATION_MESSAGES: usize = 256;
+
+/// The last validation and error messages of the graphics backend
+static VALIDATION_MESSAGES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+thread_local! {
+    /// Set while a panic is caught with [`catch_panic`], so the panic hook doesn't write a report for it
+    static CATCHING_PANIC: Cell<bool> = Cell::new(false);
+}
+
+/// Keep a validation or error message of the graphics backend for the next report.
+pub(crate) fn record_validation_message(message: String) {
+    let mut messages = VALIDATION_MESSAGES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if messages.len() == MAX_VALIDATION_MESSAGES {
+        messages.pop_front();
+    }
+    messages.push_back(message);
+}
+
+/// Run a function that may panic, like creating a GPU backend where there is no adapter. The panic is expected, so no report is written for it.
+pub(crate) fn catch_panic<T>(f: impl FnOnce() -> T + UnwindSafe) -> std::thread::Result<T> {
+    // Nested calls keep the flag of the outer call
+    let was_catching = CATCHING_PANIC.with(|catching| catching.replace(true));
+    let result = std::panic::catch_unwind(f);
+    CATCHING_PANIC.with(|catching| catching.set(was_catching));
+    result
+}
+
+/// The information collected into a diagnostics report. Anything that isn't available when the report is written is left out.
+pub(crate) struct Report<'a> {
+    /// Why the report was written
+    pub reason: &'a str,
+    /// The name of the graphics adapter
+    pub adapter: Option<&'a str>,
+    /// The size of the frame in physical pixels
+    pub size: PhysicalSize<u32>,
+    pub scale_factor: f32,
+    /// The last frame that was drawn
+    pub scene: Option<&'a Scene>,
+    pub dom: Option<(&'a RealDom, &'a Taffy)>,
+    pub style_diagnostics: &'a StyleDiagnostics,
+}
+
+impl Report<'_> {
+    /// Write the report into a new directory inside of `dir` and return the path to it.
+    ///
+    /// The report contains:
+    /// - `report.txt`: the reason for the report, the version of Blitz and the graphics adapter
+    /// - `validation.txt`: the last validation and error messages of the graphics backend
+    /// - `display_list.txt`: the layers and shapes of the last frame
+    /// - `screenshot.png`: the last frame drawn on the CPU
+    /// - `tree.txt`: the styles and layout of the focused subtree, or the whole document if nothing is focused
+    /// - `style_diagnostics.txt`: every style diagnostic reported so far
+    pub fn write(&self, dir: &Path) -> io::Result<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or_default();
+        let dir = dir.join(format!("blitz-report-{timestamp}"));
+        std::fs::create_dir_all(&dir)?;
+
+        let mut summary = String::new();
+        writeln!(summary, "reason: {}", self.reason).unwrap();
+        writeln!(summary, "blitz-core: {}", env!("CARGO_PKG_VERSION")).unwrap();
+        writeln!(summary, "os: {}", std::env::consts::OS).unwrap();
+        writeln!(
+            summary,
+            "adapter: {}",
+            self.adapter.unwrap_or("unavailable")
+        )
+        .unwrap();
+        writeln!(summary, "size: {}x{}", self.size.width, self.size.height).unwrap();
+        // The panic may have happened while a message was recorded, so don't block on the lock
+        let validation = try_guard(VALIDATION_MESSAGES.try_lock())
+            .map(|messages| messages.iter().cloned().collect::<Vec<_>>());
+        match &validation {
+            Some(messages) => writeln!(summary, "validation messages: {}", messages.len()),
+            None => writeln!(summary, "validation messages: unavailable"),
+        }
+        .unwrap();
+        std::fs::write(dir.join("report.txt"), summary)?;
+        if let Some(messages) = validation {
+            let mut output = String::new();
+            for message in messages {
+                writeln!(output, "{message}").unwrap();
+            }
+            std::fs::write(dir.join("validation.txt"), output)?;
+        }
+
+        if let Some(scene) = self.scene {
+            std::fs::write(dir.join("display_list.txt"), display_list(scene))?;
+            if self.size.width > 0 && self.size.height > 0 {
+                rasterize(scene, self.size.width, self.size.height, self.scale_factor)
+                    .save(dir.join("screenshot.png"))
+                    .map_err(io::Error::other)?;
+            }
+        }
+
+        if let Some((rdom, taffy)) = self.dom {
+            let focused = rdom
+                .get(rdom.root_id())
+                .and_then(find_focused)
+                .unwrap_or_else(|| rdom.root_id());
+            let mut tree = String::new();
+            dump_node(&mut tree, rdom.get(focused).unwrap(), taffy, 0);
+            std::fs::write(dir.join("tree.txt"), tree)?;
+        }
+
+        let mut diagnostics = String::new();
+        for diagnostic in self.style_diagnostics.all() {
+            writeln!(diagnostics, "{diagnostic}").unwrap();
+        }
+        std::fs::write(dir.join("style_diagnostics.txt"), diagnostics)?;
+
+        Ok(dir)
+    }
+}
+
+fn display_list(scene: &Scene) -> String {
+    let mut output = String::new();
+    writeln!(output, "clear color: {:?}", scene.clear_color).unwrap();
+    writeln!(output, "feathering: {}", scene.feathering).unwrap();
+    for (index, group) in scene.groups.iter().enumerate() {
+        writeln!(
+            output,
+            "group {index}: opacity {}, parent {:?}",
+            group.opacity, group.parent
+        )
+        .unwrap();
+    }
+    for (index, layer) in scene.window_layers().iter().enumerate() {
+        writeln!(
+            output,
+            "layer {index}: transform {:?}, clip {:?}, groups {:?}",
+            layer.transform.as_coeffs(),
+            layer.clip,
+            scene.group_chain(layer.group)
+        )
+        .unwrap();
+        for shape in &layer.shapes {
+            match shape {
+                // Meshes can have thousands of vertices, which makes the list hard to read
+                epaint::Shape::Mesh(mesh) => writeln!(
+                    output,
+                    "  Mesh {{ vertices: {}, bounds: {:?} }}",
+                    mesh.vertices.len(),
+                    mesh.calc_bounds()
+                ),
+                shape => writeln!(output, "  {shape:?}"),
+            }
+            .unwrap();
+        }
+    }
+    output
+}
+
+fn find_focused(node: NodeRef) -> Option<NodeId> {
+    if node.get::<Focused>().filter(|focused| focused.0).is_some() {
+        return Some(node.id());
+    }
+    node.children().into_iter().find_map(find_focused)
+}
+
+fn dump_node(output: &mut String, node: NodeRef, taffy: &Taffy, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let layout = node
+        .get::<TaffyLayout>()
+        .and_then(|layout| layout.node)
+        .and_then(|node| taffy.layout(node).ok())
+        .map(|layout| {
+            format!(
+                "x: {}, y: {}, width: {}, height: {}",
+                layout.location.x, layout.location.y, layout.size.width, layout.size.height
+            )
+        })
+        .unwrap_or_else(|| "no layout".to_string());
+    match &*node.node_type() {
+        NodeType::Element(element) => {
+            let stable_id = stable_id(node.real_dom(), node.id()).unwrap_or_default();
+            writeln!(
+                output,
+                "{indent}<{}> {:?} {stable_id} ({layout})",
+                element.tag,
+                node.id()
+            )
+            .unwrap();
+            let mut attributes: Vec<_> = element
+                .attributes
+                .iter()
+                .map(|(attribute, value)| {
+                    let name = match &attribute.namespace {
+                        Some(namespace) => format!("{namespace}:{}", attribute.name),
+                        None => attribute.name.clone(),
+                    };
+                    let value = match value.as_text() {
+                        Some(text) => text.to_string(),
+                        None => format!("{value:?}"),
+                    };
+                    (name, value)
+                })
+                .collect();
+            attributes.sort();
+            for (name, value) in attributes {
+                writeln!(output, "{indent}  {name} = {value}").unwrap();
+            }
+        }
+        NodeType::Text(text) => {
+            writeln!(output, "{indent}{:?} ({layout})", text.text).unwrap();
+        }
+        NodeType::Placeholder => {
+            writeln!(output, "{indent}placeholder").unwrap();
+        }
+    }
+    for child in node.children() {
+        dump_node(output, child, taffy, depth + 1);
+    }
+}
+
+/// Writes diagnostics reports for a running application when it panics or when the user asks for one.
+pub(crate) struct Reporter {
+    pub dir: PathBuf,
+    pub adapter: String,
+    pub rdom: Arc<RwLock<RealDom>>,
+    pub taffy: Arc<Mutex<Taffy>>,
+    pub size: Arc<Mutex<PhysicalSize<u32>>>,
+    pub style_diagnostics: Arc<StyleDiagnostics>,
+    /// The last frame that was drawn
+    pub last_frame: Mutex<Scene>,
+}
+
+impl Reporter {
+    /// Write a report whenever a thread panics, before the previous panic hook runs. Panics caught with [`catch_panic`] are expected and don't write a report.
+    pub fn install_panic_hook(self: &Arc<Self>) {
+        let reporter = self.clone();
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !CATCHING_PANIC.with(Cell::get) {
+                match reporter.write(&format!("panic: {info}")) {
+                    Ok(path) => eprintln!("wrote a diagnostics report to {}", path.display()),
+                    Err(err) => eprintln!("failed to write a diagnostics report: {err}"),
+                }
+            }
+            previous(info);
+        }));
+    }
+
+    /// Keep the frame that was just drawn for the next report. The scene is swapped with the previous frame, so nothing is copied.
+    pub fn swap_frame(&self, scene: &mut Scene) {
+        let mut last_frame = self
+            .last_frame
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        std::mem::swap(&mut *last_frame, scene);
+    }
+
+    pub fn write(&self, reason: &str) -> io::Result<PathBuf> {
+        // The thread that panicked may be holding one of these locks, so never block on them
+        let last_frame = try_guard(self.last_frame.try_lock());
+        let rdom = try_guard(self.rdom.try_read());
+        let taffy = try_guard(self.taffy.try_lock());
+        let size = try_guard(self.size.try_lock())
+            .map(|size| *size)
+            .unwrap_or(PhysicalSize::new(0, 0));
+        Report {
+            reason,
+            adapter: Some(self.adapter.as_str()),
+            size,
+            scale_factor: 1.0,
+            scene: last_frame.as_deref(),
+            dom: rdom.as_deref().zip(taffy.as_deref()),
+            style_diagnostics: &self.style_diagnostics,
+        }
+        .write(&self.dir)
+    }
+}
+
+/// Get the guard of a lock unless it is held somewhere else. A poisoned lock is still readable.
+fn try_guard<G>(result: TryLockResult<G>) -> Option<G> {
+    match result {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
@@ -0,0 +1,104 @@
+use dioxus_native_core::prelude::*;
+use epaint::{Color32, Rect, Shape, Stroke};
+use peniko::kurbo::{Affine, Point};
+use taffy::prelude::Size;
+use taffy::Taffy;
+
+use crate::coordinates::element_transform;
+use crate::layout::{set_resized, TaffyLayout};
+use crate::style::{Overflow, Resize};
+
+/// The size of the square in the bottom right corner of a resizable element that resizes it when it is dragged
+const GRIP_SIZE: f32 = 12.0;
+const GRIP_COLOR: Color32 = Color32::from_gray(128);
+
+/// The axes an element can be resized along with `resize`, or `None` if it can't be. Only elements that clip their content can be resized, like in a browser.
+pub(crate) fn resizable(node: NodeRef) -> Option<Resize> {
+    let resize = *node.get::<Resize>()?;
+    let overflow = node.get::<Overflow>()?;
+    (resize != Resize::None && (overflow.clips_x() || overflow.clips_y())).then_some(resize)
+}
+
+/// The diagonal lines of the grip of a resizable element, in the bottom right corner of its border box.
+pub(crate) fn grip_shapes(border_box: Rect) -> Vec<Shape> {
+    let corner = border_box.max - epaint::Vec2::splat(2.0);
+    [0.8, 0.45]
+        .into_iter()
+        .map(|length| {
+            let length = GRIP_SIZE * length;
+            Shape::line_segment(
+                [
+                    corner - epaint::vec2(length, 0.0),
+                    corner - epaint::vec2(0.0, length),
+                ],
+                Stroke::new(1.0, GRIP_COLOR),
+            )
+        })
+        .collect()
+}
+
+/// The mouse dragging the grip of a resizable element. The size the element is resized to is kept in its layout, see [`TaffyLayout::resized`].
+pub(crate) struct ResizeDrag {
+    element: NodeId,
+    resize: Resize,
+    /// Maps client coordinates to the coordinates of the element when the drag started
+    inverse: Affine,
+    /// Where the drag started in the coordinates of the element
+    start: Point,
+    start_size: Size<f32>,
+}
+
+impl ResizeDrag {
+    /// Start resizing the element whose grip is under a point in client coordinates, if there is one. Elements later in the document are drawn over earlier ones, so their grips are found first.
+    pub fn start(rdom: &RealDom, taffy: &Taffy, point: Point) -> Option<Self> {
+        let mut found = None;
+        let mut stack = vec![rdom.get(rdom.root_id())?];
+        while let Some(node) = stack.pop() {
+            if let Some(resize) = resizable(node) {
+                if let Some(drag) = Self::on_grip(rdom, taffy, node, resize, point) {
+                    found = Some(drag);
+                }
+            }
+            stack.extend(node.children().into_iter().rev());
+        }
+        found
+    }
+
+    fn on_grip(
+        rdom: &RealDom,
+        taffy: &Taffy,
+        node: NodeRef,
+        resize: Resize,
+        point: Point,
+    ) -> Option<Self> {
+        let layout = taffy.layout(node.get::<TaffyLayout>()?.node?).ok()?;
+        let inverse = element_transform(rdom, taffy, node.id())?.inverse();
+        let start = inverse * point;
+        let size = layout.size;
+        let on_grip = (size.width - GRIP_SIZE..=size.width).contains(&(start.x as f32))
+            && (size.height - GRIP_SIZE..=size.height).contains(&(start.y as f32));
+        on_grip.then_some(Self {
+            element: node.id(),
+            resize,
+            inverse,
+            start,
+            start_size: size,
+        })
+    }
+
+    /// Resize the element to follow the mouse at a point in client coordinates. The element is never smaller than its grip.
+    pub fn update(&self, rdom: &mut RealDom, taffy: &mut Taffy, point: Point) {
+        let delta = self.inverse * point - self.start;
+        let size = Size {
+            width: self
+                .resize
+                .resizes_x()
+                .then(|| (self.start_size.width + delta.x as f32).max(GRIP_SIZE)),
+            height: self
+                .resize
+                .resizes_y()
+                .then(|| (self.start_size.height + delta.y as f32).max(GRIP_SIZE)),
+        };
+        set_resized(rdom, taffy, self.element, size);
+    }
+}
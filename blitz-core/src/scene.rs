@@ -0,0 +1,889 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::NodeId;
+use epaint::{Color32, Mesh, Pos2, Rect, Shape, TessellationOptions, Tessellator, Vertex};
+use peniko::kurbo::Affine;
+use rustc_hash::FxHashMap;
+
+use crate::application::DirtyNodes;
+use crate::backend::{ColorMatrix, RectInstance};
+use crate::stats::{self, Counter};
+
+/// The shapes that make up a frame. This is independent of the backend used to draw it.
+pub(crate) struct Scene {
+    /// Shapes grouped by the transform and clip they are drawn with, in paint order
+    pub layers: Vec<Layer>,
+    /// Subtrees that are composited together with an opacity. The layers of a group are contiguous
+    pub groups: Vec<Group>,
+    /// The transform applied to shapes as they are pushed
+    pub transform: Affine,
+    /// The group shapes are added to as they are pushed
+    pub group: Option<usize>,
+    /// The outlines shapes are clipped to as they are pushed, from the outermost to the innermost
+    pub clip_paths: Vec<ClipPath>,
+    /// The color the frame is cleared to before anything is drawn
+    pub clear_color: Color32,
+    /// The width in physical pixels of the strip that fades out along the edges of shapes to smooth them. 0 disables feathering
+    pub feathering: f32,
+    /// True if the backend draws text with subpixel anti-aliasing, see [`crate::Config::with_subpixel_text`]
+    pub subpixel_text: bool,
+    /// The area of the window painted by each element and its children
+    pub node_bounds: FxHashMap<NodeId, Rect>,
+    /// The area of the window painted since [`Scene::start_node`] was last called
+    painted: Rect,
+    /// Set by [`Scene::mark`], so the next shape starts a new layer
+    split: bool,
+    /// The content of elements that is pushed in coordinates of its own, by element
+    pub contents: FxHashMap<NodeId, Content>,
+    /// The element whose content shapes are pushed into, see [`Scene::begin_content`]
+    content: Option<NodeId>,
+}
+
+/// Shapes that share a transform and clip rect.
+///
+/// The transform is applied when the layer is drawn instead of being baked into the vertices, so a layer that only moves doesn't need to be tessellated again.
+#[derive(Clone, PartialEq)]
+pub(crate) struct Layer {
+    pub transform: Affine,
+    /// The rect the layer is clipped to in window coordinates
+    pub clip: Rect,
+    /// The innermost group the layer belongs to
+    pub group: Option<usize>,
+    /// The outlines the layer is clipped to on top of the clip rect
+    pub clip_paths: Vec<ClipPath>,
+    pub shapes: Vec<Shape>,
+    /// Blurred before the shapes are drawn, see [`Scene::push_backdrop_blur`]
+    pub backdrop: Option<BackdropBlur>,
+    /// The element whose content the layer is part of. The transform, clip, clip paths and backdrop of the layer are in the coordinates of the content then
+    pub content: Option<NodeId>,
+    /// The shapes are text meshes with the coverage of each color channel in their texels, see [`Scene::push_subpixel_text`]
+    pub subpixel_text: bool,
+}
+
+/// The children of an element that will move or scroll without changing, like an element with `will-change` or a scroll container with content that overflows it. Their layers are pushed in coordinates of their own, so they stay the same while the element moves and their meshes are reused. Backends that cache layers draw them into a texture when they change and only composite the texture in the other frames, see [`crate::Backend::caches_layers`].
+///
+/// The coordinates of the content are scaled like the window, so a texture with a pixel per unit is as sharp as the window.
+pub(crate) struct Content {
+    /// Maps the content to the window
+    pub transform: Affine,
+    /// The rect the content is clipped to in window coordinates
+    pub clip: Rect,
+    /// The group the content is composited in
+    pub group: Option<usize>,
+    /// The outlines the content is clipped to in window coordinates
+    pub clip_paths: Vec<ClipPath>,
+    /// The area painted by the content in its own coordinates
+    pub area: Rect,
+}
+
+/// A blur of what was drawn before a layer in an area of the window, for `backdrop-filter`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct BackdropBlur {
+    /// The area in window coordinates. Pixels outside of it aren't sampled, so the edges don't pick up what is drawn around the element
+    pub area: Rect,
+    /// The standard deviation of the blur in window coordinates
+    pub radius: f32,
+}
+
+/// A convex outline that shapes are clipped to, like the rounded padding box of an element that hides its overflow. Meshes are clipped to it on the CPU when they are tessellated, without smoothing the edges.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct ClipPath {
+    /// The transform the outline is drawn with
+    pub transform: Affine,
+    pub outline: Arc<[Pos2]>,
+}
+
+/// Layers that are drawn on their own and then composited onto their parent with an opacity and a filter.
+pub(crate) struct Group {
+    pub opacity: f32,
+    pub filter: ColorMatrix,
+    pub parent: Option<usize>,
+}
+
+impl Content {
+    /// A layer of the content with its coordinates mapped to the window
+    pub fn to_window(&self, layer: &Layer) -> Layer {
+        let scale = self.transform.determinant().abs().sqrt() as f32;
+        let clip_paths = layer.clip_paths.iter().map(|clip_path| ClipPath {
+            transform: self.transform * clip_path.transform,
+            outline: clip_path.outline.clone(),
+        });
+        Layer {
+            transform: self.transform * layer.transform,
+            clip: self.clip_to_window(layer.clip),
+            group: layer.group,
+            clip_paths: self.clip_paths.iter().cloned().chain(clip_paths).collect(),
+            shapes: layer.shapes.clone(),
+            backdrop: layer.backdrop.map(|backdrop| BackdropBlur {
+                area: self.clip_to_window(backdrop.area),
+                radius: backdrop.radius * scale,
+            }),
+            content: None,
+            subpixel_text: layer.subpixel_text,
+        }
+    }
+
+    /// A rect in the coordinates of the content as a clip rect in window coordinates. Nothing is painted outside of the area of the content, so a clip that is unbounded in the content is bounded by it
+    pub fn clip_to_window(&self, clip: Rect) -> Rect {
+        let clip = clip.intersect(self.area);
+        if !clip.is_positive() {
+            return Rect::NOTHING;
+        }
+        transform_rect(self.transform, clip).intersect(self.clip)
+    }
+}
+
+/// A layer turned into triangles.
+pub(crate) struct TessellatedLayer {
+    pub transform: Affine,
+    pub clip: Rect,
+    pub group: Option<usize>,
+    pub mesh: Mesh,
+    pub backdrop: Option<BackdropBlur>,
+}
+
+/// A layer turned into the primitives a backend draws, in paint order.
+pub(crate) struct BatchedLayer {
+    /// The index of the layer in the scene. Content that is drawn from a texture has the index of its first layer
+    pub index: usize,
+    pub transform: Affine,
+    pub clip: Rect,
+    pub group: Option<usize>,
+    pub batches: Vec<Batch>,
+    pub backdrop: Option<BackdropBlur>,
+}
+
+/// Shapes that are drawn with one call to the backend
+#[derive(Clone)]
+pub(crate) enum Batch {
+    Mesh(Mesh),
+    Rects(Vec<RectInstance>),
+    /// A mesh that samples the texture of a cached layer, see [`crate::Backend::draw_cached_layer`]
+    CachedLayer(u64, Mesh),
+    /// Text with subpixel glyphs, see [`crate::Backend::draw_subpixel_text`]
+    SubpixelText(Mesh),
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            layers: Vec::new(),
+            groups: Vec::new(),
+            transform: Affine::IDENTITY,
+            group: None,
+            clip_paths: Vec::new(),
+            clear_color: Color32::WHITE,
+            feathering: 1.0,
+            subpixel_text: false,
+            node_bounds: FxHashMap::default(),
+            painted: Rect::NOTHING,
+            split: false,
+            contents: FxHashMap::default(),
+            content: None,
+        }
+    }
+}
+
+impl Scene {
+    pub fn clear(&mut self) {
+        self.layers.clear();
+        self.groups.clear();
+        self.transform = Affine::IDENTITY;
+        self.group = None;
+        self.clip_paths.clear();
+        self.node_bounds.clear();
+        self.painted = Rect::NOTHING;
+        self.split = false;
+        self.contents.clear();
+        self.content = None;
+    }
+
+    /// Start collecting the area painted by an element and its children. Returns the area painted before, which is passed to [`Scene::end_node`].
+    pub fn start_node(&mut self) -> Rect {
+        std::mem::replace(&mut self.painted, Rect::NOTHING)
+    }
+
+    /// Record the area painted by an element and its children since the matching [`Scene::start_node`].
+    pub fn end_node(&mut self, node: NodeId, painted_before: Rect) {
+        self.node_bounds.insert(node, self.painted);
+        self.painted = self.painted.union(painted_before);
+    }
+
+    /// The index of the next layer. The next shape starts a new layer, so the layers pushed from here on can be moved with [`Scene::stack`].
+    pub fn mark(&mut self) -> usize {
+        self.split = true;
+        self.layers.len()
+    }
+
+    /// Move the layers pushed since `start` into the paint order of a stacking context. `layered` are the ranges of layers painted by the elements of the stacking context with a z-index, which are moved behind the other layers if their z-index is negative and over them otherwise. Elements with the same z-index keep their order.
+    pub fn stack(&mut self, start: usize, mut layered: Vec<(i32, Range<usize>)>) {
+        if layered.is_empty() {
+            return;
+        }
+        layered.sort_by_key(|(z_index, _)| *z_index);
+        let mut layers: Vec<Option<Layer>> = self.layers.drain(start..).map(Some).collect();
+        let mut take = |range: Range<usize>| {
+            range
+                .filter_map(|index| layers[index - start].take())
+                .collect::<Vec<_>>()
+        };
+        let (behind, over): (Vec<_>, Vec<_>) =
+            layered.into_iter().partition(|(z_index, _)| *z_index < 0);
+        let behind: Vec<Layer> = behind
+            .into_iter()
+            .flat_map(|(_, range)| take(range))
+            .collect();
+        let over: Vec<Layer> = over
+            .into_iter()
+            .flat_map(|(_, range)| take(range))
+            .collect();
+        self.layers.extend(behind);
+        // The layers that are left are painted in document order
+        self.layers.extend(layers.into_iter().flatten());
+        self.layers.extend(over);
+        self.split = true;
+    }
+
+    /// Start a group. Shapes pushed until the matching [`Scene::pop_group`] are composited together with the opacity and filter.
+    pub fn push_group(&mut self, opacity: f32, filter: ColorMatrix) {
+        self.groups.push(Group {
+            opacity,
+            filter,
+            parent: self.group,
+        });
+        self.group = Some(self.groups.len() - 1);
+    }
+
+    pub fn pop_group(&mut self) {
+        self.group = self.group.and_then(|group| self.groups[group].parent);
+    }
+
+    /// Clip the shapes pushed until the matching [`Scene::pop_clip_path`] to a convex outline, which is drawn with the current transform.
+    pub fn push_clip_path(&mut self, outline: Vec<Pos2>) {
+        self.clip_paths.push(ClipPath {
+            transform: self.transform,
+            outline: outline.into(),
+        });
+    }
+
+    pub fn pop_clip_path(&mut self) {
+        self.clip_paths.pop();
+    }
+
+    /// Push the shapes until the matching [`Scene::end_content`] as the content of an element, in coordinates of its own, see [`Content`]. `transform` maps the content to the window and `clip` is the rect it is clipped to in window coordinates. The transform and clip rects of the shapes are then in the coordinates of the content.
+    ///
+    /// Returns false if shapes are already pushed into the content of another element, the content of nested elements is part of it.
+    pub fn begin_content(&mut self, node: NodeId, transform: Affine, clip: Rect) -> bool {
+        if self.content.is_some() {
+            return false;
+        }
+        self.split = true;
+        self.content = Some(node);
+        self.contents.insert(
+            node,
+            Content {
+                transform,
+                clip,
+                group: self.group,
+                clip_paths: std::mem::take(&mut self.clip_paths),
+                area: Rect::NOTHING,
+            },
+        );
+        true
+    }
+
+    pub fn end_content(&mut self) {
+        if let Some(content) = self
+            .content
+            .take()
+            .and_then(|node| self.contents.get(&node))
+        {
+            self.clip_paths = content.clip_paths.clone();
+        }
+        self.split = true;
+    }
+
+    /// The groups a layer in the group is nested in, from the outermost to the innermost.
+    pub fn group_chain(&self, group: Option<usize>) -> Vec<usize> {
+        let mut chain: Vec<usize> =
+            std::iter::successors(group, |group| self.groups[*group].parent).collect();
+        chain.reverse();
+        chain
+    }
+
+    /// Add a shape that is drawn with the current transform and clipped to a rect in window coordinates, or in the coordinates of the content shapes are pushed into.
+    pub fn push(&mut self, clip: Rect, shape: Shape) {
+        self.push_shape(clip, shape, false);
+    }
+
+    /// Add a text mesh with subpixel glyphs, see [`crate::text::text_mesh`]. It is drawn in a layer of its own, which the backend blends each color channel of on its own.
+    pub fn push_subpixel_text(&mut self, clip: Rect, mesh: Mesh) {
+        self.push_shape(clip, Shape::mesh(mesh), true);
+    }
+
+    /// True if text pushed now can be drawn with subpixel anti-aliasing. The channels are blended with the pixels below the text, so text that is drawn into a layer of its own first, like translucent, filtered and cached content, is smoothed in grayscale.
+    pub fn draws_subpixel_text(&self) -> bool {
+        self.subpixel_text && self.group.is_none() && self.content.is_none()
+    }
+
+    fn push_shape(&mut self, clip: Rect, shape: Shape, subpixel_text: bool) {
+        // Feathering fades the edges out past the bounds of the shape
+        let bounds = transform_rect(self.transform, shape.visual_bounding_rect())
+            .expand(self.feathering)
+            .intersect(clip);
+        self.paint(bounds);
+        let split = std::mem::take(&mut self.split);
+        match self.layers.last_mut() {
+            Some(layer)
+                if !split
+                    && layer.transform == self.transform
+                    && layer.clip == clip
+                    && layer.group == self.group
+                    && layer.clip_paths == self.clip_paths
+                    && layer.subpixel_text == subpixel_text =>
+            {
+                layer.shapes.push(shape)
+            }
+            _ => self.layers.push(Layer {
+                transform: self.transform,
+                clip,
+                group: self.group,
+                clip_paths: self.clip_paths.clone(),
+                shapes: vec![shape],
+                backdrop: None,
+                content: self.content,
+                subpixel_text,
+            }),
+        }
+    }
+
+    /// Add an area to the area painted by the element and its content, which is in the coordinates of the content if shapes are pushed into one.
+    fn paint(&mut self, bounds: Rect) {
+        if !bounds.is_positive() {
+            return;
+        }
+        let bounds = match self.content.and_then(|node| self.contents.get_mut(&node)) {
+            Some(content) => {
+                content.area = content.area.union(bounds);
+                transform_rect(content.transform, bounds).intersect(content.clip)
+            }
+            None => bounds,
+        };
+        if bounds.is_positive() {
+            self.painted = self.painted.union(bounds);
+        }
+    }
+
+    /// Blur what was drawn so far in an area of the window. Shapes pushed after it are drawn over the blurred pixels.
+    pub fn push_backdrop_blur(&mut self, area: Rect, radius: f32) {
+        if !area.is_positive() || radius <= 0.0 {
+            return;
+        }
+        self.paint(area);
+        self.split = false;
+        // The blur starts a layer of its own, so it is applied after the shapes before it are drawn
+        self.layers.push(Layer {
+            transform: self.transform,
+            clip: area,
+            group: self.group,
+            clip_paths: self.clip_paths.clone(),
+            shapes: Vec::new(),
+            backdrop: Some(BackdropBlur { area, radius }),
+            content: self.content,
+            subpixel_text: false,
+        });
+    }
+
+    /// The layers with the layers of content mapped to the window, for drawing the scene without keeping the content apart.
+    pub fn window_layers(&self) -> Cow<[Layer]> {
+        if self.contents.is_empty() {
+            return Cow::Borrowed(&self.layers);
+        }
+        Cow::Owned(
+            self.layers
+                .iter()
+                .map(
+                    |layer| match layer.content.and_then(|node| self.contents.get(&node)) {
+                        Some(content) => content.to_window(layer),
+                        None => layer.clone(),
+                    },
+                )
+                .collect(),
+        )
+    }
+
+    /// Grow the damaged area of the window to cover the blurred backdrops it touches. A blurred pixel depends on the pixels around it, so a backdrop is drawn again as a whole.
+    pub fn backdrop_damage(&self, damage: Rect) -> Rect {
+        self.window_layers()
+            .iter()
+            .filter_map(|layer| layer.backdrop)
+            .filter(|backdrop| backdrop.area.intersects(damage))
+            .fold(damage, |damage, backdrop| damage.union(backdrop.area))
+    }
+
+    pub fn tessellation_options(&self) -> TessellationOptions {
+        TessellationOptions {
+            feathering: self.feathering > 0.0,
+            feathering_size_in_pixels: self.feathering,
+            ..Default::default()
+        }
+    }
+
+    /// Turn the shapes into triangle meshes
+    pub fn tessellate(&self, pixels_per_point: f32) -> Vec<TessellatedLayer> {
+        let options = self.tessellation_options();
+        self.window_layers()
+            .iter()
+            .filter_map(|layer| {
+                let mesh = tessellate_layer(layer, pixels_per_point, options);
+                if mesh.is_none() && layer.backdrop.is_none() {
+                    return None;
+                }
+                Some(TessellatedLayer {
+                    transform: layer.transform,
+                    clip: layer.clip,
+                    group: layer.group,
+                    mesh: mesh.unwrap_or_default(),
+                    backdrop: layer.backdrop,
+                })
+            })
+            .collect()
+    }
+
+    /// Turn the shapes into triangle meshes, reusing the meshes of the previous frame for layers outside of the damaged area of the window that didn't change.
+    ///
+    /// The layers of content are tessellated in the coordinates of the content and kept in [`TessellationCache::contents`] instead of being returned. They are tessellated again only if the content changed, wherever it moved.
+    ///
+    /// If `rects` is true, rects are kept as [`RectInstance`]s instead of being tessellated.
+    pub fn tessellate_damaged(
+        &self,
+        pixels_per_point: f32,
+        damage: Rect,
+        cache: &mut TessellationCache,
+        rects: bool,
+    ) -> Vec<BatchedLayer> {
+        let options = self.tessellation_options();
+        let reusable = cache.pixels_per_point == pixels_per_point
+            && cache.options == options
+            && cache.rects == rects;
+        let mut previous = std::mem::take(&mut cache.layers);
+        let mut tessellated = Vec::with_capacity(self.layers.len());
+        // The layers of each content and the index of its first layer
+        let mut contents: FxHashMap<NodeId, (usize, Vec<&Layer>)> = FxHashMap::default();
+        for (index, layer) in self.layers.iter().enumerate() {
+            let layer = match layer
+                .content
+                .and_then(|node| Some((node, self.contents.get(&node)?)))
+            {
+                // TODO: Keep content apart that is clipped to a rounded outline. Until then it is drawn in window coordinates like the other layers
+                Some((_, content)) if !content.clip_paths.is_empty() => {
+                    Cow::Owned(content.to_window(layer))
+                }
+                Some((node, _)) => {
+                    contents
+                        .entry(node)
+                        .or_insert_with(|| (index, Vec::new()))
+                        .1
+                        .push(layer);
+                    cache.layers.push(None);
+                    continue;
+                }
+                None => Cow::Borrowed(layer),
+            };
+            // Layers are compared in paint order, so a layer that was inserted before others causes the ones after it to be tessellated again
+            let cached = previous
+                .get_mut(index)
+                .filter(|_| reusable && !layer_bounds(&layer).intersects(damage))
+                .and_then(Option::take)
+                .filter(|(previous, _)| *previous == *layer);
+            let (layer, batches) = match cached {
+                Some(cached) => {
+                    stats::count(Counter::LayerHits, 1);
+                    cached
+                }
+                None => {
+                    stats::count(Counter::LayerMisses, 1);
+                    let batches = batch_layer(&layer, pixels_per_point, options, rects);
+                    (layer.into_owned(), batches)
+                }
+            };
+            if !batches.is_empty() || layer.backdrop.is_some() {
+                tessellated.push(BatchedLayer {
+                    index,
+                    transform: layer.transform,
+                    clip: layer.clip,
+                    group: layer.group,
+                    batches: batches.clone(),
+                    backdrop: layer.backdrop,
+                });
+            }
+            cache.layers.push(Some((layer, batches)));
+        }
+        let mut previous_contents = std::mem::take(&mut cache.contents);
+        for (node, (first, layers)) in contents {
+            let previous = previous_contents.remove(&node);
+            let texture = previous.as_ref().and_then(|previous| previous.texture);
+            let unchanged = previous.filter(|previous| {
+                reusable
+                    && previous.layers.len() == layers.len()
+                    && previous
+                        .layers
+                        .iter()
+                        .zip(&layers)
+                        .all(|((previous, _), layer)| previous == *layer)
+            });
+            let content = match unchanged {
+                Some(previous) => {
+                    stats::count(Counter::LayerHits, previous.layers.len());
+                    CachedContent {
+                        first,
+                        changed: false,
+                        ..previous
+                    }
+                }
+                None => {
+                    stats::count(Counter::LayerMisses, layers.len());
+                    CachedContent {
+                        first,
+                        layers: layers
+                            .into_iter()
+                            .map(|layer| {
+                                let batches = batch_layer(layer, pixels_per_point, options, rects);
+                                (layer.clone(), batches)
+                            })
+                            .collect(),
+                        changed: true,
+                        texture,
+                    }
+                }
+            };
+            cache.contents.insert(node, content);
+        }
+        cache.released.extend(
+            previous_contents
+                .into_values()
+                .filter_map(|content| content.texture.map(|(id, _)| id)),
+        );
+        cache.pixels_per_point = pixels_per_point;
+        cache.options = options;
+        cache.rects = rects;
+        tessellated
+    }
+}
+
+/// The layers of the previous frame and their meshes.
+#[derive(Default)]
+pub(crate) struct TessellationCache {
+    pixels_per_point: f32,
+    options: TessellationOptions,
+    rects: bool,
+    layers: Vec<Option<(Layer, Vec<Batch>)>>,
+    /// The layers of each content in the coordinates of the content, see [`Content`]
+    pub contents: FxHashMap<NodeId, CachedContent>,
+    /// The textures of content that is gone, which the backend can free
+    pub released: Vec<u64>,
+    /// The id of the last texture of a cached layer
+    pub last_texture: u64,
+    /// The generation of the texture atlas the backend has, see [`crate::atlas::atlas_delta`]
+    pub texture_generation: u64,
+}
+
+/// The layers of a content and their batches, in the coordinates of the content.
+pub(crate) struct CachedContent {
+    /// The index of the first layer of the content in the scene
+    pub first: usize,
+    pub layers: Vec<(Layer, Vec<Batch>)>,
+    /// Set if the layers changed since the previous frame
+    pub changed: bool,
+    /// The texture the content is drawn into by backends that cache layers, and the area of the content it covers
+    pub texture: Option<(u64, Rect)>,
+}
+
+/// Turn the shapes of a layer into one mesh, clipped to the clip paths of the layer.
+fn tessellate_layer(
+    layer: &Layer,
+    pixels_per_point: f32,
+    options: TessellationOptions,
+) -> Option<Mesh> {
+    if !layer.clip.is_positive() {
+        return None;
+    }
+    // Feathering is measured in the coordinates of the layer, so account for the scale of the transform to keep the edges as wide on screen
+    let scale = layer.transform.determinant().abs().sqrt() as f32;
+    if scale == 0.0 {
+        return None;
+    }
+    let mut tessellator = Tessellator::new(pixels_per_point * scale, options, [1, 1], vec![]);
+    // Shapes are culled against the clip rect in the coordinates of the layer
+    tessellator.set_clip_rect(local_rect(layer.transform, layer.clip));
+    let mut mesh = Mesh::default();
+    for shape in &layer.shapes {
+        tessellator.tessellate_shape(shape.clone(), &mut mesh);
+    }
+    for clip_path in &layer.clip_paths {
+        // The outline is moved into the coordinates of the layer
+        let transform = layer.transform.inverse() * clip_path.transform;
+        let outline: Vec<Pos2> = clip_path
+            .outline
+            .iter()
+            .map(|pos| {
+                let pos = transform * peniko::kurbo::Point::new(pos.x as f64, pos.y as f64);
+                Pos2::new(pos.x as f32, pos.y as f32)
+            })
+            .collect();
+        mesh = clip_mesh_to_outline(&mesh, &outline);
+    }
+    (!mesh.is_empty()).then_some(mesh)
+}
+
+/// Turn the shapes of a layer into batches. Without `rects`, all shapes end up in a single mesh. Layers with clip paths are always tessellated, since rects aren't clipped to them.
+fn batch_layer(
+    layer: &Layer,
+    pixels_per_point: f32,
+    options: TessellationOptions,
+    rects: bool,
+) -> Vec<Batch> {
+    if !rects || !layer.clip_paths.is_empty() || layer.subpixel_text {
+        let mesh = tessellate_layer(layer, pixels_per_point, options);
+        if let Some(mesh) = &mesh {
+            stats::count(Counter::Vertices, mesh.vertices.len());
+        }
+        let batch = if layer.subpixel_text {
+            Batch::SubpixelText
+        } else {
+            Batch::Mesh
+        };
+        return mesh.map(batch).into_iter().collect();
+    }
+    if !layer.clip.is_positive() {
+        return Vec::new();
+    }
+    let scale = layer.transform.determinant().abs().sqrt() as f32;
+    if scale == 0.0 {
+        return Vec::new();
+    }
+    let local_clip = local_rect(layer.transform, layer.clip);
+    let mut tessellator = Tessellator::new(pixels_per_point * scale, options, [1, 1], vec![]);
+    tessellator.set_clip_rect(local_clip);
+    let mut batches = Vec::new();
+    for shape in &layer.shapes {
+        match shape {
+            Shape::Rect(rect) => {
+                let instance = RectInstance::new(rect);
+                // Culled like the tessellator culls shapes. The edge is smoothed within a pixel of the bounds
+                if !instance.bounds().expand(1.0 / scale).intersects(local_clip) {
+                    continue;
+                }
+                match batches.last_mut() {
+                    Some(Batch::Rects(rects)) => rects.push(instance),
+                    _ => batches.push(Batch::Rects(vec![instance])),
+                }
+            }
+            shape => {
+                if !matches!(batches.last(), Some(Batch::Mesh(_))) {
+                    batches.push(Batch::Mesh(Mesh::default()));
+                }
+                if let Some(Batch::Mesh(mesh)) = batches.last_mut() {
+                    tessellator.tessellate_shape(shape.clone(), mesh);
+                }
+            }
+        }
+    }
+    batches.retain(|batch| match batch {
+        Batch::Mesh(mesh) => !mesh.is_empty(),
+        Batch::Rects(rects) => !rects.is_empty(),
+        Batch::CachedLayer(..) | Batch::SubpixelText(_) => true,
+    });
+    stats::count(
+        Counter::Vertices,
+        batches
+            .iter()
+            .map(|batch| match batch {
+                Batch::Mesh(mesh) => mesh.vertices.len(),
+                _ => 0,
+            })
+            .sum(),
+    );
+    batches
+}
+
+/// The area of the window a layer draws to.
+fn layer_bounds(layer: &Layer) -> Rect {
+    let bounds = layer.shapes.iter().fold(Rect::NOTHING, |bounds, shape| {
+        bounds.union(shape.visual_bounding_rect())
+    });
+    transform_rect(layer.transform, bounds).intersect(layer.clip)
+}
+
+/// The area of the window that has to be drawn again because it looks different than in the previous frame.
+///
+/// This covers the elements that were marked dirty and the elements that were added, removed or moved, before and after they changed.
+pub(crate) fn damage(
+    previous: &FxHashMap<NodeId, Rect>,
+    current: &FxHashMap<NodeId, Rect>,
+    dirty: &DirtyNodes,
+    window: Rect,
+) -> Rect {
+    let dirty = match dirty {
+        DirtyNodes::All => return window,
+        DirtyNodes::Some(dirty) => dirty,
+    };
+    let mut damage = Rect::NOTHING;
+    for (node, bounds) in current {
+        match previous.get(node) {
+            Some(previous) if previous == bounds => {
+                if dirty.contains(node) {
+                    damage = damage.union(*bounds);
+                }
+            }
+            Some(previous) => damage = damage.union(*previous).union(*bounds),
+            None => damage = damage.union(*bounds),
+        }
+    }
+    for (node, bounds) in previous {
+        if !current.contains_key(node) {
+            damage = damage.union(*bounds);
+        }
+    }
+    damage.intersect(window)
+}
+
+/// The bounds of a rect in window coordinates mapped back into the coordinates of a transform.
+fn local_rect(transform: Affine, rect: Rect) -> Rect {
+    if transform == Affine::IDENTITY || !rect.is_finite() {
+        return rect;
+    }
+    // A transform that collapses the layer to a line or point has nothing to draw
+    if transform.determinant() == 0.0 {
+        return Rect::NOTHING;
+    }
+    transform_rect(transform.inverse(), rect)
+}
+
+/// The bounds of a rect after it is transformed.
+pub(crate) fn transform_rect(transform: Affine, rect: Rect) -> Rect {
+    if transform == Affine::IDENTITY {
+        return rect;
+    }
+    let bounds = transform.transform_rect_bbox(peniko::kurbo::Rect::new(
+        rect.min.x as f64,
+        rect.min.y as f64,
+        rect.max.x as f64,
+        rect.max.y as f64,
+    ));
+    Rect::from_min_max(
+        Pos2::new(bounds.x0 as f32, bounds.y0 as f32),
+        Pos2::new(bounds.x1 as f32, bounds.y1 as f32),
+    )
+}
+
+/// Apply a transform to the vertices of a mesh.
+pub(crate) fn transform_mesh(mesh: &mut Mesh, transform: Affine) {
+    if transform == Affine::IDENTITY {
+        return;
+    }
+    for vertex in &mut mesh.vertices {
+        let pos = transform * peniko::kurbo::Point::new(vertex.pos.x as f64, vertex.pos.y as f64);
+        vertex.pos = Pos2::new(pos.x as f32, pos.y as f32);
+    }
+}
+
+/// Clip the triangles of a mesh to a rect on the CPU.
+pub(crate) fn clip_mesh(mesh: &Mesh, clip: Rect) -> Mesh {
+    if !clip.is_positive() {
+        return Mesh::with_texture(mesh.texture_id);
+    }
+    // The signed distance from each edge of the clip rect. Points inside the rect are positive
+    let edges: [&dyn Fn(Pos2) -> f32; 4] = [
+        &|pos| pos.x - clip.min.x,
+        &|pos| clip.max.x - pos.x,
+        &|pos| pos.y - clip.min.y,
+        &|pos| clip.max.y - pos.y,
+    ];
+    clip_mesh_to_edges(mesh, &edges)
+}
+
+/// Clip the triangles of a mesh to a convex outline on the CPU. The outline can wind either way.
+pub(crate) fn clip_mesh_to_outline(mesh: &Mesh, outline: &[Pos2]) -> Mesh {
+    // Twice the signed area, which is positive for outlines that wind clockwise on screen
+    let area: f32 = (0..outline.len())
+        .map(|i| {
+            let (a, b) = (outline[i], outline[(i + 1) % outline.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum();
+    if outline.len() < 3 || area == 0.0 {
+        return Mesh::with_texture(mesh.texture_id);
+    }
+    let winding = area.signum();
+    let edges: Vec<_> = (0..outline.len())
+        .map(|i| {
+            let (a, b) = (outline[i], outline[(i + 1) % outline.len()]);
+            move |pos: Pos2| {
+                let (edge, to_pos) = (b - a, pos - a);
+                winding * (edge.x * to_pos.y - edge.y * to_pos.x)
+            }
+        })
+        .collect();
+    let edges: Vec<&dyn Fn(Pos2) -> f32> = edges
+        .iter()
+        .map(|edge| edge as &dyn Fn(Pos2) -> f32)
+        .collect();
+    clip_mesh_to_edges(mesh, &edges)
+}
+
+/// Clip the triangles of a mesh to the points where the distance to every edge is positive.
+fn clip_mesh_to_edges(mesh: &Mesh, edges: &[&dyn Fn(Pos2) -> f32]) -> Mesh {
+    let mut clipped = Mesh::with_texture(mesh.texture_id);
+    for triangle in mesh.indices.chunks_exact(3) {
+        let mut polygon: Vec<Vertex> = triangle
+            .iter()
+            .map(|index| mesh.vertices[*index as usize])
+            .collect();
+        for edge in edges {
+            polygon = clip_polygon(&polygon, *edge);
+        }
+        if polygon.len() < 3 {
+            continue;
+        }
+        // The clipped polygon is convex, so it can be drawn as a triangle fan
+        let first = clipped.vertices.len() as u32;
+        clipped.vertices.extend(polygon.iter());
+        for i in 1..polygon.len() as u32 - 1 {
+            clipped.add_triangle(first, first + i, first + i + 1);
+        }
+    }
+    clipped
+}
+
+/// Clip a convex polygon against a single edge (Sutherland-Hodgman).
+fn clip_polygon(polygon: &[Vertex], distance: &dyn Fn(Pos2) -> f32) -> Vec<Vertex> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let current_distance = distance(current.pos);
+        let next_distance = distance(next.pos);
+        if current_distance >= 0.0 {
+            clipped.push(*current);
+        }
+        if (current_distance >= 0.0) != (next_distance >= 0.0) {
+            let t = current_distance / (current_distance - next_distance);
+            clipped.push(lerp_vertex(current, next, t));
+        }
+    }
+    clipped
+}
+
+fn lerp_vertex(a: &Vertex, b: &Vertex, t: f32) -> Vertex {
+    let a_color = a.color.to_array();
+    let b_color = b.color.to_array();
+    let channel =
+        |i: usize| (a_color[i] as f32 + (b_color[i] as f32 - a_color[i] as f32) * t).round() as u8;
+    Vertex {
+        pos: a.pos + (b.pos - a.pos) * t,
+        uv: a.uv + (b.uv - a.uv) * t,
+        color: Color32::from_rgba_premultiplied(channel(0), channel(1), channel(2), channel(3)),
+    }
+}
@@ -0,0 +1,127 @@
+use std::io::{self, Cursor};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use epaint::Color32;
+use tao::event_loop::EventLoopProxy;
+use tokio::sync::oneshot;
+
+use crate::Redraw;
+
+/// Takes screenshots of the window while the application runs, for bug reports or to let users export the UI as an image.
+///
+/// Pass a clone to [`crate::Config::with_screenshots`] and keep another one in the application, for example in a Dioxus context. Screenshots can be taken from any thread and with any executor.
+#[derive(Clone, Default)]
+pub struct Screenshots {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    pending: Vec<oneshot::Sender<image::RgbaImage>>,
+    /// Wakes up the window so the screenshot is taken even if nothing changes, set once the window is opened
+    proxy: Option<EventLoopProxy<Redraw>>,
+}
+
+impl Screenshots {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Capture the next frame of the window in physical pixels. The GPU backends copy the frame they draw. The frame is drawn on the CPU from the same shapes the window is drawn with instead if the backend can't copy it, like the wgpu backend on HDR surfaces, or while the debug HUD is shown, which is left out of screenshots.
+    ///
+    /// Returns an error if the window is closed before the frame is drawn.
+    pub async fn capture(&self) -> io::Result<image::RgbaImage> {
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.pending.push(sender);
+            if let Some(proxy) = &inner.proxy {
+                let _ = proxy.send_event(Redraw);
+            }
+        }
+        receiver.await.map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the window was closed before the screenshot was taken",
+            )
+        })
+    }
+
+    /// Capture the next frame as an encoded PNG.
+    pub async fn capture_png(&self) -> io::Result<Vec<u8>> {
+        let image = self.capture().await?;
+        let mut png = Cursor::new(Vec::new());
+        image
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .map_err(io::Error::other)?;
+        Ok(png.into_inner())
+    }
+
+    /// Capture the next frame and save it to a file. The format is picked from the extension of the path.
+    pub async fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.capture().await?.save(path).map_err(io::Error::other)
+    }
+
+    pub(crate) fn attach(&self, proxy: EventLoopProxy<Redraw>) {
+        self.inner.lock().unwrap().proxy = Some(proxy);
+    }
+
+    pub(crate) fn is_pending(&self) -> bool {
+        !self.inner.lock().unwrap().pending.is_empty()
+    }
+
+    /// Send a frame to everyone waiting for a screenshot. The frame is only drawn if someone is waiting.
+    pub(crate) fn fulfill(&self, draw: impl FnOnce() -> image::RgbaImage) {
+        let pending = std::mem::take(&mut self.inner.lock().unwrap().pending);
+        if pending.is_empty() {
+            return;
+        }
+        let image = draw();
+        for sender in pending {
+            let _ = sender.send(image.clone());
+        }
+    }
+}
+
+/// Build a screenshot from the texels a GPU backend copied from its frame, which are premultiplied sRGB colors with 8 bits per channel. Each row starts `bytes_per_row` bytes after the last one. `bgra` swaps the red and blue channels of frames that store them in that order.
+pub(crate) fn image_from_texels(
+    [width, height]: [u32; 2],
+    bytes_per_row: usize,
+    texels: &[u8],
+    bgra: bool,
+) -> image::RgbaImage {
+    image::RgbaImage::from_fn(width, height, |x, y| {
+        let start = y as usize * bytes_per_row + x as usize * 4;
+        let [mut r, g, mut b, a] = [0, 1, 2, 3].map(|channel| texels[start + channel]);
+        if bgra {
+            std::mem::swap(&mut r, &mut b);
+        }
+        image::Rgba(Color32::from_rgba_premultiplied(r, g, b, a).to_srgba_unmultiplied())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texels_are_unpadded_and_swizzled() {
+        // Two rows of one pixel, padded to 8 bytes
+        let texels = [10, 20, 30, 255, 0, 0, 0, 0, 40, 50, 60, 255, 0, 0, 0, 0];
+        let image = image_from_texels([1, 2], 8, &texels, true);
+        assert_eq!(image.get_pixel(0, 0).0, [30, 20, 10, 255]);
+        assert_eq!(image.get_pixel(0, 1).0, [60, 50, 40, 255]);
+    }
+
+    #[test]
+    fn texels_are_unpremultiplied() {
+        let image = image_from_texels([1, 1], 4, &[0, 0, 0, 0], false);
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 0]);
+        let image = image_from_texels([1, 1], 4, &[128, 0, 0, 128], false);
+        let [r, g, b, a] = image.get_pixel(0, 0).0;
+        assert_eq!([g, b, a], [0, 0, 128]);
+        // The color is brighter without the alpha multiplied into it
+        assert!(r > 128, "{r}");
+    }
+}
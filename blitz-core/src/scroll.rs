@@ -0,0 +1,577 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use peniko::kurbo::{Affine, Point, Vec2};
+use shipyard::Component;
+use taffy::prelude::{Layout, LengthPercentage, Size};
+use taffy::Taffy;
+
+use crate::coordinates::element_transform;
+use crate::layout::TaffyLayout;
+use crate::mouse::{viewport_size, Placement};
+use crate::plugin::{CustomElement, Plugins};
+use crate::position::is_fixed;
+use crate::render::content_box;
+use crate::style::Overflow;
+use crate::system_settings::system_settings;
+
+/// How far a line of a mouse wheel scrolls in pixels. A notch of the wheel scrolls the number of lines of the system settings
+const LINE_HEIGHT: f64 = 40.0 / 3.0;
+/// The thickness of a scrollbar and its distance from the edges of the padding box. Scrollbars are drawn over the content, so they don't take space from it
+const SCROLLBAR_WIDTH: f32 = 6.0;
+const SCROLLBAR_MARGIN: f32 = 2.0;
+/// The shortest a thumb gets, so it can still be grabbed when the content is very long
+const MIN_THUMB_LENGTH: f32 = 20.0;
+const THUMB_COLOR: epaint::Color32 = epaint::Color32::from_rgba_premultiplied(0, 0, 0, 100);
+const TRACK_COLOR: epaint::Color32 = epaint::Color32::from_rgba_premultiplied(0, 0, 0, 16);
+
+/// How far the content of a scroll container is scrolled. It is stored on containers once they are scrolled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Component)]
+pub(crate) struct ScrollOffset(pub Vec2);
+
+/// How far the children of an element are moved by scrolling. Elements that don't scroll return zero.
+pub(crate) fn scroll_offset(node: NodeRef) -> Vec2 {
+    node.get::<ScrollOffset>()
+        .map(|offset| offset.0)
+        .unwrap_or(Vec2::ZERO)
+}
+
+/// How far the content of an element can be scrolled along each axis. The content is the border boxes of the children and the padding at the end of the element.
+pub(crate) fn scroll_range(taffy: &Taffy, node: NodeRef) -> Vec2 {
+    let Some(overflow) = node.get::<Overflow>().map(|overflow| *overflow) else {
+        return Vec2::ZERO;
+    };
+    if !overflow.scrolls_x() && !overflow.scrolls_y() {
+        return Vec2::ZERO;
+    }
+    let Some(taffy_layout) = node.get::<TaffyLayout>() else {
+        return Vec2::ZERO;
+    };
+    let Some(layout) = layout_of(taffy, node) else {
+        return Vec2::ZERO;
+    };
+    let extent = node
+        .children()
+        .into_iter()
+        .filter_map(|child| layout_of(taffy, child))
+        .fold(Vec2::ZERO, |extent, child| {
+            Vec2::new(
+                extent.x.max((child.location.x + child.size.width) as f64),
+                extent.y.max((child.location.y + child.size.height) as f64),
+            )
+        });
+    // TODO: Percentages should resolve against the width of the containing block
+    let resolve = |value: LengthPercentage| match value {
+        LengthPercentage::Points(points) => points as f64,
+        LengthPercentage::Percent(percent) => (percent * layout.size.width) as f64,
+    };
+    let style = &taffy_layout.style;
+    let end = Vec2::new(
+        resolve(style.padding.right) + resolve(style.border.right),
+        resolve(style.padding.bottom) + resolve(style.border.bottom),
+    );
+    let range = extent + end - Vec2::new(layout.size.width as f64, layout.size.height as f64);
+    Vec2::new(
+        if overflow.scrolls_x() {
+            range.x.max(0.0)
+        } else {
+            0.0
+        },
+        if overflow.scrolls_y() {
+            range.y.max(0.0)
+        } else {
+            0.0
+        },
+    )
+}
+
+/// A scrollbar of a scroll container, in the coordinates its padding box is given in.
+pub(crate) struct Scrollbar {
+    vertical: bool,
+    track: epaint::Rect,
+    /// The thumb, which shows which part of the content is visible. There is no thumb if the content fits
+    thumb: Option<epaint::Rect>,
+}
+
+impl Scrollbar {
+    /// The shapes of the scrollbar. The track is only drawn for `overflow: scroll`, which shows the scrollbar even if the content fits.
+    pub fn shapes(&self, always: bool) -> Vec<epaint::Shape> {
+        let rounding = SCROLLBAR_WIDTH / 2.0;
+        let track = always.then(|| epaint::Shape::rect_filled(self.track, rounding, TRACK_COLOR));
+        let thumb = self
+            .thumb
+            .map(|thumb| epaint::Shape::rect_filled(thumb, rounding, THUMB_COLOR));
+        track.into_iter().chain(thumb).collect()
+    }
+
+    fn along(&self, point: epaint::Pos2) -> f32 {
+        if self.vertical {
+            point.y
+        } else {
+            point.x
+        }
+    }
+
+    fn length(&self, rect: epaint::Rect) -> f32 {
+        if self.vertical {
+            rect.height()
+        } else {
+            rect.width()
+        }
+    }
+}
+
+/// The scrollbars of an element and whether their tracks are always shown, along the x axis and then the y axis. `padding_box` is the padding box of the element, which the bars are drawn inside of along its right and bottom edge.
+///
+/// A scrollbar is shown along each axis the content overflows, and along each axis with `overflow: scroll`.
+pub(crate) fn scrollbars(
+    taffy: &Taffy,
+    node: NodeRef,
+    padding_box: epaint::Rect,
+) -> Vec<(Scrollbar, bool)> {
+    let Some(overflow) = node.get::<Overflow>().map(|overflow| *overflow) else {
+        return Vec::new();
+    };
+    let range = scroll_range(taffy, node);
+    let offset = scroll_offset(node);
+    let shows_x = range.x > 0.0 || overflow.always_scrollbar_x();
+    let shows_y = range.y > 0.0 || overflow.always_scrollbar_y();
+    // The bars leave the corner where they would meet free
+    let corner = |shows: bool| {
+        if shows {
+            SCROLLBAR_WIDTH + SCROLLBAR_MARGIN
+        } else {
+            0.0
+        }
+    };
+    let inner = padding_box.shrink(SCROLLBAR_MARGIN);
+    let mut bars = Vec::new();
+    if shows_x {
+        let track = epaint::Rect::from_min_max(
+            epaint::pos2(inner.min.x, inner.max.y - SCROLLBAR_WIDTH),
+            epaint::pos2(inner.max.x - corner(shows_y), inner.max.y),
+        );
+        let bar = Scrollbar {
+            vertical: false,
+            track,
+            thumb: None,
+        };
+        bars.push((
+            with_thumb(bar, padding_box.width(), range.x, offset.x),
+            overflow.always_scrollbar_x(),
+        ));
+    }
+    if shows_y {
+        let track = epaint::Rect::from_min_max(
+            epaint::pos2(inner.max.x - SCROLLBAR_WIDTH, inner.min.y),
+            epaint::pos2(inner.max.x, inner.max.y - corner(shows_x)),
+        );
+        let bar = Scrollbar {
+            vertical: true,
+            track,
+            thumb: None,
+        };
+        bars.push((
+            with_thumb(bar, padding_box.height(), range.y, offset.y),
+            overflow.always_scrollbar_y(),
+        ));
+    }
+    bars
+}
+
+/// Place the thumb of a scrollbar. The thumb is as much shorter than the track as the visible part is than the content.
+fn with_thumb(mut bar: Scrollbar, visible: f32, range: f64, offset: f64) -> Scrollbar {
+    let track_length = bar.length(bar.track);
+    if range <= 0.0 || track_length <= 0.0 {
+        return bar;
+    }
+    let length = (track_length * visible / (visible + range as f32))
+        .max(MIN_THUMB_LENGTH)
+        .min(track_length);
+    let start = (track_length - length) * (offset / range) as f32;
+    let track = bar.track;
+    bar.thumb = Some(if bar.vertical {
+        epaint::Rect::from_min_size(
+            epaint::pos2(track.min.x, track.min.y + start),
+            epaint::vec2(track.width(), length),
+        )
+    } else {
+        epaint::Rect::from_min_size(
+            epaint::pos2(track.min.x + start, track.min.y),
+            epaint::vec2(length, track.height()),
+        )
+    });
+    bar
+}
+
+/// The mouse dragging the thumb of a scrollbar.
+pub(crate) struct ScrollbarDrag {
+    container: NodeId,
+    vertical: bool,
+    /// Maps client coordinates to the coordinates of the container when the drag started
+    inverse: Affine,
+    /// Where the drag started along the scrollbar, and the offset of the content then
+    start: f32,
+    start_offset: f64,
+    /// How far the content scrolls for each pixel the thumb moves
+    scale: f64,
+}
+
+impl ScrollbarDrag {
+    /// Start dragging the scrollbar under a point in client coordinates, if there is one. Pressing the track beside the thumb first scrolls a page towards the point. Containers later in the document are drawn over earlier ones, so their scrollbars are found first.
+    pub fn start(rdom: &mut RealDom, taffy: &Taffy, point: Point) -> Option<Self> {
+        let mut found = None;
+        let mut stack = vec![rdom.get(rdom.root_id())?];
+        while let Some(node) = stack.pop() {
+            if let Some(drag) = Self::on_scrollbar(rdom, taffy, node, point) {
+                found = Some(drag);
+            }
+            stack.extend(node.children().into_iter().rev());
+        }
+        let (drag, page) = found?;
+        if page != 0.0 {
+            let node = rdom.get(drag.container)?;
+            let range = scroll_range(taffy, node);
+            let mut offset = scroll_offset(node);
+            if drag.vertical {
+                offset.y = (offset.y + page).clamp(0.0, range.y);
+            } else {
+                offset.x = (offset.x + page).clamp(0.0, range.x);
+            }
+            rdom.get_mut(drag.container)?.insert(ScrollOffset(offset));
+            return Some(Self {
+                start_offset: if drag.vertical { offset.y } else { offset.x },
+                ..drag
+            });
+        }
+        Some(drag)
+    }
+
+    /// The drag of the scrollbar of a container under a point, and how far pressing it pages.
+    fn on_scrollbar(
+        rdom: &RealDom,
+        taffy: &Taffy,
+        node: NodeRef,
+        point: Point,
+    ) -> Option<(Self, f64)> {
+        let range = scroll_range(taffy, node);
+        if range == Vec2::ZERO {
+            return None;
+        }
+        let layout = layout_of(taffy, node)?;
+        let style = &node.get::<TaffyLayout>()?.style;
+        let inverse = element_transform(rdom, taffy, node.id())?.inverse();
+        let local = inverse * point;
+        let local = epaint::pos2(local.x as f32, local.y as f32);
+        let padding_box = padding_box(&layout, style);
+        let offset = scroll_offset(node);
+        let (bar, _) = scrollbars(taffy, node, padding_box)
+            .into_iter()
+            .find(|(bar, _)| bar.track.contains(local))?;
+        let thumb = bar.thumb?;
+        let (range, offset, page) = if bar.vertical {
+            (range.y, offset.y, padding_box.height())
+        } else {
+            (range.x, offset.x, padding_box.width())
+        };
+        let along = bar.along(local);
+        let page = if thumb.contains(local) {
+            0.0
+        } else if along < bar.along(thumb.min) {
+            -page as f64
+        } else {
+            page as f64
+        };
+        let travel = bar.length(bar.track) - bar.length(thumb);
+        Some((
+            Self {
+                container: node.id(),
+                vertical: bar.vertical,
+                inverse,
+                start: along,
+                start_offset: offset,
+                scale: if travel > 0.0 {
+                    range / travel as f64
+                } else {
+                    0.0
+                },
+            },
+            page,
+        ))
+    }
+
+    /// Scroll the container so the thumb follows the mouse at a point in client coordinates. Returns true if the content moved.
+    pub fn update(&self, rdom: &mut RealDom, taffy: &Taffy, point: Point) -> bool {
+        let Some(node) = rdom.get(self.container) else {
+            return false;
+        };
+        let range = scroll_range(taffy, node);
+        let offset = scroll_offset(node);
+        let local = self.inverse * point;
+        let moved = if self.vertical { local.y } else { local.x } - self.start as f64;
+        let mut scrolled = offset;
+        if self.vertical {
+            scrolled.y = (self.start_offset + moved * self.scale).clamp(0.0, range.y);
+        } else {
+            scrolled.x = (self.start_offset + moved * self.scale).clamp(0.0, range.x);
+        }
+        if scrolled == offset {
+            return false;
+        }
+        rdom.get_mut(self.container)
+            .unwrap()
+            .insert(ScrollOffset(scrolled));
+        true
+    }
+}
+
+/// The padding box of an element relative to its border box.
+pub(crate) fn padding_box(layout: &Layout, style: &taffy::style::Style) -> epaint::Rect {
+    // TODO: Percentages should resolve against the width of the containing block
+    let resolve = |value: LengthPercentage| match value {
+        LengthPercentage::Points(points) => points,
+        LengthPercentage::Percent(percent) => percent * layout.size.width,
+    };
+    let min = epaint::pos2(resolve(style.border.left), resolve(style.border.top));
+    let max = epaint::pos2(
+        layout.size.width - resolve(style.border.right),
+        layout.size.height - resolve(style.border.bottom),
+    );
+    epaint::Rect::from_min_max(min, max.max(min))
+}
+
+fn layout_of(taffy: &Taffy, node: NodeRef) -> Option<Layout> {
+    let node = node.get::<TaffyLayout>()?.node?;
+    taffy.layout(node).ok().copied()
+}
+
+/// Convert the delta of a mouse wheel event into pixels the content moves by.
+pub(crate) fn wheel_delta(delta: &tao::event::MouseScrollDelta) -> Vec2 {
+    match delta {
+        tao::event::MouseScrollDelta::LineDelta(x, y) => {
+            Vec2::new(*x as f64, *y as f64) * LINE_HEIGHT * system_settings().wheel_scroll_lines
+        }
+        tao::event::MouseScrollDelta::PixelDelta(delta) => Vec2::new(delta.x, delta.y),
+        _ => Vec2::ZERO,
+    }
+}
+
+/// An element under the point that is scrolled with the wheel
+enum ScrollTarget {
+    /// An element with overflowing content and how far it can be scrolled
+    Container(NodeId, Vec2),
+    /// A custom element and the point relative to its content box
+    Custom(NodeId, epaint::Pos2),
+}
+
+/// Scroll the innermost container under a point in client coordinates that can still move in the direction of `delta`. A positive delta moves the content right and down, like the delta of a mouse wheel. Returns true if anything scrolled.
+///
+/// Custom elements under the point are asked to scroll their own content first, see [`crate::ElementPlugin::scroll`].
+pub(crate) fn scroll_at(
+    rdom: &mut RealDom,
+    taffy: &Taffy,
+    plugins: &Plugins,
+    point: Point,
+    delta: Vec2,
+) -> bool {
+    let Some(viewport_size) = viewport_size(rdom, taffy) else {
+        return false;
+    };
+    let mut targets = Vec::new();
+    targets_at(
+        rdom.get(rdom.root_id()).unwrap(),
+        taffy,
+        plugins,
+        &viewport_size,
+        Placement::DOCUMENT,
+        point,
+        &mut targets,
+    );
+    // The targets are found from the outside in
+    for target in targets.into_iter().rev() {
+        let (container, range) = match target {
+            ScrollTarget::Container(container, range) => (container, range),
+            ScrollTarget::Custom(element, point) => {
+                let node = rdom.get(element).unwrap();
+                let scrolled = plugins.for_node(node).is_some_and(|plugin| {
+                    CustomElement::from_node(node).is_some_and(|element| {
+                        plugin.scroll(
+                            &element,
+                            point,
+                            epaint::vec2(delta.x as f32, delta.y as f32),
+                        )
+                    })
+                });
+                if scrolled {
+                    return true;
+                }
+                continue;
+            }
+        };
+        let node = rdom.get(container).unwrap();
+        let offset = scroll_offset(node);
+        let scrolled = Vec2::new(
+            (offset.x - delta.x).clamp(0.0, range.x),
+            (offset.y - delta.y).clamp(0.0, range.y),
+        );
+        if scrolled != offset {
+            rdom.get_mut(container)
+                .unwrap()
+                .insert(ScrollOffset(scrolled));
+            return true;
+        }
+    }
+    false
+}
+
+/// Collect the elements under a point that can be scrolled, in tree order. The point is mapped through the transforms of the elements and clipped by the ancestors that hide their overflow, like in [`crate::hit_test`].
+fn targets_at(
+    node: NodeRef,
+    taffy: &Taffy,
+    plugins: &Plugins,
+    viewport_size: &Size<u32>,
+    parent: Placement,
+    point: Point,
+    targets: &mut Vec<ScrollTarget>,
+) {
+    let Some(layout) = layout_of(taffy, node) else {
+        return;
+    };
+    let Some((placement, children_placement)) = parent.place(taffy, node, viewport_size) else {
+        return;
+    };
+    let location = placement.location;
+    let local = placement.to_local(point).filter(|local| {
+        local.x >= location.x
+            && local.y >= location.y
+            && local.x < location.x + layout.size.width as f64
+            && local.y < location.y + layout.size.height as f64
+    });
+    // Custom elements draw their own content instead of their children
+    if plugins.for_node(node).is_some() {
+        if let Some(local) = local {
+            let style = &node.get::<TaffyLayout>().unwrap().style;
+            let content_box = content_box(&layout, style, location);
+            targets.push(ScrollTarget::Custom(
+                node.id(),
+                epaint::pos2(local.x as f32, local.y as f32) - content_box.min.to_vec2(),
+            ));
+        }
+        return;
+    }
+    if local.is_some() {
+        let range = scroll_range(taffy, node);
+        if range != Vec2::ZERO {
+            targets.push(ScrollTarget::Container(node.id(), range));
+        }
+    }
+    // Children can overflow elements that don't clip them, so they are searched either way. Fixed children are laid out in the root
+    for child in node.children() {
+        let parent = if is_fixed(child) {
+            Placement::DOCUMENT
+        } else {
+            children_placement
+        };
+        targets_at(child, taffy, plugins, viewport_size, parent, point, targets);
+    }
+}
+
+/// Drives the transform and opacity of an element from how far a scroll container is scrolled, for parallax headers, reading progress bars and headers that fade out.
+///
+/// The callbacks run while the document is drawn, so the element follows the container in the same frame without a round trip through the VirtualDom. They get the progress of the container along each axis from 0 at the start to 1 at the end of its content. An axis that can't be scrolled has a progress of 0.
+///
+/// ```no_run
+/// # use blitz_core::ScrollLink;
+/// # use dioxus_native_core::prelude::*;
+/// # use peniko::kurbo::Affine;
+/// # fn link(rdom: &mut RealDom, article: NodeId, progress_bar: NodeId) {
+/// ScrollLink::new(article)
+///     .with_transform(|progress| Affine::scale_non_uniform(progress.y, 1.0))
+///     .attach(rdom, progress_bar);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ScrollLink {
+    container: NodeId,
+    transform: Option<Arc<dyn Fn(Vec2) -> Affine + Send + Sync>>,
+    opacity: Option<Arc<dyn Fn(Vec2) -> f32 + Send + Sync>>,
+}
+
+/// The link of an element, stored on the element
+#[derive(Component)]
+struct Linked(Option<ScrollLink>);
+
+impl ScrollLink {
+    pub fn new(container: NodeId) -> Self {
+        Self {
+            container,
+            transform: None,
+            opacity: None,
+        }
+    }
+
+    /// Transform the element with the result of a callback. The transform is applied after the CSS transform of the element, with the top left corner of its border box as the origin.
+    pub fn with_transform(
+        mut self,
+        transform: impl Fn(Vec2) -> Affine + Send + Sync + 'static,
+    ) -> Self {
+        self.transform = Some(Arc::new(transform));
+        self
+    }
+
+    /// Multiply the opacity of the element with the result of a callback.
+    pub fn with_opacity(mut self, opacity: impl Fn(Vec2) -> f32 + Send + Sync + 'static) -> Self {
+        self.opacity = Some(Arc::new(opacity));
+        self
+    }
+
+    /// Link an element to the container, replacing the link it had before.
+    pub fn attach(self, rdom: &mut RealDom, element: NodeId) {
+        if let Some(mut node) = rdom.get_mut(element) {
+            node.insert(Linked(Some(self)));
+        }
+    }
+
+    pub fn detach(rdom: &mut RealDom, element: NodeId) {
+        if let Some(mut node) = rdom.get_mut(element) {
+            node.insert(Linked(None));
+        }
+    }
+}
+
+/// The transform and opacity the scroll link of an element adds to it. `location` is the top left corner of its border box.
+pub(crate) fn scroll_effect(taffy: &Taffy, node: NodeRef, location: Point) -> (Affine, f32) {
+    let Some(linked) = node.get::<Linked>() else {
+        return (Affine::IDENTITY, 1.0);
+    };
+    let Some(link) = &linked.0 else {
+        return (Affine::IDENTITY, 1.0);
+    };
+    // The container may have been removed since the link was made
+    let Some(container) = node.real_dom().get(link.container) else {
+        return (Affine::IDENTITY, 1.0);
+    };
+    let range = scroll_range(taffy, container);
+    let offset = scroll_offset(container);
+    let progress = |offset: f64, range: f64| {
+        if range > 0.0 {
+            (offset / range).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    };
+    let progress = Vec2::new(progress(offset.x, range.x), progress(offset.y, range.y));
+    let transform = link
+        .transform
+        .as_ref()
+        .map_or(Affine::IDENTITY, |transform| {
+            Affine::translate(location.to_vec2())
+                * transform(progress)
+                * Affine::translate(-location.to_vec2())
+        });
+    let opacity = link
+        .opacity
+        .as_ref()
+        .map_or(1.0, |opacity| opacity(progress).clamp(0.0, 1.0));
+    (transform, opacity)
+}
@@ -0,0 +1,258 @@
+use std::ops::Range;
+
+use dioxus_native_core::prelude::*;
+use peniko::kurbo::Point;
+use shipyard::Component;
+use taffy::Taffy;
+
+use crate::coordinates::element_transform;
+use crate::events::editable_value;
+use crate::layout::TaffyLayout;
+use crate::style::{TextStyle, UserSelect};
+use crate::system_settings::system_settings;
+use crate::text::shape_text;
+
+/// A text node and a byte offset in its text
+type TextPosition = (NodeId, usize);
+
+/// The text selected in the document by dragging over it, stored on the root. Text that is edited has a selection of its own, see [`crate::editing::Editing`].
+#[derive(Clone, Debug, PartialEq, Component)]
+struct DocumentSelection {
+    /// Where the drag started
+    anchor: TextPosition,
+    /// Where the mouse is
+    focus: TextPosition,
+    /// The text nodes that have a [`Selected`] range
+    nodes: Vec<NodeId>,
+}
+
+/// The part of the text of a text node that is selected, which the renderer highlights behind the text.
+#[derive(Clone, Debug, Default, PartialEq, Component)]
+pub(crate) struct Selected(pub Range<usize>);
+
+/// The text nodes of the document in document order.
+fn text_nodes(rdom: &RealDom) -> Vec<NodeId> {
+    let mut nodes = Vec::new();
+    let mut stack = vec![rdom.get(rdom.root_id()).unwrap()];
+    while let Some(node) = stack.pop() {
+        if matches!(&*node.node_type(), NodeType::Text(_)) {
+            nodes.push(node.id());
+        }
+        stack.extend(node.children().into_iter().rev());
+    }
+    nodes
+}
+
+fn text_of(node: NodeRef) -> Option<String> {
+    match &*node.node_type() {
+        NodeType::Text(TextNode { text, .. }) => Some(text.clone()),
+        _ => None,
+    }
+}
+
+/// True if the text of a text node can be selected by dragging over it. The text of editable elements is selected while they are edited instead.
+fn selectable(node: NodeRef) -> bool {
+    node.parent().is_some_and(|parent| {
+        parent
+            .get::<UserSelect>()
+            .is_some_and(|user_select| *user_select != UserSelect::None)
+            && editable_value(parent).is_none()
+    })
+}
+
+/// The selectable text position closest to a point in client coordinates: the closest text node along the y axis, then the closest text on its closest line along the x axis.
+fn position_at(rdom: &RealDom, taffy: &Taffy, point: Point) -> Option<TextPosition> {
+    let mut closest: Option<((f64, f64), TextPosition)> = None;
+    for id in text_nodes(rdom) {
+        let node = rdom.get(id)?;
+        if !selectable(node) {
+            continue;
+        }
+        let Some(layout) = node
+            .get::<TaffyLayout>()
+            .and_then(|layout| layout.node)
+            .and_then(|layout| taffy.layout(layout).ok())
+        else {
+            continue;
+        };
+        let Some(transform) = element_transform(rdom, taffy, id) else {
+            continue;
+        };
+        let local = transform.inverse() * point;
+        let distance_to = |value: f64, size: f32| {
+            if value < 0.0 {
+                -value
+            } else {
+                (value - size as f64).max(0.0)
+            }
+        };
+        let distance = (
+            distance_to(local.y, layout.size.height),
+            distance_to(local.x, layout.size.width),
+        );
+        if closest.is_some_and(|(closest, _)| closest <= distance) {
+            continue;
+        }
+        let text = text_of(node)?;
+        let shaped = shape_text(
+            &text,
+            &node.get::<TextStyle>().unwrap(),
+            Some(layout.size.width),
+        );
+        let offset = shaped.offset_at(epaint::pos2(local.x as f32, local.y as f32));
+        closest = Some((distance, (id, offset)));
+    }
+    closest.map(|(_, position)| position)
+}
+
+/// The range of each text node that a selection between two positions covers.
+fn selected_ranges(
+    rdom: &RealDom,
+    a: TextPosition,
+    b: TextPosition,
+) -> Vec<(NodeId, Range<usize>)> {
+    let nodes = text_nodes(rdom);
+    let index = |(node, _): TextPosition| nodes.iter().position(|id| *id == node);
+    let (Some(a_index), Some(b_index)) = (index(a), index(b)) else {
+        return Vec::new();
+    };
+    let (start, end) = if (a_index, a.1) <= (b_index, b.1) {
+        ((a_index, a.1), (b_index, b.1))
+    } else {
+        ((b_index, b.1), (a_index, a.1))
+    };
+    (start.0..=end.0)
+        .filter_map(|index| {
+            let node = rdom.get(nodes[index])?;
+            if !selectable(node) {
+                return None;
+            }
+            let len = text_of(node)?.len();
+            let from = if index == start.0 { start.1 } else { 0 };
+            let to = if index == end.0 { end.1 } else { len };
+            (from < to).then_some((nodes[index], from..to))
+        })
+        .collect()
+}
+
+/// Select the text between two positions, replacing the selection of the document. Returns true if the highlighted text changed.
+fn select(rdom: &mut RealDom, anchor: TextPosition, focus: TextPosition) -> bool {
+    let ranges = selected_ranges(rdom, anchor, focus);
+    let old = clear_highlights(rdom);
+    let changed = old.len() != ranges.len()
+        || old
+            .iter()
+            .zip(&ranges)
+            .any(|((old_node, old_range), (node, range))| old_node != node || old_range != range);
+    for (node, range) in &ranges {
+        rdom.get_mut(*node).unwrap().insert(Selected(range.clone()));
+    }
+    let root_id = rdom.root_id();
+    rdom.get_mut(root_id)
+        .unwrap()
+        .insert(Some(DocumentSelection {
+            anchor,
+            focus,
+            nodes: ranges.into_iter().map(|(node, _)| node).collect(),
+        }));
+    changed
+}
+
+/// Remove the highlights of the selection and return what they were.
+fn clear_highlights(rdom: &mut RealDom) -> Vec<(NodeId, Range<usize>)> {
+    let root_id = rdom.root_id();
+    let nodes = rdom
+        .get(root_id)
+        .and_then(|root| {
+            root.get::<Option<DocumentSelection>>()
+                .map(|selection| selection.clone())
+        })
+        .flatten()
+        .map(|selection| selection.nodes)
+        .unwrap_or_default();
+    let mut old = Vec::new();
+    for id in nodes {
+        let Some(mut node) = rdom.get_mut(id) else {
+            continue;
+        };
+        if let Some(selected) = node.get::<Selected>().map(|selected| selected.0.clone()) {
+            old.push((id, selected));
+        }
+        node.insert(Selected::default());
+    }
+    old
+}
+
+/// Deselect the text of the document. Returns true if text was selected.
+pub(crate) fn clear_selection(rdom: &mut RealDom) -> bool {
+    let old = clear_highlights(rdom);
+    let root_id = rdom.root_id();
+    rdom.get_mut(root_id)
+        .unwrap()
+        .insert(None::<DocumentSelection>);
+    !old.is_empty()
+}
+
+/// The selected text of the document, or `None` if nothing is selected. Text nodes that start on a lower line than the text before them are put on a new line.
+pub(crate) fn selected_text(rdom: &RealDom, taffy: &Taffy) -> Option<String> {
+    let selection = rdom
+        .get(rdom.root_id())?
+        .get::<Option<DocumentSelection>>()?
+        .clone()?;
+    let mut text = String::new();
+    let mut last_bottom: Option<f64> = None;
+    for (id, range) in selected_ranges(rdom, selection.anchor, selection.focus) {
+        let node = rdom.get(id)?;
+        let bounds = element_transform(rdom, taffy, id).zip(
+            node.get::<TaffyLayout>()
+                .and_then(|layout| layout.node)
+                .and_then(|layout| taffy.layout(layout).ok()),
+        );
+        if let Some((transform, layout)) = bounds {
+            let top = (transform * Point::ZERO).y;
+            if last_bottom.is_some_and(|bottom| top >= bottom - 1.0) {
+                text.push('\n');
+            }
+            last_bottom = Some(top + layout.size.height as f64);
+        }
+        text.push_str(&text_of(node)?[range]);
+    }
+    (!text.is_empty()).then_some(text)
+}
+
+/// The mouse dragging over text to select it.
+pub(crate) struct SelectionDrag {
+    anchor: TextPosition,
+    /// Where the drag started in client coordinates
+    start: Point,
+    /// Set once the mouse moved further than the drag threshold, so a click doesn't select anything
+    dragging: bool,
+}
+
+impl SelectionDrag {
+    /// Start selecting at the text closest to a point in client coordinates, if the document has selectable text.
+    pub fn start(rdom: &RealDom, taffy: &Taffy, point: Point) -> Option<Self> {
+        Some(Self {
+            anchor: position_at(rdom, taffy, point)?,
+            start: point,
+            dragging: false,
+        })
+    }
+
+    /// Select the text from where the drag started to the text closest to a point in client coordinates. Returns true if the highlighted text changed.
+    pub fn update(&mut self, rdom: &mut RealDom, taffy: &Taffy, point: Point) -> bool {
+        if !self.dragging && (point - self.start).hypot() <= system_settings().drag_threshold {
+            return false;
+        }
+        self.dragging = true;
+        match position_at(rdom, taffy, point) {
+            Some(focus) => select(rdom, self.anchor, focus),
+            None => false,
+        }
+    }
+
+    /// True if the mouse moved far enough to select text.
+    pub fn dragged(&self) -> bool {
+        self.dragging
+    }
+}
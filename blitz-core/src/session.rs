@@ -0,0 +1,220 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use dioxus_html::geometry::ClientPoint;
+use dioxus_html::input_data::keyboard_types::Key;
+use dioxus_native_core::prelude::*;
+use peniko::kurbo::Point;
+use taffy::Taffy;
+use tao::event_loop::EventLoopProxy;
+use tokio::sync::oneshot;
+
+use crate::coordinates::element_transform;
+use crate::events::{BlitzEventHandler, DomEvent};
+use crate::focus::FocusState;
+use crate::gestures::GestureConfig;
+use crate::layout::TaffyLayout;
+use crate::stable_id::{find_stable_id, is_stable_id};
+use crate::{Document, Redraw};
+
+/// Drives the window with synthetic mouse and keyboard input, for end-to-end tests of an application without OS-level automation.
+///
+/// Pass a clone to [`crate::Config::with_session`] and drive the application from a test with another one. The input is handled like input from the window between frames, so elements get the same events with the same bubbling, focus changes and gestures. Each method returns once the events were sent to the DOM, the application may still be handling them.
+///
+/// Elements are found by where they were drawn in the last frame, so wait for the document to be drawn before clicking it, for example with [`crate::Screenshots::capture`]. Drive a headless [`Document`] with a [`HeadlessSession`] instead.
+#[derive(Clone, Default)]
+pub struct Session {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    pending: Vec<(SyntheticInput, oneshot::Sender<io::Result<()>>)>,
+    /// Wakes up the window so the input is handled even if nothing else happens, set once the window is opened
+    proxy: Option<EventLoopProxy<Redraw>>,
+}
+
+/// Input from a [`Session`]
+pub(crate) enum SyntheticInput {
+    /// Click the center of the first element that matches a selector
+    Click(String),
+    ClickAt(ClientPoint),
+    /// Press and release a key
+    Key(Key),
+    /// Type each character of the text into the focused element
+    Text(String),
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the mouse to the center of the first element that matches a selector and click it with the primary button.
+    ///
+    /// Selectors are a tag name, ids and classes like `button`, `#submit` or `li.item.selected`, or a [`crate::stable_id`] like `#menu/li[2]`. Returns an error if no element matches.
+    pub async fn click(&self, selector: impl Into<String>) -> io::Result<()> {
+        self.send(SyntheticInput::Click(selector.into())).await
+    }
+
+    /// Move the mouse to a point in client coordinates and click it with the primary button.
+    pub async fn click_at(&self, point: ClientPoint) -> io::Result<()> {
+        self.send(SyntheticInput::ClickAt(point)).await
+    }
+
+    /// Press and release a key over the focused element.
+    pub async fn press_key(&self, key: Key) -> io::Result<()> {
+        self.send(SyntheticInput::Key(key)).await
+    }
+
+    /// Type text into the focused element one character at a time. Each character sends key events, and an input event with the new value if the element is editable.
+    pub async fn type_text(&self, text: impl Into<String>) -> io::Result<()> {
+        self.send(SyntheticInput::Text(text.into())).await
+    }
+
+    async fn send(&self, input: SyntheticInput) -> io::Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.pending.push((input, sender));
+            if let Some(proxy) = &inner.proxy {
+                let _ = proxy.send_event(Redraw);
+            }
+        }
+        receiver.await.map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the window was closed before the input was handled",
+            )
+        })?
+    }
+
+    pub(crate) fn attach(&self, proxy: EventLoopProxy<Redraw>) {
+        self.inner.lock().unwrap().proxy = Some(proxy);
+    }
+
+    /// The input that is waiting to be handled, with the senders the results are sent back with
+    pub(crate) fn take_pending(&self) -> Vec<(SyntheticInput, oneshot::Sender<io::Result<()>>)> {
+        std::mem::take(&mut self.inner.lock().unwrap().pending)
+    }
+}
+
+/// Drives a headless [`Document`] with synthetic input like [`Session`] drives a window, for tests that run without a window. The mouse, the modifier keys and focus are kept between inputs.
+///
+/// The input is handled in the layout of the last call to [`Document::resolve`]. Each method returns the events the elements get, which the driver of the document handles, for example by sending them to a Dioxus VirtualDom.
+pub struct HeadlessSession {
+    events: BlitzEventHandler,
+}
+
+impl HeadlessSession {
+    pub fn new(document: &mut Document) -> Self {
+        let focus_state = FocusState::create(document.real_dom_mut());
+        Self {
+            events: BlitzEventHandler::new(
+                focus_state,
+                GestureConfig::default(),
+                document.plugins(),
+            ),
+        }
+    }
+
+    /// Click the center of the first element that matches a selector, see [`Session::click`].
+    pub fn click(&mut self, document: &mut Document, selector: &str) -> io::Result<Vec<DomEvent>> {
+        document.synthesize(
+            &mut self.events,
+            SyntheticInput::Click(selector.to_string()),
+        )
+    }
+
+    /// Click a point in client coordinates.
+    pub fn click_at(&mut self, document: &mut Document, point: ClientPoint) -> Vec<DomEvent> {
+        document
+            .synthesize(&mut self.events, SyntheticInput::ClickAt(point))
+            .unwrap_or_default()
+    }
+
+    /// Press and release a key over the focused element.
+    pub fn press_key(&mut self, document: &mut Document, key: Key) -> Vec<DomEvent> {
+        document
+            .synthesize(&mut self.events, SyntheticInput::Key(key))
+            .unwrap_or_default()
+    }
+
+    /// Type text into the focused element, see [`Session::type_text`].
+    pub fn type_text(&mut self, document: &mut Document, text: &str) -> Vec<DomEvent> {
+        document
+            .synthesize(&mut self.events, SyntheticInput::Text(text.to_string()))
+            .unwrap_or_default()
+    }
+
+    /// The element that has focus
+    pub fn focused(&self) -> Option<NodeId> {
+        self.events.focused()
+    }
+}
+
+/// The first element in document order that matches a compound selector of a tag name, ids and classes, or the element with a stable id, see [`crate::stable_id`].
+pub(crate) fn query_selector(rdom: &RealDom, selector: &str) -> Option<NodeId> {
+    if is_stable_id(selector) {
+        return find_stable_id(rdom, selector);
+    }
+    let mut stack = vec![rdom.get(rdom.root_id())?];
+    while let Some(node) = stack.pop() {
+        if let NodeType::Element(element) = &*node.node_type() {
+            if matches_selector(element, selector) {
+                return Some(node.id());
+            }
+        }
+        stack.extend(node.children().into_iter().rev());
+    }
+    None
+}
+
+fn matches_selector(element: &ElementNode, selector: &str) -> bool {
+    let attribute = |name: &str| {
+        element
+            .attributes
+            .iter()
+            .find(|(attribute, _)| attribute.name == name && attribute.namespace.is_none())
+            .and_then(|(_, value)| value.as_text())
+    };
+    // The tag comes first, then each id and class starts with its prefix
+    let tag_end = selector.find(['#', '.']).unwrap_or(selector.len());
+    let tag = &selector[..tag_end];
+    if !tag.is_empty() && tag != "*" && !element.tag.eq_ignore_ascii_case(tag) {
+        return false;
+    }
+    let mut rest = &selector[tag_end..];
+    while let Some(prefix) = rest.chars().next() {
+        let end = rest[1..].find(['#', '.']).map_or(rest.len(), |end| end + 1);
+        let name = &rest[1..end];
+        let matches = match prefix {
+            '#' => attribute("id") == Some(name),
+            _ => {
+                attribute("class").is_some_and(|class| class.split_whitespace().any(|c| c == name))
+            }
+        };
+        if !matches {
+            return false;
+        }
+        rest = &rest[end..];
+    }
+    true
+}
+
+/// The center of the border box of an element in client coordinates, or `None` if it hasn't been laid out yet.
+pub(crate) fn element_center(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    element: NodeId,
+) -> Option<ClientPoint> {
+    let layout = taffy
+        .layout(rdom.get(element)?.get::<TaffyLayout>()?.node?)
+        .ok()?;
+    let center = element_transform(rdom, taffy, element)?
+        * Point::new(
+            layout.size.width as f64 / 2.0,
+            layout.size.height as f64 / 2.0,
+        );
+    Some(ClientPoint::new(center.x, center.y))
+}
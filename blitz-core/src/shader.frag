@@ -7,4 +7,42 @@ layout(location = 1) in vec2 inUV;
 
 layout(location = 0) out vec4 outColor;
 
-void main() { outColor = inColor; }
\ No newline at end of file
+// Holds the glyphs of text and images. Shapes without a texture sample the white texels at uv 0, 0
+layout(set = 0, binding = 0) uniform texture2D atlas;
+layout(set = 0, binding = 1) uniform sampler atlasSampler;
+
+layout(push_constant) uniform PushConstants {
+  vec4 clip_rect;
+  vec2 screen_size;
+  vec2 translation;
+  // The columns of the linear part of the transform, see DrawConstants
+  vec4 matrix;
+  uint encode_srgb;
+} pushConstants;
+
+// 0-1 sRGB  from  0-1 linear
+vec3 srgb_from_linear(vec3 linear) {
+    bvec3 cutoff = lessThan(linear, vec3(0.0031308));
+    vec3 lower = linear * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(linear, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, cutoff);
+}
+
+// Encode a premultiplied linear color for a target that doesn't encode it. Colors are blended after they are encoded, like in a browser without linear blending
+vec4 encode_output(vec4 color) {
+  if (pushConstants.encode_srgb == 0u || color.a <= 0.0) {
+    return color;
+  }
+  return vec4(srgb_from_linear(color.rgb / color.a) * color.a, color.a);
+}
+
+void main() {
+  // Textures can only be sampled before the first discard, where control flow is still uniform
+  vec4 texel = texture(sampler2D(atlas, atlasSampler), inUV);
+  // The clip rect is in window coordinates, so it still clips correctly after the transform is applied
+  if (any(lessThan(gl_FragCoord.xy, pushConstants.clip_rect.xy)) ||
+      any(greaterThanEqual(gl_FragCoord.xy, pushConstants.clip_rect.zw))) {
+    discard;
+  }
+  outColor = encode_output(inColor * texel);
+}
\ No newline at end of file
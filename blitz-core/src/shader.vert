@@ -9,7 +9,14 @@ layout(location = 2) in vec4 color;
 layout(location = 0) out vec4 outColor;
 layout(location = 1) out vec2 outUV;
 
-layout(push_constant) uniform PushConstants { vec2 screen_size; } pushConstants;
+layout(push_constant) uniform PushConstants {
+  vec4 clip_rect;
+  vec2 screen_size;
+  vec2 translation;
+  // The columns of the linear part of the transform, see DrawConstants
+  vec4 matrix;
+  uint encode_srgb;
+} pushConstants;
 
 // 0-1 linear  from  0-255 sRGB
 vec3 linear_from_srgb(vec3 srgb) {
@@ -24,9 +31,10 @@ vec4 linear_from_srgba(vec4 srgba) {
 }
 
 void main() {
-gl_Position =
-      vec4(2.0 * pos.x / pushConstants.screen_size.x - 1.0,
-           2.0 * pos.y / pushConstants.screen_size.y - 1.0, 0.0, 1.0);
+  vec2 transformed = mat2(pushConstants.matrix) * pos + pushConstants.translation;
+  gl_Position =
+      vec4(2.0 * transformed.x / pushConstants.screen_size.x - 1.0,
+           2.0 * transformed.y / pushConstants.screen_size.y - 1.0, 0.0, 1.0);
   outColor = linear_from_srgba(color);
   outUV = uv;
 }
\ No newline at end of file
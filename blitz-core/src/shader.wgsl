@@ -0,0 +1,78 @@
+// The wgpu version of shader.vert, shader.frag and coverage.frag
+
+struct Constants {
+    clip_rect: vec4<f32>,
+    screen_size: vec2<f32>,
+    translation: vec2<f32>,
+    matrix: mat2x2<f32>,
+    // Always 0, the frame is drawn through an sRGB view
+    encode_srgb: u32,
+    white_level: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> constants: Constants;
+
+// Holds the glyphs of text. Shapes without a texture sample the white texels at uv 0, 0
+@group(1) @binding(0)
+var atlas: texture_2d<f32>;
+@group(1) @binding(1)
+var atlas_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+};
+
+// 0-1 linear  from  0-255 sRGB
+fn linear_from_srgb(srgb: vec3<f32>) -> vec3<f32> {
+    let cutoff = srgb < vec3<f32>(10.31475);
+    let lower = srgb / vec3<f32>(3294.6);
+    let higher = pow((srgb + vec3<f32>(14.025)) / vec3<f32>(269.025), vec3<f32>(2.4));
+    return select(higher, lower, cutoff);
+}
+
+@vertex
+fn vs_main(
+    @location(0) pos: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+) -> VertexOutput {
+    let transformed = constants.matrix * pos + constants.translation;
+    var out: VertexOutput;
+    // Clip space points up in wgpu, unlike in Vulkan
+    out.position = vec4<f32>(
+        2.0 * transformed.x / constants.screen_size.x - 1.0,
+        1.0 - 2.0 * transformed.y / constants.screen_size.y,
+        0.0,
+        1.0,
+    );
+    out.color = vec4<f32>(linear_from_srgb(color.rgb * 255.0), color.a);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Textures can only be sampled before the first discard, where control flow is still uniform
+    let texel = textureSample(atlas, atlas_sampler, in.uv);
+    // The clip rect is in window coordinates, so it still clips correctly after the transform is applied
+    if any(in.position.xy < constants.clip_rect.xy) || any(in.position.xy >= constants.clip_rect.zw) {
+        discard;
+    }
+    let color = in.color * texel;
+    // Premultiplied colors stay premultiplied when only the color channels are scaled
+    return vec4<f32>(color.rgb * constants.white_level, color.a);
+}
+
+// The first draw of subpixel text, whose texels hold the coverage of each color channel. The blend state multiplies the pixels below by the inverse of the output, then fs_main adds the color of the text
+@fragment
+fn fs_coverage(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(atlas, atlas_sampler, in.uv);
+    if any(in.position.xy < constants.clip_rect.xy) || any(in.position.xy >= constants.clip_rect.zw) {
+        discard;
+    }
+    // The coverage is a blend factor, so it isn't scaled by the white level
+    return texel * in.color.a;
+}
@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use tao::event_loop::EventLoopProxy;
+
+use crate::Redraw;
+
+/// How often the shader files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Watches `shader.vert` and `shader.frag` in a directory and asks for a redraw when either changes, so the pipeline can be rebuilt while the application runs.
+pub(crate) struct ShaderWatcher {
+    dir: PathBuf,
+    changed: Arc<AtomicBool>,
+}
+
+impl ShaderWatcher {
+    pub fn spawn(dir: PathBuf, proxy: EventLoopProxy<Redraw>) -> Self {
+        // Load the shaders from disk once at startup, so edits made before launching are picked up
+        let changed = Arc::new(AtomicBool::new(true));
+        std::thread::Builder::new()
+            .name("shader watcher".to_string())
+            .spawn({
+                let dir = dir.clone();
+                let changed = changed.clone();
+                move || {
+                    let mut last_modified = modified(&dir);
+                    loop {
+                        std::thread::sleep(POLL_INTERVAL);
+                        let modified = modified(&dir);
+                        if modified != last_modified {
+                            last_modified = modified;
+                            changed.store(true, Ordering::Release);
+                            // The event loop is gone once the window is closed
+                            if proxy.send_event(Redraw).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+            })
+            .unwrap();
+        Self { dir, changed }
+    }
+
+    /// Read the shader sources if they changed since the last call. Returns the vertex and fragment shader.
+    pub fn take_changed(&self) -> Option<(String, String)> {
+        if !self.changed.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+        let read = |name: &str| {
+            let path = self.dir.join(name);
+            std::fs::read_to_string(&path)
+                .map_err(|err| log::error!("failed to read the shader {}: {err}", path.display()))
+                .ok()
+        };
+        Some((read("shader.vert")?, read("shader.frag")?))
+    }
+}
+
+/// The modification times of the shaders. A file that can't be read has no time, so it counts as changed once it appears again.
+fn modified(dir: &Path) -> [Option<SystemTime>; 2] {
+    ["shader.vert", "shader.frag"].map(|name| {
+        std::fs::metadata(dir.join(name))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    })
+}
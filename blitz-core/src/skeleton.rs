@@ -0,0 +1,175 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use epaint::{Color32, Mesh, Pos2, Rect};
+
+use crate::path::parse_color;
+use crate::plugin::{CustomElement, ElementPlugin, Painter};
+
+/// How long the highlight takes to sweep over a block
+const SWEEP_DURATION: Duration = Duration::from_millis(1500);
+/// How long the window keeps drawing frames after a skeleton was last drawn. The plugin doesn't know when its elements are removed, so it animates until it stops being drawn
+const ANIMATION_LINGER: Duration = Duration::from_secs(1);
+/// The color of the blocks and of the highlight that sweeps over them, unless the element sets `color` and `highlight-color`
+const BASE_COLOR: Color32 = Color32::from_rgb(226, 228, 232);
+const HIGHLIGHT_COLOR: Color32 = Color32::from_rgb(243, 244, 246);
+/// How many segments each rounded corner is drawn with
+const CORNER_SEGMENTS: usize = 8;
+
+/// Draws `skeleton` elements, placeholders that stand in for content while it loads. It is registered by default.
+///
+/// The element is drawn as a rounded block that fills its content box, with a highlight that sweeps over it from left to right. `shape="circle"` draws a circle instead, for avatars. `lines` draws that many lines of text, with the last one shorter than the rest, and `radius` sets the corner radius in pixels. The colors of the blocks and the highlight are set with `color` and `highlight-color`.
+///
+/// Every skeleton sweeps in step, so a loading page shimmers as a whole. The element is as large as its styles make it.
+pub(crate) struct SkeletonPlugin {
+    /// When the sweep started, which all elements share
+    epoch: Instant,
+    /// When a skeleton was last drawn
+    last_painted: Mutex<Option<Instant>>,
+}
+
+impl Default for SkeletonPlugin {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+            last_painted: Mutex::new(None),
+        }
+    }
+}
+
+impl ElementPlugin for SkeletonPlugin {
+    fn tag(&self) -> &str {
+        "skeleton"
+    }
+
+    fn paint(&self, element: &CustomElement, content_box: Rect, painter: &mut Painter) {
+        let now = Instant::now();
+        *self.last_painted.lock().unwrap() = Some(now);
+        if content_box.width() <= 0.0 || content_box.height() <= 0.0 {
+            return;
+        }
+        let colors = (
+            parse_color(element.attribute("color"), BASE_COLOR),
+            parse_color(element.attribute("highlight-color"), HIGHLIGHT_COLOR),
+        );
+        let phase = (now - self.epoch).as_secs_f32() / SWEEP_DURATION.as_secs_f32();
+        let phase = phase.fract();
+        let radius = element
+            .attribute("radius")
+            .and_then(|radius| radius.trim().trim_end_matches("px").parse::<f32>().ok())
+            .unwrap_or(4.0);
+        if element.attribute("shape").map(str::trim) == Some("circle") {
+            let size = content_box.width().min(content_box.height());
+            let block = Rect::from_center_size(content_box.center(), epaint::vec2(size, size));
+            painter.add(shimmer_mesh(block, size / 2.0, phase, colors));
+            return;
+        }
+        let lines = element
+            .attribute("lines")
+            .and_then(|lines| lines.trim().parse::<usize>().ok())
+            .unwrap_or(1)
+            .max(1);
+        // Each line is a block in the middle of its share of the height, with a gap between the lines like between lines of text
+        let line_height = content_box.height() / lines as f32;
+        let block_height = if lines == 1 {
+            line_height
+        } else {
+            line_height * 0.6
+        };
+        for line in 0..lines {
+            let width = if lines > 1 && line == lines - 1 {
+                content_box.width() * 0.6
+            } else {
+                content_box.width()
+            };
+            let top =
+                content_box.min.y + line_height * line as f32 + (line_height - block_height) / 2.0;
+            let block = Rect::from_min_size(
+                epaint::pos2(content_box.min.x, top),
+                epaint::vec2(width, block_height),
+            );
+            painter.add(shimmer_mesh(block, radius, phase, colors));
+        }
+    }
+
+    fn animating(&self) -> bool {
+        self.last_painted
+            .lock()
+            .unwrap()
+            .is_some_and(|last_painted| last_painted.elapsed() < ANIMATION_LINGER)
+    }
+}
+
+/// A rounded block with a highlight across it at `phase` of its sweep, from left of the block at 0 to right of it at 1.
+///
+/// The block is cut into vertical strips with a vertex at each edge of the highlight and along the corners, so the colors of the vertices interpolate into a linear gradient.
+fn shimmer_mesh(
+    block: Rect,
+    radius: f32,
+    phase: f32,
+    (base, highlight): (Color32, Color32),
+) -> Mesh {
+    let radius = radius.clamp(0.0, block.width().min(block.height()) / 2.0);
+    let band = (block.width() * 0.4).max(40.0);
+    let center = block.min.x - band + (block.width() + band * 2.0) * phase;
+    let color_at = |x: f32| {
+        let t = 1.0 - ((x - center).abs() / band).min(1.0);
+        // Smoothstep, so the highlight fades in and out without a visible edge
+        let t = t * t * (3.0 - 2.0 * t);
+        lerp_color(base, highlight, t)
+    };
+    // How far the top and bottom edge are inset at a distance into a corner
+    let inset = |x: f32| {
+        let into_corner = (block.min.x + radius - x).max(x - (block.max.x - radius));
+        if into_corner <= 0.0 {
+            0.0
+        } else {
+            radius
+                - (radius * radius - into_corner * into_corner)
+                    .max(0.0)
+                    .sqrt()
+        }
+    };
+
+    let mut xs = vec![
+        block.min.x,
+        block.max.x,
+        center - band,
+        center,
+        center + band,
+    ];
+    for segment in 1..CORNER_SEGMENTS {
+        // The positions along the x axis of the points of a quarter circle
+        let angle = std::f32::consts::FRAC_PI_2 * segment as f32 / CORNER_SEGMENTS as f32;
+        let offset = radius * (1.0 - angle.cos());
+        xs.push(block.min.x + offset);
+        xs.push(block.max.x - offset);
+    }
+    xs.retain(|x| (block.min.x..=block.max.x).contains(x));
+    xs.sort_by(f32::total_cmp);
+    xs.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+
+    let mut mesh = Mesh::default();
+    for (index, x) in xs.iter().enumerate() {
+        let inset = inset(*x);
+        let color = color_at(*x);
+        mesh.colored_vertex(Pos2::new(*x, block.min.y + inset), color);
+        mesh.colored_vertex(Pos2::new(*x, block.max.y - inset), color);
+        if index > 0 {
+            let i = index as u32 * 2;
+            mesh.add_triangle(i - 2, i - 1, i);
+            mesh.add_triangle(i - 1, i + 1, i);
+        }
+    }
+    mesh
+}
+
+fn lerp_color(a: Color32, b: Color32, t: f32) -> Color32 {
+    let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color32::from_rgba_premultiplied(
+        lerp(a.r(), b.r()),
+        lerp(a.g(), b.g()),
+        lerp(a.b(), b.b()),
+        lerp(a.a(), b.a()),
+    )
+}
@@ -0,0 +1,253 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core::FxHashMap;
+use peniko::kurbo::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::focus::Focused;
+use crate::scroll::ScrollOffset;
+
+/// The elements of a document saved to disk, so the last UI of an application can be shown on the next launch while the VirtualDom is rebuilt.
+///
+/// Elements are found by paths of child indices from the root. Placeholders aren't saved, so they aren't counted in the paths either, see [`snapshot_children`].
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Snapshot {
+    /// The children of the root of the document
+    nodes: Vec<SnapshotNode>,
+    /// The path to the focused element
+    focused: Option<Vec<usize>>,
+    /// The paths to the scroll containers that were scrolled and their offsets
+    #[serde(default)]
+    scrolled: Vec<(Vec<usize>, [f64; 2])>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SnapshotNode {
+    Element {
+        tag: String,
+        namespace: Option<String>,
+        attributes: Vec<SnapshotAttribute>,
+        children: Vec<SnapshotNode>,
+    },
+    Text(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotAttribute {
+    name: String,
+    namespace: Option<String>,
+    value: SnapshotValue,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SnapshotValue {
+    Text(String),
+    Float(f64),
+    Int(i64),
+    Bool(bool),
+}
+
+/// The nodes created from a snapshot.
+pub(crate) struct Restored {
+    /// The restored children of the root. They are removed once the VirtualDom has been rebuilt
+    pub nodes: Vec<NodeId>,
+    /// The path to the element that was focused, to focus the same element in the rebuilt document
+    pub focused: Option<Vec<usize>>,
+    /// The paths to the scroll containers that were scrolled, to scroll the same elements in the rebuilt document
+    pub scrolled: Vec<(Vec<usize>, Vec2)>,
+}
+
+impl Snapshot {
+    pub fn capture(rdom: &RealDom) -> Self {
+        let mut captured = Captured::default();
+        let mut path = Vec::new();
+        let nodes = snapshot_children(&rdom.get(rdom.root_id()).unwrap())
+            .enumerate()
+            .filter_map(|(index, child)| {
+                path.push(index);
+                let node = capture_node(child, &mut path, &mut captured);
+                path.pop();
+                node
+            })
+            .collect();
+        Snapshot {
+            nodes,
+            focused: captured.focused,
+            scrolled: captured.scrolled,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(serde_json::to_writer(
+            BufWriter::new(File::create(path)?),
+            self,
+        )?)
+    }
+
+    /// Add the saved elements to the root of the document.
+    pub fn restore(self, rdom: &mut RealDom) -> Restored {
+        let root_id = rdom.root_id();
+        let nodes: Vec<_> = self
+            .nodes
+            .into_iter()
+            .map(|node| restore_node(rdom, node, root_id))
+            .collect();
+        // Show the focus and the scroll offsets of the last session until the document is rebuilt
+        if let Some(id) = self.focused.as_deref().and_then(|path| node_at(rdom, path)) {
+            rdom.get_mut(id).unwrap().insert(Focused(true));
+        }
+        let scrolled: Vec<_> = self
+            .scrolled
+            .into_iter()
+            .map(|(path, [x, y])| (path, Vec2::new(x, y)))
+            .collect();
+        for (path, offset) in &scrolled {
+            if let Some(id) = node_at(rdom, path) {
+                rdom.get_mut(id).unwrap().insert(ScrollOffset(*offset));
+            }
+        }
+        Restored {
+            nodes,
+            focused: self.focused,
+            scrolled,
+        }
+    }
+}
+
+/// The state of the elements on the paths to them, collected while the nodes are captured
+#[derive(Default)]
+struct Captured {
+    focused: Option<Vec<usize>>,
+    scrolled: Vec<(Vec<usize>, [f64; 2])>,
+}
+
+/// The children of a node that are saved. The indices of paths count only these, so a path finds the same element in the restored snapshot and in the rebuilt document with its placeholders.
+fn snapshot_children<'a>(node: &'a NodeRef) -> impl Iterator<Item = NodeRef<'a>> {
+    node.children()
+        .into_iter()
+        .filter(|child| !matches!(*child.node_type(), NodeType::Placeholder))
+}
+
+/// Find the element at a path of child indices from the root, see [`snapshot_children`].
+pub(crate) fn node_at(rdom: &RealDom, path: &[usize]) -> Option<NodeId> {
+    path.iter().try_fold(rdom.root_id(), |id, index| {
+        snapshot_children(&rdom.get(id)?)
+            .nth(*index)
+            .map(|child| child.id())
+    })
+}
+
+fn capture_node(
+    node: NodeRef,
+    path: &mut Vec<usize>,
+    captured: &mut Captured,
+) -> Option<SnapshotNode> {
+    if node.get::<Focused>().filter(|focused| focused.0).is_some() {
+        captured.focused = Some(path.clone());
+    }
+    if let Some(offset) = node
+        .get::<ScrollOffset>()
+        .filter(|offset| offset.0 != Vec2::ZERO)
+    {
+        captured
+            .scrolled
+            .push((path.clone(), [offset.0.x, offset.0.y]));
+    }
+    match &*node.node_type() {
+        NodeType::Element(element) => {
+            let attributes = element
+                .attributes
+                .iter()
+                .filter_map(|(attribute, value)| {
+                    let value = match value {
+                        OwnedAttributeValue::Text(text) => SnapshotValue::Text(text.clone()),
+                        OwnedAttributeValue::Float(float) => SnapshotValue::Float(*float),
+                        OwnedAttributeValue::Int(int) => SnapshotValue::Int(*int),
+                        OwnedAttributeValue::Bool(bool) => SnapshotValue::Bool(*bool),
+                        // Custom values can't be serialized
+                        _ => return None,
+                    };
+                    Some(SnapshotAttribute {
+                        name: attribute.name.clone(),
+                        namespace: attribute.namespace.clone(),
+                        value,
+                    })
+                })
+                .collect();
+            let children = snapshot_children(&node)
+                .enumerate()
+                .filter_map(|(index, child)| {
+                    path.push(index);
+                    let child = capture_node(child, path, captured);
+                    path.pop();
+                    child
+                })
+                .collect();
+            Some(SnapshotNode::Element {
+                tag: element.tag.clone(),
+                namespace: element.namespace.clone(),
+                attributes,
+                children,
+            })
+        }
+        NodeType::Text(text) => Some(SnapshotNode::Text(text.text.clone())),
+        // Left out by snapshot_children
+        NodeType::Placeholder => None,
+    }
+}
+
+fn restore_node(rdom: &mut RealDom, node: SnapshotNode, parent: NodeId) -> NodeId {
+    let id = match node {
+        SnapshotNode::Element {
+            tag,
+            namespace,
+            attributes,
+            children,
+        } => {
+            let attributes: FxHashMap<_, _> = attributes
+                .into_iter()
+                .map(|attribute| {
+                    let value = match attribute.value {
+                        SnapshotValue::Text(text) => OwnedAttributeValue::Text(text),
+                        SnapshotValue::Float(float) => OwnedAttributeValue::Float(float),
+                        SnapshotValue::Int(int) => OwnedAttributeValue::Int(int),
+                        SnapshotValue::Bool(bool) => OwnedAttributeValue::Bool(bool),
+                    };
+                    (
+                        OwnedAttributeDiscription {
+                            name: attribute.name,
+                            namespace: attribute.namespace,
+                        },
+                        value,
+                    )
+                })
+                .collect();
+            let id = rdom
+                .create_node(NodeType::Element(ElementNode {
+                    tag,
+                    namespace,
+                    attributes,
+                    // The restored elements only show the last UI, they don't handle events
+                    listeners: Default::default(),
+                }))
+                .id();
+            for child in children {
+                restore_node(rdom, child, id);
+            }
+            id
+        }
+        SnapshotNode::Text(text) => rdom.create_node(NodeType::Text(TextNode::new(text))).id(),
+    };
+    rdom.get_mut(parent).unwrap().add_child(id);
+    id
+}
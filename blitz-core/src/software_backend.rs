@@ -0,0 +1,219 @@
+use std::io;
+use std::num::NonZeroU32;
+
+use epaint::{Color32, ColorImage, ImageDelta, Mesh, Rect};
+use peniko::kurbo::Affine;
+use rustc_hash::FxHashMap;
+use tao::{dpi::PhysicalSize, window::Window};
+
+use crate::backend::{Backend, ColorMatrix, RectInstance};
+use crate::raster::{update_texture, Target};
+
+/// A backend that draws on the CPU and copies the frames into the window, so documents can be shown on machines without a GPU, like CI runners and virtual machines. Blitz falls back to it when the default backend can't be created.
+///
+/// The last frame is kept, so only the damaged area of the window is drawn again. Rects are filled by their signed distance like on the GPU, so only the other shapes are tessellated into triangles. Content that moves or scrolls without changing is kept in textures, so it is only drawn again when it changes.
+///
+/// ```no_run
+/// # use blitz_core::{Config, SoftwareBackend};
+/// let cfg = Config::new().with_backend(|window| SoftwareBackend::new(window).unwrap());
+/// ```
+pub struct SoftwareBackend {
+    // The context has to outlive the surface
+    surface: softbuffer::Surface,
+    _context: softbuffer::Context,
+    size: PhysicalSize<u32>,
+    target: Target,
+    /// The targets filtered layers are drawn over once they are finished, from the outermost to the innermost
+    parents: Vec<Target>,
+    /// Set when the target doesn't contain the last frame, so the next frame is drawn everywhere
+    invalidated: bool,
+    /// The area drawn in the frame that is being drawn
+    damage: Rect,
+    texture: ColorImage,
+    /// The textures of cached layers by id
+    cached_layers: FxHashMap<u64, ColorImage>,
+    /// The cached layer that is being drawn, with the target and damaged area of the frame
+    drawing_layer: Option<(u64, Target, Rect)>,
+}
+
+impl SoftwareBackend {
+    pub fn new(window: &Window) -> io::Result<Self> {
+        let context = unsafe { softbuffer::Context::new(window) }.map_err(io::Error::other)?;
+        let surface =
+            unsafe { softbuffer::Surface::new(&context, window) }.map_err(io::Error::other)?;
+        let mut backend = Self {
+            surface,
+            _context: context,
+            size: PhysicalSize::new(0, 0),
+            target: Target::new(0, 0, Color32::TRANSPARENT),
+            parents: Vec::new(),
+            invalidated: true,
+            damage: Rect::NOTHING,
+            texture: ColorImage::new([1, 1], Color32::WHITE),
+            cached_layers: FxHashMap::default(),
+            drawing_layer: None,
+        };
+        backend.resize(window.inner_size());
+        Ok(backend)
+    }
+}
+
+impl Backend for SoftwareBackend {
+    fn adapter_name(&self) -> String {
+        "software".to_string()
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+        else {
+            return;
+        };
+        if let Err(err) = self.surface.resize(width, height) {
+            log::error!("failed to resize the window surface: {err}");
+        }
+        self.size = size;
+        self.target = Target::new(size.width, size.height, Color32::TRANSPARENT);
+        self.invalidated = true;
+    }
+
+    fn update_texture(&mut self, delta: &ImageDelta) {
+        update_texture(&mut self.texture, delta);
+    }
+
+    fn begin_frame(&mut self, clear_color: Color32, damage: Rect) {
+        self.damage = if std::mem::take(&mut self.invalidated) {
+            Rect::from_min_size(
+                epaint::Pos2::ZERO,
+                epaint::vec2(self.size.width as f32, self.size.height as f32),
+            )
+        } else {
+            damage
+        };
+        self.target.clear_rect(self.damage, clear_color);
+    }
+
+    fn draw_mesh(&mut self, mesh: &Mesh, transform: Affine, clip: Rect, opacity: f32) {
+        let clip = clip.intersect(self.damage);
+        if !clip.is_positive() {
+            return;
+        }
+        self.target
+            .draw_mesh(mesh.clone(), transform, clip, 1.0, opacity, &self.texture);
+    }
+
+    fn draws_subpixel_text(&self) -> bool {
+        true
+    }
+
+    fn draw_subpixel_text(&mut self, mesh: &Mesh, transform: Affine, clip: Rect, opacity: f32) {
+        let clip = clip.intersect(self.damage);
+        if !clip.is_positive() {
+            return;
+        }
+        self.target
+            .draw_subpixel_text(mesh.clone(), transform, clip, opacity, &self.texture);
+    }
+
+    fn draws_rects(&self) -> bool {
+        true
+    }
+
+    fn draw_rects(&mut self, rects: &[RectInstance], transform: Affine, clip: Rect, opacity: f32) {
+        let clip = clip.intersect(self.damage);
+        if !clip.is_positive() {
+            return;
+        }
+        self.target.draw_rects(rects, transform, clip, opacity);
+    }
+
+    fn draws_filters(&self) -> bool {
+        true
+    }
+
+    fn begin_filter(&mut self) {
+        let [width, height] = self.target.size();
+        let layer = Target::transparent(width, height);
+        self.parents
+            .push(std::mem::replace(&mut self.target, layer));
+    }
+
+    fn end_filter(&mut self, matrix: &ColorMatrix, opacity: f32) {
+        let Some(parent) = self.parents.pop() else {
+            return;
+        };
+        let layer = std::mem::replace(&mut self.target, parent);
+        self.target.composite(&layer, opacity, matrix);
+    }
+
+    fn blur_backdrop(&mut self, area: Rect, radius: f32) {
+        self.target.blur(area.intersect(self.damage), radius);
+    }
+
+    fn caches_layers(&self) -> bool {
+        true
+    }
+
+    fn begin_cached_layer(&mut self, id: u64, size: [u32; 2]) {
+        let layer = Target::transparent(size[0], size[1]);
+        let frame = std::mem::replace(&mut self.target, layer);
+        // Everything in the layer is drawn
+        let damage = std::mem::replace(
+            &mut self.damage,
+            Rect::from_min_size(
+                epaint::Pos2::ZERO,
+                epaint::vec2(size[0] as f32, size[1] as f32),
+            ),
+        );
+        self.drawing_layer = Some((id, frame, damage));
+    }
+
+    fn end_cached_layer(&mut self) {
+        let Some((id, frame, damage)) = self.drawing_layer.take() else {
+            return;
+        };
+        let layer = std::mem::replace(&mut self.target, frame);
+        self.damage = damage;
+        self.cached_layers.insert(id, layer.to_image());
+    }
+
+    fn draw_cached_layer(
+        &mut self,
+        id: u64,
+        mesh: &Mesh,
+        transform: Affine,
+        clip: Rect,
+        opacity: f32,
+    ) {
+        let clip = clip.intersect(self.damage);
+        let Some(texture) = self.cached_layers.get(&id).filter(|_| clip.is_positive()) else {
+            return;
+        };
+        self.target
+            .draw_mesh(mesh.clone(), transform, clip, 1.0, opacity, texture);
+    }
+
+    fn release_cached_layer(&mut self, id: u64) {
+        self.cached_layers.remove(&id);
+    }
+
+    fn end_frame(&mut self) {
+        let mut buffer = match self.surface.buffer_mut() {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                log::warn!("skipping a frame: {err}");
+                return;
+            }
+        };
+        // The window is opaque, so the premultiplied colors are the colors over black
+        for y in 0..self.size.height {
+            for x in 0..self.size.width {
+                let [r, g, b, _] = self.target.pixel(x, y).to_array();
+                buffer[(y * self.size.width + x) as usize] = u32::from_be_bytes([0, r, g, b]);
+            }
+        }
+        if let Err(err) = buffer.present() {
+            log::warn!("failed to present a frame: {err}");
+        }
+    }
+}
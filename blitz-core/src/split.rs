@@ -0,0 +1,229 @@
+use dioxus_native_core::prelude::*;
+use keyboard_types::Key;
+use peniko::kurbo::Point;
+use shipyard::Component;
+use taffy::prelude::{Dimension, FlexDirection, Size};
+use taffy::Taffy;
+
+use crate::layout::{set_resized, TaffyLayout};
+use crate::style::SplitDivider;
+
+/// How far the arrow keys move a divider that has focus, in pixels
+const KEYBOARD_STEP: f32 = 10.0;
+/// How far the arrow keys move a divider while Shift is held
+const LARGE_KEYBOARD_STEP: f32 = 50.0;
+
+/// The size of the pane before a divider before Enter collapsed it, stored on the divider
+#[derive(Clone, Copy, Debug, PartialEq, Component)]
+struct Collapsed(f32);
+
+/// The panes on both sides of a divider
+#[derive(Clone, Copy, Debug)]
+struct Panes {
+    before: NodeId,
+    after: NodeId,
+    /// True if the container lays the panes out in a row
+    horizontal: bool,
+    /// True if the pane before the divider is right of or below it, because the container is reversed
+    reversed: bool,
+    /// The sizes of the panes along the axis of the container
+    sizes: [f32; 2],
+    /// The `min-width` or `min-height` of the panes in pixels
+    min_sizes: [f32; 2],
+}
+
+impl Panes {
+    /// The element siblings before and after a divider, or `None` if it isn't between two elements.
+    fn of(rdom: &RealDom, taffy: &Taffy, divider: NodeId) -> Option<Self> {
+        let node = rdom.get(divider)?;
+        let siblings: Vec<NodeRef> = node
+            .parent()?
+            .children()
+            .into_iter()
+            .filter(|sibling| matches!(&*sibling.node_type(), NodeType::Element(_)))
+            .collect();
+        let index = siblings
+            .iter()
+            .position(|sibling| sibling.id() == divider)?;
+        let before = *siblings.get(index.checked_sub(1)?)?;
+        let after = *siblings.get(index + 1)?;
+        let direction = node.parent()?.get::<TaffyLayout>()?.style.flex_direction;
+        let horizontal = matches!(direction, FlexDirection::Row | FlexDirection::RowReverse);
+        let main = |node: NodeRef| -> Option<(f32, f32)> {
+            let layout = node.get::<TaffyLayout>()?;
+            let size = taffy.layout(layout.node?).ok()?.size;
+            let style = &layout.style;
+            let (size, min) = if horizontal {
+                (size.width, style.min_size.width)
+            } else {
+                (size.height, style.min_size.height)
+            };
+            let min = match min {
+                Dimension::Points(min) => min,
+                _ => 0.0,
+            };
+            Some((size, min))
+        };
+        let (before_size, before_min) = main(before)?;
+        let (after_size, after_min) = main(after)?;
+        Some(Self {
+            before: before.id(),
+            after: after.id(),
+            horizontal,
+            reversed: matches!(
+                direction,
+                FlexDirection::RowReverse | FlexDirection::ColumnReverse
+            ),
+            sizes: [before_size, after_size],
+            min_sizes: [before_min, after_min],
+        })
+    }
+
+    /// Move the divider by `delta` pixels right or down from where it was when the panes were measured. The panes share the space they had, so nothing else moves.
+    fn move_divider(&self, rdom: &mut RealDom, taffy: &mut Taffy, mode: SplitDivider, delta: f32) {
+        let delta = if self.reversed { -delta } else { delta };
+        let total = self.sizes[0] + self.sizes[1];
+        let [min_before, min_after] = self.min_sizes;
+        let mut before = self.sizes[0] + delta;
+        if mode == SplitDivider::Collapse && before < min_before / 2.0 {
+            before = 0.0;
+        } else if mode == SplitDivider::Collapse && total - before < min_after / 2.0 {
+            before = total;
+        } else if min_before + min_after <= total {
+            before = before.clamp(min_before, total - min_after);
+        }
+        self.resize(rdom, taffy, before.clamp(0.0, total));
+    }
+
+    /// Give the pane before the divider a size and the pane after it the rest of their space.
+    fn resize(&self, rdom: &mut RealDom, taffy: &mut Taffy, before: f32) {
+        let total = self.sizes[0] + self.sizes[1];
+        for (pane, size) in [(self.before, before), (self.after, total - before)] {
+            let Some(mut resized) = rdom
+                .get(pane)
+                .and_then(|node| node.get::<TaffyLayout>().map(|layout| layout.resized))
+            else {
+                continue;
+            };
+            if self.horizontal {
+                resized.width = Some(size);
+            } else {
+                resized.height = Some(size);
+            }
+            set_resized(rdom, taffy, pane, resized);
+        }
+    }
+}
+
+/// The divider an element is part of, with how it resizes its panes. A divider can contain elements like a grip icon that are dragged with it.
+fn divider_of(rdom: &RealDom, element: NodeId) -> Option<(NodeId, SplitDivider)> {
+    let mut current = rdom.get(element);
+    while let Some(node) = current {
+        let mode = node.get::<SplitDivider>().map(|mode| *mode);
+        if let Some(mode) = mode.filter(|mode| *mode != SplitDivider::None) {
+            return Some((node.id(), mode));
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// The mouse dragging a divider between two panes, see [`SplitDivider`]. The sizes the panes are dragged to are kept in their layout like the size of a resized element.
+pub(crate) struct SplitDrag {
+    divider: NodeId,
+    mode: SplitDivider,
+    panes: Panes,
+    /// Where the drag started in client coordinates
+    start: Point,
+}
+
+impl SplitDrag {
+    /// Start dragging the divider the mouse was pressed on at a point in client coordinates, if the element is part of one.
+    ///
+    /// TODO: Map the movement of the mouse through the transforms of the container
+    pub fn start(rdom: &RealDom, taffy: &Taffy, element: NodeId, point: Point) -> Option<Self> {
+        let (divider, mode) = divider_of(rdom, element)?;
+        Some(Self {
+            divider,
+            mode,
+            panes: Panes::of(rdom, taffy, divider)?,
+            start: point,
+        })
+    }
+
+    /// The divider that is dragged.
+    pub fn divider(&self) -> NodeId {
+        self.divider
+    }
+
+    /// Move the divider to follow the mouse at a point in client coordinates.
+    pub fn update(&self, rdom: &mut RealDom, taffy: &mut Taffy, point: Point) {
+        let delta = point - self.start;
+        let delta = if self.panes.horizontal {
+            delta.x
+        } else {
+            delta.y
+        };
+        if rdom.get(self.divider).is_some() {
+            self.panes
+                .move_divider(rdom, taffy, self.mode, delta as f32);
+        }
+    }
+}
+
+/// The elements before and after a divider with their sizes along the axis of its container, or `None` if it isn't between two elements.
+pub(crate) fn pane_sizes(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    divider: NodeId,
+) -> Option<([NodeId; 2], [f32; 2])> {
+    let panes = Panes::of(rdom, taffy, divider)?;
+    Some(([panes.before, panes.after], panes.sizes))
+}
+
+/// Move a divider that has focus with the arrow keys along the axis of its container, further while Shift is held. Enter collapses the pane before a divider with `split-divider: collapse` and restores it again. Returns true if the panes were resized.
+pub(crate) fn divider_key(
+    rdom: &mut RealDom,
+    taffy: &mut Taffy,
+    focused: NodeId,
+    key: &Key,
+    shift: bool,
+) -> bool {
+    let Some((divider, mode)) =
+        divider_of(rdom, focused).filter(|(divider, _)| *divider == focused)
+    else {
+        return false;
+    };
+    let Some(panes) = Panes::of(rdom, taffy, divider) else {
+        return false;
+    };
+    let step = if shift {
+        LARGE_KEYBOARD_STEP
+    } else {
+        KEYBOARD_STEP
+    };
+    let delta = match (key, panes.horizontal) {
+        (Key::ArrowLeft, true) | (Key::ArrowUp, false) => -step,
+        (Key::ArrowRight, true) | (Key::ArrowDown, false) => step,
+        (Key::Enter, _) if mode == SplitDivider::Collapse => {
+            let before = {
+                let mut node = rdom.get_mut(divider).unwrap();
+                if panes.sizes[0] > 0.0 {
+                    node.insert(Collapsed(panes.sizes[0]));
+                    0.0
+                } else {
+                    // A pane that was dragged closed opens at its minimum size
+                    let restored = node
+                        .get::<Collapsed>()
+                        .map_or(panes.min_sizes[0], |size| size.0);
+                    restored.min(panes.sizes[0] + panes.sizes[1])
+                }
+            };
+            panes.resize(rdom, taffy, before);
+            return true;
+        }
+        _ => return false,
+    };
+    panes.move_divider(rdom, taffy, mode, delta);
+    true
+}
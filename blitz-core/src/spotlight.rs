@@ -0,0 +1,190 @@
+use std::f32::consts::FRAC_PI_2;
+
+use dioxus_native_core::prelude::*;
+use epaint::{Color32, Mesh, Pos2, Rect, Rounding, Shape};
+use peniko::kurbo::{Affine, Point};
+use peniko::Color;
+use shipyard::Component;
+use taffy::{prelude::Size, Taffy};
+
+use crate::coordinates::element_transform;
+use crate::document::to_color32;
+use crate::layout::TaffyLayout;
+use crate::render::get_shape;
+use crate::scene::Scene;
+
+/// The number of segments each rounded corner of the cutout is made of
+const CORNER_SEGMENTS: usize = 12;
+/// How far the dimmed area reaches past the element. The clip rect cuts it off at the edges of the window
+const DIMMED_EXTENT: f32 = 100_000.0;
+
+/// Dims the whole document except for one element, to point the user at it in onboarding tours and tutorials.
+///
+/// The cutout follows the border box of the element, including its rounded corners and transforms. Only one spotlight is shown at a time; it is stored on the root of the RealDom and drawn over everything else with the next frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spotlight {
+    pub element: NodeId,
+    /// The color drawn over the rest of the document
+    pub color: Color,
+    /// How far the cutout reaches past the border box of the element in pixels
+    pub padding: f32,
+}
+
+/// The spotlight that is shown, stored on the root
+#[derive(Component)]
+struct ActiveSpotlight(Option<Spotlight>);
+
+impl Spotlight {
+    pub fn new(element: NodeId) -> Self {
+        Self {
+            element,
+            color: Color::rgba8(0, 0, 0, 160),
+            padding: 0.0,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Show the spotlight, replacing the one that was shown before.
+    pub fn show(self, rdom: &mut RealDom) {
+        set(rdom, Some(self));
+    }
+
+    pub fn hide(rdom: &mut RealDom) {
+        set(rdom, None);
+    }
+
+    /// The spotlight that is shown in a document, if there is one.
+    pub fn of(rdom: &RealDom) -> Option<Self> {
+        rdom.get(rdom.root_id())
+            .and_then(|root| root.get::<ActiveSpotlight>().and_then(|active| active.0))
+    }
+}
+
+fn set(rdom: &mut RealDom, spotlight: Option<Spotlight>) {
+    let root_id = rdom.root_id();
+    rdom.get_mut(root_id)
+        .unwrap()
+        .insert(ActiveSpotlight(spotlight));
+}
+
+/// Dim everything outside of the element of the spotlight, if one is shown. `transform` maps the document to the window.
+pub(crate) fn paint_spotlight(
+    rdom: &RealDom,
+    taffy: &Taffy,
+    scene: &mut Scene,
+    clip: Rect,
+    transform: Affine,
+    viewport_size: &Size<u32>,
+) {
+    let Some(spotlight) = Spotlight::of(rdom) else {
+        return;
+    };
+    // The element may have been removed since the spotlight was shown
+    let Some(node) = rdom.get(spotlight.element) else {
+        return;
+    };
+    let Some(layout) = node
+        .get::<TaffyLayout>()
+        .and_then(|layout| layout.node)
+        .and_then(|node| taffy.layout(node).ok())
+    else {
+        return;
+    };
+    let Some(element_transform) = element_transform(rdom, taffy, spotlight.element) else {
+        return;
+    };
+
+    // The cutout is built around the border box of the element in its own coordinates
+    let rounding = match get_shape(layout, node, viewport_size, Point::ZERO) {
+        Shape::Rect(rect) => rect.rounding,
+        _ => Rounding::none(),
+    };
+    let padding = spotlight.padding.max(0.0);
+    let cutout = Rect::from_min_size(
+        Pos2::ZERO,
+        epaint::vec2(layout.size.width, layout.size.height),
+    )
+    .expand(padding);
+    let rounding = Rounding {
+        nw: rounding.nw + padding,
+        ne: rounding.ne + padding,
+        sw: rounding.sw + padding,
+        se: rounding.se + padding,
+    };
+
+    scene.transform = transform * element_transform;
+    scene.push(
+        clip,
+        Shape::mesh(dimmed_mesh(cutout, rounding, to_color32(spotlight.color))),
+    );
+    scene.transform = Affine::IDENTITY;
+}
+
+/// A mesh that covers everything except a rounded rect: four strips around the rect and a fan between each corner of the rect and its rounded corner
+fn dimmed_mesh(cutout: Rect, rounding: Rounding, color: Color32) -> Mesh {
+    let mut mesh = Mesh::default();
+    let outer = cutout.expand(DIMMED_EXTENT);
+    // Above, below, left and right of the cutout
+    for rect in [
+        Rect::from_x_y_ranges(outer.x_range(), outer.min.y..=cutout.min.y),
+        Rect::from_x_y_ranges(outer.x_range(), cutout.max.y..=outer.max.y),
+        Rect::from_x_y_ranges(outer.min.x..=cutout.min.x, cutout.y_range()),
+        Rect::from_x_y_ranges(cutout.max.x..=outer.max.x, cutout.y_range()),
+    ] {
+        mesh.add_colored_rect(rect, color);
+    }
+
+    // Each corner with its radius, the center of its arc and the angle the arc starts at
+    let half_size = cutout.size() / 2.0;
+    let corners = [
+        (cutout.left_top(), rounding.nw, epaint::vec2(1.0, 1.0), 2.0),
+        (
+            cutout.right_top(),
+            rounding.ne,
+            epaint::vec2(-1.0, 1.0),
+            3.0,
+        ),
+        (
+            cutout.right_bottom(),
+            rounding.se,
+            epaint::vec2(-1.0, -1.0),
+            0.0,
+        ),
+        (
+            cutout.left_bottom(),
+            rounding.sw,
+            epaint::vec2(1.0, -1.0),
+            1.0,
+        ),
+    ];
+    for (corner, radius, inward, start) in corners {
+        let radius = radius.min(half_size.x).min(half_size.y);
+        if radius <= 0.0 {
+            continue;
+        }
+        let center = corner + inward * radius;
+        let corner_index = mesh.vertices.len() as u32;
+        mesh.colored_vertex(corner, color);
+        for segment in 0..=CORNER_SEGMENTS {
+            let angle = (start + segment as f32 / CORNER_SEGMENTS as f32) * FRAC_PI_2;
+            mesh.colored_vertex(center + radius * epaint::Vec2::angled(angle), color);
+        }
+        for segment in 0..CORNER_SEGMENTS as u32 {
+            mesh.add_triangle(
+                corner_index,
+                corner_index + 1 + segment,
+                corner_index + 2 + segment,
+            );
+        }
+    }
+    mesh
+}
@@ -0,0 +1,99 @@
+use dioxus_native_core::prelude::*;
+
+/// A stable id of an element, which stays the same when the document is rebuilt with the same structure, unlike its [`NodeId`]. Tests and accessibility tools can refer to elements with it across launches and hot reloads.
+///
+/// The id is the path to the element from the closest element with an `id` attribute, or from the root. Each step is the tag of an element and its index among the elements with the same tag under its parent, so text and elements with other tags can come and go without changing it. An element with an `id` attribute is `#` followed by its id:
+///
+/// - `#sidebar` for `<nav id="sidebar">`
+/// - `#sidebar/ul[0]/li[2]` for the third `li` of the first `ul` in it
+/// - `/div[0]/button[1]` for the second button of the first `div` of a document without ids
+///
+/// Give the elements of lists that are reordered an `id`, otherwise the ids of the elements after an inserted element change. [`Document::query_selector`](crate::Document::query_selector) and the test harness accept stable ids as selectors.
+///
+/// Returns `None` for nodes that aren't elements.
+pub fn stable_id(rdom: &RealDom, node: NodeId) -> Option<String> {
+    let mut segments = Vec::new();
+    let mut current = rdom.get(node)?;
+    loop {
+        let (tag, id) = match &*current.node_type() {
+            NodeType::Element(element) => (
+                element.tag.clone(),
+                id_attribute(element).map(str::to_string),
+            ),
+            _ => return None,
+        };
+        if let Some(id) = id {
+            segments.push(format!("#{id}"));
+            break;
+        }
+        let Some(parent) = current.parent() else {
+            // The root is the start of the path
+            segments.push(String::new());
+            break;
+        };
+        let index = parent
+            .children()
+            .into_iter()
+            .filter(|sibling| has_tag(sibling, &tag))
+            .position(|sibling| sibling.id() == current.id())?;
+        segments.push(format!("{tag}[{index}]"));
+        current = parent;
+    }
+    segments.reverse();
+    Some(segments.join("/"))
+}
+
+/// Find the element with a stable id, see [`stable_id`]. An id that starts with an element with an `id` attribute starts at the first element with that id in document order.
+pub fn find_stable_id(rdom: &RealDom, stable_id: &str) -> Option<NodeId> {
+    let mut segments = stable_id.split('/');
+    let start = segments.next()?;
+    let mut current = match start.strip_prefix('#') {
+        Some(id) => find_id(rdom, id)?,
+        None if start.is_empty() => rdom.root_id(),
+        None => return None,
+    };
+    for segment in segments {
+        let (tag, index) = segment.strip_suffix(']')?.split_once('[')?;
+        let index: usize = index.parse().ok()?;
+        current = rdom
+            .get(current)?
+            .children()
+            .into_iter()
+            .filter(|child| has_tag(child, tag))
+            .nth(index)?
+            .id();
+    }
+    Some(current)
+}
+
+/// True if a selector is a stable id with a path, like `#list/li[2]`. An id without a path is also a selector that matches the same element.
+pub(crate) fn is_stable_id(selector: &str) -> bool {
+    selector.contains('/')
+}
+
+/// The first element in document order with an id.
+fn find_id(rdom: &RealDom, id: &str) -> Option<NodeId> {
+    let mut stack = vec![rdom.get(rdom.root_id())?];
+    while let Some(node) = stack.pop() {
+        if let NodeType::Element(element) = &*node.node_type() {
+            if id_attribute(element) == Some(id) {
+                return Some(node.id());
+            }
+        }
+        stack.extend(node.children().into_iter().rev());
+    }
+    None
+}
+
+fn id_attribute(element: &ElementNode) -> Option<&str> {
+    element
+        .attributes
+        .iter()
+        .find(|(attribute, _)| attribute.name == "id" && attribute.namespace.is_none())
+        .and_then(|(_, value)| value.as_text())
+        .filter(|id| !id.is_empty())
+}
+
+fn has_tag(node: &NodeRef, tag: &str) -> bool {
+    matches!(&*node.node_type(), NodeType::Element(element) if element.tag == tag)
+}
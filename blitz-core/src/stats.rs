@@ -0,0 +1,195 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::json;
+
+/// How long the stages of a frame took and how much work they did. The work of the DOM thread, like styling and laying out nodes, is counted in the first frame drawn after it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// Painting the document into the shapes of the scene on the CPU
+    pub paint_time: Duration,
+    /// Tessellating the shapes that changed into meshes on the CPU
+    pub tessellation_time: Duration,
+    /// Uploading the meshes to the backend and recording the draw calls on the CPU
+    pub submit_time: Duration,
+    /// Drawing the frame on the GPU, or `None` if the backend doesn't measure it. The time of a frame is known once the GPU finished it, so this is the time of an earlier frame
+    pub gpu_time: Option<Duration>,
+    /// The nodes whose style changed
+    pub nodes_styled: u64,
+    /// The nodes whose layout style or children changed, which taffy lays out again
+    pub nodes_laid_out: u64,
+    /// The lines of text that were shaped, while they were measured or drawn
+    pub text_runs_shaped: u64,
+    /// The vertices of the meshes that were tessellated
+    pub vertices: u64,
+    /// Glyphs that were already rasterized into the texture atlas
+    pub glyph_cache: CacheStats,
+    /// Paths of SVG shapes that were already tessellated at the size they are drawn at
+    pub path_cache: CacheStats,
+    /// Layers whose meshes were reused from the last frame
+    pub layer_cache: CacheStats,
+    /// The meshes, rects and textures the backend was asked to draw
+    pub draw_calls: u64,
+    /// The vertices of the meshes that were drawn. Rects that are drawn as instances aren't included
+    pub vertices_drawn: u64,
+    /// The indices of the meshes that were drawn
+    pub indices_drawn: u64,
+    /// The nodes that were marked dirty, or `None` if the whole window was drawn again
+    pub dirty_nodes: Option<u64>,
+}
+
+/// How often a cache had what was looked up in it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// The share of lookups that were hits, or `None` if nothing was looked up.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+
+    fn to_json(self) -> serde_json::Value {
+        json!({
+            "hits": self.hits,
+            "misses": self.misses,
+            "hit_rate": self.hit_rate(),
+        })
+    }
+}
+
+impl FrameStats {
+    /// The time the frame took on the CPU
+    pub fn cpu_time(&self) -> Duration {
+        self.paint_time + self.tessellation_time + self.submit_time
+    }
+
+    /// The stats as a JSON object, with times in milliseconds.
+    pub fn to_json(&self) -> serde_json::Value {
+        let millis = |time: Duration| time.as_secs_f64() * 1000.0;
+        json!({
+            "cpu_time": millis(self.cpu_time()),
+            "paint_time": millis(self.paint_time),
+            "tessellation_time": millis(self.tessellation_time),
+            "submit_time": millis(self.submit_time),
+            "gpu_time": self.gpu_time.map(millis),
+            "nodes_styled": self.nodes_styled,
+            "nodes_laid_out": self.nodes_laid_out,
+            "text_runs_shaped": self.text_runs_shaped,
+            "vertices": self.vertices,
+            "glyph_cache": self.glyph_cache.to_json(),
+            "path_cache": self.path_cache.to_json(),
+            "layer_cache": self.layer_cache.to_json(),
+            "draw_calls": self.draw_calls,
+            "vertices_drawn": self.vertices_drawn,
+            "indices_drawn": self.indices_drawn,
+            "dirty_nodes": self.dirty_nodes,
+        })
+    }
+
+    /// Take the work that was counted since the last frame.
+    pub(crate) fn take_counters(&mut self) {
+        self.nodes_styled = take(Counter::NodesStyled);
+        self.nodes_laid_out = take(Counter::NodesLaidOut);
+        self.text_runs_shaped = take(Counter::TextRunsShaped);
+        self.vertices = take(Counter::Vertices);
+        self.glyph_cache = CacheStats {
+            hits: take(Counter::GlyphHits),
+            misses: take(Counter::GlyphMisses),
+        };
+        self.path_cache = CacheStats {
+            hits: take(Counter::PathHits),
+            misses: take(Counter::PathMisses),
+        };
+        self.layer_cache = CacheStats {
+            hits: take(Counter::LayerHits),
+            misses: take(Counter::LayerMisses),
+        };
+        self.draw_calls = take(Counter::DrawCalls);
+        self.vertices_drawn = take(Counter::VerticesDrawn);
+        self.indices_drawn = take(Counter::IndicesDrawn);
+    }
+}
+
+/// Collects the [`FrameStats`] of the window, so an application or an overlay can show how long frames take.
+///
+/// Pass a clone to [`crate::Config::with_stats`] and read the stats from another one. Collecting the stats lets the backend measure the GPU time of frames with timestamp queries, which only the default Vulkan backend does.
+#[derive(Clone, Default)]
+pub struct Stats {
+    inner: Arc<Mutex<Option<FrameStats>>>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The stats of the last frame that was drawn, or `None` before the first frame.
+    pub fn latest(&self) -> Option<FrameStats> {
+        *self.inner.lock().unwrap()
+    }
+
+    /// Write the stats of the last frame as a line of JSON, see [`FrameStats::to_json`]. Writing them after every frame gives a log of JSON lines that shows which stage a change to the application affects.
+    ///
+    /// Nothing is written before the first frame.
+    pub fn write_json(&self, mut writer: impl Write) -> io::Result<()> {
+        let Some(stats) = self.latest() else {
+            return Ok(());
+        };
+        serde_json::to_writer(&mut writer, &stats.to_json())?;
+        writeln!(writer)
+    }
+
+    pub(crate) fn record(&self, stats: FrameStats) {
+        *self.inner.lock().unwrap() = Some(stats);
+    }
+}
+
+/// The work that is counted between frames, see [`FrameStats`].
+#[derive(Clone, Copy)]
+pub(crate) enum Counter {
+    NodesStyled,
+    NodesLaidOut,
+    TextRunsShaped,
+    Vertices,
+    GlyphHits,
+    GlyphMisses,
+    PathHits,
+    PathMisses,
+    LayerHits,
+    LayerMisses,
+    DrawCalls,
+    VerticesDrawn,
+    IndicesDrawn,
+}
+
+/// The work counted since the last frame, indexed by [`Counter`]. The stages run on the DOM thread and the thread of the window, so the counters are global.
+static COUNTERS: [AtomicU64; 13] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+
+/// Count work done by a stage of the frame.
+pub(crate) fn count(counter: Counter, amount: usize) {
+    COUNTERS[counter as usize].fetch_add(amount as u64, Ordering::Relaxed);
+}
+
+fn take(counter: Counter) -> u64 {
+    COUNTERS[counter as usize].swap(0, Ordering::Relaxed)
+}
@@ -5,7 +5,8 @@ use lightningcss::values::length::LengthValue;
 use lightningcss::values::position::HorizontalPositionKeyword;
 use lightningcss::values::position::VerticalPositionKeyword;
 
-use peniko::kurbo::Shape;
+use epaint::Color32;
+use peniko::kurbo::Point;
 use peniko::Color;
 
 use smallvec::SmallVec;
@@ -14,7 +15,7 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use taffy::prelude::Size;
 
-use crate::renderer::Renderer;
+use crate::scene::Scene;
 use crate::util::map_dimension_percentage;
 use crate::util::translate_color;
 use crate::util::AngleExt;
@@ -22,7 +23,6 @@ use crate::util::Resolve;
 
 use super::linear_gradient::LinearGradient;
 use super::radial_gradient::RadialGradient;
-use super::Repeat;
 
 #[derive(Clone, PartialEq, Debug)]
 pub(crate) enum GradientType {
@@ -230,82 +230,113 @@ impl Gradient {
         }
     }
 
+    /// Paint the gradient over the rect by subdividing it into a grid of colored vertices.
     pub(crate) fn render(
         &self,
-        _sb: &mut Renderer,
-        _shape: &impl Shape,
-        _repeat: Repeat,
-        _rect: &Size<f32>,
-        _viewport_size: &Size<u32>,
+        scene: &mut Scene,
+        rect: epaint::Rect,
+        clip: epaint::Rect,
+        viewport_size: &Size<u32>,
     ) {
-        // let stops = self.resolve_stops(rect, viewport_size);
-        // let extend = if self.repeating {
-        //     Extend::Repeat
-        // } else {
-        //     repeat.into()
-        // };
-        // match &self.gradient_type {
-        //     GradientType::Linear(gradient) => {
-        //         let bb = shape.bounding_box();
-        //         let starting_point_offset = gradient.center_offset(*rect);
-        //         let ending_point_offset =
-        //             Point::new(-starting_point_offset.x, -starting_point_offset.y);
-        //         let center = bb.center();
-        //         let start = Point::new(
-        //             center.x + starting_point_offset.x,
-        //             center.y + starting_point_offset.y,
-        //         );
-        //         let end = Point::new(
-        //             center.x + ending_point_offset.x,
-        //             center.y + ending_point_offset.y,
-        //         );
-
-        //         let kind = peniko::GradientKind::Linear { start, end };
-
-        //         let gradient = peniko::Gradient {
-        //             kind,
-        //             extend,
-        //             stops: (*stops).clone(),
-        //         };
-
-        //         let brush = peniko::BrushRef::Gradient(&gradient);
-
-        //         sb.fill(peniko::Fill::NonZero, Affine::IDENTITY, brush, None, shape)
-        //     }
-        //     GradientType::Radial(gradient) => {
-        //         let bb = shape.bounding_box();
-        //         let pos_x = bb.x0
-        //             + gradient
-        //                 .position
-        //                 .x
-        //                 .resolve(crate::util::Axis::X, rect, viewport_size);
-        //         let pos_y = bb.y0
-        //             + gradient
-        //                 .position
-        //                 .y
-        //                 .resolve(crate::util::Axis::Y, rect, viewport_size);
-        //         let pos = Point::new(pos_x, pos_y);
-
-        //         let end_radius = gradient.radius_in(pos, rect, viewport_size) as f32;
-
-        //         let kind = peniko::GradientKind::Radial {
-        //             start_center: pos,
-        //             start_radius: 0.0,
-        //             end_center: pos,
-        //             end_radius,
-        //         };
-
-        //         let gradient = peniko::Gradient {
-        //             kind,
-        //             extend,
-        //             stops: (*stops).clone(),
-        //         };
-
-        //         let brush = peniko::BrushRef::Gradient(&gradient);
-
-        //         sb.fill(peniko::Fill::NonZero, Affine::IDENTITY, brush, None, shape)
-        //     }
-        //     GradientType::Conic => todo!(),
-        // }
+        let size = Size {
+            width: rect.width(),
+            height: rect.height(),
+        };
+        let stops = self.resolve_stops(&size, viewport_size);
+        if stops.is_empty() {
+            return;
+        }
+
+        // Find the position along the gradient for a point relative to the top left of the rect
+        let gradient_position: Box<dyn Fn(Point) -> f64> = match &self.gradient_type {
+            GradientType::Linear(gradient) => {
+                let center = Point::new(size.width as f64 / 2., size.height as f64 / 2.);
+                let offset = gradient.center_offset(size);
+                let start = center - offset;
+                let line = offset * 2.;
+                let length_squared = line.hypot2().max(f64::EPSILON);
+                Box::new(move |point| (point - start).dot(line) / length_squared)
+            }
+            GradientType::Radial(gradient) => {
+                let center = Point::new(
+                    gradient
+                        .position
+                        .x
+                        .resolve(crate::util::Axis::X, &size, viewport_size),
+                    gradient
+                        .position
+                        .y
+                        .resolve(crate::util::Axis::Y, &size, viewport_size),
+                );
+                let radius = gradient
+                    .radius_in(center, &size, viewport_size)
+                    .max(f64::EPSILON);
+                Box::new(move |point| point.distance(center) / radius)
+            }
+            GradientType::Conic => {
+                let center = Point::new(size.width as f64 / 2., size.height as f64 / 2.);
+                Box::new(move |point| {
+                    // Conic gradients start at the top and go clockwise
+                    let offset = point - center;
+                    offset.x.atan2(-offset.y).rem_euclid(2. * PI) / (2. * PI)
+                })
+            }
+        };
+
+        let mut mesh = epaint::Mesh::default();
+        for y in 0..=GRADIENT_SUBDIVISIONS {
+            for x in 0..=GRADIENT_SUBDIVISIONS {
+                let local = Point::new(
+                    size.width as f64 * x as f64 / GRADIENT_SUBDIVISIONS as f64,
+                    size.height as f64 * y as f64 / GRADIENT_SUBDIVISIONS as f64,
+                );
+                let mut position = gradient_position(local) as f32;
+                if self.repeating {
+                    position = position.rem_euclid(1.0);
+                }
+                mesh.colored_vertex(
+                    epaint::pos2(rect.min.x + local.x as f32, rect.min.y + local.y as f32),
+                    sample_stops(&stops, position),
+                );
+            }
+        }
+        let row = GRADIENT_SUBDIVISIONS + 1;
+        for y in 0..GRADIENT_SUBDIVISIONS {
+            for x in 0..GRADIENT_SUBDIVISIONS {
+                let top_left = y * row + x;
+                let bottom_left = top_left + row;
+                mesh.add_triangle(top_left, top_left + 1, bottom_left);
+                mesh.add_triangle(top_left + 1, bottom_left + 1, bottom_left);
+            }
+        }
+        scene.push(clip, epaint::Shape::mesh(mesh));
+    }
+}
+
+/// The number of quads along each axis used to approximate a gradient
+const GRADIENT_SUBDIVISIONS: u32 = 32;
+
+/// Find the color at a position along the gradient line
+fn sample_stops(stops: &[peniko::ColorStop], position: f32) -> Color32 {
+    let to_color32 =
+        |color: Color| Color32::from_rgba_unmultiplied(color.r, color.g, color.b, color.a);
+    let first = stops.first().unwrap();
+    if position <= first.offset {
+        return to_color32(first.color);
+    }
+    for window in stops.windows(2) {
+        let [start, end] = window else { unreachable!() };
+        if position <= end.offset {
+            let length = (end.offset - start.offset).max(f32::EPSILON);
+            let t = ((position - start.offset) / length).clamp(0.0, 1.0);
+            let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+            return Color32::from_rgba_unmultiplied(
+                lerp(start.color.r, end.color.r),
+                lerp(start.color.g, end.color.g),
+                lerp(start.color.b, end.color.b),
+                lerp(start.color.a, end.color.a),
+            );
+        }
     }
+    to_color32(stops.last().unwrap().color)
 }
@@ -1,21 +1,24 @@
-use peniko::kurbo::Point;
+use peniko::kurbo::{Point, Vec2};
 use std::f64::consts::PI;
 use taffy::prelude::Size;
 
 #[derive(Clone, PartialEq, Debug)]
 pub(crate) struct LinearGradient {
+    // The angle from the positive x axis, clockwise
     angle_radians: f64,
 }
 
 impl LinearGradient {
+    /// Create a gradient from a css angle in radians. Css angles start at the top and go clockwise.
     pub fn new(angle: f64) -> Self {
         Self {
-            angle_radians: angle.to_radians(),
+            angle_radians: (angle - PI / 2.).rem_euclid(2. * PI),
         }
     }
 
-    pub fn center_offset(&self, size: Size<f32>) -> Point {
-        angle_to_center_offset(self.angle_radians, size)
+    /// The offset from the center of the rect to the end of the gradient line
+    pub fn center_offset(&self, size: Size<f32>) -> Vec2 {
+        angle_to_center_offset(self.angle_radians, size).to_vec2()
     }
 }
 
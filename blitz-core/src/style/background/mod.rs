@@ -4,22 +4,27 @@ mod radial_gradient;
 
 use dioxus_native_core::prelude::*;
 use dioxus_native_core_macro::partial_derive_state;
-use lightningcss::properties::background;
+use lightningcss::properties::background::{
+    self, BackgroundPosition, BackgroundRepeatKeyword, BackgroundSize,
+};
 use lightningcss::traits::Parse;
 use lightningcss::values::color::CssColor;
+use lightningcss::values::length::LengthPercentageOrAuto;
+use lightningcss::values::position::{HorizontalPosition, VerticalPosition};
 
-use peniko::kurbo::Shape;
-
+use epaint::Color32;
 use peniko::Color;
-use peniko::Extend;
 
 use shipyard::Component;
 use std::sync::Arc;
-use taffy::prelude::Layout;
 use taffy::prelude::Size;
 
-use crate::renderer::Renderer;
-use crate::util::translate_color;
+use crate::atlas::image_region;
+use crate::images::load_image;
+use crate::scene::{transform_rect, Scene};
+use crate::util::{translate_color, Axis, Resolve};
+
+use super::StyleDiagnostics;
 
 use self::gradient::Gradient;
 
@@ -36,78 +41,39 @@ impl Image {
         use lightningcss::values::image;
         match value {
             image::Image::None => Some(Self::None),
-            // image::Image::Url(url) => {
-            //     let image_ctx: &ImageContext = ctx.get().expect("ImageContext not found");
-            //     Some(Self::Image(image_ctx.load_file(url.url.as_ref()).unwrap()))
-            // }
+            // Images that fail to load are reported when they are loaded and draw nothing
+            image::Image::Url(url) => Some(
+                load_image(url.url.as_ref())
+                    .map(Self::Image)
+                    .unwrap_or_default(),
+            ),
             image::Image::Gradient(gradient) => Some(Self::Gradient((*gradient).try_into().ok()?)),
             _ => None,
         }
     }
-
-    fn render(
-        &self,
-        _sb: &mut Renderer,
-        _shape: &impl Shape,
-        _repeat: Repeat,
-        _rect: &Size<f32>,
-        _viewport_size: &Size<u32>,
-    ) {
-        // match self {
-        //     Self::Gradient(gradient) => gradient.render(sb, shape, repeat, rect, viewport_size),
-        //     Self::Image(image) => {
-        //         // Translate the image to the layout's position
-        //         match repeat {
-        //             Repeat { x: false, y: false } => {
-        //                 sb.fill(
-        //                     Fill::NonZero,
-        //                     Affine::IDENTITY,
-        //                     BrushRef::Image(image),
-        //                     None,
-        //                     &shape,
-        //                 );
-        //             }
-        //             _ => {
-        //                 sb.fill(
-        //                     Fill::NonZero,
-        //                     Affine::IDENTITY,
-        //                     BrushRef::Image(&(**image).clone().with_extend(peniko::Extend::Repeat)),
-        //                     None,
-        //                     &shape,
-        //                 );
-        //             }
-        //         }
-        //     }
-        //     _ => {}
-        // }
-    }
 }
 
-#[derive(Clone, Copy, PartialEq, Debug, Default)]
+/// How the background image repeats along each axis
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub(crate) struct Repeat {
-    x: bool,
-    y: bool,
+    x: BackgroundRepeatKeyword,
+    y: BackgroundRepeatKeyword,
 }
 
-impl From<background::BackgroundRepeat> for Repeat {
-    fn from(repeat: background::BackgroundRepeat) -> Self {
-        fn is_repeat(repeat: &background::BackgroundRepeatKeyword) -> bool {
-            use background::BackgroundRepeatKeyword::*;
-            !matches!(repeat, NoRepeat)
-        }
-
+impl Default for Repeat {
+    fn default() -> Self {
         Repeat {
-            x: is_repeat(&repeat.x),
-            y: is_repeat(&repeat.y),
+            x: BackgroundRepeatKeyword::Repeat,
+            y: BackgroundRepeatKeyword::Repeat,
         }
     }
 }
 
-impl From<Repeat> for Extend {
-    fn from(val: Repeat) -> Self {
-        match val {
-            Repeat { x: false, y: false } => Extend::Repeat,
-            _ => Extend::Pad,
+impl From<background::BackgroundRepeat> for Repeat {
+    fn from(repeat: background::BackgroundRepeat) -> Self {
+        Repeat {
+            x: repeat.x,
+            y: repeat.y,
         }
     }
 }
@@ -115,32 +81,146 @@ impl From<Repeat> for Extend {
 #[derive(PartialEq, Debug, Component)]
 pub(crate) struct Background {
     pub color: Color,
-    // pub image: Image,
-    // pub repeat: Repeat,
+    pub image: Image,
+    pub repeat: Repeat,
+    pub position: BackgroundPosition,
+    pub size: BackgroundSize,
 }
 
 impl Background {
-    pub(crate) fn draw_shape(
+    /// Draw the background image over the padding box of a node. The background color is part of the node's shape.
+    pub(crate) fn draw_image(
+        &self,
+        scene: &mut Scene,
+        rect: epaint::Rect,
+        clip: epaint::Rect,
+        viewport_size: &Size<u32>,
+    ) {
+        match &self.image {
+            // TODO: Size, position and repeat gradients too
+            Image::Gradient(gradient) => gradient.render(scene, rect, clip, viewport_size),
+            Image::Image(image) => self.draw_tiles(scene, image, rect, clip, viewport_size),
+            Image::None => {}
+        }
+    }
+
+    /// Draw an image from the texture atlas with the size, position and repeat of the background.
+    fn draw_tiles(
         &self,
-        _sb: &mut Renderer,
-        _shape: &impl Shape,
-        _rect: &Layout,
-        _viewport_size: &Size<u32>,
+        scene: &mut Scene,
+        image: &peniko::Image,
+        rect: epaint::Rect,
+        clip: epaint::Rect,
+        viewport_size: &Size<u32>,
     ) {
-        // First draw the background color
-
-        // let rect = shape.as_rect().unwrap();
-        // sb.rect(rect, self.color, viewport_size);
-        // sb.fill(
-        //     peniko::Fill::NonZero,
-        //     Affine::IDENTITY,
-        //     self.color,
-        //     None,
-        //     &shape,
-        // );
-
-        // self.image
-        //     .render(sb, shape, self.repeat, &rect.size, viewport_size)
+        let Some(region) = image_region(image) else {
+            return;
+        };
+        let area = Size {
+            width: rect.width(),
+            height: rect.height(),
+        };
+        let mut tile = self.tile_size(region.size, &area, viewport_size);
+        if tile.x <= 0.0 || tile.y <= 0.0 {
+            return;
+        }
+        // Round scales the tiles so a whole number of them fits
+        if self.repeat.x == BackgroundRepeatKeyword::Round {
+            tile.x = area.width / (area.width / tile.x).round().max(1.0);
+        }
+        if self.repeat.y == BackgroundRepeatKeyword::Round {
+            tile.y = area.height / (area.height / tile.y).round().max(1.0);
+        }
+        // Percentages position the image relative to the room left around it
+        let room = Size {
+            width: area.width - tile.x,
+            height: area.height - tile.y,
+        };
+        let offset = epaint::vec2(
+            self.position.x.resolve(Axis::X, &room, viewport_size) as f32,
+            self.position.y.resolve(Axis::Y, &room, viewport_size) as f32,
+        );
+        let columns = tile_starts(rect.min.x, area.width, tile.x, offset.x, self.repeat.x);
+        let rows = tile_starts(rect.min.y, area.height, tile.y, offset.y, self.repeat.y);
+
+        let mut mesh = epaint::Mesh::default();
+        for y in &rows {
+            for x in &columns {
+                let tile_rect = epaint::Rect::from_min_size(epaint::pos2(*x, *y), tile);
+                mesh.add_rect_with_uv(tile_rect, region.uv, Color32::WHITE);
+            }
+        }
+        if mesh.is_empty() {
+            return;
+        }
+        // Tiles that are cut off at the edges are clipped to the padding box
+        let clip = transform_rect(scene.transform, rect).intersect(clip);
+        scene.push(clip, epaint::Shape::mesh(mesh));
+    }
+
+    /// The size of one tile of the image.
+    fn tile_size(
+        &self,
+        image_size: epaint::Vec2,
+        area: &Size<f32>,
+        viewport_size: &Size<u32>,
+    ) -> epaint::Vec2 {
+        let scale_x = area.width / image_size.x;
+        let scale_y = area.height / image_size.y;
+        match &self.size {
+            BackgroundSize::Cover => image_size * scale_x.max(scale_y),
+            BackgroundSize::Contain => image_size * scale_x.min(scale_y),
+            BackgroundSize::Explicit { width, height } => {
+                let resolve = |length: &LengthPercentageOrAuto, axis: Axis| match length {
+                    LengthPercentageOrAuto::Auto => None,
+                    LengthPercentageOrAuto::LengthPercentage(length) => {
+                        Some(length.resolve(axis, area, viewport_size) as f32)
+                    }
+                };
+                // An auto side keeps the aspect ratio of the image
+                match (resolve(width, Axis::X), resolve(height, Axis::Y)) {
+                    (Some(width), Some(height)) => epaint::vec2(width, height),
+                    (Some(width), None) => epaint::vec2(width, width * image_size.y / image_size.x),
+                    (None, Some(height)) => {
+                        epaint::vec2(height * image_size.x / image_size.y, height)
+                    }
+                    (None, None) => image_size,
+                }
+            }
+        }
+    }
+}
+
+/// The most tiles that are drawn along one axis, so tiny tiles don't build huge meshes
+const MAX_TILES: f32 = 512.0;
+
+/// Where the tiles along one axis of an area start. `offset` is the position of the first tile relative to the start of the area.
+fn tile_starts(
+    start: f32,
+    length: f32,
+    tile: f32,
+    offset: f32,
+    repeat: BackgroundRepeatKeyword,
+) -> Vec<f32> {
+    let count = length / tile;
+    match repeat {
+        BackgroundRepeatKeyword::Repeat | BackgroundRepeatKeyword::Round if count <= MAX_TILES => {
+            // Tiles are repeated in both directions from the positioned one
+            let first = offset - (offset / tile).ceil() * tile;
+            (0..)
+                .map(|i| start + first + i as f32 * tile)
+                .take_while(|tile_start| *tile_start < start + length)
+                .collect()
+        }
+        // Whole tiles with the leftover space spread between them. A single tile is positioned like without repeat
+        BackgroundRepeatKeyword::Space if count >= 2.0 && count <= MAX_TILES => {
+            let count = count.floor();
+            let gap = (length - count * tile) / (count - 1.0);
+            (0..count as usize)
+                .map(|i| start + i as f32 * (tile + gap))
+                .collect()
+        }
+        _ => vec![start + offset],
     }
 }
 
@@ -148,12 +228,26 @@ impl Default for Background {
     fn default() -> Self {
         Background {
             color: Color::rgba8(255, 255, 255, 0),
-            // image: Image::default(),
-            // repeat: Repeat::default(),
+            image: Image::default(),
+            repeat: Repeat::default(),
+            position: BackgroundPosition::default(),
+            size: BackgroundSize::default(),
         }
     }
 }
 
+/// The style properties [`Background`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &[
+    "background",
+    "background-color",
+    "background-image",
+    "background-repeat",
+    "background-position",
+    "background-position-x",
+    "background-position-y",
+    "background-size",
+];
+
 #[partial_derive_state]
 impl State for Background {
     type ChildDependencies = ();
@@ -161,12 +255,7 @@ impl State for Background {
     type NodeDependencies = ();
 
     const NODE_MASK: NodeMaskBuilder<'static> =
-        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
-            "background",
-            "background-color",
-            "background-image",
-            "background-repeat",
-        ]));
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
 
     fn update<'a>(
         &mut self,
@@ -174,43 +263,98 @@ impl State for Background {
         _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
         _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
         _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
-        _ctx: &SendAnyMap,
+        ctx: &SendAnyMap,
     ) -> bool {
+        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
         let mut new = Background::default();
-        for attr in node_view.attributes().into_iter().flatten() {
+        let mut attributes: Vec<_> = node_view.attributes().into_iter().flatten().collect();
+        // The shorthand is applied first, so the longhands override it
+        attributes.sort_by_key(|attr| attr.attribute.name != "background");
+        for attr in attributes {
             if let Some(attr_value) = attr.value.as_text() {
-                match attr.attribute.name.as_str() {
+                let name = attr.attribute.name.as_str();
+                let valid = match name {
                     "background" => {
                         if let Ok(background) = background::Background::parse_string(attr_value) {
                             new.color = translate_color(&background.color);
-                            // new.repeat = background.repeat.into();
-                            // new.image = Image::try_create(background.image, ctx).expect(
-                            //     "attempted to convert a background Blitz does not support yet",
-                            // );
+                            new.repeat = background.repeat.into();
+                            new.position = background.position;
+                            new.size = background.size;
+                            match Image::try_create(background.image, ctx) {
+                                Some(image) => new.image = image,
+                                None => style_diagnostics.unsupported_value(name, attr_value),
+                            }
+                            true
+                        } else {
+                            false
                         }
                     }
                     "background-color" => {
                         if let Ok(new_color) = CssColor::parse_string(attr_value) {
                             new.color = translate_color(&new_color);
+                            true
+                        } else {
+                            false
                         }
                     }
                     "background-image" => {
-                        if let Ok(_image) =
+                        if let Ok(image) =
                             lightningcss::values::image::Image::parse_string(attr_value)
                         {
-                            // new.image = Image::try_create(image, ctx).expect(
-                            //     "attempted to convert a background Blitz does not support yet",
-                            // );
+                            match Image::try_create(image, ctx) {
+                                Some(image) => new.image = image,
+                                None => style_diagnostics.unsupported_value(name, attr_value),
+                            }
+                            true
+                        } else {
+                            false
                         }
                     }
                     "background-repeat" => {
-                        if let Ok(_repeat) = background::BackgroundRepeat::parse_string(attr_value)
-                        {
-                            // new.repeat = repeat.into();
+                        if let Ok(repeat) = background::BackgroundRepeat::parse_string(attr_value) {
+                            new.repeat = repeat.into();
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    "background-position" => {
+                        if let Ok(position) = BackgroundPosition::parse_string(attr_value) {
+                            new.position = position;
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    "background-position-x" => {
+                        if let Ok(x) = HorizontalPosition::parse_string(attr_value) {
+                            new.position.x = x;
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    "background-position-y" => {
+                        if let Ok(y) = VerticalPosition::parse_string(attr_value) {
+                            new.position.y = y;
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    "background-size" => {
+                        if let Ok(size) = BackgroundSize::parse_string(attr_value) {
+                            new.size = size;
+                            true
+                        } else {
+                            false
                         }
                     }
 
-                    _ => {}
+                    _ => true,
+                };
+                if !valid {
+                    style_diagnostics.invalid_value(name, attr_value);
                 }
             }
         }
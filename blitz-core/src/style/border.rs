@@ -3,19 +3,49 @@ use dioxus_native_core::prelude::*;
 use dioxus_native_core_macro::partial_derive_state;
 use lightningcss::properties::border::BorderColor;
 use lightningcss::properties::border::BorderSideWidth;
+use lightningcss::properties::border::BorderStyle;
 use lightningcss::properties::border::BorderWidth;
+use lightningcss::properties::border::LineStyle;
 use lightningcss::properties::border_radius::BorderRadius;
 use lightningcss::values::color::CssColor;
 use lightningcss::{properties::Property, stylesheet::ParserOptions};
 use shipyard::Component;
+use std::sync::Arc;
+
+use super::StyleDiagnostics;
 
 #[derive(Clone, PartialEq, Debug, Component)]
 pub(crate) struct Border {
     pub colors: BorderColor,
     pub width: BorderWidth,
     pub radius: BorderRadius,
+    pub style: BorderStyle,
 }
 
+/// The style properties [`Border`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &[
+    "border-color",
+    "border-top-color",
+    "border-right-color",
+    "border-bottom-color",
+    "border-left-color",
+    "border-radius",
+    "border-top-left-radius",
+    "border-top-right-radius",
+    "border-bottom-right-radius",
+    "border-bottom-left-radius",
+    "border-width",
+    "border-top-width",
+    "border-right-width",
+    "border-bottom-width",
+    "border-left-width",
+    "border-style",
+    "border-top-style",
+    "border-right-style",
+    "border-bottom-style",
+    "border-left-style",
+];
+
 #[partial_derive_state]
 impl State for Border {
     type ChildDependencies = ();
@@ -23,23 +53,7 @@ impl State for Border {
     type NodeDependencies = ();
 
     const NODE_MASK: NodeMaskBuilder<'static> =
-        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&[
-            "border-color",
-            "border-top-color",
-            "border-right-color",
-            "border-bottom-color",
-            "border-left-color",
-            "border-radius",
-            "border-top-left-radius",
-            "border-top-right-radius",
-            "border-bottom-right-radius",
-            "border-bottom-left-radius",
-            "border-width",
-            "border-top-width",
-            "border-right-width",
-            "border-bottom-width",
-            "border-left-width",
-        ]));
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
 
     fn update<'a>(
         &mut self,
@@ -47,20 +61,24 @@ impl State for Border {
         _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
         _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
         _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
-        _: &SendAnyMap,
+        ctx: &SendAnyMap,
     ) -> bool {
+        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
         let mut new = Border::default();
         if let Some(attributes) = node_view.attributes() {
             for a in attributes {
-                let mut value = ParserInput::new(a.value.as_text().unwrap());
+                let as_text = a.value.as_text().unwrap();
+                let mut value = ParserInput::new(as_text);
                 let mut parser = Parser::new(&mut value);
-                match Property::parse(
+                let Ok(property) = Property::parse(
                     a.attribute.name.as_str().into(),
                     &mut parser,
                     &ParserOptions::default(),
-                )
-                .unwrap()
-                {
+                ) else {
+                    style_diagnostics.invalid_value(&a.attribute.name, as_text);
+                    continue;
+                };
+                match property {
                     Property::BorderColor(c) => {
                         new.colors = c;
                     }
@@ -106,6 +124,21 @@ impl State for Border {
                     Property::BorderLeftWidth(width) => {
                         new.width.left = width;
                     }
+                    Property::BorderStyle(style) => {
+                        new.style = style;
+                    }
+                    Property::BorderTopStyle(style) => {
+                        new.style.top = style;
+                    }
+                    Property::BorderRightStyle(style) => {
+                        new.style.right = style;
+                    }
+                    Property::BorderBottomStyle(style) => {
+                        new.style.bottom = style;
+                    }
+                    Property::BorderLeftStyle(style) => {
+                        new.style.left = style;
+                    }
                     _ => {}
                 }
             }
@@ -148,6 +181,13 @@ impl Default for Border {
                 bottom: BorderSideWidth::default(),
                 left: BorderSideWidth::default(),
             },
+            // Blitz draws a solid border whenever a border width is set
+            style: BorderStyle {
+                top: LineStyle::Solid,
+                right: LineStyle::Solid,
+                bottom: LineStyle::Solid,
+                left: LineStyle::Solid,
+            },
         }
     }
 }
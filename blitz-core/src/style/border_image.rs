@@ -0,0 +1,393 @@
+use std::sync::Arc;
+
+use cssparser::{Parser, ParserInput};
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use epaint::{Color32, Mesh, Rect};
+use lightningcss::properties::border_image::{
+    BorderImageRepeat, BorderImageRepeatKeyword, BorderImageSideWidth, BorderImageSlice,
+};
+use lightningcss::values::image::Image;
+use lightningcss::values::length::LengthOrNumber;
+use lightningcss::values::percentage::NumberOrPercentage;
+use lightningcss::values::rect::Rect as Sides;
+use lightningcss::{properties::Property, stylesheet::ParserOptions};
+use shipyard::Component;
+use taffy::prelude::Size;
+
+use crate::atlas::image_region;
+use crate::images::load_image;
+use crate::scene::Scene;
+use crate::util::{Axis, Resolve};
+
+use super::StyleDiagnostics;
+
+/// An image that is drawn instead of the border, sliced into corners, edges and a middle so it stretches to any size without distorting the corners.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct BorderImage {
+    pub image: Option<Arc<peniko::Image>>,
+    slice: BorderImageSlice,
+    width: Sides<BorderImageSideWidth>,
+    outset: Sides<LengthOrNumber>,
+    repeat: BorderImageRepeat,
+}
+
+/// The style properties [`BorderImage`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &[
+    "border-image",
+    "border-image-source",
+    "border-image-slice",
+    "border-image-width",
+    "border-image-outset",
+    "border-image-repeat",
+];
+
+#[partial_derive_state]
+impl State for BorderImage {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+        let mut new = BorderImage::default();
+        let mut attributes: Vec<_> = node_view.attributes().into_iter().flatten().collect();
+        // The shorthand is applied first, so the longhands override it
+        attributes.sort_by_key(|a| a.attribute.name != "border-image");
+        for a in attributes {
+            let Some(as_text) = a.value.as_text() else {
+                continue;
+            };
+            let mut value = ParserInput::new(as_text);
+            let mut parser = Parser::new(&mut value);
+            let Ok(property) = Property::parse(
+                a.attribute.name.as_str().into(),
+                &mut parser,
+                &ParserOptions::default(),
+            ) else {
+                style_diagnostics.invalid_value(&a.attribute.name, as_text);
+                continue;
+            };
+            let source = match property {
+                Property::BorderImage(border_image, _) => {
+                    new.slice = border_image.slice;
+                    new.width = border_image.width;
+                    new.outset = border_image.outset;
+                    new.repeat = border_image.repeat;
+                    border_image.source
+                }
+                Property::BorderImageSource(source) => source,
+                Property::BorderImageSlice(slice) => {
+                    new.slice = slice;
+                    continue;
+                }
+                Property::BorderImageWidth(width) => {
+                    new.width = width;
+                    continue;
+                }
+                Property::BorderImageOutset(outset) => {
+                    new.outset = outset;
+                    continue;
+                }
+                Property::BorderImageRepeat(repeat) => {
+                    new.repeat = repeat;
+                    continue;
+                }
+                _ => continue,
+            };
+            new.image = match source {
+                Image::None => None,
+                Image::Url(url) => load_image(url.url.as_ref()),
+                // TODO: Slice gradients
+                _ => {
+                    style_diagnostics.unsupported_value(&a.attribute.name, as_text);
+                    None
+                }
+            };
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+impl BorderImage {
+    /// Draw the nine slices of the image around a border box. `border_widths` are the widths of the top, right, bottom and left border.
+    pub(crate) fn draw(
+        &self,
+        scene: &mut Scene,
+        border_box: Rect,
+        border_widths: [f32; 4],
+        clip: Rect,
+        viewport_size: &Size<u32>,
+    ) {
+        let Some(image) = &self.image else {
+            return;
+        };
+        let Some(region) = image_region(image) else {
+            return;
+        };
+        let image_size = region.size;
+        let box_size = Size {
+            width: border_box.width(),
+            height: border_box.height(),
+        };
+
+        // The slices are in pixels of the image, from the top, right, bottom and left edge
+        let slice = |offset: &NumberOrPercentage, length: f32| match offset {
+            NumberOrPercentage::Number(number) => number.min(length),
+            NumberOrPercentage::Percentage(percentage) => (percentage.0 * length).min(length),
+        };
+        let offsets = &self.slice.offsets;
+        let slices = [
+            slice(&offsets.0, image_size.y),
+            slice(&offsets.1, image_size.x),
+            slice(&offsets.2, image_size.y),
+            slice(&offsets.3, image_size.x),
+        ];
+
+        let outset = |outset: &LengthOrNumber, border_width: f32| match outset {
+            LengthOrNumber::Number(number) => number * border_width,
+            LengthOrNumber::Length(length) => {
+                length.resolve(Axis::Min, &box_size, viewport_size) as f32
+            }
+        };
+        let outsets = &self.outset;
+        let area = Rect::from_min_max(
+            border_box.min
+                - epaint::vec2(
+                    outset(&outsets.3, border_widths[3]),
+                    outset(&outsets.0, border_widths[0]),
+                ),
+            border_box.max
+                + epaint::vec2(
+                    outset(&outsets.1, border_widths[1]),
+                    outset(&outsets.2, border_widths[2]),
+                ),
+        );
+
+        let width =
+            |width: &BorderImageSideWidth, border_width: f32, slice: f32, axis: Axis| match width {
+                BorderImageSideWidth::Number(number) => number * border_width,
+                BorderImageSideWidth::LengthPercentage(length) => {
+                    let area_size = Size {
+                        width: area.width(),
+                        height: area.height(),
+                    };
+                    length.resolve(axis, &area_size, viewport_size) as f32
+                }
+                BorderImageSideWidth::Auto => slice,
+            };
+        let widths = &self.width;
+        let mut widths = [
+            width(&widths.0, border_widths[0], slices[0], Axis::Y),
+            width(&widths.1, border_widths[1], slices[1], Axis::X),
+            width(&widths.2, border_widths[2], slices[2], Axis::Y),
+            width(&widths.3, border_widths[3], slices[3], Axis::X),
+        ];
+        // Opposite sides that overlap are scaled down together
+        let scale = (area.height() / (widths[0] + widths[2]))
+            .min(area.width() / (widths[1] + widths[3]))
+            .min(1.0);
+        if scale < 1.0 {
+            widths = widths.map(|width| width * scale);
+        }
+
+        // The edges between the three columns and rows, in the area and in the image from 0 to 1
+        let columns = [
+            area.min.x,
+            area.min.x + widths[3],
+            area.max.x - widths[1],
+            area.max.x,
+        ];
+        let rows = [
+            area.min.y,
+            area.min.y + widths[0],
+            area.max.y - widths[2],
+            area.max.y,
+        ];
+        let image_columns = [
+            0.0,
+            slices[3] / image_size.x,
+            1.0 - slices[1] / image_size.x,
+            1.0,
+        ];
+        let image_rows = [
+            0.0,
+            slices[0] / image_size.y,
+            1.0 - slices[2] / image_size.y,
+            1.0,
+        ];
+
+        let mut mesh = Mesh::default();
+        for row in 0..3 {
+            for column in 0..3 {
+                // The middle is only drawn with the fill keyword
+                if row == 1 && column == 1 && !self.slice.fill {
+                    continue;
+                }
+                let image_x = [image_columns[column], image_columns[column + 1]];
+                let image_y = [image_rows[row], image_rows[row + 1]];
+                let slice_size = epaint::vec2(
+                    (image_x[1] - image_x[0]) * image_size.x,
+                    (image_y[1] - image_y[0]) * image_size.y,
+                );
+                if slice_size.x <= 0.0 || slice_size.y <= 0.0 {
+                    continue;
+                }
+                let target = Rect::from_x_y_ranges(
+                    columns[column]..=columns[column + 1],
+                    rows[row]..=rows[row + 1],
+                );
+                if !target.is_positive() {
+                    continue;
+                }
+                // Edges are scaled to the width of their border and then tiled along it. The middle is scaled like the edges around it
+                let tile_width = match column {
+                    1 if row == 1 => slice_size.x * scale_of(widths[0], slices[0]),
+                    1 => slice_size.x * target.height() / slice_size.y,
+                    _ => target.width(),
+                };
+                let tile_height = match row {
+                    1 if column == 1 => slice_size.y * scale_of(widths[3], slices[3]),
+                    1 => slice_size.y * target.width() / slice_size.x,
+                    _ => target.height(),
+                };
+                let horizontal = tile(
+                    target.min.x,
+                    target.width(),
+                    tile_width,
+                    self.repeat.horizontal,
+                );
+                let vertical = tile(
+                    target.min.y,
+                    target.height(),
+                    tile_height,
+                    self.repeat.vertical,
+                );
+                for (x, x_fraction) in &horizontal {
+                    for (y, y_fraction) in &vertical {
+                        let lerp = |range: [f32; 2], t: f32| range[0] + (range[1] - range[0]) * t;
+                        let uv = |x: f32, y: f32| {
+                            region.uv.min
+                                + epaint::vec2(
+                                    lerp(image_x, x) * region.uv.width(),
+                                    lerp(image_y, y) * region.uv.height(),
+                                )
+                        };
+                        mesh.add_rect_with_uv(
+                            Rect::from_x_y_ranges(x.clone(), y.clone()),
+                            Rect::from_min_max(
+                                uv(*x_fraction.start(), *y_fraction.start()),
+                                uv(*x_fraction.end(), *y_fraction.end()),
+                            ),
+                            Color32::WHITE,
+                        );
+                    }
+                }
+            }
+        }
+        if !mesh.is_empty() {
+            scene.push(clip, epaint::Shape::mesh(mesh));
+        }
+    }
+}
+
+/// How much a slice is scaled to fit its border, or 1 if the slice is empty
+fn scale_of(width: f32, slice: f32) -> f32 {
+    if slice > 0.0 {
+        width / slice
+    } else {
+        1.0
+    }
+}
+
+type Span = std::ops::RangeInclusive<f32>;
+
+/// Split a span starting at `start` into the tiles of an edge along one axis. Returns the span of each tile and the part of the slice it shows from 0 to 1.
+fn tile(
+    start: f32,
+    length: f32,
+    tile_length: f32,
+    repeat: BorderImageRepeatKeyword,
+) -> Vec<(Span, Span)> {
+    let whole = vec![(start..=start + length, 0.0..=1.0)];
+    if tile_length <= 0.0 || tile_length >= length {
+        return whole;
+    }
+    // Limits the number of quads for tiny tiles
+    let count = length / tile_length;
+    if count > 256.0 {
+        return whole;
+    }
+    match repeat {
+        BorderImageRepeatKeyword::Stretch => whole,
+        // Whole tiles, scaled so they fill the edge
+        BorderImageRepeatKeyword::Round => {
+            let count = count.round().max(1.0) as usize;
+            let tile_length = length / count as f32;
+            (0..count)
+                .map(|i| {
+                    let tile_start = start + i as f32 * tile_length;
+                    (tile_start..=tile_start + tile_length, 0.0..=1.0)
+                })
+                .collect()
+        }
+        // Whole tiles with the leftover space spread evenly between them
+        BorderImageRepeatKeyword::Space => {
+            let count = count.floor() as usize;
+            let gap = (length - count as f32 * tile_length) / (count + 1) as f32;
+            (0..count)
+                .map(|i| {
+                    let tile_start = start + gap + i as f32 * (tile_length + gap);
+                    (tile_start..=tile_start + tile_length, 0.0..=1.0)
+                })
+                .collect()
+        }
+        // Tiles centered on the edge, with partial tiles cut off at both ends
+        BorderImageRepeatKeyword::Repeat => {
+            let first = start + (length - tile_length) / 2.0
+                - ((length - tile_length) / 2.0 / tile_length).ceil() * tile_length;
+            let end = start + length;
+            let mut tiles = Vec::new();
+            let mut tile_start = first;
+            while tile_start < end {
+                let visible_start = tile_start.max(start);
+                let visible_end = (tile_start + tile_length).min(end);
+                tiles.push((
+                    visible_start..=visible_end,
+                    (visible_start - tile_start) / tile_length
+                        ..=(visible_end - tile_start) / tile_length,
+                ));
+                tile_start += tile_length;
+            }
+            tiles
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt::Display;
+use std::sync::{Mutex, PoisonError};
+
+use lightningcss::properties::PropertyId;
+use rustc_hash::FxHashSet;
+
+/// Every style property that Blitz currently resolves, from the states that resolve them. Anything else is either unknown to CSS or not supported yet.
+const SUPPORTED_PROPERTIES: &[&[&str]] = &[
+    crate::layout::LAYOUT_PROPERTIES,
+    super::overflow::PROPERTIES,
+    super::z_index::PROPERTIES,
+    super::will_change::PROPERTIES,
+    super::foreground::PROPERTIES,
+    super::text::PROPERTIES,
+    super::background::PROPERTIES,
+    super::border::PROPERTIES,
+    super::border_image::PROPERTIES,
+    super::outline::PROPERTIES,
+    super::opacity::PROPERTIES,
+    super::filter::BACKDROP_FILTER_PROPERTIES,
+    super::filter::FILTER_PROPERTIES,
+    super::transform::PROPERTIES,
+    super::pointer_events::PROPERTIES,
+    super::user_select::PROPERTIES,
+    super::touch_action::PROPERTIES,
+    super::resize::PROPERTIES,
+    super::split_divider::PROPERTIES,
+];
+
+/// The supported properties in the order they are listed
+fn supported_properties() -> impl Iterator<Item = &'static str> {
+    SUPPORTED_PROPERTIES
+        .iter()
+        .flat_map(|properties| properties.iter().copied())
+}
+
+/// A problem found while resolving the style of a node.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StyleDiagnostic {
+    /// The property is not a CSS property
+    UnknownProperty {
+        name: String,
+        suggestion: Option<&'static str>,
+    },
+    /// The property is valid CSS, but Blitz doesn't support it yet
+    UnsupportedProperty { name: String },
+    /// The value of a supported property failed to parse
+    InvalidValue { name: String, value: String },
+    /// The value is valid CSS, but Blitz doesn't support it yet
+    UnsupportedValue { name: String, value: String },
+}
+
+impl Display for StyleDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StyleDiagnostic::UnknownProperty {
+                name,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "unknown style property `{name}`, did you mean `{suggestion}`?"
+            ),
+            StyleDiagnostic::UnknownProperty {
+                name,
+                suggestion: None,
+            } => write!(f, "unknown style property `{name}`"),
+            StyleDiagnostic::UnsupportedProperty { name } => {
+                write!(f, "style property `{name}` is not supported by Blitz yet")
+            }
+            StyleDiagnostic::InvalidValue { name, value } => {
+                write!(f, "invalid value `{value}` for style property `{name}`")
+            }
+            StyleDiagnostic::UnsupportedValue { name, value } => write!(
+                f,
+                "value `{value}` for style property `{name}` is not supported by Blitz yet"
+            ),
+        }
+    }
+}
+
+/// Collects style diagnostics for the whole document. Each unique diagnostic is only reported once.
+///
+/// Diagnostics are logged as warnings when they are first found and can be drained by tooling like an inspector.
+#[derive(Default)]
+pub struct StyleDiagnostics {
+    seen: Mutex<FxHashSet<StyleDiagnostic>>,
+    pending: Mutex<Vec<StyleDiagnostic>>,
+}
+
+impl StyleDiagnostics {
+    pub(crate) fn report(&self, diagnostic: StyleDiagnostic) {
+        if self.seen.lock().unwrap().insert(diagnostic.clone()) {
+            log::warn!("{diagnostic}");
+            self.pending.lock().unwrap().push(diagnostic);
+        }
+    }
+
+    /// Reports the property if Blitz doesn't resolve it.
+    pub(crate) fn check_property(&self, name: &str) {
+        if supported_properties().any(|property| property == name) {
+            return;
+        }
+        let diagnostic = match PropertyId::from(name) {
+            PropertyId::Custom(_) => StyleDiagnostic::UnknownProperty {
+                name: name.to_string(),
+                suggestion: suggest_property(name),
+            },
+            _ => StyleDiagnostic::UnsupportedProperty {
+                name: name.to_string(),
+            },
+        };
+        self.report(diagnostic);
+    }
+
+    pub(crate) fn invalid_value(&self, name: &str, value: &str) {
+        self.report(StyleDiagnostic::InvalidValue {
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+    }
+
+    pub(crate) fn unsupported_value(&self, name: &str, value: &str) {
+        self.report(StyleDiagnostic::UnsupportedValue {
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+    }
+
+    /// Take all of the diagnostics reported since the last time this was called.
+    pub fn drain(&self) -> Vec<StyleDiagnostic> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+
+    /// Every diagnostic reported so far, including the ones that were already drained.
+    pub fn all(&self) -> Vec<StyleDiagnostic> {
+        // This is also called while writing a crash report, so a poisoned lock shouldn't cause another panic
+        let seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+        seen.iter().cloned().collect()
+    }
+}
+
+/// Find the closest supported property to a misspelled one
+fn suggest_property(name: &str) -> Option<&'static str> {
+    // Only suggest properties that are a few typos away
+    let max_distance = (name.len() / 3).max(1);
+    supported_properties()
+        .map(|property| (property, edit_distance(name, property)))
+        .filter(|(_, distance)| *distance <= max_distance)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(property, _)| property)
+}
+
+/// The number of insertions, deletions, substitutions and transpositions needed to turn one string into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn property_suggestions() {
+        assert_eq!(
+            suggest_property("backgrond-color"),
+            Some("background-color")
+        );
+        assert_eq!(suggest_property("widht"), Some("width"));
+        assert_eq!(suggest_property("border-radios"), Some("border-radius"));
+        assert_eq!(suggest_property("this-is-not-css"), None);
+    }
+
+    #[test]
+    fn properties_are_listed_once() {
+        let mut seen = FxHashSet::default();
+        for property in supported_properties() {
+            // Each property is resolved by one state
+            assert!(seen.insert(property), "{property} is listed twice");
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::properties::effects::{Filter as FilterFunction, FilterList};
+use lightningcss::traits::Parse;
+use lightningcss::values::length::Length;
+use lightningcss::values::percentage::NumberOrPercentage;
+use shipyard::Component;
+
+use crate::backend::ColorMatrix;
+
+use super::StyleDiagnostics;
+
+/// The color filters of an element and its children, combined into one matrix. The subtree is drawn offscreen and filtered as a whole by backends that support it.
+///
+/// Colors are filtered in linear space, so the results differ a little from browsers, which filter sRGB colors.
+#[derive(Clone, Copy, PartialEq, Debug, Component)]
+pub(crate) struct Filter(pub ColorMatrix);
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter(ColorMatrix::IDENTITY)
+    }
+}
+
+/// The style properties [`Filter`] is resolved from
+pub(super) const FILTER_PROPERTIES: &[&str] = &["filter"];
+
+#[partial_derive_state]
+impl State for Filter {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(FILTER_PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+        let mut new = Filter::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                let name = attr.attribute.name.as_str();
+                match FilterList::parse_string(attr_value) {
+                    Ok(FilterList::None) => {}
+                    Ok(FilterList::Filters(filters)) => {
+                        // The filters are applied in order
+                        for filter in &filters {
+                            match color_matrix(filter) {
+                                Some(matrix) => new.0 = new.0.then(matrix),
+                                None => style_diagnostics.unsupported_value(name, attr_value),
+                            }
+                        }
+                    }
+                    Err(_) => style_diagnostics.invalid_value(name, attr_value),
+                }
+            }
+        }
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+/// The matrix of a filter function, with the values from the [filter effects spec](https://www.w3.org/TR/filter-effects-1/#filter-functions). Returns None for filters that aren't color matrices.
+///
+/// TODO: Support the other color matrix filters
+fn color_matrix(filter: &FilterFunction) -> Option<ColorMatrix> {
+    let amount = |amount: &NumberOrPercentage| match amount {
+        NumberOrPercentage::Number(number) => *number,
+        NumberOrPercentage::Percentage(percentage) => percentage.0,
+    };
+    let matrix = match filter {
+        FilterFunction::Grayscale(grayscale) => {
+            let keep = 1.0 - amount(grayscale).clamp(0.0, 1.0);
+            ColorMatrix([
+                [
+                    0.2126 + 0.7874 * keep,
+                    0.7152 - 0.7152 * keep,
+                    0.0722 - 0.0722 * keep,
+                    0.0,
+                ],
+                [
+                    0.2126 - 0.2126 * keep,
+                    0.7152 + 0.2848 * keep,
+                    0.0722 - 0.0722 * keep,
+                    0.0,
+                ],
+                [
+                    0.2126 - 0.2126 * keep,
+                    0.7152 - 0.7152 * keep,
+                    0.0722 + 0.9278 * keep,
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ])
+        }
+        FilterFunction::Saturate(saturate) => {
+            let saturate = amount(saturate).max(0.0);
+            ColorMatrix([
+                [
+                    0.213 + 0.787 * saturate,
+                    0.715 - 0.715 * saturate,
+                    0.072 - 0.072 * saturate,
+                    0.0,
+                ],
+                [
+                    0.213 - 0.213 * saturate,
+                    0.715 + 0.285 * saturate,
+                    0.072 - 0.072 * saturate,
+                    0.0,
+                ],
+                [
+                    0.213 - 0.213 * saturate,
+                    0.715 - 0.715 * saturate,
+                    0.072 + 0.928 * saturate,
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ])
+        }
+        FilterFunction::Brightness(brightness) => {
+            let brightness = amount(brightness).max(0.0);
+            ColorMatrix([
+                [brightness, 0.0, 0.0, 0.0],
+                [0.0, brightness, 0.0, 0.0],
+                [0.0, 0.0, brightness, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ])
+        }
+        // Moves colors away from or towards gray
+        FilterFunction::Contrast(contrast) => {
+            let contrast = amount(contrast).max(0.0);
+            let offset = 0.5 - 0.5 * contrast;
+            ColorMatrix([
+                [contrast, 0.0, 0.0, offset],
+                [0.0, contrast, 0.0, offset],
+                [0.0, 0.0, contrast, offset],
+                [0.0, 0.0, 0.0, 1.0],
+            ])
+        }
+        _ => return None,
+    };
+    Some(matrix)
+}
+
+/// The filters applied to what is drawn behind an element before the element is drawn over it, like a frosted glass panel.
+///
+/// TODO: Support the other filter functions and clip the blur to the rounded corners of the element
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct BackdropFilter {
+    /// The standard deviation of the blur
+    pub blur: Option<Length>,
+}
+
+/// The style properties [`BackdropFilter`] is resolved from
+pub(super) const BACKDROP_FILTER_PROPERTIES: &[&str] = &["backdrop-filter"];
+
+#[partial_derive_state]
+impl State for BackdropFilter {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(BACKDROP_FILTER_PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+        let mut new = BackdropFilter::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                let name = attr.attribute.name.as_str();
+                match FilterList::parse_string(attr_value) {
+                    Ok(FilterList::None) => {}
+                    Ok(FilterList::Filters(filters)) => {
+                        for filter in filters {
+                            match filter {
+                                FilterFunction::Blur(length) => new.blur = Some(length),
+                                _ => style_diagnostics.unsupported_value(name, attr_value),
+                            }
+                        }
+                    }
+                    Err(_) => style_diagnostics.invalid_value(name, attr_value),
+                }
+            }
+        }
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
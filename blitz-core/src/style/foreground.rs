@@ -4,6 +4,9 @@ use dioxus_native_core_macro::partial_derive_state;
 use lightningcss::traits::Parse;
 use lightningcss::values::color::CssColor;
 use shipyard::Component;
+use std::sync::Arc;
+
+use super::StyleDiagnostics;
 
 #[derive(Clone, PartialEq, Debug, Component)]
 pub(crate) struct ForgroundColor(pub CssColor);
@@ -14,13 +17,16 @@ impl Default for ForgroundColor {
     }
 }
 
+/// The style properties [`ForgroundColor`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &["color"];
+
 #[partial_derive_state]
 impl State for ForgroundColor {
     type ChildDependencies = ();
     type ParentDependencies = (Self,);
     type NodeDependencies = ();
     const NODE_MASK: NodeMaskBuilder<'static> =
-        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(&["color"]));
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
 
     fn update<'a>(
         &mut self,
@@ -28,7 +34,7 @@ impl State for ForgroundColor {
         _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
         parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
         _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
-        _: &SendAnyMap,
+        ctx: &SendAnyMap,
     ) -> bool {
         let new = if let Some(color_attr) = node_view.attributes().into_iter().flatten().next() {
             if let Some(as_text) = color_attr.value.as_text() {
@@ -37,6 +43,8 @@ impl State for ForgroundColor {
                 if let Ok(new_color) = CssColor::parse(&mut parser) {
                     new_color
                 } else {
+                    let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+                    style_diagnostics.invalid_value("color", as_text);
                     return false;
                 }
             } else {
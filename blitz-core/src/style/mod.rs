@@ -1,7 +1,37 @@
 mod background;
 mod border;
+mod border_image;
+mod diagnostics;
+mod filter;
 mod foreground;
+mod opacity;
+mod outline;
+mod overflow;
+mod pointer_events;
+mod resize;
+mod split_divider;
+mod text;
+mod touch_action;
+mod transform;
+mod user_select;
+mod will_change;
+mod z_index;
 
 pub(crate) use background::Background;
 pub(crate) use border::Border;
+pub(crate) use border_image::BorderImage;
+pub use diagnostics::{StyleDiagnostic, StyleDiagnostics};
+pub(crate) use filter::{BackdropFilter, Filter};
 pub(crate) use foreground::ForgroundColor;
+pub(crate) use opacity::Opacity;
+pub(crate) use outline::Outline;
+pub(crate) use overflow::Overflow;
+pub(crate) use pointer_events::PointerEvents;
+pub(crate) use resize::Resize;
+pub(crate) use split_divider::SplitDivider;
+pub(crate) use text::{Direction, TextStyle, WhiteSpace, DEFAULT_FONT_SIZE};
+pub(crate) use touch_action::TouchAction;
+pub(crate) use transform::Transform;
+pub(crate) use user_select::UserSelect;
+pub(crate) use will_change::WillChange;
+pub(crate) use z_index::ZIndex;
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::traits::Parse;
+use lightningcss::values::alpha::AlphaValue;
+use shipyard::Component;
+
+use super::StyleDiagnostics;
+
+/// The opacity of an element and its children. The subtree is composited as a group, so overlapping children don't show through each other.
+#[derive(Clone, Copy, PartialEq, Debug, Component)]
+pub(crate) struct Opacity(pub f32);
+
+impl Default for Opacity {
+    fn default() -> Self {
+        Opacity(1.0)
+    }
+}
+
+/// The style properties [`Opacity`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &["opacity"];
+
+#[partial_derive_state]
+impl State for Opacity {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let mut new = Opacity::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                match AlphaValue::parse_string(attr_value) {
+                    Ok(alpha) => new = Opacity(alpha.0.clamp(0.0, 1.0)),
+                    Err(_) => {
+                        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+                        style_diagnostics.invalid_value("opacity", attr_value);
+                    }
+                }
+            }
+        }
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
@@ -0,0 +1,112 @@
+use cssparser::{Parser, ParserInput};
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::properties::border::BorderSideWidth;
+use lightningcss::properties::outline::OutlineStyle;
+use lightningcss::traits::Parse;
+use lightningcss::values::color::CssColor;
+use lightningcss::values::length::Length;
+use lightningcss::{properties::Property, stylesheet::ParserOptions};
+use shipyard::Component;
+use std::sync::Arc;
+
+use super::StyleDiagnostics;
+
+/// The line drawn around the border box of an element, outside of its layout. Properties that aren't set are `None`.
+///
+/// Focused elements with an outline style of `auto` or without one get a focus ring, so keyboard users can see where focus is. The other outline properties customize the ring and `outline: none` turns it off.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub(crate) struct Outline {
+    pub style: Option<OutlineStyle>,
+    pub width: Option<BorderSideWidth>,
+    pub color: Option<CssColor>,
+    /// The gap between the border box and the outline
+    pub offset: Option<Length>,
+}
+
+/// The style properties [`Outline`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &[
+    "outline",
+    "outline-color",
+    "outline-style",
+    "outline-width",
+    "outline-offset",
+];
+
+#[partial_derive_state]
+impl State for Outline {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+        let mut new = Outline::default();
+        let mut attributes: Vec<_> = node_view.attributes().into_iter().flatten().collect();
+        // The shorthand is applied first, so the longhands override it
+        attributes.sort_by_key(|a| a.attribute.name != "outline");
+        for a in attributes {
+            let Some(as_text) = a.value.as_text() else {
+                continue;
+            };
+            // lightningcss doesn't know outline-offset, it is a plain length
+            if a.attribute.name == "outline-offset" {
+                match Length::parse_string(as_text) {
+                    Ok(offset) => new.offset = Some(offset),
+                    Err(_) => style_diagnostics.invalid_value(&a.attribute.name, as_text),
+                }
+                continue;
+            }
+            let mut value = ParserInput::new(as_text);
+            let mut parser = Parser::new(&mut value);
+            let Ok(property) = Property::parse(
+                a.attribute.name.as_str().into(),
+                &mut parser,
+                &ParserOptions::default(),
+            ) else {
+                style_diagnostics.invalid_value(&a.attribute.name, as_text);
+                continue;
+            };
+            match property {
+                Property::Outline(outline) => {
+                    new.style = Some(outline.style);
+                    new.width = Some(outline.width);
+                    new.color = Some(outline.color);
+                }
+                Property::OutlineStyle(style) => new.style = Some(style),
+                Property::OutlineWidth(width) => new.width = Some(width),
+                Property::OutlineColor(color) => new.color = Some(color),
+                _ => {}
+            }
+        }
+
+        if self != &mut new {
+            *self = new;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::properties::overflow::{self, OverflowKeyword};
+use lightningcss::traits::Parse;
+use shipyard::Component;
+
+use super::StyleDiagnostics;
+
+#[derive(Clone, Copy, PartialEq, Debug, Component)]
+pub(crate) struct Overflow {
+    pub x: OverflowKeyword,
+    pub y: OverflowKeyword,
+}
+
+impl Overflow {
+    /// Returns true if children are clipped to the padding box along the x axis
+    pub fn clips_x(&self) -> bool {
+        clips(self.x)
+    }
+
+    /// Returns true if children are clipped to the padding box along the y axis
+    pub fn clips_y(&self) -> bool {
+        clips(self.y)
+    }
+
+    /// Returns true if the content can be scrolled along the x axis
+    pub fn scrolls_x(&self) -> bool {
+        scrolls(self.x)
+    }
+
+    /// Returns true if the content can be scrolled along the y axis
+    pub fn scrolls_y(&self) -> bool {
+        scrolls(self.y)
+    }
+
+    /// Returns true if the track of the scrollbar along the x axis is shown even if the content fits, like for `overflow: scroll`
+    pub fn always_scrollbar_x(&self) -> bool {
+        self.x == OverflowKeyword::Scroll
+    }
+
+    /// Returns true if the track of the scrollbar along the y axis is shown even if the content fits
+    pub fn always_scrollbar_y(&self) -> bool {
+        self.y == OverflowKeyword::Scroll
+    }
+}
+
+fn scrolls(overflow: OverflowKeyword) -> bool {
+    matches!(overflow, OverflowKeyword::Scroll | OverflowKeyword::Auto)
+}
+
+fn clips(overflow: OverflowKeyword) -> bool {
+    !matches!(overflow, OverflowKeyword::Visible)
+}
+
+impl Default for Overflow {
+    fn default() -> Self {
+        Overflow {
+            x: OverflowKeyword::Visible,
+            y: OverflowKeyword::Visible,
+        }
+    }
+}
+
+/// The style properties [`Overflow`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &["overflow", "overflow-x", "overflow-y"];
+
+#[partial_derive_state]
+impl State for Overflow {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+        let mut new = Overflow::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                let name = attr.attribute.name.as_str();
+                let valid = match name {
+                    "overflow" => {
+                        if let Ok(overflow) = overflow::Overflow::parse_string(attr_value) {
+                            new.x = overflow.x;
+                            new.y = overflow.y;
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    "overflow-x" => {
+                        if let Ok(overflow) = OverflowKeyword::parse_string(attr_value) {
+                            new.x = overflow;
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    "overflow-y" => {
+                        if let Ok(overflow) = OverflowKeyword::parse_string(attr_value) {
+                            new.y = overflow;
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    _ => true,
+                };
+                if !valid {
+                    style_diagnostics.invalid_value(name, attr_value);
+                }
+            }
+        }
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
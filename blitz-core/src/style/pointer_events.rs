@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+
+use super::StyleDiagnostics;
+
+/// False if `pointer-events: none` lets the pointer pass through the element to the elements below it, so decorative overlays don't take the clicks and hovers of the content under them. The property is inherited, so a child of such an element can take events again with `pointer-events: auto`.
+#[derive(Clone, Copy, PartialEq, Debug, Component)]
+pub(crate) struct PointerEvents(pub bool);
+
+impl Default for PointerEvents {
+    fn default() -> Self {
+        PointerEvents(true)
+    }
+}
+
+/// The style properties [`PointerEvents`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &["pointer-events"];
+
+#[partial_derive_state]
+impl State for PointerEvents {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let inherited = parent.map(|(parent,)| *parent).unwrap_or_default();
+        let mut new = inherited;
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                match attr_value.trim() {
+                    "auto" => new = PointerEvents(true),
+                    "none" => new = PointerEvents(false),
+                    "inherit" => new = inherited,
+                    _ => {
+                        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+                        style_diagnostics.invalid_value("pointer-events", attr_value);
+                    }
+                }
+            }
+        }
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+
+use super::StyleDiagnostics;
+
+/// The axes the user can resize an element along with the grip in its bottom right corner, from `resize`. Like in a browser, only elements that clip their content can be resized, see [`crate::resize`]. The property isn't inherited.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Component)]
+pub(crate) enum Resize {
+    #[default]
+    None,
+    Both,
+    Horizontal,
+    Vertical,
+}
+
+impl Resize {
+    pub fn resizes_x(&self) -> bool {
+        matches!(self, Resize::Both | Resize::Horizontal)
+    }
+
+    pub fn resizes_y(&self) -> bool {
+        matches!(self, Resize::Both | Resize::Vertical)
+    }
+}
+
+/// The style properties [`Resize`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &["resize"];
+
+#[partial_derive_state]
+impl State for Resize {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let mut new = Resize::None;
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                // Text is laid out horizontally, so the block axis is vertical
+                match attr_value.trim() {
+                    "none" => new = Resize::None,
+                    "both" => new = Resize::Both,
+                    "horizontal" | "inline" => new = Resize::Horizontal,
+                    "vertical" | "block" => new = Resize::Vertical,
+                    _ => {
+                        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+                        style_diagnostics.invalid_value("resize", attr_value);
+                    }
+                }
+            }
+        }
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
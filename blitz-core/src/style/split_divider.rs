@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+
+use super::StyleDiagnostics;
+
+/// Makes an element a divider between the panes before and after it in a flex container, from the `split-divider` property, which isn't part of CSS. Dragging the divider or pressing the arrow keys while it has focus moves the space between the two panes along the axis of the container, see [`crate::split`]. The property isn't inherited.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Component)]
+pub(crate) enum SplitDivider {
+    #[default]
+    None,
+    /// The panes stop at their `min-width` or `min-height`
+    Resize,
+    /// A pane that is dragged below half of its minimum size collapses to nothing, and Enter collapses and restores the pane before the divider
+    Collapse,
+}
+
+/// The style properties [`SplitDivider`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &["split-divider"];
+
+#[partial_derive_state]
+impl State for SplitDivider {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let mut new = SplitDivider::None;
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                match attr_value.trim() {
+                    "none" => new = SplitDivider::None,
+                    "resize" => new = SplitDivider::Resize,
+                    "collapse" => new = SplitDivider::Collapse,
+                    _ => {
+                        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+                        style_diagnostics.invalid_value("split-divider", attr_value);
+                    }
+                }
+            }
+        }
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
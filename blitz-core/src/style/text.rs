@@ -0,0 +1,545 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::properties::font::{AbsoluteFontSize, FontSize, LineHeight, RelativeFontSize};
+use lightningcss::properties::text::Spacing;
+use lightningcss::traits::Parse;
+use lightningcss::values::length::{Length, LengthValue};
+use lightningcss::values::percentage::DimensionPercentage;
+use shipyard::Component;
+
+use super::StyleDiagnostics;
+use crate::fonts::FontStyle;
+
+/// The font size of the root, which `rem` is relative to
+pub(crate) const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+/// An OpenType feature tag and its value, like `liga` 0
+pub(crate) type Feature = ([u8; 4], u32);
+
+/// The style text is shaped with. It is inherited, so text nodes get the style of their element.
+#[derive(Clone, PartialEq, Debug, Component)]
+pub(crate) struct TextStyle {
+    /// The families of font-family in order of preference, in lowercase. Text is shaped with the first family that is loaded, see [`crate::load_font`]
+    pub font_family: Vec<String>,
+    /// The font size in pixels
+    pub font_size: f32,
+    /// The weight from 1 to 1000 that the face of each family is matched with, see [`crate::FontFace`]
+    pub font_weight: u16,
+    pub font_style: FontStyle,
+    pub features: FontFeatures,
+    pub direction: Direction,
+    pub white_space: WhiteSpace,
+    /// The height of each line of text from `line-height`, or `None` for `normal`, which is the height of the font
+    pub line_height: Option<TextLineHeight>,
+    /// The space in pixels added after each character from `letter-spacing`
+    pub letter_spacing: f32,
+    /// The smallest and largest font size of `fit-text`. The text of the element is shrunk to fit the width it is given within these bounds. It isn't inherited by child elements
+    pub fit: Option<(f32, f32)>,
+    /// True if `text-overflow` is `ellipsis`, so the end of text that doesn't fit the width it is given is replaced with an ellipsis. Like `fit`, it isn't inherited by child elements
+    pub ellipsis: bool,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            font_family: Vec::new(),
+            font_size: DEFAULT_FONT_SIZE,
+            font_weight: 400,
+            font_style: FontStyle::Normal,
+            features: FontFeatures::default(),
+            direction: Direction::Auto,
+            white_space: WhiteSpace::Normal,
+            line_height: None,
+            letter_spacing: 0.0,
+            fit: None,
+            ellipsis: false,
+        }
+    }
+}
+
+/// The base direction of text from `direction`, which mixed-direction text is ordered in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum Direction {
+    /// The direction of the first strong character of the text, like `unicode-bidi: plaintext`, so text in a right to left script reads correctly without setting `direction`
+    #[default]
+    Auto,
+    Ltr,
+    Rtl,
+}
+
+/// How white space in text is handled and whether lines wrap, from `white-space`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum WhiteSpace {
+    /// Runs of white space collapse into one space and lines wrap at the break opportunities of the Unicode line breaking algorithm
+    #[default]
+    Normal,
+    /// White space collapses like `normal`, but lines don't wrap
+    Nowrap,
+    /// White space is kept and lines only break at line breaks in the text
+    Pre,
+}
+
+/// A line-height other than `normal`. A number is inherited as a number, so it is relative to the font size of the text it applies to, while lengths and percentages are inherited in pixels.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum TextLineHeight {
+    Factor(f32),
+    Pixels(f32),
+}
+
+impl TextLineHeight {
+    /// The height of a line in pixels at a font size.
+    pub fn resolve(self, font_size: f32) -> f32 {
+        match self {
+            Self::Factor(factor) => factor * font_size,
+            Self::Pixels(pixels) => pixels,
+        }
+    }
+}
+
+/// The OpenType features set by each font property. The properties are inherited independently of each other.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct FontFeatures {
+    kerning: Vec<Feature>,
+    /// The features of font-variant-ligatures, font-variant-numeric and font-variant-caps
+    variants: [Vec<Feature>; 3],
+    /// font-feature-settings, which overrides the other properties
+    settings: Vec<Feature>,
+}
+
+impl FontFeatures {
+    /// The features in the order they are applied. A feature overrides the features with the same tag before it.
+    pub fn resolve(&self) -> impl Iterator<Item = &Feature> {
+        self.kerning
+            .iter()
+            .chain(self.variants.iter().flatten())
+            .chain(&self.settings)
+    }
+}
+
+/// The longhands of font-variant
+#[derive(Clone, Copy, PartialEq)]
+enum Variant {
+    Ligatures = 0,
+    Numeric = 1,
+    Caps = 2,
+}
+
+const NO_LIGATURES: &[Feature] = &[
+    (*b"liga", 0),
+    (*b"clig", 0),
+    (*b"dlig", 0),
+    (*b"hlig", 0),
+    (*b"calt", 0),
+];
+
+/// The keywords of the font-variant longhands and the features they set
+const VARIANT_KEYWORDS: &[(&str, Variant, &[Feature])] = &[
+    (
+        "common-ligatures",
+        Variant::Ligatures,
+        &[(*b"liga", 1), (*b"clig", 1)],
+    ),
+    (
+        "no-common-ligatures",
+        Variant::Ligatures,
+        &[(*b"liga", 0), (*b"clig", 0)],
+    ),
+    (
+        "discretionary-ligatures",
+        Variant::Ligatures,
+        &[(*b"dlig", 1)],
+    ),
+    (
+        "no-discretionary-ligatures",
+        Variant::Ligatures,
+        &[(*b"dlig", 0)],
+    ),
+    ("historical-ligatures", Variant::Ligatures, &[(*b"hlig", 1)]),
+    (
+        "no-historical-ligatures",
+        Variant::Ligatures,
+        &[(*b"hlig", 0)],
+    ),
+    ("contextual", Variant::Ligatures, &[(*b"calt", 1)]),
+    ("no-contextual", Variant::Ligatures, &[(*b"calt", 0)]),
+    ("lining-nums", Variant::Numeric, &[(*b"lnum", 1)]),
+    ("oldstyle-nums", Variant::Numeric, &[(*b"onum", 1)]),
+    ("proportional-nums", Variant::Numeric, &[(*b"pnum", 1)]),
+    ("tabular-nums", Variant::Numeric, &[(*b"tnum", 1)]),
+    ("diagonal-fractions", Variant::Numeric, &[(*b"frac", 1)]),
+    ("stacked-fractions", Variant::Numeric, &[(*b"afrc", 1)]),
+    ("ordinal", Variant::Numeric, &[(*b"ordn", 1)]),
+    ("slashed-zero", Variant::Numeric, &[(*b"zero", 1)]),
+    ("small-caps", Variant::Caps, &[(*b"smcp", 1)]),
+    (
+        "all-small-caps",
+        Variant::Caps,
+        &[(*b"smcp", 1), (*b"c2sc", 1)],
+    ),
+    ("petite-caps", Variant::Caps, &[(*b"pcap", 1)]),
+    (
+        "all-petite-caps",
+        Variant::Caps,
+        &[(*b"pcap", 1), (*b"c2pc", 1)],
+    ),
+    ("unicase", Variant::Caps, &[(*b"unic", 1)]),
+    ("titling-caps", Variant::Caps, &[(*b"titl", 1)]),
+];
+
+/// The style properties [`TextStyle`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &[
+    "font-family",
+    "font-size",
+    "font-weight",
+    "font-style",
+    "font-kerning",
+    "font-variant",
+    "font-variant-ligatures",
+    "font-variant-numeric",
+    "font-variant-caps",
+    "font-feature-settings",
+    "direction",
+    "white-space",
+    "line-height",
+    "letter-spacing",
+    "fit-text",
+    "text-overflow",
+];
+
+#[partial_derive_state]
+impl State for TextStyle {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> = NodeMaskBuilder::new()
+        .with_attrs(AttributeMaskBuilder::Some(PROPERTIES))
+        .with_tag();
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+        let mut new = parent.map(|(parent,)| parent.clone()).unwrap_or_default();
+        // Text nodes fit and are cut off with their element, but child elements aren't
+        if node_view.tag().is_some() {
+            new.fit = None;
+            new.ellipsis = false;
+        }
+        // The values of inputs keep their spaces and line breaks, like the user typed them
+        if matches!(node_view.tag(), Some("input" | "textarea")) {
+            new.white_space = WhiteSpace::Pre;
+        }
+
+        let declarations: Vec<(&str, &str)> = node_view
+            .attributes()
+            .into_iter()
+            .flatten()
+            .filter_map(|attr| Some((attr.attribute.name.as_str(), attr.value.as_text()?)))
+            .collect();
+        new.apply(declarations, |name, value| {
+            style_diagnostics.invalid_value(name, value)
+        });
+
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
+
+impl TextStyle {
+    /// Apply font declarations to the style inherited from the parent. `invalid` is called with the declarations whose value can't be parsed.
+    pub fn apply<'a>(
+        &mut self,
+        mut declarations: Vec<(&'a str, &'a str)>,
+        mut invalid: impl FnMut(&'a str, &'a str),
+    ) {
+        let parent_font_size = self.font_size;
+        let parent_font_weight = self.font_weight;
+        // The attributes aren't ordered, so longhands are applied after the shorthand and font-feature-settings last, like the cascade would if they were declared in that order. Lengths in `em` are relative to the font size of the element, so they are resolved after it
+        declarations.sort_by_key(|(name, _)| match *name {
+            "font-variant" => 0,
+            "font-feature-settings" | "line-height" | "letter-spacing" => 2,
+            _ => 1,
+        });
+        for (name, value) in declarations {
+            let applied = match name {
+                "font-family" => {
+                    parse_font_family(value).map(|families| self.font_family = families)
+                }
+                "font-size" => parse_font_size(value, parent_font_size)
+                    .map(|font_size| self.font_size = font_size),
+                "font-weight" => parse_font_weight(value, parent_font_weight)
+                    .map(|font_weight| self.font_weight = font_weight),
+                "font-style" => {
+                    parse_font_style(value).map(|font_style| self.font_style = font_style)
+                }
+                "font-kerning" => {
+                    parse_kerning(value).map(|kerning| self.features.kerning = kerning)
+                }
+                "font-variant" => parse_variants(
+                    value,
+                    &[Variant::Ligatures, Variant::Numeric, Variant::Caps],
+                )
+                .map(|variants| self.features.variants = variants),
+                "font-variant-ligatures" => {
+                    parse_variant(value, Variant::Ligatures, &mut self.features)
+                }
+                "font-variant-numeric" => {
+                    parse_variant(value, Variant::Numeric, &mut self.features)
+                }
+                "font-variant-caps" => parse_variant(value, Variant::Caps, &mut self.features),
+                "font-feature-settings" => {
+                    parse_feature_settings(value).map(|settings| self.features.settings = settings)
+                }
+                "direction" => parse_direction(value).map(|direction| self.direction = direction),
+                "white-space" => {
+                    parse_white_space(value).map(|white_space| self.white_space = white_space)
+                }
+                "line-height" => parse_line_height(value, self.font_size)
+                    .map(|line_height| self.line_height = line_height),
+                "letter-spacing" => parse_letter_spacing(value, self.font_size)
+                    .map(|letter_spacing| self.letter_spacing = letter_spacing),
+                "fit-text" => parse_fit_text(value, parent_font_size).map(|fit| self.fit = fit),
+                "text-overflow" => {
+                    parse_text_overflow(value).map(|ellipsis| self.ellipsis = ellipsis)
+                }
+                _ => Some(()),
+            };
+            if applied.is_none() {
+                invalid(name, value);
+            }
+        }
+    }
+}
+
+fn parse_direction(value: &str) -> Option<Direction> {
+    match value.trim() {
+        "ltr" => Some(Direction::Ltr),
+        "rtl" => Some(Direction::Rtl),
+        _ => None,
+    }
+}
+
+/// Parse white-space. `pre-wrap`, `pre-line` and `break-spaces` aren't supported.
+fn parse_white_space(value: &str) -> Option<WhiteSpace> {
+    match value.trim() {
+        "normal" => Some(WhiteSpace::Normal),
+        "nowrap" => Some(WhiteSpace::Nowrap),
+        "pre" => Some(WhiteSpace::Pre),
+        _ => None,
+    }
+}
+
+/// Parse a list of family names, like `"Fira Sans", Arial, sans-serif`. Names are lowercase, because families are matched without regard to ASCII case.
+///
+/// Generic families aren't loaded, so text with them falls back to the default face.
+fn parse_font_family(value: &str) -> Option<Vec<String>> {
+    value
+        .split(',')
+        .map(|family| {
+            let family = family.trim();
+            let name = match family.chars().next()? {
+                quote @ ('"' | '\'') => family[1..].strip_suffix(quote)?.to_string(),
+                // Unquoted names are identifiers separated by whitespace
+                _ => family.split_whitespace().collect::<Vec<_>>().join(" "),
+            };
+            (!name.is_empty()).then(|| name.to_ascii_lowercase())
+        })
+        .collect()
+}
+
+/// Resolve a font size in pixels. Relative sizes are relative to the font size of the parent.
+fn parse_font_size(value: &str, parent: f32) -> Option<f32> {
+    let size = match FontSize::parse_string(value).ok()? {
+        FontSize::Absolute(size) => match size {
+            AbsoluteFontSize::XXSmall => 9.0,
+            AbsoluteFontSize::XSmall => 10.0,
+            AbsoluteFontSize::Small => 13.0,
+            AbsoluteFontSize::Medium => DEFAULT_FONT_SIZE,
+            AbsoluteFontSize::Large => 18.0,
+            AbsoluteFontSize::XLarge => 24.0,
+            AbsoluteFontSize::XXLarge => 32.0,
+            AbsoluteFontSize::XXXLarge => 48.0,
+        },
+        FontSize::Relative(RelativeFontSize::Larger) => parent * 1.2,
+        FontSize::Relative(RelativeFontSize::Smaller) => parent / 1.2,
+        FontSize::Length(DimensionPercentage::Percentage(percentage)) => parent * percentage.0,
+        FontSize::Length(DimensionPercentage::Dimension(length)) => resolve_length(length, parent)?,
+        FontSize::Length(DimensionPercentage::Calc(_)) => return None,
+    };
+    (size >= 0.0).then_some(size)
+}
+
+/// Resolve a length in pixels, with `em` relative to a font size.
+fn resolve_length(length: LengthValue, font_size: f32) -> Option<f32> {
+    match length {
+        LengthValue::Em(em) => Some(font_size * em),
+        LengthValue::Rem(rem) => Some(DEFAULT_FONT_SIZE * rem),
+        // TODO: Viewport units need the size of the viewport, which isn't known while styles are resolved
+        length => length.to_px(),
+    }
+}
+
+/// Parse line-height into `None` for `normal` or the height of a line. Percentages are relative to the font size of the element.
+fn parse_line_height(value: &str, font_size: f32) -> Option<Option<TextLineHeight>> {
+    let line_height = match LineHeight::parse_string(value).ok()? {
+        LineHeight::Normal => return Some(None),
+        LineHeight::Number(number) => TextLineHeight::Factor(number),
+        LineHeight::Length(DimensionPercentage::Percentage(percentage)) => {
+            TextLineHeight::Pixels(font_size * percentage.0)
+        }
+        LineHeight::Length(DimensionPercentage::Dimension(length)) => {
+            TextLineHeight::Pixels(resolve_length(length, font_size)?)
+        }
+        LineHeight::Length(DimensionPercentage::Calc(_)) => return None,
+    };
+    let (TextLineHeight::Factor(height) | TextLineHeight::Pixels(height)) = line_height;
+    (height >= 0.0).then_some(Some(line_height))
+}
+
+/// Parse letter-spacing in pixels, which is 0 for `normal`.
+fn parse_letter_spacing(value: &str, font_size: f32) -> Option<f32> {
+    match Spacing::parse_string(value).ok()? {
+        Spacing::Normal => Some(0.0),
+        Spacing::Length(Length::Value(length)) => resolve_length(length, font_size),
+        Spacing::Length(Length::Calc(_)) => None,
+    }
+}
+
+/// Parse `none` or the smallest and largest font size of fit-text, like `10px 24px`.
+fn parse_fit_text(value: &str, parent: f32) -> Option<Option<(f32, f32)>> {
+    let value = value.trim();
+    if value == "none" {
+        return Some(None);
+    }
+    let (min, max) = value.split_once(char::is_whitespace)?;
+    let min = parse_font_size(min, parent)?;
+    let max = parse_font_size(max.trim(), parent)?;
+    (min <= max).then_some(Some((min, max)))
+}
+
+/// Parse text-overflow into true for `ellipsis`. Custom strings aren't supported.
+fn parse_text_overflow(value: &str) -> Option<bool> {
+    match value.trim() {
+        "clip" => Some(false),
+        "ellipsis" => Some(true),
+        _ => None,
+    }
+}
+
+/// Resolve a font weight. `bolder` and `lighter` are relative to the weight of the parent, like the table of CSS.
+fn parse_font_weight(value: &str, parent: u16) -> Option<u16> {
+    match value.trim() {
+        "normal" => Some(400),
+        "bold" => Some(700),
+        "bolder" => Some(match parent {
+            0..=349 => 400,
+            350..=549 => 700,
+            _ => 900,
+        }),
+        "lighter" => Some(match parent {
+            0..=549 => 100,
+            550..=749 => 400,
+            _ => 700,
+        }),
+        value => {
+            let weight: f32 = value.parse().ok()?;
+            (1.0..=1000.0)
+                .contains(&weight)
+                .then(|| weight.round() as u16)
+        }
+    }
+}
+
+/// Parse font-style. Oblique angles aren't supported, because faces are only matched by whether they are oblique.
+fn parse_font_style(value: &str) -> Option<FontStyle> {
+    match value.trim() {
+        "normal" => Some(FontStyle::Normal),
+        "italic" => Some(FontStyle::Italic),
+        value if value.split_whitespace().next() == Some("oblique") => Some(FontStyle::Oblique),
+        _ => None,
+    }
+}
+
+fn parse_kerning(value: &str) -> Option<Vec<Feature>> {
+    match value.trim() {
+        "auto" => Some(Vec::new()),
+        "normal" => Some(vec![(*b"kern", 1)]),
+        "none" => Some(vec![(*b"kern", 0)]),
+        _ => None,
+    }
+}
+
+/// Parse one of the longhands of font-variant into the features.
+fn parse_variant(value: &str, variant: Variant, features: &mut FontFeatures) -> Option<()> {
+    let variants = parse_variants(value, &[variant])?;
+    features.variants[variant as usize] = variants[variant as usize].clone();
+    Some(())
+}
+
+/// Parse the keywords of font-variant or its longhands into the features of each longhand. Longhands without keywords are reset to normal.
+fn parse_variants(value: &str, allowed: &[Variant]) -> Option<[Vec<Feature>; 3]> {
+    let mut variants: [Vec<Feature>; 3] = Default::default();
+    match value.trim() {
+        "normal" => {}
+        "none" if allowed.contains(&Variant::Ligatures) => {
+            variants[Variant::Ligatures as usize] = NO_LIGATURES.to_vec();
+        }
+        value => {
+            for keyword in value.split_whitespace() {
+                let (_, variant, features) = VARIANT_KEYWORDS
+                    .iter()
+                    .find(|(name, variant, _)| *name == keyword && allowed.contains(variant))?;
+                variants[*variant as usize].extend_from_slice(features);
+            }
+        }
+    }
+    Some(variants)
+}
+
+/// Parse font-feature-settings, like `"liga" 0, "tnum"`.
+fn parse_feature_settings(value: &str) -> Option<Vec<Feature>> {
+    let value = value.trim();
+    if value == "normal" {
+        return Some(Vec::new());
+    }
+    value
+        .split(',')
+        .map(|setting| {
+            let setting = setting.trim();
+            let quote = setting.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let (tag, value) = setting[1..].split_once(quote)?;
+            let tag: [u8; 4] = tag.as_bytes().try_into().ok()?;
+            // Tags are four printable ASCII characters
+            if !tag.iter().all(|byte| (0x20..=0x7e).contains(byte)) {
+                return None;
+            }
+            let value = match value.trim() {
+                "" | "on" => 1,
+                "off" => 0,
+                value => value.parse().ok()?,
+            };
+            Some((tag, value))
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+
+use super::StyleDiagnostics;
+
+/// The directions a touch that starts on the element can scroll in, from `touch-action`. Elements like drawing canvases and drag handles set `touch-action: none` to get every move of the touch as their own gesture instead of scrolling the container they are in.
+///
+/// A touch can only do what the element and all of its ancestors allow, like in a browser. Nothing zooms, so `manipulation` allows the same as `auto`.
+#[derive(Clone, Copy, PartialEq, Debug, Component)]
+pub(crate) struct TouchAction {
+    pub pan_x: bool,
+    pub pan_y: bool,
+}
+
+impl Default for TouchAction {
+    fn default() -> Self {
+        Self {
+            pan_x: true,
+            pan_y: true,
+        }
+    }
+}
+
+impl TouchAction {
+    fn parse(value: &str) -> Option<Self> {
+        let none = Self {
+            pan_x: false,
+            pan_y: false,
+        };
+        match value {
+            "auto" | "manipulation" => return Some(Self::default()),
+            "none" => return Some(none),
+            _ => {}
+        }
+        let mut action = none;
+        for keyword in value.split_whitespace() {
+            match keyword {
+                "pan-x" if !action.pan_x => action.pan_x = true,
+                "pan-y" if !action.pan_y => action.pan_y = true,
+                // Pinch zoom and the directions of an axis are accepted, but only panning is allowed or not
+                "pinch-zoom" | "pan-left" | "pan-right" | "pan-up" | "pan-down" => {}
+                _ => return None,
+            }
+        }
+        Some(action)
+    }
+}
+
+/// The style properties [`TouchAction`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &["touch-action"];
+
+#[partial_derive_state]
+impl State for TouchAction {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let mut own = TouchAction::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                match TouchAction::parse(attr_value.trim()) {
+                    Some(action) => own = action,
+                    None => {
+                        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+                        style_diagnostics.invalid_value("touch-action", attr_value);
+                    }
+                }
+            }
+        }
+        // The property isn't inherited, but an ancestor that doesn't allow a direction takes it away from its descendants
+        let allowed = parent.map(|(parent,)| *parent).unwrap_or_default();
+        let new = TouchAction {
+            pan_x: own.pan_x && allowed.pan_x,
+            pan_y: own.pan_y && allowed.pan_y,
+        };
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use lightningcss::properties::transform::{self, TransformList};
+use lightningcss::traits::Parse;
+use lightningcss::values::percentage::NumberOrPercentage;
+use lightningcss::values::position::Position;
+use peniko::kurbo::{Affine, Point, Vec2};
+use shipyard::Component;
+use taffy::prelude::Size;
+
+use super::StyleDiagnostics;
+use crate::util::{Axis, Resolve};
+
+#[derive(Clone, PartialEq, Debug, Component)]
+pub(crate) struct Transform {
+    pub transform: TransformList,
+    pub origin: Position,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            transform: TransformList(Vec::new()),
+            origin: Position::center(),
+        }
+    }
+}
+
+impl Transform {
+    /// Resolve the transform of a box at the given location into a matrix that maps the box to its transformed position.
+    pub fn resolve(&self, location: Point, size: &Size<f32>, viewport_size: &Size<u32>) -> Affine {
+        if self.transform.0.is_empty() {
+            return Affine::IDENTITY;
+        }
+        let origin = location
+            + Vec2::new(
+                self.origin.x.resolve(Axis::X, size, viewport_size),
+                self.origin.y.resolve(Axis::Y, size, viewport_size),
+            );
+        let matrix = self
+            .transform
+            .0
+            .iter()
+            .fold(Affine::IDENTITY, |matrix, transform| {
+                matrix * resolve_transform(transform, size, viewport_size)
+            });
+        Affine::translate(origin.to_vec2()) * matrix * Affine::translate(-origin.to_vec2())
+    }
+}
+
+fn resolve_transform(
+    transform: &transform::Transform,
+    size: &Size<f32>,
+    viewport_size: &Size<u32>,
+) -> Affine {
+    use transform::Transform::*;
+    let number = |value: &NumberOrPercentage| match value {
+        NumberOrPercentage::Number(number) => *number as f64,
+        NumberOrPercentage::Percentage(percentage) => percentage.0 as f64,
+    };
+    match transform {
+        Translate(x, y) => Affine::translate((
+            x.resolve(Axis::X, size, viewport_size),
+            y.resolve(Axis::Y, size, viewport_size),
+        )),
+        TranslateX(x) => Affine::translate((x.resolve(Axis::X, size, viewport_size), 0.0)),
+        TranslateY(y) => Affine::translate((0.0, y.resolve(Axis::Y, size, viewport_size))),
+        Scale(x, y) => Affine::scale_non_uniform(number(x), number(y)),
+        ScaleX(x) => Affine::scale_non_uniform(number(x), 1.0),
+        ScaleY(y) => Affine::scale_non_uniform(1.0, number(y)),
+        Rotate(angle) | RotateZ(angle) => Affine::rotate(angle.to_radians() as f64),
+        Skew(x, y) => skew(x.to_radians() as f64, y.to_radians() as f64),
+        SkewX(x) => skew(x.to_radians() as f64, 0.0),
+        SkewY(y) => skew(0.0, y.to_radians() as f64),
+        Matrix(m) => Affine::new([m.a, m.b, m.c, m.d, m.e, m.f].map(|value| value as f64)),
+        // 3d transforms are reported as unsupported when they are parsed
+        _ => Affine::IDENTITY,
+    }
+}
+
+fn skew(x: f64, y: f64) -> Affine {
+    Affine::new([1.0, y.tan(), x.tan(), 1.0, 0.0, 0.0])
+}
+
+fn is_2d(transform: &transform::Transform) -> bool {
+    use transform::Transform::*;
+    !matches!(
+        transform,
+        TranslateZ(_)
+            | Translate3d(..)
+            | ScaleZ(_)
+            | Scale3d(..)
+            | RotateX(_)
+            | RotateY(_)
+            | Rotate3d(..)
+            | Perspective(_)
+            | Matrix3d(_)
+    )
+}
+
+/// The style properties [`Transform`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &["transform", "transform-origin"];
+
+#[partial_derive_state]
+impl State for Transform {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+        let mut new = Transform::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                let name = attr.attribute.name.as_str();
+                match name {
+                    "transform" => match TransformList::parse_string(attr_value) {
+                        Ok(transform) => {
+                            if !transform.0.iter().all(is_2d) {
+                                style_diagnostics.unsupported_value(name, attr_value);
+                            }
+                            new.transform = transform;
+                        }
+                        Err(_) => style_diagnostics.invalid_value(name, attr_value),
+                    },
+                    "transform-origin" => match Position::parse_string(attr_value) {
+                        Ok(origin) => new.origin = origin,
+                        Err(_) => style_diagnostics.invalid_value(name, attr_value),
+                    },
+                    _ => {}
+                }
+            }
+        }
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+
+use super::StyleDiagnostics;
+
+/// Whether the text of an element can be selected, from `user-select`. `auto` takes the value of the parent, so the chrome of an application can opt out of selection as a whole while the documents in it stay selectable.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Component)]
+pub(crate) enum UserSelect {
+    /// Text can be selected, and a double click selects a word
+    #[default]
+    Text,
+    /// Text can't be selected, so clicking interactive elements quickly doesn't select their labels
+    None,
+    /// The text of the element is selected as a whole, with a click or a double click
+    All,
+}
+
+/// The style properties [`UserSelect`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &["user-select"];
+
+#[partial_derive_state]
+impl State for UserSelect {
+    type ChildDependencies = ();
+    type ParentDependencies = (Self,);
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let inherited = parent.map(|(parent,)| *parent).unwrap_or_default();
+        let mut new = inherited;
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                match attr_value.trim() {
+                    "auto" | "inherit" => new = inherited,
+                    // Selections don't leave elements yet, so containing them changes nothing
+                    "text" | "contain" => new = UserSelect::Text,
+                    "none" => new = UserSelect::None,
+                    "all" => new = UserSelect::All,
+                    _ => {
+                        let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+                        style_diagnostics.invalid_value("user-select", attr_value);
+                    }
+                }
+            }
+        }
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
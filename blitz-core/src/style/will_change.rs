@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+
+use super::StyleDiagnostics;
+
+/// True if `will-change` says the element will move, scroll or fade, but its content stays the same. The content is drawn in coordinates of its own, so it is only tessellated again when it changes, and backends that cache layers draw it into a texture that is composited in every frame.
+///
+/// Like in browsers, an element that will change a property that starts a stacking context starts one already.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Component)]
+pub(crate) struct WillChange(pub bool);
+
+/// The style properties [`WillChange`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &["will-change"];
+
+#[partial_derive_state]
+impl State for WillChange {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let mut new = WillChange::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                for property in attr_value.split(',').map(str::trim) {
+                    match property {
+                        "transform" | "opacity" | "scroll-position" => new = WillChange(true),
+                        // Changing any other property changes the content
+                        property
+                            if !property.is_empty() && !property.contains(char::is_whitespace) => {}
+                        _ => {
+                            let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+                            style_diagnostics.invalid_value("will-change", attr_value);
+                        }
+                    }
+                }
+            }
+        }
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use dioxus_native_core::prelude::*;
+use dioxus_native_core_macro::partial_derive_state;
+use shipyard::Component;
+
+use super::StyleDiagnostics;
+
+/// The z-index of an element, or `None` for `auto`.
+///
+/// Every element is a flex item, so z-index applies to elements whether or not they are positioned. An element with a z-index starts a stacking context and is painted over the elements of its stacking context with a lower z-index, and over the elements that don't have one if it isn't negative.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Component)]
+pub(crate) struct ZIndex(pub Option<i32>);
+
+/// The style properties [`ZIndex`] is resolved from
+pub(super) const PROPERTIES: &[&str] = &["z-index"];
+
+#[partial_derive_state]
+impl State for ZIndex {
+    type ChildDependencies = ();
+    type ParentDependencies = ();
+    type NodeDependencies = ();
+
+    const NODE_MASK: NodeMaskBuilder<'static> =
+        NodeMaskBuilder::new().with_attrs(AttributeMaskBuilder::Some(PROPERTIES));
+
+    fn update<'a>(
+        &mut self,
+        node_view: NodeView,
+        _: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        _: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        _: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        ctx: &SendAnyMap,
+    ) -> bool {
+        let mut new = ZIndex::default();
+        for attr in node_view.attributes().into_iter().flatten() {
+            if let Some(attr_value) = attr.value.as_text() {
+                match attr_value.trim() {
+                    "auto" => new = ZIndex(None),
+                    value => match value.parse() {
+                        Ok(z_index) => new = ZIndex(Some(z_index)),
+                        Err(_) => {
+                            let style_diagnostics: &Arc<StyleDiagnostics> = ctx.get().unwrap();
+                            style_diagnostics.invalid_value("z-index", attr_value);
+                        }
+                    },
+                }
+            }
+        }
+        let updated = new != *self;
+        *self = new;
+        updated
+    }
+
+    fn create<'a>(
+        node_view: NodeView<()>,
+        node: <Self::NodeDependencies as Dependancy>::ElementBorrowed<'a>,
+        parent: Option<<Self::ParentDependencies as Dependancy>::ElementBorrowed<'a>>,
+        children: Vec<<Self::ChildDependencies as Dependancy>::ElementBorrowed<'a>>,
+        context: &SendAnyMap,
+    ) -> Self {
+        let mut myself = Self::default();
+        myself.update(node_view, node, parent, children, context);
+        myself
+    }
+}
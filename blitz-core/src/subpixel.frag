@@ -0,0 +1,46 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec4 inColor;
+layout(location = 1) in vec2 inUV;
+
+layout(location = 0) out vec4 outColor;
+
+// Holds the subpixel glyphs of text, with the coverage of each color channel in its texels
+layout(set = 0, binding = 0) uniform texture2D atlas;
+layout(set = 0, binding = 1) uniform sampler atlasSampler;
+
+layout(push_constant) uniform PushConstants {
+  vec4 clip_rect;
+  vec2 screen_size;
+  vec2 translation;
+  // The columns of the linear part of the transform, see DrawConstants
+  vec4 matrix;
+  uint encode_srgb;
+} pushConstants;
+
+// 0-1 sRGB  from  0-1 linear
+vec3 srgb_from_linear(vec3 linear) {
+    bvec3 cutoff = lessThan(linear, vec3(0.0031308));
+    vec3 lower = linear * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(linear, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, cutoff);
+}
+
+// The second pass of subpixel text, which adds the color of the text times the coverage of each channel over what coverage.frag darkened
+void main() {
+  // Textures can only be sampled before the first discard, where control flow is still uniform
+  vec4 texel = texture(sampler2D(atlas, atlasSampler), inUV);
+  if (any(lessThan(gl_FragCoord.xy, pushConstants.clip_rect.xy)) ||
+      any(greaterThanEqual(gl_FragCoord.xy, pushConstants.clip_rect.zw))) {
+    discard;
+  }
+  vec4 color = inColor * texel;
+  // Each channel has its own coverage, so the color of the text is encoded before it is multiplied with it, like the colors of shader.frag
+  if (pushConstants.encode_srgb != 0u) {
+    vec3 text = inColor.a > 0.0 ? inColor.rgb / inColor.a : vec3(0.0);
+    color.rgb = srgb_from_linear(text) * texel.rgb * inColor.a;
+  }
+  outColor = color;
+}
\ No newline at end of file
@@ -0,0 +1,163 @@
+use std::sync::RwLock;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+/// The settings of the platform that input is handled with, so double clicks, scrolling, dragging and the caret feel like the rest of the system.
+///
+/// [`SystemSettings::query`] reads them from Windows, from GNOME on Linux and from macOS. Settings the platform doesn't have keep their defaults. Replace them with [`crate::Config::with_system_settings`], for example to get the same behavior on every machine in tests.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SystemSettings {
+    /// The longest time between the two clicks of a double click
+    pub double_click_time: Duration,
+    /// How far in client pixels the mouse can move between the two clicks of a double click
+    pub double_click_slop: f64,
+    /// How many lines one notch of the mouse wheel scrolls
+    pub wheel_scroll_lines: f64,
+    /// How long the caret is shown and then hidden while it blinks, or `None` if it doesn't blink
+    pub caret_blink_interval: Option<Duration>,
+    /// The width of the caret in pixels
+    pub caret_width: f32,
+    /// How far in client pixels a pointer can move before a press turns into a drag
+    pub drag_threshold: f64,
+}
+
+impl Default for SystemSettings {
+    fn default() -> Self {
+        Self {
+            double_click_time: Duration::from_millis(500),
+            double_click_slop: 4.0,
+            wheel_scroll_lines: 3.0,
+            caret_blink_interval: Some(Duration::from_millis(530)),
+            caret_width: 1.0,
+            drag_threshold: 10.0,
+        }
+    }
+}
+
+/// The settings every window and document use, read from the platform the first time they are needed.
+static SYSTEM_SETTINGS: Lazy<RwLock<SystemSettings>> =
+    Lazy::new(|| RwLock::new(SystemSettings::query()));
+
+impl SystemSettings {
+    /// Read the settings of the platform.
+    #[cfg(target_os = "windows")]
+    pub fn query() -> Self {
+        use std::ffi::c_void;
+
+        const SM_CXDOUBLECLK: i32 = 36;
+        const SM_CXDRAG: i32 = 68;
+        const SPI_GETWHEELSCROLLLINES: u32 = 0x0068;
+        const SPI_GETCARETWIDTH: u32 = 0x2006;
+        /// The blink time of a caret that doesn't blink
+        const INFINITE: u32 = u32::MAX;
+
+        #[link(name = "user32")]
+        extern "system" {
+            fn GetDoubleClickTime() -> u32;
+            fn GetCaretBlinkTime() -> u32;
+            fn GetSystemMetrics(index: i32) -> i32;
+            fn SystemParametersInfoW(action: u32, param: u32, value: *mut c_void, ini: u32) -> i32;
+        }
+
+        let mut settings = Self::default();
+        // SAFETY: The functions only read the settings, and the values they write to are u32 like they expect
+        unsafe {
+            settings.double_click_time = Duration::from_millis(GetDoubleClickTime() as u64);
+            // The metrics are the size of the rectangle around the first click
+            settings.double_click_slop = GetSystemMetrics(SM_CXDOUBLECLK) as f64 / 2.0;
+            settings.drag_threshold = GetSystemMetrics(SM_CXDRAG) as f64;
+            settings.caret_blink_interval = match GetCaretBlinkTime() {
+                0 | INFINITE => None,
+                blink_time => Some(Duration::from_millis(blink_time as u64)),
+            };
+            let mut lines = 0u32;
+            if SystemParametersInfoW(SPI_GETWHEELSCROLLLINES, 0, &mut lines as *mut _ as _, 0) != 0
+            {
+                settings.wheel_scroll_lines = lines as f64;
+            }
+            let mut width = 0u32;
+            if SystemParametersInfoW(SPI_GETCARETWIDTH, 0, &mut width as *mut _ as _, 0) != 0 {
+                settings.caret_width = width.max(1) as f32;
+            }
+        }
+        settings
+    }
+
+    /// Read the settings of the platform.
+    #[cfg(target_os = "macos")]
+    pub fn query() -> Self {
+        let mut settings = Self::default();
+        if let Some(threshold) = read_setting(
+            "defaults",
+            &["read", "-g", "com.apple.mouse.doubleClickThreshold"],
+        )
+        .and_then(|value| value.parse::<f64>().ok())
+        {
+            settings.double_click_time = Duration::from_secs_f64(threshold);
+        }
+        settings
+    }
+
+    /// Read the settings of the platform. They are read from GNOME, which other desktops like Cinnamon and Budgie share.
+    ///
+    /// TODO: Read the settings of KDE
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    pub fn query() -> Self {
+        let mut settings = Self::default();
+        let gsetting = |schema: &str, key: &str| {
+            // Numbers are printed with their type, like `uint32 8`
+            read_setting("gsettings", &["get", schema, key])
+                .and_then(|value| value.split_whitespace().last().map(str::to_string))
+        };
+        if let Some(time) = gsetting("org.gnome.desktop.peripherals.mouse", "double-click")
+            .and_then(|value| value.parse().ok())
+        {
+            settings.double_click_time = Duration::from_millis(time);
+        }
+        if let Some(threshold) = gsetting(
+            "org.gnome.settings-daemon.peripherals.mouse",
+            "drag-threshold",
+        )
+        .and_then(|value| value.parse().ok())
+        {
+            settings.drag_threshold = threshold;
+        }
+        match gsetting("org.gnome.desktop.interface", "cursor-blink").as_deref() {
+            Some("false") => settings.caret_blink_interval = None,
+            Some(_) => {
+                // The blink time is a whole cycle of showing and hiding the caret
+                if let Some(time) = gsetting("org.gnome.desktop.interface", "cursor-blink-time")
+                    .and_then(|value| value.parse::<u64>().ok())
+                {
+                    settings.caret_blink_interval = Some(Duration::from_millis(time / 2));
+                }
+            }
+            None => {}
+        }
+        settings
+    }
+}
+
+/// The output of a command that prints a setting, or `None` if it isn't installed or the setting isn't set.
+#[cfg(not(target_os = "windows"))]
+fn read_setting(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+}
+
+/// The settings input is handled with.
+pub(crate) fn system_settings() -> SystemSettings {
+    *SYSTEM_SETTINGS.read().unwrap()
+}
+
+/// Replace the settings of the platform, see [`crate::Config::with_system_settings`].
+pub(crate) fn set_system_settings(settings: SystemSettings) {
+    *SYSTEM_SETTINGS.write().unwrap() = settings;
+}
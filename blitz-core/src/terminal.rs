@@ -0,0 +1,464 @@
+use std::ops::BitOr;
+use std::sync::{Arc, Mutex};
+
+use epaint::{pos2, vec2, Color32, Mesh, Rect, Rounding, Shape, Stroke, Vec2};
+use rustc_hash::FxHashMap;
+use taffy::prelude::{AvailableSpace, Size};
+
+use crate::fonts::FontStyle;
+use crate::plugin::{CustomElement, ElementPlugin, Painter};
+use crate::style::TextStyle;
+use crate::text::{shape, text_mesh, ShapedLine};
+
+const FOREGROUND: Color32 = Color32::from_rgb(204, 204, 204);
+const BACKGROUND: Color32 = Color32::from_rgb(24, 24, 24);
+const FONT_SIZE: f32 = 14.0;
+
+/// The attributes of the text of a cell, which combine with `|`, like `CellAttributes::BOLD | CellAttributes::UNDERLINE`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CellAttributes(u8);
+
+impl CellAttributes {
+    pub const NONE: Self = Self(0);
+    pub const BOLD: Self = Self(1);
+    pub const ITALIC: Self = Self(1 << 1);
+    pub const UNDERLINE: Self = Self(1 << 2);
+    pub const STRIKETHROUGH: Self = Self(1 << 3);
+    /// Swaps the foreground and background color of the cell
+    pub const INVERSE: Self = Self(1 << 4);
+    /// Draws the text at half of its opacity
+    pub const DIM: Self = Self(1 << 5);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for CellAttributes {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// A cell of a [`TerminalGrid`]: a character with its colors and attributes. Colors that are `None` are the default colors of the grid.
+///
+/// A character that takes two cells, like most CJK characters, is put in the first cell and the second cell is left empty with `'\0'`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TerminalCell {
+    pub character: char,
+    pub foreground: Option<Color32>,
+    pub background: Option<Color32>,
+    pub attributes: CellAttributes,
+}
+
+impl TerminalCell {
+    /// A cell with a character in the default colors.
+    pub fn new(character: char) -> Self {
+        Self {
+            character,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_colors(mut self, foreground: Option<Color32>, background: Option<Color32>) -> Self {
+        self.foreground = foreground;
+        self.background = background;
+        self
+    }
+
+    pub fn with_attributes(mut self, attributes: CellAttributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
+}
+
+impl Default for TerminalCell {
+    fn default() -> Self {
+        Self {
+            character: ' ',
+            foreground: None,
+            background: None,
+            attributes: CellAttributes::NONE,
+        }
+    }
+}
+
+/// A grid of monospace character cells for terminal emulators and embedded consoles, drawn by a plugin from a buffer of cells instead of an element for each character.
+///
+/// Backgrounds are merged into runs of cells with the same color and drawn as rects, which backends that draw rects in an instanced pass draw in one draw call. The glyphs of each row are batched into one mesh from the texture atlas, which is kept until the row changes, so redrawing a full screen where a few rows changed only builds the meshes of those rows. Register a clone with [`crate::Config::with_element_plugin`] and put an element with its tag in the document:
+///
+/// ```no_run
+/// # use blitz_core::{CellAttributes, Config, TerminalCell, TerminalGrid};
+/// let terminal = TerminalGrid::new("console", 80, 24).with_font(&["JetBrains Mono"], 13.0);
+/// let prompt: Vec<TerminalCell> = "$ cargo build"
+///     .chars()
+///     .map(|character| TerminalCell::new(character).with_attributes(CellAttributes::BOLD))
+///     .collect();
+/// terminal.write(0, 0, &prompt);
+/// terminal.set_cursor(Some((0, prompt.len())));
+/// Config::new().with_element_plugin(terminal.clone());
+/// ```
+///
+/// The element is as large as its cells unless its styles size it. Cells that change are drawn with the next change to the element, like with [`crate::DataGrid`], so change one of its attributes after writing.
+#[derive(Clone)]
+pub struct TerminalGrid {
+    tag: Arc<str>,
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    columns: usize,
+    rows: usize,
+    /// The cells row by row
+    cells: Vec<TerminalCell>,
+    /// The glyph mesh of each row relative to the top left corner of the row, with the scale it was built at, until the row changes
+    row_meshes: Vec<Option<(f32, Arc<Mesh>)>>,
+    font_family: Vec<String>,
+    font_size: f32,
+    /// The size of a cell and the distance from its top to the baseline, measured from the font
+    metrics: Option<(Vec2, f32)>,
+    /// The glyph of each character in each combination of bold and italic
+    glyphs: FxHashMap<(char, bool, bool), ShapedLine>,
+    foreground: Color32,
+    background: Color32,
+    /// The row and column of the cursor
+    cursor: Option<(usize, usize)>,
+}
+
+impl Inner {
+    fn style(&self, bold: bool, italic: bool) -> TextStyle {
+        TextStyle {
+            font_family: self.font_family.clone(),
+            font_size: self.font_size,
+            font_weight: if bold { 700 } else { 400 },
+            font_style: if italic {
+                FontStyle::Italic
+            } else {
+                FontStyle::Normal
+            },
+            ..TextStyle::default()
+        }
+    }
+
+    /// The size of a cell, which is the advance of a character rounded to whole pixels so columns line up, and the distance from its top to the baseline.
+    fn metrics(&mut self) -> (Vec2, f32) {
+        if let Some(metrics) = self.metrics {
+            return metrics;
+        }
+        let line = shape("M", &self.style(false, false));
+        let metrics = (
+            vec2(line.width.round().max(1.0), line.height().ceil().max(1.0)),
+            line.ascent.round(),
+        );
+        self.metrics = Some(metrics);
+        metrics
+    }
+
+    fn set_font(&mut self, font_family: &[&str], font_size: f32) {
+        self.font_family = font_family
+            .iter()
+            .map(|family| family.to_lowercase())
+            .collect();
+        self.font_size = font_size.max(1.0);
+        self.metrics = None;
+        self.glyphs.clear();
+        self.invalidate(0..self.rows);
+    }
+
+    fn invalidate(&mut self, rows: std::ops::Range<usize>) {
+        for mesh in &mut self.row_meshes[rows] {
+            *mesh = None;
+        }
+    }
+
+    /// The foreground and background color of a cell after its attributes
+    fn colors(&self, cell: &TerminalCell) -> (Color32, Color32) {
+        let foreground = cell.foreground.unwrap_or(self.foreground);
+        let background = cell.background.unwrap_or(self.background);
+        let (foreground, background) = if cell.attributes.contains(CellAttributes::INVERSE) {
+            (background, foreground)
+        } else {
+            (foreground, background)
+        };
+        if cell.attributes.contains(CellAttributes::DIM) {
+            (foreground.gamma_multiply(0.5), background)
+        } else {
+            (foreground, background)
+        }
+    }
+
+    /// The glyphs of a row in one mesh, relative to the top left corner of the row.
+    fn row_mesh(&mut self, row: usize, scale: f32) -> Arc<Mesh> {
+        if let Some((cached_scale, mesh)) = &self.row_meshes[row] {
+            if *cached_scale == scale {
+                return mesh.clone();
+            }
+        }
+        let (cell_size, baseline) = self.metrics();
+        let mut mesh = Mesh::default();
+        // Glyphs of fonts that are still loading are invisible, so they are shaped again until the font loads
+        let mut loaded = true;
+        for column in 0..self.columns {
+            let cell = self.cells[row * self.columns + column];
+            if cell.character.is_whitespace() || cell.character == '\0' {
+                continue;
+            }
+            let (foreground, _) = self.colors(&cell);
+            let key = (
+                cell.character,
+                cell.attributes.contains(CellAttributes::BOLD),
+                cell.attributes.contains(CellAttributes::ITALIC),
+            );
+            if !self.glyphs.contains_key(&key) {
+                let glyph = shape(
+                    cell.character.encode_utf8(&mut [0; 4]),
+                    &self.style(key.1, key.2),
+                );
+                self.glyphs.insert(key, glyph);
+            }
+            loaded &= self.glyphs[&key].visible;
+            let origin = pos2(column as f32 * cell_size.x, baseline);
+            mesh.append(text_mesh(
+                &self.glyphs[&key],
+                origin,
+                foreground,
+                scale,
+                false,
+            ));
+        }
+        let mesh = Arc::new(mesh);
+        if loaded {
+            self.row_meshes[row] = Some((scale, mesh.clone()));
+        } else {
+            self.glyphs.retain(|_, glyph| glyph.visible);
+        }
+        mesh
+    }
+}
+
+impl TerminalGrid {
+    /// A grid for the elements with a tag, with a number of columns and rows of empty cells.
+    pub fn new(tag: &str, columns: usize, rows: usize) -> Self {
+        Self {
+            tag: tag.into(),
+            inner: Arc::new(Mutex::new(Inner {
+                columns,
+                rows,
+                cells: vec![TerminalCell::default(); columns * rows],
+                row_meshes: vec![None; rows],
+                font_family: Vec::new(),
+                font_size: FONT_SIZE,
+                metrics: None,
+                glyphs: FxHashMap::default(),
+                foreground: FOREGROUND,
+                background: BACKGROUND,
+                cursor: None,
+            })),
+        }
+    }
+
+    /// Set the font families in order of preference and the font size in pixels. The default font isn't monospace, so a terminal should set a monospace font, since every character takes the width of one cell.
+    pub fn with_font(self, font_family: &[&str], font_size: f32) -> Self {
+        self.inner.lock().unwrap().set_font(font_family, font_size);
+        self
+    }
+
+    /// Set the colors of cells without their own colors.
+    pub fn with_colors(self, foreground: Color32, background: Color32) -> Self {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.foreground = foreground;
+            inner.background = background;
+            let rows = inner.rows;
+            inner.invalidate(0..rows);
+        }
+        self
+    }
+
+    /// The number of columns and rows.
+    pub fn size(&self) -> (usize, usize) {
+        let inner = self.inner.lock().unwrap();
+        (inner.columns, inner.rows)
+    }
+
+    /// The size of a cell in pixels, to work out how many columns and rows fit the element.
+    pub fn cell_size(&self) -> Vec2 {
+        self.inner.lock().unwrap().metrics().0
+    }
+
+    /// Change the number of columns and rows. The cells that are in both sizes are kept and new cells are empty.
+    pub fn resize(&self, columns: usize, rows: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        let mut cells = vec![TerminalCell::default(); columns * rows];
+        for row in 0..rows.min(inner.rows) {
+            let kept = columns.min(inner.columns);
+            let from = row * inner.columns;
+            cells[row * columns..row * columns + kept]
+                .copy_from_slice(&inner.cells[from..from + kept]);
+        }
+        inner.cells = cells;
+        inner.columns = columns;
+        inner.rows = rows;
+        inner.row_meshes = vec![None; rows];
+    }
+
+    /// Replace every cell with a buffer of cells row by row, like a full screen from a terminal emulator. A buffer that is too short leaves the cells after it empty, and cells past the end of the grid are ignored.
+    pub fn set_cells(&self, cells: &[TerminalCell]) {
+        let mut inner = self.inner.lock().unwrap();
+        let count = cells.len().min(inner.cells.len());
+        inner.cells[..count].copy_from_slice(&cells[..count]);
+        inner.cells[count..].fill(TerminalCell::default());
+        let rows = inner.rows;
+        inner.invalidate(0..rows);
+    }
+
+    /// Overwrite cells of a row from a column on, like a line of output. Cells past the end of the row are cut off.
+    pub fn write(&self, row: usize, column: usize, cells: &[TerminalCell]) {
+        let mut inner = self.inner.lock().unwrap();
+        if row >= inner.rows || column >= inner.columns {
+            return;
+        }
+        let count = cells.len().min(inner.columns - column);
+        let start = row * inner.columns + column;
+        inner.cells[start..start + count].copy_from_slice(&cells[..count]);
+        inner.invalidate(row..row + 1);
+    }
+
+    /// The cell at a row and column.
+    pub fn cell(&self, row: usize, column: usize) -> Option<TerminalCell> {
+        let inner = self.inner.lock().unwrap();
+        (row < inner.rows && column < inner.columns)
+            .then(|| inner.cells[row * inner.columns + column])
+    }
+
+    /// Show the cursor as a block around the cell at a row and column, or hide it with `None`.
+    pub fn set_cursor(&self, cursor: Option<(usize, usize)>) {
+        self.inner.lock().unwrap().cursor = cursor;
+    }
+}
+
+impl ElementPlugin for TerminalGrid {
+    fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    fn measure(
+        &self,
+        _element: &CustomElement,
+        known_dimensions: Size<Option<f32>>,
+        _available_space: Size<AvailableSpace>,
+    ) -> Size<f32> {
+        let mut inner = self.inner.lock().unwrap();
+        let (cell_size, _) = inner.metrics();
+        Size {
+            width: known_dimensions
+                .width
+                .unwrap_or(inner.columns as f32 * cell_size.x),
+            height: known_dimensions
+                .height
+                .unwrap_or(inner.rows as f32 * cell_size.y),
+        }
+    }
+
+    fn paint(&self, _element: &CustomElement, content_box: Rect, painter: &mut Painter) {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(scale) = painter.scale() else {
+            return;
+        };
+        let (cell_size, _) = inner.metrics();
+        painter.add(Shape::rect_filled(
+            content_box,
+            Rounding::none(),
+            inner.background,
+        ));
+        // Only the rows that are at least partly in the content box are drawn
+        let visible_rows = ((content_box.height() / cell_size.y).ceil() as usize).min(inner.rows);
+        painter.clipped(content_box, |painter| {
+            for row in 0..visible_rows {
+                let top = content_box.min.y + row as f32 * cell_size.y;
+                let cells = &inner.cells[row * inner.columns..(row + 1) * inner.columns];
+                let cell_rect = |column: usize, count: usize| {
+                    Rect::from_min_size(
+                        pos2(content_box.min.x + column as f32 * cell_size.x, top),
+                        vec2(count as f32 * cell_size.x, cell_size.y),
+                    )
+                };
+
+                // Neighbouring cells with the same background are drawn as one rect
+                let mut column = 0;
+                while column < cells.len() {
+                    let (_, background) = inner.colors(&cells[column]);
+                    let count = cells[column..]
+                        .iter()
+                        .take_while(|cell| inner.colors(cell).1 == background)
+                        .count();
+                    if background != inner.background {
+                        painter.add(Shape::rect_filled(
+                            cell_rect(column, count),
+                            Rounding::none(),
+                            background,
+                        ));
+                    }
+                    column += count;
+                }
+
+                for (column, cell) in cells.iter().enumerate() {
+                    let underline = cell.attributes.contains(CellAttributes::UNDERLINE);
+                    let strikethrough = cell.attributes.contains(CellAttributes::STRIKETHROUGH);
+                    if !underline && !strikethrough {
+                        continue;
+                    }
+                    let (foreground, _) = inner.colors(cell);
+                    let rect = cell_rect(column, 1);
+                    let thickness = (cell_size.y / 16.0).max(1.0);
+                    if underline {
+                        painter.add(Shape::rect_filled(
+                            Rect::from_min_max(
+                                pos2(rect.min.x, rect.max.y - thickness * 2.0),
+                                pos2(rect.max.x, rect.max.y - thickness),
+                            ),
+                            Rounding::none(),
+                            foreground,
+                        ));
+                    }
+                    if strikethrough {
+                        painter.add(Shape::rect_filled(
+                            Rect::from_center_size(rect.center(), vec2(rect.width(), thickness)),
+                            Rounding::none(),
+                            foreground,
+                        ));
+                    }
+                }
+            }
+        });
+
+        // The meshes of the rows are built after the backgrounds so they can borrow the grid mutably
+        painter.clipped(content_box, |painter| {
+            for row in 0..visible_rows {
+                let mesh = inner.row_mesh(row, scale);
+                if mesh.is_empty() {
+                    continue;
+                }
+                let mut mesh = (*mesh).clone();
+                mesh.translate(content_box.min.to_vec2() + vec2(0.0, row as f32 * cell_size.y));
+                painter.add(Shape::mesh(mesh));
+            }
+            if let Some((row, column)) = inner.cursor {
+                if row < inner.rows && column < inner.columns {
+                    let cursor = Rect::from_min_size(
+                        content_box.min
+                            + vec2(column as f32 * cell_size.x, row as f32 * cell_size.y),
+                        cell_size,
+                    );
+                    painter.add(Shape::rect_stroke(
+                        cursor.shrink(0.5),
+                        Rounding::none(),
+                        Stroke::new(1.0, inner.foreground),
+                    ));
+                }
+            }
+        });
+    }
+}
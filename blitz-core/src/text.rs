@@ -0,0 +1,916 @@
+use std::io;
+use std::ops::Range;
+
+use ab_glyph::Font as _;
+use epaint::{Color32, ColorImage, Mesh, Pos2, Rect, Rgba, Vec2};
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+use unicode_script::{Script, UnicodeScript};
+
+use crate::atlas::{AtlasKey, ATLAS};
+use crate::fonts::{self, Face};
+use crate::stats::{self, Counter};
+use crate::style::{Direction, TextStyle, WhiteSpace};
+
+/// Layout rounds the sizes of text to whole pixels, so text can be given up to half a pixel less than the width it was measured at. Lines that fit that width still fit
+const ROUNDING_SLACK: f32 = 0.5;
+
+/// A line of text shaped with a style. Positions are in pixels relative to the start of the baseline.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ShapedLine {
+    pub glyphs: Vec<ShapedGlyph>,
+    pub width: f32,
+    /// The distance from the top of the line to the baseline, including half of the line gap of the font
+    pub ascent: f32,
+    /// The distance from the baseline to the bottom of the line, including the other half of the line gap
+    pub descent: f32,
+    /// The font size the glyphs are rasterized at
+    font_size: f32,
+    /// False while a font of the text loads in its block period. Invisible text still takes the space of the fallback font
+    pub visible: bool,
+    /// True if the base direction of the text is right to left, so the end of the text is at the start of the line
+    pub rtl: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ShapedGlyph {
+    id: u16,
+    /// The id of the face the glyph is from, which is a fallback face for characters the face of the text doesn't have
+    face: u16,
+    pub x: f32,
+    /// The offset from the baseline, down is positive
+    pub y: f32,
+    /// The byte offset in the text of the first character the glyph was shaped from
+    pub cluster: usize,
+    /// The distance from the start of the line to the caret before the characters of the glyph, which is on its right side in right to left text
+    caret: f32,
+}
+
+impl ShapedLine {
+    /// True if the glyphs of the line can be color bitmaps, which keep their colors and can't be drawn as subpixel text.
+    pub fn has_color_glyphs(&self) -> bool {
+        let mut faces: Vec<u16> = self.glyphs.iter().map(|glyph| glyph.face).collect();
+        faces.dedup();
+        faces.into_iter().any(|face| fonts::face(face).color)
+    }
+
+    pub fn height(&self) -> f32 {
+        self.ascent + self.descent
+    }
+
+    /// The distance from the start of the line to the character at a byte offset in the text, or the width of the line at the end of the text.
+    ///
+    /// The glyphs are in visual order, so the character at the offset is the first one after it in the text wherever it is on the line.
+    ///
+    /// TODO: Place offsets inside of ligatures
+    pub fn offset_x(&self, offset: usize) -> f32 {
+        self.glyphs
+            .iter()
+            .filter(|glyph| glyph.cluster >= offset)
+            .min_by_key(|glyph| glyph.cluster)
+            .map_or_else(|| self.end_x(), |glyph| glyph.caret)
+    }
+
+    /// The distance from the start of the line to the end of the text
+    fn end_x(&self) -> f32 {
+        if self.rtl {
+            0.0
+        } else {
+            self.width
+        }
+    }
+
+    /// The byte offset in a text of the boundary between characters that is closest to a distance from the start of the line, the inverse of [`ShapedLine::offset_x`].
+    pub fn offset_at(&self, text: &str, x: f32) -> usize {
+        let boundaries = self
+            .glyphs
+            .iter()
+            .map(|glyph| (glyph.cluster, glyph.caret))
+            .chain([(text.len(), self.end_x())]);
+        boundaries
+            .min_by(|(_, a), (_, b)| (a - x).abs().total_cmp(&(b - x).abs()))
+            .map_or(0, |(offset, _)| offset)
+    }
+}
+
+/// Shape a line of text with the font family, weight, style and size and the OpenType features of a style. The letter spacing of the style is added after each character and the line is as tall as its line height.
+///
+/// The text is split into runs of one direction with the Unicode bidirectional algorithm, so mixed-direction text like Hebrew with numbers or English words in it is ordered correctly. Each run is shaped on its own in the order it is shown in, so the glyphs of the line are in visual order from left to right. Characters the face of the text doesn't have are shaped with fallback faces, see [`crate::FontFallback`].
+pub(crate) fn shape(text: &str, style: &TextStyle) -> ShapedLine {
+    stats::count(Counter::TextRunsShaped, 1);
+    let (face_id, visible) = fonts::select(
+        &style.font_family,
+        style.font_weight,
+        style.font_style,
+        text,
+    );
+    // Optional ligatures are turned off in spaced text, so the letters they join are spaced too
+    let no_ligatures: &[([u8; 4], u32)] = if style.letter_spacing != 0.0 {
+        &[(*b"liga", 0), (*b"clig", 0), (*b"dlig", 0)]
+    } else {
+        &[]
+    };
+    let features: Vec<rustybuzz::Feature> = no_ligatures
+        .iter()
+        .chain(style.features.resolve())
+        .map(|(tag, value)| rustybuzz::Feature::new(rustybuzz::Tag::from_bytes(tag), *value, ..))
+        .collect();
+
+    let base_level = match style.direction {
+        Direction::Auto => None,
+        Direction::Ltr => Some(unicode_bidi::Level::ltr()),
+        Direction::Rtl => Some(unicode_bidi::Level::rtl()),
+    };
+    let bidi = unicode_bidi::BidiInfo::new(text, base_level);
+    let rtl = bidi
+        .paragraphs
+        .first()
+        .map_or(style.direction == Direction::Rtl, |paragraph| {
+            paragraph.level.is_rtl()
+        });
+    let mut x = 0.0;
+    let mut glyphs = Vec::new();
+    let mut faces = vec![face_id];
+    for paragraph in &bidi.paragraphs {
+        let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            let run_rtl = levels[run.start].is_rtl();
+            let mut face_runs = face_runs(&text[run.clone()], face_id, style);
+            // The runs of the faces are shown right to left too
+            if run_rtl {
+                face_runs.reverse();
+            }
+            for (range, run_face) in face_runs {
+                let face = fonts::face(run_face);
+                let scale = face.scale(style.font_size);
+                let start = run.start + range.start;
+                let mut buffer = rustybuzz::UnicodeBuffer::new();
+                buffer.push_str(&text[start..run.start + range.end]);
+                buffer.set_direction(if run_rtl {
+                    rustybuzz::Direction::RightToLeft
+                } else {
+                    rustybuzz::Direction::LeftToRight
+                });
+                buffer.guess_segment_properties();
+                let shaped = rustybuzz::shape(&face.shaper, &features, buffer);
+                let infos = shaped.glyph_infos();
+                for (index, (info, position)) in
+                    infos.iter().zip(shaped.glyph_positions()).enumerate()
+                {
+                    // The spacing goes after the last glyph of each character, so marks stay on their base
+                    let last_of_cluster = infos
+                        .get(index + 1)
+                        .map_or(true, |next| next.cluster != info.cluster);
+                    let spacing = if last_of_cluster {
+                        style.letter_spacing
+                    } else {
+                        0.0
+                    };
+                    let advance = position.x_advance as f32 * scale + spacing;
+                    glyphs.push(ShapedGlyph {
+                        id: info.glyph_id as u16,
+                        face: run_face,
+                        x: x + position.x_offset as f32 * scale,
+                        y: -position.y_offset as f32 * scale,
+                        cluster: start + info.cluster as usize,
+                        caret: if run_rtl { x + advance } else { x },
+                    });
+                    x += advance;
+                }
+                if !faces.contains(&run_face) {
+                    faces.push(run_face);
+                }
+            }
+        }
+    }
+    // The line is tall enough for the ascent and descent of every face on it. A line height replaces the line gaps of the faces, with half of the difference above and below the glyphs
+    let (mut ascent, mut descent) = faces
+        .into_iter()
+        .map(|face| {
+            let face = fonts::face(face);
+            let scale = face.scale(style.font_size);
+            let half_gap = match style.line_height {
+                Some(_) => 0.0,
+                None => face.shaper.line_gap() as f32 * scale / 2.0,
+            };
+            (
+                face.shaper.ascender() as f32 * scale + half_gap,
+                -face.shaper.descender() as f32 * scale + half_gap,
+            )
+        })
+        .fold(
+            (0.0, 0.0),
+            |(ascent, descent), (face_ascent, face_descent)| {
+                (
+                    f32::max(ascent, face_ascent),
+                    f32::max(descent, face_descent),
+                )
+            },
+        );
+    if let Some(line_height) = style.line_height {
+        let half_leading = (line_height.resolve(style.font_size) - (ascent + descent)) / 2.0;
+        ascent += half_leading;
+        descent += half_leading;
+    }
+    ShapedLine {
+        glyphs,
+        width: x,
+        ascent,
+        descent,
+        font_size: style.font_size,
+        visible,
+        rtl,
+    }
+}
+
+/// Split a run of text into the runs of the faces its characters are shaped with, in the order of the text. Characters the face of the text doesn't have are shaped with the fallback face that has them, which the characters after them keep while it has them too. Characters that scripts share, like spaces and combining marks, stay in the face of the characters before them, so marks and emoji sequences are shaped together.
+fn face_runs(text: &str, face_id: u16, style: &TextStyle) -> Vec<(Range<usize>, u16)> {
+    let face = fonts::face(face_id);
+    let mut runs: Vec<(Range<usize>, u16)> = Vec::new();
+    for (offset, character) in text.char_indices() {
+        let previous = runs.last().map(|(_, face)| *face);
+        let shared = matches!(character.script(), Script::Common | Script::Inherited);
+        let fallback = previous
+            .filter(|previous| *previous != face_id)
+            .filter(|previous| fonts::face(*previous).has_glyph(character));
+        let run_face = match fallback {
+            Some(previous) if shared || !face.has_glyph(character) => previous,
+            _ if face.has_glyph(character) => face_id,
+            _ => fonts::fallback(character, style.font_weight, style.font_style).unwrap_or(face_id),
+        };
+        let end = offset + character.len_utf8();
+        match runs.last_mut() {
+            Some((range, last)) if *last == run_face => range.end = end,
+            _ => runs.push((offset..end, run_face)),
+        }
+    }
+    runs
+}
+
+/// Text broken into lines with the `white-space` of a style, see [`shape_text`]. Positions are in pixels relative to the top left corner of the text.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ShapedText {
+    /// The lines from the top down. There is always at least one line, so empty text has a height and a caret
+    pub lines: Vec<TextLine>,
+    /// The width of the widest line
+    pub width: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TextLine {
+    pub line: ShapedLine,
+    /// The text the line was shaped from, with its white space collapsed
+    text: String,
+    /// The byte offset in the original text of each byte of the text of the line and of its end
+    offsets: Vec<usize>,
+    /// The distance from the top of the text to the top of the line
+    pub top: f32,
+    /// The distance from the left of the text to the start of the line. Right to left lines are aligned to the right
+    pub left: f32,
+}
+
+impl TextLine {
+    /// The distance from the left of the text to the caret at an offset in the original text.
+    fn x(&self, offset: usize) -> f32 {
+        let index = self.offsets.partition_point(|start| *start < offset);
+        self.left + self.line.offset_x(index.min(self.text.len()))
+    }
+
+    fn end(&self) -> usize {
+        *self.offsets.last().unwrap()
+    }
+}
+
+impl ShapedText {
+    pub fn height(&self) -> f32 {
+        self.lines
+            .last()
+            .map_or(0.0, |line| line.top + line.line.height())
+    }
+
+    pub fn has_color_glyphs(&self) -> bool {
+        self.lines.iter().any(|line| line.line.has_color_glyphs())
+    }
+
+    /// The line an offset in the original text is on. An offset where a line wraps is at the end of the line before it.
+    fn line_of(&self, offset: usize) -> &TextLine {
+        self.lines
+            .iter()
+            .find(|line| offset <= line.end())
+            .unwrap_or_else(|| self.lines.last().unwrap())
+    }
+
+    /// The top of the caret before the character at an offset in the original text, and its height.
+    pub fn caret(&self, offset: usize) -> (Pos2, f32) {
+        let line = self.line_of(offset);
+        (epaint::pos2(line.x(offset), line.top), line.line.height())
+    }
+
+    /// The rects that highlight a range of the original text, one for each line it is on.
+    pub fn highlights(&self, range: Range<usize>) -> Vec<Rect> {
+        self.lines
+            .iter()
+            .filter_map(|line| {
+                let start = range.start.max(line.offsets[0]);
+                let end = range.end.min(line.end());
+                (start < end).then(|| {
+                    // The end is left of the start in right to left text
+                    Rect::from_two_pos(
+                        epaint::pos2(line.x(start), line.top),
+                        epaint::pos2(line.x(end), line.top + line.line.height()),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// The byte offset in the original text of the boundary between characters that is closest to a point: on the line the point is next to, the closest one along it.
+    pub fn offset_at(&self, point: Pos2) -> usize {
+        let line = self
+            .lines
+            .iter()
+            .find(|line| point.y < line.top + line.line.height())
+            .unwrap_or_else(|| self.lines.last().unwrap());
+        let offset = line.line.offset_at(&line.text, point.x - line.left);
+        line.offsets[offset.min(line.text.len())]
+    }
+}
+
+/// Collapse each run of white space into one space, like `white-space: normal` and `nowrap` do. Returns the collapsed text and the offset in the text of each of its bytes and of its end.
+fn collapse_white_space(text: &str) -> (String, Vec<usize>) {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    let mut after_space = false;
+    for (offset, character) in text.char_indices() {
+        let space = matches!(character, ' ' | '\t' | '\n' | '\r' | '\u{c}');
+        if space && after_space {
+            continue;
+        }
+        after_space = space;
+        let character = if space { ' ' } else { character };
+        collapsed.push(character);
+        offsets.extend((0..character.len_utf8()).map(|byte| offset + byte));
+    }
+    offsets.push(text.len());
+    (collapsed, offsets)
+}
+
+/// Break text into lines and shape them, with the white space handling of the style. Text with `white-space: normal` wraps at the break opportunities of the Unicode line breaking algorithm to fit `width`, without a width it doesn't wrap. White space at the end of a line that wraps hangs past its edge.
+///
+/// Text is measured for layout and drawn with the same lines. Each line is shaped with [`shape_fitted`], so fitted text shrinks instead of wrapping and lines are cut off with an ellipsis.
+pub(crate) fn shape_text(text: &str, style: &TextStyle, width: Option<f32>) -> ShapedText {
+    let (collapsed, offsets) = match style.white_space {
+        WhiteSpace::Pre => (text.to_string(), (0..=text.len()).collect()),
+        WhiteSpace::Normal | WhiteSpace::Nowrap => collapse_white_space(text),
+    };
+    let wrap_width =
+        width.filter(|_| style.white_space == WhiteSpace::Normal && style.fit.is_none());
+
+    // The ranges of the lines in the collapsed text, and whether they end where the text wraps
+    let mut ranges: Vec<(Range<usize>, bool)> = Vec::new();
+    let mut line_start = 0;
+    // The end of the last segment on the line and the width of the line up to it
+    let mut line_end = 0;
+    let mut line_width = 0.0;
+    for (end, opportunity) in linebreaks(&collapsed) {
+        let segment = &collapsed[line_end..end];
+        if let Some(wrap_width) = wrap_width {
+            let segment_width = shape(segment, style).width;
+            let hanging = segment.trim_end_matches(' ');
+            let visible_width = match hanging.len() == segment.len() {
+                true => segment_width,
+                false => shape(hanging, style).width,
+            };
+            if line_end > line_start && line_width + visible_width > wrap_width + ROUNDING_SLACK {
+                ranges.push((line_start..line_end, true));
+                line_start = line_end;
+                line_width = 0.0;
+            }
+            line_width += segment_width;
+        }
+        line_end = end;
+        if opportunity == BreakOpportunity::Mandatory {
+            ranges.push((line_start..line_end, false));
+            line_start = line_end;
+            line_width = 0.0;
+        }
+    }
+    if ranges.is_empty() {
+        ranges.push((0..0, false));
+    }
+
+    let mut lines = Vec::with_capacity(ranges.len());
+    let mut top = 0.0;
+    for (range, wraps) in ranges {
+        let line_text =
+            collapsed[range.clone()].trim_end_matches(['\n', '\r', '\u{2028}', '\u{2029}']);
+        let line_text = if wraps {
+            line_text.trim_end_matches(' ')
+        } else {
+            line_text
+        };
+        let end = range.start + line_text.len();
+        let line = shape_fitted(line_text, style, width);
+        let height = line.height();
+        lines.push(TextLine {
+            line,
+            text: line_text.to_string(),
+            offsets: offsets[range.start..=end].to_vec(),
+            top,
+            left: 0.0,
+        });
+        top += height;
+    }
+    let width = lines.iter().map(|line| line.line.width).fold(0.0, f32::max);
+    for line in &mut lines {
+        if line.line.rtl {
+            line.left = width - line.line.width;
+        }
+    }
+    ShapedText { lines, width }
+}
+
+/// Shape a line of text, shrinking it to fit a width if the style has `fit-text` and cutting off its end with an ellipsis if it still doesn't fit and the style has `text-overflow: ellipsis`. Without a width, fitted text is shaped at its largest size and isn't cut off.
+///
+/// Text is measured for layout and drawn with the same line, so the width taffy gives the text is the width of the glyphs that are drawn.
+pub(crate) fn shape_fitted(text: &str, style: &TextStyle, width: Option<f32>) -> ShapedLine {
+    let (line, style) = fit(text, style, width);
+    match width {
+        Some(width) if style.ellipsis && line.width > width + ROUNDING_SLACK => {
+            ellipsize(text, &style, width)
+        }
+        _ => line,
+    }
+}
+
+/// Shape the longest start of a line of text that fits a width with an ellipsis after it. The ellipsis is shown on its own if not even the first character fits.
+///
+/// The offsets of the glyphs before the ellipsis are the offsets in the text, so carets and selections in the visible part stay where they are.
+fn ellipsize(text: &str, style: &TextStyle, width: f32) -> ShapedLine {
+    let ends: Vec<usize> = text.char_indices().map(|(offset, _)| offset).collect();
+    let shape_start = |end: usize| shape(&format!("{}\u{2026}", text[..end].trim_end()), style);
+    // The width grows with the number of characters, so the longest start that fits is found by bisecting them
+    let (mut fits, mut overflows) = (0, ends.len());
+    while overflows - fits > 1 {
+        let middle = (fits + overflows) / 2;
+        if shape_start(ends[middle]).width <= width {
+            fits = middle;
+        } else {
+            overflows = middle;
+        }
+    }
+    shape_start(ends.get(fits).copied().unwrap_or_default())
+}
+
+/// Shape a line of text at the font size of `fit-text` that fits a width, and return it with the style it was shaped with.
+fn fit(text: &str, style: &TextStyle, width: Option<f32>) -> (ShapedLine, TextStyle) {
+    let Some((min, max)) = style.fit else {
+        return (shape(text, style), style.clone());
+    };
+    let largest = TextStyle {
+        font_size: max,
+        ..style.clone()
+    };
+    let line = shape(text, &largest);
+    match width {
+        // Glyphs aren't hinted, so the width of the line grows linearly with the font size
+        Some(width) if line.width > width => {
+            let font_size = (max * width / line.width).clamp(min, max);
+            let fitted = TextStyle {
+                font_size,
+                ..largest
+            };
+            (shape(text, &fitted), fitted)
+        }
+        _ => (line, largest),
+    }
+}
+
+/// The size of text measured with [`measure_text`], in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextMetrics {
+    /// The width of the widest line
+    pub width: f32,
+    /// The height of all lines together
+    pub height: f32,
+    pub line_count: usize,
+    /// The distance from the top of the text to the baseline of the first line
+    pub baseline: f32,
+    /// The distance from the baseline of the first line to its top, including half of the line gap of the font, or half of the leading of the line height
+    pub ascent: f32,
+    /// The distance from the baseline of the last line to its bottom, including the other half of the line gap or leading
+    pub descent: f32,
+}
+
+/// Measure text without adding it to a document, to size canvases, truncate text or align custom drawings to it.
+///
+/// `style` holds font declarations like an element's style, such as `font-family: Inter, sans-serif; font-size: 14px; font-weight: bold`. The text is shaped like the text of an element with that style, with the default font size of 16 pixels. Properties that don't affect fonts are ignored. Text doesn't wrap, so lines only end at line breaks.
+///
+/// Fonts that are still loading are measured with the face that is used until they are loaded. Returns an error if a declaration can't be parsed.
+pub fn measure_text(text: &str, style: &str) -> io::Result<TextMetrics> {
+    let mut declarations = Vec::new();
+    for declaration in style
+        .split(';')
+        .filter(|declaration| !declaration.trim().is_empty())
+    {
+        let (name, value) = declaration.split_once(':').ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("expected a declaration like `name: value`, found {declaration:?}"),
+            )
+        })?;
+        declarations.push((name.trim(), value.trim()));
+    }
+    let mut text_style = TextStyle::default();
+    let mut invalid = None;
+    text_style.apply(declarations, |name, value| {
+        invalid.get_or_insert_with(|| format!("invalid value for {name}: {value:?}"));
+    });
+    if let Some(invalid) = invalid {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, invalid));
+    }
+
+    let lines: Vec<ShapedLine> = text
+        .split('\n')
+        .map(|line| shape(line.strip_suffix('\r').unwrap_or(line), &text_style))
+        .collect();
+    let first = &lines[0];
+    let last = &lines[lines.len() - 1];
+    Ok(TextMetrics {
+        width: lines.iter().map(|line| line.width).fold(0.0, f32::max),
+        height: lines.iter().map(ShapedLine::height).sum(),
+        line_count: lines.len(),
+        baseline: first.ascent,
+        ascent: first.ascent,
+        descent: last.descent,
+    })
+}
+
+/// The weights the coverage of a subpixel is spread over its neighbours with, so the colors at the edges of glyphs don't fringe. The same as the default LCD filter of FreeType
+const LCD_FILTER: [f32; 5] = [
+    8.0 / 256.0,
+    77.0 / 256.0,
+    86.0 / 256.0,
+    77.0 / 256.0,
+    8.0 / 256.0,
+];
+
+/// Build a mesh that draws a shaped line with the start of its baseline at `origin`. `scale` is the number of pixels per unit of the mesh, so the glyphs are rasterized sharp when the mesh is drawn scaled.
+///
+/// With `subpixel`, the glyphs are rasterized with the coverage of the red, green and blue stripes of LCD pixels in the color channels of their texels, which the mesh has to be drawn with [`crate::Backend::draw_subpixel_text`]. Lines with color glyphs can't be drawn that way, see [`ShapedLine::has_color_glyphs`].
+///
+/// Color glyphs, like emoji, keep their own colors and only take the alpha of `color`. The layers of COLR glyphs that are filled with the color of the text are filled with `color`.
+pub(crate) fn text_mesh(
+    line: &ShapedLine,
+    origin: Pos2,
+    color: Color32,
+    scale: f32,
+    subpixel: bool,
+) -> Mesh {
+    let mut mesh = Mesh::default();
+    if line.glyphs.is_empty() || !line.visible || scale <= 0.0 {
+        return mesh;
+    }
+    // Texels are multiplied by the color of the vertices
+    let bitmap_color = Color32::from_white_alpha(color.a());
+    let mut atlas = ATLAS.lock().unwrap();
+    // Sizes are rounded to quarter pixels, so text that is scaled smoothly doesn't fill the atlas
+    let size = (line.font_size * scale * 4.0).round() as u32;
+    for glyph in &line.glyphs {
+        let face = fonts::face(glyph.face);
+        let key = AtlasKey::Glyph {
+            face: glyph.face,
+            id: glyph.id,
+            size,
+            subpixel,
+        };
+        // Glyphs without a bitmap are cached as None too, so they fall back to their outline without looking the bitmap up again
+        let bitmap = face.color.then(|| {
+            let layers = face.layers.as_ref().and_then(|layers| layers.of(glyph.id));
+            // Glyphs with layers in the color of the text are rasterized for each color
+            let foreground = layers
+                .as_ref()
+                .filter(|layers| layers.iter().any(|(_, color)| color.is_none()))
+                .map(|_| {
+                    let [r, g, b, _] = color.to_srgba_unmultiplied();
+                    [r, g, b]
+                });
+            let key = AtlasKey::ColorGlyph {
+                face: glyph.face,
+                id: glyph.id,
+                size,
+                foreground,
+            };
+            atlas.get_or_insert(key, || match &layers {
+                Some(layers) => rasterize_layers(face, layers, size, foreground),
+                None => rasterize_color(face, glyph.id, size),
+            })
+        });
+        let (region, color) = match bitmap.flatten() {
+            Some(region) => (region, bitmap_color),
+            None => {
+                let create = || {
+                    if subpixel {
+                        rasterize_subpixel(face, glyph.id, size)
+                    } else {
+                        rasterize(face, glyph.id, size)
+                    }
+                };
+                let Some(region) = atlas.get_or_insert(key, create) else {
+                    continue;
+                };
+                (region, color)
+            }
+        };
+        let min = origin + epaint::vec2(glyph.x, glyph.y) + region.offset / scale;
+        let rect = Rect::from_min_size(min, region.size / scale);
+        mesh.add_rect_with_uv(rect, region.uv, color);
+    }
+    mesh
+}
+
+/// The height ab_glyph scales a face to for a font size in quarter pixels. ab_glyph scales the height of the font instead of the em square
+fn scaled_height(face: &Face, size: u32) -> Option<f32> {
+    let font_size = size as f32 / 4.0;
+    Some(font_size * face.outlines.height_unscaled() / face.outlines.units_per_em()?)
+}
+
+/// Rasterize a glyph at a size in quarter pixels into white texels with the coverage as alpha, and the offset of its top left corner from the origin of the glyph.
+fn rasterize(face: &Face, id: u16, size: u32) -> Option<(ColorImage, Vec2)> {
+    let height = scaled_height(face, size)?;
+    let outlined = face.outlines.outline_glyph(
+        ab_glyph::GlyphId(id).with_scale_and_position(height, ab_glyph::point(0.0, 0.0)),
+    )?;
+    let bounds = outlined.px_bounds();
+    let mut image = ColorImage::new(
+        [bounds.width() as usize, bounds.height() as usize],
+        Color32::TRANSPARENT,
+    );
+    outlined.draw(|x, y, coverage| {
+        // Premultiplied white with linear alpha, like every other texel
+        image[(x as usize, y as usize)] =
+            Color32::from(Rgba::from_white_alpha(coverage.clamp(0.0, 1.0)));
+    });
+    Some((image, epaint::vec2(bounds.min.x, bounds.min.y)))
+}
+
+/// Rasterize the color layers of a COLR glyph at a size in quarter pixels into premultiplied texels, with the layers that have no color filled with `foreground`, and the offset of its top left corner from the origin of the glyph. The layers are blended over each other from the bottom up.
+fn rasterize_layers(
+    face: &Face,
+    layers: &[(u16, Option<Color32>)],
+    size: u32,
+    foreground: Option<[u8; 3]>,
+) -> Option<(ColorImage, Vec2)> {
+    let height = scaled_height(face, size)?;
+    let [r, g, b] = foreground.unwrap_or_default();
+    let outlined: Vec<_> = layers
+        .iter()
+        .filter_map(|(id, color)| {
+            let outlined = face.outlines.outline_glyph(
+                ab_glyph::GlyphId(*id).with_scale_and_position(height, ab_glyph::point(0.0, 0.0)),
+            )?;
+            Some((
+                outlined,
+                Rgba::from(color.unwrap_or(Color32::from_rgb(r, g, b))),
+            ))
+        })
+        .collect();
+    let bounds = outlined
+        .iter()
+        .map(|(outlined, _)| outlined.px_bounds())
+        .reduce(|a, b| ab_glyph::Rect {
+            min: ab_glyph::point(a.min.x.min(b.min.x), a.min.y.min(b.min.y)),
+            max: ab_glyph::point(a.max.x.max(b.max.x), a.max.y.max(b.max.y)),
+        })?;
+    let [width, rows] = [bounds.width() as usize, bounds.height() as usize];
+    let mut texels = vec![Rgba::TRANSPARENT; width * rows];
+    for (outlined, color) in &outlined {
+        // Pixel bounds are whole pixels, so each layer is offset by whole texels
+        let layer = outlined.px_bounds();
+        let [left, top] = [
+            (layer.min.x - bounds.min.x) as usize,
+            (layer.min.y - bounds.min.y) as usize,
+        ];
+        outlined.draw(|x, y, coverage| {
+            let index = (top + y as usize) * width + left + x as usize;
+            if let Some(texel) = texels.get_mut(index) {
+                let source = *color * coverage.clamp(0.0, 1.0);
+                *texel = source + *texel * (1.0 - source.a());
+            }
+        });
+    }
+    let image = ColorImage {
+        size: [width, rows],
+        pixels: texels.into_iter().map(Color32::from).collect(),
+    };
+    Some((image, epaint::vec2(bounds.min.x, bounds.min.y)))
+}
+
+/// Decode the color bitmap of a glyph from the CBDT or sbix table of its face, scaled from the closest strike to a size in quarter pixels, into premultiplied texels and the offset of its top left corner from the origin of the glyph.
+///
+/// Returns None if the glyph has no bitmap in a format that is supported, so it is drawn from its outline.
+fn rasterize_color(face: &Face, id: u16, size: u32) -> Option<(ColorImage, Vec2)> {
+    let font_size = size as f32 / 4.0;
+    // The smallest strike that is at least as large as the text, or the largest one
+    let strike = face
+        .outlines
+        .glyph_raster_image2(ab_glyph::GlyphId(id), font_size.ceil() as u16)?;
+    let image = match strike.format {
+        ab_glyph::GlyphImageFormat::Png => {
+            image::load_from_memory_with_format(strike.data, image::ImageFormat::Png)
+                .ok()?
+                .into_rgba8()
+        }
+        ab_glyph::GlyphImageFormat::BitmapPremulBgra32 => {
+            let (width, height) = (u32::from(strike.width), u32::from(strike.height));
+            let mut pixels = strike.data.get(..(width * height * 4) as usize)?.to_vec();
+            for pixel in pixels.chunks_exact_mut(4) {
+                let [b, g, r, a] = [pixel[0], pixel[1], pixel[2], pixel[3]];
+                let [r, g, b, _] =
+                    Color32::from_rgba_premultiplied(r, g, b, a).to_srgba_unmultiplied();
+                pixel.copy_from_slice(&[r, g, b, a]);
+            }
+            image::RgbaImage::from_raw(width, height, pixels)?
+        }
+        _ => return None,
+    };
+    let scale = font_size / f32::from(strike.pixels_per_em.max(1));
+    let width = (image.width() as f32 * scale).round().max(1.0) as u32;
+    let height = (image.height() as f32 * scale).round().max(1.0) as u32;
+    let image =
+        image::imageops::resize(&image, width, height, image::imageops::FilterType::Triangle);
+    let pixels = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            Color32::from_rgba_unmultiplied(r, g, b, a)
+        })
+        .collect();
+    let image = ColorImage {
+        size: [width as usize, height as usize],
+        pixels,
+    };
+    // The origin of the strike is the bottom left corner of the bitmap, above the baseline
+    let offset = epaint::vec2(
+        strike.origin.x * scale,
+        -strike.origin.y * scale - height as f32,
+    );
+    Some((image, offset))
+}
+
+/// Rasterize a glyph like [`rasterize`], with the coverage of the red, green and blue thirds of each pixel in the color channels and the largest of them as alpha. The glyph is rasterized at three times its width and filtered with [`LCD_FILTER`], so the image is wider than the glyph by a pixel on each side.
+fn rasterize_subpixel(face: &Face, id: u16, size: u32) -> Option<(ColorImage, Vec2)> {
+    let height = scaled_height(face, size)?;
+    let scale = ab_glyph::PxScale {
+        x: height * 3.0,
+        y: height,
+    };
+    let outlined = face.outlines.outline_glyph(
+        ab_glyph::GlyphId(id).with_scale_and_position(scale, ab_glyph::point(0.0, 0.0)),
+    )?;
+    let bounds = outlined.px_bounds();
+    let [subpixels, rows] = [bounds.width() as usize, bounds.height() as usize];
+    let mut coverage = vec![0.0; subpixels * rows];
+    outlined.draw(|x, y, value| {
+        coverage[y as usize * subpixels + x as usize] = value.clamp(0.0, 1.0);
+    });
+
+    // The filter spreads each subpixel over two neighbours on each side
+    let spread = (LCD_FILTER.len() / 2) as i32;
+    let first = bounds.min.x as i32;
+    let min_x = (first - spread).div_euclid(3);
+    let max_x = (first + subpixels as i32 + spread + 2).div_euclid(3);
+    let mut image = ColorImage::new([(max_x - min_x) as usize, rows], Color32::TRANSPARENT);
+    for y in 0..rows {
+        let row = &coverage[y * subpixels..(y + 1) * subpixels];
+        let filtered = |subpixel: i32| -> f32 {
+            LCD_FILTER
+                .iter()
+                .enumerate()
+                .filter_map(|(tap, weight)| {
+                    let x = subpixel - first + tap as i32 - spread;
+                    usize::try_from(x)
+                        .ok()
+                        .and_then(|x| row.get(x))
+                        .map(|value| value * weight)
+                })
+                .sum()
+        };
+        for x in min_x..max_x {
+            let [r, g, b] = [0, 1, 2].map(|channel| filtered(x * 3 + channel).min(1.0));
+            // Premultiplied like the other texels, with the channels covered on their own
+            image[((x - min_x) as usize, y)] =
+                Color32::from(Rgba::from_rgba_premultiplied(r, g, b, r.max(g).max(b)));
+        }
+    }
+    Some((image, epaint::vec2(min_x as f32, bounds.min.y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clusters(line: &ShapedLine) -> Vec<usize> {
+        line.glyphs.iter().map(|glyph| glyph.cluster).collect()
+    }
+
+    fn line_texts(text: &ShapedText) -> Vec<&str> {
+        text.lines.iter().map(|line| line.text.as_str()).collect()
+    }
+
+    #[test]
+    fn bidi_order() {
+        let ltr = TextStyle {
+            direction: Direction::Ltr,
+            ..TextStyle::default()
+        };
+        let line = shape("abc אבג", &ltr);
+        // The Hebrew word is shown right to left after the English one
+        assert_eq!(clusters(&line), [0, 1, 2, 3, 8, 6, 4]);
+        assert!(!line.rtl);
+
+        let rtl = TextStyle {
+            direction: Direction::Rtl,
+            ..TextStyle::default()
+        };
+        let line = shape("אבג abc", &rtl);
+        assert_eq!(clusters(&line), [7, 8, 9, 6, 4, 2, 0]);
+        assert!(line.rtl);
+        // The start of right to left text is on the right
+        assert_eq!(line.offset_x(0), line.width);
+        assert_eq!(line.offset_x("אבג abc".len()), 0.0);
+
+        // The first strong character sets the direction
+        assert!(shape("אבג abc", &TextStyle::default()).rtl);
+        assert!(!shape("abc אבג", &TextStyle::default()).rtl);
+    }
+
+    #[test]
+    fn collapse() {
+        assert_eq!(
+            collapse_white_space("a  \n b"),
+            ("a b".to_string(), vec![0, 1, 5, 6])
+        );
+        assert_eq!(collapse_white_space(""), (String::new(), vec![0]));
+    }
+
+    #[test]
+    fn wrapping() {
+        let style = TextStyle::default();
+        let width = shape("aaa bbb", &style).width;
+        let text = shape_text("aaa bbb ccc", &style, Some(width));
+        // The space where the line wraps hangs past its end
+        assert_eq!(line_texts(&text), ["aaa bbb", "ccc"]);
+        assert_eq!(text.lines[1].offsets, [8, 9, 10, 11]);
+        assert_eq!(text.lines[1].top, text.lines[0].line.height());
+        assert_eq!(text.height(), 2.0 * text.lines[0].line.height());
+        assert_eq!(text.caret(8).0, epaint::pos2(0.0, text.lines[1].top));
+
+        let text = shape_text("aaa  bbb   ccc", &style, None);
+        assert_eq!(line_texts(&text), ["aaa bbb ccc"]);
+        assert_eq!(text.offset_at(epaint::pos2(-10.0, 0.0)), 0);
+        assert_eq!(text.offset_at(epaint::pos2(1000.0, 0.0)), 14);
+
+        let nowrap = TextStyle {
+            white_space: WhiteSpace::Nowrap,
+            ..TextStyle::default()
+        };
+        let text = shape_text("aaa bbb ccc", &nowrap, Some(width));
+        assert_eq!(line_texts(&text), ["aaa bbb ccc"]);
+
+        let pre = TextStyle {
+            white_space: WhiteSpace::Pre,
+            ..TextStyle::default()
+        };
+        let text = shape_text("a  b\nc", &pre, Some(width));
+        assert_eq!(line_texts(&text), ["a  b", "c"]);
+
+        // Empty text still has a line
+        let text = shape_text("", &style, Some(width));
+        assert_eq!(line_texts(&text), [""]);
+        assert!(text.height() > 0.0);
+    }
+
+    #[test]
+    fn ellipsis() {
+        let style = TextStyle {
+            ellipsis: true,
+            ..TextStyle::default()
+        };
+        let cut = shape("hello\u{2026}", &style);
+        // The space before the ellipsis is trimmed
+        assert_eq!(shape_fitted("hello world", &style, Some(cut.width)), cut);
+        assert_eq!(
+            shape_fitted("hello world", &style, Some(1.0)),
+            shape("\u{2026}", &style)
+        );
+        assert_eq!(
+            shape_fitted("hello", &style, Some(1000.0)),
+            shape("hello", &style)
+        );
+        // Text without a width isn't cut off
+        assert_eq!(
+            shape_fitted("hello world", &style, None),
+            shape("hello world", &style)
+        );
+    }
+}
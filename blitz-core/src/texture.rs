@@ -0,0 +1,302 @@
+use beuk::ash::vk;
+use beuk::ctx::RenderContext;
+use beuk::memory::BufferHandle;
+use epaint::{Color32, ColorImage, ImageData, ImageDelta};
+
+use crate::attachment::{Attachment, COLOR_SUBRESOURCE_RANGE};
+use crate::buffer::GrowableBuffer;
+
+/// The layout of the descriptor set a texture is bound with: the image at binding 0 and its sampler at binding 1, like the texture bind group of the wgpu backend.
+pub(crate) fn create_texture_layout(device: &beuk::ash::Device) -> vk::DescriptorSetLayout {
+    let bindings = [
+        vk::DescriptorSetLayoutBinding::default()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+        vk::DescriptorSetLayoutBinding::default()
+            .binding(1)
+            .descriptor_type(vk::DescriptorType::SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT),
+    ];
+    unsafe {
+        device.create_descriptor_set_layout(
+            &vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings),
+            None,
+        )
+    }
+    .unwrap()
+}
+
+/// The sampler textures are sampled with. Glyphs and images are drawn at fractional positions, so texels are interpolated.
+pub(crate) fn create_sampler(device: &beuk::ash::Device) -> vk::Sampler {
+    let info = vk::SamplerCreateInfo::default()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+    unsafe { device.create_sampler(&info, None) }.unwrap()
+}
+
+/// An image shaders sample, with the descriptor set it is bound with.
+pub(crate) struct SampledImage {
+    pub image: Attachment,
+    pool: vk::DescriptorPool,
+    pub set: vk::DescriptorSet,
+}
+
+impl SampledImage {
+    /// Bind an image with a layout created by [`create_texture_layout`]. The image needs the `SAMPLED` usage.
+    pub fn new(
+        ctx: &RenderContext,
+        image: Attachment,
+        layout: vk::DescriptorSetLayout,
+        sampler: vk::Sampler,
+    ) -> Self {
+        let device = &ctx.device;
+        let pool_sizes = [
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::SAMPLED_IMAGE,
+                descriptor_count: 1,
+            },
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::SAMPLER,
+                descriptor_count: 1,
+            },
+        ];
+        let pool = unsafe {
+            device.create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::default()
+                    .max_sets(1)
+                    .pool_sizes(&pool_sizes),
+                None,
+            )
+        }
+        .unwrap();
+        let set = unsafe {
+            device.allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(pool)
+                    .set_layouts(std::slice::from_ref(&layout)),
+            )
+        }
+        .unwrap()[0];
+        let image_info = [vk::DescriptorImageInfo::default()
+            .image_view(image.view)
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)];
+        let sampler_info = [vk::DescriptorImageInfo::default().sampler(sampler)];
+        let writes = [
+            vk::WriteDescriptorSet::default()
+                .dst_set(set)
+                .dst_binding(0)
+                .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+                .image_info(&image_info),
+            vk::WriteDescriptorSet::default()
+                .dst_set(set)
+                .dst_binding(1)
+                .descriptor_type(vk::DescriptorType::SAMPLER)
+                .image_info(&sampler_info),
+        ];
+        unsafe { device.update_descriptor_sets(&writes, &[]) };
+        Self { image, pool, set }
+    }
+
+    /// Free the image and its descriptor set. The GPU must be done using them.
+    pub fn destroy(&self, ctx: &RenderContext) {
+        unsafe { ctx.device.destroy_descriptor_pool(self.pool, None) };
+        self.image.destroy(ctx);
+    }
+}
+
+/// The texture meshes sample with their uv coordinates, see [`crate::backend::Backend::update_texture`]. Updates are kept until the next frame and copied to the image through a staging buffer before it is drawn.
+pub(crate) struct Texture {
+    sampled: SampledImage,
+    /// The images that are copied into the texture before the next frame, with the position of their top left corner
+    pending: Vec<([usize; 2], ColorImage)>,
+    /// False until the image was copied to, so its contents can be discarded
+    written: bool,
+    staging: GrowableBuffer,
+}
+
+impl Texture {
+    /// Create a texture that is white until the first update, so shapes without a texture can be drawn before it.
+    pub fn new(ctx: &RenderContext, layout: vk::DescriptorSetLayout, sampler: vk::Sampler) -> Self {
+        let white = ColorImage::new([1, 1], Color32::WHITE);
+        Self {
+            sampled: create_image(ctx, [1, 1], layout, sampler),
+            pending: vec![([0, 0], white)],
+            written: false,
+            staging: GrowableBuffer::new("texture upload", vk::BufferUsageFlags::TRANSFER_SRC),
+        }
+    }
+
+    pub fn set(&self) -> vk::DescriptorSet {
+        self.sampled.set
+    }
+
+    /// Keep an update to copy before the next frame. A delta that replaces the whole texture with another size creates a new image.
+    pub fn update(
+        &mut self,
+        ctx: &RenderContext,
+        layout: vk::DescriptorSetLayout,
+        sampler: vk::Sampler,
+        delta: &ImageDelta,
+    ) {
+        let image = match &delta.image {
+            ImageData::Color(image) => image.clone(),
+            ImageData::Font(image) => ColorImage {
+                size: image.size,
+                pixels: image.srgba_pixels(None).collect(),
+            },
+        };
+        let pos = match delta.pos {
+            Some(pos) => pos,
+            None => {
+                let [width, height] = image.size.map(|size| size.max(1) as u32);
+                let extent = self.sampled.image.extent;
+                if extent.width != width || extent.height != height {
+                    // The last frame may still sample the old image
+                    unsafe { ctx.device.device_wait_idle().unwrap() };
+                    let old = std::mem::replace(
+                        &mut self.sampled,
+                        create_image(ctx, [width, height], layout, sampler),
+                    );
+                    old.destroy(ctx);
+                    self.written = false;
+                }
+                // Updates before it are overwritten
+                self.pending.clear();
+                [0, 0]
+            }
+        };
+        if image.width() > 0 && image.height() > 0 {
+            self.pending.push((pos, image));
+        }
+    }
+
+    /// Write the pending updates into the staging buffer. The returned upload is recorded before the frame is drawn.
+    pub fn prepare_upload(&mut self, ctx: &mut RenderContext) -> Option<TextureUpload> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let mut texels: Vec<Color32> = Vec::new();
+        let mut regions = Vec::with_capacity(self.pending.len());
+        for ([x, y], image) in self.pending.drain(..) {
+            regions.push(
+                vk::BufferImageCopy::default()
+                    .buffer_offset((texels.len() * std::mem::size_of::<Color32>()) as u64)
+                    .image_subresource(vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    })
+                    .image_offset(vk::Offset3D {
+                        x: x as i32,
+                        y: y as i32,
+                        z: 0,
+                    })
+                    .image_extent(vk::Extent3D {
+                        width: image.width() as u32,
+                        height: image.height() as u32,
+                        depth: 1,
+                    }),
+            );
+            texels.extend_from_slice(&image.pixels);
+        }
+        let buffer = self.staging.write(ctx, &texels);
+        let old_layout = if self.written {
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+        } else {
+            vk::ImageLayout::UNDEFINED
+        };
+        self.written = true;
+        Some(TextureUpload {
+            image: self.sampled.image.image,
+            buffer,
+            regions,
+            old_layout,
+        })
+    }
+
+    /// Free the image. The GPU must be done using it.
+    pub fn destroy(&self, ctx: &RenderContext) {
+        self.sampled.destroy(ctx);
+    }
+}
+
+/// Copies from the staging buffer into the texture
+pub(crate) struct TextureUpload {
+    image: vk::Image,
+    buffer: BufferHandle,
+    regions: Vec<vk::BufferImageCopy>,
+    /// The layout of the image before the copies, UNDEFINED if its contents are discarded
+    old_layout: vk::ImageLayout,
+}
+
+impl TextureUpload {
+    /// Record the copies with the barriers that order them after the last frame sampled the texture and before this frame samples it.
+    pub fn record(&self, ctx: &RenderContext, command_buffer: vk::CommandBuffer) {
+        let device = &ctx.device;
+        let to_transfer = vk::ImageMemoryBarrier::default()
+            .image(self.image)
+            .old_layout(self.old_layout)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .subresource_range(COLOR_SUBRESOURCE_RANGE);
+        let to_shader = vk::ImageMemoryBarrier::default()
+            .image(self.image)
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ)
+            .subresource_range(COLOR_SUBRESOURCE_RANGE);
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                std::slice::from_ref(&to_transfer),
+            );
+            device.cmd_copy_buffer_to_image(
+                command_buffer,
+                ctx.buffer_manager.get_buffer(self.buffer).buffer,
+                self.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &self.regions,
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                std::slice::from_ref(&to_shader),
+            );
+        }
+    }
+}
+
+/// Create an image for sRGB texels with a size in pixels. The texels are converted to linear colors when they are sampled, like the colors of vertices.
+fn create_image(
+    ctx: &RenderContext,
+    [width, height]: [u32; 2],
+    layout: vk::DescriptorSetLayout,
+    sampler: vk::Sampler,
+) -> SampledImage {
+    let image = Attachment::new(
+        ctx,
+        vk::Extent2D { width, height },
+        vk::Format::R8G8B8A8_SRGB,
+        vk::SampleCountFlags::TYPE_1,
+        vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+    );
+    SampledImage::new(ctx, image, layout, sampler)
+}
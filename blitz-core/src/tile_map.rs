@@ -0,0 +1,544 @@
+use std::f64::consts::PI;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use dioxus_native_core::prelude::*;
+use epaint::{pos2, vec2, Color32, ColorImage, Mesh, Pos2, Rect, Rounding, Shape};
+use once_cell::sync::Lazy;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::atlas::{premultiplied, AtlasKey, AtlasRegion, ATLAS};
+use crate::events::EventData;
+use crate::gestures::{Gesture, GesturePhase};
+use crate::images::decode_image;
+use crate::plugin::{CustomElement, ElementPlugin, Painter, PointerPhase};
+
+/// The color of the map where its tiles aren't loaded yet
+const BACKGROUND: Color32 = Color32::from_rgb(0xe5, 0xe3, 0xdf);
+/// How many decoded tiles each map keeps in memory, so panning back doesn't load them again
+const MAX_CACHED_TILES: usize = 256;
+/// How many tiles all maps together draw from the texture atlas. Tiles share the atlas with glyphs and images, so a few slots are reused for the tiles in view instead of filling it
+const TILE_SLOTS: usize = 32;
+/// How many threads load the tiles of each map
+const LOADERS: usize = 4;
+/// How many levels up a tile that isn't loaded looks for a tile to scale up in its place
+const MAX_FALLBACK_LEVELS: u8 = 4;
+/// How many zoom levels a pixel of mouse wheel movement zooms
+const WHEEL_ZOOM: f64 = 1.0 / 240.0;
+
+/// A tile of a tile pyramid: the column `x` and row `y` of the tile at a zoom level, which has `2^zoom` columns and rows, counted from the north west corner like in the web mercator scheme of OpenStreetMap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileId {
+    pub zoom: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileId {
+    /// The tile one level up that covers this one.
+    fn parent(self) -> Option<Self> {
+        (self.zoom > 0).then(|| Self {
+            zoom: self.zoom - 1,
+            x: self.x / 2,
+            y: self.y / 2,
+        })
+    }
+}
+
+/// Where the tiles of a [`TileMap`] come from. Tiles are loaded on background threads, so the source can block, for example to read a file or to download the tile.
+pub trait TileSource: Send + Sync + 'static {
+    /// The encoded image of a tile, like a PNG or JPEG file, or `None` if there is no tile.
+    fn load(&self, tile: TileId) -> Option<Vec<u8>>;
+}
+
+impl<F: Fn(TileId) -> Option<Vec<u8>> + Send + Sync + 'static> TileSource for F {
+    fn load(&self, tile: TileId) -> Option<Vec<u8>> {
+        self(tile)
+    }
+}
+
+/// A slippy map that draws a tile pyramid, like the tiles of OpenStreetMap, which is panned by dragging it and zoomed with the mouse wheel, a pinch or a double tap. Register a clone with [`crate::Config::with_element_plugin`] and put an element with its tag in the document, sized by its styles:
+///
+/// ```no_run
+/// # use blitz_core::{Config, TileId, TileMap};
+/// let map = TileMap::new("city-map", |tile: TileId| {
+///     std::fs::read(format!("tiles/{}/{}/{}.png", tile.zoom, tile.x, tile.y)).ok()
+/// })
+/// .with_zoom_range(0, 17)
+/// .with_view(4.9, 52.37, 12.0);
+/// Config::new().with_element_plugin(map);
+/// ```
+///
+/// Only the tiles in view are loaded, on background threads and from the middle of the view out. While a tile loads, the tile a few levels up that covers it is scaled up in its place. Decoded tiles are cached, and the tiles in view are drawn from slots of the texture atlas that are reused as the map moves, so a map shows at most 32 tiles at their own resolution at once and the rest from the levels above.
+#[derive(Clone)]
+pub struct TileMap {
+    tag: Arc<str>,
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    source: Arc<dyn TileSource>,
+    /// Started when the map is drawn the first time
+    loader: Option<Loader>,
+    /// The size of a tile in pixels at whole zoom levels
+    tile_size: f64,
+    min_zoom: u8,
+    max_zoom: u8,
+    /// The point of the world in the middle of the map, from 0 to 1 from the west and the north edge of the projection
+    center: (f64, f64),
+    zoom: f64,
+    /// The content box the map was drawn in last, to find the point under a gesture
+    viewport: Rect,
+    tiles: FxHashMap<TileId, Tile>,
+    /// Counts the times the map was drawn, which tiles remember to find the ones that weren't drawn for the longest time
+    paints: u64,
+    /// The mouse and the center of the map when the mouse was pressed
+    drag: Option<(Pos2, (f64, f64))>,
+    /// The zoom when a pinch started
+    pinch: Option<f64>,
+    /// Set when the view changed since the map was drawn
+    changed: bool,
+}
+
+enum Tile {
+    Loading,
+    Missing,
+    Loaded {
+        image: Arc<ColorImage>,
+        last_used: u64,
+    },
+}
+
+/// What a loader thread did with a request
+enum Loaded {
+    Image(Arc<ColorImage>),
+    Missing,
+    /// The tile moved out of view before it was loaded, so it is requested again when it is back
+    Skipped,
+}
+
+/// The threads that load the tiles of a map. They stop when the map is dropped.
+struct Loader {
+    requests: Sender<TileId>,
+    /// The tiles in view
+    wanted: Arc<Mutex<FxHashSet<TileId>>>,
+    loaded: Arc<Mutex<Vec<(TileId, Loaded)>>>,
+}
+
+impl Loader {
+    fn spawn(source: Arc<dyn TileSource>) -> Self {
+        let (requests, receiver) = channel::<TileId>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let wanted = Arc::new(Mutex::new(FxHashSet::default()));
+        let loaded = Arc::new(Mutex::new(Vec::new()));
+        for _ in 0..LOADERS {
+            let (receiver, wanted, loaded, source) = (
+                receiver.clone(),
+                wanted.clone(),
+                loaded.clone(),
+                source.clone(),
+            );
+            std::thread::spawn(move || loop {
+                let Ok(tile) = receiver.lock().unwrap().recv() else {
+                    return;
+                };
+                let result = if !wanted.lock().unwrap().contains(&tile) {
+                    Loaded::Skipped
+                } else {
+                    match source.load(tile).map(|data| decode_image(&data)) {
+                        Some(Ok(image)) => Loaded::Image(Arc::new(premultiplied(&image))),
+                        Some(Err(err)) => {
+                            log::warn!("failed to decode the map tile {tile:?}: {err}");
+                            Loaded::Missing
+                        }
+                        None => Loaded::Missing,
+                    }
+                };
+                loaded.lock().unwrap().push((tile, result));
+            });
+        }
+        Self {
+            requests,
+            wanted,
+            loaded,
+        }
+    }
+}
+
+impl Inner {
+    /// The zoom level the tiles in view are drawn from, the whole level closest to the zoom
+    fn level(&self) -> u8 {
+        (self.zoom.round().max(0.0) as u8).clamp(self.min_zoom, self.max_zoom)
+    }
+
+    /// The size of the world in pixels at the zoom
+    fn world_size(&self) -> f64 {
+        self.tile_size * 2f64.powf(self.zoom)
+    }
+
+    fn set_center(&mut self, (x, y): (f64, f64)) {
+        // The world repeats to the east and west, but not to the north and south
+        self.center = (x.rem_euclid(1.0), y.clamp(0.0, 1.0));
+        self.changed = true;
+    }
+
+    /// Zoom to a level, keeping the point of the world under a point in the content box where it is.
+    fn zoom_around(&mut self, point: Pos2, zoom: f64) {
+        let zoom = zoom.clamp(self.min_zoom as f64, self.max_zoom as f64);
+        let offset = (
+            (point.x - self.viewport.width() / 2.0) as f64,
+            (point.y - self.viewport.height() / 2.0) as f64,
+        );
+        let before = self.world_size();
+        let anchor = (
+            self.center.0 + offset.0 / before,
+            self.center.1 + offset.1 / before,
+        );
+        self.zoom = zoom;
+        let after = self.world_size();
+        self.set_center((anchor.0 - offset.0 / after, anchor.1 - offset.1 / after));
+    }
+
+    /// Take the tiles the loaders finished, and forget the decoded tiles that weren't drawn for the longest time once there are too many.
+    fn receive(&mut self) {
+        let Some(loader) = &self.loader else {
+            return;
+        };
+        let loaded = std::mem::take(&mut *loader.loaded.lock().unwrap());
+        for (tile, result) in loaded {
+            match result {
+                Loaded::Image(image) => {
+                    let last_used = self.paints;
+                    self.tiles.insert(tile, Tile::Loaded { image, last_used });
+                }
+                Loaded::Missing => {
+                    self.tiles.insert(tile, Tile::Missing);
+                }
+                Loaded::Skipped => {
+                    self.tiles.remove(&tile);
+                }
+            }
+        }
+        if self.tiles.len() > MAX_CACHED_TILES {
+            let mut loaded: Vec<(u64, TileId)> = self
+                .tiles
+                .iter()
+                .filter_map(|(id, tile)| match tile {
+                    Tile::Loaded { last_used, .. } => Some((*last_used, *id)),
+                    _ => None,
+                })
+                .collect();
+            loaded.sort_unstable_by_key(|(last_used, _)| *last_used);
+            let excess = self.tiles.len() - MAX_CACHED_TILES;
+            for (_, id) in loaded.into_iter().take(excess) {
+                self.tiles.remove(&id);
+            }
+        }
+    }
+
+    /// Ask the loaders for the tiles in view that aren't loaded or loading, in order.
+    fn request(&mut self, visible: &[TileId]) {
+        let loader = self
+            .loader
+            .get_or_insert_with(|| Loader::spawn(self.source.clone()));
+        *loader.wanted.lock().unwrap() = visible.iter().copied().collect();
+        for tile in visible {
+            if !self.tiles.contains_key(tile) {
+                self.tiles.insert(*tile, Tile::Loading);
+                let _ = loader.requests.send(*tile);
+            }
+        }
+    }
+
+    /// The loaded tile to draw in place of a tile, which is the tile itself or a tile a few levels up that covers it, with the part of it that covers the tile from 0 to 1.
+    fn drawable(&mut self, id: TileId) -> Option<(TileId, Arc<ColorImage>, Rect)> {
+        let mut current = id;
+        for levels in 0..=MAX_FALLBACK_LEVELS {
+            if let Some(Tile::Loaded { image, last_used }) = self.tiles.get_mut(&current) {
+                *last_used = self.paints;
+                let scale = 1.0 / (1u32 << levels) as f32;
+                let mask = (1u32 << levels) - 1;
+                let part = Rect::from_min_size(
+                    pos2((id.x & mask) as f32 * scale, (id.y & mask) as f32 * scale),
+                    vec2(scale, scale),
+                );
+                return Some((current, image.clone(), part));
+            }
+            current = current.parent()?;
+        }
+        None
+    }
+}
+
+/// The tile each slot of the atlas holds, by the map it belongs to, and when it was last drawn
+static SLOTS: Lazy<Mutex<Vec<Option<(usize, TileId, Instant)>>>> =
+    Lazy::new(|| Mutex::new(vec![None; TILE_SLOTS]));
+
+/// The region of the atlas a tile of a map is drawn from, putting it in the slot that was drawn from the longest time ago if it isn't in one. Slots that were drawn from since `painting` are in use for the frame, so None is returned if every slot is.
+fn tile_region(
+    map: usize,
+    id: TileId,
+    image: &ColorImage,
+    painting: Instant,
+) -> Option<AtlasRegion> {
+    let [width, height] = image.size;
+    let key = |slot: usize| AtlasKey::Tile {
+        slot: slot as u16,
+        width: width as u16,
+        height: height as u16,
+    };
+    let mut slots = SLOTS.lock().unwrap();
+    let now = Instant::now();
+    if let Some(slot) = slots
+        .iter()
+        .position(|slot| slot.is_some_and(|(owner, tile, _)| owner == map && tile == id))
+    {
+        slots[slot] = Some((map, id, now));
+        // A slot can only lose its region if the tile in it had another size
+        if let Some(region) = ATLAS.lock().unwrap().region(key(slot)) {
+            return Some(region);
+        }
+    }
+    let slot = slots.iter().position(Option::is_none).or_else(|| {
+        slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.is_some_and(|(_, _, used)| used < painting))
+            .min_by_key(|(_, slot)| slot.map(|(_, _, used)| used))
+            .map(|(index, _)| index)
+    })?;
+    slots[slot] = Some((map, id, now));
+    ATLAS.lock().unwrap().replace(key(slot), image)
+}
+
+/// The point of the world from 0 to 1 of a longitude and latitude in degrees, in the web mercator projection.
+fn project(longitude: f64, latitude: f64) -> (f64, f64) {
+    let latitude = latitude.clamp(-85.0511, 85.0511).to_radians();
+    (
+        (longitude + 180.0) / 360.0,
+        (1.0 - latitude.tan().asinh() / PI) / 2.0,
+    )
+}
+
+fn unproject((x, y): (f64, f64)) -> (f64, f64) {
+    (
+        x * 360.0 - 180.0,
+        (PI * (1.0 - 2.0 * y)).sinh().atan().to_degrees(),
+    )
+}
+
+impl TileMap {
+    /// A map for the elements with a tag with tiles from a source, showing the whole world.
+    pub fn new(tag: &str, source: impl TileSource) -> Self {
+        Self {
+            tag: tag.into(),
+            inner: Arc::new(Mutex::new(Inner {
+                source: Arc::new(source),
+                loader: None,
+                tile_size: 256.0,
+                min_zoom: 0,
+                max_zoom: 19,
+                center: (0.5, 0.5),
+                zoom: 0.0,
+                viewport: Rect::NOTHING,
+                tiles: FxHashMap::default(),
+                paints: 0,
+                drag: None,
+                pinch: None,
+                changed: false,
+            })),
+        }
+    }
+
+    /// Set the size of the tiles in pixels at whole zoom levels. Tiles are 256 pixels by default.
+    pub fn with_tile_size(self, tile_size: f32) -> Self {
+        self.inner.lock().unwrap().tile_size = tile_size.max(1.0) as f64;
+        self
+    }
+
+    /// Set the zoom levels the source has tiles for. The map can't be zoomed out or in further.
+    pub fn with_zoom_range(self, min_zoom: u8, max_zoom: u8) -> Self {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.min_zoom = min_zoom.min(max_zoom);
+            inner.max_zoom = max_zoom.max(min_zoom);
+            inner.zoom = inner
+                .zoom
+                .clamp(inner.min_zoom as f64, inner.max_zoom as f64);
+        }
+        self
+    }
+
+    pub fn with_view(self, longitude: f64, latitude: f64, zoom: f64) -> Self {
+        self.set_view(longitude, latitude, zoom);
+        self
+    }
+
+    /// Move the map so a longitude and latitude in degrees is in the middle of it, at a zoom level.
+    pub fn set_view(&self, longitude: f64, latitude: f64, zoom: f64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.set_center(project(longitude, latitude));
+        inner.zoom = zoom.clamp(inner.min_zoom as f64, inner.max_zoom as f64);
+    }
+
+    /// The longitude and latitude in the middle of the map and its zoom level, as the user last moved it.
+    pub fn view(&self) -> (f64, f64, f64) {
+        let inner = self.inner.lock().unwrap();
+        let (longitude, latitude) = unproject(inner.center);
+        (longitude, latitude, inner.zoom)
+    }
+}
+
+impl ElementPlugin for TileMap {
+    fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    fn paint(&self, _element: &CustomElement, content_box: Rect, painter: &mut Painter) {
+        let painting = Instant::now();
+        let map = Arc::as_ptr(&self.inner) as usize;
+        let mut inner = self.inner.lock().unwrap();
+        inner.viewport = content_box;
+        inner.changed = false;
+        inner.paints += 1;
+        inner.receive();
+        painter.add(Shape::rect_filled(
+            content_box,
+            Rounding::none(),
+            BACKGROUND,
+        ));
+
+        let level = inner.level();
+        let count = 1u32 << level;
+        let tile_size = inner.world_size() / count as f64;
+        // The point of the world at the top left corner of the content box, in pixels
+        let origin = (
+            inner.center.0 * inner.world_size() - content_box.width() as f64 / 2.0,
+            inner.center.1 * inner.world_size() - content_box.height() as f64 / 2.0,
+        );
+        let columns = (origin.0 / tile_size).floor() as i64
+            ..=((origin.0 + content_box.width() as f64) / tile_size).floor() as i64;
+        let rows = (origin.1 / tile_size).floor().max(0.0) as i64
+            ..=((origin.1 + content_box.height() as f64) / tile_size)
+                .floor()
+                .min(count as f64 - 1.0) as i64;
+        let mut visible = Vec::new();
+        for row in rows {
+            for column in columns.clone() {
+                let id = TileId {
+                    zoom: level,
+                    // The world repeats to the east and west
+                    x: column.rem_euclid(count as i64) as u32,
+                    y: row as u32,
+                };
+                let rect = Rect::from_min_size(
+                    content_box.min
+                        + vec2(
+                            (column as f64 * tile_size - origin.0) as f32,
+                            (row as f64 * tile_size - origin.1) as f32,
+                        ),
+                    vec2(tile_size as f32, tile_size as f32),
+                );
+                visible.push((id, rect));
+            }
+        }
+        // The tiles in the middle are loaded and given slots first
+        let center = content_box.center();
+        visible.sort_by(|(_, a), (_, b)| {
+            a.center()
+                .distance_sq(center)
+                .total_cmp(&b.center().distance_sq(center))
+        });
+        let ids: Vec<TileId> = visible.iter().map(|(id, _)| *id).collect();
+        inner.request(&ids);
+
+        let mut mesh = Mesh::default();
+        for (id, rect) in visible {
+            let Some((drawn, image, part)) = inner.drawable(id) else {
+                continue;
+            };
+            let Some(region) = tile_region(map, drawn, &image, painting) else {
+                continue;
+            };
+            let uv = Rect::from_min_max(
+                region.uv.lerp_inside(part.min.to_vec2()),
+                region.uv.lerp_inside(part.max.to_vec2()),
+            );
+            mesh.add_rect_with_uv(rect, uv, Color32::WHITE);
+        }
+        if !mesh.is_empty() {
+            painter.clipped(content_box, |painter| painter.add(Shape::mesh(mesh)));
+        }
+    }
+
+    fn scroll(&self, _element: &CustomElement, point: Pos2, delta: epaint::Vec2) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let zoom = inner.zoom + delta.y as f64 * WHEEL_ZOOM;
+        inner.zoom_around(point, zoom);
+        true
+    }
+
+    fn pointer(&self, _element: &CustomElement, phase: PointerPhase, point: Pos2) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match phase {
+            PointerPhase::Down => {
+                inner.drag = Some((point, inner.center));
+                false
+            }
+            PointerPhase::Move => {
+                let Some((start, center)) = inner.drag else {
+                    return false;
+                };
+                let world_size = inner.world_size();
+                inner.set_center((
+                    center.0 - (point.x - start.x) as f64 / world_size,
+                    center.1 - (point.y - start.y) as f64 / world_size,
+                ));
+                true
+            }
+            PointerPhase::Up => {
+                inner.drag = None;
+                false
+            }
+        }
+    }
+
+    fn animating(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        // The map is drawn every frame while tiles load, so they are shown as soon as they are decoded
+        inner.changed
+            || inner
+                .tiles
+                .values()
+                .any(|tile| matches!(tile, Tile::Loading))
+    }
+
+    fn handles_gestures(&self) -> bool {
+        true
+    }
+
+    fn handle_event(&self, _node: NodeMut, _event: &str, data: &EventData) -> bool {
+        let EventData::Gesture(gesture) = data else {
+            return false;
+        };
+        let mut inner = self.inner.lock().unwrap();
+        let point = pos2(gesture.client_x as f32, gesture.client_y as f32) - inner.viewport.min;
+        let point = point.to_pos2();
+        match (gesture.gesture, gesture.phase) {
+            (Gesture::Pinch { .. }, GesturePhase::Start) => inner.pinch = Some(inner.zoom),
+            (Gesture::Pinch { scale }, GesturePhase::Change) => {
+                if let Some(zoom) = inner.pinch {
+                    inner.zoom_around(point, zoom + scale.max(f64::EPSILON).log2());
+                }
+            }
+            (Gesture::Pinch { .. }, GesturePhase::End) => inner.pinch = None,
+            (Gesture::DoubleTap, _) => {
+                let zoom = inner.zoom.round() + 1.0;
+                inner.zoom_around(point, zoom);
+            }
+            _ => return false,
+        }
+        true
+    }
+}
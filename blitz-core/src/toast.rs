@@ -0,0 +1,427 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use epaint::{pos2, vec2, Color32, Pos2, Rect, Rounding, Shape, Stroke};
+use tao::event_loop::EventLoopProxy;
+
+use crate::scene::Scene;
+use crate::style::TextStyle;
+use crate::text::{shape, shape_fitted, text_mesh};
+use crate::Redraw;
+
+const WIDTH: f32 = 360.0;
+const FONT_SIZE: f32 = 14.0;
+/// The space between the text and the edge of a toast
+const PADDING: f32 = 12.0;
+/// The space between the toasts and the corner of the window
+const MARGIN: f32 = 16.0;
+/// The space between stacked toasts
+const GAP: f32 = 8.0;
+/// The size of the button that dismisses a toast
+const CLOSE_SIZE: f32 = 16.0;
+/// How long a toast takes to slide in, and to fade out when it is dismissed
+const ENTER_DURATION: Duration = Duration::from_millis(200);
+const EXIT_DURATION: Duration = Duration::from_millis(200);
+/// How long a toast is shown without [`Toast::with_duration`]
+const DEFAULT_DURATION: Duration = Duration::from_secs(4);
+const BACKGROUND: Color32 = Color32::from_rgb(0x1f, 0x29, 0x37);
+const TEXT_COLOR: Color32 = Color32::from_rgb(0xf9, 0xfa, 0xfb);
+const ACTION_COLOR: Color32 = Color32::from_rgb(0x93, 0xc5, 0xfd);
+const CLOSE_COLOR: Color32 = Color32::from_rgb(0x9c, 0xa3, 0xaf);
+
+type ActionHook = Arc<dyn Fn() + Send + Sync>;
+
+/// A short message shown by [`Toasts`], with an optional button.
+#[derive(Clone)]
+pub struct Toast {
+    message: String,
+    /// How long the toast is shown, or `None` until it is dismissed
+    duration: Option<Duration>,
+    action: Option<(String, ActionHook)>,
+}
+
+impl Toast {
+    pub fn new(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            duration: Some(DEFAULT_DURATION),
+            action: None,
+        }
+    }
+
+    /// Show the toast for a time after it slid in, instead of four seconds.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Show the toast until the user or the application dismisses it.
+    pub fn persistent(mut self) -> Self {
+        self.duration = None;
+        self
+    }
+
+    /// Add a button to the toast, like "Undo", that calls a hook and dismisses the toast when it is clicked.
+    pub fn with_action(mut self, label: &str, action: impl Fn() + Send + Sync + 'static) -> Self {
+        self.action = Some((label.to_string(), Arc::new(action)));
+        self
+    }
+}
+
+/// Identifies a toast that was shown, to dismiss it early.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ToastId(u64);
+
+/// Transient messages in the bottom right corner of the window, drawn over the document and everything else except the debug HUD.
+///
+/// Pass a clone to [`crate::Config::with_toasts`] and keep another one in the application, for example in a Dioxus context, so any component can show toasts. New toasts slide in at the bottom and push the toasts that are shown up; when a toast times out or is dismissed, it fades out and the toasts above it slide down into its place. Toasts that don't fit the stack wait in a queue until a toast leaves.
+///
+/// ```no_run
+/// # use blitz_core::{Config, Toast, Toasts};
+/// let toasts = Toasts::new();
+/// Config::new().with_toasts(toasts.clone());
+/// toasts.show(Toast::new("Message archived").with_action("Undo", || {
+///     // Move the message back
+/// }));
+/// ```
+#[derive(Clone, Default)]
+pub struct Toasts {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    /// The toasts that wait for room in the stack
+    queue: VecDeque<(ToastId, Toast)>,
+    /// The toasts in the stack from the oldest to the newest, including the ones that fade out
+    shown: Vec<Shown>,
+    next_id: u64,
+    /// The most toasts that are in the stack at a time
+    max_visible: usize,
+    /// Set when toasts were shown or dismissed since the last frame
+    changed: bool,
+    /// The area the toasts covered in the last frame, which is drawn again when they move
+    painted: Rect,
+    /// Wakes up the window when a toast is shown, set once the window is opened
+    proxy: Option<EventLoopProxy<Redraw>>,
+}
+
+impl Default for Inner {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            shown: Vec::new(),
+            next_id: 0,
+            max_visible: 3,
+            changed: false,
+            painted: Rect::NOTHING,
+            proxy: None,
+        }
+    }
+}
+
+/// A toast in the stack.
+struct Shown {
+    id: ToastId,
+    toast: Toast,
+    /// When it started to slide in
+    shown_at: Instant,
+    /// When it started to fade out
+    dismissed_at: Option<Instant>,
+    /// Where the toast and its buttons were drawn in the last frame, in window coordinates
+    rect: Rect,
+    action_rect: Option<Rect>,
+    close_rect: Rect,
+}
+
+impl Shown {
+    /// How much of the toast is in the stack, from 0 before it slides in and after it faded out to 1 while it is shown.
+    fn presence(&self, now: Instant) -> f32 {
+        let progress = |since: Instant, duration: Duration| {
+            (now.saturating_duration_since(since).as_secs_f32() / duration.as_secs_f32()).min(1.0)
+        };
+        let entered = progress(self.shown_at, ENTER_DURATION);
+        let exited = self
+            .dismissed_at
+            .map_or(0.0, |dismissed_at| progress(dismissed_at, EXIT_DURATION));
+        ease_out(entered) * (1.0 - exited)
+    }
+
+    /// When the toast times out.
+    fn expires_at(&self) -> Option<Instant> {
+        let duration = self.toast.duration?;
+        self.dismissed_at
+            .is_none()
+            .then(|| self.shown_at + ENTER_DURATION + duration)
+    }
+
+    fn animating(&self, now: Instant) -> bool {
+        self.dismissed_at.is_some() || now < self.shown_at + ENTER_DURATION
+    }
+}
+
+fn ease_out(t: f32) -> f32 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+impl Inner {
+    /// Dismiss the toasts that timed out, remove the ones that faded out and move waiting toasts into the stack.
+    fn advance(&mut self, now: Instant) {
+        for shown in &mut self.shown {
+            if shown
+                .expires_at()
+                .is_some_and(|expires_at| expires_at <= now)
+            {
+                shown.dismissed_at = Some(now);
+            }
+        }
+        self.shown.retain(|shown| {
+            shown
+                .dismissed_at
+                .map_or(true, |dismissed_at| now < dismissed_at + EXIT_DURATION)
+        });
+        while self
+            .shown
+            .iter()
+            .filter(|shown| shown.dismissed_at.is_none())
+            .count()
+            < self.max_visible
+        {
+            let Some((id, toast)) = self.queue.pop_front() else {
+                break;
+            };
+            self.shown.push(Shown {
+                id,
+                toast,
+                shown_at: now,
+                dismissed_at: None,
+                rect: Rect::NOTHING,
+                action_rect: None,
+                close_rect: Rect::NOTHING,
+            });
+        }
+    }
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many toasts are stacked at a time. The default is 3.
+    pub fn with_max_visible(self, max_visible: usize) -> Self {
+        self.inner.lock().unwrap().max_visible = max_visible.max(1);
+        self
+    }
+
+    /// Show a toast, or queue it if the stack is full.
+    pub fn show(&self, toast: Toast) -> ToastId {
+        let mut inner = self.inner.lock().unwrap();
+        let id = ToastId(inner.next_id);
+        inner.next_id += 1;
+        inner.queue.push_back((id, toast));
+        inner.changed = true;
+        drop(inner);
+        self.wake();
+        id
+    }
+
+    /// Fade out a toast, or remove it from the queue if it is waiting.
+    pub fn dismiss(&self, id: ToastId) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.queue.retain(|(queued, _)| *queued != id);
+        let now = Instant::now();
+        if let Some(shown) = inner.shown.iter_mut().find(|shown| shown.id == id) {
+            shown.dismissed_at.get_or_insert(now);
+        }
+        inner.changed = true;
+        drop(inner);
+        self.wake();
+    }
+
+    /// Fade out every toast and empty the queue.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.queue.clear();
+        let now = Instant::now();
+        for shown in &mut inner.shown {
+            shown.dismissed_at.get_or_insert(now);
+        }
+        inner.changed = true;
+        drop(inner);
+        self.wake();
+    }
+
+    fn wake(&self) {
+        if let Some(proxy) = &self.inner.lock().unwrap().proxy {
+            let _ = proxy.send_event(Redraw);
+        }
+    }
+
+    pub(crate) fn attach(&self, proxy: EventLoopProxy<Redraw>) {
+        self.inner.lock().unwrap().proxy = Some(proxy);
+    }
+
+    /// When the toasts change next without an event: now while they move or changed, otherwise when the next one times out.
+    pub(crate) fn next_change(&self, now: Instant) -> Option<Instant> {
+        let inner = self.inner.lock().unwrap();
+        if inner.changed || inner.shown.iter().any(|shown| shown.animating(now)) {
+            return Some(now);
+        }
+        inner.shown.iter().filter_map(Shown::expires_at).min()
+    }
+
+    /// Press the primary button at a point in window coordinates. Clicking the button of a toast calls its hook and clicking the close button dismisses it. Returns true if the point is on a toast, so the document doesn't get the press.
+    pub(crate) fn press(&self, point: Pos2) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        let Some(shown) = inner
+            .shown
+            .iter_mut()
+            .rev()
+            .find(|shown| shown.dismissed_at.is_none() && shown.rect.contains(point))
+        else {
+            return false;
+        };
+        let on_action = shown.action_rect.is_some_and(|rect| rect.contains(point));
+        if !on_action && !shown.close_rect.contains(point) {
+            return true;
+        }
+        shown.dismissed_at = Some(now);
+        let action = on_action
+            .then(|| {
+                shown
+                    .toast
+                    .action
+                    .as_ref()
+                    .map(|(_, action)| action.clone())
+            })
+            .flatten();
+        inner.changed = true;
+        // The hook can show another toast
+        drop(inner);
+        if let Some(action) = action {
+            action();
+        }
+        true
+    }
+
+    /// Paint the toasts over everything else in the scene. Returns the area of the window that changed.
+    pub(crate) fn paint(&self, scene: &mut Scene, window: Rect, now: Instant) -> Rect {
+        let mut inner = self.inner.lock().unwrap();
+        inner.changed = false;
+        inner.advance(now);
+        let previous = std::mem::replace(&mut inner.painted, Rect::NOTHING);
+        if inner.shown.is_empty() {
+            return previous;
+        }
+        // The toasts get a layer of their own, so the layers of the document keep their meshes
+        scene.mark();
+        let right = window.max.x - MARGIN;
+        let mut bottom = window.max.y - MARGIN;
+        let mut painted = Rect::NOTHING;
+        // The newest toast is at the bottom and pushes the older ones up as it slides in
+        for shown in inner.shown.iter_mut().rev() {
+            let presence = shown.presence(now);
+            let rect = paint_toast(scene, window, shown, right, bottom, presence);
+            painted = painted.union(rect);
+            bottom -= (rect.height() + GAP) * presence;
+        }
+        inner.painted = painted.intersect(window);
+        previous.union(inner.painted)
+    }
+}
+
+/// Paint a toast with its bottom right corner at a point, and remember where its buttons are. It slides in from the right while it enters and fades out while it leaves.
+fn paint_toast(
+    scene: &mut Scene,
+    window: Rect,
+    shown: &mut Shown,
+    right: f32,
+    bottom: f32,
+    presence: f32,
+) -> Rect {
+    let width = WIDTH.min(window.width() - MARGIN * 2.0).max(0.0);
+    let style = TextStyle {
+        font_size: FONT_SIZE,
+        ..TextStyle::default()
+    };
+    let action = shown
+        .toast
+        .action
+        .as_ref()
+        .map(|(label, _)| shape(label, &style));
+    let action_width = action.as_ref().map_or(0.0, |action| action.width + PADDING);
+    let text_width = (width - PADDING * 3.0 - CLOSE_SIZE - action_width).max(0.0);
+    // Messages that don't fit are cut off with an ellipsis
+    let message = shape_fitted(
+        &shown.toast.message,
+        &TextStyle {
+            ellipsis: true,
+            ..style
+        },
+        Some(text_width),
+    );
+    let height = message.height() + PADDING * 2.0;
+    let (slide, opacity) = match shown.dismissed_at {
+        Some(_) => (0.0, presence),
+        None => ((1.0 - presence) * width / 2.0, 1.0),
+    };
+    let rect = Rect::from_min_max(
+        pos2(right - width + slide, bottom - height),
+        pos2(right + slide, bottom),
+    );
+    let fade = |color: Color32| color.gamma_multiply(opacity);
+
+    scene.push(
+        window,
+        Shape::rect_filled(rect, Rounding::same(6.0), fade(BACKGROUND)),
+    );
+    let baseline = rect.min.y + PADDING + message.ascent;
+    let mesh = text_mesh(
+        &message,
+        pos2(rect.min.x + PADDING, baseline),
+        fade(TEXT_COLOR),
+        1.0,
+        false,
+    );
+    if !mesh.is_empty() {
+        scene.push(window, Shape::mesh(mesh));
+    }
+    let close_rect = Rect::from_center_size(
+        pos2(rect.max.x - PADDING - CLOSE_SIZE / 2.0, rect.center().y),
+        vec2(CLOSE_SIZE, CLOSE_SIZE),
+    );
+    shown.action_rect = action.map(|action| {
+        let left = close_rect.min.x - PADDING - action.width;
+        let mesh = text_mesh(
+            &action,
+            pos2(left, baseline),
+            fade(ACTION_COLOR),
+            1.0,
+            false,
+        );
+        if !mesh.is_empty() {
+            scene.push(window, Shape::mesh(mesh));
+        }
+        Rect::from_min_max(
+            pos2(left, rect.min.y),
+            pos2(left + action.width, rect.max.y),
+        )
+        .expand2(vec2(PADDING / 2.0, 0.0))
+    });
+    // The close button is a cross
+    let cross = close_rect.shrink(4.0);
+    let stroke = Stroke::new(1.5, fade(CLOSE_COLOR));
+    scene.push(
+        window,
+        Shape::line_segment([cross.left_top(), cross.right_bottom()], stroke),
+    );
+    scene.push(
+        window,
+        Shape::line_segment([cross.right_top(), cross.left_bottom()], stroke),
+    );
+    shown.rect = rect;
+    shown.close_rect = close_rect.expand(4.0);
+    rect
+}
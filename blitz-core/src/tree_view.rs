@@ -0,0 +1,739 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use dioxus_html::input_data::keyboard_types::{Key, Modifiers};
+use dioxus_native_core::prelude::*;
+use epaint::{pos2, vec2, Color32, Pos2, Rect, Rounding, Shape, Stroke, Vec2};
+use taffy::prelude::{AvailableSpace, Size};
+
+use crate::plugin::{CustomElement, ElementPlugin, Painter, PointerPhase};
+use crate::EventData;
+
+const FONT_SIZE: f32 = 13.0;
+/// How long the children of an item take to slide in or out
+const EXPAND_DURATION: Duration = Duration::from_millis(150);
+const SELECTED_BACKGROUND: Color32 = Color32::from_rgb(0xdb, 0xea, 0xfe);
+const ACTIVE_OUTLINE: Color32 = Color32::from_rgb(0x3b, 0x82, 0xf6);
+const GUIDE_COLOR: Color32 = Color32::from_rgb(0xe5, 0xe7, 0xeb);
+const ARROW_COLOR: Color32 = Color32::from_rgb(0x6b, 0x72, 0x80);
+const TEXT_COLOR: Color32 = Color32::from_rgb(0x11, 0x18, 0x27);
+/// The attribute that is changed when the tree changes from an event, so the document draws it again
+const REVISION_ATTRIBUTE: &str = "tree-revision";
+
+/// The indices of an item and its ancestors from the top level of a [`TreeView`] down, so `[2, 0]` is the first child of the third item.
+pub type TreePath = Vec<usize>;
+
+/// An item of a [`TreeView`] with the items nested in it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TreeItem {
+    pub label: String,
+    pub children: Vec<TreeItem>,
+    /// True if the children are shown
+    pub expanded: bool,
+}
+
+impl TreeItem {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_children(mut self, children: Vec<TreeItem>) -> Self {
+        self.children = children;
+        self
+    }
+
+    pub fn with_expanded(mut self, expanded: bool) -> Self {
+        self.expanded = expanded;
+        self
+    }
+}
+
+type SelectHook = Arc<dyn Fn(&[TreePath]) + Send + Sync>;
+type ToggleHook = Arc<dyn Fn(&TreePath, bool) + Send + Sync>;
+type KeyOutcome = (bool, bool, Option<(ToggleHook, TreePath, bool)>);
+
+/// A tree of items that expand and collapse, drawn by a plugin instead of elements: indent guides, arrows that turn as the children of an item slide in and out, navigation with the arrow keys and selecting more than one item with Shift and Ctrl.
+///
+/// One item of the tree is active and moves with the keys, while the element keeps the focus of the document, so the tree is a single stop in the tab order. Only the rows in view are drawn, so a tree with thousands of expanded items scrolls as fast as a short one. Register a clone with [`crate::Config::with_element_plugin`] and put an element with its tag and a `tabindex` in the document, sized by its styles:
+///
+/// ```no_run
+/// # use blitz_core::{Config, TreeItem, TreeView};
+/// let items = vec![TreeItem::new("src")
+///     .with_expanded(true)
+///     .with_children(vec![TreeItem::new("lib.rs"), TreeItem::new("main.rs")])];
+/// let tree = TreeView::new("files-tree").with_items(items);
+/// tree.on_select(|selected| {
+///     // Open the selected files
+/// });
+/// Config::new().with_element_plugin(tree);
+/// ```
+///
+/// The keys follow the tree views of desktop platforms:
+///
+/// - Up and Down move to the previous and next item and select it, Home and End to the first and last one
+/// - Right expands the active item, or moves to its first child if it is expanded
+/// - Left collapses the active item, or moves to its parent if it is collapsed
+/// - Enter expands or collapses the active item
+/// - With Shift held, moving selects the items from where the selection started, and with Ctrl held it moves without selecting; Ctrl+Space adds or removes the active item
+///
+/// Clicks select an item, with Ctrl to add or remove it and with Shift to select a range, and clicking an arrow expands or collapses its item.
+#[derive(Clone)]
+pub struct TreeView {
+    tag: Arc<str>,
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    items: Vec<TreeItem>,
+    row_height: f32,
+    /// How far each level of the tree is indented
+    indent: f32,
+    /// The item that moves with the arrow keys
+    active: Option<TreePath>,
+    selected: Vec<TreePath>,
+    /// The item a range selected with Shift starts at
+    anchor: Option<TreePath>,
+    on_select: Option<SelectHook>,
+    on_toggle: Option<ToggleHook>,
+    /// How far the rows are scrolled down
+    scroll: f32,
+    /// The size of the content box when the tree was drawn last, which limits scrolling
+    viewport: Vec2,
+    /// The item whose children slide in or out, when they started to and true if they slide in
+    animation: Option<(TreePath, Instant, bool)>,
+    /// The row the primary button was pressed on, and true if it was pressed on its arrow
+    pressed: Option<(TreePath, bool)>,
+    /// The row that was clicked, which is selected with the modifiers of the click event
+    clicked: Option<TreePath>,
+    revision: u64,
+}
+
+/// A row of the tree as it is drawn.
+struct Row {
+    path: TreePath,
+    depth: usize,
+    has_children: bool,
+    /// How far the arrow is turned from pointing right at 0 to pointing down at 1
+    turn: f32,
+    /// The top of the row in the content of the tree
+    top: f32,
+    /// True for the rows of the item whose children slide in or out
+    animated: bool,
+}
+
+/// The rows of the tree at one point in time.
+struct TreeLayout {
+    rows: Vec<Row>,
+    /// The part of the content the sliding rows are shown in
+    reveal: Option<(f32, f32)>,
+    height: f32,
+}
+
+impl TreeLayout {
+    /// The row under a y position in the content of the tree.
+    fn row_at(&self, y: f32, row_height: f32) -> Option<&Row> {
+        self.rows.iter().find(|row| {
+            let in_reveal = !row.animated
+                || self
+                    .reveal
+                    .is_some_and(|(top, bottom)| y >= top && y < bottom);
+            y >= row.top && y < row.top + row_height && in_reveal
+        })
+    }
+}
+
+/// Add the rows of items and the children of the expanded ones, and of the one that collapses while it animates.
+fn flatten(
+    items: &[TreeItem],
+    path: &mut TreePath,
+    collapsing: Option<&TreePath>,
+    rows: &mut Vec<(TreePath, usize, bool, bool)>,
+) {
+    for (index, item) in items.iter().enumerate() {
+        path.push(index);
+        rows.push((
+            path.clone(),
+            path.len() - 1,
+            !item.children.is_empty(),
+            item.expanded,
+        ));
+        if item.expanded || collapsing == Some(&*path) {
+            flatten(&item.children, path, collapsing, rows);
+        }
+        path.pop();
+    }
+}
+
+fn ease_out(t: f32) -> f32 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+impl Inner {
+    fn item(&self, path: &[usize]) -> Option<&TreeItem> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.items.get(*first)?, |item, index| {
+                item.children.get(*index)
+            })
+    }
+
+    fn item_mut(&mut self, path: &[usize]) -> Option<&mut TreeItem> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.items.get_mut(*first)?, |item, index| {
+                item.children.get_mut(*index)
+            })
+    }
+
+    /// The items that can be navigated to, in the order they are shown. The children of an item that collapses are left out while they slide out.
+    fn visible(&self) -> Vec<TreePath> {
+        let mut rows = Vec::new();
+        flatten(&self.items, &mut Vec::new(), None, &mut rows);
+        rows.into_iter().map(|(path, ..)| path).collect()
+    }
+
+    fn layout(&self, now: Instant) -> TreeLayout {
+        // How much of the children of the animated item are shown
+        let animation = self.animation.as_ref().map(|(path, start, expanding)| {
+            let t = ease_out(
+                (now.duration_since(*start).as_secs_f32() / EXPAND_DURATION.as_secs_f32()).min(1.0),
+            );
+            (path, if *expanding { t } else { 1.0 - t })
+        });
+        let mut flat = Vec::new();
+        flatten(
+            &self.items,
+            &mut Vec::new(),
+            animation.map(|(path, _)| path),
+            &mut flat,
+        );
+        let mut block = None;
+        if let Some((animated, _)) = animation {
+            if let Some(start) = flat.iter().position(|(path, ..)| path == animated) {
+                let end = flat[start + 1..]
+                    .iter()
+                    .position(|(path, ..)| !path.starts_with(animated))
+                    .map_or(flat.len(), |offset| start + 1 + offset);
+                block = Some((start + 1, end));
+            }
+        }
+        let row_height = self.row_height;
+        let (reveal, hidden) = match (block, animation) {
+            (Some((start, end)), Some((_, shown))) => {
+                let top = start as f32 * row_height;
+                let height = (end - start) as f32 * row_height;
+                (Some((top, top + height * shown)), height * (1.0 - shown))
+            }
+            _ => (None, 0.0),
+        };
+        let rows = flat
+            .into_iter()
+            .enumerate()
+            .map(|(index, (path, depth, has_children, expanded))| {
+                let in_block = block.is_some_and(|(start, end)| (start..end).contains(&index));
+                let after_block = block.is_some_and(|(_, end)| index >= end);
+                let turn = match animation {
+                    Some((animated, shown)) if *animated == path => shown,
+                    _ => expanded as u8 as f32,
+                };
+                Row {
+                    path,
+                    depth,
+                    has_children,
+                    turn,
+                    top: index as f32 * row_height - if after_block { hidden } else { 0.0 },
+                    animated: in_block,
+                }
+            })
+            .collect::<Vec<_>>();
+        let height = rows.len() as f32 * row_height - hidden;
+        TreeLayout {
+            rows,
+            reveal,
+            height,
+        }
+    }
+
+    fn clamp_scroll(&mut self, content_height: f32) {
+        let max = (content_height - self.viewport.y).max(0.0);
+        self.scroll = self.scroll.clamp(0.0, max);
+    }
+
+    /// Scroll the active item into view.
+    fn reveal_active(&mut self) {
+        let Some(active) = &self.active else {
+            return;
+        };
+        let Some(index) = self.visible().iter().position(|path| path == active) else {
+            return;
+        };
+        let top = index as f32 * self.row_height;
+        let bottom = top + self.row_height;
+        if top < self.scroll {
+            self.scroll = top;
+        } else if bottom > self.scroll + self.viewport.y {
+            self.scroll = bottom - self.viewport.y;
+        }
+    }
+
+    /// Expand a collapsed item or collapse an expanded one, and slide its children in or out. Returns the hook to call with the new state, if the item has children.
+    fn toggle(&mut self, path: &TreePath) -> Option<(Option<ToggleHook>, bool)> {
+        let item = self
+            .item_mut(path)
+            .filter(|item| !item.children.is_empty())?;
+        item.expanded = !item.expanded;
+        let expanded = item.expanded;
+        if !expanded {
+            // The active item can't stay hidden in the collapsed item
+            if self
+                .active
+                .as_ref()
+                .is_some_and(|active| active.len() > path.len() && active.starts_with(path))
+            {
+                self.active = Some(path.clone());
+            }
+        }
+        self.animation = Some((path.clone(), Instant::now(), expanded));
+        Some((self.on_toggle.clone(), expanded))
+    }
+
+    /// Make an item active and select it: on its own, added to or removed from the selection with `toggle`, or together with the items between it and the anchor with `extend`.
+    fn select(&mut self, path: TreePath, toggle: bool, extend: bool) {
+        if extend {
+            let visible = self.visible();
+            let anchor = self.anchor.clone().unwrap_or_else(|| path.clone());
+            let position = |path: &TreePath| visible.iter().position(|visible| visible == path);
+            if let (Some(from), Some(to)) = (position(&anchor), position(&path)) {
+                self.selected = visible[from.min(to)..=from.max(to)].to_vec();
+            }
+            self.anchor = Some(anchor);
+        } else if toggle {
+            match self.selected.iter().position(|selected| *selected == path) {
+                Some(index) => {
+                    self.selected.remove(index);
+                }
+                None => self.selected.push(path.clone()),
+            }
+            self.anchor = Some(path.clone());
+        } else {
+            self.selected = vec![path.clone()];
+            self.anchor = Some(path.clone());
+        }
+        self.active = Some(path);
+    }
+
+    /// Handle a key pressed while the tree has focus. Returns true if the tree changed, true if the selection changed, and the toggle hook to call if an item was expanded or collapsed.
+    fn key(&mut self, key: &Key, modifiers: Modifiers) -> KeyOutcome {
+        let visible = self.visible();
+        if visible.is_empty() {
+            return (false, false, None);
+        }
+        let shift = modifiers.contains(Modifiers::SHIFT);
+        let ctrl = modifiers.intersects(Modifiers::CONTROL | Modifiers::META);
+        let index = self
+            .active
+            .as_ref()
+            .and_then(|active| visible.iter().position(|path| path == active));
+        let target = match key {
+            Key::ArrowUp => Some(index.map_or(0, |index| index.saturating_sub(1))),
+            Key::ArrowDown => Some(index.map_or(0, |index| (index + 1).min(visible.len() - 1))),
+            Key::Home => Some(0),
+            Key::End => Some(visible.len() - 1),
+            Key::ArrowRight | Key::ArrowLeft | Key::Enter => {
+                let Some(active) = index.map(|index| visible[index].clone()) else {
+                    return (false, false, None);
+                };
+                let item = self.item(&active).unwrap();
+                let has_children = !item.children.is_empty();
+                let expanded = item.expanded;
+                match key {
+                    Key::ArrowRight if has_children && expanded => {
+                        let mut child = active;
+                        child.push(0);
+                        self.select(child, false, false);
+                        self.reveal_active();
+                        return (true, true, None);
+                    }
+                    Key::ArrowLeft if !(has_children && expanded) => {
+                        if active.len() <= 1 {
+                            return (false, false, None);
+                        }
+                        let parent = active[..active.len() - 1].to_vec();
+                        self.select(parent, false, false);
+                        self.reveal_active();
+                        return (true, true, None);
+                    }
+                    Key::ArrowRight if expanded => return (false, false, None),
+                    _ => {
+                        let Some((hook, expanded)) = self.toggle(&active) else {
+                            return (false, false, None);
+                        };
+                        return (true, false, hook.map(|hook| (hook, active, expanded)));
+                    }
+                }
+            }
+            Key::Character(character) if character == " " => {
+                let Some(active) = index.map(|index| visible[index].clone()) else {
+                    return (false, false, None);
+                };
+                self.select(active, ctrl, false);
+                return (true, true, None);
+            }
+            _ => None,
+        };
+        let Some(target) = target else {
+            return (false, false, None);
+        };
+        let path = visible[target].clone();
+        if ctrl && !shift {
+            // The active item moves and the selection stays
+            self.active = Some(path);
+            self.reveal_active();
+            return (true, false, None);
+        }
+        self.select(path, false, shift);
+        self.reveal_active();
+        (true, true, None)
+    }
+}
+
+impl TreeView {
+    /// A tree for the elements with a tag, without items.
+    pub fn new(tag: &str) -> Self {
+        Self {
+            tag: tag.into(),
+            inner: Arc::new(Mutex::new(Inner {
+                items: Vec::new(),
+                row_height: 24.0,
+                indent: 16.0,
+                active: None,
+                selected: Vec::new(),
+                anchor: None,
+                on_select: None,
+                on_toggle: None,
+                scroll: 0.0,
+                viewport: Vec2::ZERO,
+                animation: None,
+                pressed: None,
+                clicked: None,
+                revision: 0,
+            })),
+        }
+    }
+
+    pub fn with_items(self, items: Vec<TreeItem>) -> Self {
+        self.set_items(items);
+        self
+    }
+
+    /// Set the height of the rows and how far each level is indented, in pixels.
+    pub fn with_row_height(self, row_height: f32, indent: f32) -> Self {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.row_height = row_height.max(1.0);
+            inner.indent = indent.max(0.0);
+        }
+        self
+    }
+
+    /// Replace the items. The selection and the active item are cleared, because the paths may point to other items.
+    pub fn set_items(&self, items: Vec<TreeItem>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.items = items;
+        inner.selected.clear();
+        inner.active = None;
+        inner.anchor = None;
+        inner.animation = None;
+    }
+
+    /// Replace the children of an item, for example with the children that were loaded when it was expanded. Does nothing if there is no item at the path.
+    pub fn set_children(&self, path: &[usize], children: Vec<TreeItem>) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(item) = inner.item_mut(path) {
+            item.children = children;
+        }
+        // Paths into the old children may point past the new ones
+        let selected: Vec<_> = inner
+            .selected
+            .iter()
+            .filter(|selected| inner.item(selected).is_some())
+            .cloned()
+            .collect();
+        inner.selected = selected;
+        if inner
+            .active
+            .as_ref()
+            .is_some_and(|active| inner.item(active).is_none())
+        {
+            inner.active = None;
+        }
+    }
+
+    /// The items with their expanded state, to keep it for the next launch.
+    pub fn items(&self) -> Vec<TreeItem> {
+        self.inner.lock().unwrap().items.clone()
+    }
+
+    /// The selected items in the order they were selected.
+    pub fn selected(&self) -> Vec<TreePath> {
+        self.inner.lock().unwrap().selected.clone()
+    }
+
+    /// The item that moves with the arrow keys.
+    pub fn active(&self) -> Option<TreePath> {
+        self.inner.lock().unwrap().active.clone()
+    }
+
+    /// Call a hook with the selected items when the user changes the selection.
+    pub fn on_select(&self, hook: impl Fn(&[TreePath]) + Send + Sync + 'static) {
+        self.inner.lock().unwrap().on_select = Some(Arc::new(hook));
+    }
+
+    /// Call a hook when the user expands or collapses an item, with true if it was expanded, for example to load its children and pass them to [`TreeView::set_children`].
+    pub fn on_toggle(&self, hook: impl Fn(&TreePath, bool) + Send + Sync + 'static) {
+        self.inner.lock().unwrap().on_toggle = Some(Arc::new(hook));
+    }
+
+    /// Change the revision attribute of the element, so a change made in an event is drawn.
+    fn touch(&self, mut node: NodeMut) {
+        let revision = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.revision += 1;
+            inner.revision
+        };
+        if let NodeTypeMut::Element(mut element) = node.node_type_mut() {
+            element.set_attribute(
+                OwnedAttributeDiscription {
+                    name: REVISION_ATTRIBUTE.to_string(),
+                    namespace: None,
+                },
+                OwnedAttributeValue::Text(revision.to_string()),
+            );
+        }
+    }
+
+    /// Call the selection hook with the selected items.
+    fn selection_changed(&self) {
+        let (hook, selected) = {
+            let inner = self.inner.lock().unwrap();
+            (inner.on_select.clone(), inner.selected.clone())
+        };
+        // The hook can change the tree
+        if let Some(hook) = hook {
+            hook(&selected);
+        }
+    }
+}
+
+impl ElementPlugin for TreeView {
+    fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    fn measure(
+        &self,
+        _element: &CustomElement,
+        known_dimensions: Size<Option<f32>>,
+        _available_space: Size<AvailableSpace>,
+    ) -> Size<f32> {
+        // Without a height the tree grows with its rows, with one it scrolls
+        let inner = self.inner.lock().unwrap();
+        Size {
+            width: known_dimensions.width.unwrap_or_default(),
+            height: known_dimensions
+                .height
+                .unwrap_or(inner.visible().len() as f32 * inner.row_height),
+        }
+    }
+
+    fn paint(&self, _element: &CustomElement, content_box: Rect, painter: &mut Painter) {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        if inner
+            .animation
+            .as_ref()
+            .is_some_and(|(_, start, _)| now.duration_since(*start) >= EXPAND_DURATION)
+        {
+            inner.animation = None;
+        }
+        inner.viewport = content_box.size();
+        let layout = inner.layout(now);
+        inner.clamp_scroll(layout.height);
+        let scroll = inner.scroll;
+        let row_height = inner.row_height;
+        let indent = inner.indent;
+        let origin = content_box.min - vec2(0.0, scroll);
+        let reveal = layout.reveal.map(|(top, bottom)| {
+            Rect::from_min_max(
+                pos2(content_box.min.x, origin.y + top),
+                pos2(content_box.max.x, origin.y + bottom),
+            )
+        });
+        painter.clipped(content_box, |painter| {
+            // Only the rows that are at least partly in view are drawn
+            for row in layout.rows.iter().filter(|row| {
+                let top = origin.y + row.top;
+                top + row_height > content_box.min.y && top < content_box.max.y
+            }) {
+                let rect = Rect::from_min_size(
+                    pos2(content_box.min.x, origin.y + row.top),
+                    vec2(content_box.width(), row_height),
+                );
+                let clip = match (row.animated, reveal) {
+                    (true, Some(reveal)) => reveal,
+                    _ => rect,
+                };
+                painter.clipped(clip, |painter| {
+                    paint_row(painter, &inner, row, rect, indent);
+                });
+            }
+        });
+    }
+
+    fn animating(&self) -> bool {
+        self.inner.lock().unwrap().animation.is_some()
+    }
+
+    fn scroll(&self, _element: &CustomElement, _point: Pos2, delta: Vec2) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let before = inner.scroll;
+        let height = inner.layout(Instant::now()).height;
+        inner.scroll -= delta.y;
+        inner.clamp_scroll(height);
+        inner.scroll != before
+    }
+
+    fn pointer(&self, _element: &CustomElement, phase: PointerPhase, point: Pos2) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let y = point.y + inner.scroll;
+        let layout = inner.layout(Instant::now());
+        let hit = layout.row_at(y, inner.row_height).map(|row| {
+            let arrow_left = row.depth as f32 * inner.indent;
+            let on_arrow =
+                row.has_children && point.x >= arrow_left && point.x < arrow_left + inner.indent;
+            (row.path.clone(), on_arrow)
+        });
+        match phase {
+            PointerPhase::Down => {
+                inner.pressed = hit;
+                inner.clicked = None;
+                false
+            }
+            PointerPhase::Move => false,
+            PointerPhase::Up => {
+                let Some((path, on_arrow)) = inner
+                    .pressed
+                    .take()
+                    .filter(|pressed| hit.as_ref() == Some(pressed))
+                else {
+                    return false;
+                };
+                if !on_arrow {
+                    // Selected by the click event, which has the modifiers
+                    inner.clicked = Some(path);
+                    return false;
+                }
+                let toggled = inner.toggle(&path);
+                drop(inner);
+                if let Some((Some(hook), expanded)) = &toggled {
+                    hook(&path, *expanded);
+                }
+                toggled.is_some()
+            }
+        }
+    }
+
+    fn handle_event(&self, node: NodeMut, event: &str, data: &EventData) -> bool {
+        match (event, data) {
+            ("click", EventData::Mouse(data)) => {
+                let modifiers = data.modifiers();
+                {
+                    let mut inner = self.inner.lock().unwrap();
+                    let Some(path) = inner.clicked.take() else {
+                        return false;
+                    };
+                    inner.select(
+                        path,
+                        modifiers.intersects(Modifiers::CONTROL | Modifiers::META),
+                        modifiers.contains(Modifiers::SHIFT),
+                    );
+                }
+                self.touch(node);
+                self.selection_changed();
+            }
+            ("keydown", EventData::Keyboard(data)) => {
+                let (changed, selected, toggled) = self
+                    .inner
+                    .lock()
+                    .unwrap()
+                    .key(&data.key(), data.modifiers());
+                if changed {
+                    self.touch(node);
+                }
+                if let Some((hook, path, expanded)) = toggled {
+                    hook(&path, expanded);
+                }
+                if selected {
+                    self.selection_changed();
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+}
+
+/// Paint the selection, indent guides, arrow and label of a row.
+fn paint_row(painter: &mut Painter, inner: &Inner, row: &Row, rect: Rect, indent: f32) {
+    if inner.selected.contains(&row.path) {
+        painter.add(Shape::rect_filled(
+            rect,
+            Rounding::none(),
+            SELECTED_BACKGROUND,
+        ));
+    }
+    if inner.active.as_ref() == Some(&row.path) {
+        painter.add(Shape::rect_stroke(
+            rect.shrink(0.5),
+            Rounding::none(),
+            Stroke::new(1.0, ACTIVE_OUTLINE),
+        ));
+    }
+    // A line for each level the row is nested in, through the middle of the arrows of the level
+    for level in 0..row.depth {
+        let x = (rect.min.x + (level as f32 + 0.5) * indent).round() + 0.5;
+        painter.add(Shape::line_segment(
+            [pos2(x, rect.min.y), pos2(x, rect.max.y)],
+            Stroke::new(1.0, GUIDE_COLOR),
+        ));
+    }
+    let arrow_center = pos2(
+        rect.min.x + (row.depth as f32 + 0.5) * indent,
+        rect.center().y,
+    );
+    if row.has_children {
+        painter.add(arrow(arrow_center, row.turn));
+    }
+    let position = pos2(
+        rect.min.x + (row.depth as f32 + 1.0) * indent,
+        rect.center().y - FONT_SIZE * 0.6,
+    );
+    if let Some(item) = inner.item(&row.path) {
+        painter.text(&item.label, position, FONT_SIZE, TEXT_COLOR);
+    }
+}
+
+/// A small triangle that points right at a turn of 0 and down at a turn of 1.
+fn arrow(center: Pos2, turn: f32) -> Shape {
+    let angle = turn * std::f32::consts::FRAC_PI_2;
+    let (sin, cos) = angle.sin_cos();
+    let rotate = |x: f32, y: f32| center + vec2(x * cos - y * sin, x * sin + y * cos);
+    Shape::convex_polygon(
+        vec![rotate(3.5, 0.0), rotate(-2.5, 4.0), rotate(-2.5, -4.0)],
+        ARROW_COLOR,
+        Stroke::NONE,
+    )
+}
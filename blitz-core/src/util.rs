@@ -165,8 +165,8 @@ impl Resolve for PositionComponent<VerticalPositionKeyword> {
                     .map(|offset| offset.resolve(axis, rect, viewport_size))
                     .unwrap_or_default();
                 match side {
-                    VerticalPositionKeyword::Bottom => offset,
-                    VerticalPositionKeyword::Top => rect.height as f64 - offset,
+                    VerticalPositionKeyword::Top => offset,
+                    VerticalPositionKeyword::Bottom => rect.height as f64 - offset,
                 }
             }
         }
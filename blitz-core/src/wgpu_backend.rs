@@ -0,0 +1,1759 @@
+use std::mem::size_of;
+use std::num::NonZeroU64;
+use std::ops::Range;
+
+use epaint::{Color32, ColorImage, ImageData, ImageDelta, Mesh, Rgba};
+use peniko::kurbo::Affine;
+use rustc_hash::FxHashMap;
+use tao::{dpi::PhysicalSize, window::Window};
+use wgpu::util::DeviceExt;
+
+use crate::backend::{
+    Backend, ColorMatrix, DrawConstants, DynamicRange, Msaa, PresentMode, RectInstance,
+};
+use crate::screenshot::image_from_texels;
+
+/// The smallest buffer that is allocated in bytes
+const MIN_CAPACITY: u64 = 64 * 1024;
+
+/// A backend that draws with wgpu, so documents can be shown with Metal, DirectX 12 and WebGPU as well as Vulkan. It is used by default when Blitz is built with the `wgpu` feature and without the `vulkan` feature.
+///
+/// ```no_run
+/// # use blitz_core::{Config, Msaa, WgpuBackend};
+/// let cfg = Config::new().with_backend(|window| WgpuBackend::new(window, Msaa::X4));
+/// ```
+pub struct WgpuBackend {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    /// The ways the surface can be composited with the windows behind it
+    alpha_modes: Vec<wgpu::CompositeAlphaMode>,
+    /// The format frames and offscreen targets are drawn in, an sRGB view of the surface if it has one
+    format: wgpu::TextureFormat,
+    /// The value CSS white is drawn with, see [`DynamicRange`]
+    white_level: f32,
+    adapter_info: wgpu::AdapterInfo,
+    pipeline: wgpu::RenderPipeline,
+    /// Draw subpixel text in this order: the first darkens the pixels below it by the coverage of each color channel and the second adds its color
+    subpixel_pipelines: [wgpu::RenderPipeline; 2],
+    /// Draws rects as quads filled with their signed distance field
+    rect_pipeline: wgpu::RenderPipeline,
+    /// Fills the damaged area of the frame with the clear color, replacing what the last frame drew there
+    clear_pipeline: wgpu::RenderPipeline,
+    constants_layout: wgpu::BindGroupLayout,
+    texture_layout: wgpu::BindGroupLayout,
+    /// The texture meshes sample with their uv coordinates and the bind group it is bound with
+    texture: (wgpu::Texture, wgpu::BindGroup),
+    sampler: wgpu::Sampler,
+    sample_count: u32,
+    /// The texture the frame is drawn into. It is kept between frames, so only the damaged area is drawn again, and drawn to the surface texture at the end of each frame
+    frame: Option<OffscreenTarget>,
+    /// The area of the frame that is drawn again, in whole pixels
+    damage: epaint::Rect,
+    /// Blurs the rows of an area of the frame into the scratch texture
+    blur_pipeline: wgpu::RenderPipeline,
+    /// Blurs the columns of the scratch texture back into the frame
+    backdrop_pipeline: wgpu::RenderPipeline,
+    /// Draws a filtered layer over the layer below it
+    filter_pipeline: wgpu::RenderPipeline,
+    /// Draws the frame to the surface texture, which isn't multisampled
+    present_pipeline: wgpu::RenderPipeline,
+    /// The texture and constants of passes that draw a texture over their target, like blurs and filters
+    effect_layout: wgpu::BindGroupLayout,
+    /// Created with the first blurred backdrop
+    blur_textures: Option<BlurTextures>,
+    /// The layers filtered elements are drawn into, one for each level of nesting, with the size of the window or of the largest cached layer they were drawn in
+    filter_targets: Vec<OffscreenTarget>,
+    /// The textures of cached layers by id, see [`Backend::begin_cached_layer`]
+    cached_layers: FxHashMap<u64, CachedLayer>,
+    /// The damaged area of the frame while a cached layer is drawn
+    frame_damage: Option<epaint::Rect>,
+    vertex_buffer: Option<wgpu::Buffer>,
+    index_buffer: Option<wgpu::Buffer>,
+    rect_buffer: Option<wgpu::Buffer>,
+    /// The constants of every draw, each at an offset that is a multiple of the uniform alignment of the device
+    constants_buffer: Option<(wgpu::Buffer, wgpu::BindGroup)>,
+    /// The frame that is being drawn. All meshes share one vertex and one index buffer and are drawn with an offset into them
+    clear_color: Color32,
+    vertices: Vec<epaint::Vertex>,
+    indices: Vec<u32>,
+    rects: Vec<RectInstance>,
+    draw_list: Vec<Draw>,
+    /// Set by [`Backend::capture_next_frame`] until the next frame is drawn
+    capture_next: bool,
+    /// The frame copied to the CPU, until it is taken by [`Backend::captured_frame`]
+    captured: Option<image::RgbaImage>,
+}
+
+/// Primitives that are drawn with one set of constants
+struct Draw {
+    primitives: Primitives,
+    transform: Affine,
+    clip: epaint::Rect,
+}
+
+enum Primitives {
+    /// A range of the index buffer
+    Mesh {
+        first_index: u32,
+        index_count: u32,
+        vertex_offset: i32,
+        /// The cached layer the mesh samples instead of the texture
+        layer: Option<u64>,
+        /// Text with subpixel glyphs, which is drawn twice, see [`Backend::draw_subpixel_text`]
+        subpixel: bool,
+    },
+    /// A range of the rect buffer, drawn with one instance per rect
+    Rects { first_rect: u32, rect_count: u32 },
+    /// A rect of the rect buffer that fills the damaged area with the clear color, drawn without blending
+    Clear { rect: u32 },
+    /// Blur what was drawn before in an area of the window, see [`Backend::blur_backdrop`]
+    BackdropBlur { area: epaint::Rect, radius: f32 },
+    /// Draw into a new layer, see [`Backend::begin_filter`]
+    BeginFilter,
+    /// Draw the layer over the one below it with an opacity, see [`Backend::end_filter`]
+    EndFilter(ColorMatrix, f32),
+    /// Draw into the texture of a cached layer, see [`Backend::begin_cached_layer`]
+    BeginCachedLayer(u64),
+    /// Go back to drawing the frame
+    EndCachedLayer,
+}
+
+/// A texture that the frame, a filtered layer or a cached layer is drawn into
+struct OffscreenTarget {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    msaa_view: Option<wgpu::TextureView>,
+}
+
+impl OffscreenTarget {
+    fn target(&self) -> RenderTarget<'_> {
+        RenderTarget {
+            texture: &self.texture,
+            view: &self.view,
+            msaa_view: self.msaa_view.as_ref(),
+        }
+    }
+}
+
+/// The texture of a cached layer, see [`Backend::begin_cached_layer`]. Its samples are only kept while it is drawn
+struct CachedLayer {
+    target: OffscreenTarget,
+    /// Binds the texture to meshes in place of the atlas
+    bind_group: wgpu::BindGroup,
+}
+
+/// What a pass draws to: the frame, a filtered layer or a cached layer
+#[derive(Clone, Copy)]
+struct RenderTarget<'a> {
+    texture: &'a wgpu::Texture,
+    view: &'a wgpu::TextureView,
+    /// The multisampled texture that is resolved into the view
+    msaa_view: Option<&'a wgpu::TextureView>,
+}
+
+impl<'a> RenderTarget<'a> {
+    /// The attachment of a pass that draws to the target.
+    fn attachment(self, load: wgpu::LoadOp<wgpu::Color>) -> wgpu::RenderPassColorAttachment<'a> {
+        match self.msaa_view {
+            // The samples are averaged into the view. They are kept, because the next pass continues drawing on them and the whole texture is resolved, including the area the next frame doesn't draw again
+            Some(msaa_view) => wgpu::RenderPassColorAttachment {
+                view: msaa_view,
+                resolve_target: Some(self.view),
+                ops: wgpu::Operations { load, store: true },
+            },
+            None => wgpu::RenderPassColorAttachment {
+                view: self.view,
+                resolve_target: None,
+                ops: wgpu::Operations { load, store: true },
+            },
+        }
+    }
+}
+
+/// A target that passes draw to while the frame is recorded
+struct Level<'a> {
+    target: RenderTarget<'a>,
+    /// The size of the viewport, which is the size of the frame or of the cached layer the target is drawn in
+    size: [u32; 2],
+    /// The area the passes draw to, the damaged area of the frame or the whole cached layer
+    area: epaint::Rect,
+    /// What the next pass starts with
+    load: wgpu::LoadOp<wgpu::Color>,
+}
+
+impl<'a> Level<'a> {
+    /// Begin a pass that draws to the area of the level.
+    fn begin_pass<'p>(
+        &mut self,
+        encoder: &'p mut wgpu::CommandEncoder,
+        label: &str,
+    ) -> wgpu::RenderPass<'p>
+    where
+        'a: 'p,
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(self.target.attachment(self.load))],
+            depth_stencil_attachment: None,
+        });
+        self.load = wgpu::LoadOp::Load;
+        let [width, height] = self.size;
+        pass.set_viewport(0.0, 0.0, width as f32, height as f32, 0.0, 1.0);
+        // Outside of the damaged area, the frame keeps what the last frame drew
+        let area = self.area;
+        pass.set_scissor_rect(
+            area.min.x as u32,
+            area.min.y as u32,
+            area.width() as u32,
+            area.height() as u32,
+        );
+        pass
+    }
+}
+
+/// The textures a backdrop is blurred with. An area of the frame is copied into the first one, its rows are blurred into the second one and then its columns are blurred back into the frame
+struct BlurTextures {
+    size: [u32; 2],
+    copy: wgpu::Texture,
+    copy_view: wgpu::TextureView,
+    blurred_view: wgpu::TextureView,
+}
+
+/// The constants of blur.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct BlurConstants {
+    direction: [f32; 2],
+    offset: [f32; 2],
+    size: [f32; 2],
+    sigma: f32,
+    _padding: f32,
+}
+
+impl WgpuBackend {
+    /// Create a backend for a window. Multisampling falls back to the highest sample count the adapter supports.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no adapter that can draw to the window.
+    pub fn new(window: &Window, msaa: Msaa) -> Self {
+        Self::with_present_mode(window, msaa, PresentMode::Fifo)
+    }
+
+    /// Create a backend that presents frames with a present mode, see [`crate::Config::with_present_mode`]. Falls back to FIFO if the surface doesn't support the mode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no adapter that can draw to the window.
+    pub fn with_present_mode(window: &Window, msaa: Msaa, present_mode: PresentMode) -> Self {
+        Self::with_dynamic_range(window, msaa, present_mode, DynamicRange::Standard)
+    }
+
+    /// Create a backend that draws in a dynamic range, see [`crate::Config::with_dynamic_range`]. Falls back to the standard range if the surface has no 16 bit float format.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no adapter that can draw to the window.
+    pub fn with_dynamic_range(
+        window: &Window,
+        msaa: Msaa,
+        present_mode: PresentMode,
+        dynamic_range: DynamicRange,
+    ) -> Self {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        // The surface is dropped before the window, because the backend is dropped with the application state
+        let surface = unsafe { instance.create_surface(window) }.unwrap();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: Some(&surface),
+            force_fallback_adapter: false,
+        }))
+        .expect("no graphics adapter can draw to the window");
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("blitz"),
+                features: wgpu::Features::empty(),
+                limits:
+                    wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+            },
+            None,
+        ))
+        .unwrap();
+        // By default wgpu panics on errors that aren't captured. They are logged and kept for diagnostics reports instead
+        device.on_uncaptured_error(Box::new(|err| {
+            log::error!("wgpu: {err}");
+            crate::report::record_validation_message(err.to_string());
+        }));
+
+        let capabilities = surface.get_capabilities(&adapter);
+        // Float surfaces are extended linear sRGB, so the linear colors of the shader are written as they are
+        let hdr_format = wgpu::TextureFormat::Rgba16Float;
+        let dynamic_range = match dynamic_range {
+            DynamicRange::High { .. } if !capabilities.formats.contains(&hdr_format) => {
+                log::warn!("the surface has no HDR format, drawing in the standard dynamic range");
+                DynamicRange::Standard
+            }
+            dynamic_range => dynamic_range,
+        };
+        // The shader outputs linear colors, so an sRGB surface converts them back
+        let surface_format = match dynamic_range {
+            DynamicRange::Standard => capabilities
+                .formats
+                .iter()
+                .copied()
+                .find(wgpu::TextureFormat::is_srgb)
+                .unwrap_or(capabilities.formats[0]),
+            DynamicRange::High { .. } => hdr_format,
+        };
+        // Surfaces without an sRGB format are drawn through an sRGB view, which encodes the colors like an sRGB surface
+        let format = surface_format.add_srgb_suffix();
+        if !format.is_srgb() && format != hdr_format {
+            log::warn!(
+                "the surface has no sRGB format, colors are written to {format:?} as they are"
+            );
+        }
+        let size = window.inner_size();
+        let requested = match present_mode {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        };
+        let present_mode = if capabilities.present_modes.contains(&requested) {
+            requested
+        } else {
+            log::warn!("{present_mode:?} presentation is not supported by the surface, using FIFO");
+            wgpu::PresentMode::Fifo
+        };
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode,
+            alpha_mode: capabilities.alpha_modes[0],
+            view_formats: if format == surface_format {
+                Vec::new()
+            } else {
+                vec![format]
+            },
+        };
+        surface.configure(&device, &config);
+
+        let supported = adapter.get_texture_format_features(format).flags;
+        let sample_count = [8, 4, 2]
+            .into_iter()
+            .filter(|samples| *samples <= msaa.samples())
+            .find(|samples| supported.sample_count_supported(*samples))
+            .unwrap_or(1);
+        if sample_count != msaa.samples() {
+            log::warn!(
+                "{msaa:?} multisampling is not supported by the adapter, using {sample_count} samples"
+            );
+        }
+
+        let constants_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("constants"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: NonZeroU64::new(size_of::<DrawConstants>() as u64),
+                },
+                count: None,
+            }],
+        });
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("texture"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("texture"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        // Until the first update, every uv samples white
+        let texture = create_texture(
+            &device,
+            &queue,
+            &texture_layout,
+            &sampler,
+            &ColorImage::new([1, 1], Color32::WHITE),
+        );
+        // epaint colors are premultiplied
+        let pipeline = create_pipeline(
+            &device,
+            &[&constants_layout, &texture_layout],
+            format,
+            sample_count,
+            "fs_main",
+            wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+        );
+        // Subpixel text darkens each color channel of the pixels below it by its coverage, then adds its color. Blending with a second output would do both at once, but wgpu 0.16 can't
+        let coverage_pipeline = create_pipeline(
+            &device,
+            &[&constants_layout, &texture_layout],
+            format,
+            sample_count,
+            "fs_coverage",
+            wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+        );
+        let subpixel_pipeline = create_pipeline(
+            &device,
+            &[&constants_layout, &texture_layout],
+            format,
+            sample_count,
+            "fs_main",
+            wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+        );
+        let rect_pipeline = create_rect_pipeline(
+            &device,
+            &constants_layout,
+            format,
+            sample_count,
+            Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+        );
+        let clear_pipeline =
+            create_rect_pipeline(&device, &constants_layout, format, sample_count, None);
+        let effect_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("effect"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let blur = wgpu::include_wgsl!("./blur.wgsl");
+        let blur_pipeline =
+            create_effect_pipeline(&device, blur.clone(), &effect_layout, format, 1, None);
+        let backdrop_pipeline =
+            create_effect_pipeline(&device, blur, &effect_layout, format, sample_count, None);
+        let filter_pipeline = create_effect_pipeline(
+            &device,
+            wgpu::include_wgsl!("./filter.wgsl"),
+            &effect_layout,
+            format,
+            sample_count,
+            Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+        );
+        let present_pipeline = create_effect_pipeline(
+            &device,
+            wgpu::include_wgsl!("./filter.wgsl"),
+            &effect_layout,
+            format,
+            1,
+            None,
+        );
+
+        Self {
+            surface,
+            device,
+            queue,
+            config,
+            alpha_modes: capabilities.alpha_modes.clone(),
+            format,
+            white_level: dynamic_range.white_level(),
+            adapter_info: adapter.get_info(),
+            pipeline,
+            subpixel_pipelines: [coverage_pipeline, subpixel_pipeline],
+            rect_pipeline,
+            clear_pipeline,
+            constants_layout,
+            texture_layout,
+            texture,
+            sampler,
+            sample_count,
+            frame: None,
+            damage: epaint::Rect::NOTHING,
+            blur_pipeline,
+            backdrop_pipeline,
+            filter_pipeline,
+            present_pipeline,
+            effect_layout,
+            blur_textures: None,
+            filter_targets: Vec::new(),
+            cached_layers: FxHashMap::default(),
+            frame_damage: None,
+            vertex_buffer: None,
+            index_buffer: None,
+            rect_buffer: None,
+            constants_buffer: None,
+            clear_color: Color32::TRANSPARENT,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            rects: Vec::new(),
+            draw_list: Vec::new(),
+            capture_next: false,
+            captured: None,
+        }
+    }
+
+    /// The frame in window coordinates
+    fn window_rect(&self) -> epaint::Rect {
+        epaint::Rect::from_min_size(
+            epaint::Pos2::ZERO,
+            epaint::vec2(self.config.width as f32, self.config.height as f32),
+        )
+    }
+
+    /// Create a texture with a size to draw the frame or a layer into.
+    fn create_target(&self, label: &str, [width, height]: [u32; 2]) -> OffscreenTarget {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            // Blurred backdrops and screenshots are copied from it
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        OffscreenTarget {
+            view: texture.create_view(&Default::default()),
+            msaa_view: self.create_msaa_view(label, size),
+            texture,
+        }
+    }
+
+    /// Create the multisampled texture that is resolved into a target with a size, unless multisampling is off.
+    fn create_msaa_view(&self, label: &str, size: wgpu::Extent3d) -> Option<wgpu::TextureView> {
+        (self.sample_count > 1).then(|| {
+            self.device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: self.sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: self.format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&Default::default())
+        })
+    }
+
+    /// Create the textures backdrops are blurred with, unless they are already at least as large as `size`.
+    fn create_blur_textures(&mut self, size: [u32; 2]) {
+        if matches!(&self.blur_textures, Some(textures) if textures.size[0] >= size[0] && textures.size[1] >= size[1])
+        {
+            return;
+        }
+        let create = |label, usage| {
+            self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: size[0],
+                    height: size[1],
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                // Copies need the format of the frame, but may differ in whether it is sRGB
+                format: self.format,
+                usage: usage | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+        let copy = create("backdrop", wgpu::TextureUsages::COPY_DST);
+        let blurred = create("blurred backdrop", wgpu::TextureUsages::RENDER_ATTACHMENT);
+        self.blur_textures = Some(BlurTextures {
+            size,
+            copy_view: copy.create_view(&Default::default()),
+            copy,
+            blurred_view: blurred.create_view(&Default::default()),
+        });
+    }
+
+    /// Create the layers for filtered elements nested up to `depth` levels deep, at least as large as `size`.
+    fn create_filter_targets(&mut self, depth: usize, size: [u32; 2]) {
+        if self.filter_targets.first().is_some_and(|target| {
+            target.texture.width() < size[0] || target.texture.height() < size[1]
+        }) {
+            self.filter_targets.clear();
+        }
+        while self.filter_targets.len() < depth {
+            let target = self.create_target("filter", size);
+            self.filter_targets.push(target);
+        }
+    }
+
+    /// Draw a range of the draw list.
+    fn draw_range<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, draws: Range<usize>) {
+        let (Some(vertex_buffer), Some(index_buffer), Some(rect_buffer), Some((_, constants))) = (
+            &self.vertex_buffer,
+            &self.index_buffer,
+            &self.rect_buffer,
+            &self.constants_buffer,
+        ) else {
+            return;
+        };
+        if draws.is_empty() {
+            return;
+        }
+        pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        let stride = self.constants_stride();
+        let mut bound = None;
+        let mut bound_texture = None;
+        for index in draws {
+            let draw = &self.draw_list[index];
+            let (pipelines, texture) = match draw.primitives {
+                Primitives::Mesh {
+                    layer: None,
+                    subpixel: false,
+                    ..
+                } => (std::slice::from_ref(&self.pipeline), Some(&self.texture.1)),
+                // Subpixel text darkens each channel by its coverage before its color is added, which needs a draw for each blend. Where glyphs overlap, all of them darken the pixel before any color is added, unlike on the CPU
+                Primitives::Mesh {
+                    layer: None,
+                    subpixel: true,
+                    ..
+                } => (&self.subpixel_pipelines[..], Some(&self.texture.1)),
+                Primitives::Mesh {
+                    layer: Some(id), ..
+                } => match self.cached_layers.get(&id) {
+                    Some(layer) => (
+                        std::slice::from_ref(&self.pipeline),
+                        Some(&layer.bind_group),
+                    ),
+                    None => continue,
+                },
+                Primitives::Rects { .. } => (std::slice::from_ref(&self.rect_pipeline), None),
+                Primitives::Clear { .. } => (std::slice::from_ref(&self.clear_pipeline), None),
+                // Blurs, filters and cached layers end the pass, see `end_frame`
+                Primitives::BackdropBlur { .. }
+                | Primitives::BeginFilter
+                | Primitives::EndFilter(..)
+                | Primitives::BeginCachedLayer(_)
+                | Primitives::EndCachedLayer => continue,
+            };
+            for pipeline in pipelines {
+                if !bound.is_some_and(|bound| std::ptr::eq(bound, pipeline)) {
+                    pass.set_pipeline(pipeline);
+                    if texture.is_some() {
+                        pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    } else {
+                        pass.set_vertex_buffer(0, rect_buffer.slice(..));
+                    }
+                    bound = Some(pipeline);
+                    bound_texture = None;
+                }
+                if let Some(texture) = texture {
+                    if !bound_texture.is_some_and(|bound| std::ptr::eq(bound, texture)) {
+                        pass.set_bind_group(1, texture, &[]);
+                        bound_texture = Some(texture);
+                    }
+                }
+                // Each layer is drawn with its own transform and clip, so moving a layer only changes these constants
+                pass.set_bind_group(0, constants, &[(index as u64 * stride) as u32]);
+                match draw.primitives {
+                    Primitives::Mesh {
+                        first_index,
+                        index_count,
+                        vertex_offset,
+                        ..
+                    } => pass.draw_indexed(
+                        first_index..first_index + index_count,
+                        vertex_offset,
+                        0..1,
+                    ),
+                    // The vertex shader builds the 6 vertices of the quad from the vertex index
+                    Primitives::Rects {
+                        first_rect,
+                        rect_count,
+                    } => pass.draw(0..6, first_rect..first_rect + rect_count),
+                    Primitives::Clear { rect } => pass.draw(0..6, rect..rect + 1),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Blur an area of what was drawn to a level so far. The rows are blurred into a scratch texture and the columns back into the target.
+    fn blur(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        level: &Level,
+        area: epaint::Rect,
+        radius: f32,
+    ) {
+        let Some(textures) = &self.blur_textures else {
+            return;
+        };
+        let target = level.target;
+        let [level_width, level_height] = level.size;
+        let min_x = (area.min.x.floor().max(0.0) as u32).min(level_width);
+        let min_y = (area.min.y.floor().max(0.0) as u32).min(level_height);
+        let max_x = (area.max.x.ceil().max(0.0) as u32).min(level_width);
+        let max_y = (area.max.y.ceil().max(0.0) as u32).min(level_height);
+        if min_x >= max_x || min_y >= max_y {
+            return;
+        }
+        let [width, height] = [max_x - min_x, max_y - min_y];
+        encoder.copy_texture_to_texture(
+            wgpu::ImageCopyTexture {
+                texture: target.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: min_x,
+                    y: min_y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyTexture {
+                texture: &textures.copy,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        let constants = |direction: [f32; 2], offset: [u32; 2]| BlurConstants {
+            direction,
+            offset: offset.map(|offset| offset as f32),
+            size: [width as f32, height as f32],
+            sigma: radius,
+            _padding: 0.0,
+        };
+        let rows = self.effect_bind_group(
+            &textures.copy_view,
+            bytemuck::bytes_of(&constants([1.0, 0.0], [0, 0])),
+        );
+        let columns = self.effect_bind_group(
+            &textures.blurred_view,
+            bytemuck::bytes_of(&constants([0.0, 1.0], [min_x, min_y])),
+        );
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("blur rows"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &textures.blurred_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(&self.blur_pipeline);
+            pass.set_bind_group(0, &rows, &[]);
+            pass.set_scissor_rect(0, 0, width, height);
+            pass.draw(0..3, 0..1);
+        }
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("blur columns"),
+            color_attachments: &[Some(target.attachment(wgpu::LoadOp::Load))],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&self.backdrop_pipeline);
+        pass.set_bind_group(0, &columns, &[]);
+        pass.set_scissor_rect(min_x, min_y, width, height);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Draw a filtered or translucent layer over the level below it.
+    fn composite(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        layer: RenderTarget,
+        parent: &mut Level,
+        matrix: &ColorMatrix,
+        opacity: f32,
+    ) {
+        let bind_group = self.filter_bind_group(layer.view, matrix, opacity);
+        let mut pass = parent.begin_pass(encoder, "filter");
+        pass.set_pipeline(&self.filter_pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Draw the finished frame to the surface texture.
+    fn present(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: &OffscreenTarget,
+        view: &wgpu::TextureView,
+    ) {
+        let bind_group = self.filter_bind_group(&frame.view, &ColorMatrix::IDENTITY, 1.0);
+        // The surface textures don't keep what was presented, so the whole frame is drawn to them
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("present"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(&self.present_pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Bind a layer and the constants of filter.wgsl.
+    fn filter_bind_group(
+        &self,
+        layer: &wgpu::TextureView,
+        matrix: &ColorMatrix,
+        opacity: f32,
+    ) -> wgpu::BindGroup {
+        // WGSL matrices are stored as columns. The white level and the opacity follow them, padded to the size of a column
+        let constants: [[f32; 4]; 5] = std::array::from_fn(|column| match column {
+            4 => [self.white_level, opacity, 0.0, 0.0],
+            column => matrix.0.map(|row| row[column]),
+        });
+        self.effect_bind_group(layer, bytemuck::cast_slice(&constants))
+    }
+
+    /// Bind a texture and the constants of blur.wgsl or filter.wgsl.
+    fn effect_bind_group(&self, source: &wgpu::TextureView, constants: &[u8]) -> wgpu::BindGroup {
+        let buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("effect"),
+                contents: constants,
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("effect"),
+            layout: &self.effect_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// Upload the vertices, indices and constants of the frame, growing the buffers if they don't fit.
+    fn upload(&mut self) {
+        write_buffer(
+            &self.device,
+            &self.queue,
+            &mut self.vertex_buffer,
+            "vertices",
+            wgpu::BufferUsages::VERTEX,
+            bytemuck::cast_slice(&self.vertices),
+        );
+        write_buffer(
+            &self.device,
+            &self.queue,
+            &mut self.index_buffer,
+            "indices",
+            wgpu::BufferUsages::INDEX,
+            bytemuck::cast_slice(&self.indices),
+        );
+        write_buffer(
+            &self.device,
+            &self.queue,
+            &mut self.rect_buffer,
+            "rects",
+            wgpu::BufferUsages::VERTEX,
+            bytemuck::cast_slice(&self.rects),
+        );
+
+        let stride = self.constants_stride() as usize;
+        let window_size = [self.config.width as f32, self.config.height as f32];
+        let mut screen_size = window_size;
+        let mut constants = vec![0; self.draw_list.len() * stride];
+        for (draw, bytes) in self
+            .draw_list
+            .iter()
+            .zip(constants.chunks_exact_mut(stride))
+        {
+            // Meshes in cached layers are drawn in the coordinates of the layer
+            match draw.primitives {
+                Primitives::BeginCachedLayer(id) => {
+                    if let Some(layer) = self.cached_layers.get(&id) {
+                        let texture = &layer.target.texture;
+                        screen_size = [texture.width() as f32, texture.height() as f32];
+                    }
+                }
+                Primitives::EndCachedLayer => screen_size = window_size,
+                _ => {}
+            }
+            // The texels of cached layers were drawn with the white level already
+            let white_level = match draw.primitives {
+                Primitives::Mesh { layer: Some(_), .. } => 1.0,
+                _ => self.white_level,
+            };
+            let draw_constants =
+                DrawConstants::new(screen_size, draw.transform, draw.clip, false, white_level);
+            bytes[..size_of::<DrawConstants>()]
+                .copy_from_slice(bytemuck::bytes_of(&draw_constants));
+        }
+        let mut buffer = self.constants_buffer.take().map(|(buffer, _)| buffer);
+        write_buffer(
+            &self.device,
+            &self.queue,
+            &mut buffer,
+            "constants",
+            wgpu::BufferUsages::UNIFORM,
+            &constants,
+        );
+        let buffer = buffer.unwrap();
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("constants"),
+            layout: &self.constants_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: NonZeroU64::new(size_of::<DrawConstants>() as u64),
+                }),
+            }],
+        });
+        self.constants_buffer = Some((buffer, bind_group));
+    }
+
+    /// The distance between the constants of two draws in bytes
+    fn constants_stride(&self) -> u64 {
+        let alignment = self.device.limits().min_uniform_buffer_offset_alignment as u64;
+        (size_of::<DrawConstants>() as u64).next_multiple_of(alignment)
+    }
+
+    /// Add a mesh to the frame. `layer` is the cached layer it samples instead of the texture and `subpixel` is set for text with subpixel glyphs.
+    fn push_mesh(
+        &mut self,
+        mesh: &Mesh,
+        transform: Affine,
+        clip: epaint::Rect,
+        opacity: f32,
+        layer: Option<u64>,
+        subpixel: bool,
+    ) {
+        // The rest of the frame is kept from the last one
+        let clip = clip.intersect(self.damage);
+        if !clip.is_positive() {
+            return;
+        }
+        let first_index = self.indices.len() as u32;
+        let vertex_offset = self.vertices.len() as i32;
+        if opacity < 1.0 {
+            self.vertices
+                .extend(mesh.vertices.iter().map(|vertex| epaint::Vertex {
+                    color: vertex.color.linear_multiply(opacity),
+                    ..*vertex
+                }));
+        } else {
+            self.vertices.extend_from_slice(&mesh.vertices);
+        }
+        self.indices.extend_from_slice(&mesh.indices);
+        self.draw_list.push(Draw {
+            primitives: Primitives::Mesh {
+                first_index,
+                index_count: mesh.indices.len() as u32,
+                vertex_offset,
+                layer,
+                subpixel,
+            },
+            transform,
+            clip,
+        });
+    }
+
+    /// Read a buffer a frame was copied into. Screenshots are rare, so this waits for the GPU instead of reading it with the next frame.
+    fn read_texels(&self, buffer: &wgpu::Buffer) -> Option<Vec<u8>> {
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        let Ok(Ok(())) = receiver.recv() else {
+            log::warn!("failed to map the buffer the frame was copied into");
+            return None;
+        };
+        let texels = slice.get_mapped_range().to_vec();
+        buffer.unmap();
+        Some(texels)
+    }
+}
+
+impl Backend for WgpuBackend {
+    fn adapter_name(&self) -> String {
+        format!(
+            "{} ({:?})",
+            self.adapter_info.name, self.adapter_info.backend
+        )
+    }
+
+    fn set_transparent(&mut self) -> bool {
+        // The colors of the frame are premultiplied, since meshes are blended with premultiplied alpha. Surfaces that take colors that aren't premultiplied only darken the edges that are partly transparent a little
+        let Some(alpha_mode) = [
+            wgpu::CompositeAlphaMode::PreMultiplied,
+            wgpu::CompositeAlphaMode::PostMultiplied,
+        ]
+        .into_iter()
+        .find(|mode| self.alpha_modes.contains(mode)) else {
+            return false;
+        };
+        self.config.alpha_mode = alpha_mode;
+        self.surface.configure(&self.device, &self.config);
+        true
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.config.width = size.width.max(1);
+        self.config.height = size.height.max(1);
+        self.surface.configure(&self.device, &self.config);
+        self.frame = None;
+        self.blur_textures = None;
+        self.filter_targets.clear();
+    }
+
+    fn update_texture(&mut self, delta: &ImageDelta) {
+        let image = match &delta.image {
+            ImageData::Color(image) => image.clone(),
+            ImageData::Font(image) => ColorImage {
+                size: image.size,
+                pixels: image.srgba_pixels(None).collect(),
+            },
+        };
+        match delta.pos {
+            Some([x, y]) => write_texture(&self.queue, &self.texture.0, [x, y], &image),
+            None => {
+                self.texture = create_texture(
+                    &self.device,
+                    &self.queue,
+                    &self.texture_layout,
+                    &self.sampler,
+                    &image,
+                );
+            }
+        }
+    }
+
+    fn begin_frame(&mut self, clear_color: Color32, damage: epaint::Rect) {
+        self.clear_color = clear_color;
+        self.vertices.clear();
+        self.indices.clear();
+        self.rects.clear();
+        self.draw_list.clear();
+        let window = self.window_rect();
+        // A new frame holds nothing of the last one, so all of it is drawn
+        let damage = match self.frame {
+            Some(_) => damage,
+            None => {
+                self.frame =
+                    Some(self.create_target("frame", [self.config.width, self.config.height]));
+                window
+            }
+        };
+        // Passes draw to whole pixels
+        self.damage =
+            epaint::Rect::from_min_max(damage.min.floor(), damage.max.ceil()).intersect(window);
+        // A frame that is drawn again as a whole is cleared when its first pass begins
+        if self.damage != window && self.damage.is_positive() {
+            let damage = self.damage;
+            self.rects.push(RectInstance {
+                rect: [damage.min.x, damage.min.y, damage.max.x, damage.max.y],
+                fill: clear_color,
+                ..Default::default()
+            });
+            self.draw_list.push(Draw {
+                primitives: Primitives::Clear { rect: 0 },
+                transform: Affine::IDENTITY,
+                clip: damage,
+            });
+        }
+    }
+
+    fn draw_mesh(&mut self, mesh: &Mesh, transform: Affine, clip: epaint::Rect, opacity: f32) {
+        self.push_mesh(mesh, transform, clip, opacity, None, false);
+    }
+
+    fn draws_subpixel_text(&self) -> bool {
+        true
+    }
+
+    fn draw_subpixel_text(
+        &mut self,
+        mesh: &Mesh,
+        transform: Affine,
+        clip: epaint::Rect,
+        opacity: f32,
+    ) {
+        self.push_mesh(mesh, transform, clip, opacity, None, true);
+    }
+
+    fn draws_rects(&self) -> bool {
+        true
+    }
+
+    fn draw_rects(
+        &mut self,
+        rects: &[RectInstance],
+        transform: Affine,
+        clip: epaint::Rect,
+        opacity: f32,
+    ) {
+        let clip = clip.intersect(self.damage);
+        if !clip.is_positive() {
+            return;
+        }
+        let first_rect = self.rects.len() as u32;
+        self.rects
+            .extend(rects.iter().map(|rect| rect.with_opacity(opacity)));
+        self.draw_list.push(Draw {
+            primitives: Primitives::Rects {
+                first_rect,
+                rect_count: rects.len() as u32,
+            },
+            transform,
+            clip,
+        });
+    }
+
+    fn blur_backdrop(&mut self, area: epaint::Rect, radius: f32) {
+        let area = area.intersect(self.damage);
+        if !area.is_positive() {
+            return;
+        }
+        self.draw_list.push(Draw {
+            primitives: Primitives::BackdropBlur { area, radius },
+            transform: Affine::IDENTITY,
+            clip: area,
+        });
+    }
+
+    fn draws_filters(&self) -> bool {
+        true
+    }
+
+    fn begin_filter(&mut self) {
+        self.draw_list.push(Draw {
+            primitives: Primitives::BeginFilter,
+            transform: Affine::IDENTITY,
+            clip: epaint::Rect::EVERYTHING,
+        });
+    }
+
+    fn end_filter(&mut self, matrix: &ColorMatrix, opacity: f32) {
+        self.draw_list.push(Draw {
+            primitives: Primitives::EndFilter(*matrix, opacity),
+            transform: Affine::IDENTITY,
+            clip: epaint::Rect::EVERYTHING,
+        });
+    }
+
+    fn caches_layers(&self) -> bool {
+        true
+    }
+
+    fn begin_cached_layer(&mut self, id: u64, size: [u32; 2]) {
+        let extent = wgpu::Extent3d {
+            width: size[0],
+            height: size[1],
+            depth_or_array_layers: 1,
+        };
+        // The texture of another size is freed once the GPU is done with it
+        if !self
+            .cached_layers
+            .get(&id)
+            .is_some_and(|layer| layer.target.texture.size() == extent)
+        {
+            let target = self.create_target("cached layer", size);
+            let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("cached layer"),
+                layout: &self.texture_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&target.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            });
+            self.cached_layers
+                .insert(id, CachedLayer { target, bind_group });
+        } else if self.cached_layers[&id].target.msaa_view.is_none() {
+            let msaa_view = self.create_msaa_view("cached layer", extent);
+            self.cached_layers.get_mut(&id).unwrap().target.msaa_view = msaa_view;
+        }
+        self.draw_list.push(Draw {
+            primitives: Primitives::BeginCachedLayer(id),
+            transform: Affine::IDENTITY,
+            clip: epaint::Rect::EVERYTHING,
+        });
+        // Everything in the layer is drawn
+        let damage = std::mem::replace(
+            &mut self.damage,
+            epaint::Rect::from_min_size(
+                epaint::Pos2::ZERO,
+                epaint::vec2(size[0] as f32, size[1] as f32),
+            ),
+        );
+        self.frame_damage = Some(damage);
+    }
+
+    fn end_cached_layer(&mut self) {
+        let Some(damage) = self.frame_damage.take() else {
+            return;
+        };
+        self.damage = damage;
+        self.draw_list.push(Draw {
+            primitives: Primitives::EndCachedLayer,
+            transform: Affine::IDENTITY,
+            clip: epaint::Rect::EVERYTHING,
+        });
+    }
+
+    fn draw_cached_layer(
+        &mut self,
+        id: u64,
+        mesh: &Mesh,
+        transform: Affine,
+        clip: epaint::Rect,
+        opacity: f32,
+    ) {
+        if self.cached_layers.contains_key(&id) {
+            self.push_mesh(mesh, transform, clip, opacity, Some(id), false);
+        }
+    }
+
+    fn release_cached_layer(&mut self, id: u64) {
+        self.cached_layers.remove(&id);
+    }
+
+    /// Frames are copied if they store 8 bits per channel, so not on HDR surfaces
+    fn captures_frames(&self) -> bool {
+        matches!(
+            self.config.format,
+            wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+                | wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+        )
+    }
+
+    fn capture_next_frame(&mut self) {
+        self.capture_next = true;
+    }
+
+    fn captured_frame(&mut self) -> Option<image::RgbaImage> {
+        self.captured.take()
+    }
+
+    fn end_frame(&mut self) {
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            // The surface has to be configured again, for example after the window moved to another monitor
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.config);
+                return;
+            }
+            Err(err) => {
+                log::warn!("skipping a frame: {err}");
+                return;
+            }
+        };
+        self.upload();
+
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(self.format),
+            ..Default::default()
+        });
+        // The shader converts vertex colors to linear space, so the clear color needs to be linear too
+        let [r, g, b, a] = Rgba::from(self.clear_color).to_array().map(f64::from);
+        let white_level = f64::from(self.white_level);
+        let [r, g, b] = [r, g, b].map(|channel| channel * white_level);
+        let is_effect = |draw: &Draw| {
+            !matches!(
+                draw.primitives,
+                Primitives::Mesh { .. } | Primitives::Rects { .. } | Primitives::Clear { .. }
+            )
+        };
+        // Blurs and filters are drawn inside of cached layers too, which can be larger than the window
+        let size = self
+            .draw_list
+            .iter()
+            .filter_map(|draw| match draw.primitives {
+                Primitives::BeginCachedLayer(id) => self.cached_layers.get(&id),
+                _ => None,
+            })
+            .fold(
+                [self.config.width, self.config.height],
+                |[width, height], layer| {
+                    let texture = &layer.target.texture;
+                    [width.max(texture.width()), height.max(texture.height())]
+                },
+            );
+        if self
+            .draw_list
+            .iter()
+            .any(|draw| matches!(draw.primitives, Primitives::BackdropBlur { .. }))
+        {
+            self.create_blur_textures(size);
+        }
+        let mut depth = 0;
+        let mut max_depth = 0;
+        for draw in &self.draw_list {
+            match draw.primitives {
+                Primitives::BeginFilter => {
+                    depth += 1;
+                    max_depth = max_depth.max(depth);
+                }
+                Primitives::EndFilter(..) => depth -= 1,
+                _ => {}
+            }
+        }
+        self.create_filter_targets(max_depth, size);
+        let Some(frame) = &self.frame else {
+            return;
+        };
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("blitz"),
+            });
+        // The frame is drawn in passes that end at each blur, filter and cached layer, because blurs and filters read what was drawn before them. Filtered and translucent elements are drawn into a layer of their own, and cached layers into their textures
+        let mut levels = vec![Level {
+            target: frame.target(),
+            size: [self.config.width, self.config.height],
+            area: self.damage,
+            // A frame that is drawn again as a whole keeps nothing of the last one, otherwise the damaged area is cleared by the first draw
+            load: if self.damage == self.window_rect() {
+                wgpu::LoadOp::Clear(wgpu::Color { r, g, b, a })
+            } else {
+                wgpu::LoadOp::Load
+            },
+        }];
+        let mut filters = 0;
+        let mut start = 0;
+        loop {
+            let end = self.draw_list[start..]
+                .iter()
+                .position(is_effect)
+                .map_or(self.draw_list.len(), |effect| start + effect);
+            let level = levels.last_mut().unwrap();
+            // A damaged area outside of the window has nothing to draw, and a pass that neither draws nor clears is skipped
+            if level.area.is_positive()
+                && (start < end || !matches!(level.load, wgpu::LoadOp::Load))
+            {
+                let mut pass = level.begin_pass(&mut encoder, "blitz");
+                self.draw_range(&mut pass, start..end);
+            }
+            if end == self.draw_list.len() {
+                break;
+            }
+            match self.draw_list[end].primitives {
+                Primitives::BackdropBlur { area, radius } => {
+                    self.blur(&mut encoder, level, area, radius)
+                }
+                Primitives::BeginFilter => {
+                    let layer = Level {
+                        target: self.filter_targets[filters].target(),
+                        size: level.size,
+                        area: level.area,
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    };
+                    filters += 1;
+                    levels.push(layer);
+                }
+                Primitives::EndFilter(matrix, opacity) if filters > 0 => {
+                    filters -= 1;
+                    let layer = levels.pop().unwrap();
+                    // A layer outside of the damaged area was never drawn to
+                    if matches!(layer.load, wgpu::LoadOp::Load) {
+                        let parent = levels.last_mut().unwrap();
+                        self.composite(&mut encoder, layer.target, parent, &matrix, opacity);
+                    }
+                }
+                Primitives::BeginCachedLayer(id) => {
+                    if let Some(layer) = self.cached_layers.get(&id) {
+                        let texture = &layer.target.texture;
+                        levels.push(Level {
+                            target: layer.target.target(),
+                            size: [texture.width(), texture.height()],
+                            area: epaint::Rect::from_min_size(
+                                epaint::Pos2::ZERO,
+                                epaint::vec2(texture.width() as f32, texture.height() as f32),
+                            ),
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        });
+                    }
+                }
+                Primitives::EndCachedLayer if levels.len() > 1 => {
+                    levels.pop();
+                }
+                _ => {}
+            }
+            start = end + 1;
+        }
+        self.present(&mut encoder, frame, &view);
+        let capture = std::mem::take(&mut self.capture_next).then(|| {
+            let size = frame.texture.size();
+            // The rows of a copy are aligned
+            let bytes_per_row =
+                (size.width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+            let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("screenshot"),
+                size: u64::from(bytes_per_row) * u64::from(size.height),
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            encoder.copy_texture_to_buffer(
+                frame.texture.as_image_copy(),
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                size,
+            );
+            (buffer, bytes_per_row)
+        });
+        self.queue.submit(Some(encoder.finish()));
+        if let Some((buffer, bytes_per_row)) = capture {
+            let bgra = matches!(
+                self.config.format,
+                wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+            );
+            self.captured = self.read_texels(&buffer).map(|texels| {
+                image_from_texels(
+                    [frame.texture.width(), frame.texture.height()],
+                    bytes_per_row as usize,
+                    &texels,
+                    bgra,
+                )
+            });
+        }
+        output.present();
+        // The samples of cached layers are only needed while they are drawn
+        for layer in self.cached_layers.values_mut() {
+            layer.target.msaa_view = None;
+        }
+    }
+}
+
+/// Build a pipeline that draws meshes with a fragment entry point of shader.wgsl. It is bound with the constants of each draw and the texture.
+fn create_pipeline(
+    device: &wgpu::Device,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    entry_point: &str,
+    blend: wgpu::BlendState,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::include_wgsl!("./shader.wgsl"));
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("blitz"),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("blitz"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: size_of::<epaint::Vertex>() as u64,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                // The position, uv and color of epaint::Vertex
+                attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Unorm8x4],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(blend),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
+
+/// Build the pipeline that draws rects as instanced quads. Without blending, the rects replace what was drawn before.
+fn create_rect_pipeline(
+    device: &wgpu::Device,
+    constants_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    blend: Option<wgpu::BlendState>,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::include_wgsl!("./rect.wgsl"));
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("rects"),
+        bind_group_layouts: &[constants_layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("rects"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: size_of::<RectInstance>() as u64,
+                step_mode: wgpu::VertexStepMode::Instance,
+                // The rect, radii, fill, border color and border width of RectInstance
+                attributes: &wgpu::vertex_attr_array![
+                    0 => Float32x4,
+                    1 => Float32x4,
+                    2 => Unorm8x4,
+                    3 => Unorm8x4,
+                    4 => Float32,
+                ],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
+
+/// Build a pipeline that draws a texture over its whole target with an effect, see blur.wgsl and filter.wgsl. It is bound with the effect layout.
+fn create_effect_pipeline(
+    device: &wgpu::Device,
+    shader: wgpu::ShaderModuleDescriptor,
+    layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    blend: Option<wgpu::BlendState>,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(shader);
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("effect"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("effect"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            // Without blending, the texels replace what was drawn before
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
+
+/// Copy data to the start of a buffer. When the data doesn't fit, the buffer is replaced with one with room to spare so a growing document doesn't reallocate every frame.
+fn write_buffer(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &mut Option<wgpu::Buffer>,
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+    data: &[u8],
+) {
+    let size = data.len() as u64;
+    if !matches!(buffer, Some(buffer) if buffer.size() >= size) {
+        *buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: size.next_power_of_two().max(MIN_CAPACITY),
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+    }
+    queue.write_buffer(buffer.as_ref().unwrap(), 0, data);
+}
+
+/// Create a texture with the size of an image and fill it with the image. The texels are sRGB, so they are converted to linear colors when they are sampled like the colors of vertices.
+fn create_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    image: &ColorImage,
+) -> (wgpu::Texture, wgpu::BindGroup) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("texture"),
+        size: wgpu::Extent3d {
+            width: image.size[0] as u32,
+            height: image.size[1] as u32,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    write_texture(queue, &texture, [0, 0], image);
+    let view = texture.create_view(&Default::default());
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("texture"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    });
+    (texture, bind_group)
+}
+
+/// Copy an image into an area of a texture with its top left corner at `pos`.
+fn write_texture(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    pos: [usize; 2],
+    image: &ColorImage,
+) {
+    let [width, height] = image.size;
+    if width == 0 || height == 0 {
+        return;
+    }
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: pos[0] as u32,
+                y: pos[1] as u32,
+                z: 0,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        bytemuck::cast_slice(&image.pixels),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * width as u32),
+            rows_per_image: Some(height as u32),
+        },
+        wgpu::Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+    );
+}
@@ -0,0 +1,177 @@
+use epaint::{Color32, Rect, Rounding, Shadow, Shape, Vec2};
+use peniko::kurbo::Affine;
+use peniko::Color;
+use tao::dpi::PhysicalSize;
+use tao::window::WindowBuilder;
+
+use crate::backend::Backend;
+use crate::document::to_color32;
+use crate::render::rounded_outline;
+use crate::scene::Scene;
+
+/// How a window without decorations is drawn, see [`crate::Config::with_undecorated`]. Sizes are in logical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowChrome {
+    /// The radius of the corners of the window. 0 keeps them square
+    pub corner_radius: f32,
+    /// The drop shadow around the window, or `None` for a flat window
+    pub shadow: Option<WindowShadow>,
+}
+
+impl Default for WindowChrome {
+    fn default() -> Self {
+        Self {
+            corner_radius: 8.0,
+            shadow: Some(WindowShadow::default()),
+        }
+    }
+}
+
+/// The drop shadow of a window without decorations. Only platforms without a shadow of their own use the size, offset and color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowShadow {
+    /// How far the soft edge of the shadow reaches past the window
+    pub size: f32,
+    /// How far the shadow is moved down, as if the light came from above
+    pub offset_y: f32,
+    pub color: Color,
+}
+
+impl Default for WindowShadow {
+    fn default() -> Self {
+        Self {
+            size: 24.0,
+            offset_y: 6.0,
+            color: Color::rgba8(0, 0, 0, 80),
+        }
+    }
+}
+
+impl WindowChrome {
+    /// Set up a window without decorations for the chrome. The window is transparent where the platform doesn't draw the corners and the shadow itself, so the backend can draw them.
+    pub(crate) fn window_builder(&self, builder: WindowBuilder) -> WindowBuilder {
+        let builder = builder.with_decorations(false);
+        #[cfg(target_os = "windows")]
+        {
+            // The compositor draws the shadow, and rounds the corners of windows with a shadow on Windows 11
+            use tao::platform::windows::WindowBuilderExtWindows;
+            builder.with_undecorated_shadow(self.shadow.is_some())
+        }
+        #[cfg(target_os = "macos")]
+        {
+            // The shadow of a transparent window follows the shape of the pixels that aren't transparent
+            use tao::platform::macos::WindowBuilderExtMacOS;
+            builder
+                .with_transparent(true)
+                .with_has_shadow(self.shadow.is_some())
+        }
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            builder.with_transparent(true)
+        }
+    }
+}
+
+/// The chrome of a window as it is drawn on this platform with its backend.
+pub(crate) struct Chrome {
+    style: WindowChrome,
+    /// The color of the window behind the document, which is the clear color of windows with square corners
+    color: Color32,
+    /// True if the backend composites the window by its alpha, so the corners and the margin for the shadow can be see-through. Otherwise the window is drawn square without a shadow of its own
+    transparent: bool,
+    scale_factor: f64,
+}
+
+impl Chrome {
+    pub fn new(
+        style: WindowChrome,
+        color: Color,
+        backend: &mut dyn Backend,
+        scale_factor: f64,
+    ) -> Self {
+        let needs_transparency = !cfg!(target_os = "windows")
+            && (style.corner_radius > 0.0
+                || (style.shadow.is_some() && !cfg!(target_os = "macos")));
+        let transparent = needs_transparency && backend.set_transparent();
+        if needs_transparency && !transparent {
+            log::warn!("the backend can't draw transparent windows, drawing the window square without a shadow");
+        }
+        Self {
+            style,
+            color: to_color32(color),
+            transparent,
+            scale_factor,
+        }
+    }
+
+    /// The color the window is cleared to before the chrome and the document are drawn.
+    pub fn clear_color(&self) -> Color32 {
+        if self.transparent {
+            Color32::TRANSPARENT
+        } else {
+            self.color
+        }
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    /// The see-through margin around the document in physical pixels that the shadow is drawn in, on platforms that don't draw the shadow.
+    fn margin(&self) -> f64 {
+        match self.style.shadow {
+            Some(shadow)
+                if self.transparent && !cfg!(any(target_os = "windows", target_os = "macos")) =>
+            {
+                ((shadow.size + shadow.offset_y.abs()) as f64 * self.scale_factor).ceil()
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// The size the document is laid out at in a window of a size, inside of the margin.
+    pub fn document_size(&self, window_size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+        let margin = self.margin() as u32 * 2;
+        PhysicalSize::new(
+            window_size.width.saturating_sub(margin).max(1),
+            window_size.height.saturating_sub(margin).max(1),
+        )
+    }
+
+    /// The transform that moves the document inside of the margin.
+    pub fn transform(&self) -> Affine {
+        Affine::translate((self.margin(), self.margin()))
+    }
+
+    /// Paint the shadow and the background of the window, and clip the shapes pushed until [`Scene::pop_clip_path`] to its rounded corners. Returns false if nothing is clipped.
+    pub fn paint(&self, scene: &mut Scene, window_size: PhysicalSize<u32>) -> bool {
+        if !self.transparent {
+            return false;
+        }
+        let window = Rect::from_min_size(
+            epaint::Pos2::ZERO,
+            epaint::vec2(window_size.width as f32, window_size.height as f32),
+        );
+        let margin = self.margin() as f32;
+        let rect = window.shrink(margin);
+        let radius = self.style.corner_radius * self.scale_factor as f32;
+        if margin > 0.0 {
+            if let Some(shadow) = self.style.shadow {
+                let scale = self.scale_factor as f32;
+                let offset = epaint::vec2(0.0, shadow.offset_y * scale);
+                let mesh = Shadow {
+                    extrusion: shadow.size * scale,
+                    color: to_color32(shadow.color),
+                }
+                .tessellate(rect.translate(offset), Rounding::same(radius));
+                scene.push(window, Shape::mesh(mesh));
+            }
+        }
+        scene.push(
+            window,
+            Shape::rect_filled(rect, Rounding::same(radius), self.color),
+        );
+        scene.push_clip_path(rounded_outline(rect, &[Vec2::splat(radius); 4]));
+        true
+    }
+}
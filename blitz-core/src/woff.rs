@@ -0,0 +1,210 @@
+use std::io::{self, Read};
+
+/// The signatures of WOFF and WOFF2 files
+const WOFF_SIGNATURE: &[u8; 4] = b"wOFF";
+const WOFF2_SIGNATURE: &[u8; 4] = b"wOF2";
+/// The sizes of the WOFF header and of an entry of its table directory
+const WOFF_HEADER_SIZE: usize = 44;
+const WOFF_ENTRY_SIZE: usize = 20;
+/// The sizes of the sfnt header and of a table record
+const SFNT_HEADER_SIZE: usize = 12;
+const SFNT_RECORD_SIZE: usize = 16;
+/// The most tables an sfnt header can describe, since its search range is a 16 bit multiple of the record size
+const SFNT_MAX_TABLES: usize = 4095;
+
+/// The data of a TrueType or OpenType font file from a font file that may be compressed as WOFF. Other data is returned as it is.
+///
+/// Returns an error if the data is a WOFF font that is damaged, or a WOFF2 font, whose tables would have to be reconstructed.
+pub(crate) fn to_sfnt(data: Vec<u8>) -> io::Result<Vec<u8>> {
+    match data.get(..4) {
+        Some(signature) if signature == WOFF_SIGNATURE => decode_woff(&data),
+        Some(signature) if signature == WOFF2_SIGNATURE => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "WOFF2 fonts aren't supported",
+        )),
+        _ => Ok(data),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid WOFF font: {message}"),
+    )
+}
+
+fn read_u16(data: &[u8], offset: usize) -> io::Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| invalid("it is truncated"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> io::Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| invalid("it is truncated"))
+}
+
+/// Unpack the tables of a WOFF 1.0 font into an sfnt, see <https://www.w3.org/TR/WOFF/>.
+fn decode_woff(data: &[u8]) -> io::Result<Vec<u8>> {
+    if data.len() < WOFF_HEADER_SIZE {
+        return Err(invalid("it is truncated"));
+    }
+    let flavor = read_u32(data, 4)?;
+    let num_tables = read_u16(data, 12)? as usize;
+    if num_tables > SFNT_MAX_TABLES {
+        return Err(invalid("it has too many tables"));
+    }
+    let mut tables = Vec::with_capacity(num_tables);
+    for index in 0..num_tables {
+        let entry = WOFF_HEADER_SIZE + index * WOFF_ENTRY_SIZE;
+        let tag = read_u32(data, entry)?;
+        let offset = read_u32(data, entry + 4)? as usize;
+        let compressed_length = read_u32(data, entry + 8)? as usize;
+        let length = read_u32(data, entry + 12)? as usize;
+        let checksum = read_u32(data, entry + 16)?;
+        let compressed = offset
+            .checked_add(compressed_length)
+            .and_then(|end| data.get(offset..end))
+            .ok_or_else(|| invalid("a table is out of bounds"))?;
+        // Tables that don't get smaller are stored as they are
+        // The length comes from the file, so it is only trusted once the table has been inflated to it
+        let table = if compressed_length < length {
+            let mut table = Vec::new();
+            flate2::read::ZlibDecoder::new(compressed)
+                .take(length as u64)
+                .read_to_end(&mut table)?;
+            table
+        } else {
+            compressed.to_vec()
+        };
+        if table.len() != length {
+            return Err(invalid("a table doesn't have its length"));
+        }
+        tables.push((tag, checksum, table));
+    }
+    // The tables are 4 byte aligned after the header and the table records, in the order of the records
+    let mut sfnt = Vec::new();
+    let entry_selector = (num_tables.max(1) as u32).ilog2();
+    let search_range = (1 << entry_selector) * SFNT_RECORD_SIZE as u32;
+    let range_shift = (num_tables * SFNT_RECORD_SIZE) as u32
+        - search_range.min((num_tables * SFNT_RECORD_SIZE) as u32);
+    sfnt.extend(flavor.to_be_bytes());
+    sfnt.extend((num_tables as u16).to_be_bytes());
+    sfnt.extend((search_range as u16).to_be_bytes());
+    sfnt.extend((entry_selector as u16).to_be_bytes());
+    sfnt.extend((range_shift as u16).to_be_bytes());
+    let mut offset = (SFNT_HEADER_SIZE + num_tables * SFNT_RECORD_SIZE) as u32;
+    for (tag, checksum, table) in &tables {
+        sfnt.extend(tag.to_be_bytes());
+        sfnt.extend(checksum.to_be_bytes());
+        sfnt.extend(offset.to_be_bytes());
+        sfnt.extend((table.len() as u32).to_be_bytes());
+        // Offsets are 32 bits, so the tables can't add up to more than 4 GiB
+        offset = u32::try_from((table.len() + 3) & !3)
+            .ok()
+            .and_then(|padded| offset.checked_add(padded))
+            .ok_or_else(|| invalid("its tables are too large"))?;
+    }
+    for (_, _, table) in tables {
+        sfnt.extend(&table);
+        sfnt.resize((sfnt.len() + 3) & !3, 0);
+    }
+    Ok(sfnt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// A WOFF font with tables that are compressed if their flag is set and stored as they are otherwise
+    fn woff(tables: &[(&[u8; 4], &[u8], bool)]) -> Vec<u8> {
+        let mut directory = Vec::new();
+        let mut data = Vec::new();
+        let mut offset = WOFF_HEADER_SIZE + tables.len() * WOFF_ENTRY_SIZE;
+        for (tag, table, compress) in tables {
+            let stored = if *compress {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+                encoder.write_all(table).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                table.to_vec()
+            };
+            directory.extend(*tag);
+            directory.extend((offset as u32).to_be_bytes());
+            directory.extend((stored.len() as u32).to_be_bytes());
+            directory.extend((table.len() as u32).to_be_bytes());
+            directory.extend(0x1234_5678u32.to_be_bytes());
+            offset += stored.len();
+            data.extend(stored);
+        }
+        let mut woff = WOFF_SIGNATURE.to_vec();
+        woff.extend(0x0001_0000u32.to_be_bytes());
+        woff.extend((offset as u32).to_be_bytes());
+        woff.extend((tables.len() as u16).to_be_bytes());
+        woff.resize(WOFF_HEADER_SIZE, 0);
+        woff.extend(directory);
+        woff.extend(data);
+        woff
+    }
+
+    #[test]
+    fn unpacks_tables() {
+        let long = [7; 100];
+        let sfnt = to_sfnt(woff(&[(b"glyf", &long, true), (b"head", b"abcde", false)])).unwrap();
+        // Two tables have a search range of one record and a range shift of the other
+        assert_eq!(
+            sfnt[..SFNT_HEADER_SIZE],
+            [0, 1, 0, 0, 0, 2, 0, 32, 0, 1, 0, 0]
+        );
+        let record = |index: usize| {
+            let start = SFNT_HEADER_SIZE + index * SFNT_RECORD_SIZE;
+            &sfnt[start..start + SFNT_RECORD_SIZE]
+        };
+        assert_eq!(&record(0)[..4], b"glyf");
+        assert_eq!(read_u32(record(0), 4).unwrap(), 0x1234_5678);
+        assert_eq!(read_u32(record(0), 8).unwrap(), 44);
+        assert_eq!(read_u32(record(0), 12).unwrap(), 100);
+        assert_eq!(&record(1)[..4], b"head");
+        assert_eq!(read_u32(record(1), 8).unwrap(), 144);
+        assert_eq!(read_u32(record(1), 12).unwrap(), 5);
+        assert_eq!(sfnt[44..144], long);
+        // Tables are padded to 4 bytes
+        assert_eq!(sfnt[144..], *b"abcde\0\0\0");
+    }
+
+    #[test]
+    fn other_fonts_are_unchanged() {
+        let data = vec![0, 1, 0, 0, 1, 2, 3];
+        assert_eq!(to_sfnt(data.clone()).unwrap(), data);
+    }
+
+    #[test]
+    fn truncated() {
+        let font = woff(&[(b"head", b"abcde", false)]);
+        for length in [10, WOFF_HEADER_SIZE, font.len() - 1] {
+            let err = to_sfnt(font[..length].to_vec()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn bad_table_length() {
+        // The table inflates to 100 bytes, but the directory claims 200
+        let mut font = woff(&[(b"glyf", &[7; 100], true)]);
+        let length = WOFF_HEADER_SIZE + 12;
+        font[length..length + 4].copy_from_slice(&200u32.to_be_bytes());
+        let err = to_sfnt(font).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn too_many_tables() {
+        let mut font = woff(&[]);
+        font[12..14].copy_from_slice(&4096u16.to_be_bytes());
+        let err = to_sfnt(font).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,80 @@
+use blitz_core::Document;
+use dioxus_native_core::prelude::*;
+use dioxus_native_core::FxHashMap;
+
+/// Elements that don't render anything
+const IGNORED_ELEMENTS: &[&str] = &["head", "title", "meta", "link", "script", "style"];
+
+/// Load an html string into the document. Inline `style` attributes are split into style attributes like Dioxus does.
+pub fn load_html(document: &mut Document, html: &str) -> Result<(), tl::ParseError> {
+    let dom = tl::parse(html, tl::ParserOptions::default())?;
+    let parser = dom.parser();
+    let rdom = document.real_dom_mut();
+    let root_id = rdom.root_id();
+    for child in dom.children() {
+        insert_node(rdom, parser, *child, root_id);
+    }
+    Ok(())
+}
+
+fn insert_node(rdom: &mut RealDom, parser: &tl::Parser, handle: tl::NodeHandle, parent: NodeId) {
+    let Some(node) = handle.get(parser) else {
+        return;
+    };
+    let id = match node {
+        tl::Node::Tag(tag) => {
+            let tag_name = tag.name().as_utf8_str().to_lowercase();
+            if IGNORED_ELEMENTS.contains(&tag_name.as_str()) {
+                return;
+            }
+            let mut attributes = FxHashMap::default();
+            for (name, value) in tag.attributes().iter() {
+                let value = value.unwrap_or_default();
+                if name == "style" {
+                    for declaration in value.split(';') {
+                        if let Some((property, value)) = declaration.split_once(':') {
+                            attributes.insert(
+                                OwnedAttributeDiscription {
+                                    name: property.trim().to_string(),
+                                    namespace: Some("style".to_string()),
+                                },
+                                OwnedAttributeValue::Text(value.trim().to_string()),
+                            );
+                        }
+                    }
+                } else {
+                    attributes.insert(
+                        OwnedAttributeDiscription {
+                            name: name.to_string(),
+                            namespace: None,
+                        },
+                        OwnedAttributeValue::Text(value.to_string()),
+                    );
+                }
+            }
+            let id = rdom
+                .create_node(NodeType::Element(ElementNode {
+                    tag: tag_name,
+                    namespace: None,
+                    attributes,
+                    listeners: Default::default(),
+                }))
+                .id();
+            for child in tag.children().top().iter() {
+                insert_node(rdom, parser, *child, id);
+            }
+            id
+        }
+        tl::Node::Raw(text) => {
+            let text = text.as_utf8_str();
+            // Whitespace between elements doesn't render
+            if text.trim().is_empty() {
+                return;
+            }
+            rdom.create_node(NodeType::Text(TextNode::new(text.to_string())))
+                .id()
+        }
+        tl::Node::Comment(_) => return,
+    };
+    rdom.get_mut(parent).unwrap().add_child(id);
+}
@@ -0,0 +1,165 @@
+//! Render HTML files with Blitz without opening a window and compare the results.
+//!
+//! ```sh
+//! blitz-shot render page.html --width 800 --height 600 --scale 2 -o page.png
+//! blitz-shot diff expected.png page.png --output diff.png
+//! ```
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use blitz_core::{Config, Document, StyleDiagnostics};
+use clap::{Parser, Subcommand};
+
+mod html;
+
+#[derive(Parser)]
+#[command(about = "Render HTML with Blitz to a PNG and compare screenshots")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a HTML file to a PNG
+    Render {
+        input: PathBuf,
+        /// The width of the viewport in logical pixels
+        #[arg(long, default_value_t = 800)]
+        width: u32,
+        /// The height of the viewport in logical pixels
+        #[arg(long, default_value_t = 600)]
+        height: u32,
+        /// The number of physical pixels per logical pixel
+        #[arg(long, default_value_t = 1.0)]
+        scale: f32,
+        /// The width in physical pixels of the smoothed edges of shapes. 0 disables anti-aliasing
+        #[arg(long, default_value_t = 1.0)]
+        feathering: f32,
+        #[arg(short, long, default_value = "screenshot.png")]
+        output: PathBuf,
+        /// Also write a diagnostics report into this directory
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+    /// Compare two PNGs. Exits with a non-zero status if they differ
+    Diff {
+        expected: PathBuf,
+        actual: PathBuf,
+        /// Write an image that highlights the pixels that differ
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// The largest difference in any channel that is still considered equal
+        #[arg(long, default_value_t = 0)]
+        tolerance: u8,
+    },
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let result = match args.command {
+        Command::Render {
+            input,
+            width,
+            height,
+            scale,
+            feathering,
+            output,
+            report,
+        } => render(input, width, height, scale, feathering, output, report),
+        Command::Diff {
+            expected,
+            actual,
+            output,
+            tolerance,
+        } => diff(expected, actual, output, tolerance),
+    };
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn render(
+    input: PathBuf,
+    width: u32,
+    height: u32,
+    scale: f32,
+    feathering: f32,
+    output: PathBuf,
+    report: Option<PathBuf>,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let source = std::fs::read_to_string(&input)?;
+    let style_diagnostics = Arc::new(StyleDiagnostics::default());
+    let mut document = Document::new(
+        Config::new()
+            .with_style_diagnostics(style_diagnostics.clone())
+            .with_feathering(feathering),
+    );
+    html::load_html(&mut document, &source)?;
+
+    let physical_width = (width as f32 * scale).round() as u32;
+    let physical_height = (height as f32 * scale).round() as u32;
+    let image = document.render_to_image(physical_width, physical_height, scale);
+    for diagnostic in style_diagnostics.drain() {
+        eprintln!("warning: {diagnostic}");
+    }
+    image.save(&output)?;
+    println!("wrote {}", output.display());
+    if let Some(report) = report {
+        let report =
+            document.write_diagnostics_report(report, physical_width, physical_height, scale)?;
+        println!("wrote a diagnostics report to {}", report.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn diff(
+    expected: PathBuf,
+    actual: PathBuf,
+    output: Option<PathBuf>,
+    tolerance: u8,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let expected = image::open(&expected)?.to_rgba8();
+    let actual = image::open(&actual)?.to_rgba8();
+    if expected.dimensions() != actual.dimensions() {
+        println!(
+            "size differs: expected {:?}, got {:?}",
+            expected.dimensions(),
+            actual.dimensions()
+        );
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let mut different = 0;
+    let highlighted = image::RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let a = expected.get_pixel(x, y);
+        let b = actual.get_pixel(x, y);
+        let differs = a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > tolerance);
+        if differs {
+            different += 1;
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            // Fade the matching pixels so the differences stand out
+            let [r, g, b, _] = a.0;
+            let gray = ((r as u32 + g as u32 + b as u32) / 3 / 4 + 191) as u8;
+            image::Rgba([gray, gray, gray, 255])
+        }
+    });
+
+    if let Some(output) = output {
+        highlighted.save(&output)?;
+    }
+    if different == 0 {
+        println!("images match");
+        Ok(ExitCode::SUCCESS)
+    } else {
+        println!("{different} pixels differ");
+        Ok(ExitCode::FAILURE)
+    }
+}
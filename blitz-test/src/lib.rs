@@ -0,0 +1,226 @@
+//! Run a Dioxus app headlessly with Blitz and make assertions about its document, as the basis of app level test suites.
+//!
+//! The app runs on a tokio runtime with a paused clock, so timers only fire when the test moves the clock with [`TestApp::advance`]. Input is injected with the same event handling as a window, and the document is styled and laid out after every change, so tests can check text, attributes, layout and focus without a GPU or a display.
+
+use std::io;
+use std::ops::Deref;
+use std::time::Duration;
+
+use blitz_core::{Config, Document, DomEvent, HeadlessSession};
+use dioxus::core::{Component, VirtualDom};
+use dioxus_html::geometry::ClientPoint;
+use dioxus_html::input_data::keyboard_types::Key;
+use dioxus_native_core::prelude::*;
+use futures_util::FutureExt;
+use peniko::kurbo::Rect;
+use taffy::prelude::Size;
+use tokio::runtime::Runtime;
+
+/// How far the clock moves at once in [`TestApp::advance`], which is about one frame
+const FRAME: Duration = Duration::from_millis(16);
+
+/// A Dioxus app running headlessly in a test.
+///
+/// Methods that take a selector find the first element that matches it in document order. Selectors are a tag name, ids and classes like `button`, `#submit` or `li.item.selected`, or a stable id like `#menu/li[2]` that [`TestApp::stable_id`] returns. Assertions panic if no element matches, like assertions do.
+pub struct TestApp {
+    vdom: VirtualDom,
+    dioxus_state: DioxusState,
+    document: Document,
+    session: HeadlessSession,
+    /// The clock of the runtime is paused, it only moves in [`TestApp::advance`]
+    runtime: Runtime,
+    viewport_size: Size<f32>,
+}
+
+impl TestApp {
+    /// Build an app and lay it out in a viewport of 800 by 600 pixels.
+    pub fn new(app: Component<()>) -> Self {
+        Self::with_props(app, (), Config::default())
+    }
+
+    pub fn with_props<Props: 'static>(app: Component<Props>, props: Props, cfg: Config) -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()
+            .expect("failed to create the runtime of the test");
+        let mut document = Document::new(cfg);
+        let mut vdom = VirtualDom::new_with_props(app, props);
+        let rdom = document.real_dom_mut();
+        let mut dioxus_state = DioxusState::create(rdom);
+        // Tasks spawned while the app is built run on the runtime
+        let muts = {
+            let _guard = runtime.enter();
+            vdom.rebuild()
+        };
+        dioxus_state.apply_mutations(rdom, muts);
+        let session = HeadlessSession::new(&mut document);
+        let mut app = Self {
+            vdom,
+            dioxus_state,
+            document,
+            session,
+            runtime,
+            viewport_size: Size {
+                width: 800.0,
+                height: 600.0,
+            },
+        };
+        app.settle();
+        app
+    }
+
+    /// Lay the document out in a viewport with a size in logical pixels.
+    pub fn set_viewport(&mut self, width: f32, height: f32) {
+        self.viewport_size = Size { width, height };
+        self.document.resolve(self.viewport_size);
+    }
+
+    /// Run the work that is ready without moving the clock, like tasks that were woken and components that have to render again, and lay the document out again.
+    pub fn settle(&mut self) {
+        let _guard = self.runtime.enter();
+        // Polling for work once at a time keeps the paused runtime from moving the clock to the next timer on its own
+        while self.vdom.wait_for_work().now_or_never().is_some() {
+            let muts = self.vdom.render_immediate();
+            self.dioxus_state
+                .apply_mutations(self.document.real_dom_mut(), muts);
+        }
+        self.document.resolve(self.viewport_size);
+    }
+
+    /// Move the clock forward, firing the timers of the app as if the time passed. The clock moves a frame at a time and the work of each frame is settled before the next, so intervals fire as often as they would in a window.
+    pub fn advance(&mut self, duration: Duration) {
+        let mut remaining = duration;
+        while !remaining.is_zero() {
+            let step = remaining.min(FRAME);
+            self.runtime.block_on(tokio::time::advance(step));
+            remaining -= step;
+            self.settle();
+        }
+    }
+
+    /// Click the center of the first element that matches a selector. Returns an error if no element that was laid out matches.
+    pub fn click(&mut self, selector: &str) -> io::Result<()> {
+        let events = self.session.click(&mut self.document, selector)?;
+        self.dispatch(events);
+        Ok(())
+    }
+
+    /// Click a point in client coordinates.
+    pub fn click_at(&mut self, x: f64, y: f64) {
+        let events = self
+            .session
+            .click_at(&mut self.document, ClientPoint::new(x, y));
+        self.dispatch(events);
+    }
+
+    /// Press and release a key over the focused element.
+    pub fn press_key(&mut self, key: Key) {
+        let events = self.session.press_key(&mut self.document, key);
+        self.dispatch(events);
+    }
+
+    /// Type text into the focused element. Editable elements get an input event with their new value for every character.
+    pub fn type_text(&mut self, text: &str) {
+        let events = self.session.type_text(&mut self.document, text);
+        self.dispatch(events);
+    }
+
+    /// Send events to the app like the driver of a window does, and settle the work they cause.
+    fn dispatch(&mut self, events: Vec<DomEvent>) {
+        {
+            let _guard = self.runtime.enter();
+            let rdom = self.document.real_dom_mut();
+            for event in events {
+                let Some(node) = rdom.get_mut(event.element) else {
+                    continue;
+                };
+                if let Some(id) = node.mounted_id() {
+                    self.vdom.handle_event(
+                        event.name,
+                        event.data.deref().clone().into_any(),
+                        id,
+                        event.bubbles,
+                    );
+                }
+            }
+        }
+        self.settle();
+    }
+
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    pub fn document_mut(&mut self) -> &mut Document {
+        &mut self.document
+    }
+
+    /// The first element that matches a selector.
+    pub fn find(&self, selector: &str) -> Option<NodeId> {
+        self.document.query_selector(selector)
+    }
+
+    /// The stable id of the first element that matches a selector, which finds the same element after the app is rebuilt, see [`blitz_core::stable_id`].
+    pub fn stable_id(&self, selector: &str) -> String {
+        self.document
+            .stable_id(self.expect(selector))
+            .unwrap_or_else(|| panic!("{selector} has no stable id"))
+    }
+
+    fn expect(&self, selector: &str) -> NodeId {
+        self.find(selector)
+            .unwrap_or_else(|| panic!("no element matches {selector}"))
+    }
+
+    /// The text of an element and all of its children, like `textContent` in a browser.
+    pub fn text(&self, selector: &str) -> String {
+        let mut text = String::new();
+        let rdom = self.document.real_dom();
+        let mut stack = vec![rdom.get(self.expect(selector)).unwrap()];
+        while let Some(node) = stack.pop() {
+            if let NodeType::Text(TextNode { text: content, .. }) = &*node.node_type() {
+                text += content;
+            }
+            stack.extend(node.children().into_iter().rev());
+        }
+        text
+    }
+
+    /// The value of an attribute of an element as text, or `None` if it isn't set.
+    pub fn attribute(&self, selector: &str, name: &str) -> Option<String> {
+        let node = self.document.real_dom().get(self.expect(selector)).unwrap();
+        let NodeType::Element(element) = &*node.node_type() else {
+            return None;
+        };
+        let (_, value) = element
+            .attributes
+            .iter()
+            .find(|(attribute, _)| attribute.name == name && attribute.namespace.is_none())?;
+        match value {
+            OwnedAttributeValue::Text(text) => Some(text.clone()),
+            OwnedAttributeValue::Float(float) => Some(float.to_string()),
+            OwnedAttributeValue::Int(int) => Some(int.to_string()),
+            OwnedAttributeValue::Bool(bool) => Some(bool.to_string()),
+            _ => None,
+        }
+    }
+
+    /// The bounding box of the border box of an element in client coordinates, including the transforms of the element and its ancestors.
+    pub fn rect(&self, selector: &str) -> Rect {
+        let element = self.expect(selector);
+        self.document
+            .bounding_box(element)
+            .unwrap_or_else(|| panic!("{selector} wasn't laid out"))
+    }
+
+    /// The element that has focus
+    pub fn focused(&self) -> Option<NodeId> {
+        self.session.focused()
+    }
+
+    /// True if the first element that matches a selector has focus.
+    pub fn is_focused(&self, selector: &str) -> bool {
+        self.focused() == Some(self.expect(selector))
+    }
+}
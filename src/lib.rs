@@ -1,49 +1,150 @@
+use std::io;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
 
 use dioxus::core::{Component, VirtualDom};
 use dioxus_native_core::prelude::*;
 
 use blitz_core::EventData;
-use blitz_core::{render, Config, Driver};
+use blitz_core::{render, render_async, BlockingExecutor, Config, Document, Driver, RenderError};
+use taffy::Taffy;
 
-pub async fn launch(app: Component<()>) {
-    launch_cfg(app, Config::default()).await
+#[cfg(feature = "liveview")]
+mod liveview;
+#[cfg(feature = "liveview")]
+pub use liveview::{launch_liveview, ChannelTransport, Transport};
+
+/// Launch the app in a new window. This blocks the main thread until the window is closed.
+///
+/// The app runs on a tokio runtime that Blitz creates, so `main` doesn't need to set one up.
+///
+/// # Panics
+///
+/// Panics if the window can't be opened or no graphics adapter can draw to it.
+pub fn launch(app: Component<()>) {
+    // The default config has no runtime that could be rejected
+    launch_cfg(app, Config::default()).unwrap()
+}
+
+/// Launch the app in a new window without tokio. This blocks the main thread until the window is closed.
+///
+/// Async tasks in the app are polled by a minimal executor, so they can't depend on a runtime like tokio. Use [`launch_cfg`] with [`Config::with_executor`] to provide another executor.
+///
+/// # Panics
+///
+/// Panics if the window can't be opened or no graphics adapter can draw to it.
+pub fn launch_blocking(app: Component<()>) {
+    // Only a tokio runtime can be rejected
+    launch_cfg(app, Config::new().with_executor(BlockingExecutor)).unwrap()
 }
 
-pub async fn launch_cfg(app: Component<()>, cfg: Config) {
-    launch_cfg_with_props(app, (), cfg).await
+/// Launch the app in a new window with a config. This blocks the main thread until the window is closed.
+///
+/// # Errors
+///
+/// Returns [`RenderError::CurrentThreadRuntime`] if [`Config::with_runtime`] set a current thread runtime.
+///
+/// # Panics
+///
+/// Panics if the window can't be opened or no graphics adapter can draw to it.
+pub fn launch_cfg(app: Component<()>, cfg: Config) -> Result<(), RenderError> {
+    launch_cfg_with_props(app, (), cfg)
 }
 
-pub async fn launch_cfg_with_props<Props: 'static + Send>(
+pub fn launch_cfg_with_props<Props: 'static + Send>(
     app: Component<Props>,
     props: Props,
     cfg: Config,
-) {
-    render(
-        move |rdom, _| {
-            let mut vdom = VirtualDom::new_with_props(app, props);
-            let muts = vdom.rebuild();
-            let mut rdom = rdom.write().unwrap();
-            let mut dioxus_state = DioxusState::create(&mut rdom);
-            dioxus_state.apply_mutations(&mut rdom, muts);
-            DioxusRenderer {
-                vdom,
-                dioxus_state,
-                #[cfg(all(feature = "hot-reload", debug_assertions))]
-                hot_reload_rx: {
-                    let (hot_reload_tx, hot_reload_rx) =
-                        tokio::sync::mpsc::unbounded_channel::<dioxus_hot_reload::HotReloadMsg>();
-                    dioxus_hot_reload::connect(move |msg| {
-                        let _ = hot_reload_tx.send(msg);
-                    });
-                    hot_reload_rx
-                },
-            }
-        },
-        cfg,
-    )
-    .await;
+) -> Result<(), RenderError> {
+    render(create_renderer(app, props), cfg)
+}
+
+/// Launch the app in a new window from inside of a multi-threaded tokio runtime, like the one created by `#[tokio::main]`. The app's async tasks run on that runtime.
+///
+/// The window still owns the main thread, so this never completes.
+///
+/// # Panics
+///
+/// Panics if the window can't be opened or no graphics adapter can draw to it.
+pub async fn launch_async(app: Component<()>) {
+    // The default config has no runtime that could be rejected
+    launch_cfg_async(app, Config::default()).await.unwrap()
+}
+
+pub async fn launch_cfg_async(app: Component<()>, cfg: Config) -> Result<(), RenderError> {
+    launch_cfg_with_props_async(app, (), cfg).await
+}
+
+pub async fn launch_cfg_with_props_async<Props: 'static + Send>(
+    app: Component<Props>,
+    props: Props,
+    cfg: Config,
+) -> Result<(), RenderError> {
+    render_async(create_renderer(app, props), cfg).await
+}
+
+/// Render the first frame of the app into an image without opening a window, for example to render previews of a UI on a server. The size is in physical pixels.
+///
+/// Async tasks and suspended components aren't waited for, so the image shows what the app renders right away. No GPU is needed.
+pub fn render_to_image(app: Component<()>, width: u32, height: u32) -> image::RgbaImage {
+    render_to_image_cfg_with_props(app, (), Config::default(), width, height, 1.0)
+}
+
+/// Like [`render_to_image`], but the image is written to a PNG file.
+pub fn render_to_png(
+    app: Component<()>,
+    width: u32,
+    height: u32,
+    path: impl AsRef<Path>,
+) -> io::Result<()> {
+    render_to_image(app, width, height)
+        .save_with_format(path, image::ImageFormat::Png)
+        .map_err(io::Error::other)
+}
+
+/// Like [`render_to_image`], with props, a config and a scale factor. The document is laid out at the size divided by the scale factor.
+pub fn render_to_image_cfg_with_props<Props: 'static>(
+    app: Component<Props>,
+    props: Props,
+    cfg: Config,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> image::RgbaImage {
+    let mut document = Document::new(cfg);
+    let mut vdom = VirtualDom::new_with_props(app, props);
+    let muts = vdom.rebuild();
+    let rdom = document.real_dom_mut();
+    let mut dioxus_state = DioxusState::create(rdom);
+    dioxus_state.apply_mutations(rdom, muts);
+    document.render_to_image(width, height, scale_factor)
+}
+
+fn create_renderer<Props: 'static + Send>(
+    app: Component<Props>,
+    props: Props,
+) -> impl FnOnce(&Arc<RwLock<RealDom>>, &Arc<Mutex<Taffy>>) -> DioxusRenderer + Send + 'static {
+    move |rdom, _| {
+        let mut vdom = VirtualDom::new_with_props(app, props);
+        let muts = vdom.rebuild();
+        let mut rdom = rdom.write().unwrap();
+        let mut dioxus_state = DioxusState::create(&mut rdom);
+        dioxus_state.apply_mutations(&mut rdom, muts);
+        DioxusRenderer {
+            vdom,
+            dioxus_state,
+            #[cfg(all(feature = "hot-reload", debug_assertions))]
+            hot_reload_rx: {
+                let (hot_reload_tx, hot_reload_rx) =
+                    tokio::sync::mpsc::unbounded_channel::<dioxus_hot_reload::HotReloadMsg>();
+                dioxus_hot_reload::connect(move |msg| {
+                    let _ = hot_reload_tx.send(msg);
+                });
+                hot_reload_rx
+            },
+        }
+    }
 }
 
 struct DioxusRenderer {
@@ -0,0 +1,137 @@
+//! Render a VirtualDom that runs on a server.
+//!
+//! The server sends the mutations of the VirtualDom as JSON, the same way dioxus-liveview sends them to a browser, and Blitz applies them to the RealDom. Input events are sent back as `{"method": "user_event", "params": {"name", "element", "data", "bubbles"}}` messages.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+
+use dioxus::core::Mutations;
+use dioxus_native_core::prelude::*;
+use taffy::Taffy;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use blitz_core::{render, Config, Driver, EventData, RenderError};
+
+/// A connection to a server that runs the VirtualDom.
+pub trait Transport: Send + 'static {
+    /// Wait for the next message from the server. Returns `None` once the connection is closed.
+    fn recv(&mut self) -> Pin<Box<dyn Future<Output = Option<String>> + '_>>;
+
+    /// Send a message to the server.
+    fn send(&mut self, message: String);
+}
+
+/// A transport backed by channels, so any connection (a websocket, a pipe, etc.) can be bridged to Blitz by forwarding its messages.
+pub struct ChannelTransport {
+    /// Messages from the server
+    pub incoming: UnboundedReceiver<String>,
+    /// Messages to the server
+    pub outgoing: UnboundedSender<String>,
+}
+
+impl Transport for ChannelTransport {
+    fn recv(&mut self) -> Pin<Box<dyn Future<Output = Option<String>> + '_>> {
+        Box::pin(self.incoming.recv())
+    }
+
+    fn send(&mut self, message: String) {
+        // The server may have gone away, which is noticed when receiving
+        let _ = self.outgoing.send(message);
+    }
+}
+
+/// Launch a window that renders a VirtualDom running on a server. This blocks the main thread until the window is closed.
+///
+/// # Errors
+///
+/// Returns [`RenderError::CurrentThreadRuntime`] if [`Config::with_runtime`] set a current thread runtime.
+pub fn launch_liveview(transport: impl Transport, cfg: Config) -> Result<(), RenderError> {
+    render(
+        move |rdom: &Arc<RwLock<RealDom>>, _: &Arc<Mutex<Taffy>>| {
+            let mut rdom = rdom.write().unwrap();
+            LiveViewRenderer {
+                transport,
+                dioxus_state: DioxusState::create(&mut rdom),
+                queued: Vec::new(),
+                closed: false,
+            }
+        },
+        cfg,
+    )
+}
+
+struct LiveViewRenderer<T> {
+    transport: T,
+    dioxus_state: DioxusState,
+    /// Messages received since the last update
+    queued: Vec<String>,
+    closed: bool,
+}
+
+impl<T: Transport> Driver for LiveViewRenderer<T> {
+    fn update(&mut self, mut root: NodeMut<()>) {
+        let rdom = root.real_dom_mut();
+        for message in self.queued.drain(..) {
+            match serde_json::from_str::<Mutations>(&message) {
+                Ok(muts) => self.dioxus_state.apply_mutations(rdom, muts),
+                Err(err) => {
+                    log::warn!("ignoring a liveview message that isn't a set of mutations: {err}")
+                }
+            }
+        }
+    }
+
+    fn handle_event(
+        &mut self,
+        node: NodeMut<()>,
+        event: &str,
+        value: Arc<EventData>,
+        bubbles: bool,
+    ) {
+        let Some(id) = node.mounted_id() else {
+            return;
+        };
+        let data = match &*value {
+            EventData::Mouse(data) => serde_json::to_value(data),
+            EventData::Keyboard(data) => serde_json::to_value(data),
+            EventData::Focus(data) => serde_json::to_value(data),
+            EventData::Wheel(data) => serde_json::to_value(data),
+            EventData::Form(data) => serde_json::to_value(data),
+            EventData::Gesture(data) => serde_json::to_value(data),
+        };
+        let data = match data {
+            Ok(data) => data,
+            Err(err) => {
+                log::warn!("failed to serialize a {event} event: {err}");
+                return;
+            }
+        };
+        let message = serde_json::json!({
+            "method": "user_event",
+            "params": {
+                "name": event,
+                "element": id,
+                "data": data,
+                "bubbles": bubbles,
+            }
+        });
+        self.transport.send(message.to_string());
+    }
+
+    fn poll_async(&mut self) -> Pin<Box<dyn Future<Output = ()> + '_>> {
+        Box::pin(async {
+            if self.closed {
+                // Keep showing the last state of the document
+                return std::future::pending().await;
+            }
+            match self.transport.recv().await {
+                Some(message) => self.queued.push(message),
+                None => {
+                    log::info!("the liveview connection was closed");
+                    self.closed = true;
+                }
+            }
+        })
+    }
+}